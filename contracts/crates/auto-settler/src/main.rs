@@ -0,0 +1,110 @@
+//! Auto-settlement daemon for a lobby owner.
+//!
+//! ```text
+//! auto-settler <rpc-url> --owner <keypair> [--results-authority <keypair>]
+//!              [--concurrency <n>] [--priority-fee <micro-lamports>]
+//!              [--interval <seconds>] [--webhook <url>] [--dry-run] [--once]
+//! ```
+//!
+//! Every `--interval` it looks over the owner's lobbies. One whose winner was
+//! verified by proof or attested by the enclave is finished, co-signed by
+//! `--results-authority` where the lobby needs it. In a finished lobby,
+//! winning bets are paid out in batches and losing ones marked lost, then the
+//! settled bets are closed, their rent going back to their bettors, and once
+//! nothing is owed or outstanding the lobby is archived.
+//!
+//! Up to `--concurrency` lobbies are settled at once, every transaction
+//! bidding `--priority-fee` micro-lamports per compute unit. Each batch sent,
+//! or that would be with `--dry-run`, is posted as JSON to `--webhook`.
+//! `--once` makes a single pass and exits, for running from cron.
+
+mod rpc;
+mod settle;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use poker_betting::Lobby;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+
+use crate::rpc::{Fetched, Rpc};
+use crate::settle::Settler;
+
+const USAGE: &str = "usage: auto-settler <rpc-url> --owner <keypair> \
+    [--results-authority <keypair>] [--concurrency <n>] [--priority-fee <micro-lamports>] \
+    [--interval <seconds>] [--webhook <url>] [--dry-run] [--once]";
+const DEFAULT_CONCURRENCY: usize = 4;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+fn read_keypair(path: &str) -> Result<Keypair> {
+    read_keypair_file(path).map_err(|err| anyhow::anyhow!("reading {path}: {err}"))
+}
+
+/// Settles `lobbies` from `concurrency` threads.
+fn settle_all(settler: &Settler, lobbies: &[Fetched<Lobby>], concurrency: usize) {
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| {
+                while let Some(lobby) = lobbies.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Err(err) = settler.settle(lobby) {
+                        eprintln!("{}: settling failed: {err:#}", lobby.address);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(rpc_url) = args.next() else {
+        bail!(USAGE);
+    };
+    let mut owner = None;
+    let mut results_authority = None;
+    let mut concurrency = DEFAULT_CONCURRENCY;
+    let mut priority_fee = 0;
+    let mut interval = DEFAULT_INTERVAL;
+    let mut webhook = None;
+    let mut dry_run = false;
+    let mut once = false;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--owner" => owner = Some(read_keypair(&value()?)?),
+            "--results-authority" => results_authority = Some(read_keypair(&value()?)?),
+            "--concurrency" => concurrency = value()?.parse()?,
+            "--priority-fee" => priority_fee = value()?.parse()?,
+            "--interval" => interval = Duration::from_secs(value()?.parse()?),
+            "--webhook" => webhook = Some(value()?),
+            "--dry-run" => dry_run = true,
+            "--once" => once = true,
+            _ => bail!("unknown argument {arg}"),
+        }
+    }
+    if concurrency == 0 {
+        bail!("--concurrency must be at least 1");
+    }
+    let settler = Settler {
+        rpc: Rpc::new(&rpc_url),
+        owner: owner.context(USAGE)?,
+        results_authority,
+        priority_fee,
+        webhook,
+        dry_run,
+    };
+
+    loop {
+        match settler.lobbies() {
+            Ok(lobbies) => settle_all(&settler, &lobbies, concurrency),
+            Err(err) => eprintln!("listing lobbies failed: {err:#}"),
+        }
+        if once {
+            return Ok(());
+        }
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,149 @@
+//! The Solana JSON-RPC calls the settler makes.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+/// Most accounts `getMultipleAccounts` takes per call.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// How long to wait for a sent transaction to confirm.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Rpc {
+    url: String,
+    agent: ureq::Agent,
+}
+
+/// A program account with its address.
+pub struct Fetched<T> {
+    pub address: Pubkey,
+    pub account: T,
+}
+
+fn decode<T: AccountDeserialize>(address: &Pubkey, data: &Value) -> Result<T> {
+    let data = STANDARD.decode(data[0].as_str().context("account data")?)?;
+    T::try_deserialize(&mut &data[..]).with_context(|| format!("decoding account {address}"))
+}
+
+impl Rpc {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(&request)
+            .with_context(|| method.to_string())?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            bail!("{}", error["message"].as_str().unwrap_or("unknown error"));
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Every account of type `T` owned by `program`, optionally only those
+    /// holding `key` at byte `offset`.
+    pub fn program_accounts<T: AccountDeserialize + Discriminator>(
+        &self,
+        program: &Pubkey,
+        key_at: Option<(usize, &Pubkey)>,
+    ) -> Result<Vec<Fetched<T>>> {
+        let mut filters = vec![json!({
+            "memcmp": {
+                "offset": 0,
+                "bytes": STANDARD.encode(T::DISCRIMINATOR),
+                "encoding": "base64",
+            }
+        })];
+        if let Some((offset, key)) = key_at {
+            filters.push(json!({ "memcmp": { "offset": offset, "bytes": key.to_string() } }));
+        }
+        let config = json!({ "commitment": "confirmed", "encoding": "base64", "filters": filters });
+        let result = self.call("getProgramAccounts", json!([program.to_string(), config]))?;
+        let accounts = result.as_array().context("getProgramAccounts result")?;
+        accounts
+            .iter()
+            .map(|entry| {
+                let address: Pubkey = entry["pubkey"].as_str().context("pubkey")?.parse()?;
+                Ok(Fetched {
+                    address,
+                    account: decode(&address, &entry["account"]["data"])?,
+                })
+            })
+            .collect()
+    }
+
+    /// The accounts at `addresses` decoded as `T`, `None` where there is none.
+    pub fn accounts<T: AccountDeserialize>(&self, addresses: &[Pubkey]) -> Result<Vec<Option<T>>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let keys: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let config = json!({ "commitment": "confirmed", "encoding": "base64" });
+            let result = self.call("getMultipleAccounts", json!([keys, config]))?;
+            let values = result["value"]
+                .as_array()
+                .context("getMultipleAccounts result")?;
+            for (address, value) in chunk.iter().zip(values) {
+                accounts.push(if value.is_null() {
+                    None
+                } else {
+                    Some(decode(address, &value["data"])?)
+                });
+            }
+        }
+        Ok(accounts)
+    }
+
+    pub fn latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        result["value"]["blockhash"]
+            .as_str()
+            .context("getLatestBlockhash result")?
+            .parse()
+            .context("blockhash")
+    }
+
+    /// Sends `transaction` and waits for it to confirm.
+    pub fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature> {
+        let encoded = STANDARD.encode(bincode::serialize(transaction)?);
+        let config = json!({ "encoding": "base64", "preflightCommitment": "confirmed" });
+        let signature: Signature = self
+            .call("sendTransaction", json!([encoded, config]))?
+            .as_str()
+            .context("sendTransaction result")?
+            .parse()
+            .context("signature")?;
+
+        let started = Instant::now();
+        while started.elapsed() < CONFIRM_TIMEOUT {
+            let result = self.call("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = &result["value"][0];
+            if !status.is_null() && status["confirmationStatus"] != "processed" {
+                if !status["err"].is_null() {
+                    bail!("{signature} failed: {}", status["err"]);
+                }
+                return Ok(signature);
+            }
+            sleep(Duration::from_millis(500));
+        }
+        bail!("{signature} did not confirm within {CONFIRM_TIMEOUT:?}")
+    }
+}
@@ -0,0 +1,630 @@
+//! Works out what each of the owner's lobbies needs next and sends it.
+
+use std::fmt;
+use std::slice;
+use std::time::Duration;
+
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anyhow::{Context, Result};
+use poker_betting::{
+    logic, pda, Bet, BetStatus, Lobby, LobbyStatus, MarketMode, MatchStake, PoolSnapshot,
+};
+use serde_json::json;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::rpc::{Fetched, Rpc};
+
+/// Offset of `owner` in a `Lobby`: right after the discriminator.
+const OWNER_OFFSET: usize = 8;
+/// Offset of `lobby` in a `Bet`: after the discriminator and `bettor`.
+const LOBBY_OFFSET: usize = 8 + 32;
+/// Payouts packed into one transaction; each brings up to four new account
+/// keys.
+const PAYOUTS_PER_TRANSACTION: usize = 4;
+/// Bets one `settle_losing_bets` marks lost.
+const LOSSES_PER_INSTRUCTION: usize = 20;
+/// Bet closes packed into one transaction; each brings two new account keys.
+const CLOSES_PER_TRANSACTION: usize = 10;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Finish,
+    Distribute,
+    MarkLosses,
+    SweepUnwonPool,
+    CloseBets,
+    Archive,
+}
+
+impl Stage {
+    fn per_transaction(self) -> usize {
+        match self {
+            Stage::Distribute => PAYOUTS_PER_TRANSACTION,
+            Stage::CloseBets => CLOSES_PER_TRANSACTION,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Finish => "finish",
+            Stage::Distribute => "distribute",
+            Stage::MarkLosses => "mark-losses",
+            Stage::SweepUnwonPool => "sweep-unwon-pool",
+            Stage::CloseBets => "close-bets",
+            Stage::Archive => "archive",
+        })
+    }
+}
+
+pub struct Step {
+    pub stage: Stage,
+    pub lobby: Pubkey,
+    pub instruction: Instruction,
+    /// Bets the instruction settles
+    pub bets: usize,
+    /// Whether the results authority co-signs
+    pub co_signed: bool,
+}
+
+pub struct Settler {
+    pub rpc: Rpc,
+    pub owner: Keypair,
+    pub results_authority: Option<Keypair>,
+    /// Micro-lamports bid per compute unit; none when zero
+    pub priority_fee: u64,
+    pub webhook: Option<String>,
+    pub dry_run: bool,
+}
+
+impl Settler {
+    /// The owner's lobbies with a verified winner to finish them with, or
+    /// finished and not yet archived.
+    pub fn lobbies(&self) -> Result<Vec<Fetched<Lobby>>> {
+        let owner = self.owner.pubkey();
+        let lobbies = self
+            .rpc
+            .program_accounts::<Lobby>(&poker_betting::ID, Some((OWNER_OFFSET, &owner)))?;
+        Ok(lobbies
+            .into_iter()
+            .filter(|lobby| {
+                let lobby = &lobby.account;
+                lobby.status == LobbyStatus::Finished
+                    || (!lobby.status.is_over() && lobby.verified_winner.is_some())
+            })
+            .collect())
+    }
+
+    /// Finishes a lobby whose winner was verified by proof or attested by the
+    /// enclave. Lobbies needing placings or final chips, which aren't on
+    /// chain, are left to be finished by hand.
+    fn finish(&self, lobby: &Fetched<Lobby>) -> Result<Option<Step>> {
+        let (address, account) = (lobby.address, &lobby.account);
+        let Some(winner) = &account.verified_winner else {
+            return Ok(None);
+        };
+        if account.payout_curve.len() > 1 || !account.chip_buckets.is_empty() {
+            eprintln!("{address}: needs placings or final chips, finish it by hand");
+            return Ok(None);
+        }
+        if account.player_pools > 0 && !account.pools_locked {
+            eprintln!("{address}: player pools aren't aggregated yet");
+            return Ok(None);
+        }
+        let co_signer = self.results_authority.as_ref().map(Keypair::pubkey);
+        if let Some(required) = account.results_authority {
+            if co_signer != Some(required) {
+                eprintln!("{address}: results authority {required} must co-sign");
+                return Ok(None);
+            }
+        }
+        let (winner_stake, winner_operator) = if account.match_pool > 0 {
+            let stake = pda::match_stake_pda(&address, winner);
+            let operator = self
+                .rpc
+                .accounts::<MatchStake>(&[stake])?
+                .pop()
+                .flatten()
+                .with_context(|| format!("{address}: no match stake for {winner}"))?
+                .operator;
+            (Some(stake), Some(operator))
+        } else {
+            (None, None)
+        };
+
+        let owner = self.owner.pubkey();
+        let treasury = pda::treasury_pda();
+        let mut accounts = poker_betting::accounts::FinishGame {
+            lobby: address,
+            owner,
+            operator_profile: pda::operator_profile_pda(&owner),
+            engine_game: None,
+            config: pda::config_pda(),
+            treasury,
+            escrow: pda::escrow_pda(&address),
+            winner_stake,
+            winner_operator,
+            system_program: system_program::ID,
+            results_authority: account.results_authority,
+            snapshot: account.pools_locked.then(|| pda::snapshot_pda(&address)),
+            charity: account.charity,
+        }
+        .to_account_metas(None);
+        accounts.extend(
+            account
+                .rake_splits
+                .iter()
+                .filter(|split| split.recipient != treasury)
+                .map(|split| AccountMeta::new(split.recipient, false)),
+        );
+        Ok(Some(Step {
+            stage: Stage::Finish,
+            lobby: address,
+            instruction: Instruction {
+                program_id: poker_betting::ID,
+                accounts,
+                data: poker_betting::instruction::FinishGame {
+                    placings: Vec::new(),
+                    final_chips: Vec::new(),
+                }
+                .data(),
+            },
+            bets: 0,
+            co_signed: account.results_authority.is_some(),
+        }))
+    }
+
+    /// Pays the finished lobby's winning bets and marks its losing ones lost,
+    /// updating `statuses` to what each bet settled ends up as. Bets on
+    /// disqualified models are left for their bettors to refund.
+    fn settle_bets(
+        &self,
+        lobby: &Fetched<Lobby>,
+        bets: &[Fetched<Bet>],
+        statuses: &mut [BetStatus],
+    ) -> Result<Vec<Step>> {
+        let (address, account) = (lobby.address, &lobby.account);
+        // Merkle-settled lobbies are claimed with proofs, and custody funds
+        // must come back before anything is paid
+        if account.payout_root.is_some() || account.payout_root_challenged || account.in_custody > 0
+        {
+            return Ok(Vec::new());
+        }
+        let winner = account
+            .winner
+            .clone()
+            .context("finished without a winner")?;
+
+        let mut steps = Vec::new();
+        let mut losers = Vec::new();
+        for (index, bet) in bets.iter().enumerate() {
+            if account.is_disqualified(&bet.account.player_name) {
+                continue;
+            }
+            if !bet.account.wins(account)? {
+                if bet.account.status.can_become(&BetStatus::Lost) {
+                    losers.push(bet.address);
+                    statuses[index] = BetStatus::Lost;
+                }
+                continue;
+            }
+            if !bet.account.status.can_become(&BetStatus::Paid) {
+                continue;
+            }
+            statuses[index] = BetStatus::Paid;
+            let bettor = bet.account.bettor;
+            let accounts = poker_betting::accounts::DistributeSingleWinning {
+                lobby: address,
+                bet: bet.address,
+                owner: self.owner.pubkey(),
+                escrow: pda::escrow_pda(&address),
+                bettor,
+                payout_recipient: bet.account.payout_address,
+                bettor_stats: pda::bettor_stats_pda(&bettor),
+                snapshot: account.pools_locked.then(|| pda::snapshot_pda(&address)),
+                config: pda::config_pda(),
+                treasury: pda::treasury_pda(),
+                denylist: pda::denylist_pda(),
+                system_program: system_program::ID,
+            };
+            steps.push(Step {
+                stage: Stage::Distribute,
+                lobby: address,
+                instruction: Instruction {
+                    program_id: poker_betting::ID,
+                    accounts: accounts.to_account_metas(None),
+                    data: poker_betting::instruction::DistributeSingleWinning {
+                        winner_name: winner.clone(),
+                    }
+                    .data(),
+                },
+                bets: 1,
+                co_signed: false,
+            });
+        }
+
+        for chunk in losers.chunks(LOSSES_PER_INSTRUCTION) {
+            let mut accounts = poker_betting::accounts::SettleLosingBets {
+                lobby: address,
+                keeper: None,
+                keeper_authority: None,
+                config: None,
+            }
+            .to_account_metas(None);
+            accounts.extend(chunk.iter().map(|bet| AccountMeta::new(*bet, false)));
+            steps.push(Step {
+                stage: Stage::MarkLosses,
+                lobby: address,
+                instruction: Instruction {
+                    program_id: poker_betting::ID,
+                    accounts,
+                    data: poker_betting::instruction::SettleLosingBets {}.data(),
+                },
+                bets: chunk.len(),
+                co_signed: false,
+            });
+        }
+        Ok(steps)
+    }
+
+    /// Pools of the finished lobby, main and chip-count, and its freeroll
+    /// prize, that nobody backed to win, once they can be swept.
+    fn unwon_pool(&self, lobby: &Fetched<Lobby>) -> Result<u64> {
+        let account = &lobby.account;
+        if account.payout_root.is_some() || account.in_custody > 0 {
+            return Ok(0);
+        }
+        let side_pools = logic::unwon_chip_pool(account)? + logic::unwon_freeroll_prize(account)?;
+        if account.market_mode != MarketMode::Parimutuel {
+            return Ok(side_pools);
+        }
+        let snapshot = if account.pools_locked {
+            let snapshot = self
+                .rpc
+                .accounts::<PoolSnapshot>(&[pda::snapshot_pda(&lobby.address)])?
+                .pop()
+                .flatten();
+            Some(snapshot.context("pool snapshot not found")?)
+        } else {
+            None
+        };
+        Ok(logic::unwon_pool(account, snapshot.as_ref())? + side_pools)
+    }
+
+    fn sweep_unwon_pool(&self, lobby: &Fetched<Lobby>) -> Step {
+        let accounts = poker_betting::accounts::SweepUnwonPool {
+            lobby: lobby.address,
+            snapshot: lobby
+                .account
+                .pools_locked
+                .then(|| pda::snapshot_pda(&lobby.address)),
+            escrow: pda::escrow_pda(&lobby.address),
+            treasury: pda::treasury_pda(),
+            system_program: system_program::ID,
+            keeper: None,
+            keeper_authority: None,
+            config: None,
+        };
+        Step {
+            stage: Stage::SweepUnwonPool,
+            lobby: lobby.address,
+            instruction: Instruction {
+                program_id: poker_betting::ID,
+                accounts: accounts.to_account_metas(None),
+                data: poker_betting::instruction::SweepUnwonPool {}.data(),
+            },
+            bets: 0,
+            co_signed: false,
+        }
+    }
+
+    fn close_bet(&self, lobby: &Fetched<Lobby>, bet: &Fetched<Bet>) -> Step {
+        let accounts = poker_betting::accounts::CloseBet {
+            lobby: lobby.address,
+            bet: bet.address,
+            bettor: bet.account.bettor,
+        };
+        Step {
+            stage: Stage::CloseBets,
+            lobby: lobby.address,
+            instruction: Instruction {
+                program_id: poker_betting::ID,
+                accounts: accounts.to_account_metas(None),
+                data: poker_betting::instruction::CloseBet {}.data(),
+            },
+            bets: 1,
+            co_signed: false,
+        }
+    }
+
+    fn archive(&self, lobby: &Fetched<Lobby>) -> Step {
+        let accounts = poker_betting::accounts::ArchiveLobby {
+            lobby: lobby.address,
+            config: pda::config_pda(),
+            owner: self.owner.pubkey(),
+        };
+        Step {
+            stage: Stage::Archive,
+            lobby: lobby.address,
+            instruction: Instruction {
+                program_id: poker_betting::ID,
+                accounts: accounts.to_account_metas(None),
+                data: poker_betting::instruction::ArchiveLobby {}.data(),
+            },
+            bets: 0,
+            co_signed: false,
+        }
+    }
+
+    /// What the lobby needs next: finishing, or settling its bets.
+    pub fn plan(&self, lobby: &Fetched<Lobby>) -> Result<Vec<Step>> {
+        if lobby.account.status != LobbyStatus::Finished {
+            return Ok(self.finish(lobby)?.into_iter().collect());
+        }
+        let bets = self
+            .rpc
+            .program_accounts::<Bet>(&poker_betting::ID, Some((LOBBY_OFFSET, &lobby.address)))?;
+        self.plan_settlement(lobby, &bets)
+    }
+
+    /// Settles a finished lobby's `bets` in the order the program allows:
+    /// winners are paid, then losses marked and a pool nobody won swept,
+    /// then every settled bet closed, and the lobby archived last, once
+    /// nothing is owed and no bet is left outstanding.
+    fn plan_settlement(&self, lobby: &Fetched<Lobby>, bets: &[Fetched<Bet>]) -> Result<Vec<Step>> {
+        let account = &lobby.account;
+        // Statuses the bets have once the payouts and losses land
+        let mut statuses: Vec<BetStatus> =
+            bets.iter().map(|bet| bet.account.status.clone()).collect();
+        let mut steps = self.settle_bets(lobby, bets, &mut statuses)?;
+        let unwon = self.unwon_pool(lobby)?;
+        if unwon > 0 {
+            steps.push(self.sweep_unwon_pool(lobby));
+        }
+        for (bet, status) in bets.iter().zip(&statuses) {
+            let mut after = bet.account.clone();
+            after.status = status.clone();
+            if logic::check_bet_closable(account, &after).is_ok() {
+                steps.push(self.close_bet(lobby, bet));
+            }
+        }
+
+        let outstanding = statuses
+            .iter()
+            .filter(|status| status.is_outstanding())
+            .count();
+        let mut swept = account.clone();
+        swept.escrow_balance = swept.escrow_balance.saturating_sub(unwon);
+        if swept.fully_settled() && outstanding == 0 {
+            steps.push(self.archive(lobby));
+        }
+        Ok(steps)
+    }
+
+    /// Sends the lobby's next steps in batches, a stage at a time. A batch
+    /// that fails is retried one step at a time, so one bad bet doesn't hold
+    /// up the rest of its stage, but no later stage is sent after a failure.
+    pub fn settle(&self, lobby: &Fetched<Lobby>) -> Result<()> {
+        let steps = self.plan(lobby)?;
+        for stage in steps.chunk_by(|a, b| a.stage == b.stage) {
+            let mut failed = false;
+            for batch in stage.chunks(stage[0].stage.per_transaction()) {
+                match self.send(batch) {
+                    Err(err) if batch.len() > 1 => {
+                        eprintln!("{}: batch failed, retrying singly: {err:#}", lobby.address);
+                        for step in batch {
+                            let step = slice::from_ref(step);
+                            let result = self.send(step);
+                            failed |= result.is_err();
+                            self.report(step, &result);
+                        }
+                    }
+                    result => {
+                        failed |= result.is_err();
+                        self.report(batch, &result);
+                    }
+                }
+            }
+            if failed {
+                eprintln!("{}: {} failed, stopping", lobby.address, stage[0].stage);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends `batch` in one transaction; `None` on a dry run.
+    fn send(&self, batch: &[Step]) -> Result<Option<Signature>> {
+        if self.dry_run {
+            return Ok(None);
+        }
+        let mut instructions = Vec::with_capacity(batch.len() + 1);
+        if self.priority_fee > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.priority_fee,
+            ));
+        }
+        instructions.extend(batch.iter().map(|step| step.instruction.clone()));
+        let mut signers = vec![&self.owner];
+        if let Some(results_authority) = &self.results_authority {
+            if batch.iter().any(|step| step.co_signed)
+                && results_authority.pubkey() != self.owner.pubkey()
+            {
+                signers.push(results_authority);
+            }
+        }
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.owner.pubkey()),
+            &signers,
+            self.rpc.latest_blockhash()?,
+        );
+        Ok(Some(self.rpc.send_and_confirm(&transaction)?))
+    }
+
+    /// Logs how `batch` went and posts it to the webhook, if any.
+    fn report(&self, batch: &[Step], result: &Result<Option<Signature>>) {
+        let (lobby, stage) = (batch[0].lobby, batch[0].stage);
+        let bets: usize = batch.iter().map(|step| step.bets).sum();
+        match result {
+            Ok(Some(signature)) => println!("{lobby}: {stage}, {bets} bets: {signature}"),
+            Ok(None) => println!("{lobby}: would {stage}, {bets} bets"),
+            Err(err) => eprintln!("{lobby}: {stage} failed: {err:#}"),
+        }
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        let (signature, error) = match result {
+            Ok(signature) => (signature.map(|signature| signature.to_string()), None),
+            Err(err) => (None, Some(format!("{err:#}"))),
+        };
+        let body = json!({
+            "lobby": lobby.to_string(),
+            "stage": stage.to_string(),
+            "bets": bets,
+            "dry_run": self.dry_run,
+            "signature": signature,
+            "error": error,
+        });
+        if let Err(err) = ureq::post(webhook).timeout(WEBHOOK_TIMEOUT).send_json(body) {
+            eprintln!("webhook failed: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AnchorDeserialize;
+    use poker_betting::BetSide;
+
+    use super::*;
+
+    fn settler() -> Settler {
+        Settler {
+            rpc: Rpc::new("http://localhost:8899"),
+            owner: Keypair::new(),
+            results_authority: None,
+            priority_fee: 0,
+            webhook: None,
+            dry_run: true,
+        }
+    }
+
+    /// A lobby of models `a` and `b` that `a` won, with nothing left in
+    /// escrow.
+    fn finished_lobby() -> Fetched<Lobby> {
+        let mut lobby = Lobby::deserialize(&mut &vec![0; Lobby::LEN][..]).unwrap();
+        lobby.status = LobbyStatus::Finished;
+        lobby.model_names = vec!["a".to_string(), "b".to_string()];
+        lobby.model_pools = vec![0; 2];
+        lobby.lay_pools = vec![0; 2];
+        lobby.disqualified = vec![false; 2];
+        lobby.freeroll_picks = vec![0; 2];
+        lobby.payout_curve = vec![10_000];
+        lobby.position_paid = vec![0];
+        lobby.winner = Some("a".to_string());
+        Fetched {
+            address: Pubkey::new_unique(),
+            account: lobby,
+        }
+    }
+
+    fn bet(lobby: &Fetched<Lobby>, player_name: &str, status: BetStatus) -> Fetched<Bet> {
+        let mut bet = Bet::deserialize(&mut &vec![0; Bet::LEN][..]).unwrap();
+        bet.bettor = Pubkey::new_unique();
+        bet.lobby = lobby.address;
+        bet.player_name = player_name.to_string();
+        bet.amount = 1_000;
+        bet.side = BetSide::Back;
+        bet.status = status;
+        Fetched {
+            address: Pubkey::new_unique(),
+            account: bet,
+        }
+    }
+
+    fn stages(steps: &[Step]) -> Vec<Stage> {
+        steps.iter().map(|step| step.stage).collect()
+    }
+
+    #[test]
+    fn pays_then_marks_losses_then_closes() {
+        let mut lobby = finished_lobby();
+        lobby.account.escrow_balance = 2_000;
+        let bets = [
+            bet(&lobby, "b", BetStatus::Active),
+            bet(&lobby, "a", BetStatus::Paid),
+            bet(&lobby, "a", BetStatus::Locked),
+        ];
+        let steps = settler().plan_settlement(&lobby, &bets).unwrap();
+        // Still owed, so not archived yet
+        assert_eq!(
+            stages(&steps),
+            [
+                Stage::Distribute,
+                Stage::MarkLosses,
+                Stage::CloseBets,
+                Stage::CloseBets,
+                Stage::CloseBets,
+            ]
+        );
+    }
+
+    #[test]
+    fn archives_last_once_every_bet_is_settled() {
+        let lobby = finished_lobby();
+        assert!(lobby.account.fully_settled());
+        let bets = [
+            bet(&lobby, "a", BetStatus::Paid),
+            bet(&lobby, "b", BetStatus::Locked),
+        ];
+        let steps = settler().plan_settlement(&lobby, &bets).unwrap();
+        assert_eq!(
+            stages(&steps),
+            [
+                Stage::MarkLosses,
+                Stage::CloseBets,
+                Stage::CloseBets,
+                Stage::Archive,
+            ]
+        );
+    }
+
+    #[test]
+    fn a_pool_nobody_won_is_swept_before_the_archive() {
+        let mut lobby = finished_lobby();
+        lobby.account.model_pools = vec![0, 1_000];
+        lobby.account.total_bets = 1_000;
+        lobby.account.escrow_balance = 1_000;
+        let bets = [bet(&lobby, "b", BetStatus::Locked)];
+        let steps = settler().plan_settlement(&lobby, &bets).unwrap();
+        assert_eq!(
+            stages(&steps),
+            [
+                Stage::MarkLosses,
+                Stage::SweepUnwonPool,
+                Stage::CloseBets,
+                Stage::Archive,
+            ]
+        );
+    }
+
+    #[test]
+    fn an_unrefunded_voided_bet_holds_up_the_archive() {
+        let mut lobby = finished_lobby();
+        lobby.account.disqualified[1] = true;
+        let bets = [
+            bet(&lobby, "a", BetStatus::Paid),
+            bet(&lobby, "b", BetStatus::Active),
+        ];
+        let steps = settler().plan_settlement(&lobby, &bets).unwrap();
+        assert_eq!(stages(&steps), [Stage::CloseBets]);
+    }
+}
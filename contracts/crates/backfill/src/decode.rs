@@ -0,0 +1,252 @@
+//! Turns a `getTransaction` response into the records the dataset keeps.
+//!
+//! Only top-level instructions to the betting program are decoded; account
+//! positions follow the program's `Accounts` structs, where an omitted
+//! optional account is passed as the program id.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{Context, Result};
+use poker_betting::{instruction, BetSide};
+use poker_betting_events::{parse_logs, ProgramEvent, PROGRAM_ID};
+use serde_json::Value;
+
+/// `CreateLobby` accounts
+const CREATE_LOBBY_LOBBY: usize = 3;
+const CREATE_LOBBY_OWNER: usize = 5;
+/// `PlaceBet` accounts
+const PLACE_BET_LOBBY: usize = 0;
+const PLACE_BET_BET: usize = 1;
+const PLACE_BET_BETTOR: usize = 2;
+/// `FinishGame` accounts
+const FINISH_GAME_LOBBY: usize = 0;
+/// `DistributeSingleWinning` accounts
+const DISTRIBUTE_LOBBY: usize = 0;
+const DISTRIBUTE_BET: usize = 1;
+const DISTRIBUTE_BETTOR: usize = 4;
+const DISTRIBUTE_PAYOUT_RECIPIENT: usize = 5;
+/// `ClaimAll` accounts, followed by groups of lobby, bet, escrow, snapshot
+/// and recipient
+const CLAIM_ALL_BETTOR: usize = 0;
+const CLAIM_ALL_GROUPS: usize = 6;
+
+pub enum Record {
+    LobbyCreated {
+        lobby: String,
+        owner: String,
+        game_id: String,
+        model_names: Vec<String>,
+        starting_chips: u64,
+    },
+    BetPlaced {
+        bet: String,
+        lobby: String,
+        bettor: String,
+        player_name: String,
+        amount: u64,
+        side: String,
+    },
+    GameFinished {
+        lobby: String,
+        placings: Vec<String>,
+    },
+    BetSettled {
+        bet: String,
+        lobby: String,
+        bettor: String,
+        winner_name: Option<String>,
+        /// Lamports the recipient gained, when no other transfer to it in the
+        /// transaction blurs that
+        payout: Option<u64>,
+    },
+    LobbyUpdated {
+        lobby: String,
+        seq: u64,
+        action: String,
+        updated_at: i64,
+    },
+}
+
+pub struct Transaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Records with the index of the instruction they came from; events use
+    /// the instruction count
+    pub records: Vec<(usize, Record)>,
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn numbers(value: &Value) -> Vec<u64> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(Value::as_u64).collect())
+        .unwrap_or_default()
+}
+
+fn args<T: AnchorDeserialize>(mut data: &[u8]) -> Result<T> {
+    Ok(T::deserialize(&mut data)?)
+}
+
+/// Decodes a successful transaction; `None` for failed ones.
+pub fn decode_transaction(tx: &Value) -> Result<Option<Transaction>> {
+    let meta = &tx["meta"];
+    if !meta["err"].is_null() {
+        return Ok(None);
+    }
+    let message = &tx["transaction"]["message"];
+    let mut keys = strings(&message["accountKeys"]);
+    keys.extend(strings(&meta["loadedAddresses"]["writable"]));
+    keys.extend(strings(&meta["loadedAddresses"]["readonly"]));
+    let pre_balances = numbers(&meta["preBalances"]);
+    let post_balances = numbers(&meta["postBalances"]);
+    let program_id = PROGRAM_ID.to_string();
+    let fee_payer = keys.first().cloned().unwrap_or_default();
+
+    let instructions = message["instructions"]
+        .as_array()
+        .context("transaction instructions")?;
+    let mut records = Vec::new();
+    for (index, ix) in instructions.iter().enumerate() {
+        let program = ix["programIdIndex"].as_u64().context("programIdIndex")? as usize;
+        if keys.get(program) != Some(&program_id) {
+            continue;
+        }
+        let accounts: Vec<usize> = numbers(&ix["accounts"])
+            .into_iter()
+            .map(|i| i as usize)
+            .collect();
+        let account = |position: usize| -> Result<String> {
+            let key = accounts
+                .get(position)
+                .and_then(|i| keys.get(*i))
+                .context("instruction account out of range")?;
+            Ok(key.clone())
+        };
+        let received = |key: &str| -> Option<u64> {
+            if key == fee_payer {
+                return None;
+            }
+            let i = keys.iter().position(|k| k == key)?;
+            post_balances.get(i)?.checked_sub(*pre_balances.get(i)?)
+        };
+        let data = bs58::decode(ix["data"].as_str().context("instruction data")?).into_vec()?;
+        if data.len() < 8 {
+            continue;
+        }
+        let (discriminator, body) = data.split_at(8);
+
+        if discriminator == instruction::CreateLobby::DISCRIMINATOR {
+            let ix: instruction::CreateLobby = args(body)?;
+            records.push((
+                index,
+                Record::LobbyCreated {
+                    lobby: account(CREATE_LOBBY_LOBBY)?,
+                    owner: account(CREATE_LOBBY_OWNER)?,
+                    game_id: ix.game_id,
+                    model_names: ix.model_names,
+                    starting_chips: ix.starting_chips,
+                },
+            ));
+        } else if discriminator == instruction::PlaceBet::DISCRIMINATOR {
+            // Only the arguments every version of `place_bet` starts with
+            let (player_name, amount, _min_expected_odds_bps, side): (String, u64, u32, BetSide) =
+                args(body)?;
+            records.push((
+                index,
+                Record::BetPlaced {
+                    bet: account(PLACE_BET_BET)?,
+                    lobby: account(PLACE_BET_LOBBY)?,
+                    bettor: account(PLACE_BET_BETTOR)?,
+                    player_name,
+                    amount,
+                    side: format!("{side:?}"),
+                },
+            ));
+        } else if discriminator == instruction::FinishGame::DISCRIMINATOR {
+            let ix: instruction::FinishGame = args(body)?;
+            records.push((
+                index,
+                Record::GameFinished {
+                    lobby: account(FINISH_GAME_LOBBY)?,
+                    placings: ix.placings,
+                },
+            ));
+        } else if discriminator == instruction::DistributeSingleWinning::DISCRIMINATOR {
+            let ix: instruction::DistributeSingleWinning = args(body)?;
+            let bettor = account(DISTRIBUTE_BETTOR)?;
+            let recipient = account(DISTRIBUTE_PAYOUT_RECIPIENT)?;
+            let recipient = if recipient == program_id {
+                bettor.clone()
+            } else {
+                recipient
+            };
+            records.push((
+                index,
+                Record::BetSettled {
+                    bet: account(DISTRIBUTE_BET)?,
+                    lobby: account(DISTRIBUTE_LOBBY)?,
+                    bettor,
+                    winner_name: Some(ix.winner_name),
+                    payout: received(&recipient),
+                },
+            ));
+        } else if discriminator == instruction::ClaimAll::DISCRIMINATOR {
+            let bettor = account(CLAIM_ALL_BETTOR)?;
+            let groups =
+                accounts.len().saturating_sub(CLAIM_ALL_GROUPS) / poker_betting::CLAIM_GROUP_LEN;
+            for group in 0..groups {
+                let first = CLAIM_ALL_GROUPS + group * poker_betting::CLAIM_GROUP_LEN;
+                records.push((
+                    index,
+                    Record::BetSettled {
+                        bet: account(first + 1)?,
+                        lobby: account(first)?,
+                        bettor: bettor.clone(),
+                        winner_name: None,
+                        payout: if groups == 1 {
+                            received(&account(first + 4)?)
+                        } else {
+                            None
+                        },
+                    },
+                ));
+            }
+        }
+    }
+
+    let logs = strings(&meta["logMessages"]);
+    let parsed = parse_logs(&logs);
+    for error in &parsed.errors {
+        let signature = tx["transaction"]["signatures"][0].as_str().unwrap_or("?");
+        eprintln!("{signature}: skipping an event: {error}");
+    }
+    for event in parsed.events {
+        if let ProgramEvent::LobbyUpdated(event) = event {
+            records.push((
+                instructions.len(),
+                Record::LobbyUpdated {
+                    lobby: event.lobby.to_string(),
+                    seq: event.seq,
+                    action: format!("{:?}", event.action),
+                    updated_at: event.updated_at,
+                },
+            ));
+        }
+    }
+
+    Ok(Some(Transaction {
+        slot: tx["slot"].as_u64().context("slot")?,
+        block_time: tx["blockTime"].as_i64(),
+        records,
+    }))
+}
@@ -0,0 +1,89 @@
+//! Walks the betting program's transaction history over RPC and writes a
+//! normalized dataset of lobbies, bets and settlements for analytics.
+//!
+//! ```text
+//! backfill <rpc-url> <database> [--max-transactions <n>]
+//! ```
+//!
+//! `<database>` is a SQLite file path, or a `postgres://` URL when built with
+//! the `postgres` feature. The walk goes from the newest signature back to the
+//! program's first, and a rerun resumes below the oldest one already stored.
+
+mod decode;
+mod rpc;
+mod store;
+
+use anyhow::{bail, Context, Result};
+use poker_betting_events::PROGRAM_ID;
+
+use crate::decode::decode_transaction;
+use crate::rpc::Rpc;
+use crate::store::Store;
+
+/// Signatures requested per page; the RPC maximum.
+const PAGE_SIZE: usize = 1_000;
+
+fn open_store(database: &str) -> Result<Box<dyn Store>> {
+    if database.starts_with("postgres://") || database.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        return Ok(Box::new(store::Postgres::connect(database)?));
+        #[cfg(not(feature = "postgres"))]
+        bail!("built without the `postgres` feature");
+    }
+    let path = database.strip_prefix("sqlite://").unwrap_or(database);
+    Ok(Box::new(store::Sqlite::open(path)?))
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(rpc_url), Some(database)) = (args.next(), args.next()) else {
+        bail!("usage: backfill <rpc-url> <database> [--max-transactions <n>]");
+    };
+    let mut max_transactions = usize::MAX;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-transactions" => {
+                max_transactions = args
+                    .next()
+                    .context("--max-transactions needs a value")?
+                    .parse()?;
+            }
+            _ => bail!("unknown argument {arg}"),
+        }
+    }
+
+    let rpc = Rpc::new(&rpc_url);
+    let mut store = open_store(&database)?;
+    store::migrate(store.as_mut())?;
+    let mut before = store.state(store::CURSOR)?;
+    let program_id = PROGRAM_ID.to_string();
+    let mut processed = 0;
+    while processed < max_transactions {
+        let page = rpc.signatures(&program_id, before.as_deref(), PAGE_SIZE)?;
+        if page.is_empty() {
+            break;
+        }
+        for info in page.iter().take(max_transactions - processed) {
+            let tx = if info.failed {
+                None
+            } else {
+                match rpc.transaction(&info.signature)? {
+                    Some(tx) => decode_transaction(&tx)
+                        .with_context(|| format!("decoding {}", info.signature))?,
+                    None => {
+                        eprintln!("{} is no longer available, skipping", info.signature);
+                        None
+                    }
+                }
+            };
+            store::ingest(store.as_mut(), &info.signature, tx.as_ref())?;
+            before = Some(info.signature.clone());
+            processed += 1;
+        }
+        eprintln!(
+            "{processed} transactions, back to {}",
+            before.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Just enough Solana JSON-RPC for walking a program's history.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+/// Attempts per request before giving up; public RPCs rate-limit bursts.
+const MAX_ATTEMPTS: u32 = 5;
+
+pub struct Rpc {
+    url: String,
+    agent: ureq::Agent,
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub failed: bool,
+}
+
+impl Rpc {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut attempt = 1;
+        let mut response: Value = loop {
+            match self.agent.post(&self.url).send_json(&request) {
+                Ok(response) => break response.into_json()?,
+                Err(err) if attempt < MAX_ATTEMPTS => {
+                    eprintln!("{method} failed ({err}), retrying");
+                    sleep(Duration::from_millis(500 << attempt));
+                    attempt += 1;
+                }
+                Err(err) => return Err(err).context(method.to_string()),
+            }
+        };
+        if let Some(error) = response.get("error") {
+            bail!("{method}: {error}");
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Up to `limit` signatures involving `address`, newest first, older than
+    /// `before` if given.
+    pub fn signatures(
+        &self,
+        address: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "limit": limit, "commitment": "finalized" });
+        if let Some(before) = before {
+            config["before"] = json!(before);
+        }
+        let result = self.call("getSignaturesForAddress", json!([address, config]))?;
+        let entries = result
+            .as_array()
+            .context("getSignaturesForAddress result")?;
+        entries
+            .iter()
+            .map(|entry| {
+                Ok(SignatureInfo {
+                    signature: entry["signature"]
+                        .as_str()
+                        .context("signature")?
+                        .to_string(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect()
+    }
+
+    /// The transaction in JSON encoding, or `None` if the node no longer has it.
+    pub fn transaction(&self, signature: &str) -> Result<Option<Value>> {
+        let config = json!({
+            "encoding": "json",
+            "commitment": "finalized",
+            "maxSupportedTransactionVersion": 0,
+        });
+        let result = self.call("getTransaction", json!([signature, config]))?;
+        Ok((!result.is_null()).then_some(result))
+    }
+}
@@ -0,0 +1,319 @@
+//! SQL sink for the dataset. Statements are written once, with `$n`
+//! placeholders, in SQL that SQLite and Postgres both accept.
+
+use anyhow::{Context, Result};
+
+use crate::decode::{Record, Transaction};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS lobbies (
+    address TEXT PRIMARY KEY,
+    game_id TEXT,
+    owner TEXT,
+    model_names TEXT,
+    starting_chips BIGINT,
+    created_slot BIGINT,
+    created_at BIGINT,
+    finished_slot BIGINT,
+    finished_at BIGINT,
+    placings TEXT,
+    winner TEXT
+);
+CREATE TABLE IF NOT EXISTS bets (
+    signature TEXT NOT NULL,
+    instruction_index BIGINT NOT NULL,
+    address TEXT NOT NULL,
+    lobby TEXT NOT NULL,
+    bettor TEXT NOT NULL,
+    player_name TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    side TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    placed_at BIGINT,
+    PRIMARY KEY (signature, instruction_index)
+);
+CREATE TABLE IF NOT EXISTS settlements (
+    signature TEXT NOT NULL,
+    instruction_index BIGINT NOT NULL,
+    bet TEXT NOT NULL,
+    lobby TEXT NOT NULL,
+    bettor TEXT NOT NULL,
+    payout BIGINT,
+    slot BIGINT NOT NULL,
+    settled_at BIGINT,
+    PRIMARY KEY (signature, instruction_index, bet)
+);
+CREATE TABLE IF NOT EXISTS lobby_updates (
+    lobby TEXT NOT NULL,
+    seq BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    signature TEXT NOT NULL,
+    PRIMARY KEY (lobby, seq)
+);
+CREATE TABLE IF NOT EXISTS backfill_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+pub enum Param {
+    Int(Option<i64>),
+    Text(Option<String>),
+}
+
+fn int(value: u64) -> Result<Param> {
+    Ok(Param::Int(Some(
+        i64::try_from(value).context("value exceeds BIGINT")?,
+    )))
+}
+
+fn text(value: &str) -> Param {
+    Param::Text(Some(value.to_string()))
+}
+
+pub trait Store {
+    fn batch(&mut self, sql: &str) -> Result<()>;
+    fn execute(&mut self, sql: &str, params: &[Param]) -> Result<()>;
+    fn state(&mut self, key: &str) -> Result<Option<String>>;
+}
+
+pub struct Sqlite(rusqlite::Connection);
+
+impl Sqlite {
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Self(rusqlite::Connection::open(path)?))
+    }
+}
+
+impl Store for Sqlite {
+    fn batch(&mut self, sql: &str) -> Result<()> {
+        Ok(self.0.execute_batch(sql)?)
+    }
+
+    fn execute(&mut self, sql: &str, params: &[Param]) -> Result<()> {
+        use rusqlite::types::Value;
+        let params = params.iter().map(|param| match param {
+            Param::Int(v) => v.map_or(Value::Null, Value::Integer),
+            Param::Text(v) => v.clone().map_or(Value::Null, Value::Text),
+        });
+        // `?n` binds by position in SQLite, where `$n` would be a name
+        self.0
+            .execute(&sql.replace('$', "?"), rusqlite::params_from_iter(params))?;
+        Ok(())
+    }
+
+    fn state(&mut self, key: &str) -> Result<Option<String>> {
+        use rusqlite::OptionalExtension;
+        Ok(self
+            .0
+            .query_row(
+                "SELECT value FROM backfill_state WHERE key = ?1",
+                [key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub struct Postgres(postgres::Client);
+
+#[cfg(feature = "postgres")]
+impl Postgres {
+    pub fn connect(url: &str) -> Result<Self> {
+        Ok(Self(postgres::Client::connect(url, postgres::NoTls)?))
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl Store for Postgres {
+    fn batch(&mut self, sql: &str) -> Result<()> {
+        Ok(self.0.batch_execute(sql)?)
+    }
+
+    fn execute(&mut self, sql: &str, params: &[Param]) -> Result<()> {
+        let params: Vec<Box<dyn postgres::types::ToSql + Sync>> = params
+            .iter()
+            .map(|param| -> Box<dyn postgres::types::ToSql + Sync> {
+                match param {
+                    Param::Int(v) => Box::new(*v),
+                    Param::Text(v) => Box::new(v.clone()),
+                }
+            })
+            .collect();
+        let params: Vec<&(dyn postgres::types::ToSql + Sync)> =
+            params.iter().map(|param| param.as_ref()).collect();
+        self.0.execute(sql, &params)?;
+        Ok(())
+    }
+
+    fn state(&mut self, key: &str) -> Result<Option<String>> {
+        let row = self
+            .0
+            .query_opt("SELECT value FROM backfill_state WHERE key = $1", &[&key])?;
+        Ok(row.map(|row| row.get(0)))
+    }
+}
+
+pub fn migrate(store: &mut dyn Store) -> Result<()> {
+    store.batch(SCHEMA)
+}
+
+fn set_state(store: &mut dyn Store, key: &str, value: &str) -> Result<()> {
+    store.execute(
+        "INSERT INTO backfill_state (key, value) VALUES ($1, $2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        &[text(key), text(value)],
+    )
+}
+
+fn ensure_lobby(store: &mut dyn Store, lobby: &str) -> Result<()> {
+    store.execute(
+        "INSERT INTO lobbies (address) VALUES ($1) ON CONFLICT DO NOTHING",
+        &[text(lobby)],
+    )
+}
+
+/// Key of the oldest signature processed, where the walk resumes.
+pub const CURSOR: &str = "before";
+
+/// Writes a transaction's records, if any, and moves the cursor past it, in a
+/// single database transaction.
+pub fn ingest(store: &mut dyn Store, signature: &str, tx: Option<&Transaction>) -> Result<()> {
+    store.batch("BEGIN")?;
+    let written = match tx {
+        Some(tx) => write_records(store, signature, tx),
+        None => Ok(()),
+    }
+    .and_then(|()| set_state(store, CURSOR, signature));
+    match written {
+        Ok(()) => store.batch("COMMIT"),
+        Err(err) => {
+            store.batch("ROLLBACK")?;
+            Err(err)
+        }
+    }
+}
+
+/// Safe to repeat, and to apply out of order: the walk goes newest to oldest.
+fn write_records(store: &mut dyn Store, signature: &str, tx: &Transaction) -> Result<()> {
+    let time = || Param::Int(tx.block_time);
+    for (index, record) in &tx.records {
+        let index = int(*index as u64)?;
+        match record {
+            Record::LobbyCreated {
+                lobby,
+                owner,
+                game_id,
+                model_names,
+                starting_chips,
+            } => {
+                ensure_lobby(store, lobby)?;
+                store.execute(
+                    "UPDATE lobbies SET game_id = $2, owner = $3, model_names = $4,
+                     starting_chips = $5, created_slot = $6, created_at = $7
+                     WHERE address = $1",
+                    &[
+                        text(lobby),
+                        text(game_id),
+                        text(owner),
+                        text(&serde_json::to_string(model_names)?),
+                        int(*starting_chips)?,
+                        int(tx.slot)?,
+                        time(),
+                    ],
+                )?;
+            }
+            Record::BetPlaced {
+                bet,
+                lobby,
+                bettor,
+                player_name,
+                amount,
+                side,
+            } => {
+                store.execute(
+                    "INSERT INTO bets (signature, instruction_index, address, lobby, bettor,
+                     player_name, amount, side, slot, placed_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT DO NOTHING",
+                    &[
+                        text(signature),
+                        index,
+                        text(bet),
+                        text(lobby),
+                        text(bettor),
+                        text(player_name),
+                        int(*amount)?,
+                        text(side),
+                        int(tx.slot)?,
+                        time(),
+                    ],
+                )?;
+            }
+            Record::GameFinished { lobby, placings } => {
+                ensure_lobby(store, lobby)?;
+                store.execute(
+                    "UPDATE lobbies SET finished_slot = $2, finished_at = $3, placings = $4,
+                     winner = COALESCE($5, winner) WHERE address = $1",
+                    &[
+                        text(lobby),
+                        int(tx.slot)?,
+                        time(),
+                        text(&serde_json::to_string(placings)?),
+                        Param::Text(placings.first().cloned()),
+                    ],
+                )?;
+            }
+            Record::BetSettled {
+                bet,
+                lobby,
+                bettor,
+                winner_name,
+                payout,
+            } => {
+                store.execute(
+                    "INSERT INTO settlements (signature, instruction_index, bet, lobby, bettor,
+                     payout, slot, settled_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING",
+                    &[
+                        text(signature),
+                        index,
+                        text(bet),
+                        text(lobby),
+                        text(bettor),
+                        payout.map(int).transpose()?.unwrap_or(Param::Int(None)),
+                        int(tx.slot)?,
+                        time(),
+                    ],
+                )?;
+                if let Some(winner_name) = winner_name {
+                    ensure_lobby(store, lobby)?;
+                    store.execute(
+                        "UPDATE lobbies SET winner = $2 WHERE address = $1 AND winner IS NULL",
+                        &[text(lobby), text(winner_name)],
+                    )?;
+                }
+            }
+            Record::LobbyUpdated {
+                lobby,
+                seq,
+                action,
+                updated_at,
+            } => {
+                store.execute(
+                    "INSERT INTO lobby_updates (lobby, seq, action, updated_at, signature)
+                     VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+                    &[
+                        text(lobby),
+                        int(*seq)?,
+                        text(action),
+                        Param::Int(Some(*updated_at)),
+                        text(signature),
+                    ],
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Operator commands for the poker-betting and poker-engine programs.
+//!
+//! ```text
+//! cli sweep-rent <rpc-url> --owner <keypair> [--dry-run]
+//! ```
+//!
+//! `sweep-rent` reclaims the rent `--owner` paid for accounts nothing reads
+//! any more: its settled bets are closed, its fully settled lobbies are
+//! archived down to an audit stub, and, when it is the engine authority, the
+//! hand records of finalized games whose lobbies have all settled are closed.
+//! Closes are sent in batches and the SOL recovered is printed per kind;
+//! `--dry-run` only reports what would be.
+
+mod rpc;
+mod sweep;
+
+use anyhow::{bail, Context, Result};
+use solana_sdk::signature::{read_keypair_file, Signer};
+
+use crate::rpc::Rpc;
+
+const USAGE: &str = "usage: cli sweep-rent <rpc-url> --owner <keypair> [--dry-run]";
+
+fn sweep_rent(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let Some(rpc_url) = args.next() else {
+        bail!(USAGE);
+    };
+    let mut owner = None;
+    let mut dry_run = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--owner" => owner = Some(args.next().context("--owner needs a value")?),
+            "--dry-run" => dry_run = true,
+            _ => bail!("unknown argument {arg}"),
+        }
+    }
+    let owner = owner.context(USAGE)?;
+    let wallet =
+        read_keypair_file(&owner).map_err(|err| anyhow::anyhow!("reading {owner}: {err}"))?;
+
+    let rpc = Rpc::new(&rpc_url);
+    let closes = sweep::find(&rpc, &wallet.pubkey())?;
+    if closes.is_empty() {
+        println!("nothing to close for {}", wallet.pubkey());
+        return Ok(());
+    }
+    sweep::run(&rpc, &wallet, &closes, dry_run)
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("sweep-rent") => sweep_rent(args),
+        _ => bail!(USAGE),
+    }
+}
@@ -0,0 +1,240 @@
+//! `sweep-rent`: finds the accounts a wallet paid rent for that nothing reads
+//! any more, and closes them in batches.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
+use anyhow::Result;
+use poker_betting::{logic, pda, Bet, Lobby, LobbyStatus};
+use poker_engine::{EngineConfig, GameRecord, HandRecord};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::rpc::Rpc;
+
+/// Closes packed into one transaction. Each adds at most three new account
+/// keys, so this stays under the transaction size limit.
+const CLOSES_PER_TRANSACTION: usize = 6;
+
+/// Offset of `bettor` in a `Bet` and of `owner` in a `Lobby`: right after the
+/// discriminator.
+const WALLET_OFFSET: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Bet,
+    /// Archived rather than closed; only the rent above the stub's comes back
+    Lobby,
+    HandRecord,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Bet => "bets",
+            Kind::Lobby => "lobbies",
+            Kind::HandRecord => "hand records",
+        })
+    }
+}
+
+pub struct Close {
+    pub kind: Kind,
+    pub instruction: Instruction,
+    /// Rent returned to the wallet once the instruction lands
+    pub lamports: u64,
+}
+
+/// Settled bets placed by `wallet`.
+fn bets(rpc: &Rpc, wallet: &Pubkey) -> Result<Vec<Close>> {
+    let bets = rpc.program_accounts::<Bet>(&poker_betting::ID, Some((WALLET_OFFSET, wallet)))?;
+    let lobby_keys: Vec<Pubkey> = bets.iter().map(|bet| bet.account.lobby).collect();
+    let lobbies = rpc.accounts::<Lobby>(&lobby_keys)?;
+
+    let mut closes = Vec::new();
+    for (bet, lobby) in bets.into_iter().zip(lobbies) {
+        let Some(lobby) = lobby else {
+            continue;
+        };
+        if logic::check_bet_closable(&lobby, &bet.account).is_err() {
+            continue;
+        }
+        let accounts = poker_betting::accounts::CloseBet {
+            lobby: bet.account.lobby,
+            bet: bet.address,
+            bettor: *wallet,
+        };
+        closes.push(Close {
+            kind: Kind::Bet,
+            instruction: Instruction {
+                program_id: poker_betting::ID,
+                accounts: accounts.to_account_metas(None),
+                data: poker_betting::instruction::CloseBet {}.data(),
+            },
+            lamports: bet.lamports,
+        });
+    }
+    Ok(closes)
+}
+
+/// Fully settled lobbies owned by `wallet`, to archive.
+fn lobbies(rpc: &Rpc, wallet: &Pubkey) -> Result<Vec<Close>> {
+    let lobbies =
+        rpc.program_accounts::<Lobby>(&poker_betting::ID, Some((WALLET_OFFSET, wallet)))?;
+    let mut closes = Vec::new();
+    for lobby in lobbies {
+        if !lobby.account.fully_settled() {
+            continue;
+        }
+        let mut stub = lobby.account;
+        stub.strip_for_archive();
+        stub.status = LobbyStatus::Archived;
+        let stub_rent = Rent::default().minimum_balance(8 + stub.try_to_vec()?.len());
+        let accounts = poker_betting::accounts::ArchiveLobby {
+            lobby: lobby.address,
+            config: pda::config_pda(),
+            owner: *wallet,
+        };
+        closes.push(Close {
+            kind: Kind::Lobby,
+            instruction: Instruction {
+                program_id: poker_betting::ID,
+                accounts: accounts.to_account_metas(None),
+                data: poker_betting::instruction::ArchiveLobby {}.data(),
+            },
+            lamports: lobby.lamports.saturating_sub(stub_rent),
+        });
+    }
+    Ok(closes)
+}
+
+/// Hand records of finalized games whose lobbies have all settled, when
+/// `wallet` is the engine authority that paid for them.
+fn hand_records(rpc: &Rpc, wallet: &Pubkey) -> Result<Vec<Close>> {
+    let engine = Pubkey::find_program_address(&[b"engine"], &poker_engine::ID).0;
+    let Some(Some(config)) = rpc.accounts::<EngineConfig>(&[engine])?.pop() else {
+        return Ok(Vec::new());
+    };
+    if config.authority != *wallet {
+        return Ok(Vec::new());
+    }
+
+    // A lobby reads its game's hand records until it is fully settled, and
+    // a settled one signs for their closing
+    let lobbies = rpc.program_accounts::<Lobby>(&poker_betting::ID, None)?;
+    let mut in_use = HashSet::new();
+    let mut settled = HashMap::new();
+    for lobby in lobbies {
+        let game = (lobby.account.owner, lobby.account.game_id.clone());
+        if lobby.account.status == LobbyStatus::Archived || lobby.account.fully_settled() {
+            settled.insert(game, lobby.address);
+        } else {
+            in_use.insert(game);
+        }
+    }
+    let games: HashMap<Pubkey, Pubkey> = rpc
+        .program_accounts::<GameRecord>(&poker_engine::ID, None)?
+        .into_iter()
+        .filter_map(|game| {
+            let key = (game.account.owner, game.account.game_id);
+            if !game.account.finalized || in_use.contains(&key) {
+                return None;
+            }
+            Some((game.address, *settled.get(&key)?))
+        })
+        .collect();
+
+    let hands = rpc.program_accounts::<HandRecord>(&poker_engine::ID, None)?;
+    Ok(hands
+        .into_iter()
+        .filter_map(|hand| {
+            let lobby = *games.get(&hand.account.game)?;
+            let accounts = poker_betting::accounts::CloseHandRecord {
+                lobby,
+                engine,
+                engine_game: hand.account.game,
+                hand: hand.address,
+                authority: *wallet,
+                engine_program: poker_engine::ID,
+            };
+            Some(Close {
+                kind: Kind::HandRecord,
+                instruction: Instruction {
+                    program_id: poker_betting::ID,
+                    accounts: accounts.to_account_metas(None),
+                    data: poker_betting::instruction::CloseHandRecord {
+                        hand_number: hand.account.hand_number,
+                    }
+                    .data(),
+                },
+                lamports: hand.lamports,
+            })
+        })
+        .collect())
+}
+
+/// Everything `wallet` can close now.
+pub fn find(rpc: &Rpc, wallet: &Pubkey) -> Result<Vec<Close>> {
+    let mut closes = bets(rpc, wallet)?;
+    closes.extend(lobbies(rpc, wallet)?);
+    closes.extend(hand_records(rpc, wallet)?);
+    Ok(closes)
+}
+
+/// Closes `closes` in batches signed and paid for by `wallet`, then prints
+/// the rent recovered per kind. A batch that fails is reported and skipped.
+pub fn run(rpc: &Rpc, wallet: &Keypair, closes: &[Close], dry_run: bool) -> Result<()> {
+    let mut recovered: Vec<(Kind, usize, u64)> = Vec::new();
+    let mut record = |close: &Close| match recovered
+        .iter_mut()
+        .find(|(kind, _, _)| *kind == close.kind)
+    {
+        Some((_, count, lamports)) => {
+            *count += 1;
+            *lamports += close.lamports;
+        }
+        None => recovered.push((close.kind, 1, close.lamports)),
+    };
+
+    if dry_run {
+        closes.iter().for_each(&mut record);
+    } else {
+        for batch in closes.chunks(CLOSES_PER_TRANSACTION) {
+            let instructions: Vec<Instruction> = batch
+                .iter()
+                .map(|close| close.instruction.clone())
+                .collect();
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&wallet.pubkey()),
+                &[wallet],
+                rpc.latest_blockhash()?,
+            );
+            match rpc.send_and_confirm(&transaction) {
+                Ok(signature) => {
+                    println!("{signature}: closed {} accounts", batch.len());
+                    batch.iter().for_each(&mut record);
+                }
+                Err(err) => eprintln!("batch failed: {err:#}"),
+            }
+        }
+    }
+
+    let verb = if dry_run {
+        "would recover"
+    } else {
+        "recovered"
+    };
+    let mut total = 0;
+    for (kind, count, lamports) in &recovered {
+        println!("{count} {kind}: {} SOL", lamports_to_sol(*lamports));
+        total += lamports;
+    }
+    println!("{verb} {} SOL", lamports_to_sol(total));
+    Ok(())
+}
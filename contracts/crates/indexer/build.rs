@@ -0,0 +1,22 @@
+//! Generates the gRPC server for `proto/events.proto`. The messages are
+//! written out in `src/grpc.rs`, so building needs no `protoc`.
+
+fn main() {
+    let subscribe = tonic_build::manual::Method::builder()
+        .name("subscribe")
+        .route_name("Subscribe")
+        .input_type("crate::grpc::proto::SubscribeRequest")
+        .output_type("crate::grpc::proto::Event")
+        .codec_path("tonic::codec::ProstCodec")
+        .server_streaming()
+        .build();
+    let service = tonic_build::manual::Service::builder()
+        .name("EventStream")
+        .package("pokerx402.indexer.v1")
+        .method(subscribe)
+        .build();
+    tonic_build::manual::Builder::new()
+        .build_client(false)
+        .compile(&[service]);
+    println!("cargo:rerun-if-changed=build.rs");
+}
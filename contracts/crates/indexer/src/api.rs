@@ -0,0 +1,98 @@
+//! HTTP API over the sampled history.
+//!
+//! `GET /lobbies/{id}/odds-history?since_slot=<slot>&limit=<n>` returns a
+//! lobby's samples, oldest first; `{id}` is the lobby address or game id.
+//! `GET /metrics` serves the Prometheus metrics. `POST /graphql` serves the
+//! GraphQL API when a backfilled dataset is given.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::graphql::Graphql;
+use crate::metrics::Metrics;
+use crate::store;
+
+/// Most samples one response carries.
+const MAX_LIMIT: u64 = 5_000;
+/// Largest GraphQL request body read.
+const MAX_BODY: u64 = 64 * 1024;
+
+fn respond(request: Request, status: u16, body: &Value) -> Result<()> {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    request.respond(
+        Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header),
+    )?;
+    Ok(())
+}
+
+fn query_param(query: &str, name: &str) -> Option<u64> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+fn handle(
+    connection: &Connection,
+    graphql: Option<&Graphql>,
+    metrics: &Metrics,
+    mut request: Request,
+) -> Result<()> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let id = match (request.method(), segments.as_slice()) {
+        (Method::Get, ["lobbies", id, "odds-history"]) => *id,
+        (Method::Get, ["metrics"]) => {
+            let header = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+                .expect("valid header");
+            request.respond(Response::from_string(metrics.encode()?).with_header(header))?;
+            return Ok(());
+        }
+        (Method::Post, ["graphql"]) => {
+            let Some(graphql) = graphql else {
+                return respond(request, 404, &json!({ "error": "no dataset to query" }));
+            };
+            return match graphql.execute(request.as_reader().take(MAX_BODY)) {
+                Ok(response) => respond(request, 200, &response),
+                Err(err) => respond(request, 400, &json!({ "error": format!("{err:#}") })),
+            };
+        }
+        _ => return respond(request, 404, &json!({ "error": "not found" })),
+    };
+    let since_slot = query_param(query, "since_slot").unwrap_or(0);
+    let limit = query_param(query, "limit")
+        .unwrap_or(MAX_LIMIT)
+        .min(MAX_LIMIT);
+    match store::odds_history(connection, id, since_slot, limit)? {
+        Some(history) => respond(request, 200, &history),
+        None => respond(request, 404, &json!({ "error": "unknown lobby" })),
+    }
+}
+
+pub fn serve(
+    listen: &str,
+    database: &str,
+    dataset: Option<&str>,
+    metrics: Arc<Metrics>,
+) -> Result<()> {
+    let server = Server::http(listen).map_err(|err| anyhow::anyhow!("binding {listen}: {err}"))?;
+    let connection = store::open(database)?;
+    let graphql = dataset
+        .map(|dataset| Graphql::open(database, dataset))
+        .transpose()?;
+    for request in server.incoming_requests() {
+        if let Err(err) = handle(&connection, graphql.as_ref(), &metrics, request) {
+            eprintln!("request failed: {err:#}");
+        }
+    }
+    Ok(())
+}
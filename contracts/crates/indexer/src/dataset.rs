@@ -0,0 +1,312 @@
+//! Reads of the dataset `backfill` writes to SQLite. Its rows are the GraphQL
+//! API's objects; their nested fields resolve in `graphql`.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use async_graphql::SimpleObject;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+
+/// How long a read waits out a backfill write before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn open(path: &str) -> Result<Connection> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(connection)
+}
+
+/// Fields other than the address are null until the lobby's creation or
+/// finish is backfilled.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Lobby {
+    pub address: String,
+    pub game_id: Option<String>,
+    pub owner: Option<String>,
+    pub models: Vec<String>,
+    pub starting_chips: Option<i64>,
+    pub created_slot: Option<i64>,
+    pub created_at: Option<i64>,
+    pub finished_slot: Option<i64>,
+    pub finished_at: Option<i64>,
+    /// Model names, winner first
+    pub placings: Vec<String>,
+    pub winner: Option<String>,
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Bet {
+    pub address: String,
+    /// Transaction that placed it
+    pub signature: String,
+    pub lobby_address: String,
+    pub bettor: String,
+    pub model: String,
+    pub side: String,
+    /// Lamports staked
+    pub amount: i64,
+    pub slot: i64,
+    pub placed_at: Option<i64>,
+}
+
+/// A bet's settlement.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Payout {
+    /// Transaction that settled the bet
+    pub signature: String,
+    pub bet_address: String,
+    pub lobby_address: String,
+    pub bettor: String,
+    /// Lamports the bettor received, when the transaction shows it
+    /// unambiguously
+    pub amount: Option<i64>,
+    pub slot: i64,
+    pub settled_at: Option<i64>,
+}
+
+#[derive(SimpleObject)]
+pub struct Stats {
+    pub lobbies: i64,
+    pub finished_lobbies: i64,
+    pub bets: i64,
+    pub bettors: i64,
+    /// Lamports staked
+    pub volume: i64,
+    /// Lamports paid out, over payouts with a known amount
+    pub paid_out: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct LobbyStats {
+    pub bets: i64,
+    pub bettors: i64,
+    pub volume: i64,
+    pub paid_out: i64,
+    pub models: Vec<ModelStats>,
+}
+
+#[derive(SimpleObject)]
+pub struct ModelStats {
+    pub model: String,
+    pub bets: i64,
+    pub volume: i64,
+}
+
+/// Which lobbies to list; unset fields match any.
+#[derive(Default)]
+pub struct LobbyFilter {
+    pub owner: Option<String>,
+    pub game_id: Option<String>,
+    pub finished: Option<bool>,
+}
+
+fn json_list(row: &Row, index: usize) -> rusqlite::Result<Vec<String>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(json) => serde_json::from_str(&json).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(index, Type::Text, err.into())
+        }),
+        None => Ok(Vec::new()),
+    }
+}
+
+const LOBBY_COLUMNS: &str = "address, game_id, owner, model_names, starting_chips, \
+    created_slot, created_at, finished_slot, finished_at, placings, winner";
+
+fn lobby_row(row: &Row) -> rusqlite::Result<Lobby> {
+    Ok(Lobby {
+        address: row.get(0)?,
+        game_id: row.get(1)?,
+        owner: row.get(2)?,
+        models: json_list(row, 3)?,
+        starting_chips: row.get(4)?,
+        created_slot: row.get(5)?,
+        created_at: row.get(6)?,
+        finished_slot: row.get(7)?,
+        finished_at: row.get(8)?,
+        placings: json_list(row, 9)?,
+        winner: row.get(10)?,
+    })
+}
+
+pub fn lobby(connection: &Connection, address: &str) -> Result<Option<Lobby>> {
+    Ok(connection
+        .query_row(
+            &format!("SELECT {LOBBY_COLUMNS} FROM lobbies WHERE address = ?1"),
+            [address],
+            lobby_row,
+        )
+        .optional()?)
+}
+
+/// Lobbies matching `filter`, newest first, those whose creation isn't
+/// backfilled yet last.
+pub fn lobbies(
+    connection: &Connection,
+    filter: &LobbyFilter,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<Lobby>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT {LOBBY_COLUMNS} FROM lobbies
+         WHERE (?1 IS NULL OR owner = ?1) AND (?2 IS NULL OR game_id = ?2)
+         AND (?3 IS NULL OR (finished_slot IS NOT NULL) = ?3)
+         ORDER BY created_slot IS NULL, created_slot DESC, address
+         LIMIT ?4 OFFSET ?5"
+    ))?;
+    let rows = statement.query_map(
+        params![
+            filter.owner,
+            filter.game_id,
+            filter.finished,
+            limit as i64,
+            offset as i64
+        ],
+        lobby_row,
+    )?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+const BET_COLUMNS: &str =
+    "address, signature, lobby, bettor, player_name, amount, side, slot, placed_at";
+
+fn bet_row(row: &Row) -> rusqlite::Result<Bet> {
+    Ok(Bet {
+        address: row.get(0)?,
+        signature: row.get(1)?,
+        lobby_address: row.get(2)?,
+        bettor: row.get(3)?,
+        model: row.get(4)?,
+        amount: row.get(5)?,
+        side: row.get(6)?,
+        slot: row.get(7)?,
+        placed_at: row.get(8)?,
+    })
+}
+
+/// The latest bet placed at `address`.
+pub fn bet(connection: &Connection, address: &str) -> Result<Option<Bet>> {
+    Ok(connection
+        .query_row(
+            &format!(
+                "SELECT {BET_COLUMNS} FROM bets WHERE address = ?1
+                 ORDER BY slot DESC LIMIT 1"
+            ),
+            [address],
+            bet_row,
+        )
+        .optional()?)
+}
+
+/// Bets in `lobby` by `bettor`, either unset to match any, newest first.
+pub fn bets(
+    connection: &Connection,
+    lobby: Option<&str>,
+    bettor: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<Bet>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT {BET_COLUMNS} FROM bets
+         WHERE (?1 IS NULL OR lobby = ?1) AND (?2 IS NULL OR bettor = ?2)
+         ORDER BY slot DESC, signature, instruction_index
+         LIMIT ?3 OFFSET ?4"
+    ))?;
+    let rows = statement.query_map(params![lobby, bettor, limit as i64, offset as i64], bet_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Payouts in `lobby`, to `bettor`, of `bet`, unset ones matching any,
+/// newest first.
+pub fn payouts(
+    connection: &Connection,
+    lobby: Option<&str>,
+    bettor: Option<&str>,
+    bet: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<Payout>> {
+    let mut statement = connection.prepare(
+        "SELECT signature, bet, lobby, bettor, payout, slot, settled_at FROM settlements
+         WHERE (?1 IS NULL OR lobby = ?1) AND (?2 IS NULL OR bettor = ?2)
+         AND (?3 IS NULL OR bet = ?3)
+         ORDER BY slot DESC, signature, instruction_index, bet
+         LIMIT ?4 OFFSET ?5",
+    )?;
+    let rows = statement.query_map(
+        params![lobby, bettor, bet, limit as i64, offset as i64],
+        |row| {
+            Ok(Payout {
+                signature: row.get(0)?,
+                bet_address: row.get(1)?,
+                lobby_address: row.get(2)?,
+                bettor: row.get(3)?,
+                amount: row.get(4)?,
+                slot: row.get(5)?,
+                settled_at: row.get(6)?,
+            })
+        },
+    )?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+pub fn stats(connection: &Connection) -> Result<Stats> {
+    Ok(connection.query_row(
+        "SELECT
+           (SELECT COUNT(*) FROM lobbies),
+           (SELECT COUNT(*) FROM lobbies WHERE finished_slot IS NOT NULL),
+           (SELECT COUNT(*) FROM bets),
+           (SELECT COUNT(DISTINCT bettor) FROM bets),
+           (SELECT COALESCE(SUM(amount), 0) FROM bets),
+           (SELECT COALESCE(SUM(payout), 0) FROM settlements)",
+        [],
+        |row| {
+            Ok(Stats {
+                lobbies: row.get(0)?,
+                finished_lobbies: row.get(1)?,
+                bets: row.get(2)?,
+                bettors: row.get(3)?,
+                volume: row.get(4)?,
+                paid_out: row.get(5)?,
+            })
+        },
+    )?)
+}
+
+pub fn lobby_stats(connection: &Connection, address: &str) -> Result<LobbyStats> {
+    let (bets, bettors, volume, paid_out) = connection.query_row(
+        "SELECT
+           (SELECT COUNT(*) FROM bets WHERE lobby = ?1),
+           (SELECT COUNT(DISTINCT bettor) FROM bets WHERE lobby = ?1),
+           (SELECT COALESCE(SUM(amount), 0) FROM bets WHERE lobby = ?1),
+           (SELECT COALESCE(SUM(payout), 0) FROM settlements WHERE lobby = ?1)",
+        [address],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    let mut statement = connection.prepare(
+        "SELECT player_name, COUNT(*), SUM(amount) FROM bets WHERE lobby = ?1
+         GROUP BY player_name ORDER BY player_name",
+    )?;
+    let models = statement
+        .query_map([address], |row| {
+            Ok(ModelStats {
+                model: row.get(0)?,
+                bets: row.get(1)?,
+                volume: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(LobbyStats {
+        bets,
+        bettors,
+        volume,
+        paid_out,
+        models,
+    })
+}
@@ -0,0 +1,303 @@
+//! GraphQL API over the backfilled dataset and the sampled odds history:
+//! lobbies, bets, payouts and stats, each nesting the others they relate
+//! to. Lists are Relay connections paged forward with `first` and `after`.
+
+use std::io::Read;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_graphql::connection::{Connection, CursorType, Edge};
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, Object, OutputType, Schema,
+    SimpleObject,
+};
+use rusqlite::Connection as Database;
+use serde_json::Value;
+
+use crate::dataset::{self, Bet, Lobby, LobbyFilter, LobbyStats, Payout, Stats};
+use crate::store;
+
+/// Items a page holds when `first` isn't given, and at most.
+const DEFAULT_PAGE: usize = 20;
+const MAX_PAGE: usize = 100;
+/// Deepest nesting a query may use, so one request can't fan out without
+/// bound.
+const MAX_DEPTH: usize = 8;
+
+struct Databases {
+    samples: Mutex<Database>,
+    dataset: Mutex<Database>,
+}
+
+fn dataset<T>(
+    ctx: &Context<'_>,
+    read: impl FnOnce(&Database) -> Result<T>,
+) -> async_graphql::Result<T> {
+    let databases = ctx.data_unchecked::<Databases>();
+    Ok(read(
+        &databases.dataset.lock().expect("dataset lock poisoned"),
+    )?)
+}
+
+fn samples<T>(
+    ctx: &Context<'_>,
+    read: impl FnOnce(&Database) -> Result<T>,
+) -> async_graphql::Result<T> {
+    let databases = ctx.data_unchecked::<Databases>();
+    Ok(read(
+        &databases.samples.lock().expect("samples lock poisoned"),
+    )?)
+}
+
+/// The page of `fetch(offset, limit)` after cursor `after`. Cursors are
+/// offsets, which stay put as the data grows: the dataset only gains older
+/// rows, listed last, and the samples only newer ones, also listed last.
+fn page<T: OutputType>(
+    after: Option<String>,
+    first: Option<i32>,
+    fetch: impl FnOnce(usize, usize) -> async_graphql::Result<Vec<T>>,
+) -> async_graphql::Result<Connection<usize, T>> {
+    let offset = match after {
+        Some(cursor) => usize::decode_cursor(&cursor)? + 1,
+        None => 0,
+    };
+    let limit = match first {
+        Some(first) => usize::try_from(first)
+            .map_err(|_| "first must not be negative")?
+            .min(MAX_PAGE),
+        None => DEFAULT_PAGE,
+    };
+    let mut items = fetch(offset, limit + 1)?;
+    let has_next_page = items.len() > limit;
+    items.truncate(limit);
+    let mut connection = Connection::new(offset > 0, has_next_page);
+    connection.edges.extend(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| Edge::new(offset + i, item)),
+    );
+    Ok(connection)
+}
+
+#[derive(SimpleObject)]
+pub struct OddsSample {
+    pub slot: u64,
+    pub sampled_at: i64,
+    /// Lamports staked in the lobby
+    pub total_bets: u64,
+    pub models: Vec<ModelOdds>,
+}
+
+#[derive(SimpleObject)]
+pub struct ModelOdds {
+    pub model: String,
+    /// Lamports staked on the model
+    pub pool: u64,
+    /// Decimal odds of backing it in basis points; null while it has no stake
+    pub odds_bps: Option<u64>,
+}
+
+#[ComplexObject]
+impl Lobby {
+    /// Newest first.
+    async fn bets(
+        &self,
+        ctx: &Context<'_>,
+        bettor: Option<String>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<usize, Bet>> {
+        page(after, first, |offset, limit| {
+            dataset(ctx, |db| {
+                dataset::bets(db, Some(&self.address), bettor.as_deref(), offset, limit)
+            })
+        })
+    }
+
+    /// Newest first.
+    async fn payouts(
+        &self,
+        ctx: &Context<'_>,
+        bettor: Option<String>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<usize, Payout>> {
+        page(after, first, |offset, limit| {
+            dataset(ctx, |db| {
+                dataset::payouts(
+                    db,
+                    Some(&self.address),
+                    bettor.as_deref(),
+                    None,
+                    offset,
+                    limit,
+                )
+            })
+        })
+    }
+
+    /// Pools and odds as sampled while the lobby was open, oldest first.
+    async fn odds_history(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] since_slot: u64,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<usize, OddsSample>> {
+        page(after, first, |offset, limit| {
+            samples(ctx, |db| {
+                let models = store::model_names(db, &self.address)?.unwrap_or_default();
+                let samples =
+                    store::samples(db, &self.address, since_slot, offset as u64, limit as u64)?;
+                Ok(samples
+                    .into_iter()
+                    .map(|sample| OddsSample {
+                        slot: sample.slot,
+                        sampled_at: sample.sampled_at,
+                        total_bets: sample.total_bets,
+                        models: models
+                            .iter()
+                            .zip(sample.model_pools)
+                            .zip(sample.implied_odds_bps)
+                            .map(|((model, pool), odds_bps)| ModelOdds {
+                                model: model.clone(),
+                                pool,
+                                odds_bps,
+                            })
+                            .collect(),
+                    })
+                    .collect())
+            })
+        })
+    }
+
+    async fn stats(&self, ctx: &Context<'_>) -> async_graphql::Result<LobbyStats> {
+        dataset(ctx, |db| dataset::lobby_stats(db, &self.address))
+    }
+}
+
+#[ComplexObject]
+impl Bet {
+    async fn lobby(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Lobby>> {
+        dataset(ctx, |db| dataset::lobby(db, &self.lobby_address))
+    }
+
+    /// Null until the bet is settled.
+    async fn payout(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Payout>> {
+        dataset(ctx, |db| {
+            Ok(dataset::payouts(db, None, None, Some(&self.address), 0, 1)?.pop())
+        })
+    }
+}
+
+#[ComplexObject]
+impl Payout {
+    async fn bet(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Bet>> {
+        dataset(ctx, |db| dataset::bet(db, &self.bet_address))
+    }
+
+    async fn lobby(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Lobby>> {
+        dataset(ctx, |db| dataset::lobby(db, &self.lobby_address))
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    async fn lobby(
+        &self,
+        ctx: &Context<'_>,
+        address: String,
+    ) -> async_graphql::Result<Option<Lobby>> {
+        dataset(ctx, |db| dataset::lobby(db, &address))
+    }
+
+    /// Newest first; lobbies whose creation isn't backfilled yet come last.
+    async fn lobbies(
+        &self,
+        ctx: &Context<'_>,
+        owner: Option<String>,
+        game_id: Option<String>,
+        finished: Option<bool>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<usize, Lobby>> {
+        let filter = LobbyFilter {
+            owner,
+            game_id,
+            finished,
+        };
+        page(after, first, |offset, limit| {
+            dataset(ctx, |db| dataset::lobbies(db, &filter, offset, limit))
+        })
+    }
+
+    /// Newest first.
+    async fn bets(
+        &self,
+        ctx: &Context<'_>,
+        lobby: Option<String>,
+        bettor: Option<String>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<usize, Bet>> {
+        page(after, first, |offset, limit| {
+            dataset(ctx, |db| {
+                dataset::bets(db, lobby.as_deref(), bettor.as_deref(), offset, limit)
+            })
+        })
+    }
+
+    /// Newest first.
+    async fn payouts(
+        &self,
+        ctx: &Context<'_>,
+        lobby: Option<String>,
+        bettor: Option<String>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<usize, Payout>> {
+        page(after, first, |offset, limit| {
+            dataset(ctx, |db| {
+                dataset::payouts(db, lobby.as_deref(), bettor.as_deref(), None, offset, limit)
+            })
+        })
+    }
+
+    async fn stats(&self, ctx: &Context<'_>) -> async_graphql::Result<Stats> {
+        dataset(ctx, dataset::stats)
+    }
+}
+
+/// The schema and the runtime its resolvers run on.
+pub struct Graphql {
+    schema: Schema<Query, EmptyMutation, EmptySubscription>,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Graphql {
+    /// Serves the samples in `database` and the backfilled dataset at
+    /// `dataset`.
+    pub fn open(database: &str, dataset: &str) -> Result<Self> {
+        let databases = Databases {
+            samples: Mutex::new(store::open(database)?),
+            dataset: Mutex::new(dataset::open(dataset)?),
+        };
+        Ok(Self {
+            schema: Schema::build(Query, EmptyMutation, EmptySubscription)
+                .data(databases)
+                .limit_depth(MAX_DEPTH)
+                .finish(),
+            runtime: tokio::runtime::Builder::new_current_thread().build()?,
+        })
+    }
+
+    /// Runs the request in `body`; errors only if it isn't a GraphQL request.
+    pub fn execute(&self, body: impl Read) -> Result<Value> {
+        let request: async_graphql::Request = serde_json::from_reader(body)?;
+        let response = self.runtime.block_on(self.schema.execute(request));
+        Ok(serde_json::to_value(response)?)
+    }
+}
@@ -0,0 +1,180 @@
+//! gRPC service streaming live bet, odds and winner events to bots, as
+//! published by the tailer. The contract is `proto/events.proto`.
+
+use std::pin::Pin;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::tail::Published;
+
+use self::proto::event_stream_server::{EventStream, EventStreamServer};
+use self::proto::{Event, SubscribeRequest};
+
+/// Messages of `proto/events.proto`, kept in step with it by hand.
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SubscribeRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub lobbies: Vec<String>,
+        #[prost(string, repeated, tag = "2")]
+        pub owners: Vec<String>,
+        #[prost(string, repeated, tag = "3")]
+        pub models: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Event {
+        #[prost(string, tag = "1")]
+        pub lobby: String,
+        #[prost(string, tag = "2")]
+        pub owner: String,
+        #[prost(string, tag = "3")]
+        pub signature: String,
+        #[prost(uint64, tag = "4")]
+        pub slot: u64,
+        #[prost(oneof = "event::Kind", tags = "5, 6, 7")]
+        pub kind: Option<event::Kind>,
+    }
+
+    pub mod event {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Kind {
+            #[prost(message, tag = "5")]
+            BetPlaced(super::BetPlaced),
+            #[prost(message, tag = "6")]
+            OddsChanged(super::OddsChanged),
+            #[prost(message, tag = "7")]
+            WinnerDeclared(super::WinnerDeclared),
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Side {
+        Back = 0,
+        Lay = 1,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BetPlaced {
+        #[prost(string, tag = "1")]
+        pub bet: String,
+        #[prost(string, tag = "2")]
+        pub bettor: String,
+        #[prost(string, tag = "3")]
+        pub model: String,
+        #[prost(enumeration = "Side", tag = "4")]
+        pub side: i32,
+        #[prost(uint64, tag = "5")]
+        pub amount: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OddsChanged {
+        #[prost(message, repeated, tag = "1")]
+        pub models: Vec<ModelOdds>,
+        #[prost(uint64, tag = "2")]
+        pub total_bets: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ModelOdds {
+        #[prost(string, tag = "1")]
+        pub model: String,
+        #[prost(uint64, tag = "2")]
+        pub pool: u64,
+        #[prost(uint64, optional, tag = "3")]
+        pub odds_bps: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WinnerDeclared {
+        #[prost(string, tag = "1")]
+        pub winner: String,
+    }
+
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/pokerx402.indexer.v1.EventStream.rs"
+    ));
+}
+
+/// A subscription's filters; an empty one matches everything.
+struct Filter {
+    lobbies: Vec<Pubkey>,
+    owners: Vec<Pubkey>,
+    models: Vec<String>,
+}
+
+/// The addresses in `keys`, or the first that isn't one.
+fn parse_keys(keys: &[String]) -> Result<Vec<Pubkey>, String> {
+    keys.iter()
+        .map(|key| key.parse().map_err(|_| format!("{key} is not an address")))
+        .collect()
+}
+
+impl Filter {
+    fn new(request: &SubscribeRequest) -> Result<Self, String> {
+        Ok(Self {
+            lobbies: parse_keys(&request.lobbies)?,
+            owners: parse_keys(&request.owners)?,
+            models: request.models.clone(),
+        })
+    }
+
+    fn matches(&self, published: &Published) -> bool {
+        (self.lobbies.is_empty() || self.lobbies.contains(&published.lobby))
+            && (self.owners.is_empty() || self.owners.contains(&published.owner))
+            && (self.models.is_empty()
+                || published
+                    .models
+                    .iter()
+                    .any(|model| self.models.contains(model)))
+    }
+}
+
+struct Service {
+    sender: broadcast::Sender<Published>,
+}
+
+#[tonic::async_trait]
+impl EventStream for Service {
+    type SubscribeStream = Pin<Box<dyn Stream<Item = Result<Event, Status>> + Send>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let filter = Filter::new(request.get_ref()).map_err(Status::invalid_argument)?;
+        let events = BroadcastStream::new(self.sender.subscribe()).filter_map(move |item| {
+            match item {
+                Ok(published) => filter.matches(&published).then_some(Ok(published.event)),
+                // Ends the stream; the client resubscribes and resyncs
+                Err(BroadcastStreamRecvError::Lagged(missed)) => Some(Err(Status::data_loss(
+                    format!("fell behind and missed {missed} events"),
+                ))),
+            }
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// Serves the event stream on `listen` until the server fails.
+pub fn serve(listen: &str, sender: broadcast::Sender<Published>) -> Result<()> {
+    let address = listen.parse()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(
+        tonic::transport::Server::builder()
+            .add_service(EventStreamServer::new(Service { sender }))
+            .serve(address),
+    )?;
+    Ok(())
+}
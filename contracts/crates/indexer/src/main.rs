@@ -0,0 +1,135 @@
+//! Indexer service: samples every open lobby's pools and implied odds once
+//! per batch of slots into SQLite, and serves the history for odds charts
+//! along with Prometheus metrics. Given `--dataset`, the SQLite dataset
+//! `backfill` wrote, it serves a GraphQL API over that and the odds history
+//! too. With `--grpc-listen` it also follows the
+//! program's transactions and streams bet, odds and winner events over gRPC
+//! (`proto/events.proto`). `reconcile` instead checks each open lobby's
+//! escrow against its outstanding bets once, exiting non-zero on any
+//! mismatch so it can alert from cron.
+//!
+//! ```text
+//! indexer <rpc-url> <database> [--listen <addr>] [--slot-batch <slots>]
+//!         [--grpc-listen <addr>] [--dataset <path>]
+//! indexer reconcile <rpc-url>
+//! ```
+
+mod api;
+mod dataset;
+mod graphql;
+mod grpc;
+mod metrics;
+mod reconcile;
+mod rpc;
+mod sampler;
+mod store;
+mod tail;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use tokio::sync::broadcast;
+
+use crate::metrics::Metrics;
+use crate::rpc::Rpc;
+use crate::sampler::Sampler;
+use crate::tail::Tailer;
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+/// About a minute of slots.
+const DEFAULT_SLOT_BATCH: u64 = 150;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Events buffered per gRPC subscriber before it is dropped as too slow.
+const STREAM_BUFFER: usize = 4_096;
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("reconcile") {
+        let rpc_url = args.nth(1).context("usage: indexer reconcile <rpc-url>")?;
+        return run_reconcile(&rpc_url);
+    }
+    let (Some(rpc_url), Some(database)) = (args.next(), args.next()) else {
+        bail!(
+            "usage: indexer <rpc-url> <database> [--listen <addr>] [--slot-batch <slots>] \
+             [--grpc-listen <addr>] [--dataset <path>]"
+        );
+    };
+    let mut listen = DEFAULT_LISTEN.to_string();
+    let mut slot_batch = DEFAULT_SLOT_BATCH;
+    let mut grpc_listen = None;
+    let mut dataset = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--listen" => listen = value()?,
+            "--slot-batch" => slot_batch = value()?.parse()?,
+            "--grpc-listen" => grpc_listen = Some(value()?),
+            "--dataset" => dataset = Some(value()?),
+            _ => bail!("unknown argument {arg}"),
+        }
+    }
+
+    let metrics = Arc::new(Metrics::new()?);
+    let connection = store::open(&database)?;
+    let api = {
+        let database = database.clone();
+        let metrics = metrics.clone();
+        thread::spawn(move || api::serve(&listen, &database, dataset.as_deref(), metrics))
+    };
+    let grpc = grpc_listen.map(|grpc_listen| {
+        let (sender, _) = broadcast::channel(STREAM_BUFFER);
+        let mut tailer = Tailer::new(Rpc::new(&rpc_url, metrics.clone()), sender.clone());
+        thread::spawn(move || {
+            let mut failures = 0;
+            loop {
+                match tailer.poll() {
+                    Ok(()) => failures = 0,
+                    Err(err) => {
+                        eprintln!("tailing failed, retrying: {err:#}");
+                        failures += 1;
+                    }
+                }
+                thread::sleep(tail::retry_delay(failures));
+            }
+        });
+        thread::spawn(move || grpc::serve(&grpc_listen, sender))
+    });
+
+    let mut sampler = Sampler::new(Rpc::new(&rpc_url, metrics.clone()), connection, metrics);
+    let mut last_sampled = 0;
+    while !api.is_finished() && !grpc.as_ref().is_some_and(|grpc| grpc.is_finished()) {
+        match sampler.slot() {
+            Ok(slot) if slot >= last_sampled + slot_batch => match sampler.sample() {
+                Ok(slot) => last_sampled = slot,
+                Err(err) => eprintln!("sampling failed: {err:#}"),
+            },
+            Ok(_) => {}
+            Err(err) => eprintln!("getSlot failed: {err:#}"),
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    if let Some(grpc) = grpc.filter(|grpc| grpc.is_finished()) {
+        return grpc.join().expect("grpc thread panicked");
+    }
+    api.join().expect("api thread panicked")
+}
+
+fn run_reconcile(rpc_url: &str) -> Result<()> {
+    let rpc = Rpc::new(rpc_url, Arc::new(Metrics::new()?));
+    let reconciliations = reconcile::reconcile(&rpc)?;
+    let mismatches = reconciliations
+        .iter()
+        .filter(|reconciliation| !reconciliation.is_consistent())
+        .inspect(|reconciliation| eprintln!("mismatch: {reconciliation}"))
+        .count();
+    println!(
+        "reconciled {} open lobbies, {mismatches} mismatched",
+        reconciliations.len()
+    );
+    if mismatches > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
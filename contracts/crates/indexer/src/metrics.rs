@@ -0,0 +1,80 @@
+//! Prometheus metrics, served at `GET /metrics`.
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+pub struct Metrics {
+    registry: Registry,
+    pub rpc_errors: IntCounterVec,
+    /// Rate gives stake per second; the program keeps no per-lobby bet count
+    pub bet_volume: IntCounter,
+    pub settlement_latency: Histogram,
+    pub escrow_lamports: IntGaugeVec,
+    pub escrow_liabilities: IntGaugeVec,
+    pub escrow_shortfall: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+        let rpc_errors = IntCounterVec::new(
+            Opts::new("poker_rpc_errors_total", "Failed RPC calls"),
+            &["method"],
+        )?;
+        let bet_volume = IntCounter::new(
+            "poker_bet_volume_lamports_total",
+            "Stake added to lobbies since the indexer started",
+        )?;
+        let settlement_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "poker_settlement_latency_seconds",
+                "Time from lobby creation to finish",
+            )
+            .buckets(vec![
+                60.0, 300.0, 900.0, 1_800.0, 3_600.0, 7_200.0, 21_600.0, 86_400.0,
+            ]),
+        )?;
+        let escrow_lamports = IntGaugeVec::new(
+            Opts::new(
+                "poker_escrow_lamports",
+                "Spendable lamports in an open lobby's escrow",
+            ),
+            &["lobby"],
+        )?;
+        let escrow_liabilities = IntGaugeVec::new(
+            Opts::new(
+                "poker_escrow_liabilities_lamports",
+                "Lamports an open lobby's escrow owes, as tracked by the program",
+            ),
+            &["lobby"],
+        )?;
+        let escrow_shortfall = IntGauge::new(
+            "poker_escrow_shortfall_lamports",
+            "Liabilities not covered by escrow, summed over open lobbies",
+        )?;
+        registry.register(Box::new(rpc_errors.clone()))?;
+        registry.register(Box::new(bet_volume.clone()))?;
+        registry.register(Box::new(settlement_latency.clone()))?;
+        registry.register(Box::new(escrow_lamports.clone()))?;
+        registry.register(Box::new(escrow_liabilities.clone()))?;
+        registry.register(Box::new(escrow_shortfall.clone()))?;
+        Ok(Self {
+            registry,
+            rpc_errors,
+            bet_volume,
+            settlement_latency,
+            escrow_lamports,
+            escrow_liabilities,
+            escrow_shortfall,
+        })
+    }
+
+    /// Every metric in the Prometheus text format.
+    pub fn encode(&self) -> prometheus::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer).expect("text format is UTF-8"))
+    }
+}
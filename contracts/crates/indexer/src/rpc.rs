@@ -0,0 +1,208 @@
+//! The Solana JSON-RPC calls the sampler makes.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use poker_betting::Lobby;
+use serde_json::{json, Value};
+
+use crate::metrics::Metrics;
+
+/// Most accounts `getMultipleAccounts` takes per call.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+pub struct Rpc {
+    url: String,
+    agent: ureq::Agent,
+    metrics: Arc<Metrics>,
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub failed: bool,
+}
+
+impl Rpc {
+    pub fn new(url: &str, metrics: Arc<Metrics>) -> Self {
+        Self {
+            url: url.to_string(),
+            metrics,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let result = self.try_call(method, params);
+        if result.is_err() {
+            self.metrics.rpc_errors.with_label_values(&[method]).inc();
+        }
+        result
+    }
+
+    fn try_call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(&request)
+            .with_context(|| method.to_string())?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            bail!("{method}: {error}");
+        }
+        Ok(response["result"].take())
+    }
+
+    pub fn slot(&self) -> Result<u64> {
+        self.call("getSlot", json!([{ "commitment": "confirmed" }]))?
+            .as_u64()
+            .context("getSlot result")
+    }
+
+    /// Lamports held by each of `addresses`, zero for missing accounts.
+    pub fn balances(&self, addresses: &[Pubkey]) -> Result<Vec<u64>> {
+        let mut balances = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let keys: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let config =
+                json!({ "commitment": "confirmed", "dataSlice": { "offset": 0, "length": 0 } });
+            let result = self.call("getMultipleAccounts", json!([keys, config]))?;
+            let accounts = result["value"]
+                .as_array()
+                .context("getMultipleAccounts result")?;
+            balances.extend(
+                accounts
+                    .iter()
+                    .map(|account| account["lamports"].as_u64().unwrap_or(0)),
+            );
+        }
+        Ok(balances)
+    }
+
+    /// Every lobby account with its address, as of the returned slot.
+    pub fn lobbies(&self) -> Result<(u64, Vec<(String, Lobby)>)> {
+        self.program_accounts()
+    }
+
+    /// The lobbies at `addresses`, `None` where there is no account.
+    pub fn lobbies_at(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Lobby>>> {
+        let mut lobbies = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let keys: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let config = json!({ "commitment": "confirmed", "encoding": "base64" });
+            let result = self.call("getMultipleAccounts", json!([keys, config]))?;
+            let accounts = result["value"]
+                .as_array()
+                .context("getMultipleAccounts result")?;
+            for (address, account) in chunk.iter().zip(accounts) {
+                if account.is_null() {
+                    lobbies.push(None);
+                    continue;
+                }
+                let data = STANDARD.decode(account["data"][0].as_str().context("account data")?)?;
+                let lobby = Lobby::try_deserialize(&mut &data[..])
+                    .with_context(|| format!("decoding lobby {address}"))?;
+                lobbies.push(Some(lobby));
+            }
+        }
+        Ok(lobbies)
+    }
+
+    /// Signatures of program transactions newer than `until` and older than
+    /// `before`, newest first, at most `limit` of them.
+    pub fn program_signatures(
+        &self,
+        until: Option<&str>,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "limit": limit, "commitment": "confirmed" });
+        if let Some(until) = until {
+            config["until"] = json!(until);
+        }
+        if let Some(before) = before {
+            config["before"] = json!(before);
+        }
+        let result = self.call(
+            "getSignaturesForAddress",
+            json!([poker_betting::ID.to_string(), config]),
+        )?;
+        let entries = result
+            .as_array()
+            .context("getSignaturesForAddress result")?;
+        entries
+            .iter()
+            .map(|entry| {
+                Ok(SignatureInfo {
+                    signature: entry["signature"]
+                        .as_str()
+                        .context("signature")?
+                        .to_string(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect()
+    }
+
+    /// The transaction in JSON encoding, or `None` if the node doesn't have
+    /// it yet.
+    pub fn transaction(&self, signature: &str) -> Result<Option<Value>> {
+        let config = json!({
+            "encoding": "json",
+            "commitment": "confirmed",
+            "maxSupportedTransactionVersion": 0,
+        });
+        let result = self.call("getTransaction", json!([signature, config]))?;
+        Ok((!result.is_null()).then_some(result))
+    }
+
+    /// Every program account of type `T` with its address, as of the returned
+    /// slot.
+    pub fn program_accounts<T: AccountDeserialize + Discriminator>(
+        &self,
+    ) -> Result<(u64, Vec<(String, T)>)> {
+        let config = json!({
+            "commitment": "confirmed",
+            "encoding": "base64",
+            "withContext": true,
+            "filters": [{
+                "memcmp": {
+                    "offset": 0,
+                    "bytes": STANDARD.encode(T::DISCRIMINATOR),
+                    "encoding": "base64",
+                }
+            }],
+        });
+        let result = self.call(
+            "getProgramAccounts",
+            json!([poker_betting::ID.to_string(), config]),
+        )?;
+        let slot = result["context"]["slot"]
+            .as_u64()
+            .context("getProgramAccounts context")?;
+        let accounts = result["value"]
+            .as_array()
+            .context("getProgramAccounts result")?;
+        let decoded = accounts
+            .iter()
+            .map(|entry| {
+                let address = entry["pubkey"].as_str().context("pubkey")?.to_string();
+                let data = entry["account"]["data"][0]
+                    .as_str()
+                    .context("account data")?;
+                let data = STANDARD.decode(data)?;
+                let account = T::try_deserialize(&mut &data[..])
+                    .with_context(|| format!("decoding account {address}"))?;
+                Ok((address, account))
+            })
+            .collect::<Result<_>>()?;
+        Ok((slot, decoded))
+    }
+}
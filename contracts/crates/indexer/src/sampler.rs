@@ -0,0 +1,95 @@
+//! Periodic reads of every lobby, recorded as samples and metrics.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anyhow::Result;
+use poker_betting::{pda, LobbyStatus};
+use rusqlite::Connection;
+
+use crate::metrics::Metrics;
+use crate::rpc::Rpc;
+use crate::store;
+
+pub struct Sampler {
+    rpc: Rpc,
+    connection: Connection,
+    metrics: Arc<Metrics>,
+    /// Status and stake of each lobby at the previous sample
+    previous: HashMap<String, (LobbyStatus, u64)>,
+}
+
+impl Sampler {
+    pub fn new(rpc: Rpc, connection: Connection, metrics: Arc<Metrics>) -> Self {
+        Self {
+            rpc,
+            connection,
+            metrics,
+            previous: HashMap::new(),
+        }
+    }
+
+    pub fn slot(&self) -> Result<u64> {
+        self.rpc.slot()
+    }
+
+    /// Samples every lobby and returns the slot it was read at.
+    pub fn sample(&mut self) -> Result<u64> {
+        let (slot, lobbies) = self.rpc.lobbies()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+        let mut open = Vec::new();
+        for (address, lobby) in &lobbies {
+            let finished = lobby.status.is_over();
+            if let Some((status, total_bets)) = self.previous.get(address) {
+                self.metrics
+                    .bet_volume
+                    .inc_by(lobby.total_bets.saturating_sub(*total_bets));
+                if finished && !status.is_over() {
+                    self.metrics
+                        .settlement_latency
+                        .observe((now - lobby.created_at) as f64);
+                    let _ = self.metrics.escrow_lamports.remove_label_values(&[address]);
+                    let _ = self
+                        .metrics
+                        .escrow_liabilities
+                        .remove_label_values(&[address]);
+                }
+            }
+            self.previous
+                .insert(address.clone(), (lobby.status.clone(), lobby.total_bets));
+            if !finished {
+                store::insert_sample(&self.connection, address, lobby, slot, now)?;
+                open.push((address, lobby));
+            }
+        }
+
+        let escrows = open
+            .iter()
+            .map(|(address, _)| {
+                let lobby = Pubkey::from_str(address)?;
+                Ok(pda::escrow_pda(&lobby))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let balances = self.rpc.balances(&escrows)?;
+        let rent_exempt_minimum = Rent::default().minimum_balance(0);
+        let mut shortfall = 0;
+        for ((address, lobby), balance) in open.iter().zip(balances) {
+            let spendable = balance.saturating_sub(rent_exempt_minimum);
+            shortfall += lobby.escrow_balance.saturating_sub(spendable);
+            self.metrics
+                .escrow_lamports
+                .with_label_values(&[address])
+                .set(spendable as i64);
+            self.metrics
+                .escrow_liabilities
+                .with_label_values(&[address])
+                .set(lobby.escrow_balance as i64);
+        }
+        self.metrics.escrow_shortfall.set(shortfall as i64);
+        Ok(slot)
+    }
+}
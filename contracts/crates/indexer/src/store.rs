@@ -0,0 +1,183 @@
+//! SQLite time series of lobby pools and odds.
+
+use anyhow::Result;
+use poker_betting::{BetSide, Lobby, MarketMode};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+
+const SCHEMA: &str = "
+PRAGMA journal_mode = WAL;
+CREATE TABLE IF NOT EXISTS lobbies (
+    address TEXT PRIMARY KEY,
+    game_id TEXT NOT NULL,
+    model_names TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS lobbies_game_id ON lobbies (game_id);
+CREATE TABLE IF NOT EXISTS odds_samples (
+    lobby TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    sampled_at INTEGER NOT NULL,
+    total_bets INTEGER NOT NULL,
+    model_pools TEXT NOT NULL,
+    implied_odds_bps TEXT NOT NULL,
+    PRIMARY KEY (lobby, slot)
+);
+";
+
+pub fn open(path: &str) -> Result<Connection> {
+    let connection = Connection::open(path)?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+/// Decimal odds in basis points of backing each model now: the market
+/// maker's quotes in fixed-odds lobbies, else parimutuel odds, `null` while a
+/// model has no stake.
+pub fn implied_odds(lobby: &Lobby) -> Vec<Option<u64>> {
+    (0..lobby.model_names.len())
+        .map(|i| match lobby.market_mode {
+            MarketMode::FixedOdds => lobby.quoted_odds_bps.get(i).map(|odds| *odds as u64),
+            _ => lobby.implied_odds_after(i, BetSide::Back, 0).ok(),
+        })
+        .collect()
+}
+
+pub fn insert_sample(
+    connection: &Connection,
+    address: &str,
+    lobby: &Lobby,
+    slot: u64,
+    sampled_at: i64,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO lobbies (address, game_id, model_names) VALUES (?1, ?2, ?3)
+         ON CONFLICT DO NOTHING",
+        params![
+            address,
+            lobby.game_id,
+            serde_json::to_string(&lobby.model_names)?
+        ],
+    )?;
+    connection.execute(
+        "INSERT INTO odds_samples (lobby, slot, sampled_at, total_bets, model_pools,
+         implied_odds_bps) VALUES (?1, ?2, ?3, ?4, ?5, ?6) ON CONFLICT DO NOTHING",
+        params![
+            address,
+            slot as i64,
+            sampled_at,
+            lobby.total_bets as i64,
+            serde_json::to_string(&lobby.model_pools)?,
+            serde_json::to_string(&implied_odds(lobby))?,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Samples of the lobby with address or game id `id` from `since_slot` on,
+/// oldest first; `None` if no such lobby was ever sampled.
+pub fn odds_history(
+    connection: &Connection,
+    id: &str,
+    since_slot: u64,
+    limit: u64,
+) -> Result<Option<Value>> {
+    let lobby = connection
+        .query_row(
+            "SELECT address, game_id, model_names FROM lobbies
+             WHERE address = ?1 OR game_id = ?1",
+            [id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((address, game_id, model_names)) = lobby else {
+        return Ok(None);
+    };
+
+    let samples = samples(connection, &address, since_slot, 0, limit)?
+        .into_iter()
+        .map(|sample| {
+            json!({
+                "slot": sample.slot,
+                "sampled_at": sample.sampled_at,
+                "total_bets": sample.total_bets,
+                "model_pools": sample.model_pools,
+                "implied_odds_bps": sample.implied_odds_bps,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Some(json!({
+        "lobby": address,
+        "game_id": game_id,
+        "models": serde_json::from_str::<Value>(&model_names)?,
+        "samples": samples,
+    })))
+}
+
+/// One sample of a lobby's pools and odds.
+pub struct Sample {
+    pub slot: u64,
+    pub sampled_at: i64,
+    pub total_bets: u64,
+    pub model_pools: Vec<u64>,
+    pub implied_odds_bps: Vec<Option<u64>>,
+}
+
+/// Samples of the lobby at `address` from `since_slot` on, oldest first,
+/// skipping the first `offset`.
+pub fn samples(
+    connection: &Connection,
+    address: &str,
+    since_slot: u64,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<Sample>> {
+    let mut statement = connection.prepare(
+        "SELECT slot, sampled_at, total_bets, model_pools, implied_odds_bps
+         FROM odds_samples WHERE lobby = ?1 AND slot >= ?2 ORDER BY slot
+         LIMIT ?3 OFFSET ?4",
+    )?;
+    let rows = statement.query_map(
+        params![address, since_slot as i64, limit as i64, offset as i64],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        },
+    )?;
+    rows.map(|row| {
+        let (slot, sampled_at, total_bets, model_pools, implied_odds_bps) = row?;
+        Ok(Sample {
+            slot: slot as u64,
+            sampled_at,
+            total_bets: total_bets as u64,
+            model_pools: serde_json::from_str(&model_pools)?,
+            implied_odds_bps: serde_json::from_str(&implied_odds_bps)?,
+        })
+    })
+    .collect()
+}
+
+/// Model names of the sampled lobby at `address`.
+pub fn model_names(connection: &Connection, address: &str) -> Result<Option<Vec<String>>> {
+    let names: Option<String> = connection
+        .query_row(
+            "SELECT model_names FROM lobbies WHERE address = ?1",
+            [address],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(names
+        .map(|names| serde_json::from_str(&names))
+        .transpose()?)
+}
@@ -0,0 +1,240 @@
+//! Follows the betting program's transactions as they confirm and turns
+//! their events into the typed events the gRPC stream serves.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Context, Result};
+use poker_betting::{BetSide, Lobby, LobbyAction};
+use poker_betting_events::{parse_transaction, ProgramEvent};
+use tokio::sync::broadcast;
+
+use crate::grpc::proto::{self, event::Kind};
+use crate::rpc::{Rpc, SignatureInfo};
+use crate::store;
+
+/// Signatures requested per page; a poll pages back until it reaches the
+/// last transaction it published.
+const PAGE_SIZE: usize = 1_000;
+pub const POLL_INTERVAL: Duration = Duration::from_millis(400);
+/// Longest wait between polls after consecutive failures.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Wait before the next poll after `failures` failed polls in a row, doubling
+/// from `POLL_INTERVAL` up to `MAX_RETRY_DELAY`.
+pub fn retry_delay(failures: u32) -> Duration {
+    POLL_INTERVAL
+        .saturating_mul(1 << failures.min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// An event with what subscribers filter it on.
+#[derive(Clone)]
+pub struct Published {
+    pub event: proto::Event,
+    pub lobby: Pubkey,
+    pub owner: Pubkey,
+    /// Models the event concerns
+    pub models: Vec<String>,
+}
+
+pub struct Tailer {
+    rpc: Rpc,
+    sender: broadcast::Sender<Published>,
+    /// Newest transaction published; `None` until the first poll
+    cursor: Option<String>,
+    /// Odds last published per lobby, so unchanged odds aren't sent again
+    odds: HashMap<Pubkey, Vec<Option<u64>>>,
+}
+
+impl Tailer {
+    pub fn new(rpc: Rpc, sender: broadcast::Sender<Published>) -> Self {
+        Self {
+            rpc,
+            sender,
+            cursor: None,
+            odds: HashMap::new(),
+        }
+    }
+
+    /// Publishes the events of each program transaction since the last poll,
+    /// oldest first. The first poll only marks where to start. The cursor
+    /// only moves past a transaction once it's published, so a poll that
+    /// fails part way resumes from there.
+    pub fn poll(&mut self) -> Result<()> {
+        let Some(cursor) = self.cursor.clone() else {
+            let page = self.rpc.program_signatures(None, None, 1)?;
+            self.cursor = page.into_iter().next().map(|info| info.signature);
+            return Ok(());
+        };
+
+        // Newest first, paging back until the cursor
+        let mut signatures: Vec<SignatureInfo> = Vec::new();
+        loop {
+            let before = signatures.last().map(|info| info.signature.as_str());
+            let page = self
+                .rpc
+                .program_signatures(Some(&cursor), before, PAGE_SIZE)?;
+            let exhausted = page.len() < PAGE_SIZE;
+            signatures.extend(page);
+            if exhausted {
+                break;
+            }
+        }
+
+        for info in signatures.iter().rev() {
+            if !info.failed {
+                self.publish(&info.signature)?;
+            }
+            self.cursor = Some(info.signature.clone());
+        }
+        Ok(())
+    }
+
+    fn send(
+        &self,
+        lobby: &Pubkey,
+        owner: &Pubkey,
+        header: &proto::Event,
+        kind: Kind,
+        models: Vec<String>,
+    ) {
+        let event = proto::Event {
+            kind: Some(kind),
+            ..header.clone()
+        };
+        // Fails only when nobody is subscribed
+        let _ = self.sender.send(Published {
+            event,
+            lobby: *lobby,
+            owner: *owner,
+            models,
+        });
+    }
+
+    fn publish(&mut self, signature: &str) -> Result<()> {
+        let Some(transaction) = self.rpc.transaction(signature)? else {
+            bail!("{signature} is not available yet");
+        };
+        let slot = transaction["slot"].as_u64().context("transaction slot")?;
+        let parsed =
+            parse_transaction(&transaction).with_context(|| format!("decoding {signature}"))?;
+        for error in &parsed.errors {
+            eprintln!("{signature}: skipping an event: {error}");
+        }
+        let events = parsed.events;
+
+        // Lobbies the transaction touched, with whether it finished them
+        let mut touched: Vec<(Pubkey, bool)> = Vec::new();
+        for event in &events {
+            let (lobby, finished) = match event {
+                ProgramEvent::LobbyUpdated(update) => {
+                    (update.lobby, update.action == LobbyAction::GameFinished)
+                }
+                ProgramEvent::BetPlaced(bet) => (bet.lobby, false),
+                _ => continue,
+            };
+            match touched.iter_mut().find(|(address, _)| *address == lobby) {
+                Some((_, was_finished)) => *was_finished |= finished,
+                None => touched.push((lobby, finished)),
+            }
+        }
+        if touched.is_empty() {
+            return Ok(());
+        }
+        let addresses: Vec<Pubkey> = touched.iter().map(|(address, _)| *address).collect();
+        let lobbies: HashMap<Pubkey, Lobby> = addresses
+            .iter()
+            .copied()
+            .zip(self.rpc.lobbies_at(&addresses)?)
+            .filter_map(|(address, lobby)| Some((address, lobby?)))
+            .collect();
+        let header = |address: &Pubkey, lobby: &Lobby| proto::Event {
+            lobby: address.to_string(),
+            owner: lobby.owner.to_string(),
+            signature: signature.to_string(),
+            slot,
+            kind: None,
+        };
+
+        for event in events {
+            let ProgramEvent::BetPlaced(bet) = event else {
+                continue;
+            };
+            let Some(lobby) = lobbies.get(&bet.lobby) else {
+                continue;
+            };
+            let side = match bet.side {
+                BetSide::Back => proto::Side::Back,
+                BetSide::Lay => proto::Side::Lay,
+            };
+            let kind = Kind::BetPlaced(proto::BetPlaced {
+                bet: bet.bet.to_string(),
+                bettor: bet.bettor.to_string(),
+                model: bet.player_name.clone(),
+                side: side as i32,
+                amount: bet.amount,
+            });
+            let header = header(&bet.lobby, lobby);
+            self.send(
+                &bet.lobby,
+                &lobby.owner,
+                &header,
+                kind,
+                vec![bet.player_name],
+            );
+        }
+
+        for (address, finished) in touched {
+            let Some(lobby) = lobbies.get(&address) else {
+                continue;
+            };
+            let header = header(&address, lobby);
+            if !lobby.status.is_over() {
+                let odds = store::implied_odds(lobby);
+                if self.odds.get(&address) != Some(&odds) {
+                    let models = lobby
+                        .model_names
+                        .iter()
+                        .zip(&lobby.model_pools)
+                        .zip(&odds)
+                        .map(|((model, pool), odds)| proto::ModelOdds {
+                            model: model.clone(),
+                            pool: *pool,
+                            odds_bps: *odds,
+                        })
+                        .collect();
+                    let kind = Kind::OddsChanged(proto::OddsChanged {
+                        models,
+                        total_bets: lobby.total_bets,
+                    });
+                    self.send(
+                        &address,
+                        &lobby.owner,
+                        &header,
+                        kind,
+                        lobby.model_names.clone(),
+                    );
+                    self.odds.insert(address, odds);
+                }
+            }
+            if finished {
+                self.odds.remove(&address);
+                if let Some(winner) = &lobby.winner {
+                    let kind = Kind::WinnerDeclared(proto::WinnerDeclared {
+                        winner: winner.clone(),
+                    });
+                    self.send(
+                        &address,
+                        &lobby.owner,
+                        &header,
+                        kind,
+                        lobby.model_names.clone(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+//! Load test for `place_bet`: funds a set of fresh bettor keypairs from a
+//! payer, then has them all bet on one lobby at once from a pool of threads,
+//! and reports confirmed bets per second and how the rest failed.
+//!
+//! ```text
+//! loadtest <rpc-url> <payer-keypair> <game-id> [--bettors <n>] [--threads <n>]
+//!          [--amount <lamports>] [--timeout <seconds>] [--owner <pubkey>]
+//! ```
+//!
+//! Meant for a local validator with the programs deployed and the lobby
+//! already created, by the payer unless `--owner` names its owner. Each
+//! bettor places a single back bet, since a bet account is unique per lobby,
+//! side and wallet; bets rotate over the lobby's models.
+
+mod report;
+mod rpc;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anyhow::{bail, ensure, Context, Result};
+use poker_betting::{pda, BetSide, Lobby};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+
+use crate::report::Report;
+use crate::rpc::{Rpc, Status};
+
+/// Transfers packed into each funding transaction.
+const TRANSFERS_PER_TRANSACTION: usize = 20;
+/// Covers the signature fee of the bet transaction.
+const FEE_ALLOWANCE: u64 = 10_000;
+/// Blockhashes expire after about a minute; refresh well before that.
+const BLOCKHASH_MAX_AGE: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+struct Options {
+    bettors: usize,
+    threads: usize,
+    amount: u64,
+    timeout: Duration,
+    /// Owner of the lobby; the payer when not given
+    owner: Option<Pubkey>,
+}
+
+fn place_bet(lobby: &Pubkey, bettor: &Pubkey, player_name: &str, amount: u64) -> Instruction {
+    let accounts = poker_betting::accounts::PlaceBet {
+        lobby: *lobby,
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        bettor: *bettor,
+        bettor_stats: pda::bettor_stats_pda(bettor),
+        self_exclusion: pda::self_exclusion_pda(bettor),
+        denylist: pda::denylist_pda(),
+        escrow: pda::escrow_pda(lobby),
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        attestation: None,
+        system_program: solana_sdk::system_program::ID,
+        instructions: Some(solana_sdk::sysvar::instructions::ID),
+        price_update: None,
+    };
+    let data = poker_betting::instruction::PlaceBet {
+        player_name: player_name.to_string(),
+        amount,
+        min_expected_odds_bps: 0,
+        side: BetSide::Back,
+        client_id: [0; 16],
+        memo: None,
+    };
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Polls until every signature has landed or failed, or `timeout` passes,
+/// recording each outcome in `report`.
+fn confirm(
+    rpc: &Rpc,
+    signatures: &[Signature],
+    started: Instant,
+    timeout: Duration,
+    report: &mut Report,
+) -> Result<()> {
+    let mut pending = signatures.to_vec();
+    while !pending.is_empty() && started.elapsed() < timeout {
+        thread::sleep(POLL_INTERVAL);
+        let statuses = rpc.statuses(&pending)?;
+        let mut still_pending = Vec::new();
+        for (signature, status) in pending.into_iter().zip(statuses) {
+            match status {
+                Status::Pending => still_pending.push(signature),
+                Status::Confirmed => report.confirm(started),
+                Status::Failed(err) => report.fail(format!("on chain: {err}")),
+            }
+        }
+        pending = still_pending;
+    }
+    for _ in &pending {
+        report.fail("not confirmed before the timeout".to_string());
+    }
+    Ok(())
+}
+
+/// Sends each bettor `lamports` from `payer` and waits for the transfers to
+/// confirm.
+fn fund(rpc: &Rpc, payer: &Keypair, bettors: &[Keypair], lamports: u64) -> Result<()> {
+    let blockhash = rpc.latest_blockhash()?;
+    let signatures = bettors
+        .chunks(TRANSFERS_PER_TRANSACTION)
+        .map(|chunk| {
+            let transfers: Vec<_> = chunk
+                .iter()
+                .map(|bettor| {
+                    system_instruction::transfer(&payer.pubkey(), &bettor.pubkey(), lamports)
+                })
+                .collect();
+            let transaction = Transaction::new_signed_with_payer(
+                &transfers,
+                Some(&payer.pubkey()),
+                &[payer],
+                blockhash,
+            );
+            rpc.send(&transaction)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut report = Report::default();
+    confirm(
+        rpc,
+        &signatures,
+        Instant::now(),
+        Duration::from_secs(60),
+        &mut report,
+    )?;
+    ensure!(
+        report.failures.is_empty(),
+        "funding failed: {:?}",
+        report.failures
+    );
+    Ok(())
+}
+
+/// Sends every bettor's bet from `options.threads` threads, returning the
+/// signatures the RPC accepted.
+fn hammer(
+    rpc: &Rpc,
+    lobby: &Pubkey,
+    models: &[String],
+    bettors: &[Keypair],
+    options: &Options,
+    report: &mut Report,
+) -> Result<Vec<Signature>> {
+    let next = AtomicUsize::new(0);
+    let blockhash = Mutex::new((rpc.latest_blockhash()?, Instant::now()));
+    let results = Mutex::new(Vec::with_capacity(bettors.len()));
+    thread::scope(|scope| {
+        for _ in 0..options.threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(bettor) = bettors.get(index) else {
+                    break;
+                };
+                let recent_blockhash = current_blockhash(rpc, &blockhash);
+                let player_name = &models[index % models.len()];
+                let sent = Instant::now();
+                let result = recent_blockhash.and_then(|recent_blockhash| {
+                    let transaction = Transaction::new_signed_with_payer(
+                        &[place_bet(
+                            lobby,
+                            &bettor.pubkey(),
+                            player_name,
+                            options.amount,
+                        )],
+                        Some(&bettor.pubkey()),
+                        &[bettor],
+                        recent_blockhash,
+                    );
+                    rpc.send(&transaction)
+                });
+                results.lock().unwrap().push((sent.elapsed(), result));
+            });
+        }
+    });
+
+    let mut signatures = Vec::new();
+    for (latency, result) in results.into_inner().unwrap() {
+        report.sent += 1;
+        report.send_latencies.push(latency);
+        match result {
+            Ok(signature) => signatures.push(signature),
+            Err(err) => report.fail(format!("rejected by rpc: {err:#}")),
+        }
+    }
+    Ok(signatures)
+}
+
+fn current_blockhash(rpc: &Rpc, cached: &Mutex<(Hash, Instant)>) -> Result<Hash> {
+    let mut cached = cached.lock().unwrap();
+    if cached.1.elapsed() > BLOCKHASH_MAX_AGE {
+        *cached = (rpc.latest_blockhash()?, Instant::now());
+    }
+    Ok(cached.0)
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(rpc_url), Some(payer), Some(game_id)) = (args.next(), args.next(), args.next())
+    else {
+        bail!(
+            "usage: loadtest <rpc-url> <payer-keypair> <game-id> [--bettors <n>] [--threads <n>] \
+             [--amount <lamports>] [--timeout <seconds>] [--owner <pubkey>]"
+        );
+    };
+    let mut options = Options {
+        bettors: 100,
+        threads: 8,
+        amount: 10_000_000,
+        timeout: Duration::from_secs(60),
+        owner: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--bettors" => options.bettors = value()?.parse()?,
+            "--threads" => options.threads = value()?.parse()?,
+            "--amount" => options.amount = value()?.parse()?,
+            "--timeout" => options.timeout = Duration::from_secs(value()?.parse()?),
+            "--owner" => options.owner = Some(value()?.parse()?),
+            _ => bail!("unknown argument {arg}"),
+        }
+    }
+    ensure!(options.threads > 0, "--threads must be positive");
+
+    let rpc = Rpc::new(&rpc_url);
+    let payer =
+        read_keypair_file(&payer).map_err(|err| anyhow::anyhow!("reading {payer}: {err}"))?;
+    let owner = options.owner.unwrap_or_else(|| payer.pubkey());
+    let lobby = pda::lobby_pda(&owner, &game_id);
+    let data = rpc
+        .account_data(&lobby)?
+        .with_context(|| format!("no lobby for game {game_id}"))?;
+    let models = Lobby::try_deserialize(&mut &data[..])?.model_names;
+    ensure!(!models.is_empty(), "lobby {lobby} has no models");
+
+    let bettors: Vec<Keypair> = (0..options.bettors).map(|_| Keypair::new()).collect();
+    let rent = Rent::default();
+    let lamports = options.amount
+        + rent.minimum_balance(8 + poker_betting::Bet::LEN)
+        + rent.minimum_balance(8 + poker_betting::BettorStats::LEN)
+        + FEE_ALLOWANCE;
+    eprintln!(
+        "funding {} bettors with {lamports} lamports each",
+        bettors.len()
+    );
+    fund(&rpc, &payer, &bettors, lamports)?;
+
+    eprintln!(
+        "placing {} bets on {lobby} from {} threads",
+        bettors.len(),
+        options.threads
+    );
+    let mut report = Report::default();
+    let started = Instant::now();
+    let signatures = hammer(&rpc, &lobby, &models, &bettors, &options, &mut report)?;
+    confirm(&rpc, &signatures, started, options.timeout, &mut report)?;
+    print!("{report}");
+    Ok(())
+}
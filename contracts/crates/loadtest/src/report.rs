@@ -0,0 +1,66 @@
+//! Outcome of a load test run.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct Report {
+    pub sent: usize,
+    pub send_latencies: Vec<Duration>,
+    pub confirmed: usize,
+    /// Time from the first send to the last confirmation observed
+    pub elapsed: Duration,
+    /// Count of each way a bet failed, keyed by where it failed and why
+    pub failures: BTreeMap<String, usize>,
+}
+
+impl Report {
+    pub fn fail(&mut self, reason: String) {
+        *self.failures.entry(reason).or_default() += 1;
+    }
+
+    pub fn confirm(&mut self, started: Instant) {
+        self.confirmed += 1;
+        self.elapsed = started.elapsed();
+    }
+
+    fn tps(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.confirmed as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn latency(&self, percentile: usize) -> Duration {
+        let mut latencies = self.send_latencies.clone();
+        latencies.sort();
+        latencies
+            .get((latencies.len() * percentile / 100).min(latencies.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "sent       {}", self.sent)?;
+        writeln!(f, "confirmed  {}", self.confirmed)?;
+        writeln!(
+            f,
+            "throughput {:.1} confirmed bets/s over {:.1}s",
+            self.tps(),
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "send rtt   p50 {:?}, p99 {:?}",
+            self.latency(50),
+            self.latency(99)
+        )?;
+        for (reason, count) in &self.failures {
+            writeln!(f, "failed     {count:>6}  {reason}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! The Solana JSON-RPC calls a load test makes.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+/// Most signatures `getSignatureStatuses` takes per call.
+const MAX_SIGNATURE_STATUSES: usize = 256;
+
+pub struct Rpc {
+    url: String,
+    agent: ureq::Agent,
+}
+
+/// Why a transaction the RPC accepted did or didn't land.
+pub enum Status {
+    Pending,
+    Confirmed,
+    /// The transaction error, as the RPC reports it
+    Failed(String),
+}
+
+impl Rpc {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(&request)
+            .with_context(|| method.to_string())?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            bail!("{}", error["message"].as_str().unwrap_or("unknown error"));
+        }
+        Ok(response["result"].take())
+    }
+
+    pub fn latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        result["value"]["blockhash"]
+            .as_str()
+            .context("getLatestBlockhash result")?
+            .parse()
+            .context("blockhash")
+    }
+
+    /// Data of the account at `address`, if it exists.
+    pub fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), { "commitment": "confirmed", "encoding": "base64" }]),
+        )?;
+        let value = &result["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        let data = value["data"][0].as_str().context("account data")?;
+        Ok(Some(STANDARD.decode(data)?))
+    }
+
+    /// Sends `transaction` without preflight, so every failure is counted
+    /// where it happens: at the RPC or on chain.
+    pub fn send(&self, transaction: &Transaction) -> Result<Signature> {
+        let encoded = STANDARD.encode(bincode::serialize(transaction)?);
+        let config = json!({ "encoding": "base64", "skipPreflight": true });
+        self.call("sendTransaction", json!([encoded, config]))?
+            .as_str()
+            .context("sendTransaction result")?
+            .parse()
+            .context("signature")
+    }
+
+    pub fn statuses(&self, signatures: &[Signature]) -> Result<Vec<Status>> {
+        let mut statuses = Vec::with_capacity(signatures.len());
+        for chunk in signatures.chunks(MAX_SIGNATURE_STATUSES) {
+            let keys: Vec<String> = chunk.iter().map(Signature::to_string).collect();
+            let result = self.call("getSignatureStatuses", json!([keys]))?;
+            let values = result["value"]
+                .as_array()
+                .context("getSignatureStatuses result")?;
+            statuses.extend(values.iter().map(|status| {
+                if status.is_null() || status["confirmationStatus"] == "processed" {
+                    Status::Pending
+                } else if status["err"].is_null() {
+                    Status::Confirmed
+                } else {
+                    Status::Failed(status["err"].to_string())
+                }
+            }));
+        }
+        Ok(statuses)
+    }
+}
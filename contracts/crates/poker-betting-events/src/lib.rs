@@ -0,0 +1,278 @@
+//! Typed decoding of poker-betting events for indexers and bots.
+//!
+//! The program logs each event with `sol_log_data` as an 8-byte discriminator
+//! followed by the Borsh-encoded event, which shows up in a transaction's log
+//! messages as `Program data: <base64>`. `parse_logs` walks the logs of a
+//! `getTransaction` response, keeps the data logged by the betting program
+//! itself (not by programs it calls or that call it) and decodes it.
+//!
+//! The layout is versioned by `EVENT_SCHEMA_VERSION`. Events only gain fields
+//! at the end, so decoding tolerates trailing bytes from newer programs, and
+//! events this crate doesn't know are skipped. An event that fails to decode,
+//! e.g. one carrying an enum variant newer than this crate, is reported on its
+//! own without losing the rest of the transaction's events.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::Engine;
+use serde_json::Value;
+
+pub use poker_betting::{
+    BetPlaced, BetSettled, BetStatusChanged, BountyClaimed, BountyFunded, CompressedBetPlaced,
+    ConfigChangeCancelled, ConfigChangeExecuted, ConfigChangeProposed, EscrowSwept, FeeTierApplied,
+    KeeperSlashed, LobbyUpdated, MessagePosted, PrizePoolBoosted, ShieldedNoteDeposited,
+    SpectatorPassPurchased, EVENT_SCHEMA_VERSION, ID as PROGRAM_ID,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error("invalid base64 in program data log: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("malformed {event} event: {source}")]
+    Event {
+        event: &'static str,
+        source: std::io::Error,
+    },
+    #[error("transaction has no log messages")]
+    MissingLogs,
+}
+
+/// A log line that should have held a betting program event but didn't
+/// decode.
+#[derive(Debug, thiserror::Error)]
+#[error("log line {line}: {error}")]
+pub struct LogError {
+    /// Index of the line in the transaction's log messages
+    pub line: usize,
+    pub error: ParseError,
+}
+
+/// The events decoded from a transaction's logs, and the lines that failed.
+#[derive(Default)]
+pub struct ParsedLogs {
+    pub events: Vec<ProgramEvent>,
+    pub errors: Vec<LogError>,
+}
+
+/// An event logged by the betting program.
+pub enum ProgramEvent {
+    MessagePosted(MessagePosted),
+    PrizePoolBoosted(PrizePoolBoosted),
+    EscrowSwept(EscrowSwept),
+    LobbyUpdated(LobbyUpdated),
+    SpectatorPassPurchased(SpectatorPassPurchased),
+    CompressedBetPlaced(CompressedBetPlaced),
+    ShieldedNoteDeposited(ShieldedNoteDeposited),
+    BountyFunded(BountyFunded),
+    BountyClaimed(BountyClaimed),
+    FeeTierApplied(FeeTierApplied),
+    BetPlaced(BetPlaced),
+    BetStatusChanged(BetStatusChanged),
+    ConfigChangeProposed(ConfigChangeProposed),
+    ConfigChangeExecuted(ConfigChangeExecuted),
+    ConfigChangeCancelled(ConfigChangeCancelled),
+    BetSettled(BetSettled),
+    KeeperSlashed(KeeperSlashed),
+}
+
+fn decode<T: AnchorDeserialize>(event: &'static str, mut body: &[u8]) -> Result<T, ParseError> {
+    T::deserialize(&mut body).map_err(|source| ParseError::Event { event, source })
+}
+
+impl ProgramEvent {
+    /// Decodes one `sol_log_data` payload. `None` if it isn't a known event.
+    pub fn decode(data: &[u8]) -> Result<Option<Self>, ParseError> {
+        if data.len() < 8 {
+            return Ok(None);
+        }
+        let (discriminator, body) = data.split_at(8);
+        let event = match discriminator {
+            d if d == MessagePosted::DISCRIMINATOR => {
+                Self::MessagePosted(decode("MessagePosted", body)?)
+            }
+            d if d == PrizePoolBoosted::DISCRIMINATOR => {
+                Self::PrizePoolBoosted(decode("PrizePoolBoosted", body)?)
+            }
+            d if d == EscrowSwept::DISCRIMINATOR => Self::EscrowSwept(decode("EscrowSwept", body)?),
+            d if d == LobbyUpdated::DISCRIMINATOR => {
+                Self::LobbyUpdated(decode("LobbyUpdated", body)?)
+            }
+            d if d == SpectatorPassPurchased::DISCRIMINATOR => {
+                Self::SpectatorPassPurchased(decode("SpectatorPassPurchased", body)?)
+            }
+            d if d == CompressedBetPlaced::DISCRIMINATOR => {
+                Self::CompressedBetPlaced(decode("CompressedBetPlaced", body)?)
+            }
+            d if d == ShieldedNoteDeposited::DISCRIMINATOR => {
+                Self::ShieldedNoteDeposited(decode("ShieldedNoteDeposited", body)?)
+            }
+            d if d == BountyFunded::DISCRIMINATOR => {
+                Self::BountyFunded(decode("BountyFunded", body)?)
+            }
+            d if d == BountyClaimed::DISCRIMINATOR => {
+                Self::BountyClaimed(decode("BountyClaimed", body)?)
+            }
+            d if d == FeeTierApplied::DISCRIMINATOR => {
+                Self::FeeTierApplied(decode("FeeTierApplied", body)?)
+            }
+            d if d == BetPlaced::DISCRIMINATOR => Self::BetPlaced(decode("BetPlaced", body)?),
+            d if d == BetStatusChanged::DISCRIMINATOR => {
+                Self::BetStatusChanged(decode("BetStatusChanged", body)?)
+            }
+            d if d == ConfigChangeProposed::DISCRIMINATOR => {
+                Self::ConfigChangeProposed(decode("ConfigChangeProposed", body)?)
+            }
+            d if d == ConfigChangeExecuted::DISCRIMINATOR => {
+                Self::ConfigChangeExecuted(decode("ConfigChangeExecuted", body)?)
+            }
+            d if d == ConfigChangeCancelled::DISCRIMINATOR => {
+                Self::ConfigChangeCancelled(decode("ConfigChangeCancelled", body)?)
+            }
+            d if d == BetSettled::DISCRIMINATOR => Self::BetSettled(decode("BetSettled", body)?),
+            d if d == KeeperSlashed::DISCRIMINATOR => {
+                Self::KeeperSlashed(decode("KeeperSlashed", body)?)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+}
+
+fn decode_log(data: &str) -> Result<Option<ProgramEvent>, ParseError> {
+    let data = base64::engine::general_purpose::STANDARD.decode(data)?;
+    ProgramEvent::decode(&data)
+}
+
+/// Decodes the betting program's events from a transaction's log messages,
+/// in the order they were logged. A line that fails to decode is reported in
+/// `errors` and the others are still decoded.
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> ParsedLogs {
+    let program_id = PROGRAM_ID.to_string();
+    let mut call_stack: Vec<bool> = Vec::new();
+    let mut parsed = ParsedLogs::default();
+    for (line, log) in logs.iter().enumerate() {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix("Program data: ") {
+            if call_stack.last() == Some(&true) {
+                match decode_log(data) {
+                    Ok(event) => parsed.events.extend(event),
+                    Err(error) => parsed.errors.push(LogError { line, error }),
+                }
+            }
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => call_stack.push(program == program_id),
+                (Some(_), Some("success" | "failed:")) => {
+                    call_stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    parsed
+}
+
+/// Decodes the events of a JSON-RPC `getTransaction` response, either the
+/// whole response or its `result`.
+pub fn parse_transaction(response: &Value) -> Result<ParsedLogs, ParseError> {
+    let transaction = response.get("result").unwrap_or(response);
+    let logs = transaction
+        .pointer("/meta/logMessages")
+        .and_then(Value::as_array)
+        .ok_or(ParseError::MissingLogs)?;
+    let logs: Vec<&str> = logs.iter().filter_map(Value::as_str).collect();
+    Ok(parse_logs(&logs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::Event;
+    use poker_betting::LobbyAction;
+
+    fn data_line(data: &[u8]) -> String {
+        format!(
+            "Program data: {}",
+            base64::engine::general_purpose::STANDARD.encode(data)
+        )
+    }
+
+    fn lobby_updated(seq: u64) -> String {
+        let event = LobbyUpdated {
+            lobby: Pubkey::new_unique(),
+            seq,
+            action: LobbyAction::BetPlaced,
+            updated_at: 0,
+        };
+        data_line(&event.data())
+    }
+
+    fn seqs(parsed: &ParsedLogs) -> Vec<u64> {
+        parsed
+            .events
+            .iter()
+            .map(|event| match event {
+                ProgramEvent::LobbyUpdated(update) => update.seq,
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_malformed_event_does_not_drop_the_others() {
+        // A `LobbyAction` variant this crate doesn't know
+        let mut unknown_action = LobbyUpdated::DISCRIMINATOR.to_vec();
+        unknown_action.extend_from_slice(&[0; 40]);
+        unknown_action.push(u8::MAX);
+        unknown_action.extend_from_slice(&[0; 8]);
+        let logs = vec![
+            format!("Program {PROGRAM_ID} invoke [1]"),
+            lobby_updated(1),
+            data_line(&unknown_action),
+            "Program data: not base64!".to_string(),
+            lobby_updated(2),
+            format!("Program {PROGRAM_ID} success"),
+        ];
+
+        let parsed = parse_logs(&logs);
+        assert_eq!(seqs(&parsed), [1, 2]);
+        let lines: Vec<usize> = parsed.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [2, 3]);
+    }
+
+    #[test]
+    fn only_the_betting_programs_own_data_is_decoded() {
+        let other = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {other} invoke [1]"),
+            lobby_updated(1),
+            format!("Program {PROGRAM_ID} invoke [2]"),
+            lobby_updated(2),
+            format!("Program {other} invoke [3]"),
+            lobby_updated(3),
+            format!("Program {other} success"),
+            lobby_updated(4),
+            format!("Program {PROGRAM_ID} success"),
+            format!("Program {other} success"),
+        ];
+
+        let parsed = parse_logs(&logs);
+        assert_eq!(seqs(&parsed), [2, 4]);
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn unknown_events_are_skipped() {
+        let logs = vec![
+            format!("Program {PROGRAM_ID} invoke [1]"),
+            data_line(&[7; 16]),
+            lobby_updated(1),
+            format!("Program {PROGRAM_ID} success"),
+        ];
+
+        let parsed = parse_logs(&logs);
+        assert_eq!(seqs(&parsed), [1]);
+        assert!(parsed.errors.is_empty());
+    }
+}
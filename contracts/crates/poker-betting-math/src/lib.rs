@@ -0,0 +1,231 @@
+//! Fee, odds and payout arithmetic of the poker-betting program.
+//!
+//! The program settles bets with these functions, and off-chain code that
+//! estimates a payout should call the same ones, so an estimate can only
+//! differ from what the chain pays if the pools move in between. The crate is
+//! `no_std` and has no dependencies so it builds for the SBF target as-is.
+//!
+//! Everything multiplies in u128 before dividing, so `a * b / c` never loses
+//! precision to an intermediate overflow, and every result is checked back
+//! into range. Division by zero is reported as `Overflow`, like every other
+//! arithmetic failure.
+
+#![no_std]
+
+use core::fmt;
+
+/// Denominator of basis-point quantities (fees, shares, odds).
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Decimal odds of 1.0, in basis points.
+pub const ODDS_SCALE: u64 = 10_000;
+
+/// An intermediate or result left the range of its type, or a division by zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("arithmetic overflow")
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Overflow>;
+
+/// `a * b / c`, rounded down.
+pub fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    narrow(mul_div_wide(a as u128, b as u128, c as u128)?)
+}
+
+/// `a * b / c`, rounded up.
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Result<u64> {
+    if c == 0 {
+        return Err(Overflow);
+    }
+    let product = (a as u128).checked_mul(b as u128).ok_or(Overflow)?;
+    narrow(product.div_ceil(c as u128))
+}
+
+/// `a * b / c` over u128, rounded down, for accumulators that outgrow u64.
+pub fn mul_div_wide(a: u128, b: u128, c: u128) -> Result<u128> {
+    if c == 0 {
+        return Err(Overflow);
+    }
+    Ok(a.checked_mul(b).ok_or(Overflow)? / c)
+}
+
+/// `bps` basis points of `amount`, rounded down.
+pub fn apply_bps(amount: u64, bps: u64) -> Result<u64> {
+    mul_div(amount, bps, BPS_DENOMINATOR)
+}
+
+/// `part` as basis points of `total`; zero when `total` is zero.
+pub fn bps_share(part: u64, total: u64) -> Result<u64> {
+    if total == 0 {
+        return Ok(0);
+    }
+    mul_div(part, BPS_DENOMINATOR, total)
+}
+
+pub fn narrow(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| Overflow)
+}
+
+/// What is left of a bet of `amount` once the protocol fee of `fee_bps` is
+/// taken.
+pub fn stake_after_fee(amount: u64, fee_bps: u64) -> Result<u64> {
+    amount
+        .checked_sub(apply_bps(amount, fee_bps)?)
+        .ok_or(Overflow)
+}
+
+/// Pro-rata share of `distributable` for `stake` out of `winning_stake`.
+pub fn parimutuel_payout(stake: u64, distributable: u64, winning_stake: u64) -> Result<u64> {
+    mul_div(stake, distributable, winning_stake)
+}
+
+/// Payout of a parimutuel claim of `stake` when `settled_stake` of the
+/// winning stake has already claimed `paid`. Shares round down; the claim
+/// that settles the last of the winning stake receives whatever is left, so
+/// the pool empties exactly.
+pub fn parimutuel_claim(
+    stake: u64,
+    settled_stake: u64,
+    paid: u64,
+    distributable: u64,
+    winning_stake: u64,
+) -> Result<u64> {
+    let settled = settled_stake.checked_add(stake).ok_or(Overflow)?;
+    if settled > winning_stake {
+        return Err(Overflow);
+    }
+    if settled == winning_stake {
+        distributable.checked_sub(paid).ok_or(Overflow)
+    } else {
+        parimutuel_payout(stake, distributable, winning_stake)
+    }
+}
+
+pub fn fixed_odds_payout(stake: u64, odds_bps: u32) -> Result<u64> {
+    mul_div(stake, odds_bps as u64, ODDS_SCALE)
+}
+
+/// Parimutuel decimal odds, in basis points, of a pool of `distributable`
+/// shared by `winning_stake`.
+pub fn implied_odds_bps(distributable: u64, winning_stake: u128) -> Result<u64> {
+    narrow(mul_div_wide(
+        distributable as u128,
+        ODDS_SCALE as u128,
+        winning_stake,
+    )?)
+}
+
+/// Parimutuel decimal odds, in basis points, of a bet of `stake` that counts
+/// as `weight` in a pool of `distributable` shared by `winning_weight`, its
+/// own weight included.
+pub fn weighted_odds_bps(
+    stake: u64,
+    weight: u64,
+    distributable: u64,
+    winning_weight: u64,
+) -> Result<u64> {
+    let payout = mul_div(weight, distributable, winning_weight)?;
+    mul_div(payout, ODDS_SCALE, stake)
+}
+
+/// Floor of the square root of `n`. Newton's method from a first guess above
+/// the root, so every iterate stays below 2^33 and nothing can overflow.
+pub fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    let mut root = 1u64 << (64 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (root + n / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mul_div_keeps_the_full_intermediate_product() {
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+        assert_eq!(mul_div(u64::MAX, 3, 4), Ok(u64::MAX / 4 * 3 + 2));
+        assert_eq!(mul_div(7, 3, 2), Ok(10));
+        assert_eq!(mul_div_ceil(7, 3, 2), Ok(11));
+        assert_eq!(mul_div_ceil(6, 3, 2), Ok(9));
+    }
+
+    #[test]
+    fn results_out_of_range_and_division_by_zero_fail() {
+        assert_eq!(mul_div(u64::MAX, 2, 1), Err(Overflow));
+        assert_eq!(mul_div_ceil(u64::MAX, 2, 1), Err(Overflow));
+        assert_eq!(mul_div(1, 1, 0), Err(Overflow));
+        assert_eq!(mul_div_ceil(1, 1, 0), Err(Overflow));
+        assert_eq!(mul_div_wide(u128::MAX, 2, 1), Err(Overflow));
+        assert_eq!(mul_div_wide(1, 1, 0), Err(Overflow));
+        assert_eq!(narrow(u64::MAX as u128 + 1), Err(Overflow));
+        assert_eq!(narrow(u64::MAX as u128), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn basis_points_round_down() {
+        assert_eq!(apply_bps(999, 250), Ok(24));
+        assert_eq!(apply_bps(u64::MAX, BPS_DENOMINATOR), Ok(u64::MAX));
+        assert_eq!(bps_share(1, 3), Ok(3_333));
+        assert_eq!(bps_share(5, 0), Ok(0));
+        assert_eq!(stake_after_fee(1_000, 250), Ok(975));
+        assert_eq!(stake_after_fee(1_000, 2 * BPS_DENOMINATOR), Err(Overflow));
+    }
+
+    #[test]
+    fn parimutuel_claims_sum_to_the_pool() {
+        for (stakes, distributable) in [
+            (&[1, 1, 1][..], 100),
+            (&[3, 5, 7, 11][..], 1_000_003),
+            (&[u64::MAX / 2, u64::MAX / 2][..], u64::MAX),
+        ] {
+            let winning_stake: u64 = stakes.iter().sum();
+            let (mut settled, mut paid) = (0, 0);
+            for stake in stakes {
+                let claim =
+                    parimutuel_claim(*stake, settled, paid, distributable, winning_stake).unwrap();
+                assert!(claim >= parimutuel_payout(*stake, distributable, winning_stake).unwrap());
+                settled += stake;
+                paid += claim;
+            }
+            assert_eq!(paid, distributable);
+        }
+    }
+
+    #[test]
+    fn a_claim_beyond_the_winning_stake_fails() {
+        assert_eq!(parimutuel_claim(2, 9, 90, 100, 10), Err(Overflow));
+        assert_eq!(parimutuel_payout(1, 100, 0), Err(Overflow));
+    }
+
+    #[test]
+    fn odds_are_in_basis_points() {
+        assert_eq!(fixed_odds_payout(1_000, 25_000), Ok(2_500));
+        assert_eq!(fixed_odds_payout(u64::MAX, u32::MAX), Err(Overflow));
+        assert_eq!(implied_odds_bps(1_000, 400), Ok(25_000));
+        assert_eq!(implied_odds_bps(1_000, 0), Err(Overflow));
+        // A bet of 100 weighing 10 out of 20 takes half of 1_000
+        assert_eq!(weighted_odds_bps(100, 10, 1_000, 20), Ok(50_000));
+    }
+
+    #[test]
+    fn isqrt_is_the_floor_of_the_root() {
+        for n in (0..10_000).chain([u64::MAX - 1, u64::MAX, 1 << 62, (1 << 32) - 1]) {
+            let root = isqrt(n) as u128;
+            assert!(root * root <= n as u128, "{n}");
+            assert!((root + 1) * (root + 1) > n as u128, "{n}");
+        }
+    }
+}
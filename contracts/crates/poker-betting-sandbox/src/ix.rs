@@ -0,0 +1,1082 @@
+//! Instructions tests send, with the accounts the programs derive filled in.
+//! Optional accounts a builder doesn't take are passed as absent.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use poker_betting::{pda, BetSide, BlindLevel, ConfigChange, FeeTier, LobbyStatus};
+use poker_engine::shuffle::Card;
+use poker_engine::PlayerAction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+
+fn betting(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn engine(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: poker_engine::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// The poker engine's config, which names the game server.
+pub fn engine_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"engine"], &poker_engine::ID).0
+}
+
+/// The engine's record of `owner`'s game `game_id`, which `finish_game`
+/// reads the winner from.
+pub fn game_pda(owner: &Pubkey, game_id: &str) -> Pubkey {
+    let game_id_hash = poker_betting::game_id_hash(game_id);
+    Pubkey::find_program_address(&[b"game", owner.as_ref(), &game_id_hash], &poker_engine::ID).0
+}
+
+pub fn initialize_config(admin: &Pubkey, fee_bps: u16) -> Instruction {
+    let accounts = poker_betting::accounts::InitializeConfig {
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        admin: *admin,
+        program: poker_betting::ID,
+        program_data: pda::program_data(),
+        system_program: system_program::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::InitializeConfig { fee_bps },
+    )
+}
+
+pub fn set_min_operator_reputation(admin: &Pubkey, min_operator_reputation: u64) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetMinOperatorReputation {
+            min_operator_reputation,
+        },
+    )
+}
+
+pub fn set_bet_composition_guard(admin: &Pubkey, enabled: bool) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetBetCompositionGuard { enabled },
+    )
+}
+
+/// Prices bets against the Pyth feed `usd_price_feed`, bounded to
+/// `min_bet_usd..=max_bet_usd` micro-USD.
+pub fn set_usd_pricing(
+    admin: &Pubkey,
+    usd_price_feed: [u8; 32],
+    min_bet_usd: u64,
+    max_bet_usd: u64,
+) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    let data = poker_betting::instruction::SetUsdPricing {
+        usd_price_feed: Some(usd_price_feed),
+        max_price_age: 60,
+        max_price_conf_bps: 100,
+        min_bet_usd,
+        max_bet_usd,
+    };
+    betting(accounts, data)
+}
+
+pub fn set_fee_tiers(admin: &Pubkey, fee_tiers: Vec<FeeTier>) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetFeeTiers { fee_tiers },
+    )
+}
+
+pub fn set_keeper_terms(admin: &Pubkey, min_keeper_stake: u64, keeper_fee: u64) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    let data = poker_betting::instruction::SetKeeperTerms {
+        min_keeper_stake,
+        keeper_fee,
+    };
+    betting(accounts, data)
+}
+
+pub fn set_min_payout(admin: &Pubkey, min_payout_lamports: u64) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetMinPayout {
+            min_payout_lamports,
+        },
+    )
+}
+
+/// Queues `change` behind the admin timelock.
+pub fn propose_config_change(admin: &Pubkey, change: ConfigChange) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::ProposeConfigChange { change },
+    )
+}
+
+pub fn execute_config_change(admin: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    betting(accounts, poker_betting::instruction::ExecuteConfigChange {})
+}
+
+/// A winner-takes-pool lobby of `models`, with one blind level lasting
+/// `max_hands`.
+pub fn create_lobby(
+    owner: &Pubkey,
+    game_id: &str,
+    models: &[String],
+    starting_chips: u64,
+    max_hands: u64,
+) -> Instruction {
+    let lobby = pda::lobby_pda(owner, game_id);
+    let accounts = poker_betting::accounts::CreateLobby {
+        config: pda::config_pda(),
+        owner_stats: pda::owner_stats_pda(owner),
+        operator_profile: pda::operator_profile_pda(owner),
+        lobby,
+        bond: pda::operator_bond_pda(&lobby),
+        owner: *owner,
+        escrow: pda::escrow_pda(&lobby),
+        system_program: system_program::ID,
+        parent_lobby: None,
+    };
+    let data = poker_betting::instruction::CreateLobby {
+        game_id: game_id.to_string(),
+        model_names: models.to_vec(),
+        starting_chips,
+        blinds: vec![BlindLevel {
+            hands: max_hands,
+            small: 10,
+            big: 20,
+        }],
+        max_hands,
+        payout_curve: Vec::new(),
+        chip_buckets: Vec::new(),
+        rake_splits: Vec::new(),
+    };
+    betting(accounts, data)
+}
+
+pub fn set_betting_deadline(lobby: &Pubkey, owner: &Pubkey, deadline: i64) -> Instruction {
+    let accounts = poker_betting::accounts::UpdateLobbyStatus {
+        lobby: *lobby,
+        owner: *owner,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetBettingDeadline { deadline },
+    )
+}
+
+pub fn update_lobby_status(lobby: &Pubkey, owner: &Pubkey, status: LobbyStatus) -> Instruction {
+    let accounts = poker_betting::accounts::UpdateLobbyStatus {
+        lobby: *lobby,
+        owner: *owner,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::UpdateLobbyStatus { status },
+    )
+}
+
+pub fn set_reveal_deadline(lobby: &Pubkey, owner: &Pubkey, reveal_deadline: i64) -> Instruction {
+    let accounts = poker_betting::accounts::UpdateLobbyStatus {
+        lobby: *lobby,
+        owner: *owner,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetRevealDeadline { reveal_deadline },
+    )
+}
+
+/// Commits `lobby`, on `game_id`, to a game transcript.
+pub fn commit_transcript(
+    lobby: &Pubkey,
+    owner: &Pubkey,
+    game_id: &str,
+    commitment: [u8; 32],
+) -> Instruction {
+    let accounts = poker_betting::accounts::CommitTranscript {
+        lobby: *lobby,
+        engine_game: game_pda(owner, game_id),
+        owner: *owner,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::CommitTranscript { commitment },
+    )
+}
+
+/// A back bet on `player_name`.
+pub fn place_bet(lobby: &Pubkey, bettor: &Pubkey, player_name: &str, amount: u64) -> Instruction {
+    let accounts = poker_betting::accounts::PlaceBet {
+        lobby: *lobby,
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        bettor: *bettor,
+        bettor_stats: pda::bettor_stats_pda(bettor),
+        self_exclusion: pda::self_exclusion_pda(bettor),
+        denylist: pda::denylist_pda(),
+        escrow: pda::escrow_pda(lobby),
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        attestation: None,
+        system_program: system_program::ID,
+        instructions: None,
+        price_update: None,
+    };
+    let data = poker_betting::instruction::PlaceBet {
+        player_name: player_name.to_string(),
+        amount,
+        min_expected_odds_bps: 0,
+        side: BetSide::Back,
+        client_id: [0; 16],
+        memo: None,
+    };
+    betting(accounts, data)
+}
+
+pub fn open_player_pool(lobby: &Pubkey, payer: &Pubkey, model_index: u8) -> Instruction {
+    let accounts = poker_betting::accounts::OpenPlayerPool {
+        lobby: *lobby,
+        pool: pda::player_pool_pda(lobby, model_index),
+        payer: *payer,
+        system_program: system_program::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::OpenPlayerPool { model_index },
+    )
+}
+
+/// A back bet held in the pool of model `model_index` until it's aggregated.
+pub fn place_pooled_bet(
+    lobby: &Pubkey,
+    bettor: &Pubkey,
+    model_index: u8,
+    amount: u64,
+) -> Instruction {
+    let accounts = poker_betting::accounts::PlacePooledBet {
+        lobby: *lobby,
+        pool: pda::player_pool_pda(lobby, model_index),
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        bettor: *bettor,
+        bettor_stats: pda::bettor_stats_pda(bettor),
+        self_exclusion: pda::self_exclusion_pda(bettor),
+        denylist: pda::denylist_pda(),
+        config: pda::config_pda(),
+        attestation: None,
+        system_program: system_program::ID,
+        instructions: None,
+        price_update: None,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::PlacePooledBet { amount },
+    )
+}
+
+/// Finishes `lobby` with the winner the engine's record of `game_id` holds.
+pub fn finish_game(lobby: &Pubkey, owner: &Pubkey, game_id: &str) -> Instruction {
+    let accounts = poker_betting::accounts::FinishGame {
+        lobby: *lobby,
+        owner: *owner,
+        operator_profile: pda::operator_profile_pda(owner),
+        engine_game: Some(game_pda(owner, game_id)),
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        escrow: pda::escrow_pda(lobby),
+        winner_stake: None,
+        winner_operator: None,
+        system_program: system_program::ID,
+        results_authority: None,
+        snapshot: None,
+        charity: None,
+    };
+    let data = poker_betting::instruction::FinishGame {
+        placings: Vec::new(),
+        final_chips: Vec::new(),
+    };
+    betting(accounts, data)
+}
+
+pub fn disqualify_model(lobby: &Pubkey, owner: &Pubkey, model: &str) -> Instruction {
+    let accounts = poker_betting::accounts::DisqualifyModel {
+        lobby: *lobby,
+        owner: *owner,
+        snapshot: None,
+    };
+    let data = poker_betting::instruction::DisqualifyModel {
+        player_name: model.to_string(),
+    };
+    betting(accounts, data)
+}
+
+/// Pays `bettor`'s back bet on `winner_name`, sent by the lobby owner.
+pub fn distribute_single_winning(
+    lobby: &Pubkey,
+    owner: &Pubkey,
+    bettor: &Pubkey,
+    winner_name: &str,
+) -> Instruction {
+    let accounts = poker_betting::accounts::DistributeSingleWinning {
+        lobby: *lobby,
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        owner: *owner,
+        escrow: pda::escrow_pda(lobby),
+        bettor: *bettor,
+        payout_recipient: None,
+        bettor_stats: pda::bettor_stats_pda(bettor),
+        snapshot: None,
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        denylist: pda::denylist_pda(),
+        system_program: system_program::ID,
+    };
+    let data = poker_betting::instruction::DistributeSingleWinning {
+        winner_name: winner_name.to_string(),
+    };
+    betting(accounts, data)
+}
+
+/// Marks `bets` lost, without a keeper.
+pub fn settle_losing_bets(lobby: &Pubkey, bets: &[Pubkey]) -> Instruction {
+    let mut instruction = betting(
+        poker_betting::accounts::SettleLosingBets {
+            lobby: *lobby,
+            keeper: None,
+            keeper_authority: None,
+            config: None,
+        },
+        poker_betting::instruction::SettleLosingBets {},
+    );
+    instruction
+        .accounts
+        .extend(bets.iter().map(|bet| AccountMeta::new(*bet, false)));
+    instruction
+}
+
+/// Settles the finished lobby by Merkle distribution of `total` under `root`.
+pub fn post_payout_root(lobby: &Pubkey, owner: &Pubkey, root: [u8; 32], total: u64) -> Instruction {
+    let accounts = poker_betting::accounts::UpdateLobbyStatus {
+        lobby: *lobby,
+        owner: *owner,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::PostPayoutRoot { root, total },
+    )
+}
+
+/// Claims `bettor`'s leaf of the payout root, paid to `bettor`.
+pub fn claim_with_proof(
+    lobby: &Pubkey,
+    bettor: &Pubkey,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let accounts = poker_betting::accounts::ClaimWithProof {
+        lobby: *lobby,
+        claim: pda::merkle_claim_pda(lobby, bettor),
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        bettor: *bettor,
+        payout_recipient: None,
+        escrow: pda::escrow_pda(lobby),
+        bettor_stats: pda::bettor_stats_pda(bettor),
+        config: pda::config_pda(),
+        denylist: pda::denylist_pda(),
+        system_program: system_program::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::ClaimWithProof { amount, proof },
+    )
+}
+
+/// Marks `bettor`'s winning back bet paid by their payout-root claim.
+pub fn settle_claimed_bet(lobby: &Pubkey, bettor: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::SettleClaimedBet {
+        lobby: *lobby,
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        claim: pda::merkle_claim_pda(lobby, bettor),
+    };
+    betting(accounts, poker_betting::instruction::SettleClaimedBet {})
+}
+
+/// Closes `bettor`'s settled back bet, returning its rent.
+pub fn close_bet(lobby: &Pubkey, bettor: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::CloseBet {
+        lobby: *lobby,
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        bettor: *bettor,
+    };
+    betting(accounts, poker_betting::instruction::CloseBet {})
+}
+
+/// Sends the winnings of `bettor`'s back bet to `payout_address`.
+pub fn set_payout_address(
+    lobby: &Pubkey,
+    bettor: &Pubkey,
+    payout_address: Option<Pubkey>,
+) -> Instruction {
+    let accounts = poker_betting::accounts::SetPayoutAddress {
+        bet: pda::bet_pda(lobby, bettor, &BetSide::Back),
+        bettor: *bettor,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::SetPayoutAddress { payout_address },
+    )
+}
+
+pub fn open_hand_market(
+    lobby: &Pubkey,
+    owner: &Pubkey,
+    game_id: &str,
+    hand_number: u64,
+) -> Instruction {
+    let accounts = poker_betting::accounts::OpenHandMarket {
+        lobby: *lobby,
+        engine_game: game_pda(owner, game_id),
+        hand_market: pda::hand_market_pda(lobby, hand_number),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::OpenHandMarket { hand_number },
+    )
+}
+
+/// A bet on `player_name` winning hand `hand_number`.
+pub fn place_hand_bet(
+    lobby: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+    bettor: &Pubkey,
+    player_name: &str,
+    amount: u64,
+) -> Instruction {
+    let hand_market = pda::hand_market_pda(lobby, hand_number);
+    let accounts = poker_betting::accounts::PlaceHandBet {
+        lobby: *lobby,
+        engine_game: *game,
+        hand_market,
+        hand_bet: pda::hand_bet_pda(&hand_market, bettor),
+        bettor: *bettor,
+        bettor_stats: pda::bettor_stats_pda(bettor),
+        self_exclusion: pda::self_exclusion_pda(bettor),
+        denylist: pda::denylist_pda(),
+        hand_escrow: pda::hand_escrow_pda(lobby),
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        attestation: None,
+        system_program: system_program::ID,
+        instructions: None,
+        price_update: None,
+    };
+    let data = poker_betting::instruction::PlaceHandBet {
+        player_name: player_name.to_string(),
+        amount,
+    };
+    betting(accounts, data)
+}
+
+/// Voids the markets on hands `game` ended before dealing; permissionless.
+pub fn void_unplayed_hand_markets(lobby: &Pubkey, game: &Pubkey, hands: &[u64]) -> Instruction {
+    let mut instruction = betting(
+        poker_betting::accounts::VoidUnplayedHandMarkets {
+            lobby: *lobby,
+            engine_game: *game,
+        },
+        poker_betting::instruction::VoidUnplayedHandMarkets {},
+    );
+    instruction.accounts.extend(
+        hands
+            .iter()
+            .map(|hand| AccountMeta::new(pda::hand_market_pda(lobby, *hand), false)),
+    );
+    instruction
+}
+
+/// Pays or refunds `bettor`'s bet on hand `hand_number`.
+pub fn claim_hand_bet(lobby: &Pubkey, hand_number: u64, bettor: &Pubkey) -> Instruction {
+    let hand_market = pda::hand_market_pda(lobby, hand_number);
+    let accounts = poker_betting::accounts::ClaimHandBet {
+        lobby: *lobby,
+        hand_market,
+        hand_bet: pda::hand_bet_pda(&hand_market, bettor),
+        bettor: *bettor,
+        hand_escrow: pda::hand_escrow_pda(lobby),
+        system_program: system_program::ID,
+    };
+    betting(accounts, poker_betting::instruction::ClaimHandBet {})
+}
+
+/// Sweeps the pool nobody backed to win to the treasury; permissionless.
+pub fn sweep_unwon_pool(lobby: &Pubkey) -> Instruction {
+    sweep_unwon_pool_with_keeper(lobby, None)
+}
+
+/// `sweep_unwon_pool` cranked by the keeper of `keeper_authority`, if any.
+pub fn sweep_unwon_pool_with_keeper(
+    lobby: &Pubkey,
+    keeper_authority: Option<&Pubkey>,
+) -> Instruction {
+    let accounts = poker_betting::accounts::SweepUnwonPool {
+        lobby: *lobby,
+        snapshot: None,
+        escrow: pda::escrow_pda(lobby),
+        treasury: pda::treasury_pda(),
+        system_program: system_program::ID,
+        keeper: keeper_authority.map(pda::keeper_pda),
+        keeper_authority: keeper_authority.copied(),
+        config: keeper_authority.map(|_| pda::config_pda()),
+    };
+    betting(accounts, poker_betting::instruction::SweepUnwonPool {})
+}
+
+pub fn archive_lobby(lobby: &Pubkey, owner: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::ArchiveLobby {
+        lobby: *lobby,
+        config: pda::config_pda(),
+        owner: *owner,
+    };
+    betting(accounts, poker_betting::instruction::ArchiveLobby {})
+}
+
+/// Closes hand `hand_number` of `lobby`'s game, `game`, through the engine.
+pub fn close_hand_record(
+    lobby: &Pubkey,
+    authority: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+) -> Instruction {
+    let accounts = poker_betting::accounts::CloseHandRecord {
+        lobby: *lobby,
+        engine: engine_pda(),
+        engine_game: *game,
+        hand: hand_pda(game, hand_number),
+        authority: *authority,
+        engine_program: poker_engine::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::CloseHandRecord { hand_number },
+    )
+}
+
+/// The engine's own `close_hand`, with `lobby` signing as the game's lobby.
+pub fn close_hand(
+    authority: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+    lobby: &Pubkey,
+) -> Instruction {
+    let accounts = poker_engine::accounts::CloseHand {
+        engine: engine_pda(),
+        game: *game,
+        hand: hand_pda(game, hand_number),
+        lobby: *lobby,
+        authority: *authority,
+    };
+    let data = poker_engine::instruction::CloseHand {
+        _hand_number: hand_number,
+    };
+    engine(accounts, data)
+}
+
+pub fn post_bond(lobby: &Pubkey, owner: &Pubkey, amount: u64) -> Instruction {
+    let accounts = poker_betting::accounts::PostBond {
+        bond: pda::operator_bond_pda(lobby),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    betting(accounts, poker_betting::instruction::PostBond { amount })
+}
+
+pub fn slash_bond(lobby: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::SlashBond {
+        lobby: *lobby,
+        bond: pda::operator_bond_pda(lobby),
+        escrow: pda::escrow_pda(lobby),
+        system_program: system_program::ID,
+        keeper: None,
+        keeper_authority: None,
+        config: None,
+    };
+    betting(accounts, poker_betting::instruction::SlashBond {})
+}
+
+/// Claims `bettor`'s share of the slashed bond for their back bet.
+pub fn claim_bond_compensation(lobby: &Pubkey, bettor: &Pubkey) -> Instruction {
+    claim_bond_compensation_to(lobby, bettor, None)
+}
+
+/// `claim_bond_compensation` paid to the bet's payout address, if it has one.
+pub fn claim_bond_compensation_to(
+    lobby: &Pubkey,
+    bettor: &Pubkey,
+    payout_recipient: Option<&Pubkey>,
+) -> Instruction {
+    let bet = pda::bet_pda(lobby, bettor, &BetSide::Back);
+    let accounts = poker_betting::accounts::ClaimBondCompensation {
+        lobby: *lobby,
+        bet,
+        claim: pda::bond_claim_pda(&bet),
+        bettor: *bettor,
+        payout_recipient: payout_recipient.copied(),
+        escrow: pda::escrow_pda(lobby),
+        system_program: system_program::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::ClaimBondCompensation {},
+    )
+}
+
+/// Makes `authority` the engine's game server.
+pub fn initialize_engine(authority: &Pubkey) -> Instruction {
+    let accounts = poker_engine::accounts::InitializeEngine {
+        engine: engine_pda(),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    engine(accounts, poker_engine::instruction::InitializeEngine {})
+}
+
+/// Opens the engine's record of the game `owner`'s lobby `game_id` plays.
+pub fn create_game(
+    authority: &Pubkey,
+    owner: &Pubkey,
+    game_id: &str,
+    player_names: &[String],
+) -> Instruction {
+    let accounts = poker_engine::accounts::CreateGame {
+        engine: engine_pda(),
+        game: game_pda(owner, game_id),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    let data = poker_engine::instruction::CreateGame {
+        game_id: game_id.to_string(),
+        player_names: player_names.to_vec(),
+        owner: *owner,
+    };
+    engine(accounts, data)
+}
+
+/// The engine's record of hand `hand_number` of `game`.
+pub fn hand_pda(game: &Pubkey, hand_number: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"hand", game.as_ref(), &hand_number.to_le_bytes()],
+        &poker_engine::ID,
+    )
+    .0
+}
+
+/// Records hand `hand_number` of `game` as won by `winner_name`.
+pub fn record_hand(
+    authority: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+    winner_name: &str,
+) -> Instruction {
+    let accounts = poker_engine::accounts::RecordHand {
+        engine: engine_pda(),
+        game: *game,
+        hand: hand_pda(game, hand_number),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    let data = poker_engine::instruction::RecordHand {
+        hand_number,
+        pot: 0,
+        board: [0; 5],
+        winner_name: winner_name.to_string(),
+    };
+    engine(accounts, data)
+}
+
+pub fn finalize_game(authority: &Pubkey, game: &Pubkey, winner_name: &str) -> Instruction {
+    finalize_game_with_placings(authority, game, winner_name, &[])
+}
+
+/// `finalize_game` with the players' finishing order.
+pub fn finalize_game_with_placings(
+    authority: &Pubkey,
+    game: &Pubkey,
+    winner_name: &str,
+    placings: &[&str],
+) -> Instruction {
+    let accounts = poker_engine::accounts::FinalizeGame {
+        engine: engine_pda(),
+        game: *game,
+        authority: *authority,
+    };
+    let data = poker_engine::instruction::FinalizeGame {
+        winner_name: winner_name.to_string(),
+        placings: placings.iter().map(|player| player.to_string()).collect(),
+    };
+    engine(accounts, data)
+}
+
+/// The heads-up table of `game`.
+pub fn table_pda(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"table", game.as_ref()], &poker_engine::ID).0
+}
+
+pub fn create_table(
+    authority: &Pubkey,
+    game: &Pubkey,
+    players: [Pubkey; 2],
+    buy_in: u64,
+    blinds: (u64, u64),
+    turn_timeout: i64,
+) -> Instruction {
+    let accounts = poker_engine::accounts::CreateTable {
+        engine: engine_pda(),
+        game: *game,
+        table: table_pda(game),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    let data = poker_engine::instruction::CreateTable {
+        players,
+        buy_in,
+        small_blind: blinds.0,
+        big_blind: blinds.1,
+        turn_timeout,
+    };
+    engine(accounts, data)
+}
+
+pub fn join_table(game: &Pubkey, player: &Pubkey, seat: u8) -> Instruction {
+    let accounts = poker_engine::accounts::JoinTable {
+        table: table_pda(game),
+        player: *player,
+        system_program: system_program::ID,
+    };
+    engine(accounts, poker_engine::instruction::JoinTable { seat })
+}
+
+pub fn leave_table(game: &Pubkey, player: &Pubkey) -> Instruction {
+    let accounts = poker_engine::accounts::LeaveTable {
+        table: table_pda(game),
+        player: *player,
+    };
+    engine(accounts, poker_engine::instruction::LeaveTable {})
+}
+
+/// Starts hand `hand_number` of the table with these card commitments.
+pub fn start_hand(
+    authority: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+    hole_commitments: [[u8; 32]; 2],
+    board_commitment: [u8; 32],
+) -> Instruction {
+    let accounts = poker_engine::accounts::TableDeck {
+        engine: engine_pda(),
+        table: table_pda(game),
+        deck: deck_pda(game, hand_number),
+        authority: *authority,
+    };
+    let data = poker_engine::instruction::StartHand {
+        hole_commitments,
+        board_commitment,
+    };
+    engine(accounts, data)
+}
+
+/// Reveals hand `hand_number`'s cards at showdown.
+pub fn showdown(
+    authority: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+    hole_cards: [[u8; 2]; 2],
+    hole_salts: [[u8; 32]; 2],
+    board_salt: [u8; 32],
+) -> Instruction {
+    let accounts = poker_engine::accounts::TableDeck {
+        engine: engine_pda(),
+        table: table_pda(game),
+        deck: deck_pda(game, hand_number),
+        authority: *authority,
+    };
+    let data = poker_engine::instruction::Showdown {
+        hole_cards,
+        hole_salts,
+        board_salt,
+    };
+    engine(accounts, data)
+}
+
+/// The shuffled deck of hand `hand_number` at `game`'s table.
+pub fn deck_pda(game: &Pubkey, hand_number: u64) -> Pubkey {
+    let table = table_pda(game);
+    Pubkey::find_program_address(
+        &[b"deck", table.as_ref(), &hand_number.to_le_bytes()],
+        &poker_engine::ID,
+    )
+    .0
+}
+
+/// The deck `stage` of `deck` outputs.
+pub fn shuffle_stage_pda(deck: &Pubkey, stage: u8) -> Pubkey {
+    Pubkey::find_program_address(&[b"stage", deck.as_ref(), &[stage]], &poker_engine::ID).0
+}
+
+pub fn create_deck(
+    authority: &Pubkey,
+    game: &Pubkey,
+    hand_number: u64,
+    shufflers: &[Pubkey],
+) -> Instruction {
+    let accounts = poker_engine::accounts::CreateDeck {
+        engine: engine_pda(),
+        table: table_pda(game),
+        deck: deck_pda(game, hand_number),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    let data = poker_engine::instruction::CreateDeck {
+        hand_number,
+        shufflers: shufflers.to_vec(),
+    };
+    engine(accounts, data)
+}
+
+pub fn init_shuffle_stage(deck: &Pubkey, shuffler: &Pubkey, stage: u8) -> Instruction {
+    let accounts = poker_engine::accounts::InitShuffleStage {
+        deck: *deck,
+        output: shuffle_stage_pda(deck, stage),
+        shuffler: *shuffler,
+        system_program: system_program::ID,
+    };
+    engine(
+        accounts,
+        poker_engine::instruction::InitShuffleStage { stage },
+    )
+}
+
+pub fn write_shuffle_chunk(
+    deck: &Pubkey,
+    shuffler: &Pubkey,
+    stage: u8,
+    offset: u8,
+    cards: &[Card],
+) -> Instruction {
+    let accounts = poker_engine::accounts::WriteShuffleStage {
+        deck: *deck,
+        output: shuffle_stage_pda(deck, stage),
+        shuffler: *shuffler,
+    };
+    let data = poker_engine::instruction::WriteShuffleChunk {
+        offset,
+        cards: cards.to_vec(),
+    };
+    engine(accounts, data)
+}
+
+pub fn seal_shuffle(
+    deck: &Pubkey,
+    shuffler: &Pubkey,
+    stage: u8,
+    commitment: [u8; 32],
+) -> Instruction {
+    let accounts = poker_engine::accounts::WriteShuffleStage {
+        deck: *deck,
+        output: shuffle_stage_pda(deck, stage),
+        shuffler: *shuffler,
+    };
+    engine(
+        accounts,
+        poker_engine::instruction::SealShuffle { commitment },
+    )
+}
+
+pub fn verify_shuffle_proof(
+    deck: &Pubkey,
+    caller: &Pubkey,
+    stage: u8,
+    key: Card,
+    permutation: &[u8],
+    salt: [u8; 32],
+) -> Instruction {
+    let accounts = poker_engine::accounts::VerifyShuffleProof {
+        deck: *deck,
+        input: (stage > 1).then(|| shuffle_stage_pda(deck, stage - 1)),
+        output: shuffle_stage_pda(deck, stage),
+        caller: *caller,
+    };
+    let data = poker_engine::instruction::VerifyShuffleProof {
+        stage,
+        key,
+        permutation: permutation.to_vec(),
+        salt,
+    };
+    engine(accounts, data)
+}
+
+pub fn deal_street(authority: &Pubkey, game: &Pubkey, cards: &[u8]) -> Instruction {
+    let accounts = poker_engine::accounts::TableAuthority {
+        engine: engine_pda(),
+        table: table_pda(game),
+        authority: *authority,
+    };
+    let data = poker_engine::instruction::DealStreet {
+        cards: cards.to_vec(),
+    };
+    engine(accounts, data)
+}
+
+pub fn act(game: &Pubkey, player: &Pubkey, action: PlayerAction) -> Instruction {
+    let accounts = poker_engine::accounts::Act {
+        table: table_pda(game),
+        player: *player,
+    };
+    engine(accounts, poker_engine::instruction::Act { action })
+}
+
+pub fn claim_timeout(game: &Pubkey, caller: &Pubkey) -> Instruction {
+    let accounts = poker_engine::accounts::ClaimTimeout {
+        table: table_pda(game),
+        caller: *caller,
+    };
+    engine(accounts, poker_engine::instruction::ClaimTimeout {})
+}
+
+pub fn finalize_table_game(game: &Pubkey, caller: &Pubkey) -> Instruction {
+    let accounts = poker_engine::accounts::FinalizeTableGame {
+        table: table_pda(game),
+        game: *game,
+        caller: *caller,
+    };
+    engine(accounts, poker_engine::instruction::FinalizeTableGame {})
+}
+
+pub fn register_model_operator(
+    lobby: &Pubkey,
+    owner: &Pubkey,
+    model_name: &str,
+    operator: &Pubkey,
+    stake_amount: u64,
+) -> Instruction {
+    let accounts = poker_betting::accounts::RegisterModelOperator {
+        lobby: *lobby,
+        match_stake: pda::match_stake_pda(lobby, model_name),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    let data = poker_betting::instruction::RegisterModelOperator {
+        model_name: model_name.to_string(),
+        operator: *operator,
+        stake_amount,
+    };
+    betting(accounts, data)
+}
+
+pub fn post_match_stake(lobby: &Pubkey, model_name: &str, operator: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::PostMatchStake {
+        lobby: *lobby,
+        match_stake: pda::match_stake_pda(lobby, model_name),
+        escrow: pda::escrow_pda(lobby),
+        operator: *operator,
+        system_program: system_program::ID,
+    };
+    let data = poker_betting::instruction::PostMatchStake {
+        model_name: model_name.to_string(),
+    };
+    betting(accounts, data)
+}
+
+pub fn refund_match_stake(
+    lobby: &Pubkey,
+    owner: &Pubkey,
+    model_name: &str,
+    operator: &Pubkey,
+) -> Instruction {
+    let accounts = poker_betting::accounts::RefundMatchStake {
+        lobby: *lobby,
+        match_stake: pda::match_stake_pda(lobby, model_name),
+        escrow: pda::escrow_pda(lobby),
+        operator: *operator,
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    let data = poker_betting::instruction::RefundMatchStake {
+        _model_name: model_name.to_string(),
+    };
+    betting(accounts, data)
+}
+
+pub fn register_keeper(authority: &Pubkey, stake: u64) -> Instruction {
+    let accounts = poker_betting::accounts::RegisterKeeper {
+        keeper: pda::keeper_pda(authority),
+        authority: *authority,
+        config: pda::config_pda(),
+        system_program: system_program::ID,
+    };
+    betting(
+        accounts,
+        poker_betting::instruction::RegisterKeeper { stake },
+    )
+}
+
+pub fn commit_keeper(authority: &Pubkey, lobby: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::CommitKeeper {
+        keeper: pda::keeper_pda(authority),
+        authority: *authority,
+        lobby: *lobby,
+        config: pda::config_pda(),
+    };
+    betting(accounts, poker_betting::instruction::CommitKeeper {})
+}
+
+/// Slashes up to `amount` of the stake of `keeper_authority`'s keeper.
+pub fn slash_keeper(admin: &Pubkey, keeper_authority: &Pubkey, amount: u64) -> Instruction {
+    let accounts = poker_betting::accounts::SlashKeeper {
+        config: pda::config_pda(),
+        admin: *admin,
+        keeper: pda::keeper_pda(keeper_authority),
+        treasury: pda::treasury_pda(),
+        system_program: system_program::ID,
+    };
+    betting(accounts, poker_betting::instruction::SlashKeeper { amount })
+}
@@ -0,0 +1,32 @@
+//! Simulation sandbox for testing the betting program, time-dependent
+//! features above all.
+//!
+//! `Sandbox` runs the program's entrypoint in-process against accounts held
+//! in memory, alongside the poker engine whose game records it settles from. Its clock only moves when told to, with `warp` and `warp_to`,
+//! so deadlines, timelocks and unbonding periods can be stepped over without
+//! waiting on a validator. The system program instructions the betting
+//! program invokes are applied directly; invoking any other program fails.
+//!
+//! `ix` builds the instructions tests send, and `Scenario` builds the usual
+//! starting points on top of it:
+//!
+//! ```no_run
+//! use poker_betting_sandbox::Scenario;
+//!
+//! let mut scenario = Scenario::lobby().with_bets(10).past_deadline().build()?;
+//! scenario.sandbox.warp(60);
+//! # Ok::<(), poker_betting_sandbox::Error>(())
+//! ```
+//!
+//! Transactions are atomic and check signers, writability and that lamports
+//! balance, but charge no fees, meter no compute and don't enforce rent
+//! exemption or account ownership.
+
+pub mod ix;
+mod sandbox;
+mod scenario;
+mod stubs;
+mod system;
+
+pub use sandbox::{Error, Sandbox, START_TIMESTAMP};
+pub use scenario::{LobbyScenario, Scenario};
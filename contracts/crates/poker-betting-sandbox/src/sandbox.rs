@@ -0,0 +1,391 @@
+//! Accounts held in memory and a clock that only moves when told to.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use poker_betting::{pda, BettingError};
+use poker_engine::EngineError;
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::Clock;
+use solana_sdk::entrypoint::{self, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_loader;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+use crate::{stubs, system};
+
+/// Unix time the clock starts at.
+pub const START_TIMESTAMP: i64 = 1_700_000_000;
+const SLOT_DURATION_MS: i64 = 400;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Program(#[from] ProgramError),
+    #[error("{0} must sign")]
+    MissingSignature(Pubkey),
+    #[error("no program {0} in the sandbox")]
+    UnknownProgram(Pubkey),
+    #[error("read-only account {0} was modified")]
+    ReadonlyModified(Pubkey),
+    #[error("instruction changed the total lamports")]
+    UnbalancedInstruction,
+}
+
+impl From<BettingError> for Error {
+    fn from(err: BettingError) -> Self {
+        Self::Program(ProgramError::Custom(err.into()))
+    }
+}
+
+impl From<EngineError> for Error {
+    fn from(err: EngineError) -> Self {
+        Self::Program(ProgramError::Custom(err.into()))
+    }
+}
+
+/// An account in the serialized input: where its lamports sit, and whether
+/// the instruction may write it.
+struct Serialized {
+    key: Pubkey,
+    offset: usize,
+    is_writable: bool,
+}
+
+/// Lays `accounts` out the way the runtime passes them to a program, in a
+/// buffer aligned for `entrypoint::deserialize`.
+fn serialize(
+    instruction: &Instruction,
+    accounts: &HashMap<Pubkey, Account>,
+) -> (Vec<u64>, Vec<Serialized>) {
+    let mut bytes = Vec::new();
+    let mut serialized: Vec<Serialized> = Vec::new();
+    bytes.extend_from_slice(&(instruction.accounts.len() as u64).to_le_bytes());
+    for (position, meta) in instruction.accounts.iter().enumerate() {
+        let first = instruction.accounts[..position]
+            .iter()
+            .position(|earlier| earlier.pubkey == meta.pubkey);
+        if let Some(first) = first {
+            bytes.push(first as u8);
+            bytes.extend_from_slice(&[0; 7]);
+            continue;
+        }
+        let (is_signer, is_writable) = instruction
+            .accounts
+            .iter()
+            .filter(|other| other.pubkey == meta.pubkey)
+            .fold((false, false), |(signer, writable), other| {
+                (signer || other.is_signer, writable || other.is_writable)
+            });
+        let account = accounts.get(&meta.pubkey).cloned().unwrap_or_default();
+        bytes.extend_from_slice(&[
+            NON_DUP_MARKER,
+            is_signer as u8,
+            is_writable as u8,
+            account.executable as u8,
+        ]);
+        bytes.extend_from_slice(&(account.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(meta.pubkey.as_ref());
+        bytes.extend_from_slice(account.owner.as_ref());
+        serialized.push(Serialized {
+            key: meta.pubkey,
+            offset: bytes.len(),
+            is_writable,
+        });
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(size_of::<u64>()), 0);
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&instruction.data);
+    bytes.extend_from_slice(instruction.program_id.as_ref());
+
+    let mut buffer = vec![0u64; bytes.len().div_ceil(size_of::<u64>())];
+    for (word, chunk) in buffer.iter_mut().zip(bytes.chunks(size_of::<u64>())) {
+        let mut le = [0; size_of::<u64>()];
+        le[..chunk.len()].copy_from_slice(chunk);
+        *word = u64::from_le_bytes(le);
+    }
+    (buffer, serialized)
+}
+
+/// Reads back the account `serialize` laid out at `offset`, which was
+/// `previous` before the instruction.
+fn read_account(bytes: &[u8], offset: usize, previous: &Account) -> Account {
+    let word = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+    let data_len = word(offset + 8) as usize;
+    Account {
+        lamports: word(offset),
+        data: bytes[offset + 16..offset + 16 + data_len].to_vec(),
+        owner: Pubkey::try_from(&bytes[offset - 32..offset]).unwrap(),
+        executable: previous.executable,
+        rent_epoch: previous.rent_epoch,
+    }
+}
+
+/// The betting program with its accounts in memory.
+pub struct Sandbox {
+    accounts: HashMap<Pubkey, Account>,
+    clock: Clock,
+    /// Logs of the last transaction, in the runtime's format
+    logs: Vec<String>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sandbox {
+    /// A sandbox holding only the system, betting and engine programs, its
+    /// clock at `START_TIMESTAMP`. The betting program has no upgrade
+    /// authority until `set_upgrade_authority` gives it one.
+    pub fn new() -> Self {
+        stubs::install();
+        let program = |owner, data| Account {
+            lamports: 1,
+            data,
+            owner,
+            executable: true,
+            rent_epoch: u64::MAX,
+        };
+        let loader_state = UpgradeableLoaderState::Program {
+            programdata_address: pda::program_data(),
+        };
+        let accounts = HashMap::from([
+            (system_program::ID, program(native_loader::ID, Vec::new())),
+            (
+                poker_betting::ID,
+                program(
+                    bpf_loader_upgradeable::ID,
+                    bincode::serialize(&loader_state).unwrap(),
+                ),
+            ),
+            (
+                poker_engine::ID,
+                program(bpf_loader_upgradeable::ID, Vec::new()),
+            ),
+        ]);
+        let mut sandbox = Self {
+            accounts,
+            clock: Clock {
+                slot: 1,
+                unix_timestamp: START_TIMESTAMP,
+                epoch_start_timestamp: START_TIMESTAMP,
+                ..Clock::default()
+            },
+            logs: Vec::new(),
+        };
+        sandbox.set_upgrade_authority(None);
+        sandbox
+    }
+
+    /// Makes `authority` the betting program's upgrade authority, or freezes
+    /// the program with `None`.
+    pub fn set_upgrade_authority(&mut self, authority: Option<Pubkey>) {
+        let program_data = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: authority,
+        };
+        self.accounts.insert(
+            pda::program_data(),
+            Account {
+                lamports: 1,
+                data: bincode::serialize(&program_data).unwrap(),
+                owner: bpf_loader_upgradeable::ID,
+                executable: false,
+                rent_epoch: u64::MAX,
+            },
+        );
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    /// The program account at `address`, if it holds a `T`.
+    pub fn get<T: AccountDeserialize>(&self, address: &Pubkey) -> Option<T> {
+        let account = self.accounts.get(address)?;
+        T::try_deserialize(&mut &account.data[..]).ok()
+    }
+
+    /// Rewrites the `T` held at `address` in place, for states no instruction
+    /// sequence reaches quickly. The account keeps its size.
+    pub fn modify<T: AccountDeserialize + AccountSerialize>(
+        &mut self,
+        address: &Pubkey,
+        change: impl FnOnce(&mut T),
+    ) {
+        let account = self.accounts.get_mut(address).expect("no such account");
+        let mut value = T::try_deserialize(&mut &account.data[..]).expect("not a T");
+        change(&mut value);
+        let mut data = Vec::with_capacity(account.data.len());
+        value.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |account| account.lamports)
+    }
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    /// Credits `address` with `lamports` out of thin air.
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        self.accounts
+            .entry(*address)
+            .or_insert_with(|| Account::new(0, 0, &system_program::ID))
+            .lamports += lamports;
+    }
+
+    /// A new keypair holding `lamports`.
+    pub fn funded_keypair(&mut self, lamports: u64) -> Keypair {
+        let keypair = Keypair::new();
+        self.airdrop(&keypair.pubkey(), lamports);
+        keypair
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn now(&self) -> i64 {
+        self.clock.unix_timestamp
+    }
+
+    /// Moves the clock forward to `unix_timestamp`, and the slot with it.
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        let elapsed = unix_timestamp - self.clock.unix_timestamp;
+        assert!(elapsed >= 0, "the clock only moves forward");
+        self.clock.unix_timestamp = unix_timestamp;
+        self.clock.slot += (elapsed * 1_000 / SLOT_DURATION_MS) as u64;
+    }
+
+    /// Moves the clock forward by `seconds`.
+    pub fn warp(&mut self, seconds: i64) {
+        self.warp_to(self.clock.unix_timestamp + seconds);
+    }
+
+    /// Logs of the last transaction, which `poker_betting_events::parse_logs`
+    /// decodes events from.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    pub fn execute(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Result<(), Error> {
+        self.process(&[instruction], signers)
+    }
+
+    /// Runs `instructions` as one transaction signed by `signers`: all of
+    /// them apply, or, if one fails, none.
+    pub fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), Error> {
+        stubs::set_clock(&self.clock);
+        stubs::take_logs();
+        let signers: Vec<Pubkey> = signers.iter().map(|signer| signer.pubkey()).collect();
+        let mut accounts = self.accounts.clone();
+        let result = instructions
+            .iter()
+            .try_for_each(|instruction| Self::run(&mut accounts, instruction, &signers));
+        self.logs = stubs::take_logs();
+        if result.is_ok() {
+            self.accounts = accounts;
+        }
+        result
+    }
+
+    fn run(
+        accounts: &mut HashMap<Pubkey, Account>,
+        instruction: &Instruction,
+        signers: &[Pubkey],
+    ) -> Result<(), Error> {
+        let program_id = instruction.program_id;
+        if !accounts
+            .get(&program_id)
+            .is_some_and(|program| program.executable)
+        {
+            return Err(Error::UnknownProgram(program_id));
+        }
+        if let Some(meta) = instruction
+            .accounts
+            .iter()
+            .find(|meta| meta.is_signer && !signers.contains(&meta.pubkey))
+        {
+            return Err(Error::MissingSignature(meta.pubkey));
+        }
+
+        let (mut buffer, serialized) = serialize(instruction, accounts);
+        stubs::log(format!("Program {program_id} invoke [1]"));
+        stubs::set_program(program_id);
+        let result = {
+            // SAFETY: `serialize` laid the buffer out as the runtime does, and
+            // it outlives the account infos pointing into it
+            let (program_id, infos, data) =
+                unsafe { entrypoint::deserialize(buffer.as_mut_ptr() as *mut u8) };
+            if *program_id == poker_betting::ID {
+                poker_betting::entry(program_id, &infos, data)
+            } else if *program_id == poker_engine::ID {
+                poker_engine::entry(program_id, &infos, data)
+            } else if *program_id == system_program::ID {
+                system::process(instruction, &infos)
+            } else {
+                Err(ProgramError::IncorrectProgramId)
+            }
+        };
+        match &result {
+            Ok(()) => stubs::log(format!("Program {program_id} success")),
+            Err(err) => stubs::log(format!("Program {program_id} failed: {err}")),
+        }
+        result?;
+
+        // SAFETY: plain bytes of the buffer, no account info points into
+        // it anymore
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                buffer.as_ptr() as *const u8,
+                buffer.len() * size_of::<u64>(),
+            )
+        };
+        let mut before = 0u128;
+        let mut after = 0u128;
+        let mut updated = Vec::with_capacity(serialized.len());
+        for account in &serialized {
+            let previous = accounts.get(&account.key).cloned().unwrap_or_default();
+            let current = read_account(bytes, account.offset, &previous);
+            if !account.is_writable && current != previous {
+                return Err(Error::ReadonlyModified(account.key));
+            }
+            before += previous.lamports as u128;
+            after += current.lamports as u128;
+            updated.push((account.key, current));
+        }
+        if before != after {
+            return Err(Error::UnbalancedInstruction);
+        }
+        for (key, account) in updated {
+            // Accounts drained of lamports are gone, as on chain
+            if account.lamports == 0 {
+                accounts.remove(&key);
+            } else {
+                accounts.insert(key, account);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+//! Builders for the states tests start from.
+
+use poker_betting::{pda, BetSide, Lobby};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::ix;
+use crate::{Error, Sandbox};
+
+const DEFAULT_MODELS: [&str; 2] = ["model-a", "model-b"];
+/// Lamports each bet stakes unless `with_stake` says otherwise.
+const DEFAULT_STAKE: u64 = LAMPORTS_PER_SOL / 10;
+/// Betting deadline, after the lobby is created, that `past_deadline` sets
+/// when `with_deadline` hasn't.
+const DEFAULT_DEADLINE: i64 = 60 * 60;
+const STARTING_CHIPS: u64 = 1_000;
+const MAX_HANDS: u64 = 100;
+/// Lamports every keypair a scenario makes starts with.
+const FUNDING: u64 = 10 * LAMPORTS_PER_SOL;
+
+/// A sandbox set up by a builder, with the keys and addresses it used.
+pub struct Scenario {
+    pub sandbox: Sandbox,
+    pub admin: Keypair,
+    /// Lobby owner, also the engine's game server
+    pub owner: Keypair,
+    pub game_id: String,
+    pub lobby: Pubkey,
+    /// One per bet, in the order they bet
+    pub bettors: Vec<Keypair>,
+}
+
+impl Scenario {
+    /// A lobby under a freshly initialized config, created by a fresh owner,
+    /// and the engine's record of its game.
+    pub fn lobby() -> LobbyScenario {
+        LobbyScenario {
+            game_id: "game-1".to_string(),
+            models: DEFAULT_MODELS.map(str::to_string).to_vec(),
+            fee_bps: 0,
+            bets: 0,
+            stake: DEFAULT_STAKE,
+            deadline: None,
+            past_deadline: false,
+        }
+    }
+
+    pub fn lobby_account(&self) -> Lobby {
+        self.sandbox
+            .get(&self.lobby)
+            .expect("scenario lobby is missing")
+    }
+
+    /// The engine's record of the lobby's game.
+    pub fn game(&self) -> Pubkey {
+        ix::game_pda(&self.owner.pubkey(), &self.game_id)
+    }
+
+    /// The back bet `bettors[index]` placed.
+    pub fn bet(&self, index: usize) -> Pubkey {
+        pda::bet_pda(&self.lobby, &self.bettors[index].pubkey(), &BetSide::Back)
+    }
+
+    /// Finalizes the engine's game with `winner` and finishes the lobby on it.
+    pub fn finish(&mut self, winner: &str) -> Result<(), Error> {
+        let owner = self.owner.pubkey();
+        self.sandbox.process(
+            &[
+                ix::finalize_game(&owner, &self.game(), winner),
+                ix::finish_game(&self.lobby, &owner, &self.game_id),
+            ],
+            &[&self.owner],
+        )
+    }
+}
+
+#[must_use]
+pub struct LobbyScenario {
+    game_id: String,
+    models: Vec<String>,
+    fee_bps: u16,
+    bets: usize,
+    stake: u64,
+    /// Seconds after creation
+    deadline: Option<i64>,
+    past_deadline: bool,
+}
+
+impl LobbyScenario {
+    pub fn with_game_id(mut self, game_id: &str) -> Self {
+        self.game_id = game_id.to_string();
+        self
+    }
+
+    pub fn with_models(mut self, models: &[&str]) -> Self {
+        self.models = models.iter().map(|model| model.to_string()).collect();
+        self
+    }
+
+    pub fn with_fee_bps(mut self, fee_bps: u16) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+
+    /// Places `bets` back bets from as many bettors, spread over the models
+    /// in turn.
+    pub fn with_bets(mut self, bets: usize) -> Self {
+        self.bets = bets;
+        self
+    }
+
+    /// Lamports each bet stakes.
+    pub fn with_stake(mut self, stake: u64) -> Self {
+        self.stake = stake;
+        self
+    }
+
+    /// Closes betting `seconds` after the lobby is created.
+    pub fn with_deadline(mut self, seconds: i64) -> Self {
+        self.deadline = Some(seconds);
+        self
+    }
+
+    /// Warps the clock past the betting deadline once the bets are in.
+    pub fn past_deadline(mut self) -> Self {
+        self.past_deadline = true;
+        self
+    }
+
+    pub fn build(self) -> Result<Scenario, Error> {
+        let mut sandbox = Sandbox::new();
+        let admin = sandbox.funded_keypair(FUNDING);
+        let owner = sandbox.funded_keypair(FUNDING);
+        let lobby = pda::lobby_pda(&owner.pubkey(), &self.game_id);
+
+        sandbox.set_upgrade_authority(Some(admin.pubkey()));
+        sandbox.execute(
+            ix::initialize_config(&admin.pubkey(), self.fee_bps),
+            &[&admin],
+        )?;
+        sandbox.process(
+            &[
+                ix::create_lobby(
+                    &owner.pubkey(),
+                    &self.game_id,
+                    &self.models,
+                    STARTING_CHIPS,
+                    MAX_HANDS,
+                ),
+                ix::initialize_engine(&owner.pubkey()),
+                ix::create_game(
+                    &owner.pubkey(),
+                    &owner.pubkey(),
+                    &self.game_id,
+                    &self.models,
+                ),
+            ],
+            &[&owner],
+        )?;
+        let deadline = match (self.deadline, self.past_deadline) {
+            (Some(seconds), _) => Some(sandbox.now() + seconds),
+            (None, true) => Some(sandbox.now() + DEFAULT_DEADLINE),
+            (None, false) => None,
+        };
+        if let Some(deadline) = deadline {
+            sandbox.execute(
+                ix::set_betting_deadline(&lobby, &owner.pubkey(), deadline),
+                &[&owner],
+            )?;
+        }
+
+        let mut bettors = Vec::with_capacity(self.bets);
+        for index in 0..self.bets {
+            let bettor = sandbox.funded_keypair(FUNDING);
+            let model = &self.models[index % self.models.len()];
+            sandbox.execute(
+                ix::place_bet(&lobby, &bettor.pubkey(), model, self.stake),
+                &[&bettor],
+            )?;
+            bettors.push(bettor);
+        }
+        if let (Some(deadline), true) = (deadline, self.past_deadline) {
+            sandbox.warp_to(deadline.max(sandbox.now()) + 1);
+        }
+
+        Ok(Scenario {
+            sandbox,
+            admin,
+            owner,
+            game_id: self.game_id,
+            lobby,
+            bettors,
+        })
+    }
+}
@@ -0,0 +1,170 @@
+//! Answers the syscalls the program makes off-chain, from the state of the
+//! sandbox running on the calling thread.
+
+use std::cell::RefCell;
+use std::sync::Once;
+
+use base64::Engine;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::clock::Clock;
+use solana_sdk::entrypoint::{ProgramResult, SUCCESS};
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::program_stubs::{self, SyscallStubs};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::system_program;
+
+use crate::system;
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    /// Program the top-level instruction executing on this thread calls
+    static PROGRAM: RefCell<Pubkey> = RefCell::new(Pubkey::default());
+    /// Logs of the transaction executing on this thread
+    static LOGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+static INSTALL: Once = Once::new();
+
+/// Routes the program's syscalls here; stubs are process-wide, so once.
+pub fn install() {
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(Stubs));
+    });
+}
+
+pub fn set_clock(clock: &Clock) {
+    CLOCK.with(|current| *current.borrow_mut() = clock.clone());
+}
+
+pub fn set_program(program_id: Pubkey) {
+    PROGRAM.with(|program| *program.borrow_mut() = program_id);
+}
+
+pub fn log(line: String) {
+    LOGS.with(|logs| logs.borrow_mut().push(line));
+}
+
+pub fn take_logs() -> Vec<String> {
+    LOGS.with(|logs| logs.take())
+}
+
+/// Checks `instruction` asks no more of its accounts than the caller holds:
+/// signers must have signed or be the caller's PDAs signed for by
+/// `signers_seeds`, and writable accounts must be writable.
+fn check_privileges(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let caller = PROGRAM.with(|program| *program.borrow());
+    let signed_for: Vec<Pubkey> = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<_, _>>()?;
+    for meta in &instruction.accounts {
+        let info = account_infos
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if meta.is_signer && !info.is_signer && !signed_for.contains(&meta.pubkey) {
+            log(format!("sandbox: {} must sign", meta.pubkey));
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if meta.is_writable && !info.is_writable {
+            log(format!("sandbox: {} is not writable", meta.pubkey));
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the engine on `instruction`'s accounts, with the privileges the
+/// instruction grants them, as the program it is called from.
+fn invoke_engine(instruction: &Instruction, account_infos: &[AccountInfo]) -> ProgramResult {
+    let infos = instruction
+        .accounts
+        .iter()
+        .map(|meta| {
+            let mut info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            Ok(info)
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+    // The entrypoint wants the infos to live as long as the accounts they
+    // point into; leaked, as the sandbox only runs for a test
+    let infos = Box::leak(infos.into_boxed_slice());
+
+    let caller = PROGRAM.with(|program| *program.borrow());
+    set_program(poker_engine::ID);
+    let result = poker_engine::entry(&poker_engine::ID, infos, &instruction.data);
+    set_program(caller);
+    result
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        log(format!("Program log: {message}"));
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|field| base64::engine::general_purpose::STANDARD.encode(field))
+            .collect();
+        log(format!("Program data: {}", fields.join(" ")));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let program_id = instruction.program_id;
+        log(format!("Program {program_id} invoke [2]"));
+        let result = if program_id == system_program::ID {
+            check_privileges(instruction, account_infos, signers_seeds)
+                .and_then(|()| system::process(instruction, account_infos))
+        } else if program_id == poker_engine::ID {
+            check_privileges(instruction, account_infos, signers_seeds)
+                .and_then(|()| invoke_engine(instruction, account_infos))
+        } else {
+            log(format!("sandbox: invoking {program_id} is not supported"));
+            Err(ProgramError::IncorrectProgramId)
+        };
+        match &result {
+            Ok(()) => log(format!("Program {program_id} success")),
+            Err(err) => log(format!("Program {program_id} failed: {err}")),
+        }
+        result
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CLOCK.with(|clock| clock.borrow().clone());
+        // SAFETY: the program passes a pointer to a `Clock` to fill in
+        unsafe { (var_addr as *mut Clock).write(clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // SAFETY: as above, for `Rent`
+        unsafe { (var_addr as *mut Rent).write(Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // SAFETY: as above, for `EpochSchedule`
+        unsafe { (var_addr as *mut EpochSchedule).write(EpochSchedule::default()) };
+        SUCCESS
+    }
+}
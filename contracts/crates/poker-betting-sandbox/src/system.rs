@@ -0,0 +1,105 @@
+//! The system program instructions the betting program uses, applied to the
+//! accounts directly.
+
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::{SystemError, SystemInstruction, MAX_PERMITTED_DATA_LENGTH};
+use solana_sdk::system_program;
+
+use crate::stubs::log;
+
+fn system_error(err: SystemError) -> ProgramError {
+    ProgramError::Custom(err as u32)
+}
+
+/// Whether the system program may still hand `account` over: it owns it and
+/// it holds no data.
+fn is_unused(account: &AccountInfo) -> bool {
+    *account.owner == system_program::ID && account.data_is_empty()
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !is_unused(from) {
+        log(format!(
+            "sandbox: the system program can't debit {}",
+            from.key
+        ));
+        return Err(ProgramError::InvalidArgument);
+    }
+    let balance = from.lamports();
+    if balance < lamports {
+        return Err(system_error(SystemError::ResultWithNegativeLamports));
+    }
+    **from.try_borrow_mut_lamports()? = balance - lamports;
+    let credited = to
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **to.try_borrow_mut_lamports()? = credited;
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !is_unused(account) {
+        return Err(system_error(SystemError::AccountAlreadyInUse));
+    }
+    if space > MAX_PERMITTED_DATA_LENGTH {
+        return Err(system_error(SystemError::InvalidAccountDataLength));
+    }
+    account.realloc(space as usize, true)
+}
+
+fn assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner == owner {
+        return Ok(());
+    }
+    if *account.owner != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    account.assign(owner);
+    Ok(())
+}
+
+/// Applies `instruction`, whose accounts must all be among `accounts`.
+pub fn process(instruction: &Instruction, accounts: &[AccountInfo]) -> ProgramResult {
+    let account = |index: usize| {
+        let key = instruction
+            .accounts
+            .get(index)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?
+            .pubkey;
+        accounts
+            .iter()
+            .find(|info| *info.key == key)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+    let decoded = bincode::deserialize(&instruction.data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    match decoded {
+        SystemInstruction::Transfer { lamports } => transfer(account(0)?, account(1)?, lamports),
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let to = account(1)?;
+            if to.lamports() > 0 {
+                return Err(system_error(SystemError::AccountAlreadyInUse));
+            }
+            allocate(to, space)?;
+            assign(to, &owner)?;
+            transfer(account(0)?, to, lamports)
+        }
+        SystemInstruction::Allocate { space } => allocate(account(0)?, space),
+        SystemInstruction::Assign { owner } => assign(account(0)?, &owner),
+        other => {
+            log(format!(
+                "sandbox: system instruction {other:?} is not supported"
+            ));
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
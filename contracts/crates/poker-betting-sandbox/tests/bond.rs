@@ -0,0 +1,115 @@
+//! Slashing an operator's bond and sharing it among the lobby's bettors.
+
+use poker_betting::{pda, BettingError, OperatorBond};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+const BOND: u64 = LAMPORTS_PER_SOL;
+const STAKE: u64 = LAMPORTS_PER_SOL / 10;
+
+/// A lobby with two bets whose bonded owner missed its settlement deadline
+/// and was slashed.
+fn slashed() -> Scenario {
+    let mut scenario = Scenario::lobby()
+        .with_bets(2)
+        .with_stake(STAKE)
+        .build()
+        .unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    scenario
+        .sandbox
+        .execute(ix::post_bond(&lobby, &owner, BOND), &[&scenario.owner])
+        .unwrap();
+    let settle_by = scenario.sandbox.now() + 60;
+    scenario.sandbox.modify(
+        &pda::operator_bond_pda(&lobby),
+        |bond: &mut OperatorBond| bond.settle_by = settle_by,
+    );
+    scenario.sandbox.warp(60);
+    scenario
+        .sandbox
+        .execute(ix::slash_bond(&lobby), &[])
+        .unwrap();
+    scenario
+}
+
+#[test]
+fn each_bet_claims_its_share_once() {
+    let mut scenario = slashed();
+    let lobby = scenario.lobby;
+    let bettor = &scenario.bettors[0];
+    let before = scenario.sandbox.lamports(&bettor.pubkey());
+    scenario
+        .sandbox
+        .execute(
+            ix::claim_bond_compensation(&lobby, &bettor.pubkey()),
+            &[bettor],
+        )
+        .unwrap();
+    let claim_rent = scenario
+        .sandbox
+        .lamports(&pda::bond_claim_pda(&scenario.bet(0)));
+    // Half the stake, so half the bond
+    assert_eq!(
+        scenario.sandbox.lamports(&bettor.pubkey()) + claim_rent - before,
+        BOND / 2
+    );
+
+    let result = scenario.sandbox.execute(
+        ix::claim_bond_compensation(&lobby, &bettor.pubkey()),
+        &[bettor],
+    );
+    assert!(result.is_err());
+    assert_eq!(scenario.lobby_account().compensation_paid, BOND / 2);
+}
+
+#[test]
+fn compensation_goes_to_the_payout_address() {
+    let mut scenario = slashed();
+    let lobby = scenario.lobby;
+    let bettor = &scenario.bettors[0];
+    let cold_wallet = Pubkey::new_unique();
+    scenario
+        .sandbox
+        .execute(
+            ix::set_payout_address(&lobby, &bettor.pubkey(), Some(cold_wallet)),
+            &[bettor],
+        )
+        .unwrap();
+
+    let result = scenario.sandbox.execute(
+        ix::claim_bond_compensation(&lobby, &bettor.pubkey()),
+        &[bettor],
+    );
+    assert_eq!(result, Err(BettingError::InvalidPayoutRecipient.into()));
+    scenario
+        .sandbox
+        .execute(
+            ix::claim_bond_compensation_to(&lobby, &bettor.pubkey(), Some(&cold_wallet)),
+            &[bettor],
+        )
+        .unwrap();
+    assert_eq!(scenario.sandbox.lamports(&cold_wallet), BOND / 2);
+}
+
+#[test]
+fn bets_placed_after_the_slash_are_not_compensated() {
+    let mut scenario = slashed();
+    let lobby = scenario.lobby;
+    let late = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    scenario.sandbox.warp(1);
+    scenario
+        .sandbox
+        .execute(
+            ix::place_bet(&lobby, &late.pubkey(), "model-a", STAKE),
+            &[&late],
+        )
+        .unwrap();
+    let result = scenario.sandbox.execute(
+        ix::claim_bond_compensation(&lobby, &late.pubkey()),
+        &[&late],
+    );
+    assert_eq!(result, Err(BettingError::NotCompensable.into()));
+}
@@ -0,0 +1,30 @@
+//! The guard against bets composed with settlement in one transaction.
+
+use poker_betting::BettingError;
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::signature::Signer;
+
+#[test]
+fn every_way_of_betting_needs_the_instructions_sysvar_under_the_guard() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (admin, lobby) = (scenario.admin.pubkey(), scenario.lobby);
+    let bettor = scenario.sandbox.funded_keypair(1_000_000_000);
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::set_bet_composition_guard(&admin, true),
+                ix::open_player_pool(&lobby, &admin, 0),
+            ],
+            &[&scenario.admin],
+        )
+        .unwrap();
+
+    for instruction in [
+        ix::place_bet(&lobby, &bettor.pubkey(), "model-a", 1_000_000),
+        ix::place_pooled_bet(&lobby, &bettor.pubkey(), 0, 1_000_000),
+    ] {
+        let result = scenario.sandbox.execute(instruction, &[&bettor]);
+        assert_eq!(result, Err(BettingError::InstructionsSysvarRequired.into()));
+    }
+}
@@ -0,0 +1,102 @@
+//! The protocol fee and its volume tiers.
+
+use poker_betting::{pda, Bet, BetSide, BettorStats, FeeTier, PlayerPool};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+const MONTH: i64 = 30 * 24 * 60 * 60;
+
+#[test]
+fn a_bettor_pays_the_fee_of_the_tier_its_volume_reaches() {
+    let mut scenario = Scenario::lobby().with_fee_bps(500).build().unwrap();
+    let (admin, owner) = (scenario.admin.pubkey(), scenario.owner.pubkey());
+    let models = ["model-a".to_string(), "model-b".to_string()];
+    let tier = FeeTier {
+        min_volume: LAMPORTS_PER_SOL,
+        fee_bps: 100,
+    };
+    scenario
+        .sandbox
+        .execute(ix::set_fee_tiers(&admin, vec![tier]), &[&scenario.admin])
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::create_lobby(&owner, "game-2", &models, 1_000, 100),
+            &[&scenario.owner],
+        )
+        .unwrap();
+
+    // The first bet is at the base fee and lifts the bettor into the tier,
+    // whether or not either bet goes on to win
+    let bettor = scenario.sandbox.funded_keypair(10 * LAMPORTS_PER_SOL);
+    for (lobby, stake) in [
+        (scenario.lobby, LAMPORTS_PER_SOL / 100 * 95),
+        (
+            pda::lobby_pda(&owner, "game-2"),
+            LAMPORTS_PER_SOL / 100 * 99,
+        ),
+    ] {
+        scenario
+            .sandbox
+            .execute(
+                ix::place_bet(&lobby, &bettor.pubkey(), "model-a", LAMPORTS_PER_SOL),
+                &[&bettor],
+            )
+            .unwrap();
+        let bet: Bet = scenario
+            .sandbox
+            .get(&pda::bet_pda(&lobby, &bettor.pubkey(), &BetSide::Back))
+            .unwrap();
+        assert_eq!(bet.amount, stake);
+    }
+
+    // The window's volume slides out over the next one rather than resetting
+    let stats: BettorStats = scenario
+        .sandbox
+        .get(&pda::bettor_stats_pda(&bettor.pubkey()))
+        .unwrap();
+    let start = stats.month_started_at;
+    let volume = 2 * LAMPORTS_PER_SOL;
+    assert_eq!(stats.rolling_monthly_volume(start + MONTH), Ok(volume));
+    assert_eq!(
+        stats.rolling_monthly_volume(start + MONTH + MONTH / 2),
+        Ok(volume / 2)
+    );
+    assert_eq!(stats.rolling_monthly_volume(start + 2 * MONTH), Ok(0));
+}
+
+#[test]
+fn a_pooled_bet_pays_its_tier_fee() {
+    let mut scenario = Scenario::lobby().with_fee_bps(500).build().unwrap();
+    let (admin, lobby) = (scenario.admin.pubkey(), scenario.lobby);
+    let tier = FeeTier {
+        min_volume: 0,
+        fee_bps: 100,
+    };
+    let bettor = scenario.sandbox.funded_keypair(10 * LAMPORTS_PER_SOL);
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::set_fee_tiers(&admin, vec![tier]),
+                ix::open_player_pool(&lobby, &admin, 0),
+            ],
+            &[&scenario.admin],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::place_pooled_bet(&lobby, &bettor.pubkey(), 0, LAMPORTS_PER_SOL),
+            &[&bettor],
+        )
+        .unwrap();
+    let pool: PlayerPool = scenario
+        .sandbox
+        .get(&pda::player_pool_pda(&lobby, 0))
+        .unwrap();
+    assert_eq!(pool.fees, LAMPORTS_PER_SOL / 100);
+    assert_eq!(pool.stake, LAMPORTS_PER_SOL / 100 * 99);
+}
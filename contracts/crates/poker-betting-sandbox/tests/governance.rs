@@ -0,0 +1,177 @@
+//! Config initialization and handing the admin over to SPL Governance.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use poker_betting::{pda, BettingError, Config, ConfigChange, FeeTier, SPL_GOVERNANCE_ID};
+use poker_betting_sandbox::{ix, Sandbox, Scenario};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn assign_governance(admin: &Pubkey, governance: &Pubkey) -> Instruction {
+    let accounts = poker_betting::accounts::AssignGovernance {
+        config: pda::config_pda(),
+        admin: *admin,
+        governance: *governance,
+    };
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: accounts.to_account_metas(None),
+        data: poker_betting::instruction::AssignGovernance {}.data(),
+    }
+}
+
+fn set_fee_bps(admin: &Pubkey, fee_bps: u16) -> Instruction {
+    let accounts = poker_betting::accounts::AdminAction {
+        config: pda::config_pda(),
+        admin: *admin,
+    };
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: accounts.to_account_metas(None),
+        data: poker_betting::instruction::SetFeeBps { fee_bps }.data(),
+    }
+}
+
+/// A governance account as the SPL Governance program would own it. Proposals
+/// sign for it by CPI; here the test holds its key instead.
+fn governance(sandbox: &mut Sandbox) -> Keypair {
+    let governance = Keypair::new();
+    sandbox.set_account(
+        governance.pubkey(),
+        Account::new(LAMPORTS_PER_SOL, 0, &SPL_GOVERNANCE_ID),
+    );
+    governance
+}
+
+#[test]
+fn only_the_upgrade_authority_initializes_the_config() {
+    let mut sandbox = Sandbox::new();
+    let deployer = sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    let squatter = sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    sandbox.set_upgrade_authority(Some(deployer.pubkey()));
+
+    let result = sandbox.execute(ix::initialize_config(&squatter.pubkey(), 0), &[&squatter]);
+    assert_eq!(result, Err(BettingError::NotUpgradeAuthority.into()));
+
+    sandbox
+        .execute(ix::initialize_config(&deployer.pubkey(), 0), &[&deployer])
+        .unwrap();
+    let config: Config = sandbox.get(&pda::config_pda()).unwrap();
+    assert_eq!(config.admin, deployer.pubkey());
+}
+
+#[test]
+fn a_frozen_program_cannot_initialize_the_config() {
+    let mut sandbox = Sandbox::new();
+    let deployer = sandbox.funded_keypair(LAMPORTS_PER_SOL);
+
+    let result = sandbox.execute(ix::initialize_config(&deployer.pubkey(), 0), &[&deployer]);
+    assert_eq!(result, Err(BettingError::NotUpgradeAuthority.into()));
+}
+
+#[test]
+fn governance_replaces_the_admin() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let admin = scenario.admin.pubkey();
+    let governance = governance(&mut scenario.sandbox);
+
+    scenario
+        .sandbox
+        .execute(
+            assign_governance(&admin, &governance.pubkey()),
+            &[&scenario.admin],
+        )
+        .unwrap();
+
+    let result = scenario
+        .sandbox
+        .execute(set_fee_bps(&admin, 100), &[&scenario.admin]);
+    assert_eq!(result, Err(BettingError::Unauthorized.into()));
+
+    scenario
+        .sandbox
+        .execute(set_fee_bps(&governance.pubkey(), 100), &[&governance])
+        .unwrap();
+    let config: Config = scenario.sandbox.get(&pda::config_pda()).unwrap();
+    assert_eq!(config.admin, governance.pubkey());
+    assert_eq!(config.fee_bps, 100);
+}
+
+#[test]
+fn governance_must_be_owned_by_spl_governance() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let impostor = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+
+    let result = scenario.sandbox.execute(
+        assign_governance(&scenario.admin.pubkey(), &impostor.pubkey()),
+        &[&scenario.admin],
+    );
+    assert_eq!(result, Err(BettingError::InvalidGovernance.into()));
+}
+
+#[test]
+fn governance_cannot_be_assigned_by_anyone_else() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let stranger = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    let governance = governance(&mut scenario.sandbox);
+
+    let result = scenario.sandbox.execute(
+        assign_governance(&stranger.pubkey(), &governance.pubkey()),
+        &[&stranger],
+    );
+    assert_eq!(result, Err(BettingError::Unauthorized.into()));
+}
+
+#[test]
+fn a_timelocked_admin_changes_settings_only_through_proposals() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let admin = scenario.admin.pubkey();
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::propose_config_change(&admin, ConfigChange::AdminTimelock(3_600)),
+                ix::execute_config_change(&admin),
+            ],
+            &[&scenario.admin],
+        )
+        .unwrap();
+
+    // Raising the minimum payout would otherwise sweep every winning at once
+    for instruction in [
+        ix::set_min_payout(&admin, u64::MAX),
+        ix::set_fee_tiers(
+            &admin,
+            vec![FeeTier {
+                min_volume: 0,
+                fee_bps: 0,
+            }],
+        ),
+    ] {
+        let result = scenario.sandbox.execute(instruction, &[&scenario.admin]);
+        assert_eq!(result, Err(BettingError::TimelockActive.into()));
+    }
+
+    scenario
+        .sandbox
+        .execute(
+            ix::propose_config_change(&admin, ConfigChange::MinPayout(1_000)),
+            &[&scenario.admin],
+        )
+        .unwrap();
+    scenario.sandbox.warp(1);
+    let result = scenario
+        .sandbox
+        .execute(ix::execute_config_change(&admin), &[&scenario.admin]);
+    assert_eq!(result, Err(BettingError::TimelockActive.into()));
+
+    scenario.sandbox.warp(3_600);
+    scenario
+        .sandbox
+        .execute(ix::execute_config_change(&admin), &[&scenario.admin])
+        .unwrap();
+    let config: Config = scenario.sandbox.get(&pda::config_pda()).unwrap();
+    assert_eq!(config.min_payout_lamports, 1_000);
+}
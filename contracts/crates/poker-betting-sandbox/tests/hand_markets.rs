@@ -0,0 +1,122 @@
+//! Bets on the winners of single hands.
+
+use poker_betting::BettingError;
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+const STAKE: u64 = LAMPORTS_PER_SOL / 10;
+
+#[test]
+fn a_hand_market_closes_before_the_hand_is_dealt() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let (game_id, game) = (scenario.game_id.clone(), scenario.game());
+    let early = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    let late = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+
+    // Hand 0 is the first dealt, so it gets no market
+    let result = scenario.sandbox.execute(
+        ix::open_hand_market(&lobby, &owner, &game_id, 0),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::HandMarketClosed.into()));
+    scenario
+        .sandbox
+        .execute(
+            ix::open_hand_market(&lobby, &owner, &game_id, 1),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::place_hand_bet(&lobby, &game, 1, &early.pubkey(), "model-a", STAKE),
+            &[&early],
+        )
+        .unwrap();
+
+    // Once hand 0 is recorded, hand 1 is being dealt
+    scenario
+        .sandbox
+        .execute(
+            ix::record_hand(&owner, &game, 0, "model-b"),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let result = scenario.sandbox.execute(
+        ix::place_hand_bet(&lobby, &game, 1, &late.pubkey(), "model-a", STAKE),
+        &[&late],
+    );
+    assert_eq!(result, Err(BettingError::HandMarketClosed.into()));
+}
+
+#[test]
+fn bets_on_hands_the_game_never_dealt_are_refunded() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let (game_id, game) = (scenario.game_id.clone(), scenario.game());
+    let bettor = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::open_hand_market(&lobby, &owner, &game_id, 5),
+                ix::record_hand(&owner, &game, 0, "model-b"),
+            ],
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::place_hand_bet(&lobby, &game, 5, &bettor.pubkey(), "model-a", STAKE),
+            &[&bettor],
+        )
+        .unwrap();
+    let stake = scenario.lobby_account().hand_escrow_balance;
+
+    // Not until the game is over
+    let void = ix::void_unplayed_hand_markets(&lobby, &game, &[5]);
+    let result = scenario.sandbox.execute(void.clone(), &[]);
+    assert_eq!(result, Err(BettingError::GameNotFinalized.into()));
+    scenario.finish("model-a").unwrap();
+    scenario.sandbox.execute(void, &[]).unwrap();
+    assert!(scenario.lobby_account().open_hand_markets.is_empty());
+
+    let before = scenario.sandbox.lamports(&bettor.pubkey());
+    scenario
+        .sandbox
+        .execute(ix::claim_hand_bet(&lobby, 5, &bettor.pubkey()), &[&bettor])
+        .unwrap();
+    assert_eq!(scenario.sandbox.lamports(&bettor.pubkey()) - before, stake);
+    assert_eq!(scenario.lobby_account().hand_escrow_balance, 0);
+}
+
+#[test]
+fn hand_bets_are_guarded_against_composition_with_settlement() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let (game_id, game) = (scenario.game_id.clone(), scenario.game());
+    let bettor = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    scenario
+        .sandbox
+        .execute(
+            ix::open_hand_market(&lobby, &owner, &game_id, 1),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::set_bet_composition_guard(&scenario.admin.pubkey(), true),
+            &[&scenario.admin],
+        )
+        .unwrap();
+
+    let result = scenario.sandbox.execute(
+        ix::place_hand_bet(&lobby, &game, 1, &bettor.pubkey(), "model-a", STAKE),
+        &[&bettor],
+    );
+    assert_eq!(result, Err(BettingError::InstructionsSysvarRequired.into()));
+}
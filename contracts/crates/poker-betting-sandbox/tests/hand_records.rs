@@ -0,0 +1,79 @@
+//! Closing the engine's hand records once nothing reads them.
+
+use poker_betting::BettingError;
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::signature::Signer;
+
+#[test]
+fn hand_records_close_once_the_lobby_settles() {
+    let mut scenario = Scenario::lobby()
+        .with_bets(1)
+        .past_deadline()
+        .build()
+        .unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let game = scenario.game();
+    scenario
+        .sandbox
+        .execute(
+            ix::record_hand(&owner, &game, 0, "model-a"),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+
+    // The winner hasn't been paid yet
+    let result = scenario.sandbox.execute(
+        ix::close_hand_record(&lobby, &owner, &game, 0),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::LobbyNotSettled.into()));
+
+    let winner = scenario.bettors[0].pubkey();
+    scenario
+        .sandbox
+        .execute(
+            ix::distribute_single_winning(&lobby, &owner, &winner, "model-a"),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let hand = ix::hand_pda(&game, 0);
+    let rent = scenario.sandbox.lamports(&hand);
+    let before = scenario.sandbox.lamports(&owner);
+    scenario
+        .sandbox
+        .execute(
+            ix::close_hand_record(&lobby, &owner, &game, 0),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    assert!(scenario.sandbox.account(&hand).is_none());
+    assert_eq!(scenario.sandbox.lamports(&owner), before + rent);
+}
+
+#[test]
+fn the_engine_closes_hand_records_only_for_the_lobby() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let owner = scenario.owner.pubkey();
+    let game = scenario.game();
+    scenario
+        .sandbox
+        .execute(
+            ix::record_hand(&owner, &game, 0, "model-a"),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+
+    // The authority can't sign as the lobby itself
+    let result = scenario
+        .sandbox
+        .execute(ix::close_hand(&owner, &game, 0, &owner), &[&scenario.owner]);
+    assert!(result.is_err());
+    assert!(scenario.sandbox.account(&ix::hand_pda(&game, 0)).is_some());
+}
+
+#[test]
+fn the_engine_knows_the_betting_program() {
+    assert_eq!(poker_engine::BETTING_PROGRAM_ID, poker_betting::ID);
+}
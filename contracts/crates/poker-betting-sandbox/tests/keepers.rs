@@ -0,0 +1,74 @@
+//! Keepers committing to cranks, earning their fees and being slashed for
+//! missing them.
+
+use poker_betting::{pda, BettingError, Keeper, KEEPER_FEE_WINDOW};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+const STAKE: u64 = LAMPORTS_PER_SOL;
+const FEE: u64 = 1_000;
+
+#[test]
+fn a_keeper_is_paid_for_its_crank_and_slashed_for_missing_one() {
+    // Bettor 0 backs model-a, so model-b's win leaves a pool to sweep
+    let mut scenario = Scenario::lobby()
+        .with_bets(1)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-b").unwrap();
+    let (admin, lobby) = (scenario.admin.pubkey(), scenario.lobby);
+    let authority = scenario.sandbox.funded_keypair(10 * LAMPORTS_PER_SOL);
+    let keeper_address = pda::keeper_pda(&authority.pubkey());
+    scenario
+        .sandbox
+        .execute(ix::set_keeper_terms(&admin, STAKE, FEE), &[&scenario.admin])
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::register_keeper(&authority.pubkey(), STAKE),
+            &[&authority],
+        )
+        .unwrap();
+
+    // Nothing to slash it for until it has missed a committed crank
+    let slash = ix::slash_keeper(&admin, &authority.pubkey(), STAKE / 2);
+    let result = scenario.sandbox.execute(slash.clone(), &[&scenario.admin]);
+    assert_eq!(result, Err(BettingError::KeeperNotSlashable.into()));
+    scenario
+        .sandbox
+        .execute(
+            ix::commit_keeper(&authority.pubkey(), &lobby),
+            &[&authority],
+        )
+        .unwrap();
+    scenario.sandbox.warp(1);
+    let result = scenario.sandbox.execute(slash.clone(), &[&scenario.admin]);
+    assert_eq!(result, Err(BettingError::KeeperNotSlashable.into()));
+
+    scenario
+        .sandbox
+        .execute(
+            ix::sweep_unwon_pool_with_keeper(&lobby, Some(&authority.pubkey())),
+            &[&authority],
+        )
+        .unwrap();
+    let keeper: Keeper = scenario.sandbox.get(&keeper_address).unwrap();
+    assert_eq!(keeper.fees_owed, FEE);
+    assert_eq!(keeper.committed_lobby, None);
+
+    scenario
+        .sandbox
+        .execute(
+            ix::commit_keeper(&authority.pubkey(), &lobby),
+            &[&authority],
+        )
+        .unwrap();
+    scenario.sandbox.warp(KEEPER_FEE_WINDOW + 1);
+    scenario.sandbox.execute(slash, &[&scenario.admin]).unwrap();
+    let keeper: Keeper = scenario.sandbox.get(&keeper_address).unwrap();
+    assert_eq!(keeper.stake, STAKE / 2);
+    assert_eq!(keeper.committed_lobby, None);
+}
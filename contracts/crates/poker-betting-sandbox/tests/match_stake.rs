@@ -0,0 +1,85 @@
+//! Operator match stakes that are never matched going back to their
+//! operators.
+
+use poker_betting::{BettingError, MATCH_STAKE_WINDOW};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = LAMPORTS_PER_SOL;
+
+/// A lobby whose two models' operators are registered, with only model-a's
+/// stake posted.
+fn half_staked() -> (Scenario, Keypair, Keypair) {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let operator_a = scenario.sandbox.funded_keypair(10 * STAKE);
+    let operator_b = scenario.sandbox.funded_keypair(10 * STAKE);
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::register_model_operator(&lobby, &owner, "model-a", &operator_a.pubkey(), STAKE),
+                ix::register_model_operator(&lobby, &owner, "model-b", &operator_b.pubkey(), STAKE),
+            ],
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::post_match_stake(&lobby, "model-a", &operator_a.pubkey()),
+            &[&operator_a],
+        )
+        .unwrap();
+    (scenario, operator_a, operator_b)
+}
+
+#[test]
+fn an_unmatched_stake_is_refunded_once_the_window_closes() {
+    let (mut scenario, operator_a, _) = half_staked();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let refund = ix::refund_match_stake(&lobby, &owner, "model-a", &operator_a.pubkey());
+
+    let result = scenario.sandbox.execute(refund.clone(), &[]);
+    assert_eq!(result, Err(BettingError::MatchStillOn.into()));
+
+    scenario.sandbox.warp(MATCH_STAKE_WINDOW);
+    let before = scenario.sandbox.lamports(&operator_a.pubkey());
+    scenario.sandbox.execute(refund, &[]).unwrap();
+    assert_eq!(
+        scenario.sandbox.lamports(&operator_a.pubkey()) - before,
+        STAKE
+    );
+    assert_eq!(scenario.lobby_account().match_pool, 0);
+}
+
+#[test]
+fn stakes_cannot_be_posted_after_the_window() {
+    let (mut scenario, _, operator_b) = half_staked();
+    scenario.sandbox.warp(MATCH_STAKE_WINDOW);
+    let result = scenario.sandbox.execute(
+        ix::post_match_stake(&scenario.lobby, "model-b", &operator_b.pubkey()),
+        &[&operator_b],
+    );
+    assert_eq!(result, Err(BettingError::MatchStakeWindowClosed.into()));
+}
+
+#[test]
+fn a_finish_without_the_match_refunds_the_posted_stake() {
+    let (mut scenario, operator_a, _) = half_staked();
+    scenario.finish("model-a").unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let before = scenario.sandbox.lamports(&operator_a.pubkey());
+    scenario
+        .sandbox
+        .execute(
+            ix::refund_match_stake(&lobby, &owner, "model-a", &operator_a.pubkey()),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        scenario.sandbox.lamports(&operator_a.pubkey()) - before,
+        STAKE
+    );
+}
@@ -0,0 +1,72 @@
+//! Settling a lobby by Merkle distribution instead of bet by bet.
+
+use poker_betting::{merkle, pda, Bet, BetStatus, LobbyStatus, PAYOUT_CHALLENGE_WINDOW};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::signature::Signer;
+
+#[test]
+fn claimed_bets_close_and_the_lobby_archives() {
+    // Bettor 0 backs model-a, which wins, and takes the whole pool
+    let mut scenario = Scenario::lobby()
+        .with_bets(2)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let winner = scenario.bettors[0].pubkey();
+    let total = scenario.lobby_account().escrow_balance;
+    scenario
+        .sandbox
+        .execute(
+            ix::post_payout_root(&lobby, &owner, merkle::leaf(&winner, total), total),
+            &[&scenario.owner],
+        )
+        .unwrap();
+
+    // Nothing to settle the bet by until its bettor has claimed
+    let result = scenario
+        .sandbox
+        .execute(ix::settle_claimed_bet(&lobby, &winner), &[]);
+    assert!(result.is_err());
+
+    scenario.sandbox.warp(PAYOUT_CHALLENGE_WINDOW);
+    let before = scenario.sandbox.lamports(&winner);
+    scenario
+        .sandbox
+        .execute(
+            ix::claim_with_proof(&lobby, &winner, total, Vec::new()),
+            &[&scenario.bettors[0]],
+        )
+        .unwrap();
+    let claim_rent = scenario
+        .sandbox
+        .lamports(&pda::merkle_claim_pda(&lobby, &winner));
+    assert_eq!(
+        scenario.sandbox.lamports(&winner) + claim_rent - before,
+        total
+    );
+
+    scenario
+        .sandbox
+        .execute(ix::settle_claimed_bet(&lobby, &winner), &[])
+        .unwrap();
+    let bet: Bet = scenario.sandbox.get(&scenario.bet(0)).unwrap();
+    assert!(bet.status == BetStatus::Paid);
+    scenario
+        .sandbox
+        .execute(ix::settle_losing_bets(&lobby, &[scenario.bet(1)]), &[])
+        .unwrap();
+
+    scenario
+        .sandbox
+        .execute(ix::archive_lobby(&lobby, &owner), &[&scenario.owner])
+        .unwrap();
+    assert!(scenario.lobby_account().status == LobbyStatus::Archived);
+    for bettor in &scenario.bettors {
+        scenario
+            .sandbox
+            .execute(ix::close_bet(&lobby, &bettor.pubkey()), &[bettor])
+            .unwrap();
+    }
+}
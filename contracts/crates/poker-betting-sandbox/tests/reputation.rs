@@ -0,0 +1,76 @@
+//! Gating lobby creation on the owner's reputation.
+
+use poker_betting::{pda, BettingError, OperatorProfile};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+#[test]
+fn a_new_operator_earns_its_reputation_on_probation() {
+    // The scenario's lobby draws enough volume to count; its first is
+    // created before the minimum is set
+    let mut scenario = Scenario::lobby()
+        .with_bets(1)
+        .with_stake(LAMPORTS_PER_SOL)
+        .past_deadline()
+        .build()
+        .unwrap();
+    let (admin, owner) = (scenario.admin.pubkey(), scenario.owner.pubkey());
+    let models = ["model-a".to_string(), "model-b".to_string()];
+    scenario
+        .sandbox
+        .execute(
+            ix::set_min_operator_reputation(&admin, 2),
+            &[&scenario.admin],
+        )
+        .unwrap();
+    let create = |scenario: &mut Scenario, game_id: &str| {
+        scenario.sandbox.process(
+            &[
+                ix::create_lobby(&owner, game_id, &models, 1_000, 100),
+                ix::create_game(&owner, &owner, game_id, &models),
+            ],
+            &[&scenario.owner],
+        )
+    };
+
+    // The second lobby is still on probation, the third isn't
+    create(&mut scenario, "game-2").unwrap();
+    assert_eq!(
+        create(&mut scenario, "game-3"),
+        Err(BettingError::ReputationTooLow.into())
+    );
+
+    // Finishing a lobby nobody bet on earns nothing
+    scenario.finish("model-a").unwrap();
+    let lobby = pda::lobby_pda(&owner, "game-2");
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::finalize_game(&owner, &ix::game_pda(&owner, "game-2"), "model-a"),
+                ix::finish_game(&lobby, &owner, "game-2"),
+            ],
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let profile: OperatorProfile = scenario
+        .sandbox
+        .get(&pda::operator_profile_pda(&owner))
+        .unwrap();
+    assert_eq!(profile.lobbies_settled, 2);
+    assert_eq!(profile.reputation(), 1);
+    assert_eq!(
+        create(&mut scenario, "game-3"),
+        Err(BettingError::ReputationTooLow.into())
+    );
+    // The lobby that drew volume did earn it
+    scenario
+        .sandbox
+        .execute(
+            ix::set_min_operator_reputation(&admin, 1),
+            &[&scenario.admin],
+        )
+        .unwrap();
+    create(&mut scenario, "game-3").unwrap();
+}
@@ -0,0 +1,398 @@
+//! A lobby's life from betting through the finish to its bets' closing.
+
+use poker_betting::{pda, Bet, BetStatus, BettingError, Config, Lobby, LobbyStatus};
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+#[test]
+fn a_lobby_with_a_long_game_id_finishes_on_its_engine_record() {
+    let game_id = "g".repeat(poker_betting::MAX_GAME_ID_LEN);
+    let mut scenario = Scenario::lobby()
+        .with_game_id(&game_id)
+        .with_bets(2)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+    let lobby = scenario.lobby_account();
+    assert_eq!(lobby.game_id, game_id);
+    assert_eq!(lobby.winner.as_deref(), Some("model-a"));
+}
+
+#[test]
+fn owners_sharing_a_game_id_finish_on_their_own_engine_records() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let engine_authority = scenario.owner.pubkey();
+    let rival = scenario.sandbox.funded_keypair(LAMPORTS_PER_SOL);
+    let (game_id, models) = (
+        scenario.game_id.clone(),
+        scenario.lobby_account().model_names,
+    );
+    let rival_lobby = pda::lobby_pda(&rival.pubkey(), &game_id);
+    scenario
+        .sandbox
+        .execute(
+            ix::create_lobby(&rival.pubkey(), &game_id, &models, 1_000, 100),
+            &[&rival],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::create_game(&engine_authority, &rival.pubkey(), &game_id, &models),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    assert_ne!(ix::game_pda(&rival.pubkey(), &game_id), scenario.game());
+
+    // The scenario's finalized game doesn't finish the rival's lobby
+    scenario.finish("model-a").unwrap();
+    let finish_rival = ix::finish_game(&rival_lobby, &rival.pubkey(), &game_id);
+    assert!(scenario
+        .sandbox
+        .execute(finish_rival.clone(), &[&rival])
+        .is_err());
+
+    scenario
+        .sandbox
+        .execute(
+            ix::finalize_game(
+                &engine_authority,
+                &ix::game_pda(&rival.pubkey(), &game_id),
+                "model-b",
+            ),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    scenario.sandbox.warp(1);
+    scenario.sandbox.execute(finish_rival, &[&rival]).unwrap();
+    let rival_account: Lobby = scenario.sandbox.get(&rival_lobby).unwrap();
+    assert_eq!(rival_account.winner.as_deref(), Some("model-b"));
+    assert_eq!(scenario.lobby_account().winner.as_deref(), Some("model-a"));
+}
+
+#[test]
+fn winners_claim_and_every_bet_closes() {
+    // Bettors 0 and 2 back model-a, 1 and 3 model-b
+    let mut scenario = Scenario::lobby()
+        .with_bets(4)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+    let lobby = scenario.lobby_account();
+    assert!(lobby.status == LobbyStatus::Finished);
+    assert_eq!(lobby.winner.as_deref(), Some("model-a"));
+
+    let owner = scenario.owner.pubkey();
+    for index in [0, 2] {
+        let bettor = scenario.bettors[index].pubkey();
+        let before = scenario.sandbox.lamports(&bettor);
+        scenario
+            .sandbox
+            .execute(
+                ix::distribute_single_winning(&scenario.lobby, &owner, &bettor, "model-a"),
+                &[&scenario.owner],
+            )
+            .unwrap();
+        let bet: Bet = scenario.sandbox.get(&scenario.bet(index)).unwrap();
+        assert!(bet.status == BetStatus::Paid);
+        // Two winners split four equal stakes
+        assert_eq!(scenario.sandbox.lamports(&bettor) - before, 2 * bet.amount);
+    }
+    let losers = [scenario.bet(1), scenario.bet(3)];
+    scenario
+        .sandbox
+        .execute(ix::settle_losing_bets(&scenario.lobby, &losers), &[])
+        .unwrap();
+    for loser in losers {
+        let bet: Bet = scenario.sandbox.get(&loser).unwrap();
+        assert!(bet.status == BetStatus::Lost);
+    }
+
+    for index in 0..4 {
+        let bettor = &scenario.bettors[index];
+        scenario
+            .sandbox
+            .execute(ix::close_bet(&scenario.lobby, &bettor.pubkey()), &[bettor])
+            .unwrap();
+        assert!(scenario.sandbox.account(&scenario.bet(index)).is_none());
+    }
+}
+
+#[test]
+fn an_open_bet_cannot_be_closed() {
+    let mut scenario = Scenario::lobby().with_bets(1).build().unwrap();
+    let bettor = &scenario.bettors[0];
+    let result = scenario
+        .sandbox
+        .execute(ix::close_bet(&scenario.lobby, &bettor.pubkey()), &[bettor]);
+    assert!(result.is_err());
+    assert!(scenario.sandbox.account(&scenario.bet(0)).is_some());
+}
+
+#[test]
+fn bets_close_at_the_deadline() {
+    let mut scenario = Scenario::lobby().with_deadline(60).build().unwrap();
+    let early = scenario.sandbox.funded_keypair(1_000_000_000);
+    let late = scenario.sandbox.funded_keypair(1_000_000_000);
+
+    scenario.sandbox.warp(59);
+    scenario
+        .sandbox
+        .execute(
+            ix::place_bet(&scenario.lobby, &early.pubkey(), "model-a", 1_000_000),
+            &[&early],
+        )
+        .unwrap();
+
+    scenario.sandbox.warp(1);
+    let result = scenario.sandbox.execute(
+        ix::place_bet(&scenario.lobby, &late.pubkey(), "model-a", 1_000_000),
+        &[&late],
+    );
+    assert_eq!(result, Err(BettingError::BettingClosed.into()));
+    assert_eq!(scenario.lobby_account().total_bets, 1_000_000);
+}
+
+#[test]
+fn only_finish_game_finishes_a_lobby() {
+    let mut scenario = Scenario::lobby()
+        .with_bets(2)
+        .past_deadline()
+        .build()
+        .unwrap();
+    let owner = scenario.owner.pubkey();
+    let result = scenario.sandbox.execute(
+        ix::update_lobby_status(&scenario.lobby, &owner, LobbyStatus::Finished),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::FinishThroughFinishGame.into()));
+
+    // Without a declared winner nothing can be paid
+    let bettor = scenario.bettors[0].pubkey();
+    let result = scenario.sandbox.execute(
+        ix::distribute_single_winning(&scenario.lobby, &owner, &bettor, "model-a"),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::LobbyNotFinished.into()));
+}
+
+#[test]
+fn a_finished_lobby_never_goes_back() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    scenario.finish("model-a").unwrap();
+    let owner = scenario.owner.pubkey();
+    for status in [LobbyStatus::Running, LobbyStatus::Waiting] {
+        let result = scenario.sandbox.execute(
+            ix::update_lobby_status(&scenario.lobby, &owner, status),
+            &[&scenario.owner],
+        );
+        assert_eq!(result, Err(BettingError::InvalidLobbyTransition.into()));
+    }
+    let result = scenario.sandbox.execute(
+        ix::finish_game(&scenario.lobby, &owner, &scenario.game_id),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::LobbyAlreadyFinished.into()));
+}
+
+#[test]
+fn a_started_lobby_cannot_go_back_to_waiting() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    scenario
+        .sandbox
+        .execute(
+            ix::update_lobby_status(&lobby, &owner, LobbyStatus::Running),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let result = scenario.sandbox.execute(
+        ix::update_lobby_status(&lobby, &owner, LobbyStatus::Waiting),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::InvalidLobbyTransition.into()));
+}
+
+#[test]
+fn winnings_are_settled_against_the_declared_winner() {
+    let mut scenario = Scenario::lobby()
+        .with_bets(2)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+    let owner = scenario.owner.pubkey();
+    // Bettor 1 backed model-b, which lost
+    let bettor = scenario.bettors[1].pubkey();
+    let result = scenario.sandbox.execute(
+        ix::distribute_single_winning(&scenario.lobby, &owner, &bettor, "model-b"),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::BetOnWrongPlayer.into()));
+}
+
+#[test]
+fn the_game_cannot_finish_while_blind_bets_can_be_revealed() {
+    let mut scenario = Scenario::lobby().with_deadline(60).build().unwrap();
+    let reveal_deadline = scenario.sandbox.now() + 120;
+    let owner = scenario.owner.pubkey();
+    scenario
+        .sandbox
+        .execute(
+            ix::set_reveal_deadline(&scenario.lobby, &owner, reveal_deadline),
+            &[&scenario.owner],
+        )
+        .unwrap();
+
+    scenario.sandbox.warp(60);
+    assert_eq!(
+        scenario.finish("model-a"),
+        Err(BettingError::RevealWindowOpen.into())
+    );
+    scenario.sandbox.warp_to(reveal_deadline);
+    scenario.finish("model-a").unwrap();
+}
+
+#[test]
+fn a_lobby_archives_only_once_every_bet_is_settled() {
+    let mut scenario = Scenario::lobby()
+        .with_bets(2)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-a").unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let winner = scenario.bettors[0].pubkey();
+    scenario
+        .sandbox
+        .execute(
+            ix::distribute_single_winning(&lobby, &owner, &winner, "model-a"),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    assert!(scenario.lobby_account().fully_settled());
+
+    // The escrow is empty, but the losing bet hasn't been marked
+    let result = scenario
+        .sandbox
+        .execute(ix::archive_lobby(&lobby, &owner), &[&scenario.owner]);
+    assert_eq!(result, Err(BettingError::BetsOutstanding.into()));
+
+    let loser = scenario.bet(1);
+    scenario
+        .sandbox
+        .execute(ix::settle_losing_bets(&lobby, &[loser]), &[])
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(ix::archive_lobby(&lobby, &owner), &[&scenario.owner])
+        .unwrap();
+    assert!(scenario.lobby_account().status == LobbyStatus::Archived);
+
+    for bettor in &scenario.bettors {
+        scenario
+            .sandbox
+            .execute(ix::close_bet(&lobby, &bettor.pubkey()), &[bettor])
+            .unwrap();
+    }
+}
+
+#[test]
+fn a_disqualified_winner_gives_way_to_the_next_eligible_placing() {
+    for (placings, winner) in [
+        (&[][..], None),
+        (&["model-a", "model-c", "model-b"][..], Some("model-c")),
+    ] {
+        let mut scenario = Scenario::lobby()
+            .with_models(&["model-a", "model-b", "model-c"])
+            .build()
+            .unwrap();
+        let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+        scenario
+            .sandbox
+            .process(
+                &[
+                    ix::disqualify_model(&lobby, &owner, "model-a"),
+                    ix::finalize_game_with_placings(&owner, &scenario.game(), "model-a", placings),
+                ],
+                &[&scenario.owner],
+            )
+            .unwrap();
+        let result = scenario.sandbox.execute(
+            ix::finish_game(&lobby, &owner, &scenario.game_id),
+            &[&scenario.owner],
+        );
+        match winner {
+            // Without a finishing order no eligible model can take the win
+            None => assert_eq!(result, Err(BettingError::InvalidPlacings.into())),
+            Some(winner) => {
+                result.unwrap();
+                let lobby = scenario.lobby_account();
+                assert_eq!(lobby.winner.as_deref(), Some(winner));
+                assert_eq!(lobby.placings, [winner]);
+            }
+        }
+    }
+}
+
+#[test]
+fn a_pool_nobody_backed_to_win_is_swept_to_the_treasury() {
+    // Bettor 0 backs model-a, which loses
+    let mut scenario = Scenario::lobby()
+        .with_bets(1)
+        .past_deadline()
+        .build()
+        .unwrap();
+    scenario.finish("model-b").unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let stake = scenario.lobby_account().total_bets;
+    let treasury = scenario.sandbox.lamports(&pda::treasury_pda());
+
+    scenario
+        .sandbox
+        .execute(ix::sweep_unwon_pool(&lobby), &[])
+        .unwrap();
+    assert_eq!(
+        scenario.sandbox.lamports(&pda::treasury_pda()),
+        treasury + stake
+    );
+    assert!(scenario.lobby_account().fully_settled());
+    scenario.sandbox.warp(1);
+    let result = scenario.sandbox.execute(ix::sweep_unwon_pool(&lobby), &[]);
+    assert_eq!(result, Err(BettingError::NothingToClaim.into()));
+
+    scenario
+        .sandbox
+        .execute(ix::settle_losing_bets(&lobby, &[scenario.bet(0)]), &[])
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(ix::archive_lobby(&lobby, &owner), &[&scenario.owner])
+        .unwrap();
+}
+
+#[test]
+fn archiving_releases_rake_still_reserved() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    scenario.finish("model-a").unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    scenario
+        .sandbox
+        .modify(&lobby, |lobby: &mut Lobby| lobby.rake = 1_000);
+    scenario
+        .sandbox
+        .modify(&pda::config_pda(), |config: &mut Config| {
+            config.reserved_rake = 1_000
+        });
+
+    scenario
+        .sandbox
+        .execute(ix::archive_lobby(&lobby, &owner), &[&scenario.owner])
+        .unwrap();
+    let config: Config = scenario.sandbox.get(&pda::config_pda()).unwrap();
+    assert_eq!(config.reserved_rake, 0);
+    assert_eq!(scenario.lobby_account().rake, 0);
+}
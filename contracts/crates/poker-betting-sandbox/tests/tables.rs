@@ -0,0 +1,371 @@
+//! Heads-up tables on the engine.
+
+use anchor_lang::solana_program::hash::hashv;
+use poker_betting_sandbox::{ix, Scenario};
+use poker_engine::shuffle::{self, Card, DECK_SIZE};
+use poker_engine::{EngineError, GameRecord, PlayerAction, Table};
+use solana_sdk::signature::{Keypair, Signer};
+
+const BUY_IN: u64 = 1_000;
+const TURN_TIMEOUT: i64 = 30;
+const SALT: [u8; 32] = [9; 32];
+
+fn key() -> Card {
+    let mut key = [0; 32];
+    key[31] = 3;
+    key
+}
+
+/// A lobby's game with a table both seats have joined.
+fn seated() -> (Scenario, [Keypair; 2]) {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let owner = scenario.owner.pubkey();
+    let game = scenario.game();
+    let players = [(); 2].map(|()| scenario.sandbox.funded_keypair(1_000_000_000));
+    scenario
+        .sandbox
+        .execute(
+            ix::create_table(
+                &owner,
+                &game,
+                [players[0].pubkey(), players[1].pubkey()],
+                BUY_IN,
+                (10, 20),
+                TURN_TIMEOUT,
+            ),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    for (seat, player) in players.iter().enumerate() {
+        scenario
+            .sandbox
+            .execute(
+                ix::join_table(&game, &player.pubkey(), seat as u8),
+                &[player],
+            )
+            .unwrap();
+    }
+    (scenario, players)
+}
+
+/// Seals hand `hand_number`'s deck as shuffled into `permutation` by the
+/// owner, its only shuffler.
+fn shuffle(scenario: &mut Scenario, hand_number: u64, permutation: &[u8]) {
+    let owner = scenario.owner.pubkey();
+    let deck = ix::deck_pda(&scenario.game(), hand_number);
+    let plaintext = shuffle::plaintext_deck();
+    let cards: Vec<Card> = permutation
+        .iter()
+        .map(|index| shuffle::encrypt(&plaintext[*index as usize], &key()))
+        .collect();
+
+    let mut instructions = vec![
+        ix::create_deck(&owner, &scenario.game(), hand_number, &[owner]),
+        ix::init_shuffle_stage(&deck, &owner, 1),
+    ];
+    for (chunk, cards) in cards.chunks(13).enumerate() {
+        instructions.push(ix::write_shuffle_chunk(
+            &deck,
+            &owner,
+            1,
+            (chunk * 13) as u8,
+            cards,
+        ));
+    }
+    let commitment = shuffle::shuffle_commitment(&key(), permutation, &SALT);
+    instructions.push(ix::seal_shuffle(&deck, &owner, 1, commitment));
+    scenario
+        .sandbox
+        .process(&instructions, &[&scenario.owner])
+        .unwrap();
+}
+
+/// Starts hand `hand_number` on an unshuffled deck, committing to no cards.
+fn start_hand(scenario: &mut Scenario, hand_number: u64) {
+    let order: Vec<u8> = (0..DECK_SIZE as u8).collect();
+    shuffle(scenario, hand_number, &order);
+    let owner = scenario.owner.pubkey();
+    scenario
+        .sandbox
+        .execute(
+            ix::start_hand(&owner, &scenario.game(), hand_number, [[0; 32]; 2], [0; 32]),
+            &[&scenario.owner],
+        )
+        .unwrap();
+}
+
+#[test]
+fn an_absent_seat_forfeits_after_missing_turns_in_a_row() {
+    let (mut scenario, players) = seated();
+    let owner = scenario.owner.pubkey();
+    let game = scenario.game();
+    let caller = players[1].pubkey();
+    let time_out = |scenario: &mut Scenario| {
+        scenario.sandbox.warp(TURN_TIMEOUT + 1);
+        scenario
+            .sandbox
+            .execute(ix::claim_timeout(&game, &caller), &[&players[1]])
+            .unwrap();
+    };
+
+    // Seat 0 deals first and folds its small blind on the first timeout
+    start_hand(&mut scenario, 0);
+    time_out(&mut scenario);
+    // Next hand seat 1 calls, and seat 0 checks its big blind on the second
+    start_hand(&mut scenario, 1);
+    scenario
+        .sandbox
+        .execute(ix::act(&game, &caller, PlayerAction::Call), &[&players[1]])
+        .unwrap();
+    time_out(&mut scenario);
+    scenario
+        .sandbox
+        .execute(
+            ix::deal_street(&owner, &game, &[0, 1, 2]),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let table: Table = scenario.sandbox.get(&ix::table_pda(&game)).unwrap();
+    assert_eq!(table.missed_turns, [2, 0]);
+
+    // The third in a row forfeits the match
+    time_out(&mut scenario);
+    let table: Table = scenario.sandbox.get(&ix::table_pda(&game)).unwrap();
+    assert_eq!(table.stacks, [0, 2 * BUY_IN]);
+    scenario
+        .sandbox
+        .execute(ix::finalize_table_game(&game, &caller), &[&players[1]])
+        .unwrap();
+    let game: GameRecord = scenario.sandbox.get(&game).unwrap();
+    assert_eq!(game.winner.as_deref(), Some("model-b"));
+}
+
+#[test]
+fn acting_resets_a_seats_missed_turns() {
+    let (mut scenario, players) = seated();
+    let game = scenario.game();
+    start_hand(&mut scenario, 0);
+    scenario.sandbox.warp(TURN_TIMEOUT + 1);
+    scenario
+        .sandbox
+        .execute(
+            ix::claim_timeout(&game, &players[1].pubkey()),
+            &[&players[1]],
+        )
+        .unwrap();
+
+    // Seat 1 deals next and acts first; seat 0 then acts in time
+    start_hand(&mut scenario, 1);
+    scenario
+        .sandbox
+        .execute(
+            ix::act(&game, &players[1].pubkey(), PlayerAction::Call),
+            &[&players[1]],
+        )
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(
+            ix::act(&game, &players[0].pubkey(), PlayerAction::Check),
+            &[&players[0]],
+        )
+        .unwrap();
+    let table: Table = scenario.sandbox.get(&ix::table_pda(&game)).unwrap();
+    assert_eq!(table.missed_turns, [0, 0]);
+}
+
+#[test]
+fn a_hand_needs_its_deck_shuffled_first() {
+    let (mut scenario, _) = seated();
+    let owner = scenario.owner.pubkey();
+    let game = scenario.game();
+    let deck = ix::deck_pda(&game, 0);
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::create_deck(&owner, &game, 0, &[owner]),
+                ix::init_shuffle_stage(&deck, &owner, 1),
+            ],
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let result = scenario.sandbox.execute(
+        ix::start_hand(&owner, &game, 0, [[0; 32]; 2], [0; 32]),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(EngineError::DeckNotShuffled.into()));
+}
+
+#[test]
+fn the_showdown_reveals_the_cards_dealt_from_the_verified_deck() {
+    let (mut scenario, players) = seated();
+    let owner = scenario.owner.pubkey();
+    let game = scenario.game();
+    // Seat 0 holds two aces; seat 1's two and three make a straight on the
+    // three, four, five, three and six of the board
+    let hole_cards = [[12, 25], [0, 14]];
+    let board = [1, 2, 3, 40, 30];
+    let dealt: Vec<u8> = hole_cards.iter().flatten().chain(&board).copied().collect();
+    let rest = (0..DECK_SIZE as u8).filter(|card| !dealt.contains(card));
+    let permutation: Vec<u8> = dealt.iter().copied().chain(rest).collect();
+    shuffle(&mut scenario, 0, &permutation);
+    scenario
+        .sandbox
+        .execute(
+            ix::start_hand(
+                &owner,
+                &game,
+                0,
+                hole_cards.map(|cards| hashv(&[&cards, &SALT]).to_bytes()),
+                hashv(&[&board, &SALT]).to_bytes(),
+            ),
+            &[&scenario.owner],
+        )
+        .unwrap();
+
+    // Seat 0 calls, then both check down every street
+    let act = |seat: usize, action| ix::act(&game, &players[seat].pubkey(), action);
+    scenario
+        .sandbox
+        .execute(act(0, PlayerAction::Call), &[&players[0]])
+        .unwrap();
+    scenario
+        .sandbox
+        .execute(act(1, PlayerAction::Check), &[&players[1]])
+        .unwrap();
+    for cards in [&board[..3], &board[3..4], &board[4..]] {
+        scenario
+            .sandbox
+            .execute(ix::deal_street(&owner, &game, cards), &[&scenario.owner])
+            .unwrap();
+        for seat in [1, 0] {
+            scenario
+                .sandbox
+                .execute(act(seat, PlayerAction::Check), &[&players[seat]])
+                .unwrap();
+        }
+    }
+
+    let showdown = |hole_cards| ix::showdown(&owner, &game, 0, hole_cards, [SALT; 2], SALT);
+    let result = scenario
+        .sandbox
+        .execute(showdown(hole_cards), &[&scenario.owner]);
+    assert_eq!(result, Err(EngineError::DeckNotVerified.into()));
+
+    let deck = ix::deck_pda(&game, 0);
+    scenario
+        .sandbox
+        .execute(
+            ix::verify_shuffle_proof(&deck, &owner, 1, key(), &permutation, SALT),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    let result = scenario
+        .sandbox
+        .execute(showdown([hole_cards[1], hole_cards[0]]), &[&scenario.owner]);
+    assert_eq!(result, Err(EngineError::NotDealtFromDeck.into()));
+
+    scenario
+        .sandbox
+        .execute(showdown(hole_cards), &[&scenario.owner])
+        .unwrap();
+    let table: Table = scenario.sandbox.get(&ix::table_pda(&game)).unwrap();
+    assert_eq!(table.stacks, [BUY_IN - 20, BUY_IN + 20]);
+}
+
+#[test]
+fn seats_are_bound_to_their_registered_players() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let owner = scenario.owner.pubkey();
+    let game = scenario.game();
+    let players = [(); 2].map(|()| scenario.sandbox.funded_keypair(1_000_000_000));
+    let stranger = scenario.sandbox.funded_keypair(1_000_000_000);
+    scenario
+        .sandbox
+        .execute(
+            ix::create_table(
+                &owner,
+                &game,
+                [players[0].pubkey(), players[1].pubkey()],
+                BUY_IN,
+                (10, 20),
+                TURN_TIMEOUT,
+            ),
+            &[&scenario.owner],
+        )
+        .unwrap();
+
+    for (player, seat) in [(&stranger, 0), (&players[1], 0)] {
+        let result = scenario
+            .sandbox
+            .execute(ix::join_table(&game, &player.pubkey(), seat), &[player]);
+        assert_eq!(result, Err(EngineError::NotSeatPlayer.into()));
+    }
+    for (seat, player) in players.iter().enumerate() {
+        scenario
+            .sandbox
+            .execute(
+                ix::join_table(&game, &player.pubkey(), seat as u8),
+                &[player],
+            )
+            .unwrap();
+    }
+
+    // Cashing out before the first hand loses nothing, and the seat can be
+    // taken again
+    let caller = players[1].pubkey();
+    scenario
+        .sandbox
+        .execute(ix::leave_table(&game, &players[0].pubkey()), &[&players[0]])
+        .unwrap();
+    let result = scenario
+        .sandbox
+        .execute(ix::finalize_table_game(&game, &caller), &[&players[1]]);
+    assert_eq!(result, Err(EngineError::NoWinnerYet.into()));
+    scenario.sandbox.warp(1);
+    scenario
+        .sandbox
+        .execute(
+            ix::join_table(&game, &players[0].pubkey(), 0),
+            &[&players[0]],
+        )
+        .unwrap();
+}
+
+#[test]
+fn leaving_mid_game_forfeits_the_match() {
+    let (mut scenario, players) = seated();
+    let game = scenario.game();
+    start_hand(&mut scenario, 0);
+    scenario
+        .sandbox
+        .execute(
+            ix::act(&game, &players[0].pubkey(), PlayerAction::Fold),
+            &[&players[0]],
+        )
+        .unwrap();
+
+    scenario
+        .sandbox
+        .execute(ix::leave_table(&game, &players[0].pubkey()), &[&players[0]])
+        .unwrap();
+    let table: Table = scenario.sandbox.get(&ix::table_pda(&game)).unwrap();
+    assert_eq!(table.forfeited, Some(0));
+    // Nobody takes the forfeited seat before the match is finalized
+    let result = scenario.sandbox.execute(
+        ix::join_table(&game, &players[0].pubkey(), 0),
+        &[&players[0]],
+    );
+    assert_eq!(result, Err(EngineError::MatchForfeited.into()));
+
+    scenario
+        .sandbox
+        .execute(
+            ix::finalize_table_game(&game, &players[1].pubkey()),
+            &[&players[1]],
+        )
+        .unwrap();
+    let game: GameRecord = scenario.sandbox.get(&game).unwrap();
+    assert_eq!(game.winner.as_deref(), Some("model-b"));
+}
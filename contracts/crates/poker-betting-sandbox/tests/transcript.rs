@@ -0,0 +1,53 @@
+//! Committing a lobby to its game transcript.
+
+use poker_betting::BettingError;
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::signature::Signer;
+
+const COMMITMENT: [u8; 32] = [7; 32];
+
+#[test]
+fn the_transcript_is_committed_before_the_first_hand() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let game_id = scenario.game_id.clone();
+    scenario
+        .sandbox
+        .execute(
+            ix::commit_transcript(&lobby, &owner, &game_id, COMMITMENT),
+            &[&scenario.owner],
+        )
+        .unwrap();
+    assert_eq!(
+        scenario.lobby_account().transcript_commitment,
+        Some(COMMITMENT)
+    );
+
+    let result = scenario.sandbox.execute(
+        ix::commit_transcript(&lobby, &owner, &game_id, [8; 32]),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::TranscriptAlreadyCommitted.into()));
+}
+
+#[test]
+fn the_transcript_cannot_be_committed_once_a_hand_is_played() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (lobby, owner) = (scenario.lobby, scenario.owner.pubkey());
+    let game_id = scenario.game_id.clone();
+    scenario
+        .sandbox
+        .execute(
+            ix::record_hand(&owner, &scenario.game(), 0, "model-a"),
+            &[&scenario.owner],
+        )
+        .unwrap();
+
+    // The lobby still waits, but the engine has recorded a hand
+    let result = scenario.sandbox.execute(
+        ix::commit_transcript(&lobby, &owner, &game_id, COMMITMENT),
+        &[&scenario.owner],
+    );
+    assert_eq!(result, Err(BettingError::LobbyAlreadyStarted.into()));
+    assert_eq!(scenario.lobby_account().transcript_commitment, None);
+}
@@ -0,0 +1,30 @@
+//! Bounding every bet's USD value.
+
+use poker_betting::BettingError;
+use poker_betting_sandbox::{ix, Scenario};
+use solana_sdk::signature::Signer;
+
+#[test]
+fn every_way_of_betting_needs_a_price_under_usd_bounds() {
+    let mut scenario = Scenario::lobby().build().unwrap();
+    let (admin, lobby) = (scenario.admin.pubkey(), scenario.lobby);
+    let bettor = scenario.sandbox.funded_keypair(1_000_000_000);
+    scenario
+        .sandbox
+        .process(
+            &[
+                ix::set_usd_pricing(&admin, [1; 32], 1_000_000, 0),
+                ix::open_player_pool(&lobby, &admin, 0),
+            ],
+            &[&scenario.admin],
+        )
+        .unwrap();
+
+    for instruction in [
+        ix::place_bet(&lobby, &bettor.pubkey(), "model-a", 1_000_000),
+        ix::place_pooled_bet(&lobby, &bettor.pubkey(), 0, 1_000_000),
+    ] {
+        let result = scenario.sandbox.execute(instruction, &[&bettor]);
+        assert_eq!(result, Err(BettingError::PriceUpdateRequired.into()));
+    }
+}
@@ -0,0 +1,65 @@
+//! Prints the instructions that propose finishing a vault-owned lobby, as
+//! base58 program ids, account metas and hex data, ready to be signed by a
+//! multisig member and sent.
+//!
+//! ```text
+//! propose_finish_game <multisig> <member> <next-transaction-index> <game-id> <winner>
+//! ```
+
+use std::env;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use poker_betting_squads::{finish_game, propose, vault, FinishGameAccounts, Proposer};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [multisig, member, transaction_index, game_id, winner] = &args[..] else {
+        eprintln!(
+            "usage: propose_finish_game <multisig> <member> <next-transaction-index> <game-id> \
+             <winner>"
+        );
+        std::process::exit(2);
+    };
+    let multisig = Pubkey::from_str(multisig).expect("multisig address");
+    let member = Pubkey::from_str(member).expect("member address");
+    let transaction_index: u64 = transaction_index.parse().expect("transaction index");
+
+    let proposer = Proposer {
+        multisig,
+        creator: member,
+        rent_payer: member,
+        vault_index: 0,
+    };
+    let finish = finish_game(
+        &vault(&multisig, 0),
+        game_id,
+        FinishGameAccounts::default(),
+        poker_betting::instruction::FinishGame {
+            placings: vec![winner.clone()],
+            final_chips: Vec::new(),
+        },
+    );
+    let instructions = propose(
+        &proposer,
+        transaction_index,
+        &[finish],
+        Some(format!("finish {game_id}")),
+    )
+    .expect("vault transaction");
+    for instruction in instructions {
+        println!("program {}", instruction.program_id);
+        for meta in &instruction.accounts {
+            println!(
+                "  {} signer={} writable={}",
+                meta.pubkey, meta.is_signer, meta.is_writable
+            );
+        }
+        let data: String = instruction
+            .data
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        println!("  data {data}");
+    }
+}
@@ -0,0 +1,364 @@
+//! Runs poker-betting lobbies from a Squads v4 multisig.
+//!
+//! The multisig's vault PDA is the lobby owner: it signs `create_lobby` and
+//! `finish_game` when a vault transaction executes, and can be the recipient
+//! of treasury spends. Each builder here returns the poker-betting
+//! instruction with the vault in place of a wallet, and [`propose`] wraps
+//! instructions in the `vault_transaction_create` and `proposal_create`
+//! instructions that put them to a vote. Members then approve and execute the
+//! proposal with the Squads app or SDK as usual.
+//!
+//! Squads instructions are built by hand so its crates are not needed.
+
+pub mod message;
+
+use anchor_lang::prelude::{borsh, pubkey, AnchorSerialize, Pubkey};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use poker_betting::pda;
+
+pub const SQUADS_ID: Pubkey = pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
+/// `sha256("global:<name>")[..8]`
+const VAULT_TRANSACTION_CREATE_DISCRIMINATOR: [u8; 8] = [48, 250, 78, 168, 208, 226, 218, 211];
+const PROPOSAL_CREATE_DISCRIMINATOR: [u8; 8] = [220, 60, 73, 224, 30, 108, 79, 159];
+
+/// Offset of `transaction_index` in a `Multisig` account: discriminator,
+/// create key, config authority, threshold and time lock come first.
+const TRANSACTION_INDEX_OFFSET: usize = 8 + 32 + 32 + 2 + 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0} must sign, but a vault transaction can only be signed by the vault")]
+    UnsupportedSigner(Pubkey),
+    #[error("a vault transaction references at most 255 accounts")]
+    TooManyAccounts,
+    #[error("a vault transaction holds at most 255 instructions")]
+    TooManyInstructions,
+    #[error("instruction data is longer than 65535 bytes")]
+    InstructionTooLarge,
+    #[error("account data is too short for a Squads multisig")]
+    InvalidMultisig,
+}
+
+#[derive(AnchorSerialize)]
+struct VaultTransactionCreateArgs {
+    vault_index: u8,
+    ephemeral_signers: u8,
+    transaction_message: Vec<u8>,
+    memo: Option<String>,
+}
+
+#[derive(AnchorSerialize)]
+struct ProposalCreateArgs {
+    transaction_index: u64,
+    draft: bool,
+}
+
+pub fn vault(multisig: &Pubkey, vault_index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"multisig", multisig.as_ref(), b"vault", &[vault_index]],
+        &SQUADS_ID,
+    )
+    .0
+}
+
+pub fn transaction(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"multisig",
+            multisig.as_ref(),
+            b"transaction",
+            &transaction_index.to_le_bytes(),
+        ],
+        &SQUADS_ID,
+    )
+    .0
+}
+
+pub fn proposal(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"multisig",
+            multisig.as_ref(),
+            b"transaction",
+            &transaction_index.to_le_bytes(),
+            b"proposal",
+        ],
+        &SQUADS_ID,
+    )
+    .0
+}
+
+/// Index the next proposal must use, read from the multisig account's data.
+pub fn next_transaction_index(multisig_data: &[u8]) -> Result<u64, Error> {
+    let bytes = multisig_data
+        .get(TRANSACTION_INDEX_OFFSET..TRANSACTION_INDEX_OFFSET + 8)
+        .ok_or(Error::InvalidMultisig)?;
+    let current = u64::from_le_bytes(bytes.try_into().unwrap());
+    Ok(current + 1)
+}
+
+/// Accounts shared by the instructions that open a proposal.
+pub struct Proposer {
+    pub multisig: Pubkey,
+    /// Multisig member with the initiate permission.
+    pub creator: Pubkey,
+    pub rent_payer: Pubkey,
+    pub vault_index: u8,
+}
+
+/// Builds `vault_transaction_create` and `proposal_create` for
+/// `instructions`, executed by the vault as proposal `transaction_index`.
+/// Both are signed by the creator and rent payer.
+pub fn propose(
+    proposer: &Proposer,
+    transaction_index: u64,
+    instructions: &[Instruction],
+    memo: Option<String>,
+) -> Result<[Instruction; 2], Error> {
+    let vault = vault(&proposer.multisig, proposer.vault_index);
+    let transaction_message = message::compile(&vault, instructions)?;
+
+    let mut data = VAULT_TRANSACTION_CREATE_DISCRIMINATOR.to_vec();
+    VaultTransactionCreateArgs {
+        vault_index: proposer.vault_index,
+        ephemeral_signers: 0,
+        transaction_message,
+        memo,
+    }
+    .serialize(&mut data)
+    .unwrap();
+    let create_transaction = Instruction {
+        program_id: SQUADS_ID,
+        accounts: vec![
+            AccountMeta::new(proposer.multisig, false),
+            AccountMeta::new(transaction(&proposer.multisig, transaction_index), false),
+            AccountMeta::new_readonly(proposer.creator, true),
+            AccountMeta::new(proposer.rent_payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    };
+
+    let mut data = PROPOSAL_CREATE_DISCRIMINATOR.to_vec();
+    ProposalCreateArgs {
+        transaction_index,
+        draft: false,
+    }
+    .serialize(&mut data)
+    .unwrap();
+    let create_proposal = Instruction {
+        program_id: SQUADS_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(proposer.multisig, false),
+            AccountMeta::new(proposal(&proposer.multisig, transaction_index), false),
+            AccountMeta::new_readonly(proposer.creator, true),
+            AccountMeta::new(proposer.rent_payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    };
+    Ok([create_transaction, create_proposal])
+}
+
+/// `create_lobby` with `vault` as the owner, paying the lobby's rent and
+/// accruing its owner stats and operator reputation.
+pub fn create_lobby(vault: &Pubkey, args: poker_betting::instruction::CreateLobby) -> Instruction {
+    let lobby = pda::lobby_pda(vault, &args.game_id);
+    let accounts = poker_betting::accounts::CreateLobby {
+        config: pda::config_pda(),
+        owner_stats: pda::owner_stats_pda(vault),
+        operator_profile: pda::operator_profile_pda(vault),
+        lobby,
+        bond: pda::operator_bond_pda(&lobby),
+        owner: *vault,
+        escrow: pda::escrow_pda(&lobby),
+        system_program: system_program::ID,
+        parent_lobby: None,
+    };
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// Optional accounts of `finish_game`.
+#[derive(Default)]
+pub struct FinishGameAccounts {
+    pub engine_game: Option<Pubkey>,
+    pub winner_stake: Option<Pubkey>,
+    pub winner_operator: Option<Pubkey>,
+    /// The lobby's pool snapshot, for a charity round with locked pools
+    pub snapshot: Option<Pubkey>,
+    /// The charity of a charity round
+    pub charity: Option<Pubkey>,
+    /// The lobby's rake recipients other than the treasury, in split order
+    pub rake_recipients: Vec<Pubkey>,
+}
+
+/// `finish_game` for a lobby owned by `vault`. A lobby with a results
+/// authority also needs that authority's signature, which a vault
+/// transaction can't carry, so such lobbies can't be finished this way.
+pub fn finish_game(
+    vault: &Pubkey,
+    game_id: &str,
+    optional: FinishGameAccounts,
+    args: poker_betting::instruction::FinishGame,
+) -> Instruction {
+    let lobby = pda::lobby_pda(vault, game_id);
+    let accounts = poker_betting::accounts::FinishGame {
+        lobby,
+        owner: *vault,
+        operator_profile: pda::operator_profile_pda(vault),
+        engine_game: optional.engine_game,
+        config: pda::config_pda(),
+        treasury: pda::treasury_pda(),
+        escrow: pda::escrow_pda(&lobby),
+        winner_stake: optional.winner_stake,
+        winner_operator: optional.winner_operator,
+        system_program: system_program::ID,
+        results_authority: None,
+        snapshot: optional.snapshot,
+        charity: optional.charity,
+    };
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(
+        optional
+            .rake_recipients
+            .iter()
+            .map(|recipient| AccountMeta::new(*recipient, false)),
+    );
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: metas,
+        data: args.data(),
+    }
+}
+
+/// `treasury_spend` sending `amount` to `vault`. `admin` signs it directly;
+/// when the admin is itself a vault, pass the same key as both.
+pub fn treasury_spend_to_vault(admin: &Pubkey, vault: &Pubkey, amount: u64) -> Instruction {
+    let accounts = poker_betting::accounts::TreasurySpend {
+        config: pda::config_pda(),
+        admin: *admin,
+        treasury: pda::treasury_pda(),
+        recipient: *vault,
+        system_program: system_program::ID,
+    };
+    Instruction {
+        program_id: poker_betting::ID,
+        accounts: accounts.to_account_metas(None),
+        data: poker_betting::instruction::TreasurySpend { amount }.data(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposer() -> Proposer {
+        Proposer {
+            multisig: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            rent_payer: Pubkey::new_unique(),
+            vault_index: 0,
+        }
+    }
+
+    #[test]
+    fn the_next_index_follows_the_multisig_transaction_index() {
+        let mut data = vec![0; TRANSACTION_INDEX_OFFSET + 8];
+        data[TRANSACTION_INDEX_OFFSET..].copy_from_slice(&41u64.to_le_bytes());
+        assert_eq!(next_transaction_index(&data).unwrap(), 42);
+        assert!(matches!(
+            next_transaction_index(&data[..TRANSACTION_INDEX_OFFSET + 7]),
+            Err(Error::InvalidMultisig)
+        ));
+    }
+
+    #[test]
+    fn a_proposal_wraps_the_vault_signed_instructions() {
+        let proposer = proposer();
+        let vault = vault(&proposer.multisig, proposer.vault_index);
+        let finish = finish_game(
+            &vault,
+            "game-1",
+            FinishGameAccounts {
+                rake_recipients: vec![Pubkey::new_unique()],
+                ..FinishGameAccounts::default()
+            },
+            poker_betting::instruction::FinishGame {
+                placings: Vec::new(),
+                final_chips: Vec::new(),
+            },
+        );
+        let message = message::compile(&vault, std::slice::from_ref(&finish)).unwrap();
+        let [create_transaction, create_proposal] = propose(&proposer, 7, &[finish], None).unwrap();
+
+        assert_eq!(create_transaction.program_id, SQUADS_ID);
+        assert_eq!(
+            create_transaction.data[..8],
+            VAULT_TRANSACTION_CREATE_DISCRIMINATOR
+        );
+        assert_eq!(
+            create_transaction.accounts[1].pubkey,
+            transaction(&proposer.multisig, 7)
+        );
+        assert!(create_transaction
+            .data
+            .windows(message.len())
+            .any(|window| window == message));
+
+        assert_eq!(create_proposal.data[..8], PROPOSAL_CREATE_DISCRIMINATOR);
+        assert_eq!(create_proposal.data[8..16], 7u64.to_le_bytes());
+        assert_eq!(
+            create_proposal.accounts[1].pubkey,
+            proposal(&proposer.multisig, 7)
+        );
+        for instruction in [&create_transaction, &create_proposal] {
+            let signers: Vec<_> = instruction
+                .accounts
+                .iter()
+                .filter(|meta| meta.is_signer)
+                .map(|meta| meta.pubkey)
+                .collect();
+            assert_eq!(signers, [proposer.creator, proposer.rent_payer]);
+        }
+    }
+
+    #[test]
+    fn the_vault_owns_the_lobby_and_signs_for_it() {
+        let vault = vault(&Pubkey::new_unique(), 0);
+        let recipient = Pubkey::new_unique();
+        let finish = finish_game(
+            &vault,
+            "game-1",
+            FinishGameAccounts {
+                rake_recipients: vec![recipient],
+                ..FinishGameAccounts::default()
+            },
+            poker_betting::instruction::FinishGame {
+                placings: Vec::new(),
+                final_chips: Vec::new(),
+            },
+        );
+        assert_eq!(finish.accounts[0].pubkey, pda::lobby_pda(&vault, "game-1"));
+        let signers: Vec<_> = finish
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_signer)
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(signers, [vault]);
+        let last = finish.accounts.last().unwrap();
+        assert!(last.pubkey == recipient && last.is_writable);
+
+        let spend = treasury_spend_to_vault(&vault, &vault, 5);
+        assert!(spend
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == vault && meta.is_writable));
+    }
+}
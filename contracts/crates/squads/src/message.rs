@@ -0,0 +1,153 @@
+//! Squads' compact `TransactionMessage`, the inner transaction a vault
+//! transaction executes.
+//!
+//! Accounts are ordered writable signers, read-only signers, writable
+//! non-signers, then read-only non-signers, like a legacy Solana message, and
+//! every vector is prefixed by a one-byte length, except instruction data,
+//! whose length is two bytes.
+
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::pubkey::Pubkey;
+
+use crate::Error;
+
+struct Key {
+    pubkey: Pubkey,
+    signer: bool,
+    writable: bool,
+}
+
+/// Compiles `instructions` with `vault` as the only signer, since a vault
+/// transaction can't collect other signatures when it executes.
+pub fn compile(vault: &Pubkey, instructions: &[Instruction]) -> Result<Vec<u8>, Error> {
+    let mut keys = vec![Key {
+        pubkey: *vault,
+        signer: true,
+        writable: true,
+    }];
+    let mut add = |pubkey: Pubkey, signer: bool, writable: bool| -> Result<(), Error> {
+        if signer && pubkey != *vault {
+            return Err(Error::UnsupportedSigner(pubkey));
+        }
+        match keys.iter_mut().find(|key| key.pubkey == pubkey) {
+            Some(key) => key.writable |= writable,
+            None => keys.push(Key {
+                pubkey,
+                signer,
+                writable,
+            }),
+        }
+        Ok(())
+    };
+    for instruction in instructions {
+        for meta in &instruction.accounts {
+            add(meta.pubkey, meta.is_signer, meta.is_writable)?;
+        }
+        add(instruction.program_id, false, false)?;
+    }
+    // Stable, so the vault stays first among the writable signers
+    keys.sort_by_key(|key| (!key.signer, !key.writable));
+    if keys.len() > u8::MAX as usize {
+        return Err(Error::TooManyAccounts);
+    }
+    let index = |pubkey: &Pubkey| keys.iter().position(|key| key.pubkey == *pubkey).unwrap() as u8;
+
+    let count = |signer: bool, writable: bool| {
+        keys.iter()
+            .filter(|key| key.signer == signer && key.writable == writable)
+            .count() as u8
+    };
+    let mut message = vec![
+        keys.iter().filter(|key| key.signer).count() as u8,
+        count(true, true),
+        count(false, true),
+    ];
+    message.push(keys.len() as u8);
+    for key in &keys {
+        message.extend_from_slice(key.pubkey.as_ref());
+    }
+    message.push(u8::try_from(instructions.len()).map_err(|_| Error::TooManyInstructions)?);
+    for instruction in instructions {
+        message.push(index(&instruction.program_id));
+        message.push(instruction.accounts.len() as u8);
+        message.extend(instruction.accounts.iter().map(|meta| index(&meta.pubkey)));
+        let data_len =
+            u16::try_from(instruction.data.len()).map_err(|_| Error::InstructionTooLarge)?;
+        message.extend_from_slice(&data_len.to_le_bytes());
+        message.extend_from_slice(&instruction.data);
+    }
+    // No address lookup tables
+    message.push(0);
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::instruction::AccountMeta;
+
+    use super::*;
+
+    #[test]
+    fn accounts_are_ordered_like_a_legacy_message() {
+        let (vault, program, read, write) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let instructions = [
+            Instruction {
+                program_id: program,
+                accounts: vec![
+                    AccountMeta::new_readonly(read, false),
+                    AccountMeta::new_readonly(write, false),
+                    AccountMeta::new(vault, true),
+                ],
+                data: vec![7, 8],
+            },
+            // The same account writable here makes it writable throughout
+            Instruction {
+                program_id: program,
+                accounts: vec![AccountMeta::new(write, false)],
+                data: Vec::new(),
+            },
+        ];
+        let message = compile(&vault, &instructions).unwrap();
+
+        let mut expected = vec![1, 1, 1, 4];
+        for key in [vault, write, read, program] {
+            expected.extend_from_slice(key.as_ref());
+        }
+        expected.extend_from_slice(&[2, 3, 3, 2, 1, 0, 2, 0, 7, 8]);
+        expected.extend_from_slice(&[3, 1, 1, 0, 0]);
+        expected.push(0);
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn only_the_vault_can_sign() {
+        let (vault, member) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new_readonly(member, true)],
+            data: Vec::new(),
+        };
+        assert!(matches!(
+            compile(&vault, &[instruction]),
+            Err(Error::UnsupportedSigner(signer)) if signer == member
+        ));
+    }
+
+    #[test]
+    fn oversized_instruction_data_is_rejected() {
+        let instruction = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: Vec::new(),
+            data: vec![0; u16::MAX as usize + 1],
+        };
+        assert!(matches!(
+            compile(&Pubkey::new_unique(), &[instruction]),
+            Err(Error::InstructionTooLarge)
+        ));
+    }
+}
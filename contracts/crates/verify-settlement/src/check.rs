@@ -0,0 +1,187 @@
+//! Replays a lobby's winning claims with the shared payout math and compares
+//! each against what the chain paid.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use poker_betting::{logic, Bet, BetStatus, Config, Lobby, PoolSnapshot};
+use poker_betting_math as math;
+
+/// A `BetSettled` event of the lobby, as logged by a payout transaction.
+pub struct Settlement {
+    pub signature: String,
+    pub bet: Pubkey,
+    pub status: BetStatus,
+    pub payout: u64,
+}
+
+pub enum Discrepancy {
+    /// Paid a different amount than the bet's share, or swept when it should
+    /// have been paid or the reverse. Swept payouts are logged as zero.
+    WrongPayout {
+        bet: Pubkey,
+        signature: String,
+        expected: u64,
+        actual: u64,
+    },
+    SettledTwice {
+        bet: Pubkey,
+        signature: String,
+    },
+    PaidLoser {
+        bet: Pubkey,
+        signature: String,
+        payout: u64,
+    },
+    /// The bet account says it was paid but no payout transaction was found.
+    MissingSettlement {
+        bet: Pubkey,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::WrongPayout {
+                bet,
+                signature,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{bet}: expected {expected} lamports, paid {actual} in {signature}"
+            ),
+            Discrepancy::SettledTwice { bet, signature } => {
+                write!(f, "{bet}: paid again in {signature}")
+            }
+            Discrepancy::PaidLoser {
+                bet,
+                signature,
+                payout,
+            } => write!(f, "{bet}: losing bet paid {payout} in {signature}"),
+            Discrepancy::MissingSettlement { bet } => {
+                write!(f, "{bet}: marked paid, but no payout transaction found")
+            }
+        }
+    }
+}
+
+pub struct Report {
+    pub checked: usize,
+    pub discrepancies: Vec<Discrepancy>,
+    /// Winning bets not claimed yet, with what they are owed now.
+    pub unclaimed: Vec<(Pubkey, u64)>,
+    /// Payouts whose bet account has since been closed, so its stake is unknown.
+    pub unverifiable: Vec<String>,
+}
+
+/// Stake settled and lamports paid so far at each paid position, mirroring
+/// the lobby's `position_stake_paid` and `position_paid`.
+struct Claims {
+    settled: Vec<u64>,
+    paid: Vec<u64>,
+}
+
+impl Claims {
+    /// What `bet` is owed as the next claim, recorded as settled.
+    fn claim(&mut self, lobby: &Lobby, bet: &Bet, snapshot: Option<&PoolSnapshot>) -> Result<u64> {
+        let winner = lobby.winner.as_deref().context("lobby has no winner")?;
+        if bet.odds_bps > 0 {
+            return math::fixed_odds_payout(bet.amount, bet.odds_bps).map_err(anyhow::Error::msg);
+        }
+        let position = logic::winning_position(lobby, bet, winner)?;
+        let (distributable, winning_stake) =
+            logic::parimutuel_terms(lobby, bet, winner, snapshot, position)?;
+        let weight = lobby.weighting.weight(bet.amount);
+        let payout = math::parimutuel_claim(
+            weight,
+            self.settled[position],
+            self.paid[position],
+            distributable,
+            winning_stake,
+        )
+        .map_err(anyhow::Error::msg)?;
+        self.settled[position] += weight;
+        self.paid[position] += payout;
+        Ok(payout)
+    }
+}
+
+/// Checks `settlements`, oldest first, against the payouts `bets` are owed.
+/// The last claim at a position receives its pool's rounding remainder, so
+/// claims are replayed in the order they settled.
+pub fn verify(
+    lobby: &Lobby,
+    config: &Config,
+    snapshot: Option<&PoolSnapshot>,
+    bets: &HashMap<Pubkey, Bet>,
+    settlements: &[Settlement],
+) -> Result<Report> {
+    let positions = lobby.payout_curve.len().max(1);
+    let mut claims = Claims {
+        settled: vec![0; positions],
+        paid: vec![0; positions],
+    };
+    let mut report = Report {
+        checked: 0,
+        discrepancies: Vec::new(),
+        unclaimed: Vec::new(),
+        unverifiable: Vec::new(),
+    };
+    let mut settled = HashSet::new();
+    for settlement in settlements {
+        if !matches!(settlement.status, BetStatus::Paid | BetStatus::BelowMinimum) {
+            continue;
+        }
+        if !settled.insert(settlement.bet) {
+            report.discrepancies.push(Discrepancy::SettledTwice {
+                bet: settlement.bet,
+                signature: settlement.signature.clone(),
+            });
+            continue;
+        }
+        let Some(bet) = bets.get(&settlement.bet) else {
+            report.unverifiable.push(settlement.signature.clone());
+            continue;
+        };
+        report.checked += 1;
+        if !bet.wins(lobby)? {
+            report.discrepancies.push(Discrepancy::PaidLoser {
+                bet: settlement.bet,
+                signature: settlement.signature.clone(),
+                payout: settlement.payout,
+            });
+            continue;
+        }
+        let owed = claims.claim(lobby, bet, snapshot)?;
+        // A payout below the minimum is swept to the treasury and logged as zero
+        let sweep = owed < config.min_payout_lamports;
+        let expected = if sweep { 0 } else { owed };
+        let swept = matches!(settlement.status, BetStatus::BelowMinimum);
+        if expected != settlement.payout || sweep != swept {
+            report.discrepancies.push(Discrepancy::WrongPayout {
+                bet: settlement.bet,
+                signature: settlement.signature.clone(),
+                expected,
+                actual: settlement.payout,
+            });
+        }
+    }
+
+    for (address, bet) in bets {
+        if settled.contains(address) || !bet.wins(lobby)? {
+            continue;
+        }
+        if matches!(bet.status, BetStatus::Paid | BetStatus::BelowMinimum) {
+            report
+                .discrepancies
+                .push(Discrepancy::MissingSettlement { bet: *address });
+        } else if bet.status.is_outstanding() {
+            let owed = logic::claimable_amount(lobby, bet, snapshot, config)?;
+            report.unclaimed.push((*address, owed));
+        }
+    }
+    Ok(report)
+}
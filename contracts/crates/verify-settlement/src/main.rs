@@ -0,0 +1,128 @@
+//! Re-derives what each bet on a finished lobby is owed from its on-chain
+//! `Bet` account and the declared winner, using the same payout math as the
+//! program, and compares that with the `BetSettled` events of the lobby's
+//! payout transactions.
+//!
+//! ```text
+//! verify-settlement <rpc-url> <lobby>
+//! ```
+//!
+//! Prints each discrepancy, then the winning bets still unclaimed, and exits
+//! with an error if any discrepancy was found. Bets closed since they were
+//! paid can't be checked; their payouts are listed as unverifiable, and the
+//! rounding remainder the last claim at a position receives may then be
+//! reported against the wrong bet. Lobbies settled from a Merkle root are
+//! not supported.
+
+mod check;
+mod rpc;
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, ensure, Context, Result};
+use poker_betting::{pda, Config, Lobby, LobbyStatus, PoolSnapshot};
+use poker_betting_events::{parse_transaction, ProgramEvent};
+
+use crate::check::Settlement;
+use crate::rpc::Rpc;
+
+/// Signatures requested per page; the RPC maximum.
+const PAGE_SIZE: usize = 1_000;
+
+/// `BetSettled` events of `lobby`, oldest first.
+fn settlements(rpc: &Rpc, lobby: &Pubkey) -> Result<Vec<Settlement>> {
+    let address = lobby.to_string();
+    let mut signatures = Vec::new();
+    let mut before: Option<String> = None;
+    loop {
+        let page = rpc.signatures(&address, before.as_deref(), PAGE_SIZE)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(last.signature.clone());
+        signatures.extend(page.into_iter().filter(|info| !info.failed));
+    }
+
+    let mut settlements = Vec::new();
+    for info in signatures.iter().rev() {
+        let Some(tx) = rpc.transaction(&info.signature)? else {
+            eprintln!("{} is no longer available, skipping", info.signature);
+            continue;
+        };
+        let parsed =
+            parse_transaction(&tx).with_context(|| format!("decoding {}", info.signature))?;
+        for error in &parsed.errors {
+            eprintln!("{}: skipping an event: {error}", info.signature);
+        }
+        for event in parsed.events {
+            if let ProgramEvent::BetSettled(settled) = event {
+                if settled.lobby == *lobby {
+                    settlements.push(Settlement {
+                        signature: info.signature.clone(),
+                        bet: settled.bet,
+                        status: settled.status,
+                        payout: settled.payout,
+                    });
+                }
+            }
+        }
+    }
+    Ok(settlements)
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(rpc_url), Some(lobby_address), None) = (args.next(), args.next(), args.next()) else {
+        bail!("usage: verify-settlement <rpc-url> <lobby>");
+    };
+    let lobby_key: Pubkey = lobby_address.parse().context("lobby address")?;
+
+    let rpc = Rpc::new(&rpc_url);
+    let lobby: Lobby = rpc
+        .account(&lobby_key)?
+        .with_context(|| format!("no lobby at {lobby_key}"))?;
+    ensure!(
+        lobby.status != LobbyStatus::Archived,
+        "lobby {lobby_key} is archived and no longer holds its pools"
+    );
+    ensure!(
+        lobby.status == LobbyStatus::Finished && lobby.winner.is_some(),
+        "lobby {lobby_key} has not finished"
+    );
+    ensure!(
+        lobby.payout_root.is_none(),
+        "lobby {lobby_key} settles from a Merkle root, not bet by bet"
+    );
+    let config: Config = rpc
+        .account(&pda::config_pda())?
+        .context("program config not found")?;
+    let snapshot: Option<PoolSnapshot> = if lobby.pools_locked {
+        let snapshot = rpc.account(&pda::snapshot_pda(&lobby_key))?;
+        Some(snapshot.context("pool snapshot not found")?)
+    } else {
+        None
+    };
+    let bets: HashMap<_, _> = rpc.bets(&lobby_key)?.into_iter().collect();
+    let settlements = settlements(&rpc, &lobby_key)?;
+
+    let report = check::verify(&lobby, &config, snapshot.as_ref(), &bets, &settlements)?;
+    for discrepancy in &report.discrepancies {
+        println!("{discrepancy}");
+    }
+    for (bet, owed) in &report.unclaimed {
+        println!("{bet}: unclaimed, owed {owed}");
+    }
+    for signature in &report.unverifiable {
+        println!("{signature}: paid a closed bet, not verified");
+    }
+    println!(
+        "{} payouts checked, {} discrepancies",
+        report.checked,
+        report.discrepancies.len()
+    );
+    if !report.discrepancies.is_empty() {
+        bail!("settlement of {lobby_key} does not match its bets");
+    }
+    Ok(())
+}
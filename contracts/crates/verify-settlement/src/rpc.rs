@@ -0,0 +1,145 @@
+//! The Solana JSON-RPC calls a settlement check makes.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use poker_betting::Bet;
+use serde_json::{json, Value};
+
+/// Attempts per request before giving up; public RPCs rate-limit bursts.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Offset of `lobby` in a `Bet` account, after the discriminator and bettor.
+const BET_LOBBY_OFFSET: usize = 8 + 32;
+
+pub struct Rpc {
+    url: String,
+    agent: ureq::Agent,
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub failed: bool,
+}
+
+impl Rpc {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut attempt = 1;
+        let mut response: Value = loop {
+            match self.agent.post(&self.url).send_json(&request) {
+                Ok(response) => break response.into_json()?,
+                Err(err) if attempt < MAX_ATTEMPTS => {
+                    eprintln!("{method} failed ({err}), retrying");
+                    sleep(Duration::from_millis(500 << attempt));
+                    attempt += 1;
+                }
+                Err(err) => return Err(err).context(method.to_string()),
+            }
+        };
+        if let Some(error) = response.get("error") {
+            bail!("{method}: {error}");
+        }
+        Ok(response["result"].take())
+    }
+
+    /// The account at `address` decoded as `T`, if it exists.
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>> {
+        let config = json!({ "commitment": "finalized", "encoding": "base64" });
+        let result = self.call("getAccountInfo", json!([address.to_string(), config]))?;
+        let value = &result["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        let data = STANDARD.decode(value["data"][0].as_str().context("account data")?)?;
+        let account = T::try_deserialize(&mut &data[..])
+            .with_context(|| format!("decoding account {address}"))?;
+        Ok(Some(account))
+    }
+
+    /// Every open bet account on `lobby` with its address. Bets closed after
+    /// settlement are gone and not returned.
+    pub fn bets(&self, lobby: &Pubkey) -> Result<Vec<(Pubkey, Bet)>> {
+        let config = json!({
+            "commitment": "finalized",
+            "encoding": "base64",
+            "filters": [
+                { "memcmp": { "offset": 0, "bytes": STANDARD.encode(Bet::DISCRIMINATOR), "encoding": "base64" } },
+                { "memcmp": { "offset": BET_LOBBY_OFFSET, "bytes": lobby.to_string() } },
+            ],
+        });
+        let result = self.call(
+            "getProgramAccounts",
+            json!([poker_betting::ID.to_string(), config]),
+        )?;
+        let accounts = result.as_array().context("getProgramAccounts result")?;
+        accounts
+            .iter()
+            .map(|entry| {
+                let address: Pubkey = entry["pubkey"].as_str().context("pubkey")?.parse()?;
+                let data = entry["account"]["data"][0]
+                    .as_str()
+                    .context("account data")?;
+                let data = STANDARD.decode(data)?;
+                let bet = Bet::try_deserialize(&mut &data[..])
+                    .with_context(|| format!("decoding bet {address}"))?;
+                Ok((address, bet))
+            })
+            .collect()
+    }
+
+    /// Up to `limit` signatures involving `address`, newest first, older than
+    /// `before` if given.
+    pub fn signatures(
+        &self,
+        address: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "limit": limit, "commitment": "finalized" });
+        if let Some(before) = before {
+            config["before"] = json!(before);
+        }
+        let result = self.call("getSignaturesForAddress", json!([address, config]))?;
+        let entries = result
+            .as_array()
+            .context("getSignaturesForAddress result")?;
+        entries
+            .iter()
+            .map(|entry| {
+                Ok(SignatureInfo {
+                    signature: entry["signature"]
+                        .as_str()
+                        .context("signature")?
+                        .to_string(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect()
+    }
+
+    /// The transaction in JSON encoding, or `None` if the node no longer has it.
+    pub fn transaction(&self, signature: &str) -> Result<Option<Value>> {
+        let config = json!({
+            "encoding": "json",
+            "commitment": "finalized",
+            "maxSupportedTransactionVersion": 0,
+        });
+        let result = self.call("getTransaction", json!([signature, config]))?;
+        Ok((!result.is_null()).then_some(result))
+    }
+}
@@ -0,0 +1,132 @@
+//! Mints compressed NFTs through Metaplex Bubblegum's `mint_v1`.
+//!
+//! The instruction is built by hand so the Metaplex crates are not needed. The
+//! program's achievement authority PDA must be the tree's creator or delegate.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+pub const BUBBLEGUM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
+/// `sha256("global:mint_v1")[..8]`
+const MINT_V1_DISCRIMINATOR: [u8; 8] = [145, 98, 192, 118, 184, 147, 118, 104];
+
+#[derive(AnchorSerialize)]
+enum TokenStandard {
+    NonFungible,
+}
+
+#[derive(AnchorSerialize)]
+enum TokenProgramVersion {
+    Original,
+}
+
+#[derive(AnchorSerialize)]
+struct Collection {
+    verified: bool,
+    key: Pubkey,
+}
+
+#[derive(AnchorSerialize)]
+struct Uses {
+    use_method: u8,
+    remaining: u64,
+    total: u64,
+}
+
+#[derive(AnchorSerialize)]
+struct Creator {
+    address: Pubkey,
+    verified: bool,
+    share: u8,
+}
+
+/// Bubblegum `MetadataArgs`, in field order.
+#[derive(AnchorSerialize)]
+struct MetadataArgs {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    edition_nonce: Option<u8>,
+    token_standard: Option<TokenStandard>,
+    collection: Option<Collection>,
+    uses: Option<Uses>,
+    token_program_version: TokenProgramVersion,
+    creators: Vec<Creator>,
+}
+
+/// Bubblegum program and the accounts `mint_v1` takes, in order.
+pub struct MintV1<'a, 'info> {
+    pub bubblegum_program: &'a AccountInfo<'info>,
+    pub tree_config: &'a AccountInfo<'info>,
+    pub leaf_owner: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    pub tree_delegate: &'a AccountInfo<'info>,
+    pub log_wrapper: &'a AccountInfo<'info>,
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+/// Mints an immutable, royalty-free leaf to `leaf_owner`, who is also its
+/// delegate. `signer_seeds` sign for the tree delegate.
+pub fn mint_v1(
+    accounts: MintV1,
+    name: String,
+    symbol: String,
+    uri: String,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let metadata = MetadataArgs {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points: 0,
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: Vec::new(),
+    };
+    let mut data = MINT_V1_DISCRIMINATOR.to_vec();
+    metadata.serialize(&mut data)?;
+
+    let ix = Instruction {
+        program_id: BUBBLEGUM_ID,
+        accounts: vec![
+            AccountMeta::new(accounts.tree_config.key(), false),
+            AccountMeta::new_readonly(accounts.leaf_owner.key(), false),
+            AccountMeta::new_readonly(accounts.leaf_owner.key(), false),
+            AccountMeta::new(accounts.merkle_tree.key(), false),
+            AccountMeta::new(accounts.payer.key(), true),
+            AccountMeta::new_readonly(accounts.tree_delegate.key(), true),
+            AccountMeta::new_readonly(accounts.log_wrapper.key(), false),
+            AccountMeta::new_readonly(accounts.compression_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.bubblegum_program.clone(),
+            accounts.tree_config.clone(),
+            accounts.leaf_owner.clone(),
+            accounts.merkle_tree.clone(),
+            accounts.payer.clone(),
+            accounts.tree_delegate.clone(),
+            accounts.log_wrapper.clone(),
+            accounts.compression_program.clone(),
+            accounts.system_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! CPI into SPL Account Compression for concurrent Merkle trees.
+//!
+//! Instructions are built by hand so the crate is not needed. The program's
+//! `bet_tree_authority` PDA is the authority of every tree it writes to.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+pub const ACCOUNT_COMPRESSION_ID: Pubkey = pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+pub const NOOP_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// `sha256("global:<name>")[..8]`
+const INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR: [u8; 8] = [191, 11, 119, 7, 180, 107, 220, 110];
+const APPEND_DISCRIMINATOR: [u8; 8] = [149, 120, 18, 222, 236, 225, 88, 203];
+const REPLACE_LEAF_DISCRIMINATOR: [u8; 8] = [204, 165, 76, 100, 73, 147, 0, 128];
+
+/// Compression program and the accounts every tree instruction takes.
+pub struct TreeAccounts<'a, 'info> {
+    pub compression_program: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+    pub noop: &'a AccountInfo<'info>,
+}
+
+fn invoke_tree<'info>(
+    accounts: TreeAccounts<'_, 'info>,
+    data: Vec<u8>,
+    proof: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut metas = vec![
+        AccountMeta::new(accounts.merkle_tree.key(), false),
+        AccountMeta::new_readonly(accounts.authority.key(), true),
+        AccountMeta::new_readonly(accounts.noop.key(), false),
+    ];
+    metas.extend(
+        proof
+            .iter()
+            .map(|node| AccountMeta::new_readonly(node.key(), false)),
+    );
+    let mut infos = vec![
+        accounts.compression_program.clone(),
+        accounts.merkle_tree.clone(),
+        accounts.authority.clone(),
+        accounts.noop.clone(),
+    ];
+    infos.extend_from_slice(proof);
+
+    let ix = Instruction {
+        program_id: ACCOUNT_COMPRESSION_ID,
+        accounts: metas,
+        data,
+    };
+    invoke_signed(&ix, &infos, signer_seeds)?;
+    Ok(())
+}
+
+/// Initializes a tree account the caller allocated and assigned to the
+/// compression program.
+pub fn init_empty_merkle_tree<'info>(
+    accounts: TreeAccounts<'_, 'info>,
+    max_depth: u32,
+    max_buffer_size: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR.to_vec();
+    (max_depth, max_buffer_size).serialize(&mut data)?;
+    invoke_tree(accounts, data, &[], signer_seeds)
+}
+
+pub fn append<'info>(
+    accounts: TreeAccounts<'_, 'info>,
+    leaf: [u8; 32],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = APPEND_DISCRIMINATOR.to_vec();
+    leaf.serialize(&mut data)?;
+    invoke_tree(accounts, data, &[], signer_seeds)
+}
+
+/// Replaces `previous_leaf` at `index`; fails unless `proof` leads from it
+/// to `root`, a root still in the tree's change log.
+#[allow(clippy::too_many_arguments)]
+pub fn replace_leaf<'info>(
+    accounts: TreeAccounts<'_, 'info>,
+    root: [u8; 32],
+    previous_leaf: [u8; 32],
+    new_leaf: [u8; 32],
+    index: u32,
+    proof: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = REPLACE_LEAF_DISCRIMINATOR.to_vec();
+    (root, previous_leaf, new_leaf, index).serialize(&mut data)?;
+    invoke_tree(accounts, data, proof, signer_seeds)
+}
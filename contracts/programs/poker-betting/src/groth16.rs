@@ -0,0 +1,103 @@
+//! Groth16 verification over BN254 using the alt_bn128 syscalls.
+//!
+//! Points use the EIP-197 encoding the syscalls expect: big-endian field
+//! elements, G1 as `x || y` (64 bytes) and G2 as `x_c1 || x_c0 || y_c1 || y_c0`
+//! (128 bytes).
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::alt_bn128::prelude::{
+    alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing,
+};
+
+use crate::BettingError;
+
+pub type G1 = [u8; 64];
+pub type G2 = [u8; 128];
+
+/// BN254 base field modulus.
+const BASE_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// BN254 scalar field modulus; public inputs must be below it.
+pub const SCALAR_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Proof {
+    pub a: G1,
+    pub b: G2,
+    pub c: G1,
+}
+
+/// Verifying key fields a verifier account stores.
+pub struct VerifyingKey<'a> {
+    pub alpha_g1: &'a G1,
+    pub beta_g2: &'a G2,
+    pub gamma_g2: &'a G2,
+    pub delta_g2: &'a G2,
+    /// One point per public input, plus the constant term first
+    pub ic: &'a [G1],
+}
+
+/// Checks `e(-A, B) · e(alpha, beta) · e(vk_x, gamma) · e(C, delta) == 1`
+/// where `vk_x = ic[0] + Σ inputs[i] · ic[i + 1]`.
+pub fn verify(vk: &VerifyingKey, proof: &Proof, inputs: &[[u8; 32]]) -> Result<bool> {
+    require!(
+        vk.ic.len() == inputs.len() + 1,
+        BettingError::InvalidVerifyingKey
+    );
+    require!(
+        inputs.iter().all(|input| *input < SCALAR_FIELD_MODULUS),
+        BettingError::InvalidPublicInput
+    );
+
+    let mut vk_x = vk.ic[0];
+    for (input, ic) in inputs.iter().zip(&vk.ic[1..]) {
+        let term = alt_bn128_multiplication(&[ic.as_slice(), input].concat())
+            .map_err(|_| error!(BettingError::InvalidProof))?;
+        let sum = alt_bn128_addition(&[vk_x.as_slice(), &term].concat())
+            .map_err(|_| error!(BettingError::InvalidProof))?;
+        vk_x.copy_from_slice(&sum);
+    }
+
+    let pairing_input = [
+        negate_g1(&proof.a).as_slice(),
+        &proof.b,
+        vk.alpha_g1,
+        vk.beta_g2,
+        &vk_x,
+        vk.gamma_g2,
+        &proof.c,
+        vk.delta_g2,
+    ]
+    .concat();
+    let result =
+        alt_bn128_pairing(&pairing_input).map_err(|_| error!(BettingError::InvalidProof))?;
+    Ok(result.last() == Some(&1) && result[..result.len() - 1].iter().all(|b| *b == 0))
+}
+
+/// `(x, y) -> (x, q - y)`; the point at infinity maps to itself.
+fn negate_g1(point: &G1) -> G1 {
+    let mut out = *point;
+    if point[32..].iter().all(|b| *b == 0) {
+        return out;
+    }
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let diff = BASE_FIELD_MODULUS[i] as i16 - point[32 + i] as i16 - borrow;
+        borrow = (diff < 0) as i16;
+        out[32 + i] = diff.rem_euclid(256) as u8;
+    }
+    out
+}
+
+/// Encodes a small integer as a big-endian field element.
+pub fn scalar_from_u64(value: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[24..].copy_from_slice(&value.to_be_bytes());
+    out
+}
@@ -0,0 +1,28 @@
+//! Funding a bet with a Jupiter swap in the same transaction.
+//!
+//! The program doesn't call Jupiter. The client fetches the swap instructions
+//! from Jupiter's `/swap-instructions` API with SOL as the output mint and
+//! `wrapAndUnwrapSol` off, so the output lands in the bettor's wrapped SOL
+//! account, and brackets them with `begin_bet_swap`, which records that
+//! account's balance, and `place_bet_with_swap`, which bets exactly the
+//! balance gained since then.
+
+use anchor_lang::solana_program::instruction::Instruction;
+
+/// Orders a swap-funded bet: compute budget instructions, Jupiter's setup
+/// instructions (such as creating the wrapped SOL account), `begin_bet_swap`,
+/// the swap itself, then `place_bet_with_swap`. Jupiter's cleanup instruction
+/// must be left out, as it would unwrap the output before the bet reads it.
+pub fn compose_bet_with_swap(
+    compute_budget: impl IntoIterator<Item = Instruction>,
+    setup: impl IntoIterator<Item = Instruction>,
+    begin: Instruction,
+    swap: Instruction,
+    place: Instruction,
+) -> Vec<Instruction> {
+    compute_budget
+        .into_iter()
+        .chain(setup)
+        .chain([begin, swap, place])
+        .collect()
+}
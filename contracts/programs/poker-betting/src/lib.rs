@@ -1,301 +1,12539 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_spl::token::{self, spl_token::native_mint, Mint, Token, TokenAccount};
+
+pub mod bubblegum;
+pub mod compression;
+pub mod groth16;
+pub mod jupiter;
+pub mod logic;
+pub mod math;
+pub mod merkle;
+pub mod pda;
+pub mod pyth;
+pub mod shielded;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod vrf;
+pub mod wormhole;
 
 declare_id!("85kCu1ahjWTXMmgbpmrXgKNL2DxrrWusYrTYWwA68NMq");
 
+/// SPL Governance program. A governance PDA owned by this program can be made the
+/// config admin so protocol parameters are changed through executed proposals.
+pub const SPL_GOVERNANCE_ID: Pubkey = pubkey!("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+
+/// Maximum number of AI models competing in a lobby.
+pub const MAX_MODELS: usize = 10;
+
+/// Upper bound on the protocol fee (10%).
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Maximum number of ISO 3166-1 alpha-2 region codes a config or lobby can restrict.
+pub const MAX_RESTRICTED_REGIONS: usize = 16;
+
+/// Delay before a loosened responsible-gambling limit takes effect.
+pub const LIMIT_INCREASE_DELAY: i64 = 24 * 60 * 60;
+
+/// Maximum length in bytes of a lobby feed message.
+pub const MAX_MESSAGE_LEN: usize = 140;
+
+/// Number of most recent messages kept in a lobby's feed.
+pub const FEED_CAPACITY: usize = 16;
+
+/// Decimal odds are stored in basis points: 25_000 means a 2.5x return on stake.
+pub use math::ODDS_SCALE;
+
+/// Fixed-point iterations used to size a hedge against odds that move with it.
+pub const HEDGE_ITERATIONS: usize = 4;
+
+/// Fixed-point scale for the staking pool's accumulated reward per staked token.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+/// Capacity of the screening denylist.
+pub const MAX_DENYLIST_ENTRIES: usize = 256;
+
+/// Accounts per bet in `claim_all`'s remaining accounts.
+pub const CLAIM_GROUP_LEN: usize = 5;
+
+/// Public inputs of the outcome circuit: transcript commitment and winner index.
+pub const OUTCOME_PUBLIC_INPUTS: usize = 2;
+
+/// Maximum buckets in a lobby's final-chip-count market.
+pub const MAX_CHIP_BUCKETS: usize = 8;
+
+/// Maximum length of the achievement metadata base URI.
+pub const MAX_ACHIEVEMENT_URI_LEN: usize = 128;
+
+/// Time bettors have to challenge a posted payout root before claims open.
+pub const PAYOUT_CHALLENGE_WINDOW: i64 = 24 * 60 * 60;
+
+/// Share of an unrevealed blind bet kept by the treasury on refund.
+pub const BLIND_BET_PENALTY_BPS: u64 = 1_000;
+
+/// Public inputs of the bounty event circuit: statement and hand binding.
+pub const BOUNTY_PUBLIC_INPUTS: usize = 2;
+
+/// Settled lobbies an operator's reputation loses per dispute lost.
+pub const DISPUTE_REPUTATION_PENALTY: u64 = 5;
+
+/// Lamports bet on a lobby for its settlement to count toward its operator's
+/// reputation, so empty lobbies can't farm it.
+pub const MIN_REPUTATION_VOLUME: u64 = LAMPORTS_PER_SOL;
+
+/// Delay before a rotated results authority takes over a lobby.
+pub const RESULTS_AUTHORITY_DELAY: i64 = 24 * 60 * 60;
+
+/// Most volume tiers the fee schedule can have.
+pub const MAX_FEE_TIERS: usize = 4;
+
+/// Maximum length in bytes of a bet's memo.
+pub const MAX_BET_MEMO_LEN: usize = 32;
+
+/// Most recipients a lobby's rake can be split between.
+pub const MAX_RAKE_RECIPIENTS: usize = 4;
+
+/// Most levels a lobby's blinds schedule can have.
+pub const MAX_BLIND_LEVELS: usize = 8;
+
+/// Most times a lobby's game terms can be amended.
+pub const MAX_TERM_AMENDMENTS: usize = 4;
+
+/// Most lobbies a streak series can run over.
+pub const MAX_STREAK_LOBBIES: usize = 32;
+
+/// Most streak lengths a series can pay a prize for.
+pub const MAX_STREAK_PRIZES: usize = 10;
+
+/// Longest game id a lobby accepts. Lobbies are seeded by the id's hash, so
+/// this only bounds the account's size.
+pub const MAX_GAME_ID_LEN: usize = 256;
+
+/// Longest lobby template name; templates are seeded by the name itself.
+pub const MAX_TEMPLATE_NAME_LEN: usize = 32;
+
+/// Most hand markets a lobby can have open at once.
+pub const MAX_OPEN_HAND_MARKETS: usize = 16;
+
+/// Accounts per hand market `checkpoint_settle` takes: the market and the
+/// engine's record of its hand.
+pub const CHECKPOINT_GROUP_LEN: usize = 2;
+
+/// Time after a lobby finishes its losing bettors have to enter its raffle.
+pub const RAFFLE_ENTRY_WINDOW: i64 = 24 * 60 * 60;
+
+/// How long after a lobby finishes its model operators can claim their API
+/// cost reimbursement.
+pub const REIMBURSEMENT_CLAIM_WINDOW: i64 = 30 * 24 * 60 * 60;
+
+/// How long after a crank becomes possible a keeper doing it earns the crank
+/// fee.
+pub const KEEPER_FEE_WINDOW: i64 = 60 * 60;
+
+/// Time a retired keeper's stake stays slashable before it can be withdrawn.
+pub const KEEPER_UNBONDING_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+/// Time after the first operator registers that every registered operator
+/// has to post its match stake, if the game hasn't started first.
+pub const MATCH_STAKE_WINDOW: i64 = 24 * 60 * 60;
+
+// Seeds of the program's PDAs. Off-chain code should derive addresses with
+// the helpers in `pda` rather than repeating these.
+pub const ACHIEVEMENT_SEED: &[u8] = b"achievement";
+pub const ACHIEVEMENT_AUTHORITY_SEED: &[u8] = b"achievement_authority";
+pub const ACHIEVEMENT_CONFIG_SEED: &[u8] = b"achievement_config";
+pub const ATTESTATION_SEED: &[u8] = b"attestation";
+pub const BET_SEED: &[u8] = b"bet";
+pub const BET_TREE_AUTHORITY_SEED: &[u8] = b"bet_tree_authority";
+pub const BETTOR_STATS_SEED: &[u8] = b"bettor_stats";
+pub const BLIND_BET_SEED: &[u8] = b"blind_bet";
+pub const BOND_CLAIM_SEED: &[u8] = b"bond_claim";
+pub const BOUNTY_SEED: &[u8] = b"bounty";
+pub const BOUNTY_CONTRIBUTION_SEED: &[u8] = b"bounty_contribution";
+pub const BOUNTY_VERIFIER_SEED: &[u8] = b"bounty_verifier";
+pub const BRIDGE_CONFIG_SEED: &[u8] = b"bridge_config";
+pub const CHIP_BET_SEED: &[u8] = b"chip_bet";
+pub const CONDITIONAL_SEED: &[u8] = b"conditional";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const CONSUMED_VAA_SEED: &[u8] = b"consumed_vaa";
+pub const DENYLIST_SEED: &[u8] = b"denylist";
+pub const DEPOSIT_VAULT_SEED: &[u8] = b"deposit_vault";
+pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const FEED_SEED: &[u8] = b"feed";
+pub const FOLLOW_SEED: &[u8] = b"follow";
+pub const FREEROLL_PICK_SEED: &[u8] = b"freeroll_pick";
+pub const GAME_CONFIG_SEED: &[u8] = b"game_config";
+pub const HAND_BET_SEED: &[u8] = b"hand_bet";
+pub const HAND_ESCROW_SEED: &[u8] = b"hand_escrow";
+pub const HAND_MARKET_SEED: &[u8] = b"hand_market";
+pub const KEEPER_SEED: &[u8] = b"keeper";
+pub const LAY_BET_SEED: &[u8] = b"lay_bet";
+pub const LOBBY_SEED: &[u8] = b"lobby";
+pub const LOBBY_TEMPLATE_SEED: &[u8] = b"lobby_template";
+pub const MATCH_STAKE_SEED: &[u8] = b"match_stake";
+pub const MERKLE_CLAIM_SEED: &[u8] = b"merkle_claim";
+pub const NULLIFIER_SEED: &[u8] = b"nullifier";
+pub const OPERATOR_BOND_SEED: &[u8] = b"operator_bond";
+pub const OPERATOR_PROFILE_SEED: &[u8] = b"operator_profile";
+pub const ORDER_SEED: &[u8] = b"order";
+pub const OUTCOME_VERIFIER_SEED: &[u8] = b"outcome_verifier";
+pub const OWNER_STATS_SEED: &[u8] = b"owner_stats";
+pub const PLAYER_POOL_SEED: &[u8] = b"player_pool";
+pub const POSITION_SEED: &[u8] = b"position";
+pub const REVENUE_SEED: &[u8] = b"revenue";
+pub const SEASON_SEED: &[u8] = b"season";
+pub const SELF_EXCLUSION_SEED: &[u8] = b"self_exclusion";
+pub const SHIELDED_POOL_SEED: &[u8] = b"shielded_pool";
+pub const SHIELDED_VERIFIER_SEED: &[u8] = b"shielded_verifier";
+pub const SNAPSHOT_SEED: &[u8] = b"snapshot";
+pub const SPECTATOR_PASS_SEED: &[u8] = b"spectator_pass";
+pub const STAKE_SEED: &[u8] = b"stake";
+pub const STAKE_POOL_SEED: &[u8] = b"stake_pool";
+pub const STAKE_VAULT_SEED: &[u8] = b"stake_vault";
+pub const STREAK_SEED: &[u8] = b"streak";
+pub const STREAK_SERIES_SEED: &[u8] = b"streak_series";
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+pub const SWAP_RECEIPT_SEED: &[u8] = b"swap_receipt";
+pub const TREASURY_SEED: &[u8] = b"treasury";
+pub const WEEKLY_PAYOUT_SEED: &[u8] = b"weekly_payout";
+
+/// Version of the event layout, logged through `sol_log_data` as an 8-byte
+/// discriminator followed by the Borsh-encoded event. Events only gain fields
+/// at the end; this is bumped whenever one does, an event is added or an enum
+/// an event carries, such as `LobbyAction`, gains a variant.
+#[constant]
+pub const EVENT_SCHEMA_VERSION: u8 = 8;
+
+/// Paid wins needed for the `TenWins` achievement.
+pub const TEN_WINS: u64 = 10;
+
 #[program]
 pub mod poker_betting {
     use super::*;
 
-    pub fn create_lobby(
-        ctx: Context<CreateLobby>,
-        game_id: String,
-        model_names: Vec<String>,
-        starting_chips: u64,
-        small_blind: u64,
-        big_blind: u64,
-        max_hands: u64,
-    ) -> Result<()> {
-        let lobby = &mut ctx.accounts.lobby;
-        lobby.owner = ctx.accounts.owner.key();
-        lobby.game_id = game_id;
-        lobby.model_names = model_names;
-        lobby.starting_chips = starting_chips;
-        lobby.small_blind = small_blind;
-        lobby.big_blind = big_blind;
-        lobby.max_hands = max_hands;
-        lobby.status = LobbyStatus::Waiting;
-        lobby.total_bets = 0;
-        lobby.created_at = Clock::get()?.unix_timestamp;
-        lobby.updated_at = Clock::get()?.unix_timestamp;
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, BettingError::InvalidFeeBps);
 
-        // Fund the escrow PDA with rent-exempt minimum so it can receive CPI transfers
-        // The minimum rent-exempt balance for a 0-byte account is ~890880 lamports
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.fee_bps = fee_bps;
+        config.paused = false;
+        config.revenue_share_bps = 0;
+        config.accrued_revenue_share = 0;
+        config.max_bets_per_window = 0;
+        config.bet_window = 0;
+        config.lobby_creation_cooldown = 0;
+        config.compliance_mode = ComplianceMode::Open;
+        config.restricted_regions = Vec::new();
+        config.attestation_required = false;
+        config.enclave_measurement = None;
+        config.enclave_signer = None;
+        config.min_payout_lamports = 0;
+        config.season = 0;
+        config.volume_points_bps = 0;
+        config.win_points_bps = 0;
+        config.min_operator_bond = 0;
+        config.settlement_window = 0;
+        config.min_operator_reputation = 0;
+        config.fee_tiers = Vec::new();
+        config.guard_bet_composition = false;
+        config.admin_timelock = 0;
+        config.pending_change = None;
+        config.pending_change_eta = 0;
+        config.expected_upgrade_authority = None;
+        config.program_frozen = false;
+        config.usd_price_feed = None;
+        config.max_price_age = 0;
+        config.max_price_conf_bps = 0;
+        config.min_bet_usd = 0;
+        config.max_bet_usd = 0;
+        config.custody = None;
+        config.custody_threshold = 0;
+        config.updated_at = Clock::get()?.unix_timestamp;
+
+        // Fund the treasury PDA the same way lobby escrows are funded
         let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
-        
+
         anchor_lang::solana_program::program::invoke(
             &anchor_lang::solana_program::system_instruction::transfer(
-                ctx.accounts.owner.key,
-                ctx.accounts.escrow.key,
+                ctx.accounts.admin.key,
+                ctx.accounts.treasury.key,
                 rent_exempt_minimum,
             ),
             &[
-                ctx.accounts.owner.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_admin(ctx: Context<AdminAction>, new_admin: Pubkey) -> Result<()> {
+        set_config_directly(&mut ctx.accounts.config, ConfigChange::Admin(new_admin))
+    }
+
+    /// Hands the config over to an SPL Governance account. After this, every admin
+    /// instruction must be executed from a proposal of that governance.
+    pub fn assign_governance(ctx: Context<AssignGovernance>) -> Result<()> {
+        let governance = ctx.accounts.governance.key();
+        set_config_directly(&mut ctx.accounts.config, ConfigChange::Admin(governance))
+    }
+
+    pub fn set_fee_bps(ctx: Context<AdminAction>, fee_bps: u16) -> Result<()> {
+        set_config_directly(&mut ctx.accounts.config, ConfigChange::FeeBps(fee_bps))
+    }
+
+    /// Queues a sensitive config update, executable once `admin_timelock` has
+    /// passed. Only one change can be pending at a time.
+    pub fn propose_config_change(ctx: Context<AdminAction>, change: ConfigChange) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let now = Clock::get()?.unix_timestamp;
+        let eta = logic::propose_config_change(config, change.clone(), now)?;
+        emit!(ConfigChangeProposed { change, eta });
+        Ok(())
+    }
+
+    pub fn execute_config_change(ctx: Context<AdminAction>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let change = logic::execute_config_change(config, Clock::get()?.unix_timestamp)?;
+        emit!(ConfigChangeExecuted { change });
+        Ok(())
+    }
+
+    pub fn cancel_config_change(ctx: Context<AdminAction>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let change = config
+            .pending_change
+            .take()
+            .ok_or(BettingError::NoPendingConfigChange)?;
+        config.pending_change_eta = 0;
+        emit!(ConfigChangeCancelled { change });
+        Ok(())
+    }
+
+    /// Sets how many season points, in basis points per lamport, wagering and
+    /// winning earn.
+    pub fn set_points_weights(
+        ctx: Context<AdminAction>,
+        volume_points_bps: u16,
+        win_points_bps: u16,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.volume_points_bps = volume_points_bps;
+        config.win_points_bps = win_points_bps;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Sets the bond new lobbies' owners must post and the deadline they must
+    /// finish by to keep it.
+    pub fn set_bond_requirements(
+        ctx: Context<AdminAction>,
+        min_operator_bond: u64,
+        settlement_window: i64,
+    ) -> Result<()> {
+        set_config_directly(
+            &mut ctx.accounts.config,
+            ConfigChange::BondRequirements {
+                min_operator_bond,
+                settlement_window,
+            },
+        )
+    }
+
+    /// Sets the volume tiers whose bettors are charged a lower fee on every bet.
+    /// Higher tiers need more volume and charge no more.
+    pub fn set_fee_tiers(ctx: Context<AdminAction>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+        set_config_directly(&mut ctx.accounts.config, ConfigChange::FeeTiers(fee_tiers))
+    }
+
+    /// Gates lobby creation on the owner's `OperatorProfile` reputation.
+    pub fn set_min_operator_reputation(
+        ctx: Context<AdminAction>,
+        min_operator_reputation: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.min_operator_reputation = min_operator_reputation;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Turns the check that bets aren't composed with settlement in one
+    /// transaction on or off. Clients must pass the instructions sysvar to
+    /// `place_bet` while it is on.
+    pub fn set_bet_composition_guard(ctx: Context<AdminAction>, enabled: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.guard_bet_composition = enabled;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Records the upgrade authority the program should have, or `None` if it
+    /// should be frozen, for `verify_program_authority` to check against.
+    pub fn set_expected_upgrade_authority(
+        ctx: Context<AdminAction>,
+        authority: Option<Pubkey>,
+    ) -> Result<()> {
+        set_config_directly(
+            &mut ctx.accounts.config,
+            ConfigChange::ExpectedUpgradeAuthority(authority),
+        )
+    }
+
+    /// Returns the program's live upgrade authority, failing if it differs
+    /// from the one recorded in config. Clients simulate it to warn about a
+    /// single-key upgrade authority, or prepend it to a bet as a guard.
+    pub fn verify_program_authority(
+        ctx: Context<VerifyProgramAuthority>,
+    ) -> Result<ProgramAuthority> {
+        let config = &ctx.accounts.config;
+        let upgrade_authority = ctx.accounts.program_data.upgrade_authority_address;
+        let recorded = config.program_frozen || config.expected_upgrade_authority.is_some();
+        if recorded {
+            require!(
+                upgrade_authority == config.expected_upgrade_authority,
+                BettingError::UpgradeAuthorityMismatch
+            );
+        }
+        Ok(ProgramAuthority {
+            upgrade_authority,
+            recorded,
+        })
+    }
+
+    /// Sets the Pyth SOL/USD feed and how fresh and tight its price must be,
+    /// and the USD bounds every bet must fall within.
+    pub fn set_usd_pricing(
+        ctx: Context<AdminAction>,
+        usd_price_feed: Option<[u8; 32]>,
+        max_price_age: i64,
+        max_price_conf_bps: u16,
+        min_bet_usd: u64,
+        max_bet_usd: u64,
+    ) -> Result<()> {
+        require!(
+            max_price_age > 0 && max_price_conf_bps as u64 <= math::BPS_DENOMINATOR,
+            BettingError::InvalidPriceSettings
+        );
+        require!(
+            max_bet_usd == 0 || min_bet_usd <= max_bet_usd,
+            BettingError::InvalidPriceSettings
+        );
+        require!(
+            usd_price_feed.is_some() || (min_bet_usd == 0 && max_bet_usd == 0),
+            BettingError::InvalidPriceSettings
+        );
+        let config = &mut ctx.accounts.config;
+        config.usd_price_feed = usd_price_feed;
+        config.max_price_age = max_price_age;
+        config.max_price_conf_bps = max_price_conf_bps;
+        config.min_bet_usd = min_bet_usd;
+        config.max_bet_usd = max_bet_usd;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Closes the current season with the Merkle root of every bettor's
+    /// points in it, computed off-chain from `BettorStats`, for airdrop claims.
+    /// Points start over in the next season.
+    pub fn finalize_season(
+        ctx: Context<FinalizeSeason>,
+        merkle_root: [u8; 32],
+        total_points: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let config = &mut ctx.accounts.config;
+        let season = &mut ctx.accounts.season;
+        season.season = config.season;
+        season.merkle_root = merkle_root;
+        season.total_points = total_points;
+        season.finalized_at = now;
+
+        config.season = config.season.checked_add(1).ok_or(BettingError::Overflow)?;
+        config.updated_at = now;
+        Ok(())
+    }
+
+    /// Winnings below `min_payout_lamports` aren't worth a claim; they are
+    /// swept to the treasury at settlement instead.
+    pub fn set_min_payout(ctx: Context<AdminAction>, min_payout_lamports: u64) -> Result<()> {
+        set_config_directly(
+            &mut ctx.accounts.config,
+            ConfigChange::MinPayout(min_payout_lamports),
+        )
+    }
+
+    /// Adds `wallet` to the screening denylist, blocking its bets and payouts.
+    pub fn add_to_denylist(ctx: Context<ManageDenylist>, wallet: Pubkey) -> Result<()> {
+        let denylist = &mut ctx.accounts.denylist;
+        if let Err(index) = denylist.entries.binary_search(&wallet) {
+            require!(
+                denylist.entries.len() < MAX_DENYLIST_ENTRIES,
+                BettingError::DenylistFull
+            );
+            denylist.entries.insert(index, wallet);
+        }
+        denylist.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn remove_from_denylist(ctx: Context<ManageDenylist>, wallet: Pubkey) -> Result<()> {
+        let denylist = &mut ctx.accounts.denylist;
+        if let Ok(index) = denylist.entries.binary_search(&wallet) {
+            denylist.entries.remove(index);
+        }
+        denylist.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn set_paused(ctx: Context<AdminAction>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.paused = paused;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn set_revenue_share_bps(ctx: Context<AdminAction>, revenue_share_bps: u16) -> Result<()> {
+        set_config_directly(
+            &mut ctx.accounts.config,
+            ConfigChange::RevenueShareBps(revenue_share_bps),
+        )
+    }
+
+    /// Share of each bet's fee, net of revenue share, set aside for the
+    /// lobby's consolation raffle among losing bettors.
+    pub fn set_raffle_bps(ctx: Context<AdminAction>, raffle_bps: u16) -> Result<()> {
+        set_config_directly(&mut ctx.accounts.config, ConfigChange::RaffleBps(raffle_bps))
+    }
+
+    /// Fee charged on the stake when a bettor switches a bet's model, in basis
+    /// points. Zero makes switching free.
+    pub fn set_selection_change_fee_bps(
+        ctx: Context<AdminAction>,
+        selection_change_fee_bps: u16,
+    ) -> Result<()> {
+        set_config_directly(
+            &mut ctx.accounts.config,
+            ConfigChange::SelectionChangeFeeBps(selection_change_fee_bps),
+        )
+    }
+
+    /// Sets the stake keepers need and the fee they earn per crank step.
+    pub fn set_keeper_terms(
+        ctx: Context<AdminAction>,
+        min_keeper_stake: u64,
+        keeper_fee: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.min_keeper_stake = min_keeper_stake;
+        config.keeper_fee = keeper_fee;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// A zero value disables the corresponding limit.
+    pub fn set_rate_limits(
+        ctx: Context<AdminAction>,
+        max_bets_per_window: u32,
+        bet_window: i64,
+        lobby_creation_cooldown: i64,
+    ) -> Result<()> {
+        require!(
+            bet_window >= 0 && lobby_creation_cooldown >= 0,
+            BettingError::InvalidRateLimit
+        );
+        let config = &mut ctx.accounts.config;
+        config.max_bets_per_window = max_bets_per_window;
+        config.bet_window = bet_window;
+        config.lobby_creation_cooldown = lobby_creation_cooldown;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Trusts results signed by `enclave_signer`, a key whose SGX/Nitro
+    /// attestation report shows it was generated by an enclave running
+    /// `measurement`. The report is checked by governance before this is set;
+    /// pass `None` to stop accepting attested results.
+    pub fn set_enclave(
+        ctx: Context<AdminAction>,
+        measurement: Option<[u8; 32]>,
+        enclave_signer: Option<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.enclave_measurement = measurement;
+        config.enclave_signer = enclave_signer;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Protocol-wide compliance defaults. New lobbies inherit them; the region list
+    /// and attestation bit also apply on top of each lobby's own settings.
+    pub fn set_compliance(
+        ctx: Context<AdminAction>,
+        compliance_mode: ComplianceMode,
+        restricted_regions: Vec<[u8; 2]>,
+        attestation_required: bool,
+    ) -> Result<()> {
+        require!(
+            restricted_regions.len() <= MAX_RESTRICTED_REGIONS,
+            BettingError::TooManyRegions
+        );
+        let config = &mut ctx.accounts.config;
+        config.compliance_mode = compliance_mode;
+        config.restricted_regions = restricted_regions;
+        config.attestation_required = attestation_required;
+        config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Moves lamports that reached a lobby escrow outside of the program's own
+    /// flows (direct transfers) to the treasury. Tracked funds are never touched.
+    pub fn sweep_excess(ctx: Context<SweepExcess>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let escrow = &ctx.accounts.escrow;
+        let excess = escrow
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0))
+            .saturating_sub(lobby.escrow_balance);
+        require!(excess > 0, BettingError::NothingToClaim);
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                escrow.key,
+                ctx.accounts.treasury.key,
+                excess,
+            ),
+            &[
+                escrow.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+
+        let seq = touch_lobby(lobby, LobbyAction::EscrowSwept)?;
+        emit!(EscrowSwept {
+            lobby: lobby_key,
+            seq,
+            amount: excess,
+        });
+        Ok(())
+    }
+
+    /// Sets the custody account and the escrow a running lobby keeps hot.
+    pub fn set_custody(
+        ctx: Context<AdminAction>,
+        custody: Option<Pubkey>,
+        threshold: u64,
+    ) -> Result<()> {
+        set_config_directly(
+            &mut ctx.accounts.config,
+            ConfigChange::Custody { custody, threshold },
+        )
+    }
+
+    /// Moves a running lobby's escrow above `custody_threshold` to custody.
+    /// Anyone can crank it; the lamports stay owed to the lobby and come back
+    /// through `fund_settlement` before winnings are paid.
+    pub fn sweep_to_custody(ctx: Context<SweepToCustody>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let lobby = &mut ctx.accounts.lobby;
+        let custody = ctx.accounts.custody.key();
+        require!(
+            config.custody == Some(custody) && (lobby.in_custody == 0 || lobby.custody == custody),
+            BettingError::InvalidCustody
+        );
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        let amount = lobby
+            .escrow_balance
+            .saturating_sub(config.custody_threshold);
+        require!(amount > 0, BettingError::NothingToClaim);
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                &custody,
+                amount,
+            ),
+            &[
                 ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.custody.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(amount)?;
+        lobby.custody = custody;
+        lobby.in_custody = lobby
+            .in_custody
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::CustodySwept)?;
+        Ok(())
+    }
+
+    /// Returns `amount` of a lobby's swept escrow from custody, which signs.
+    pub fn fund_settlement(ctx: Context<FundSettlement>, amount: u64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.custody == ctx.accounts.custody.key(),
+            BettingError::InvalidCustody
+        );
+        lobby.in_custody = lobby
+            .in_custody
+            .checked_sub(amount)
+            .ok_or(BettingError::Overflow)?;
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.custody.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+        lobby.track_deposit(amount)?;
+        touch_lobby(lobby, LobbyAction::SettlementFunded)?;
+        Ok(())
+    }
+
+    pub fn treasury_spend(ctx: Context<TreasurySpend>, amount: u64) -> Result<()> {
+        let treasury = &ctx.accounts.treasury;
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
+        let available = treasury
+            .lamports()
+            .checked_sub(rent_exempt_minimum)
+            .and_then(|v| v.checked_sub(ctx.accounts.config.accrued_revenue_share))
+            .and_then(|v| v.checked_sub(ctx.accounts.config.reserved_rake))
+            .ok_or(BettingError::InsufficientTreasury)?;
+        require!(amount <= available, BettingError::InsufficientTreasury);
+
+        let seeds: &[&[u8]] = &[TREASURY_SEED, &[ctx.bumps.treasury]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                treasury.key,
+                ctx.accounts.recipient.key,
+                amount,
+            ),
+            &[
+                treasury.to_account_info(),
+                ctx.accounts.recipient.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
+            &[seeds],
         )?;
 
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_lobby(
+        ctx: Context<CreateLobby>,
+        game_id: String,
+        model_names: Vec<String>,
+        starting_chips: u64,
+        blinds: Vec<BlindLevel>,
+        max_hands: u64,
+        payout_curve: Vec<u16>,
+        chip_buckets: Vec<u64>,
+        rake_splits: Vec<RakeSplit>,
+    ) -> Result<()> {
+        // A continuation ignores the declared settings passed in and carries
+        // over its parent's
+        let terms = match &ctx.accounts.parent_lobby {
+            Some(parent) => {
+                require!(
+                    parent.owner == ctx.accounts.owner.key(),
+                    BettingError::Unauthorized
+                );
+                require!(
+                    !parent.status.is_over() && parent.continuation.is_none(),
+                    BettingError::InvalidParentLobby
+                );
+                LobbyTerms {
+                    model_names: parent.model_names.clone(),
+                    starting_chips: parent.starting_chips,
+                    blinds: parent.blinds.clone(),
+                    max_hands,
+                    payout_curve: parent.payout_curve.clone(),
+                    chip_buckets: parent.chip_buckets.clone(),
+                    rake_splits: parent.rake_splits.clone(),
+                }
+            }
+            None => LobbyTerms {
+                model_names,
+                starting_chips,
+                blinds,
+                max_hands,
+                payout_curve,
+                chip_buckets,
+                rake_splits,
+            },
+        };
+        open_lobby(ctx.accounts.lobby_accounts(), game_id, terms)
+    }
+
+    /// `client_id` and `memo` are the caller's own references for the bet, kept
+    /// on it and echoed in `BetPlaced`. A non-zero `client_id` also makes the
+    /// call idempotent: resubmitting a bet that already landed succeeds without
+    /// charging again.
     pub fn place_bet(
         ctx: Context<PlaceBet>,
         player_name: String,
         amount: u64,
+        min_expected_odds_bps: u32,
+        side: BetSide,
+        client_id: [u8; 16],
+        memo: Option<String>,
     ) -> Result<()> {
         msg!("Entry: place_bet");
         msg!("Crate ID: {}", crate::ID);
         msg!("Program ID from ctx: {}", ctx.program_id);
+        let bet = &ctx.accounts.bet;
+        if bet.bettor != Pubkey::default() {
+            require!(
+                client_id != [0; 16] && bet.client_id == client_id,
+                BettingError::BetAlreadyPlaced
+            );
+            require!(
+                bet.player_name == player_name
+                    && bet.side == side
+                    && bet.requested_amount == amount,
+                BettingError::ClientIdReused
+            );
+            return Ok(());
+        }
 
-        let lobby = &mut ctx.accounts.lobby;
-        let bet = &mut ctx.accounts.bet;
-        let bettor = &ctx.accounts.bettor;
-        let escrow = &ctx.accounts.escrow;
+        place_bet_with(
+            ctx.accounts.bet_accounts(),
+            ctx.program_id,
+            player_name,
+            amount,
+            min_expected_odds_bps,
+            side,
+            client_id,
+            memo,
+        )
+    }
 
+    /// Starts the lobby, moving it from `Waiting` to `Running`. It finishes
+    /// only through `finish_game`, and is archived only by `archive_lobby`.
+    pub fn update_lobby_status(ctx: Context<UpdateLobbyStatus>, status: LobbyStatus) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
         require!(
-            lobby.status == LobbyStatus::Waiting || lobby.status == LobbyStatus::Running,
-            BettingError::LobbyNotOpenForBets
+            status != LobbyStatus::Finished,
+            BettingError::FinishThroughFinishGame
         );
         require!(
-            lobby.model_names.contains(&player_name),
-            BettingError::InvalidPlayerName
+            lobby.status != LobbyStatus::Archived && status != LobbyStatus::Archived,
+            BettingError::LobbyArchived
         );
-        require!(amount > 0, BettingError::BetAmountMustBePositive);
+        transition_lobby(lobby, status)?;
+        touch_lobby(lobby, LobbyAction::StatusUpdated)?;
+        Ok(())
+    }
 
-        // Use Anchor's CPI helper which properly handles account permissions
-        let cpi_accounts = anchor_lang::system_program::Transfer {
-            from: bettor.to_account_info(),
-            to: escrow.to_account_info(),
-        };
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            cpi_accounts,
+    /// Requires bettors to present an attestation from `issuer` before betting.
+    /// Pass `None` to lift the requirement.
+    pub fn set_attestation_issuer(
+        ctx: Context<UpdateLobbyStatus>,
+        issuer: Option<Pubkey>,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting,
+            BettingError::LobbyAlreadyStarted
         );
-        anchor_lang::system_program::transfer(cpi_context, amount)?;
+        lobby.attestation_issuer = issuer;
+        touch_lobby(lobby, LobbyAction::AttestationIssuerSet)?;
+        Ok(())
+    }
+
+    pub fn set_lobby_compliance(
+        ctx: Context<UpdateLobbyStatus>,
+        compliance_mode: ComplianceMode,
+        restricted_regions: Vec<[u8; 2]>,
+        attestation_required: bool,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            restricted_regions.len() <= MAX_RESTRICTED_REGIONS,
+            BettingError::TooManyRegions
+        );
+        lobby.compliance_mode = compliance_mode;
+        lobby.restricted_regions = restricted_regions;
+        lobby.attestation_required = attestation_required;
+        touch_lobby(lobby, LobbyAction::ComplianceUpdated)?;
+        Ok(())
+    }
+
+    /// Bets are refused from `deadline` on (zero for no deadline), after which
+    /// anyone can lock the pools.
+    pub fn set_betting_deadline(ctx: Context<UpdateLobbyStatus>, deadline: i64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(!lobby.pools_locked, BettingError::BettingClosed);
+        lobby.betting_deadline = deadline;
+        touch_lobby(lobby, LobbyAction::DeadlineSet)?;
+        Ok(())
+    }
+
+    /// Amends the hand limit and blinds before the game starts, recording the
+    /// new terms in the lobby's amendment history. Once betting closes the
+    /// terms are final, so they can't change under locked bets.
+    pub fn amend_game_terms(
+        ctx: Context<UpdateLobbyStatus>,
+        max_hands: u64,
+        blinds: Vec<BlindLevel>,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            !lobby.pools_locked && !lobby.betting_closed(Clock::get()?.unix_timestamp),
+            BettingError::TermsLocked
+        );
+        require!(
+            lobby.status == LobbyStatus::Waiting && lobby.game_config.is_none(),
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            lobby.term_amendments.len() < MAX_TERM_AMENDMENTS,
+            BettingError::TooManyAmendments
+        );
+        require!(BlindLevel::valid(&blinds), BettingError::InvalidBlinds);
+        lobby.max_hands = max_hands;
+        lobby.blinds = blinds.clone();
+        lobby.term_amendments.push(TermAmendment {
+            max_hands,
+            blinds,
+            amended_at: Clock::get()?.unix_timestamp,
+        });
+        touch_lobby(lobby, LobbyAction::TermsAmended)?;
+        Ok(())
+    }
+
+    /// Prices a spectator pass for the lobby's stream; zero stops pass sales.
+    pub fn set_spectator_fee(ctx: Context<UpdateLobbyStatus>, fee: u64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        lobby.spectator_fee = fee;
+        touch_lobby(lobby, LobbyAction::SpectatorFeeSet)?;
+        Ok(())
+    }
+
+    /// Makes the lobby a charity round: `charity_bps` of the losing pool goes
+    /// to `charity` when the game finishes instead of to the winners. Must be
+    /// set before any bet is placed; `None` turns it off.
+    pub fn set_charity(
+        ctx: Context<UpdateLobbyStatus>,
+        charity: Option<Pubkey>,
+        charity_bps: u16,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting && lobby.total_bets == 0,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            charity.is_some() == (charity_bps > 0)
+                && charity_bps as u64 <= math::BPS_DENOMINATOR
+                && (charity.is_none() || lobby.market_mode == MarketMode::Parimutuel),
+            BettingError::InvalidCharity
+        );
+        lobby.charity = charity;
+        lobby.charity_bps = charity_bps;
+        touch_lobby(lobby, LobbyAction::CharitySet)?;
+        Ok(())
+    }
+
+    /// Sets how stakes weigh in the lobby's parimutuel pool: by stake, by the
+    /// square root of the stake, or by the stake capped per wallet. Weighted
+    /// lobbies pay out winner-take-all and take only plain back bets. Must
+    /// be set before any bet is placed.
+    pub fn set_pool_weighting(
+        ctx: Context<UpdateLobbyStatus>,
+        weighting: PoolWeighting,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting && lobby.total_bets == 0,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            weighting == PoolWeighting::Stake
+                || (lobby.market_mode == MarketMode::Parimutuel
+                    && lobby.payout_curve.len() == 1
+                    && lobby.player_pools == 0
+                    && weighting != PoolWeighting::Capped { max_stake: 0 }),
+            BettingError::InvalidPoolWeighting
+        );
+        lobby.weighting = weighting;
+        touch_lobby(lobby, LobbyAction::PoolWeightingSet)?;
+        Ok(())
+    }
+
+    /// Enables blind bets, revealed between the betting deadline and
+    /// `reveal_deadline`. Pools can't be locked until the reveal window ends.
+    pub fn set_reveal_deadline(ctx: Context<UpdateLobbyStatus>, reveal_deadline: i64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(!lobby.pools_locked, BettingError::BettingClosed);
+        require!(
+            lobby.betting_deadline > 0 && reveal_deadline > lobby.betting_deadline,
+            BettingError::InvalidRevealDeadline
+        );
+        lobby.reveal_deadline = reveal_deadline;
+        touch_lobby(lobby, LobbyAction::DeadlineSet)?;
+        Ok(())
+    }
+
+    /// Freezes the pool totals into a `PoolSnapshot` that parimutuel payouts are
+    /// computed from, first folding in every player pool, which must all be
+    /// passed as `remaining_accounts`. The owner may lock at any time; anyone
+    /// may once the betting deadline has passed.
+    pub fn lock_pools<'info>(ctx: Context<'_, '_, 'info, 'info, LockPools<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let lobby = &mut ctx.accounts.lobby;
+        let deadline_passed = lobby.betting_deadline > 0 && now >= lobby.betting_deadline;
+        require!(
+            deadline_passed || lobby.owner == ctx.accounts.caller.key(),
+            BettingError::Unauthorized
+        );
+        require!(!lobby.pools_locked, BettingError::BettingClosed);
+        require!(
+            lobby.reveal_deadline == 0 || now >= lobby.reveal_deadline,
+            BettingError::RevealWindowOpen
+        );
+        fold_player_pools(
+            lobby,
+            &mut ctx.accounts.config,
+            ctx.remaining_accounts,
+            &ctx.accounts.escrow,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+            ctx.program_id,
+        )?;
+
+        lobby.pools_locked = true;
+        let seq = touch_lobby(lobby, LobbyAction::PoolsLocked)?;
+
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.lobby = lobby.key();
+        snapshot.model_pools = lobby.model_pools.clone();
+        snapshot.lay_pools = lobby.lay_pools.clone();
+        snapshot.total_bets = lobby.total_bets;
+        snapshot.total_lay = lobby.total_lay;
+        snapshot.sponsor_pool = lobby.sponsor_pool;
+        snapshot.seq = seq;
+        snapshot.locked_at = now;
+        Ok(())
+    }
+
+    /// Commits the lobby to a game transcript (a BN254 scalar, e.g. a Poseidon
+    /// hash of the hand log). Can only be set once, before the game's first
+    /// hand, so the commitment can't be fitted to how the game went.
+    pub fn commit_transcript(ctx: Context<CommitTranscript>, commitment: [u8; 32]) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.transcript_commitment.is_none(),
+            BettingError::TranscriptAlreadyCommitted
+        );
+        require!(
+            lobby.status == LobbyStatus::Waiting && ctx.accounts.engine_game.hands_played == 0,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            commitment < groth16::SCALAR_FIELD_MODULUS,
+            BettingError::InvalidPublicInput
+        );
+        lobby.transcript_commitment = Some(commitment);
+        touch_lobby(lobby, LobbyAction::TranscriptCommitted)?;
+        Ok(())
+    }
+
+    pub fn issue_attestation(
+        ctx: Context<IssueAttestation>,
+        subject: Pubkey,
+        jurisdiction: [u8; 2],
+        expires_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(expires_at > now, BettingError::AttestationExpired);
+
+        let attestation = &mut ctx.accounts.attestation;
+        attestation.issuer = ctx.accounts.issuer.key();
+        attestation.subject = subject;
+        attestation.jurisdiction = jurisdiction;
+        attestation.issued_at = now;
+        attestation.expires_at = expires_at;
+        attestation.revoked = false;
+        Ok(())
+    }
+
+    pub fn revoke_attestation(ctx: Context<RevokeAttestation>) -> Result<()> {
+        ctx.accounts.attestation.revoked = true;
+        Ok(())
+    }
+
+    /// Pays a winning bet. `winner_name` must be the winner the lobby
+    /// finished with; the payout is settled against that.
+    pub fn distribute_single_winning(
+        ctx: Context<DistributeSingleWinning>,
+        winner_name: String,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        require!(
+            accounts.lobby.owner == accounts.owner.key(),
+            BettingError::Unauthorized
+        );
+        let winner = accounts.lobby.winner.as_ref();
+        require!(winner.is_some(), BettingError::LobbyNotFinished);
+        require!(
+            winner == Some(&winner_name),
+            BettingError::BetOnWrongPlayer
+        );
+        require!(
+            accounts.bet.bettor == accounts.bettor.key(),
+            BettingError::InvalidBettor
+        );
+        let recipient = payout_recipient(
+            &accounts.bet,
+            accounts.bettor.to_account_info(),
+            accounts.payout_recipient.as_ref().map(|r| r.to_account_info()),
+        )?;
+
+        let transfer_amount = pay_winning_bet(
+            &mut accounts.lobby,
+            &mut accounts.bet,
+            accounts.snapshot.as_deref(),
+            &accounts.config,
+            &accounts.denylist.to_account_info(),
+            accounts.escrow.to_account_info(),
+            recipient.clone(),
+            accounts.treasury.to_account_info(),
+            accounts.system_program.to_account_info(),
+            ctx.program_id,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let stats = &mut accounts.bettor_stats;
+        stats.roll_limit_windows(now);
+        stats.record_payout(transfer_amount, now, &accounts.config)?;
+        stats.record_usd_payout(&accounts.bet, transfer_amount)?;
+
+        Ok(())
+    }
+
+    pub fn initialize_stake_pool(ctx: Context<InitializeStakePool>, epoch_duration: i64) -> Result<()> {
+        require!(epoch_duration > 0, BettingError::InvalidEpochDuration);
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.stake_mint = ctx.accounts.stake_mint.key();
+        pool.vault = ctx.accounts.vault.key();
+        pool.total_staked = 0;
+        pool.acc_reward_per_share = 0;
+        pool.epoch = 0;
+        pool.epoch_duration = epoch_duration;
+        pool.epoch_started_at = now;
+        pool.total_distributed = 0;
+        pool.bump = ctx.bumps.stake_pool;
+
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
+
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.admin.key,
+                ctx.accounts.revenue_vault.key,
+                rent_exempt_minimum,
+            ),
+            &[
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.revenue_vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Closes the current epoch: the revenue share accrued in the treasury since the
+    /// last epoch is moved to the revenue vault and credited to current stakers.
+    /// Anyone can crank this once the epoch has elapsed.
+    pub fn advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let config = &mut ctx.accounts.config;
+        let pool = &mut ctx.accounts.stake_pool;
+        require!(
+            now >= pool.epoch_started_at + pool.epoch_duration,
+            BettingError::EpochNotElapsed
+        );
+
+        // With nobody staked the share keeps accruing into the next epoch
+        let amount = config.accrued_revenue_share;
+        if amount > 0 && pool.total_staked > 0 {
+            let seeds: &[&[u8]] = &[TREASURY_SEED, &[ctx.bumps.treasury]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    ctx.accounts.treasury.key,
+                    ctx.accounts.revenue_vault.key,
+                    amount,
+                ),
+                &[
+                    ctx.accounts.treasury.to_account_info(),
+                    ctx.accounts.revenue_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+
+            let increment =
+                math::mul_div_wide(amount as u128, REWARD_PRECISION, pool.total_staked as u128)?;
+            pool.acc_reward_per_share = pool
+                .acc_reward_per_share
+                .checked_add(increment)
+                .ok_or(BettingError::Overflow)?;
+            pool.total_distributed = pool
+                .total_distributed
+                .checked_add(amount)
+                .ok_or(BettingError::Overflow)?;
+            config.accrued_revenue_share = 0;
+        }
+
+        pool.epoch = pool.epoch.checked_add(1).ok_or(BettingError::Overflow)?;
+        pool.epoch_started_at = now;
+        Ok(())
+    }
+
+    /// Stakes `amount` from the next epoch on. Revenue is credited to the stake
+    /// active when an epoch closes, so stake added mid-epoch waits out the
+    /// epoch rather than collecting a share it wasn't staked for.
+    pub fn stake_protocol_token(ctx: Context<StakeProtocolToken>, amount: u64) -> Result<()> {
+        require!(amount > 0, BettingError::StakeAmountMustBePositive);
+
+        let pool = &mut ctx.accounts.stake_pool;
+        let position = &mut ctx.accounts.stake_position;
+        if position.owner == Pubkey::default() {
+            position.owner = ctx.accounts.staker.key();
+        }
+        position.settle(pool)?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.staker_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.staker.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        position.pending = position.pending.checked_add(amount).ok_or(BettingError::Overflow)?;
+        position.pending_epoch = pool.epoch;
+        Ok(())
+    }
+
+    /// Unstakes `amount`, taking it from stake still waiting for its first
+    /// epoch before active stake.
+    pub fn unstake_protocol_token(ctx: Context<UnstakeProtocolToken>, amount: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.stake_pool;
+        let position = &mut ctx.accounts.stake_position;
+        position.settle(pool)?;
+        let staked = position
+            .amount
+            .checked_add(position.pending)
+            .ok_or(BettingError::Overflow)?;
+        require!(amount > 0 && amount <= staked, BettingError::InsufficientStake);
+
+        let seeds: &[&[u8]] = &[STAKE_POOL_SEED, &[pool.bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.staker_token_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        let from_pending = amount.min(position.pending);
+        let from_active = amount - from_pending;
+        position.pending -= from_pending;
+        position.amount -= from_active;
+        position.reward_debt = position.accrued(pool.acc_reward_per_share)?;
+        pool.total_staked -= from_active;
+        Ok(())
+    }
+
+    /// Starts a position's stake from a past epoch earning. Stake, unstake and
+    /// claim do this too; anyone can crank it for a staker who does none.
+    pub fn activate_stake(ctx: Context<ActivateStake>) -> Result<()> {
+        ctx.accounts
+            .stake_position
+            .settle(&mut ctx.accounts.stake_pool)
+    }
+
+    pub fn claim_revenue_share(ctx: Context<ClaimRevenueShare>) -> Result<()> {
+        let pool = &mut ctx.accounts.stake_pool;
+        let position = &mut ctx.accounts.stake_position;
+        position.settle(pool)?;
+
+        let amount = position.unclaimed;
+        require!(amount > 0, BettingError::NothingToClaim);
+
+        let seeds: &[&[u8]] = &[REVENUE_SEED, &[ctx.bumps.revenue_vault]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.revenue_vault.key,
+                ctx.accounts.staker.key,
+                amount,
+            ),
+            &[
+                ctx.accounts.revenue_vault.to_account_info(),
+                ctx.accounts.staker.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+
+        position.unclaimed = 0;
+        Ok(())
+    }
+
+    /// Blocks the signer from betting until `until`. The period can only ever be
+    /// extended, and there is deliberately no admin path to lift it.
+    pub fn self_exclude(ctx: Context<SelfExclude>, until: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(until > now, BettingError::InvalidExclusionPeriod);
+
+        let exclusion = &mut ctx.accounts.self_exclusion;
+        require!(until >= exclusion.until, BettingError::ExclusionCannotBeShortened);
+        if exclusion.wallet == Pubkey::default() {
+            exclusion.wallet = ctx.accounts.wallet.key();
+            exclusion.created_at = now;
+        }
+        exclusion.until = until;
+        exclusion.updated_at = now;
+        Ok(())
+    }
+
+    /// Sets the caller's wager and loss caps (zero means no cap). Tighter limits
+    /// apply immediately; looser ones only after `LIMIT_INCREASE_DELAY`.
+    pub fn set_betting_limits(ctx: Context<SetBettingLimits>, limits: BettingLimits) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stats = &mut ctx.accounts.bettor_stats;
+        stats.bettor = ctx.accounts.bettor.key();
+        stats.apply_pending_limits(now);
+
+        let immediate = stats.limits.tightened(&limits);
+        stats.pending_limits = if immediate == limits {
+            None
+        } else {
+            Some(limits)
+        };
+        stats.pending_limits_at = now + LIMIT_INCREASE_DELAY;
+        stats.limits = immediate;
+        Ok(())
+    }
+
+    /// Sets the caller's caps in micro-USD, enforced at the Pyth price of each
+    /// bet alongside the lamport caps. Looser limits wait for
+    /// `LIMIT_INCREASE_DELAY`, like `set_betting_limits`.
+    pub fn set_usd_betting_limits(
+        ctx: Context<SetBettingLimits>,
+        limits: BettingLimits,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stats = &mut ctx.accounts.bettor_stats;
+        stats.bettor = ctx.accounts.bettor.key();
+        stats.apply_pending_limits(now);
+
+        let immediate = stats.usd_limits.tightened(&limits);
+        stats.pending_usd_limits = if immediate == limits {
+            None
+        } else {
+            Some(limits)
+        };
+        stats.pending_usd_limits_at = now + LIMIT_INCREASE_DELAY;
+        stats.usd_limits = immediate;
+        Ok(())
+    }
+
+    pub fn post_message(ctx: Context<PostMessage>, text: String) -> Result<()> {
+        require!(
+            !text.is_empty() && text.len() <= MAX_MESSAGE_LEN,
+            BettingError::InvalidMessageLength
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let lobby_key = ctx.accounts.lobby.key();
+        let author = ctx.accounts.author.key();
+        let feed = &mut ctx.accounts.feed;
+        feed.lobby = lobby_key;
+
+        let message = FeedMessage {
+            author,
+            text: text.clone(),
+            posted_at: now,
+        };
+        let slot = (feed.total_messages % FEED_CAPACITY as u64) as usize;
+        if slot < feed.messages.len() {
+            feed.messages[slot] = message;
+        } else {
+            feed.messages.push(message);
+        }
+        feed.total_messages = feed.total_messages.checked_add(1).ok_or(BettingError::Overflow)?;
+
+        emit!(MessagePosted {
+            lobby: lobby_key,
+            lobby_seq: ctx.accounts.lobby.seq,
+            author,
+            sequence: feed.total_messages - 1,
+            text,
+            posted_at: now,
+        });
+        Ok(())
+    }
+
+    /// Adds SOL to a lobby's prize pool without betting. Sponsor funds are shared
+    /// by the winning bettors alongside the losing pools.
+    pub fn boost_prize_pool(ctx: Context<BoostPrizePool>, amount: u64) -> Result<()> {
+        require!(amount > 0, BettingError::BetAmountMustBePositive);
+        let lobby = &mut ctx.accounts.lobby;
+        require!(!lobby.status.is_over(), BettingError::LobbyNotOpenForBets);
+        require!(!lobby.pools_locked, BettingError::BettingClosed);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.sponsor.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        lobby.track_deposit(amount)?;
+        lobby.sponsor_pool = lobby
+            .sponsor_pool
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::PrizePoolBoosted)?;
+
+        emit!(PrizePoolBoosted {
+            lobby: lobby.key(),
+            seq: lobby.seq,
+            sponsor: ctx.accounts.sponsor.key(),
+            amount,
+            sponsor_pool: lobby.sponsor_pool,
+        });
+        Ok(())
+    }
+
+    /// Adds SOL to a lobby's freeroll prize, shared equally by the wallets that
+    /// picked the winner for free. Picks open once the prize is funded.
+    pub fn fund_freeroll(ctx: Context<BoostPrizePool>, amount: u64) -> Result<()> {
+        require!(amount > 0, BettingError::BetAmountMustBePositive);
+        let lobby = &mut ctx.accounts.lobby;
+        require!(!lobby.status.is_over(), BettingError::LobbyNotOpenForBets);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.sponsor.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        lobby.track_deposit(amount)?;
+        lobby.freeroll_pool = lobby
+            .freeroll_pool
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::FreerollFunded)?;
+        Ok(())
+    }
+
+    /// Picks the winner of a freeroll lobby without staking anything. One pick
+    /// per wallet, gated like bets on the lobby's attestation issuer.
+    pub fn pick_freeroll(ctx: Context<PickFreeroll>, player_name: String) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        let bettor = accounts.bettor.key();
+        require!(lobby.freeroll_pool > 0, BettingError::FreerollNotFunded);
+        require!(
+            lobby.status == LobbyStatus::Waiting || lobby.status == LobbyStatus::Running,
+            BettingError::LobbyNotOpenForBets
+        );
+        require!(
+            !lobby.betting_closed(Clock::get()?.unix_timestamp),
+            BettingError::BettingClosed
+        );
+        require!(!accounts.config.paused, BettingError::ProtocolPaused);
+        check_not_denied(&accounts.denylist, &bettor, ctx.program_id)?;
+        check_attestation(lobby, &accounts.config, accounts.attestation.as_ref(), bettor)?;
+
+        let model_index = lobby.model_index(&player_name)?;
+        lobby.freeroll_picks[model_index] = lobby.freeroll_picks[model_index]
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+
+        let pick = &mut accounts.pick;
+        pick.bettor = bettor;
+        pick.lobby = lobby.key();
+        pick.model_index = model_index as u8;
+        pick.picked_at = Clock::get()?.unix_timestamp;
+        touch_lobby(lobby, LobbyAction::FreerollPicked)?;
+        Ok(())
+    }
+
+    /// Pays a correct freeroll pick its equal share of the prize and closes
+    /// it. The last correct pick claimed takes the rounding remainder.
+    pub fn claim_freeroll_prize(ctx: Context<ClaimFreerollPrize>) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(lobby.in_custody == 0, BettingError::FundsInCustody);
+        check_not_denied(&accounts.denylist, &accounts.bettor.key(), ctx.program_id)?;
+        let winner = lobby
+            .winner
+            .as_deref()
+            .ok_or(BettingError::LobbyNotFinished)?;
+        let winner_index = lobby.model_index(winner)?;
+        require!(
+            accounts.pick.model_index as usize == winner_index,
+            BettingError::BetOnWrongPlayer
+        );
+
+        let winning_picks = lobby.freeroll_picks[winner_index];
+        let settled = lobby
+            .freeroll_paid_picks
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        let prize = if settled == winning_picks {
+            lobby
+                .freeroll_pool
+                .checked_sub(lobby.freeroll_paid)
+                .ok_or(BettingError::Overflow)?
+        } else {
+            parimutuel_payout(1, lobby.freeroll_pool, winning_picks)?
+        };
+        lobby.freeroll_paid_picks = settled;
+        lobby.freeroll_paid = lobby
+            .freeroll_paid
+            .checked_add(prize)
+            .ok_or(BettingError::Overflow)?;
+
+        if prize > 0 {
+            let lobby_key = lobby.key();
+            let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    accounts.escrow.key,
+                    accounts.bettor.key,
+                    prize,
+                ),
+                &[
+                    accounts.escrow.to_account_info(),
+                    accounts.bettor.to_account_info(),
+                    accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+        }
+        lobby.track_withdrawal(prize)?;
+        touch_lobby(lobby, LobbyAction::FreerollPrizePaid)?;
+        Ok(())
+    }
+
+    /// Registers the operator of one of the lobby's models and the stake each
+    /// operator must post for the head-to-head match. The first registration
+    /// starts the `MATCH_STAKE_WINDOW` every stake has to be posted in.
+    pub fn register_model_operator(
+        ctx: Context<RegisterModelOperator>,
+        model_name: String,
+        operator: Pubkey,
+        stake_amount: u64,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting,
+            BettingError::LobbyAlreadyStarted
+        );
+        let model_index = lobby.model_index(&model_name)?;
+        require!(stake_amount > 0, BettingError::StakeAmountMustBePositive);
+        let now = Clock::get()?.unix_timestamp;
+        if lobby.match_stakes_registered == 0 {
+            lobby.match_stake_deadline = now + MATCH_STAKE_WINDOW;
+        }
+        require!(
+            now < lobby.match_stake_deadline,
+            BettingError::MatchStakeWindowClosed
+        );
+        lobby.match_stakes_registered |= 1 << model_index;
+
+        let match_stake = &mut ctx.accounts.match_stake;
+        match_stake.lobby = lobby.key();
+        match_stake.model_name = model_name;
+        match_stake.operator = operator;
+        match_stake.amount = stake_amount;
+        match_stake.posted = false;
+        Ok(())
+    }
+
+    pub fn post_match_stake(ctx: Context<PostMatchStake>, model_name: String) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let match_stake = &mut ctx.accounts.match_stake;
+        require!(
+            lobby.status == LobbyStatus::Waiting,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            Clock::get()?.unix_timestamp < lobby.match_stake_deadline,
+            BettingError::MatchStakeWindowClosed
+        );
+        require!(!match_stake.posted, BettingError::MatchStakeAlreadyPosted);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.operator.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, match_stake.amount)?;
+
+        match_stake.posted = true;
+        lobby.match_stakes_posted |= 1 << lobby.model_index(&model_name)?;
+        lobby.track_deposit(match_stake.amount)?;
+        lobby.match_pool = lobby
+            .match_pool
+            .checked_add(match_stake.amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::MatchStakePosted)?;
+        Ok(())
+    }
+
+    /// Refunds a posted match stake to its operator when the match is off:
+    /// not every registered stake was posted in time, or the game finished
+    /// with a winner whose operator had none. Anyone can crank it.
+    pub fn refund_match_stake(ctx: Context<RefundMatchStake>, _model_name: String) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let match_stake = &ctx.accounts.match_stake;
+        require!(match_stake.posted, BettingError::MatchStakeNotPosted);
+        require!(
+            lobby.match_void(Clock::get()?.unix_timestamp),
+            BettingError::MatchStillOn
+        );
+        require!(lobby.in_custody == 0, BettingError::FundsInCustody);
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                ctx.accounts.operator.key,
+                match_stake.amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.operator.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(match_stake.amount)?;
+        lobby.match_pool = lobby
+            .match_pool
+            .checked_sub(match_stake.amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::MatchStakeRefunded)?;
+        Ok(())
+    }
+
+    /// Finishes the lobby with its proof-verified or enclave-attested winner, or otherwise the winner
+    /// finalized in the poker engine's game record, and pays the combined operator match stakes, minus the protocol
+    /// fee, to the winning model's operator. Blocked while blind bets can
+    /// still be revealed.
+    /// The finishing order, starting with the declared winner, is the engine
+    /// game's when the engine declares the winner, and `placings` must then
+    /// be empty; otherwise `placings` gives it. Disqualified models are
+    /// skipped, so a disqualified winner's place goes to the next eligible
+    /// model. The order must name an eligible model for every paid position,
+    /// and may be empty when the lobby pays one position and its winner is
+    /// eligible.
+    /// `final_chips` is each model's closing stack, in `model_names` order; it is
+    /// required when the lobby has a chip-count market and optional otherwise.
+    /// `remaining_accounts` are the lobby's rake recipients other than the
+    /// treasury, in `rake_splits` order.
+    pub fn finish_game<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinishGame<'info>>,
+        placings: Vec<String>,
+        final_chips: Vec<u64>,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        if let Some(results_authority) = lobby.results_authority {
+            let signer = ctx.accounts.results_authority.as_ref().map(|s| s.key());
+            require!(
+                signer == Some(results_authority),
+                BettingError::ResultsAuthorityRequired
+            );
+        }
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        require!(
+            lobby.player_pools == 0 || lobby.pools_locked,
+            BettingError::PlayerPoolsNotAggregated
+        );
+        require!(
+            lobby.reveal_deadline == 0 || Clock::get()?.unix_timestamp >= lobby.reveal_deadline,
+            BettingError::RevealWindowOpen
+        );
+        let (declared, placings) = match (&lobby.verified_winner, &ctx.accounts.engine_game) {
+            (Some(winner), _) => (winner.clone(), placings),
+            (None, Some(engine_game)) => {
+                require!(engine_game.finalized, BettingError::GameNotFinalized);
+                require!(placings.is_empty(), BettingError::InvalidPlacings);
+                let winner = engine_game
+                    .winner
+                    .clone()
+                    .ok_or(BettingError::GameNotFinalized)?;
+                (winner, engine_game.placings.clone())
+            }
+            (None, None) => return err!(BettingError::GameNotFinalized),
+        };
+        lobby.model_index(&declared)?;
+        require!(
+            placings.is_empty() || placings[0] == declared,
+            BettingError::InvalidPlacings
+        );
+        for (i, name) in placings.iter().enumerate() {
+            lobby.model_index(name)?;
+            require!(!placings[..i].contains(name), BettingError::InvalidPlacings);
+        }
+        let order = if placings.is_empty() {
+            vec![declared]
+        } else {
+            placings
+        };
+        let eligible: Vec<String> = order
+            .into_iter()
+            .filter(|name| !lobby.is_disqualified(name))
+            .collect();
+        let paid_positions = lobby.payout_curve.len().max(1);
+        require!(
+            eligible.len() >= paid_positions,
+            BettingError::InvalidPlacings
+        );
+        let winner_name = eligible[0].clone();
+
+        // A match left incomplete, or whose winner had no operator stake, is
+        // off; its posted stakes are refunded by `refund_match_stake`
+        let match_pool = lobby.match_pool;
+        let winner_bit = 1 << lobby.model_index(&winner_name)?;
+        if match_pool > 0
+            && lobby.match_stakes_posted == lobby.match_stakes_registered
+            && lobby.match_stakes_posted & winner_bit != 0
+        {
+            let winner_stake = ctx
+                .accounts
+                .winner_stake
+                .as_ref()
+                .ok_or(BettingError::MatchStakeNotPosted)?;
+            let winner_operator = ctx
+                .accounts
+                .winner_operator
+                .as_ref()
+                .ok_or(BettingError::MatchStakeNotPosted)?;
+            require!(
+                winner_stake.lobby == lobby.key() && winner_stake.model_name == winner_name,
+                BettingError::InvalidMatchStake
+            );
+            require!(winner_stake.posted, BettingError::MatchStakeNotPosted);
+            require!(
+                winner_operator.key() == winner_stake.operator,
+                BettingError::InvalidMatchStake
+            );
+
+            let fee = math::apply_bps(match_pool, ctx.accounts.config.fee_bps as u64)?;
+            let lobby_key = lobby.key();
+            let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+            for (to, amount) in [
+                (ctx.accounts.treasury.to_account_info(), fee),
+                (winner_operator.to_account_info(), match_pool - fee),
+            ] {
+                if amount == 0 {
+                    continue;
+                }
+                anchor_lang::solana_program::program::invoke_signed(
+                    &anchor_lang::solana_program::system_instruction::transfer(
+                        ctx.accounts.escrow.key,
+                        to.key,
+                        amount,
+                    ),
+                    &[
+                        ctx.accounts.escrow.to_account_info(),
+                        to,
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                    &[seeds],
+                )?;
+            }
+            lobby.track_withdrawal(match_pool)?;
+            lobby.match_pool = 0;
+        }
+        pay_rake(
+            lobby,
+            &mut ctx.accounts.config,
+            &ctx.accounts.treasury,
+            ctx.remaining_accounts,
+            &ctx.accounts.system_program,
+            ctx.bumps.treasury,
+        )?;
+
+        lobby.placings = eligible[..paid_positions].to_vec();
+        if !final_chips.is_empty() || !lobby.chip_buckets.is_empty() {
+            let total_chips = lobby
+                .starting_chips
+                .checked_mul(lobby.model_names.len() as u64)
+                .ok_or(BettingError::Overflow)?;
+            require!(
+                final_chips.len() == lobby.model_names.len()
+                    && final_chips.iter().try_fold(0u64, |sum, chips| sum.checked_add(*chips))
+                        == Some(total_chips),
+                BettingError::InvalidFinalChips
+            );
+            lobby.final_chips = final_chips;
+        }
+        if let Some(charity) = lobby.charity {
+            let winner_index = lobby.model_index(&winner_name)?;
+            let (distributable, winning_stake) = if lobby.pools_locked {
+                ctx.accounts
+                    .snapshot
+                    .as_ref()
+                    .ok_or(BettingError::SnapshotRequired)?
+                    .parimutuel_totals(winner_index)?
+            } else {
+                lobby.parimutuel_totals(winner_index)?
+            };
+            let amount = lobby.charity_cut(distributable, winning_stake)?;
+            if amount > 0 {
+                let charity_account = ctx
+                    .accounts
+                    .charity
+                    .as_ref()
+                    .ok_or(BettingError::InvalidCharity)?;
+                require!(
+                    charity_account.key() == charity,
+                    BettingError::InvalidCharity
+                );
+                require!(lobby.in_custody == 0, BettingError::FundsInCustody);
+                let lobby_key = lobby.key();
+                let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+                anchor_lang::solana_program::program::invoke_signed(
+                    &anchor_lang::solana_program::system_instruction::transfer(
+                        ctx.accounts.escrow.key,
+                        charity_account.key,
+                        amount,
+                    ),
+                    &[
+                        ctx.accounts.escrow.to_account_info(),
+                        charity_account.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                    &[seeds],
+                )?;
+                lobby.track_withdrawal(amount)?;
+            }
+            lobby.charity_amount = amount;
+        }
+        transition_lobby(lobby, LobbyStatus::Finished)?;
+        lobby.finished_at = Clock::get()?.unix_timestamp;
+        lobby.winner = Some(winner_name);
+        lobby.raffle_entries_close_at = Clock::get()?.unix_timestamp + RAFFLE_ENTRY_WINDOW;
+        lobby.reimbursement_closes_at = Clock::get()?.unix_timestamp + REIMBURSEMENT_CLAIM_WINDOW;
+        ctx.accounts.operator_profile.record_settlement(
+            lobby.total_bets,
+            Clock::get()?.unix_timestamp - lobby.created_at,
+        )?;
+        touch_lobby(lobby, LobbyAction::GameFinished)?;
+        Ok(())
+    }
+
+    /// Switches a lobby to fixed-odds mode backed by `market_maker`. Must be done
+    /// before any bet is placed.
+    pub fn set_market_maker(
+        ctx: Context<UpdateLobbyStatus>,
+        market_mode: MarketMode,
+        market_maker: Option<Pubkey>,
+        quote_max_age: i64,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting && lobby.total_bets == 0,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(quote_max_age >= 0, BettingError::InvalidRateLimit);
+        require!(
+            market_mode == MarketMode::Parimutuel || lobby.payout_curve.len() == 1,
+            BettingError::InvalidPayoutCurve
+        );
+        if market_mode == MarketMode::FixedOdds {
+            require!(market_maker.is_some(), BettingError::Unauthorized);
+            require!(lobby.charity.is_none(), BettingError::InvalidCharity);
+            require!(
+                lobby.weighting == PoolWeighting::Stake,
+                BettingError::InvalidPoolWeighting
+            );
+        }
+        lobby.market_mode = market_mode;
+        lobby.market_maker = market_maker;
+        lobby.quote_max_age = quote_max_age;
+        touch_lobby(lobby, LobbyAction::MarketMakerSet)?;
+        Ok(())
+    }
+
+    /// Publishes the market maker's current decimal odds per model. Informational
+    /// in parimutuel lobbies; in fixed-odds lobbies bets are struck at these odds
+    /// for as long as the quote is younger than `quote_max_age`.
+    pub fn update_quoted_odds(ctx: Context<UpdateQuotedOdds>, odds_bps: Vec<u32>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.market_maker == Some(ctx.accounts.market_maker.key()),
+            BettingError::Unauthorized
+        );
+        require!(
+            odds_bps.len() == lobby.model_names.len(),
+            BettingError::InvalidOdds
+        );
+        let now = Clock::get()?.unix_timestamp;
+        lobby.quoted_odds_bps = odds_bps;
+        lobby.quoted_at = now;
+        touch_lobby(lobby, LobbyAction::OddsQuoted)?;
+        Ok(())
+    }
+
+    pub fn fund_market_maker_bankroll(ctx: Context<FundBankroll>, amount: u64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.market_maker == Some(ctx.accounts.market_maker.key()),
+            BettingError::Unauthorized
+        );
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.market_maker.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        lobby.track_deposit(amount)?;
+        lobby.market_maker_bankroll = lobby
+            .market_maker_bankroll
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::BankrollFunded)?;
+        Ok(())
+    }
+
+    /// Returns what is left of the bankroll and the losing stakes to the market
+    /// maker once the outcome is known and winning liabilities are reserved.
+    pub fn withdraw_market_maker_funds(ctx: Context<FundBankroll>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.market_maker == Some(ctx.accounts.market_maker.key()),
+            BettingError::Unauthorized
+        );
+        require!(
+            lobby.market_mode == MarketMode::FixedOdds,
+            BettingError::InvalidOdds
+        );
+        let winner = lobby.winner.clone().ok_or(BettingError::LobbyNotFinished)?;
+        let winner_index = lobby.model_index(&winner)?;
+
+        let reserved = lobby.model_liabilities[winner_index]
+            .saturating_sub(lobby.fixed_odds_paid)
+            .checked_add(lobby.sponsor_pool)
+            .and_then(|v| v.checked_add(lobby.order_book_locked))
+            .and_then(|v| v.checked_add(lobby.freeroll_pool - lobby.freeroll_paid))
+            .ok_or(BettingError::Overflow)?;
+        let amount = lobby.escrow_balance.saturating_sub(reserved);
+        require!(amount > 0, BettingError::NothingToClaim);
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                ctx.accounts.market_maker.key,
+                amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.market_maker.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+
+        lobby.track_withdrawal(amount)?;
+        lobby.market_maker_bankroll = 0;
+        touch_lobby(lobby, LobbyAction::MarketMakerWithdrawn)?;
+        Ok(())
+    }
+
+    /// Posts a back or lay order on the lobby's peer-to-peer order book. Backers
+    /// lock their stake; layers lock the liability of backing `stake` at `odds_bps`.
+    pub fn post_order(
+        ctx: Context<PostOrder>,
+        _order_id: u64,
+        model_name: String,
+        side: BetSide,
+        odds_bps: u32,
+        stake: u64,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.status == LobbyStatus::Waiting || lobby.status == LobbyStatus::Running,
+            BettingError::LobbyNotOpenForBets
+        );
+        require!(!ctx.accounts.config.paused, BettingError::ProtocolPaused);
+        require!(stake > 0, BettingError::BetAmountMustBePositive);
+        require!(odds_bps as u64 > ODDS_SCALE, BettingError::InvalidOdds);
+        let model_index = lobby.model_index(&model_name)?;
+
+        let order = &mut ctx.accounts.order;
+        order.lobby = lobby.key();
+        order.maker = ctx.accounts.maker.key();
+        order.model_index = model_index as u8;
+        order.side = side;
+        order.odds_bps = odds_bps;
+        order.remaining_stake = stake;
+        order.filled_stake = 0;
+        order.status = OrderStatus::Open;
+        order.created_at = Clock::get()?.unix_timestamp;
+
+        let collateral = order.collateral_for(stake)?;
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.maker.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, collateral)?;
+
+        lobby.track_deposit(collateral)?;
+        lobby.order_book_locked = lobby
+            .order_book_locked
+            .checked_add(collateral)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::OrderPosted)?;
+        Ok(())
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let order = &mut ctx.accounts.order;
+        require!(order.status == OrderStatus::Open, BettingError::OrderNotOpen);
+
+        let refund = order.collateral_for(order.remaining_stake)?;
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                ctx.accounts.maker.key,
+                refund,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.maker.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+
+        lobby.track_withdrawal(refund)?;
+        lobby.order_book_locked = lobby.order_book_locked.saturating_sub(refund);
+        touch_lobby(lobby, LobbyAction::OrderCancelled)?;
+        order.remaining_stake = 0;
+        order.status = OrderStatus::Cancelled;
+        Ok(())
+    }
+
+    /// Permissionless crank pairing a back order with a lay order on the same
+    /// model. The match is struck at the lay order's odds, which are at least
+    /// what the backer asked for, so no collateral needs to move.
+    pub fn match_orders(ctx: Context<MatchOrders>) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        let back = &mut ctx.accounts.back_order;
+        let lay = &mut ctx.accounts.lay_order;
+        require!(
+            lobby.status == LobbyStatus::Waiting || lobby.status == LobbyStatus::Running,
+            BettingError::LobbyNotOpenForBets
+        );
+        require!(
+            back.lobby == lobby.key() && lay.lobby == lobby.key(),
+            BettingError::OrdersDoNotCross
+        );
+        require!(
+            back.side == BetSide::Back && lay.side == BetSide::Lay,
+            BettingError::OrdersDoNotCross
+        );
+        require!(
+            back.status == OrderStatus::Open && lay.status == OrderStatus::Open,
+            BettingError::OrderNotOpen
+        );
+        require!(
+            back.model_index == lay.model_index && back.odds_bps <= lay.odds_bps,
+            BettingError::OrdersDoNotCross
+        );
+
+        let matched_stake = back.remaining_stake.min(lay.remaining_stake);
+        let liability = lay.collateral_for(matched_stake)?;
+
+        let position = &mut ctx.accounts.position;
+        position.lobby = lobby.key();
+        position.backer = back.maker;
+        position.layer = lay.maker;
+        position.model_index = back.model_index;
+        position.odds_bps = lay.odds_bps;
+        position.backer_stake = matched_stake;
+        position.layer_liability = liability;
+        position.settled = false;
+        position.matched_at = Clock::get()?.unix_timestamp;
+
+        for order in [&mut **back, &mut **lay] {
+            order.remaining_stake -= matched_stake;
+            order.filled_stake += matched_stake;
+            if order.remaining_stake == 0 {
+                order.status = OrderStatus::Filled;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pays a matched position to the backer or the layer once the lobby has a
+    /// winner. Anyone may crank it.
+    pub fn settle_position(ctx: Context<SettlePosition>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let position = &mut ctx.accounts.position;
+        require!(!position.settled, BettingError::BetAlreadyProcessed);
+        let winner = lobby.winner.clone().ok_or(BettingError::LobbyNotFinished)?;
+        let backer_won = lobby.model_index(&winner)? == position.model_index as usize;
+
+        let recipient = if backer_won {
+            &ctx.accounts.backer
+        } else {
+            &ctx.accounts.layer
+        };
+        let amount = position
+            .backer_stake
+            .checked_add(position.layer_liability)
+            .ok_or(BettingError::Overflow)?;
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                recipient.key,
+                amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                recipient.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+
+        lobby.track_withdrawal(amount)?;
+        lobby.order_book_locked = lobby.order_book_locked.saturating_sub(amount);
+        touch_lobby(lobby, LobbyAction::PositionSettled)?;
+        position.settled = true;
+        Ok(())
+    }
+
+    /// Places the opposite-side bet needed to equalise the bettor's return on
+    /// `player_name` whatever the outcome, sized against the current pools.
+    /// `side` is the side of the new bet and `max_amount` caps what it may cost.
+    pub fn hedge_position(
+        ctx: Context<HedgePosition>,
+        player_name: String,
+        max_amount: u64,
+        min_expected_odds_bps: u32,
+        side: BetSide,
+    ) -> Result<()> {
+        let existing = &ctx.accounts.existing_bet;
+        let lobby = &ctx.accounts.place.lobby;
+        require!(
+            existing.status == BetStatus::Active,
+            BettingError::BetAlreadyProcessed
+        );
+        require!(
+            existing.player_name == player_name,
+            BettingError::InvalidPlayerName
+        );
+        require!(
+            lobby.market_mode == MarketMode::Parimutuel,
+            BettingError::LayRequiresParimutuel
+        );
+
+        let model_index = lobby.model_index(&player_name)?;
+        let existing_odds = lobby.implied_odds_after(model_index, existing.side, 0)?;
+        let mut stake = 0u64;
+        for _ in 0..HEDGE_ITERATIONS {
+            let odds = lobby.implied_odds_after(model_index, side, stake)?;
+            require!(odds > 0, BettingError::InvalidOdds);
+            stake = math::mul_div(existing.amount, existing_odds, odds)?;
+        }
+
+        // Gross up so the stake left after the protocol fee is the hedge size
+        let volume = ctx
+            .accounts
+            .place
+            .bettor_stats
+            .rolling_monthly_volume(Clock::get()?.unix_timestamp)?;
+        let fee_bps = ctx.accounts.place.config.bettor_fee_bps(volume);
+        let net_bps = math::BPS_DENOMINATOR - fee_bps as u64;
+        let amount = math::mul_div_ceil(stake, math::BPS_DENOMINATOR, net_bps)?;
+        require!(amount <= max_amount, BettingError::SlippageExceeded);
+
+        place_bet_with(
+            ctx.accounts.place.bet_accounts(),
+            ctx.program_id,
+            player_name,
+            amount,
+            min_expected_odds_bps,
+            side,
+            [0; 16],
+            None,
+        )
+    }
+
+    /// Deposits `amount` into a conditional bet that a crank places on the
+    /// signer's behalf once `condition` holds, up until `deadline`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_conditional_bet(
+        ctx: Context<CreateConditionalBet>,
+        player_name: String,
+        side: BetSide,
+        amount: u64,
+        condition: TriggerCondition,
+        threshold_bps: u32,
+        deadline: i64,
+        min_expected_odds_bps: u32,
+    ) -> Result<()> {
+        require!(amount > 0, BettingError::BetAmountMustBePositive);
+        require!(
+            deadline > Clock::get()?.unix_timestamp,
+            BettingError::InvalidDeadline
+        );
+        ctx.accounts.lobby.model_index(&player_name)?;
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.owner.to_account_info(),
+            to: ctx.accounts.conditional_bet.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let conditional = &mut ctx.accounts.conditional_bet;
+        conditional.owner = ctx.accounts.owner.key();
+        conditional.lobby = ctx.accounts.lobby.key();
+        conditional.player_name = player_name;
+        conditional.side = side;
+        conditional.amount = amount;
+        conditional.condition = condition;
+        conditional.threshold_bps = threshold_bps;
+        conditional.deadline = deadline;
+        conditional.min_expected_odds_bps = min_expected_odds_bps;
+        conditional.executed = false;
+        conditional.created_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Permissionless crank placing a conditional bet once its trigger holds.
+    pub fn execute_conditional_bet(ctx: Context<ExecuteConditionalBet>) -> Result<()> {
+        let conditional = &ctx.accounts.conditional_bet;
+        require!(!conditional.executed, BettingError::BetAlreadyProcessed);
+        require!(
+            Clock::get()?.unix_timestamp <= conditional.deadline,
+            BettingError::InvalidDeadline
+        );
+        require!(
+            conditional.is_triggered(&ctx.accounts.lobby)?,
+            BettingError::ConditionNotMet
+        );
+
+        let player_name = conditional.player_name.clone();
+        let amount = conditional.amount;
+        let min_expected_odds_bps = conditional.min_expected_odds_bps;
+        let side = conditional.side;
+        let accounts = &mut *ctx.accounts;
+        place_bet_with(
+            BetAccounts {
+                lobby: &mut accounts.lobby,
+                bet: &mut accounts.bet,
+                bettor: accounts.owner.key(),
+                funder: Funder::Program(accounts.conditional_bet.to_account_info()),
+                bettor_stats: &mut accounts.bettor_stats,
+                self_exclusion: accounts.self_exclusion.to_account_info(),
+                denylist: accounts.denylist.to_account_info(),
+                escrow: accounts.escrow.to_account_info(),
+                config: &mut accounts.config,
+                treasury: accounts.treasury.to_account_info(),
+                attestation: accounts.attestation.as_ref(),
+                system_program: accounts.system_program.to_account_info(),
+                price_update: accounts.price_update.as_ref().map(|p| p.to_account_info()),
+                instructions: accounts.instructions.as_ref().map(|i| i.to_account_info()),
+            },
+            ctx.program_id,
+            player_name,
+            amount,
+            min_expected_odds_bps,
+            side,
+            [0; 16],
+            None,
+        )?;
+
+        ctx.accounts.conditional_bet.executed = true;
+        Ok(())
+    }
+
+    /// Closes a conditional bet, returning any unspent deposit and its rent.
+    pub fn cancel_conditional_bet(_ctx: Context<CancelConditionalBet>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn deposit_to_vault(ctx: Context<DepositToVault>, amount: u64) -> Result<()> {
+        require!(amount > 0, BettingError::BetAmountMustBePositive);
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.owner.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, amount)?;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.owner = ctx.accounts.owner.key();
+        vault.total_deposited = vault
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+
+    pub fn withdraw_from_vault(ctx: Context<WithdrawFromVault>, amount: u64) -> Result<()> {
+        Funder::Program(ctx.accounts.vault.to_account_info()).pay(
+            &ctx.accounts.owner.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )
+    }
+
+    /// Pre-authorizes betting `amount_per_lobby` on `model_name` in every lobby
+    /// `lobby_owner` creates from now on, paid from the subscriber's vault.
+    pub fn subscribe(
+        ctx: Context<Subscribe>,
+        lobby_owner: Pubkey,
+        model_name: String,
+        amount_per_lobby: u64,
+    ) -> Result<()> {
+        require!(amount_per_lobby > 0, BettingError::BetAmountMustBePositive);
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.subscriber = ctx.accounts.subscriber.key();
+        subscription.lobby_owner = lobby_owner;
+        subscription.model_name = model_name;
+        subscription.amount_per_lobby = amount_per_lobby;
+        subscription.lobbies_bet = 0;
+        subscription.created_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn cancel_subscription(_ctx: Context<CancelSubscription>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Permissionless crank placing a subscriber's bet on a newly created lobby.
+    pub fn execute_subscription_bet(ctx: Context<ExecuteSubscriptionBet>) -> Result<()> {
+        let subscription = &ctx.accounts.subscription;
+        require!(
+            ctx.accounts.lobby.owner == subscription.lobby_owner,
+            BettingError::InvalidBetAccount
+        );
+        require!(
+            ctx.accounts.lobby.created_at >= subscription.created_at,
+            BettingError::InvalidBetAccount
+        );
+
+        let player_name = subscription.model_name.clone();
+        let amount = subscription.amount_per_lobby;
+        let accounts = &mut *ctx.accounts;
+        place_bet_with(
+            BetAccounts {
+                lobby: &mut accounts.lobby,
+                bet: &mut accounts.bet,
+                bettor: accounts.subscriber.key(),
+                funder: Funder::Program(accounts.vault.to_account_info()),
+                bettor_stats: &mut accounts.bettor_stats,
+                self_exclusion: accounts.self_exclusion.to_account_info(),
+                denylist: accounts.denylist.to_account_info(),
+                escrow: accounts.escrow.to_account_info(),
+                config: &mut accounts.config,
+                treasury: accounts.treasury.to_account_info(),
+                attestation: accounts.attestation.as_ref(),
+                system_program: accounts.system_program.to_account_info(),
+                price_update: accounts.price_update.as_ref().map(|p| p.to_account_info()),
+                instructions: accounts.instructions.as_ref().map(|i| i.to_account_info()),
+            },
+            ctx.program_id,
+            player_name,
+            amount,
+            0,
+            BetSide::Back,
+            [0; 16],
+            None,
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.lobbies_bet = subscription
+            .lobbies_bet
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+
+    /// Registers the signer as a follower of `leader`, mirroring the leader's bets
+    /// from the follower's deposit vault for at most `per_lobby_amount` each.
+    pub fn follow(ctx: Context<Follow>, leader: Pubkey, per_lobby_amount: u64) -> Result<()> {
+        require!(per_lobby_amount > 0, BettingError::BetAmountMustBePositive);
+        require!(
+            leader != ctx.accounts.follower.key(),
+            BettingError::InvalidBettor
+        );
+        let follow = &mut ctx.accounts.follow;
+        follow.follower = ctx.accounts.follower.key();
+        follow.leader = leader;
+        follow.per_lobby_amount = per_lobby_amount;
+        follow.paused = false;
+        follow.bets_mirrored = 0;
+        follow.created_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Kill switch: stops (or resumes) mirroring without closing the follow.
+    pub fn set_follow_paused(ctx: Context<UpdateFollow>, paused: bool) -> Result<()> {
+        ctx.accounts.follow.paused = paused;
+        Ok(())
+    }
+
+    pub fn unfollow(_ctx: Context<Unfollow>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Permissionless crank copying a leader's bet into the follower's account.
+    pub fn mirror_bet(ctx: Context<MirrorBet>) -> Result<()> {
+        let follow = &ctx.accounts.follow;
+        let leader_bet = &ctx.accounts.leader_bet;
+        require!(!follow.paused, BettingError::FollowPaused);
+        require!(
+            leader_bet.status == BetStatus::Active,
+            BettingError::BetAlreadyProcessed
+        );
+        require!(
+            leader_bet.placed_at >= follow.created_at,
+            BettingError::InvalidBetAccount
+        );
+
+        let player_name = leader_bet.player_name.clone();
+        let side = leader_bet.side;
+        let amount = follow.per_lobby_amount.min(leader_bet.requested_amount);
+        let accounts = &mut *ctx.accounts;
+        place_bet_with(
+            BetAccounts {
+                lobby: &mut accounts.lobby,
+                bet: &mut accounts.bet,
+                bettor: accounts.follower.key(),
+                funder: Funder::Program(accounts.vault.to_account_info()),
+                bettor_stats: &mut accounts.bettor_stats,
+                self_exclusion: accounts.self_exclusion.to_account_info(),
+                denylist: accounts.denylist.to_account_info(),
+                escrow: accounts.escrow.to_account_info(),
+                config: &mut accounts.config,
+                treasury: accounts.treasury.to_account_info(),
+                attestation: accounts.attestation.as_ref(),
+                system_program: accounts.system_program.to_account_info(),
+                price_update: accounts.price_update.as_ref().map(|p| p.to_account_info()),
+                instructions: accounts.instructions.as_ref().map(|i| i.to_account_info()),
+            },
+            ctx.program_id,
+            player_name,
+            amount,
+            0,
+            side,
+            [0; 16],
+            None,
+        )?;
+
+        let follow = &mut ctx.accounts.follow;
+        follow.bets_mirrored = follow
+            .bets_mirrored
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+
+    /// Sets the Groth16 verifying key for outcome proofs. The circuit's public
+    /// inputs are the transcript commitment and the winner's model index.
+    pub fn set_outcome_verifier(
+        ctx: Context<SetOutcomeVerifier>,
+        alpha_g1: groth16::G1,
+        beta_g2: groth16::G2,
+        gamma_g2: groth16::G2,
+        delta_g2: groth16::G2,
+        ic: Vec<groth16::G1>,
+    ) -> Result<()> {
+        require!(
+            ic.len() == OUTCOME_PUBLIC_INPUTS + 1,
+            BettingError::InvalidVerifyingKey
+        );
+        let verifier = &mut ctx.accounts.verifier;
+        verifier.alpha_g1 = alpha_g1;
+        verifier.beta_g2 = beta_g2;
+        verifier.gamma_g2 = gamma_g2;
+        verifier.delta_g2 = delta_g2;
+        verifier.ic = ic;
+        ctx.accounts.config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Records `winner_name` as the lobby's winner if `proof` shows it is the
+    /// outcome of the committed transcript. Anyone can submit the proof.
+    pub fn verify_outcome_proof(
+        ctx: Context<VerifyOutcomeProof>,
+        winner_name: String,
+        proof: groth16::Proof,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        require!(
+            lobby.verified_winner.is_none(),
+            BettingError::WinnerAlreadyVerified
+        );
+        let commitment = lobby
+            .transcript_commitment
+            .ok_or(BettingError::TranscriptNotCommitted)?;
+        let winner_index = lobby.model_index(&winner_name)?;
+
+        let verifier = &ctx.accounts.verifier;
+        let vk = groth16::VerifyingKey {
+            alpha_g1: &verifier.alpha_g1,
+            beta_g2: &verifier.beta_g2,
+            gamma_g2: &verifier.gamma_g2,
+            delta_g2: &verifier.delta_g2,
+            ic: &verifier.ic,
+        };
+        let inputs = [commitment, groth16::scalar_from_u64(winner_index as u64)];
+        require!(
+            groth16::verify(&vk, &proof, &inputs)?,
+            BettingError::InvalidProof
+        );
+
+        lobby.verified_winner = Some(winner_name);
+        touch_lobby(lobby, LobbyAction::OutcomeVerified)?;
+        Ok(())
+    }
+
+    /// Records `winner_name` as the lobby's winner from a result the enclave
+    /// signed. The transaction must carry an Ed25519 program instruction over
+    /// `attested_result_message` immediately before this one.
+    pub fn submit_attested_result(
+        ctx: Context<SubmitAttestedResult>,
+        winner_name: String,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let measurement = config
+            .enclave_measurement
+            .ok_or(BettingError::EnclaveNotConfigured)?;
+        let enclave_signer = config
+            .enclave_signer
+            .ok_or(BettingError::EnclaveNotConfigured)?;
+
+        let lobby = &mut ctx.accounts.lobby;
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        require!(
+            lobby.verified_winner.is_none(),
+            BettingError::WinnerAlreadyVerified
+        );
+        lobby.model_index(&winner_name)?;
+
+        let message = attested_result_message(&lobby.key(), &measurement, &winner_name);
+        verify_preceding_ed25519(&ctx.accounts.instructions, &enclave_signer, &message)?;
+
+        lobby.verified_winner = Some(winner_name);
+        touch_lobby(lobby, LobbyAction::ResultAttested)?;
+        Ok(())
+    }
+
+    /// Configures cross-chain bet intake: the Wormhole core bridge, the one
+    /// emitter (chain and contract) trusted to send bets, and the rate bridged
+    /// USDC is converted to lamports at.
+    pub fn set_bridge_config(
+        ctx: Context<SetBridgeConfig>,
+        core_bridge: Pubkey,
+        emitter_chain: u16,
+        emitter_address: [u8; 32],
+        lamports_per_usdc: u64,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_config;
+        bridge.core_bridge = core_bridge;
+        bridge.emitter_chain = emitter_chain;
+        bridge.emitter_address = emitter_address;
+        bridge.lamports_per_usdc = lamports_per_usdc;
+        ctx.accounts.config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Places a bet carried by a verified Wormhole VAA from the registered
+    /// emitter. The relayer, who redeems the bridged USDC, fronts the stake in
+    /// lamports at the configured rate. Each VAA can be consumed once.
+    pub fn place_bet_from_wormhole(
+        ctx: Context<PlaceBetFromWormhole>,
+        vaa_hash: [u8; 32],
+        side: BetSide,
+    ) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_config;
+        let posted_vaa = &ctx.accounts.posted_vaa;
+        require!(
+            posted_vaa.owner == &bridge.core_bridge,
+            BettingError::InvalidVaa
+        );
+        let (expected_vaa, _) =
+            Pubkey::find_program_address(&[b"PostedVAA", &vaa_hash], &bridge.core_bridge);
+        require!(posted_vaa.key() == expected_vaa, BettingError::InvalidVaa);
+
+        let vaa = wormhole::PostedVaa::parse(&posted_vaa.try_borrow_data()?)?;
+        require!(
+            vaa.emitter_chain == bridge.emitter_chain
+                && vaa.emitter_address == bridge.emitter_address,
+            BettingError::UnknownEmitter
+        );
+        let payload = wormhole::BetPayload::parse(&vaa.payload)?;
+        require!(
+            payload.lobby == ctx.accounts.lobby.key()
+                && payload.bettor == ctx.accounts.bettor.key()
+                && payload.side == side,
+            BettingError::InvalidVaa
+        );
+        let lamports = math::mul_div(payload.amount, bridge.lamports_per_usdc, 1_000_000)?;
+
+        let consumed = &mut ctx.accounts.consumed_vaa;
+        consumed.vaa_hash = vaa_hash;
+        consumed.emitter_chain = vaa.emitter_chain;
+        consumed.sequence = vaa.sequence;
+        consumed.evm_bettor = payload.evm_bettor;
+        consumed.bet = ctx.accounts.bet.key();
+        consumed.consumed_at = Clock::get()?.unix_timestamp;
+
+        let accounts = &mut *ctx.accounts;
+        place_bet_with(
+            BetAccounts {
+                lobby: &mut accounts.lobby,
+                bet: &mut accounts.bet,
+                bettor: accounts.bettor.key(),
+                funder: Funder::Signer(accounts.relayer.to_account_info()),
+                bettor_stats: &mut accounts.bettor_stats,
+                self_exclusion: accounts.self_exclusion.to_account_info(),
+                denylist: accounts.denylist.to_account_info(),
+                escrow: accounts.escrow.to_account_info(),
+                config: &mut accounts.config,
+                treasury: accounts.treasury.to_account_info(),
+                attestation: accounts.attestation.as_ref(),
+                system_program: accounts.system_program.to_account_info(),
+                price_update: accounts.price_update.as_ref().map(|p| p.to_account_info()),
+                instructions: accounts.instructions.as_ref().map(|i| i.to_account_info()),
+            },
+            ctx.program_id,
+            payload.player_name,
+            lamports,
+            0,
+            payload.side,
+            [0; 16],
+            None,
+        )
+    }
+
+    /// Sends the bet's winnings to `payout_address` (e.g. a cold wallet or an
+    /// exchange deposit address) instead of the betting wallet. `None` reverts
+    /// to the bettor. Only possible before the bet is settled.
+    pub fn set_payout_address(
+        ctx: Context<SetPayoutAddress>,
+        payout_address: Option<Pubkey>,
+    ) -> Result<()> {
+        let bet = &mut ctx.accounts.bet;
+        require!(
+            bet.status.is_outstanding(),
+            BettingError::BetAlreadyProcessed
+        );
+        bet.payout_address = payout_address;
+        Ok(())
+    }
+
+    /// Settles every winning bet of the signer across finished lobbies in one
+    /// transaction. `remaining_accounts` holds one group per bet of
+    /// `[lobby, bet, escrow, snapshot, recipient]`: pass the program id as the
+    /// snapshot when the lobby's pools aren't locked, and the bet's payout
+    /// address or the bettor as the recipient.
+    pub fn claim_all<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimAll<'info>>) -> Result<()> {
+        let groups = ctx.remaining_accounts.chunks_exact(CLAIM_GROUP_LEN);
+        require!(
+            groups.remainder().is_empty() && groups.len() > 0,
+            BettingError::InvalidClaimAccounts
+        );
+
+        let bettor = ctx.accounts.bettor.to_account_info();
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.bettor_stats.roll_limit_windows(now);
+        for group in groups {
+            let [lobby_info, bet_info, escrow_info, snapshot_info, recipient_info] = group else {
+                unreachable!()
+            };
+            let mut lobby = Account::<Lobby>::try_from(lobby_info)?;
+            let mut bet = Account::<Bet>::try_from(bet_info)?;
+            require!(bet.bettor == bettor.key(), BettingError::InvalidBettor);
+
+            let lobby_key = lobby.key();
+            let (escrow, _) =
+                Pubkey::find_program_address(&[ESCROW_SEED, lobby_key.as_ref()], ctx.program_id);
+            require!(escrow_info.key() == escrow, BettingError::InvalidClaimAccounts);
+            let snapshot = if lobby.pools_locked {
+                let (expected, _) = Pubkey::find_program_address(
+                    &[SNAPSHOT_SEED, lobby_key.as_ref()],
+                    ctx.program_id,
+                );
+                require!(snapshot_info.key() == expected, BettingError::SnapshotRequired);
+                Some(Account::<PoolSnapshot>::try_from(snapshot_info)?)
+            } else {
+                None
+            };
+            require!(
+                recipient_info.key() == bet.payout_address.unwrap_or(bettor.key()),
+                BettingError::InvalidPayoutRecipient
+            );
+
+            let paid = pay_winning_bet(
+                &mut lobby,
+                &mut bet,
+                snapshot.as_deref(),
+                &ctx.accounts.config,
+                &ctx.accounts.denylist.to_account_info(),
+                escrow_info.clone(),
+                recipient_info.clone(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.program_id,
+            )?;
+            ctx.accounts
+                .bettor_stats
+                .record_payout(paid, now, &ctx.accounts.config)?;
+            ctx.accounts.bettor_stats.record_usd_payout(&bet, paid)?;
+            lobby.exit(ctx.program_id)?;
+            bet.exit(ctx.program_id)?;
+        }
+        Ok(())
+    }
+
+    /// Bets on the bucket of the winner's final chip count. Bucket `i` covers
+    /// stacks from `chip_buckets[i - 1]` (or zero) up to `chip_buckets[i]`; the
+    /// last bucket is open-ended. Settles parimutuel within the bucket pool.
+    pub fn place_chip_bucket_bet(
+        ctx: Context<PlaceChipBucketBet>,
+        bucket: u8,
+        amount: u64,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        let bettor = accounts.bettor.key();
+        require!(
+            (bucket as usize) < lobby.bucket_pools.len(),
+            BettingError::InvalidChipBucket
+        );
+        let price = check_open_for_bets(
+            lobby,
+            &accounts.config,
+            amount,
+            accounts.instructions.as_deref(),
+            accounts.price_update.as_deref(),
+        )?;
+        check_attestation(lobby, &accounts.config, accounts.attestation.as_ref(), bettor)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let fee = bettor_fee(
+            &accounts.config,
+            &accounts.bettor_stats,
+            lobby.key(),
+            amount,
+            now,
+        )?;
+        let stake = amount.checked_sub(fee).ok_or(BettingError::Overflow)?;
+        require!(stake > 0, BettingError::BetAmountMustBePositive);
+
+        record_wager(
+            &accounts.config,
+            &mut accounts.bettor_stats,
+            &accounts.self_exclusion,
+            &accounts.denylist,
+            bettor,
+            amount,
+            now,
+            price.as_ref(),
+            ctx.program_id,
+        )?;
+        let funder = Funder::Signer(accounts.bettor.to_account_info());
+        let system_program = accounts.system_program.to_account_info();
+        collect_fee(
+            &funder,
+            lobby,
+            &mut accounts.config,
+            &accounts.treasury,
+            &system_program,
+            fee,
+        )?;
+        funder.pay(&accounts.escrow, &system_program, stake)?;
+        lobby.track_deposit(stake)?;
+
+        let chip_bet = &mut accounts.chip_bet;
+        chip_bet.bettor = bettor;
+        chip_bet.lobby = lobby.key();
+        chip_bet.bucket = bucket;
+        chip_bet.amount = stake;
+        chip_bet.placed_at = now;
+        chip_bet.status = BetStatus::Active;
+
+        lobby.bucket_pools[bucket as usize] = lobby.bucket_pools[bucket as usize]
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::BetPlaced)?;
+        Ok(())
+    }
+
+    /// Pays a chip-count bet whose bucket holds the winner's final stack. The
+    /// claim that settles the last of the winning bucket takes the rounding
+    /// remainder, as in the main market.
+    pub fn distribute_chip_bucket_winning(ctx: Context<DistributeChipBucketWinning>) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        require!(lobby.owner == accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(lobby.payout_root.is_none(), BettingError::MerkleSettlement);
+        let chip_bet = &mut accounts.chip_bet;
+        require!(
+            chip_bet.lobby == lobby.key()
+                || (lobby.chip_market_rolled && lobby.parent_lobby == Some(chip_bet.lobby)),
+            BettingError::InvalidBetAccount
+        );
+        require!(
+            chip_bet.status == BetStatus::Active,
+            BettingError::BetAlreadyProcessed
+        );
+        check_not_denied(&accounts.denylist, &chip_bet.bettor, ctx.program_id)?;
+
+        let winning_bucket = lobby.winning_chip_bucket()?;
+        require!(
+            chip_bet.bucket as usize == winning_bucket,
+            BettingError::BetOnWrongPlayer
+        );
+        let distributable = lobby
+            .bucket_pools
+            .iter()
+            .try_fold(0u64, |sum, pool| sum.checked_add(*pool))
+            .ok_or(BettingError::Overflow)?;
+        let winning_stake = lobby.bucket_pools[winning_bucket];
+        let settled = lobby
+            .bucket_stake_paid
+            .checked_add(chip_bet.amount)
+            .ok_or(BettingError::Overflow)?;
+        require!(settled <= winning_stake, BettingError::Overflow);
+        let transfer_amount = if settled == winning_stake {
+            distributable
+                .checked_sub(lobby.bucket_paid)
+                .ok_or(BettingError::Overflow)?
+        } else {
+            parimutuel_payout(chip_bet.amount, distributable, winning_stake)?
+        };
+        lobby.bucket_stake_paid = settled;
+        lobby.bucket_paid = lobby
+            .bucket_paid
+            .checked_add(transfer_amount)
+            .ok_or(BettingError::Overflow)?;
+
+        let below_minimum = transfer_amount < accounts.config.min_payout_lamports;
+        let to = if below_minimum {
+            accounts.treasury.to_account_info()
+        } else {
+            accounts.bettor.to_account_info()
+        };
+        if transfer_amount > 0 {
+            let lobby_key = lobby.key();
+            let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    accounts.escrow.key,
+                    to.key,
+                    transfer_amount,
+                ),
+                &[
+                    accounts.escrow.to_account_info(),
+                    to,
+                    accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+        }
+        lobby.track_withdrawal(transfer_amount)?;
+
+        if below_minimum {
+            chip_bet.status = BetStatus::BelowMinimum;
+            touch_lobby(lobby, LobbyAction::PayoutSwept)?;
+        } else {
+            chip_bet.status = BetStatus::Paid;
+            touch_lobby(lobby, LobbyAction::WinningsPaid)?;
+            let now = Clock::get()?.unix_timestamp;
+            let stats = &mut accounts.bettor_stats;
+            stats.roll_limit_windows(now);
+            stats.record_payout(transfer_amount, now, &accounts.config)?;
+        }
+        Ok(())
+    }
+
+    /// Sells the viewer a pass to the lobby's stream. The protocol fee share
+    /// goes to the treasury and the rest to the lobby owner. Streaming servers
+    /// gate on the pass account existing; see `SpectatorPass::grants_access`.
+    pub fn buy_spectator_pass(ctx: Context<BuySpectatorPass>) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        let fee = lobby.spectator_fee;
+        require!(fee > 0, BettingError::SpectatorPassesNotForSale);
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        check_not_denied(&ctx.accounts.denylist, ctx.accounts.viewer.key, ctx.program_id)?;
+
+        let protocol_cut = math::apply_bps(fee, ctx.accounts.config.fee_bps as u64)?;
+        for (to, amount) in [
+            (ctx.accounts.treasury.to_account_info(), protocol_cut),
+            (ctx.accounts.owner.to_account_info(), fee - protocol_cut),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            let cpi_accounts = anchor_lang::system_program::Transfer {
+                from: ctx.accounts.viewer.to_account_info(),
+                to,
+            };
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                cpi_accounts,
+            );
+            anchor_lang::system_program::transfer(cpi_context, amount)?;
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let pass = &mut ctx.accounts.pass;
+        pass.lobby = lobby.key();
+        pass.viewer = ctx.accounts.viewer.key();
+        pass.paid = fee;
+        pass.purchased_at = now;
+
+        emit!(SpectatorPassPurchased {
+            lobby: pass.lobby,
+            viewer: pass.viewer,
+            paid: fee,
+            purchased_at: now,
+        });
+        Ok(())
+    }
+
+    /// Points achievements at a Bubblegum tree whose creator or delegate is
+    /// the `achievement_authority` PDA. Leaf URIs are `metadata_uri` followed
+    /// by the achievement's slug.
+    pub fn set_achievement_tree(
+        ctx: Context<SetAchievementTree>,
+        merkle_tree: Pubkey,
+        metadata_uri: String,
+    ) -> Result<()> {
+        require!(
+            metadata_uri.len() <= MAX_ACHIEVEMENT_URI_LEN,
+            BettingError::AchievementUriTooLong
+        );
+        let achievements = &mut ctx.accounts.achievement_config;
+        achievements.merkle_tree = merkle_tree;
+        achievements.metadata_uri = metadata_uri;
+        ctx.accounts.config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Enters the signer's best payout of the current calendar week into that
+    /// week's record, taking it over if larger.
+    pub fn submit_weekly_payout(ctx: Context<SubmitWeeklyPayout>, week: i64) -> Result<()> {
+        require!(
+            week == calendar_week(Clock::get()?.unix_timestamp),
+            BettingError::InvalidAchievementWeek
+        );
+        let stats = &ctx.accounts.bettor_stats;
+        require!(
+            stats.best_payout_week == week && stats.best_payout > 0,
+            BettingError::AchievementNotEarned
+        );
+        let record = &mut ctx.accounts.weekly_record;
+        record.week = week;
+        if stats.best_payout > record.amount {
+            record.holder = stats.bettor;
+            record.amount = stats.best_payout;
+        }
+        Ok(())
+    }
+
+    /// Mints an achievement the signer has earned as a compressed NFT. Each
+    /// achievement can be claimed once per wallet.
+    pub fn claim_achievement(
+        ctx: Context<ClaimAchievement>,
+        achievement: Achievement,
+    ) -> Result<()> {
+        let bettor = ctx.accounts.bettor.key();
+        let stats = &ctx.accounts.bettor_stats;
+        let earned = match achievement {
+            Achievement::FirstBet => stats.total_bets >= 1,
+            Achievement::TenWins => stats.wins >= TEN_WINS,
+            Achievement::BiggestPayoutOfWeek { week } => {
+                require!(
+                    week < calendar_week(Clock::get()?.unix_timestamp),
+                    BettingError::InvalidAchievementWeek
+                );
+                let record = ctx
+                    .accounts
+                    .weekly_record
+                    .as_ref()
+                    .ok_or(BettingError::AchievementNotEarned)?;
+                record.week == week && record.holder == bettor
+            }
+        };
+        require!(earned, BettingError::AchievementNotEarned);
+
+        let now = Clock::get()?.unix_timestamp;
+        let claim = &mut ctx.accounts.claim;
+        claim.bettor = bettor;
+        claim.achievement = achievement;
+        claim.claimed_at = now;
+
+        let uri = format!(
+            "{}{}",
+            ctx.accounts.achievement_config.metadata_uri,
+            achievement.slug()
+        );
+        let seeds: &[&[u8]] = &[
+            ACHIEVEMENT_AUTHORITY_SEED,
+            &[ctx.bumps.achievement_authority],
+        ];
+        bubblegum::mint_v1(
+            bubblegum::MintV1 {
+                bubblegum_program: &ctx.accounts.bubblegum_program.to_account_info(),
+                tree_config: &ctx.accounts.tree_config.to_account_info(),
+                leaf_owner: &ctx.accounts.bettor.to_account_info(),
+                merkle_tree: &ctx.accounts.merkle_tree.to_account_info(),
+                payer: &ctx.accounts.bettor.to_account_info(),
+                tree_delegate: &ctx.accounts.achievement_authority.to_account_info(),
+                log_wrapper: &ctx.accounts.log_wrapper.to_account_info(),
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            },
+            achievement.name(),
+            "PXACH".to_string(),
+            uri,
+            &[seeds],
+        )?;
+        Ok(())
+    }
+
+    /// Settles a finished lobby by Merkle distribution: `root` commits to every
+    /// `(bettor, payout)` the operator computed off-chain from the lobby's bets,
+    /// paying `total` in all. Bettors can challenge the root for
+    /// `PAYOUT_CHALLENGE_WINDOW`, after which they claim with proofs. Only
+    /// possible before any bet has been settled individually.
+    pub fn post_payout_root(
+        ctx: Context<UpdateLobbyStatus>,
+        root: [u8; 32],
+        total: u64,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(lobby.payout_root.is_none(), BettingError::MerkleSettlement);
+        require!(
+            lobby.position_paid.iter().all(|paid| *paid == 0)
+                && lobby.fixed_odds_paid == 0
+                && lobby.bucket_paid == 0,
+            BettingError::MerkleSettlement
+        );
+        require!(total <= lobby.escrow_balance, BettingError::InvalidPayoutRoot);
+
+        lobby.payout_root = Some(root);
+        lobby.payout_root_posted_at = Clock::get()?.unix_timestamp;
+        lobby.payout_root_total = total;
+        lobby.payout_root_challenged = false;
+        lobby.merkle_paid = 0;
+        touch_lobby(lobby, LobbyAction::PayoutRootPosted)?;
+        Ok(())
+    }
+
+    /// Freezes claims against the payout root until the admin rules on it. Any
+    /// bettor on the lobby may challenge during the challenge window.
+    pub fn challenge_payout_root(ctx: Context<ChallengePayoutRoot>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.payout_root.is_some(), BettingError::InvalidPayoutRoot);
+        require!(
+            Clock::get()?.unix_timestamp < lobby.payout_root_posted_at + PAYOUT_CHALLENGE_WINDOW,
+            BettingError::ChallengeWindowClosed
+        );
+        lobby.payout_root_challenged = true;
+        touch_lobby(lobby, LobbyAction::PayoutRootChallenged)?;
+        Ok(())
+    }
+
+    /// Rules on a challenged payout root: upheld roots open for claims,
+    /// rejected ones are cleared for the operator to post again.
+    pub fn resolve_payout_challenge(ctx: Context<ResolvePayoutChallenge>, uphold: bool) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.payout_root_challenged, BettingError::InvalidPayoutRoot);
+        lobby.payout_root_challenged = false;
+        if !uphold {
+            lobby.payout_root = None;
+            lobby.payout_root_total = 0;
+            lobby.disputes_lost = lobby
+                .disputes_lost
+                .checked_add(1)
+                .ok_or(BettingError::Overflow)?;
+            let profile = &mut ctx.accounts.operator_profile;
+            profile.disputes_lost = profile
+                .disputes_lost
+                .checked_add(1)
+                .ok_or(BettingError::Overflow)?;
+        }
+        touch_lobby(lobby, LobbyAction::PayoutRootResolved)?;
+        Ok(())
+    }
+
+    /// Pays the signer's leaf of the lobby's payout root once the challenge
+    /// window has passed unchallenged, to the payout address of the bet
+    /// passed if it has one.
+    pub fn claim_with_proof(
+        ctx: Context<ClaimWithProof>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let lobby = &mut ctx.accounts.lobby;
+        let root = lobby.payout_root.ok_or(BettingError::InvalidPayoutRoot)?;
+        require!(
+            !lobby.payout_root_challenged
+                && now >= lobby.payout_root_posted_at + PAYOUT_CHALLENGE_WINDOW,
+            BettingError::ChallengeWindowOpen
+        );
+        require!(lobby.in_custody == 0, BettingError::FundsInCustody);
+        let bettor = ctx.accounts.bettor.key();
+        check_not_denied(&ctx.accounts.denylist, &bettor, ctx.program_id)?;
+        require!(
+            merkle::verify(&proof, &root, merkle::leaf(&bettor, amount)),
+            BettingError::InvalidMerkleProof
+        );
+        let paid = lobby
+            .merkle_paid
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        require!(paid <= lobby.payout_root_total, BettingError::InvalidPayoutRoot);
+        lobby.merkle_paid = paid;
+        let recipient = payout_recipient(
+            &ctx.accounts.bet,
+            ctx.accounts.bettor.to_account_info(),
+            ctx.accounts
+                .payout_recipient
+                .as_ref()
+                .map(|r| r.to_account_info()),
+        )?;
+        check_not_denied(&ctx.accounts.denylist, recipient.key, ctx.program_id)?;
+
+        let claim = &mut ctx.accounts.claim;
+        claim.lobby = lobby.key();
+        claim.bettor = bettor;
+        claim.amount = amount;
+        claim.claimed_at = now;
+
+        if amount > 0 {
+            let lobby_key = lobby.key();
+            let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    ctx.accounts.escrow.key,
+                    recipient.key,
+                    amount,
+                ),
+                &[
+                    ctx.accounts.escrow.to_account_info(),
+                    recipient,
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+        }
+        lobby.track_withdrawal(amount)?;
+        touch_lobby(lobby, LobbyAction::WinningsPaid)?;
+
+        let stats = &mut ctx.accounts.bettor_stats;
+        stats.roll_limit_windows(now);
+        stats.record_payout(amount, now, &ctx.accounts.config)?;
+        Ok(())
+    }
+
+    /// Marks a winning bet on a lobby settled by payout root paid once its
+    /// bettor has claimed against the root, so the bet can close. Anyone can
+    /// call it.
+    pub fn settle_claimed_bet(ctx: Context<SettleClaimedBet>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &mut ctx.accounts.bet;
+        require!(lobby.payout_root.is_some(), BettingError::InvalidPayoutRoot);
+        require!(bet.wins(lobby)?, BettingError::BetOnWrongPlayer);
+        transition_bet(lobby, bet, BetStatus::Paid)
+    }
+
+    /// Opens compressed betting on a lobby. `merkle_tree` must already be
+    /// allocated for `max_depth`/`max_buffer_size` and owned by the compression
+    /// program; the `bet_tree_authority` PDA becomes its authority.
+    pub fn init_bet_tree(
+        ctx: Context<InitBetTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(lobby.bet_tree.is_none(), BettingError::CompressedBetsUnsupported);
+
+        let seeds: &[&[u8]] = &[BET_TREE_AUTHORITY_SEED, &[ctx.bumps.tree_authority]];
+        compression::init_empty_merkle_tree(
+            compression::TreeAccounts {
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                merkle_tree: &ctx.accounts.merkle_tree.to_account_info(),
+                authority: &ctx.accounts.tree_authority.to_account_info(),
+                noop: &ctx.accounts.log_wrapper.to_account_info(),
+            },
+            max_depth,
+            max_buffer_size,
+            &[seeds],
+        )?;
+        lobby.bet_tree = Some(ctx.accounts.merkle_tree.key());
+        lobby.compressed_bets = 0;
+        touch_lobby(lobby, LobbyAction::BetTreeInitialized)?;
+        Ok(())
+    }
+
+    /// Places a back bet stored as a leaf of the lobby's bet tree instead of a
+    /// `Bet` account, saving the rent. Indexers rebuild the tree from
+    /// `CompressedBetPlaced` events. Parimutuel winner-takes-pool lobbies only.
+    pub fn place_compressed_bet(
+        ctx: Context<PlaceCompressedBet>,
+        player_name: String,
+        amount: u64,
+        min_expected_odds_bps: u32,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        let bettor = accounts.bettor.key();
+        let price = check_open_for_bets(
+            lobby,
+            &accounts.config,
+            amount,
+            accounts.instructions.as_deref(),
+            accounts.price_update.as_deref(),
+        )?;
+        require!(
+            lobby.market_mode == MarketMode::Parimutuel
+                && lobby.payout_curve.len() == 1
+                && lobby.weighting == PoolWeighting::Stake,
+            BettingError::CompressedBetsUnsupported
+        );
+        let model_index = lobby.model_index(&player_name)?;
+        check_attestation(lobby, &accounts.config, accounts.attestation.as_ref(), bettor)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let fee = bettor_fee(
+            &accounts.config,
+            &accounts.bettor_stats,
+            lobby.key(),
+            amount,
+            now,
+        )?;
+        let stake = amount.checked_sub(fee).ok_or(BettingError::Overflow)?;
+        require!(stake > 0, BettingError::BetAmountMustBePositive);
+        require!(
+            lobby.implied_odds_after(model_index, BetSide::Back, stake)?
+                >= min_expected_odds_bps as u64,
+            BettingError::SlippageExceeded
+        );
+
+        record_wager(
+            &accounts.config,
+            &mut accounts.bettor_stats,
+            &accounts.self_exclusion,
+            &accounts.denylist,
+            bettor,
+            amount,
+            now,
+            price.as_ref(),
+            ctx.program_id,
+        )?;
+        let funder = Funder::Signer(accounts.bettor.to_account_info());
+        let system_program = accounts.system_program.to_account_info();
+        collect_fee(
+            &funder,
+            lobby,
+            &mut accounts.config,
+            &accounts.treasury,
+            &system_program,
+            fee,
+        )?;
+        funder.pay(&accounts.escrow, &system_program, stake)?;
+        lobby.track_deposit(stake)?;
+
+        let bet = CompressedBet {
+            bettor,
+            lobby: lobby.key(),
+            player_name,
+            amount: stake,
+            placed_at: now,
+            index: lobby.compressed_bets,
+        };
+        let seeds: &[&[u8]] = &[BET_TREE_AUTHORITY_SEED, &[ctx.bumps.tree_authority]];
+        compression::append(
+            compression::TreeAccounts {
+                compression_program: &accounts.compression_program.to_account_info(),
+                merkle_tree: &accounts.merkle_tree.to_account_info(),
+                authority: &accounts.tree_authority.to_account_info(),
+                noop: &accounts.log_wrapper.to_account_info(),
+            },
+            bet.leaf()?,
+            &[seeds],
+        )?;
+
+        lobby.compressed_bets = lobby
+            .compressed_bets
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        lobby.total_bets = lobby
+            .total_bets
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        lobby.model_pools[model_index] = lobby.model_pools[model_index]
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::BetPlaced)?;
+        emit!(CompressedBetPlaced { bet });
+        Ok(())
+    }
+
+    /// Pays a winning compressed bet to its bettor. `remaining_accounts` is the
+    /// leaf's proof against `root`; the leaf is zeroed so it can't be claimed
+    /// twice.
+    pub fn claim_compressed_bet<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimCompressedBet<'info>>,
+        bet: CompressedBet,
+        root: [u8; 32],
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        require!(
+            bet.bettor == accounts.bettor.key() && bet.lobby == lobby.key(),
+            BettingError::InvalidBetAccount
+        );
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(lobby.payout_root.is_none(), BettingError::MerkleSettlement);
+        check_not_denied(&accounts.denylist, &bet.bettor, ctx.program_id)?;
+        let winner_name = lobby.winner.clone().ok_or(BettingError::LobbyNotFinished)?;
+        require!(bet.player_name == winner_name, BettingError::BetOnWrongPlayer);
+
+        let index = u32::try_from(bet.index).map_err(|_| error!(BettingError::InvalidBetAccount))?;
+        let seeds: &[&[u8]] = &[BET_TREE_AUTHORITY_SEED, &[ctx.bumps.tree_authority]];
+        compression::replace_leaf(
+            compression::TreeAccounts {
+                compression_program: &accounts.compression_program.to_account_info(),
+                merkle_tree: &accounts.merkle_tree.to_account_info(),
+                authority: &accounts.tree_authority.to_account_info(),
+                noop: &accounts.log_wrapper.to_account_info(),
+            },
+            root,
+            bet.leaf()?,
+            [0; 32],
+            index,
+            ctx.remaining_accounts,
+            &[seeds],
+        )?;
+
+        let winner_index = lobby.model_index(&winner_name)?;
+        let (distributable, winning_stake) = if lobby.pools_locked {
+            accounts
+                .snapshot
+                .as_ref()
+                .ok_or(BettingError::SnapshotRequired)?
+                .parimutuel_totals(winner_index)?
+        } else {
+            lobby.parimutuel_totals(winner_index)?
+        };
+        let distributable = distributable
+            .checked_sub(lobby.charity_amount)
+            .ok_or(BettingError::Overflow)?;
+        let transfer_amount = lobby.settle_parimutuel(0, bet.amount, distributable, winning_stake)?;
+
+        let below_minimum = transfer_amount < accounts.config.min_payout_lamports;
+        let to = if below_minimum {
+            accounts.treasury.to_account_info()
+        } else {
+            accounts.bettor.to_account_info()
+        };
+        if transfer_amount > 0 {
+            let lobby_key = lobby.key();
+            let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    accounts.escrow.key,
+                    to.key,
+                    transfer_amount,
+                ),
+                &[
+                    accounts.escrow.to_account_info(),
+                    to,
+                    accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+        }
+        lobby.track_withdrawal(transfer_amount)?;
+
+        if below_minimum {
+            touch_lobby(lobby, LobbyAction::PayoutSwept)?;
+        } else {
+            touch_lobby(lobby, LobbyAction::WinningsPaid)?;
+            let now = Clock::get()?.unix_timestamp;
+            let stats = &mut accounts.bettor_stats;
+            stats.roll_limit_windows(now);
+            stats.record_payout(transfer_amount, now, &accounts.config)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_shielded_verifier(
+        ctx: Context<SetShieldedVerifier>,
+        alpha_g1: groth16::G1,
+        beta_g2: groth16::G2,
+        gamma_g2: groth16::G2,
+        delta_g2: groth16::G2,
+        ic: Vec<groth16::G1>,
+    ) -> Result<()> {
+        require!(
+            ic.len() == shielded::PUBLIC_INPUTS + 1,
+            BettingError::InvalidVerifyingKey
+        );
+        let verifier = &mut ctx.accounts.verifier;
+        verifier.alpha_g1 = alpha_g1;
+        verifier.beta_g2 = beta_g2;
+        verifier.gamma_g2 = gamma_g2;
+        verifier.delta_g2 = delta_g2;
+        verifier.ic = ic;
+        ctx.accounts.config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Creates the shielded note pool. Every note is worth `denomination`.
+    pub fn initialize_shielded_pool(
+        ctx: Context<InitializeShieldedPool>,
+        denomination: u64,
+    ) -> Result<()> {
+        require!(denomination > 0, BettingError::BetAmountMustBePositive);
+        let pool = &mut ctx.accounts.pool;
+        pool.denomination = denomination;
+        pool.next_index = 0;
+        pool.filled_subtrees = (0..shielded::TREE_DEPTH).map(shielded::zero).collect();
+        pool.roots = vec![shielded::zero(shielded::TREE_DEPTH)];
+        pool.root_index = 0;
+        ctx.accounts.config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Deposits one note of the pool's denomination under `commitment`, a hash
+    /// of the note's secret and nullifier known only to the depositor.
+    pub fn shielded_deposit(ctx: Context<ShieldedDeposit>, commitment: [u8; 32]) -> Result<()> {
+        require!(
+            commitment < groth16::SCALAR_FIELD_MODULUS,
+            BettingError::InvalidPublicInput
+        );
+        let denomination = ctx.accounts.pool.denomination;
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.depositor.to_account_info(),
+            to: ctx.accounts.pool.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            cpi_accounts,
+        );
+        anchor_lang::system_program::transfer(cpi_context, denomination)?;
+
+        let pool = &mut ctx.accounts.pool;
+        let leaf_index = pool.next_index;
+        let root = shielded::insert(&mut pool.filled_subtrees, leaf_index, commitment)?;
+        pool.next_index += 1;
+        pool.push_root(root);
+        emit!(ShieldedNoteDeposited {
+            commitment,
+            leaf_index,
+            root,
+        });
+        Ok(())
+    }
+
+    /// Spends a shielded note on a bet for `bettor`, normally a fresh wallet
+    /// that later claims the winnings. The relayer submitting the proof pays
+    /// the account rent; the stake comes from the pool.
+    pub fn place_shielded_bet(
+        ctx: Context<PlaceShieldedBet>,
+        nullifier_hash: [u8; 32],
+        root: [u8; 32],
+        proof: groth16::Proof,
+        player_name: String,
+        side: BetSide,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.pool.roots.contains(&root),
+            BettingError::UnknownShieldedRoot
+        );
+        let binding = shielded::bet_binding(
+            &ctx.accounts.lobby.key(),
+            &ctx.accounts.bettor.key(),
+            side,
+            &player_name,
+        );
+        let verifier = &ctx.accounts.verifier;
+        let vk = groth16::VerifyingKey {
+            alpha_g1: &verifier.alpha_g1,
+            beta_g2: &verifier.beta_g2,
+            gamma_g2: &verifier.gamma_g2,
+            delta_g2: &verifier.delta_g2,
+            ic: &verifier.ic,
+        };
+        require!(
+            groth16::verify(&vk, &proof, &[root, nullifier_hash, binding])?,
+            BettingError::InvalidProof
+        );
+
+        let nullifier = &mut ctx.accounts.nullifier;
+        nullifier.nullifier_hash = nullifier_hash;
+        nullifier.spent_at = Clock::get()?.unix_timestamp;
+
+        let accounts = &mut *ctx.accounts;
+        let denomination = accounts.pool.denomination;
+        place_bet_with(
+            BetAccounts {
+                lobby: &mut accounts.lobby,
+                bet: &mut accounts.bet,
+                bettor: accounts.bettor.key(),
+                funder: Funder::Program(accounts.pool.to_account_info()),
+                bettor_stats: &mut accounts.bettor_stats,
+                self_exclusion: accounts.self_exclusion.to_account_info(),
+                denylist: accounts.denylist.to_account_info(),
+                escrow: accounts.escrow.to_account_info(),
+                config: &mut accounts.config,
+                treasury: accounts.treasury.to_account_info(),
+                attestation: accounts.attestation.as_ref(),
+                system_program: accounts.system_program.to_account_info(),
+                price_update: accounts.price_update.as_ref().map(|p| p.to_account_info()),
+                instructions: accounts.instructions.as_ref().map(|i| i.to_account_info()),
+            },
+            ctx.program_id,
+            player_name,
+            denomination,
+            0,
+            side,
+            [0; 16],
+            None,
+        )
+    }
+
+    /// Escrows a back bet whose model is hidden behind `commitment`, the hash
+    /// of `blind_bet_commitment(lobby, bettor, model, salt)`, so observers can't
+    /// copy it or move the odds against it before betting closes.
+    pub fn place_blind_bet(
+        ctx: Context<PlaceBlindBet>,
+        commitment: [u8; 32],
+        amount: u64,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        let bettor = accounts.bettor.key();
+        let price = check_open_for_bets(
+            lobby,
+            &accounts.config,
+            amount,
+            accounts.instructions.as_deref(),
+            accounts.price_update.as_deref(),
+        )?;
+        require!(
+            lobby.reveal_deadline > 0
+                && lobby.market_mode == MarketMode::Parimutuel
+                && lobby.weighting == PoolWeighting::Stake,
+            BettingError::BlindBetsDisabled
+        );
+        check_attestation(lobby, &accounts.config, accounts.attestation.as_ref(), bettor)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let fee = bettor_fee(
+            &accounts.config,
+            &accounts.bettor_stats,
+            lobby.key(),
+            amount,
+            now,
+        )?;
+        let stake = amount.checked_sub(fee).ok_or(BettingError::Overflow)?;
+        require!(stake > 0, BettingError::BetAmountMustBePositive);
+
+        record_wager(
+            &accounts.config,
+            &mut accounts.bettor_stats,
+            &accounts.self_exclusion,
+            &accounts.denylist,
+            bettor,
+            amount,
+            now,
+            price.as_ref(),
+            ctx.program_id,
+        )?;
+        let funder = Funder::Signer(accounts.bettor.to_account_info());
+        let system_program = accounts.system_program.to_account_info();
+        collect_fee(
+            &funder,
+            lobby,
+            &mut accounts.config,
+            &accounts.treasury,
+            &system_program,
+            fee,
+        )?;
+        funder.pay(&accounts.escrow, &system_program, stake)?;
+        lobby.track_deposit(stake)?;
+        lobby.blind_stake = lobby
+            .blind_stake
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+
+        let blind_bet = &mut accounts.blind_bet;
+        blind_bet.bettor = bettor;
+        blind_bet.lobby = lobby.key();
+        blind_bet.commitment = commitment;
+        blind_bet.amount = stake;
+        blind_bet.requested_amount = amount;
+        blind_bet.placed_at = now;
+        touch_lobby(lobby, LobbyAction::BetPlaced)?;
+        Ok(())
+    }
+
+    /// Opens a blind bet into a regular back bet on `player_name`. Only during
+    /// the reveal window.
+    pub fn reveal_bet(ctx: Context<RevealBet>, player_name: String, salt: [u8; 32]) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let now = Clock::get()?.unix_timestamp;
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        require!(
+            lobby.betting_closed(now) && !lobby.pools_locked && now < lobby.reveal_deadline,
+            BettingError::RevealWindowClosed
+        );
+        let blind_bet = &ctx.accounts.blind_bet;
+        require!(
+            blind_bet_commitment(&lobby.key(), &blind_bet.bettor, &player_name, &salt)
+                == blind_bet.commitment,
+            BettingError::CommitmentMismatch
+        );
+        let model_index = lobby.model_index(&player_name)?;
+        require!(
+            !lobby.is_disqualified(&player_name),
+            BettingError::ModelDisqualified
+        );
+
+        lobby.blind_stake = lobby
+            .blind_stake
+            .checked_sub(blind_bet.amount)
+            .ok_or(BettingError::Overflow)?;
+        lobby.total_bets = lobby
+            .total_bets
+            .checked_add(blind_bet.amount)
+            .ok_or(BettingError::Overflow)?;
+        lobby.model_pools[model_index] = lobby.model_pools[model_index]
+            .checked_add(blind_bet.amount)
+            .ok_or(BettingError::Overflow)?;
+
+        let bet = &mut ctx.accounts.bet;
+        bet.bettor = blind_bet.bettor;
+        bet.lobby = lobby.key();
+        bet.player_name = player_name;
+        bet.amount = blind_bet.amount;
+        bet.requested_amount = blind_bet.requested_amount;
+        bet.placed_at = blind_bet.placed_at;
+        bet.status = BetStatus::Active;
+        lobby.outstanding_bets = lobby
+            .outstanding_bets
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        bet.odds_bps = 0;
+        bet.side = BetSide::Back;
+        bet.payout_address = None;
+        bet.client_id = [0; 16];
+        bet.memo = None;
+        touch_lobby(lobby, LobbyAction::BetRevealed)?;
+        Ok(())
+    }
+
+    /// Refunds a blind bet left unrevealed after the reveal window, keeping
+    /// `BLIND_BET_PENALTY_BPS` of it for the treasury.
+    pub fn refund_unrevealed_bet(ctx: Context<RefundUnrevealedBet>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            Clock::get()?.unix_timestamp >= lobby.reveal_deadline,
+            BettingError::RevealWindowOpen
+        );
+        let amount = ctx.accounts.blind_bet.amount;
+        let penalty = math::apply_bps(amount, BLIND_BET_PENALTY_BPS)?;
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        for (to, lamports) in [
+            (ctx.accounts.treasury.to_account_info(), penalty),
+            (ctx.accounts.bettor.to_account_info(), amount - penalty),
+        ] {
+            if lamports == 0 {
+                continue;
+            }
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    ctx.accounts.escrow.key,
+                    to.key,
+                    lamports,
+                ),
+                &[
+                    ctx.accounts.escrow.to_account_info(),
+                    to,
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+        }
+        lobby.track_withdrawal(amount)?;
+        lobby.blind_stake = lobby
+            .blind_stake
+            .checked_sub(amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::BlindBetRefunded)?;
+        let (lobby, due_at) = (lobby.key(), lobby.reveal_deadline);
+        let accounts = &mut *ctx.accounts;
+        credit_keeper(
+            accounts.keeper.as_mut(),
+            accounts.keeper_authority.as_ref(),
+            accounts.config.as_deref(),
+            lobby,
+            due_at,
+            1,
+        )
+    }
+
+    /// Stores the verifying key for the bounty event circuit, whose public
+    /// inputs are the event statement and `bounty_hand_binding` of the hand.
+    pub fn set_bounty_verifier(
+        ctx: Context<SetBountyVerifier>,
+        alpha_g1: groth16::G1,
+        beta_g2: groth16::G2,
+        gamma_g2: groth16::G2,
+        delta_g2: groth16::G2,
+        ic: Vec<groth16::G1>,
+    ) -> Result<()> {
+        require!(
+            ic.len() == BOUNTY_PUBLIC_INPUTS + 1,
+            BettingError::InvalidVerifyingKey
+        );
+        let verifier = &mut ctx.accounts.verifier;
+        verifier.alpha_g1 = alpha_g1;
+        verifier.beta_g2 = beta_g2;
+        verifier.gamma_g2 = gamma_g2;
+        verifier.delta_g2 = delta_g2;
+        verifier.ic = ic;
+        ctx.accounts.config.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Opens a bounty on `event` happening in one of the lobby's hands, funded
+    /// with `amount`. Anyone can add to it with `fund_bounty`.
+    pub fn create_bounty(
+        ctx: Context<CreateBounty>,
+        bounty_id: u64,
+        event: BountyEvent,
+        amount: u64,
+    ) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        if let BountyEvent::HandWonBy { player_name } = &event {
+            lobby.model_index(player_name)?;
+        }
+
+        let bounty = &mut ctx.accounts.bounty;
+        bounty.lobby = lobby.key();
+        bounty.bounty_id = bounty_id;
+        bounty.event = event;
+        bounty.total = 0;
+        bounty.claimed_by = None;
+        bounty.created_at = Clock::get()?.unix_timestamp;
+        add_to_bounty(
+            bounty,
+            &mut ctx.accounts.contribution,
+            &ctx.accounts.funder,
+            &ctx.accounts.system_program,
+            amount,
+        )
+    }
+
+    pub fn fund_bounty(ctx: Context<FundBounty>, amount: u64) -> Result<()> {
+        require!(
+            !ctx.accounts.lobby.status.is_over(),
+            BettingError::LobbyAlreadyFinished
+        );
+        require!(
+            ctx.accounts.bounty.claimed_by.is_none(),
+            BettingError::BountyClaimed
+        );
+        add_to_bounty(
+            &mut ctx.accounts.bounty,
+            &mut ctx.accounts.contribution,
+            &ctx.accounts.funder,
+            &ctx.accounts.system_program,
+            amount,
+        )
+    }
+
+    /// Pays out a bounty whose event happened in `hand`. Signed by the engine
+    /// authority (the game server) before the lobby finishes. `proof` is only
+    /// read for `BountyEvent::Proven`.
+    pub fn claim_bounty(ctx: Context<ClaimBounty>, proof: Option<groth16::Proof>) -> Result<()> {
+        require!(
+            !ctx.accounts.lobby.status.is_over(),
+            BettingError::LobbyAlreadyFinished
+        );
+        let bounty = &mut ctx.accounts.bounty;
+        require!(bounty.claimed_by.is_none(), BettingError::BountyClaimed);
+
+        let hand = &ctx.accounts.hand;
+        let happened = match &bounty.event {
+            BountyEvent::HandWonBy { player_name } => hand.winner_name == *player_name,
+            BountyEvent::PotAtLeast { pot } => hand.pot >= *pot,
+            BountyEvent::Proven { statement } => {
+                let proof = proof.ok_or(BettingError::InvalidProof)?;
+                let verifier = ctx
+                    .accounts
+                    .verifier
+                    .as_ref()
+                    .ok_or(BettingError::InvalidProof)?;
+                let vk = groth16::VerifyingKey {
+                    alpha_g1: &verifier.alpha_g1,
+                    beta_g2: &verifier.beta_g2,
+                    gamma_g2: &verifier.gamma_g2,
+                    delta_g2: &verifier.delta_g2,
+                    ic: &verifier.ic,
+                };
+                let binding = bounty_hand_binding(&hand.key(), &hand.trace_hashes);
+                groth16::verify(&vk, &proof, &[*statement, binding])?
+            }
+        };
+        require!(happened, BettingError::BountyEventNotMet);
+
+        bounty.claimed_by = Some(hand.key());
+        let total = bounty.total;
+        Funder::Program(bounty.to_account_info()).pay(
+            &ctx.accounts.recipient,
+            &ctx.accounts.system_program,
+            total,
+        )?;
+        emit!(BountyClaimed {
+            bounty: bounty.key(),
+            hand: hand.key(),
+            recipient: ctx.accounts.recipient.key(),
+            amount: total,
+        });
+        Ok(())
+    }
+
+    /// Returns a funder's contribution to a bounty left unclaimed when the
+    /// lobby finished.
+    pub fn refund_bounty(ctx: Context<RefundBounty>) -> Result<()> {
+        require!(
+            ctx.accounts.lobby.status.is_over(),
+            BettingError::LobbyNotFinished
+        );
+        let bounty = &mut ctx.accounts.bounty;
+        require!(bounty.claimed_by.is_none(), BettingError::BountyClaimed);
+        let amount = ctx.accounts.contribution.amount;
+        bounty.total = bounty
+            .total
+            .checked_sub(amount)
+            .ok_or(BettingError::Overflow)?;
+        Funder::Program(bounty.to_account_info()).pay(
+            &ctx.accounts.funder,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+        Ok(())
+    }
+
+    /// Adds to the owner's bond on a lobby.
+    pub fn post_bond(ctx: Context<PostBond>, amount: u64) -> Result<()> {
+        require!(!ctx.accounts.bond.slashed, BettingError::BondSlashed);
+        require!(amount > 0, BettingError::BetAmountMustBePositive);
+        add_to_bond(
+            &mut ctx.accounts.bond,
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+            amount,
+        )
+    }
+
+    /// Moves a lobby's bond into its escrow for bettors to claim pro-rata, once
+    /// a payout root was rejected or the lobby missed its settlement deadline.
+    /// Anyone can call it.
+    pub fn slash_bond(ctx: Context<SlashBond>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bond = &mut ctx.accounts.bond;
+        require!(!bond.slashed, BettingError::BondSlashed);
+        let missed_deadline = bond.settle_by > 0
+            && !lobby.status.is_over()
+            && Clock::get()?.unix_timestamp >= bond.settle_by;
+        require!(
+            lobby.disputes_lost > 0 || missed_deadline,
+            BettingError::BondNotSlashable
+        );
+
+        let amount = bond.amount;
+        bond.amount = 0;
+        bond.slashed = true;
+        Funder::Program(bond.to_account_info()).pay(
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+        lobby.track_deposit(amount)?;
+        lobby.slashed_bond = amount;
+        lobby.bond_slashed_at = Clock::get()?.unix_timestamp;
+        lobby.compensable_stake = lobby.total_bets;
+        touch_lobby(lobby, LobbyAction::BondSlashed)?;
+        let (lobby, due_at) = (lobby.key(), bond.settle_by);
+        let accounts = &mut *ctx.accounts;
+        credit_keeper(
+            accounts.keeper.as_mut(),
+            accounts.keeper_authority.as_ref(),
+            accounts.config.as_deref(),
+            lobby,
+            due_at,
+            1,
+        )
+    }
+
+    /// Pays a bettor their share of a slashed bond, in proportion to their
+    /// share of the stake in the lobby when it was slashed, to the bet's
+    /// payout address if it has one. Bets placed after the slash, cashed out
+    /// or refunded get nothing, and each bet claims once.
+    pub fn claim_bond_compensation(ctx: Context<ClaimBondCompensation>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &ctx.accounts.bet;
+        require!(lobby.slashed_bond > 0, BettingError::BondNotSlashable);
+        require!(
+            !matches!(bet.status, BetStatus::CashedOut | BetStatus::Refunded),
+            BettingError::BetAlreadyProcessed
+        );
+        require!(
+            bet.placed_at <= lobby.bond_slashed_at,
+            BettingError::NotCompensable
+        );
+        let share = math::mul_div(bet.amount, lobby.slashed_bond, lobby.compensable_stake)?;
+        let paid = lobby
+            .compensation_paid
+            .checked_add(share)
+            .ok_or(BettingError::Overflow)?;
+        require!(paid <= lobby.slashed_bond, BettingError::NotCompensable);
+        lobby.compensation_paid = paid;
+
+        // The claim account marks the bet compensated
+        let claim = &mut ctx.accounts.claim;
+        claim.bet = bet.key();
+        claim.amount = share;
+
+        let recipient = payout_recipient(
+            bet,
+            ctx.accounts.bettor.to_account_info(),
+            ctx.accounts
+                .payout_recipient
+                .as_ref()
+                .map(|r| r.to_account_info()),
+        )?;
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                recipient.key,
+                share,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                recipient,
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(share)?;
+        Ok(())
+    }
+
+    /// Returns the bond to the owner once the lobby finished with no payout
+    /// root disputed or still open to challenge.
+    pub fn withdraw_bond(ctx: Context<WithdrawBond>) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        require!(lobby.status.is_over(), BettingError::LobbyNotFinished);
+        require!(!ctx.accounts.bond.slashed, BettingError::BondSlashed);
+        require!(
+            lobby.disputes_lost == 0 && !lobby.payout_root_challenged,
+            BettingError::BondNotWithdrawable
+        );
+        require!(
+            lobby.payout_root.is_none()
+                || Clock::get()?.unix_timestamp
+                    >= lobby.payout_root_posted_at + PAYOUT_CHALLENGE_WINDOW,
+            BettingError::ChallengeWindowOpen
+        );
+        Ok(())
+    }
+
+    /// Opens the player pool for one of a lobby's models. Bets placed through it
+    /// with `place_pooled_bet` leave the lobby read-only, so bets on different
+    /// models no longer contend for one account. Anyone can open a pool.
+    pub fn open_player_pool(ctx: Context<OpenPlayerPool>, model_index: u8) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            (model_index as usize) < lobby.model_names.len(),
+            BettingError::InvalidPlayerName
+        );
+        require!(!lobby.pools_locked, BettingError::BettingClosed);
+        require!(
+            lobby.weighting == PoolWeighting::Stake,
+            BettingError::WeightedPoolUnsupported
+        );
+
+        let pool = &mut ctx.accounts.pool;
+        pool.lobby = lobby.key();
+        pool.model_index = model_index;
+        pool.stake = 0;
+        pool.fees = 0;
+        pool.bets = 0;
+        pool.pending_bets = 0;
+        lobby.player_pools = lobby
+            .player_pools
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::PlayerPoolOpened)?;
+        Ok(())
+    }
+
+    /// Places a parimutuel back bet on the pool's model. Stake and fee are held
+    /// in the pool until it is aggregated, so the bet takes the final odds and
+    /// has no slippage bound.
+    pub fn place_pooled_bet(ctx: Context<PlacePooledBet>, amount: u64) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &accounts.lobby;
+        let bettor = accounts.bettor.key();
+        let price = check_open_for_bets(
+            lobby,
+            &accounts.config,
+            amount,
+            accounts.instructions.as_deref(),
+            accounts.price_update.as_deref(),
+        )?;
+        require!(
+            lobby.market_mode == MarketMode::Parimutuel,
+            BettingError::PooledBetRequiresParimutuel
+        );
+        check_attestation(
+            lobby,
+            &accounts.config,
+            accounts.attestation.as_ref(),
+            bettor,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let fee = bettor_fee(
+            &accounts.config,
+            &accounts.bettor_stats,
+            lobby.key(),
+            amount,
+            now,
+        )?;
+        let stake = amount.checked_sub(fee).ok_or(BettingError::Overflow)?;
+        require!(stake > 0, BettingError::BetAmountMustBePositive);
+
+        record_wager(
+            &accounts.config,
+            &mut accounts.bettor_stats,
+            &accounts.self_exclusion,
+            &accounts.denylist,
+            bettor,
+            amount,
+            now,
+            price.as_ref(),
+            ctx.program_id,
+        )?;
+        Funder::Signer(accounts.bettor.to_account_info()).pay(
+            &accounts.pool.to_account_info(),
+            &accounts.system_program,
+            amount,
+        )?;
+
+        let pool = &mut accounts.pool;
+        pool.stake = pool
+            .stake
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        pool.fees = pool.fees.checked_add(fee).ok_or(BettingError::Overflow)?;
+        pool.bets = pool.bets.checked_add(1).ok_or(BettingError::Overflow)?;
+        pool.pending_bets = pool
+            .pending_bets
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+
+        let bet = &mut accounts.bet;
+        bet.bettor = bettor;
+        bet.lobby = lobby.key();
+        bet.player_name = lobby.model_names[pool.model_index as usize].clone();
+        bet.amount = stake;
+        bet.requested_amount = amount;
+        bet.placed_at = now;
+        bet.status = BetStatus::Active;
+        bet.odds_bps = 0;
+        bet.side = BetSide::Back;
+        bet.payout_address = None;
+        bet.client_id = [0; 16];
+        bet.memo = None;
+        Ok(())
+    }
+
+    /// Folds every player pool into the lobby ahead of lock, e.g. so pooled bets
+    /// can be refunded from escrow. `remaining_accounts` is every open pool.
+    pub fn aggregate_player_pools<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AggregatePlayerPools<'info>>,
+    ) -> Result<()> {
+        fold_player_pools(
+            &mut ctx.accounts.lobby,
+            &mut ctx.accounts.config,
+            ctx.remaining_accounts,
+            &ctx.accounts.escrow,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+            ctx.program_id,
+        )
+    }
+
+    /// Marks a bet `Locked` once its lobby has stopped taking bets. Anyone can
+    /// call it.
+    pub fn lock_bet(ctx: Context<UpdateBetStatus>) -> Result<()> {
+        require!(
+            ctx.accounts
+                .lobby
+                .betting_closed(Clock::get()?.unix_timestamp),
+            BettingError::BettingStillOpen
+        );
+        transition_bet(&mut ctx.accounts.lobby, &mut ctx.accounts.bet, BetStatus::Locked)
+    }
+
+    /// Marks a bet `Won` or `Lost` once its lobby has finished, including a
+    /// disputed bet once the challenge is resolved. Anyone can call it.
+    pub fn record_bet_result(ctx: Context<UpdateBetStatus>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.status.is_over(), BettingError::LobbyNotFinished);
+        require!(
+            !lobby.payout_root_challenged,
+            BettingError::ChallengeWindowOpen
+        );
+        let bet = &mut ctx.accounts.bet;
+        require!(
+            !lobby.is_disqualified(&bet.player_name),
+            BettingError::BetVoided
+        );
+        let status = if bet.wins(lobby)? {
+            BetStatus::Won
+        } else {
+            BetStatus::Lost
+        };
+        transition_bet(lobby, bet, status)
+    }
+
+    /// Holds a bet's result while the lobby's payout root is challenged. Anyone
+    /// can call it.
+    pub fn dispute_bet(ctx: Context<UpdateBetStatus>) -> Result<()> {
+        require!(
+            ctx.accounts.lobby.payout_root_challenged,
+            BettingError::InvalidPayoutRoot
+        );
+        transition_bet(&mut ctx.accounts.lobby, &mut ctx.accounts.bet, BetStatus::Disputed)
+    }
+
+    /// Returns a parimutuel bet's stake to the bettor while betting is still
+    /// open. The protocol fee is not returned.
+    pub fn cash_out_bet(ctx: Context<CashOutBet>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &mut ctx.accounts.bet;
+        logic::withdraw_stake(lobby, bet, bet.amount, Clock::get()?.unix_timestamp)?;
+        transition_bet(lobby, bet, BetStatus::CashedOut)?;
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                ctx.accounts.bettor.key,
+                bet.amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.bettor.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(bet.amount)?;
+        emit_bet_settled(bet, bet.amount)?;
+        if let Some(stats) = &mut ctx.accounts.bettor_stats {
+            stats.record_usd_payout(bet, bet.amount)?;
+        }
+        touch_lobby(lobby, LobbyAction::BetCashedOut)?;
+        Ok(())
+    }
+
+    /// Marks bets on models that didn't win as `Lost` in one batch, so their
+    /// bettors can close them. `remaining_accounts` are the bets. Anyone can call
+    /// it once the lobby has finished.
+    pub fn settle_losing_bets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleLosingBets<'info>>,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.status.is_over(), BettingError::LobbyNotFinished);
+        require!(
+            !lobby.payout_root_challenged,
+            BettingError::ChallengeWindowOpen
+        );
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            BettingError::InvalidBetAccount
+        );
+        for info in ctx.remaining_accounts {
+            let mut bet = Account::<Bet>::try_from(info)?;
+            require!(bet.lobby == lobby.key(), BettingError::InvalidBetAccount);
+            require!(!bet.wins(lobby)?, BettingError::BetWon);
+            require!(
+                !lobby.is_disqualified(&bet.player_name),
+                BettingError::BetVoided
+            );
+            transition_bet(lobby, &mut bet, BetStatus::Lost)?;
+            emit_bet_settled(&bet, 0)?;
+            bet.exit(ctx.program_id)?;
+        }
+        let (lobby, due_at) = (lobby.key(), lobby.finished_at);
+        let accounts = &mut *ctx.accounts;
+        credit_keeper(
+            accounts.keeper.as_mut(),
+            accounts.keeper_authority.as_ref(),
+            accounts.config.as_deref(),
+            lobby,
+            due_at,
+            ctx.remaining_accounts.len() as u64,
+        )
+    }
+
+    /// Sweeps a finished parimutuel lobby's pool to the treasury when nobody
+    /// backed a paid position, since no bet can claim it; every bet on the
+    /// lobby then loses. Its chip-count pool is swept the same way when
+    /// nobody picked the winning bucket, and its freeroll prize when no free
+    /// pick was correct. Anyone can call it.
+    pub fn sweep_unwon_pool(ctx: Context<SweepUnwonPool>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(lobby.payout_root.is_none(), BettingError::MerkleSettlement);
+        require!(lobby.in_custody == 0, BettingError::FundsInCustody);
+        let pool = if lobby.market_mode == MarketMode::Parimutuel {
+            logic::unwon_pool(lobby, ctx.accounts.snapshot.as_deref())?
+        } else {
+            0
+        };
+        let chip_pool = logic::unwon_chip_pool(lobby)?;
+        let freeroll_prize = logic::unwon_freeroll_prize(lobby)?;
+        let amount = pool
+            .checked_add(chip_pool)
+            .and_then(|amount| amount.checked_add(freeroll_prize))
+            .ok_or(BettingError::Overflow)?;
+        require!(amount > 0, BettingError::NothingToClaim);
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                ctx.accounts.treasury.key,
+                amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(amount)?;
+        lobby.position_paid[0] = lobby.position_paid[0]
+            .checked_add(pool)
+            .ok_or(BettingError::Overflow)?;
+        lobby.bucket_paid = lobby
+            .bucket_paid
+            .checked_add(chip_pool)
+            .ok_or(BettingError::Overflow)?;
+        lobby.freeroll_paid = lobby
+            .freeroll_paid
+            .checked_add(freeroll_prize)
+            .ok_or(BettingError::Overflow)?;
+
+        let seq = touch_lobby(lobby, LobbyAction::EscrowSwept)?;
+        emit!(EscrowSwept {
+            lobby: lobby_key,
+            seq,
+            amount,
+        });
+        let due_at = lobby.finished_at;
+        let accounts = &mut *ctx.accounts;
+        credit_keeper(
+            accounts.keeper.as_mut(),
+            accounts.keeper_authority.as_ref(),
+            accounts.config.as_deref(),
+            lobby_key,
+            due_at,
+            1,
+        )
+    }
+
+    /// Closes a settled bet and returns its rent to the bettor. Anyone can
+    /// call it. A raffle entry stays open until the draw shows it didn't win,
+    /// or the prize is paid.
+    pub fn close_bet(ctx: Context<CloseBet>) -> Result<()> {
+        logic::check_bet_closable(&ctx.accounts.lobby, &ctx.accounts.bet)
+    }
+
+    /// Sets the key that must co-sign `finish_game`. The first one applies at
+    /// once; replacing it only takes effect `RESULTS_AUTHORITY_DELAY` later,
+    /// through `apply_results_authority`.
+    pub fn set_results_authority(
+        ctx: Context<UpdateLobbyStatus>,
+        results_authority: Pubkey,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        if lobby.results_authority.is_none() {
+            lobby.results_authority = Some(results_authority);
+        } else {
+            lobby.pending_results_authority = Some(results_authority);
+            lobby.results_authority_change_at =
+                Clock::get()?.unix_timestamp + RESULTS_AUTHORITY_DELAY;
+        }
+        touch_lobby(lobby, LobbyAction::ResultsAuthoritySet)?;
+        Ok(())
+    }
+
+    /// Hands the lobby to the rotated results authority once its delay is over.
+    pub fn apply_results_authority(ctx: Context<UpdateLobbyStatus>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        let pending = lobby
+            .pending_results_authority
+            .ok_or(BettingError::NoPendingResultsAuthority)?;
+        require!(
+            Clock::get()?.unix_timestamp >= lobby.results_authority_change_at,
+            BettingError::ResultsAuthorityDelayActive
+        );
+        lobby.results_authority = Some(pending);
+        lobby.pending_results_authority = None;
+        lobby.results_authority_change_at = 0;
+        touch_lobby(lobby, LobbyAction::ResultsAuthoritySet)?;
+        Ok(())
+    }
+
+    /// Parimutuel or quoted odds, in basis points, a bet of `amount` on `side`
+    /// of `player_name` would get after fees, returned as return data.
+    pub fn get_implied_odds(
+        ctx: Context<GetImpliedOdds>,
+        player_name: String,
+        side: BetSide,
+        amount: u64,
+    ) -> Result<u64> {
+        let lobby = &ctx.accounts.lobby;
+        let model_index = lobby.model_index(&player_name)?;
+        match lobby.market_mode {
+            MarketMode::Parimutuel => {
+                let stake = math::stake_after_fee(amount, ctx.accounts.config.fee_bps as u64)?;
+                lobby.implied_odds_after(model_index, side, stake)
+            }
+            MarketMode::FixedOdds => {
+                require!(side == BetSide::Back, BettingError::LayRequiresParimutuel);
+                Ok(lobby.quoted_odds_bps[model_index] as u64)
+            }
+        }
+    }
+
+    /// Lamports `bettor`'s back and lay bets on the lobby would pay if claimed
+    /// now, returned as return data.
+    pub fn get_claimable_amount(ctx: Context<GetClaimableAmount>, _bettor: Pubkey) -> Result<u64> {
+        let accounts = &ctx.accounts;
+        let mut claimable = 0u64;
+        for bet in [&accounts.back_bet, &accounts.lay_bet] {
+            if bet.owner != ctx.program_id || bet.data_is_empty() {
+                continue;
+            }
+            let bet = Bet::try_deserialize(&mut &bet.try_borrow_data()?[..])?;
+            let amount = logic::claimable_amount(
+                &accounts.lobby,
+                &bet,
+                accounts.snapshot.as_deref(),
+                &accounts.config,
+            )?;
+            claimable = claimable
+                .checked_add(amount)
+                .ok_or(BettingError::Overflow)?;
+        }
+        Ok(claimable)
+    }
+
+    /// Pools, current odds and state of a lobby, returned as return data.
+    pub fn get_lobby_summary(ctx: Context<ViewLobby>) -> Result<LobbySummary> {
+        let lobby = &ctx.accounts.lobby;
+        let odds_bps = (0..lobby.model_names.len())
+            .map(|index| lobby.current_odds_bps(index))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(LobbySummary {
+            status: lobby.status.clone(),
+            market_mode: lobby.market_mode.clone(),
+            model_names: lobby.model_names.clone(),
+            model_pools: lobby.model_pools.clone(),
+            lay_pools: lobby.lay_pools.clone(),
+            odds_bps,
+            total_bets: lobby.total_bets,
+            sponsor_pool: lobby.sponsor_pool,
+            betting_closed: lobby.betting_closed(Clock::get()?.unix_timestamp),
+            winner: lobby.winner.clone(),
+            seq: lobby.seq,
+            charity: lobby.charity,
+            charity_bps: lobby.charity_bps,
+            freeroll_pool: lobby.freeroll_pool,
+            freeroll_picks: lobby.freeroll_picks.clone(),
+            weighting: lobby.weighting.clone(),
+        })
+    }
+
+    /// Records the bettor's wrapped SOL balance ahead of a Jupiter swap into
+    /// it, for `place_bet_with_swap` later in the same transaction.
+    pub fn begin_bet_swap(ctx: Context<BeginBetSwap>) -> Result<()> {
+        let receipt = &mut ctx.accounts.swap_receipt;
+        receipt.bettor = ctx.accounts.bettor.key();
+        receipt.lobby = ctx.accounts.lobby.key();
+        receipt.token_account = ctx.accounts.wsol_account.key();
+        receipt.balance_before = ctx.accounts.wsol_account.amount;
+        receipt.slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    /// Bets the wrapped SOL a swap added since `begin_bet_swap`, and nothing
+    /// else. The wrapped SOL account is closed back to the bettor to unwrap
+    /// the output, which is then staked like any other bet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_bet_with_swap(
+        ctx: Context<PlaceBetWithSwap>,
+        player_name: String,
+        min_output: u64,
+        min_expected_odds_bps: u32,
+        side: BetSide,
+        client_id: [u8; 16],
+        memo: Option<String>,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let receipt = &accounts.swap_receipt;
+        require!(
+            receipt.lobby == accounts.place.lobby.key()
+                && receipt.token_account == accounts.wsol_account.key(),
+            BettingError::InvalidSwapReceipt
+        );
+        require!(
+            receipt.slot == Clock::get()?.slot,
+            BettingError::SwapReceiptExpired
+        );
+        let output = accounts
+            .wsol_account
+            .amount
+            .checked_sub(receipt.balance_before)
+            .ok_or(BettingError::InvalidSwapReceipt)?;
+        require!(
+            output > 0 && output >= min_output,
+            BettingError::SwapOutputTooLow
+        );
+
+        let bettor = accounts.place.bettor.to_account_info();
+        token::close_account(CpiContext::new(
+            accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: accounts.wsol_account.to_account_info(),
+                destination: bettor.clone(),
+                authority: bettor.clone(),
+            },
+        ))?;
+        accounts.swap_receipt.close(bettor)?;
+
+        place_bet_with(
+            accounts.place.bet_accounts(),
+            ctx.program_id,
+            player_name,
+            output,
+            min_expected_odds_bps,
+            side,
+            client_id,
+            memo,
+        )
+    }
+
+    /// Opens a streak series. `prizes[i]` is paid once a wallet's run of
+    /// correct picks reaches `i + 1` lobbies; prizes may only grow with the
+    /// streak. Sponsors fund it with `fund_streak_series`.
+    pub fn create_streak_series(
+        ctx: Context<CreateStreakSeries>,
+        series_id: u64,
+        prizes: Vec<u64>,
+    ) -> Result<()> {
+        require!(
+            !prizes.is_empty()
+                && prizes.len() <= MAX_STREAK_PRIZES
+                && prizes[0] > 0
+                && prizes.windows(2).all(|pair| pair[0] <= pair[1]),
+            BettingError::InvalidStreakPrizes
+        );
+        let series = &mut ctx.accounts.series;
+        series.authority = ctx.accounts.authority.key();
+        series.series_id = series_id;
+        series.lobbies = Vec::new();
+        series.prizes = prizes;
+        Ok(())
+    }
+
+    /// Appends a lobby to the series. Lobbies count in the order they are
+    /// added, and must be added before they finish.
+    pub fn add_streak_lobby(ctx: Context<AddStreakLobby>) -> Result<()> {
+        let series = &mut ctx.accounts.series;
+        let lobby = &ctx.accounts.lobby;
+        require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+        require!(
+            series.lobbies.len() < MAX_STREAK_LOBBIES && !series.lobbies.contains(&lobby.key()),
+            BettingError::InvalidStreakLobby
+        );
+        series.lobbies.push(lobby.key());
+        Ok(())
+    }
+
+    pub fn fund_streak_series(ctx: Context<FundStreakSeries>, amount: u64) -> Result<()> {
+        require!(amount > 0, BettingError::BetAmountMustBePositive);
+        Funder::Signer(ctx.accounts.sponsor.to_account_info()).pay(
+            &ctx.accounts.series.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )
+    }
+
+    /// Scores the bettor's back bet on a finished series lobby: a winning bet
+    /// extends their streak, a losing one resets it. Lobbies are scored in
+    /// series order, and skipping one also resets the streak.
+    pub fn record_streak_pick(ctx: Context<RecordStreakPick>) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        let bet = &ctx.accounts.bet;
+        require!(lobby.status.is_over(), BettingError::LobbyNotFinished);
+        let index = ctx
+            .accounts
+            .series
+            .lobbies
+            .iter()
+            .position(|key| *key == lobby.key())
+            .ok_or(BettingError::InvalidStreakLobby)? as u32;
+
+        // A cashed-out or refunded bet is no pick at all
+        let won = bet.status.is_outstanding()
+            || matches!(bet.status, BetStatus::Paid | BetStatus::BelowMinimum);
+        let won = won && bet.wins(lobby)?;
+
+        let streak = &mut ctx.accounts.streak;
+        streak.bettor = ctx.accounts.bettor.key();
+        streak.series = ctx.accounts.series.key();
+        require!(index >= streak.next_index, BettingError::InvalidStreakLobby);
+        if index > streak.next_index || !won {
+            streak.current = 0;
+            streak.claimed = 0;
+        }
+        if won {
+            streak.current += 1;
+            streak.best = streak.best.max(streak.current);
+        }
+        streak.next_index = index + 1;
+        Ok(())
+    }
+
+    /// Pays the prizes for every streak length reached since the last claim
+    /// in the bettor's current run.
+    pub fn claim_streak_prize(ctx: Context<ClaimStreakPrize>) -> Result<()> {
+        let series = &ctx.accounts.series;
+        let streak = &mut ctx.accounts.streak;
+        let reached = (streak.current as usize).min(series.prizes.len());
+        let amount = series
+            .prizes
+            .get(streak.claimed as usize..reached)
+            .unwrap_or_default()
+            .iter()
+            .try_fold(0u64, |sum, prize| sum.checked_add(*prize))
+            .ok_or(BettingError::Overflow)?;
+        require!(amount > 0, BettingError::NothingToClaim);
+        streak.claimed = reached as u32;
+        Funder::Program(series.to_account_info()).pay(
+            &ctx.accounts.bettor.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )
+    }
+
+    /// Enters a losing bet in the lobby's consolation raffle, one ticket per
+    /// lamport staked. Open for `RAFFLE_ENTRY_WINDOW` after the lobby finishes.
+    pub fn enter_raffle(ctx: Context<EnterRaffle>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &mut ctx.accounts.bet;
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(
+            lobby.raffle_pool > 0
+                && lobby.raffle_seed.is_none()
+                && Clock::get()?.unix_timestamp < lobby.raffle_entries_close_at,
+            BettingError::RaffleClosed
+        );
+        require!(
+            bet.raffle_ticket.is_none()
+                && matches!(
+                    bet.status,
+                    BetStatus::Active | BetStatus::Locked | BetStatus::Lost
+                )
+                && !bet.wins(lobby)?
+                && !lobby.is_disqualified(&bet.player_name),
+            BettingError::NotARaffleEntry
+        );
+        bet.raffle_ticket = Some(lobby.raffle_tickets);
+        lobby.raffle_tickets = lobby
+            .raffle_tickets
+            .checked_add(bet.amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::RaffleEntered)?;
+        Ok(())
+    }
+
+    /// Closes raffle entries and fixes the seed to request ORAO VRF randomness
+    /// for; see `vrf::randomness_address`. A raffle nobody entered releases
+    /// its prize back to the treasury. Anyone can call it once entries close.
+    pub fn request_raffle(ctx: Context<RequestRaffle>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.status == LobbyStatus::Finished
+                && Clock::get()?.unix_timestamp >= lobby.raffle_entries_close_at,
+            BettingError::RaffleEntriesOpen
+        );
+        require!(
+            lobby.raffle_pool > 0 && lobby.raffle_seed.is_none(),
+            BettingError::RaffleClosed
+        );
+        if lobby.raffle_tickets == 0 {
+            let config = &mut ctx.accounts.config;
+            config.reserved_rake = config.reserved_rake.saturating_sub(lobby.raffle_pool);
+            lobby.raffle_pool = 0;
+        } else {
+            // Only known once entries close, so no entry can be placed
+            // knowing the randomness
+            lobby.raffle_seed = Some(
+                anchor_lang::solana_program::hash::hashv(&[
+                    lobby.key().as_ref(),
+                    b"raffle",
+                    &Clock::get()?.slot.to_le_bytes(),
+                ])
+                .to_bytes(),
+            );
+        }
+        touch_lobby(lobby, LobbyAction::RaffleRequested)?;
+        Ok(())
+    }
+
+    /// Draws the winning ticket from the fulfilled VRF randomness, so each
+    /// entry wins with probability proportional to its stake.
+    pub fn run_raffle(ctx: Context<RunRaffle>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let seed = lobby.raffle_seed.ok_or(BettingError::RaffleClosed)?;
+        require!(lobby.raffle_ticket.is_none(), BettingError::RaffleClosed);
+        let randomness = vrf::fulfilled_randomness(&ctx.accounts.randomness, &seed)?;
+        let random = u128::from_le_bytes(randomness[..16].try_into().unwrap());
+        lobby.raffle_ticket = Some((random % lobby.raffle_tickets as u128) as u64);
+        touch_lobby(lobby, LobbyAction::RaffleDrawn)?;
+        Ok(())
+    }
+
+    /// Pays the raffle prize from the treasury to the entry holding the drawn
+    /// ticket.
+    pub fn claim_raffle_prize(ctx: Context<ClaimRafflePrize>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &ctx.accounts.bet;
+        let drawn = lobby.raffle_ticket.ok_or(BettingError::RaffleClosed)?;
+        require!(lobby.raffle_pool > 0, BettingError::NothingToClaim);
+        let start = bet.raffle_ticket.ok_or(BettingError::NotARaffleEntry)?;
+        require!(
+            (start..start + bet.amount).contains(&drawn),
+            BettingError::NotARaffleEntry
+        );
+
+        let prize = lobby.raffle_pool;
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.treasury.key,
+                ctx.accounts.bettor.key,
+                prize,
+            ),
+            &[
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.bettor.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[TREASURY_SEED, &[ctx.bumps.treasury]]],
+        )?;
+        let config = &mut ctx.accounts.config;
+        config.reserved_rake = config.reserved_rake.saturating_sub(prize);
+        lobby.raffle_pool = 0;
+        touch_lobby(lobby, LobbyAction::RafflePrizePaid)?;
+        Ok(())
+    }
+
+    /// Moves a parimutuel bet's stake to another model while betting is still
+    /// open, instead of cashing out and betting again. The change fee, if
+    /// any, is paid on top of the stake.
+    pub fn change_bet_selection(
+        ctx: Context<ChangeBetSelection>,
+        new_player: String,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &mut ctx.accounts.bet;
+        let fee = logic::change_selection(
+            lobby,
+            bet,
+            new_player,
+            &ctx.accounts.config,
+            Clock::get()?.unix_timestamp,
+        )?;
+        collect_fee(
+            &Funder::Signer(ctx.accounts.bettor.to_account_info()),
+            lobby,
+            &mut ctx.accounts.config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            fee,
+        )?;
+        touch_lobby(lobby, LobbyAction::BetSelectionChanged)?;
+        Ok(())
+    }
+
+    /// Returns `amount` of a parimutuel bet's stake to the bettor while
+    /// betting is still open, keeping the rest of the bet live. The protocol
+    /// fee on it is not returned.
+    pub fn reduce_bet(ctx: Context<CashOutBet>, amount: u64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &mut ctx.accounts.bet;
+        let remaining = logic::check_reduction(bet, amount)?;
+        logic::withdraw_stake(lobby, bet, amount, Clock::get()?.unix_timestamp)?;
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                ctx.accounts.bettor.key,
+                amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.bettor.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(amount)?;
+        if let Some(stats) = &mut ctx.accounts.bettor_stats {
+            stats.record_usd_payout(bet, amount)?;
+        }
+        bet.usd_value = bet.usd_value.saturating_sub(bet.usd(amount)?);
+        bet.amount = remaining;
+        touch_lobby(lobby, LobbyAction::BetReduced)?;
+        Ok(())
+    }
+
+    /// Voids every bet on a model disqualified mid-tournament, e.g. after an
+    /// API outage or a rule breach, and takes its stake out of the pools so
+    /// the rest of the lobby plays on. Voided bets are refunded through
+    /// `refund_voided_bet`. Once pools are locked their snapshot is adjusted
+    /// too.
+    pub fn disqualify_model(ctx: Context<DisqualifyModel>, player_name: String) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        logic::disqualify_model(lobby, ctx.accounts.snapshot.as_deref_mut(), &player_name)?;
+        touch_lobby(lobby, LobbyAction::ModelDisqualified)?;
+        Ok(())
+    }
+
+    /// Returns a voided bet's stake to its bettor, or its payout address. The
+    /// protocol fee is not returned. Anyone can call it.
+    pub fn refund_voided_bet(ctx: Context<RefundVoidedBet>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let bet = &mut ctx.accounts.bet;
+        require!(
+            lobby.is_disqualified(&bet.player_name),
+            BettingError::BetNotVoided
+        );
+        transition_bet(lobby, bet, BetStatus::Refunded)?;
+        let recipient = payout_recipient(
+            bet,
+            ctx.accounts.bettor.to_account_info(),
+            ctx.accounts
+                .payout_recipient
+                .as_ref()
+                .map(|r| r.to_account_info()),
+        )?;
+
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[ESCROW_SEED, lobby_key.as_ref(), &[ctx.bumps.escrow]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.escrow.key,
+                recipient.key,
+                bet.amount,
+            ),
+            &[
+                ctx.accounts.escrow.to_account_info(),
+                recipient,
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.track_withdrawal(bet.amount)?;
+        emit_bet_settled(bet, bet.amount)?;
+        touch_lobby(lobby, LobbyAction::BetRefunded)?;
+        Ok(())
+    }
+
+    /// Rolls a parent lobby's unresolved chip-count market into its
+    /// continuation: the stake moves to the continuation's escrow and its
+    /// bets settle there, on the continuation's final chip counts.
+    pub fn roll_chip_market_forward(ctx: Context<RollChipMarketForward>) -> Result<()> {
+        let parent = &mut ctx.accounts.parent_lobby;
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            parent.owner == ctx.accounts.owner.key(),
+            BettingError::Unauthorized
+        );
+        require!(
+            !parent.status.is_over() && !lobby.status.is_over(),
+            BettingError::LobbyAlreadyFinished
+        );
+        require!(
+            !parent.bucket_pools.is_empty() && parent.chip_buckets == lobby.chip_buckets,
+            BettingError::InvalidChipBucket
+        );
+        require!(parent.in_custody == 0, BettingError::FundsInCustody);
+
+        let amount = parent
+            .bucket_pools
+            .iter()
+            .try_fold(0u64, |sum, pool| sum.checked_add(*pool))
+            .ok_or(BettingError::Overflow)?;
+        if amount > 0 {
+            let parent_key = parent.key();
+            let seeds: &[&[u8]] = &[ESCROW_SEED, parent_key.as_ref(), &[ctx.bumps.parent_escrow]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    ctx.accounts.parent_escrow.key,
+                    ctx.accounts.escrow.key,
+                    amount,
+                ),
+                &[
+                    ctx.accounts.parent_escrow.to_account_info(),
+                    ctx.accounts.escrow.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[seeds],
+            )?;
+            parent.track_withdrawal(amount)?;
+            lobby.track_deposit(amount)?;
+        }
+        for (pool, rolled) in lobby.bucket_pools.iter_mut().zip(&parent.bucket_pools) {
+            *pool = pool.checked_add(*rolled).ok_or(BettingError::Overflow)?;
+        }
+        parent.chip_buckets = Vec::new();
+        parent.bucket_pools = Vec::new();
+        lobby.chip_market_rolled = true;
+        touch_lobby(parent, LobbyAction::ChipMarketRolled)?;
+        touch_lobby(lobby, LobbyAction::ChipMarketRolled)?;
+        Ok(())
+    }
+
+    /// Freezes the lobby's game terms into its `GameConfig` once the game has
+    /// started. The account is never written again, so the terms the game
+    /// was played under can be read apart from the lobby's betting state.
+    /// Anyone can call it, paying the rent.
+    pub fn freeze_game_config(ctx: Context<FreezeGameConfig>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.status != LobbyStatus::Waiting,
+            BettingError::LobbyNotStarted
+        );
+        let game_config = &mut ctx.accounts.game_config;
+        game_config.lobby = lobby.key();
+        game_config.model_names = lobby.model_names.clone();
+        game_config.starting_chips = lobby.starting_chips;
+        game_config.blinds = lobby.blinds.clone();
+        game_config.max_hands = lobby.max_hands;
+        game_config.frozen_at = Clock::get()?.unix_timestamp;
+        lobby.game_config = Some(game_config.key());
+        touch_lobby(lobby, LobbyAction::GameConfigFrozen)?;
+        Ok(())
+    }
+
+    /// Sets aside `reimbursement_bps` of the lobby's rake to reimburse its
+    /// model operators' API costs, split by hands played once the game
+    /// finishes. Must be set before any bet is placed.
+    pub fn set_operator_reimbursement(
+        ctx: Context<UpdateLobbyStatus>,
+        reimbursement_bps: u16,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            lobby.status == LobbyStatus::Waiting && lobby.total_bets == 0,
+            BettingError::LobbyAlreadyStarted
+        );
+        require!(
+            reimbursement_bps as u64 <= math::BPS_DENOMINATOR,
+            BettingError::InvalidReimbursementShare
+        );
+        lobby.reimbursement_bps = reimbursement_bps;
+        touch_lobby(lobby, LobbyAction::OperatorReimbursementSet)?;
+        Ok(())
+    }
+
+    /// Credits each model that recorded a reasoning trace for the engine's
+    /// next uncounted hand with one hand played. Permissionless; hands are
+    /// counted in order once the engine game is finalized.
+    pub fn count_reimbursed_hand(ctx: Context<CountReimbursedHand>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let hand = &ctx.accounts.hand;
+        require!(
+            lobby.reimbursement_bps > 0,
+            BettingError::NoOperatorReimbursement
+        );
+        require!(
+            ctx.accounts.engine_game.finalized,
+            BettingError::GameNotFinalized
+        );
+        require!(
+            hand.hand_number == lobby.hands_counted,
+            BettingError::HandOutOfOrder
+        );
+        let players = &ctx.accounts.engine_game.player_names;
+        for (name, trace) in players.iter().zip(&hand.trace_hashes) {
+            if *trace == [0; 32] {
+                continue;
+            }
+            if let Some(index) = lobby.model_names.iter().position(|model| model == name) {
+                lobby.reimbursed_hands[index] = lobby.reimbursed_hands[index]
+                    .checked_add(1)
+                    .ok_or(BettingError::Overflow)?;
+            }
+        }
+        lobby.hands_counted = lobby
+            .hands_counted
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::ReimbursedHandCounted)?;
+        Ok(())
+    }
+
+    /// Pays a model's operator its share of the reimbursement pool, by hands
+    /// played, once the game is finalized and every hand counted.
+    pub fn claim_operator_reimbursement(
+        ctx: Context<ClaimOperatorReimbursement>,
+        model_name: String,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        let engine_game = &ctx.accounts.engine_game;
+        require!(
+            lobby.status == LobbyStatus::Finished,
+            BettingError::LobbyNotFinished
+        );
+        require!(engine_game.finalized, BettingError::GameNotFinalized);
+        require!(
+            lobby.hands_counted == engine_game.hands_played,
+            BettingError::HandsNotCounted
+        );
+        require!(
+            Clock::get()?.unix_timestamp < lobby.reimbursement_closes_at,
+            BettingError::ReimbursementClosed
+        );
+        let index = lobby.model_index(&model_name)?;
+        require!(
+            !lobby.reimbursement_claimed[index],
+            BettingError::NothingToClaim
+        );
+        let total_hands = lobby
+            .reimbursed_hands
+            .iter()
+            .try_fold(0u64, |sum, hands| sum.checked_add(*hands))
+            .ok_or(BettingError::Overflow)?;
+        let amount = math::mul_div(
+            lobby.operator_reimbursement,
+            lobby.reimbursed_hands[index],
+            total_hands,
+        )?;
+        require!(amount > 0, BettingError::NothingToClaim);
+
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.treasury.key,
+                ctx.accounts.operator.key,
+                amount,
+            ),
+            &[
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.operator.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[TREASURY_SEED, &[ctx.bumps.treasury]]],
+        )?;
+        let config = &mut ctx.accounts.config;
+        config.reserved_rake = config.reserved_rake.saturating_sub(amount);
+        lobby.reimbursement_claimed[index] = true;
+        lobby.reimbursement_paid = lobby
+            .reimbursement_paid
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::OperatorReimbursed)?;
+        Ok(())
+    }
+
+    /// Returns the reimbursement nobody claimed, rounding dust included, to
+    /// the treasury once the claim window has closed. Permissionless.
+    pub fn release_operator_reimbursement(
+        ctx: Context<ReleaseOperatorReimbursement>,
+    ) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(
+            lobby.status == LobbyStatus::Finished
+                && Clock::get()?.unix_timestamp >= lobby.reimbursement_closes_at,
+            BettingError::ReimbursementOpen
+        );
+        let unclaimed = lobby
+            .operator_reimbursement
+            .checked_sub(lobby.reimbursement_paid)
+            .ok_or(BettingError::Overflow)?;
+        require!(unclaimed > 0, BettingError::NothingToClaim);
+        let config = &mut ctx.accounts.config;
+        config.reserved_rake = config.reserved_rake.saturating_sub(unclaimed);
+        lobby.operator_reimbursement = lobby.reimbursement_paid;
+        touch_lobby(lobby, LobbyAction::OperatorReimbursementReleased)?;
+        let (lobby, due_at) = (lobby.key(), lobby.reimbursement_closes_at);
+        let accounts = &mut *ctx.accounts;
+        credit_keeper(
+            accounts.keeper.as_mut(),
+            accounts.keeper_authority.as_ref(),
+            Some(&accounts.config),
+            lobby,
+            due_at,
+            1,
+        )
+    }
+
+    /// Opens a market on the winner of hand `hand_number` of the lobby's
+    /// engine game. The hand must not have been dealt yet: the engine must
+    /// still be recording an earlier one.
+    pub fn open_hand_market(ctx: Context<OpenHandMarket>, hand_number: u64) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        logic::check_hand_market_opens(lobby, ctx.accounts.engine_game.hands_played, hand_number)?;
+
+        let market = &mut ctx.accounts.hand_market;
+        market.lobby = lobby.key();
+        market.hand_number = hand_number;
+        market.model_pools = vec![0; lobby.model_names.len()];
+        market.total = 0;
+        market.settled = false;
+        market.winner_index = None;
+        market.stake_paid = 0;
+        market.paid = 0;
+        lobby.open_hand_markets.push(hand_number);
+        touch_lobby(lobby, LobbyAction::HandMarketOpened)?;
+        Ok(())
+    }
+
+    /// Bets on the winner of a hand. Open until the engine records the hand
+    /// before it, so betting closes before the hand is dealt, even after the
+    /// match market has locked.
+    pub fn place_hand_bet(
+        ctx: Context<PlaceHandBet>,
+        player_name: String,
+        amount: u64,
+    ) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        let market = &mut accounts.hand_market;
+        let bettor = accounts.bettor.key();
+        logic::check_hand_bets_open(lobby, market, accounts.engine_game.hands_played)?;
+        check_bet_terms(
+            &accounts.config,
+            amount,
+            accounts.instructions.as_deref(),
+            accounts.price_update.as_deref(),
+        )?;
+        let model_index = lobby.model_index(&player_name)?;
+        require!(
+            !lobby.disqualified[model_index],
+            BettingError::ModelDisqualified
+        );
+        check_attestation(lobby, &accounts.config, accounts.attestation.as_ref(), bettor)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let fee = bettor_fee(
+            &accounts.config,
+            &accounts.bettor_stats,
+            lobby.key(),
+            amount,
+            now,
+        )?;
+        let stake = amount.checked_sub(fee).ok_or(BettingError::Overflow)?;
+        require!(stake > 0, BettingError::BetAmountMustBePositive);
+
+        record_wager(
+            &accounts.config,
+            &mut accounts.bettor_stats,
+            &accounts.self_exclusion,
+            &accounts.denylist,
+            bettor,
+            amount,
+            now,
+            None,
+            ctx.program_id,
+        )?;
+        let funder = Funder::Signer(accounts.bettor.to_account_info());
+        let system_program = accounts.system_program.to_account_info();
+        collect_fee(
+            &funder,
+            lobby,
+            &mut accounts.config,
+            &accounts.treasury,
+            &system_program,
+            fee,
+        )?;
+        funder.pay(&accounts.hand_escrow, &system_program, stake)?;
+        lobby.hand_escrow_balance = lobby
+            .hand_escrow_balance
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+
+        let hand_bet = &mut accounts.hand_bet;
+        hand_bet.bettor = bettor;
+        hand_bet.market = market.key();
+        hand_bet.model_index = model_index as u8;
+        hand_bet.amount = stake;
+        hand_bet.placed_at = now;
+        hand_bet.status = BetStatus::Active;
+
+        market.model_pools[model_index] = market.model_pools[model_index]
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        market.total = market
+            .total
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        touch_lobby(lobby, LobbyAction::BetPlaced)?;
+        Ok(())
+    }
+
+    /// Settles the hand markets of hands `first_hand..=last_hand` from the
+    /// engine's hand records, while the match market stays open. The range
+    /// picks up where the last checkpoint ended and must include every open
+    /// market in it. `remaining_accounts` holds `[hand_market, hand]` per
+    /// market. Only the hand escrow backs these markets; the match escrow is
+    /// not touched.
+    pub fn checkpoint_settle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CheckpointSettle<'info>>,
+        first_hand: u64,
+        last_hand: u64,
+    ) -> Result<()> {
+        let engine_game = ctx.accounts.engine_game.key();
+        let hands_played = ctx.accounts.engine_game.hands_played;
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
+        require!(
+            first_hand == lobby.hands_checkpointed
+                && last_hand >= first_hand
+                && last_hand < hands_played,
+            BettingError::InvalidHandRange
+        );
+        let groups = ctx.remaining_accounts.chunks_exact(CHECKPOINT_GROUP_LEN);
+        require!(
+            groups.remainder().is_empty(),
+            BettingError::InvalidHandMarket
+        );
+
+        for group in groups {
+            let [market_info, hand_info] = group else {
+                return err!(BettingError::InvalidHandMarket);
+            };
+            let mut market = Account::<HandMarket>::try_from(market_info)?;
+            let hand = Account::<poker_engine::HandRecord>::try_from(hand_info)?;
+            require!(
+                market.lobby == lobby.key()
+                    && !market.settled
+                    && (first_hand..=last_hand).contains(&market.hand_number)
+                    && hand.game == engine_game
+                    && hand.hand_number == market.hand_number,
+                BettingError::InvalidHandMarket
+            );
+            logic::settle_hand_market(lobby, &mut market, Some(&hand.winner_name));
+            market.exit(ctx.program_id)?;
+        }
+        require!(
+            lobby.open_hand_markets.iter().all(|open| *open > last_hand),
+            BettingError::HandMarketsUnsettled
+        );
+        require!(
+            ctx.accounts.hand_escrow.lamports() >= lobby.hand_escrow_balance,
+            BettingError::EscrowUnderflow
+        );
+        lobby.hands_checkpointed = last_hand + 1;
+        touch_lobby(lobby, LobbyAction::HandsCheckpointed)?;
+        Ok(())
+    }
+
+    /// Voids the hand markets of hands the lobby's engine game ended before
+    /// dealing, so their bets are refunded through `claim_hand_bet`.
+    /// Permissionless once the engine game is finalized. `remaining_accounts`
+    /// holds the markets.
+    pub fn void_unplayed_hand_markets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VoidUnplayedHandMarkets<'info>>,
+    ) -> Result<()> {
+        let hands_played = ctx.accounts.engine_game.hands_played;
+        require!(
+            ctx.accounts.engine_game.finalized,
+            BettingError::GameNotFinalized
+        );
+        let lobby = &mut ctx.accounts.lobby;
+        for market_info in ctx.remaining_accounts {
+            let mut market = Account::<HandMarket>::try_from(market_info)?;
+            require!(
+                market.lobby == lobby.key()
+                    && !market.settled
+                    && market.hand_number >= hands_played,
+                BettingError::InvalidHandMarket
+            );
+            logic::settle_hand_market(lobby, &mut market, None);
+            market.exit(ctx.program_id)?;
+        }
+        touch_lobby(lobby, LobbyAction::HandMarketsVoided)?;
+        Ok(())
+    }
+
+    /// Pays a winning hand bet, or refunds the stake of a bet on a void hand,
+    /// from the hand escrow. The claim that settles the last of the winning
+    /// stake takes the rounding remainder, as in the match market.
+    pub fn claim_hand_bet(ctx: Context<ClaimHandBet>) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        let lobby = &mut accounts.lobby;
+        let market = &mut accounts.hand_market;
+        let hand_bet = &mut accounts.hand_bet;
+        let (amount, status) = logic::claim_hand_bet(market, hand_bet)?;
+        let lobby_key = lobby.key();
+        let seeds: &[&[u8]] = &[
+            HAND_ESCROW_SEED,
+            lobby_key.as_ref(),
+            &[ctx.bumps.hand_escrow],
+        ];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                accounts.hand_escrow.key,
+                accounts.bettor.key,
+                amount,
+            ),
+            &[
+                accounts.hand_escrow.to_account_info(),
+                accounts.bettor.to_account_info(),
+                accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        lobby.hand_escrow_balance = lobby
+            .hand_escrow_balance
+            .checked_sub(amount)
+            .ok_or(BettingError::EscrowUnderflow)?;
+        hand_bet.status = status;
+        touch_lobby(lobby, LobbyAction::WinningsPaid)?;
+        Ok(())
+    }
+
+    /// Archives a fully settled lobby whose bets have all been paid, refunded
+    /// or marked lost: strips it to an audit stub and shrinks the account to
+    /// fit, returning the freed rent to the owner. Bets on it can still be
+    /// closed.
+    pub fn archive_lobby(ctx: Context<ArchiveLobby>) -> Result<()> {
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.fully_settled(), BettingError::LobbyNotSettled);
+        require!(lobby.outstanding_bets == 0, BettingError::BetsOutstanding);
+        transition_lobby(lobby, LobbyStatus::Archived)?;
+        release_rake(lobby, &mut ctx.accounts.config);
+        lobby.strip_for_archive();
+        touch_lobby(lobby, LobbyAction::Archived)?;
+
+        let info = lobby.to_account_info();
+        let space = 8 + lobby.try_to_vec()?.len();
+        let freed = info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(space));
+        info.realloc(space, false)?;
+        **info.try_borrow_mut_lamports()? -= freed;
+        let owner = ctx.accounts.owner.to_account_info();
+        **owner.try_borrow_mut_lamports()? = owner
+            .lamports()
+            .checked_add(freed)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+
+    /// Closes a hand record of the lobby's game through the engine, which
+    /// only accepts the lobby's signature. The lobby signs once it has fully
+    /// settled, as nothing reads its game's hand records after that.
+    pub fn close_hand_record(ctx: Context<CloseHandRecord>, hand_number: u64) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        require!(
+            lobby.fully_settled() || lobby.status == LobbyStatus::Archived,
+            BettingError::LobbyNotSettled
+        );
+        let seeds: &[&[u8]] = &[
+            LOBBY_SEED,
+            lobby.owner.as_ref(),
+            lobby.game_id_hash.as_ref(),
+            &[ctx.bumps.lobby],
+        ];
+        let accounts = poker_engine::cpi::accounts::CloseHand {
+            engine: ctx.accounts.engine.to_account_info(),
+            game: ctx.accounts.engine_game.to_account_info(),
+            hand: ctx.accounts.hand.to_account_info(),
+            lobby: lobby.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        poker_engine::cpi::close_hand(
+            CpiContext::new_with_signer(
+                ctx.accounts.engine_program.to_account_info(),
+                accounts,
+                &[seeds],
+            ),
+            hand_number,
+        )
+    }
+
+    /// Registers the signer as a keeper, staking `stake` lamports into its
+    /// keeper account. Keepers earn `keeper_fee` per step of the settlement,
+    /// refund and expiry cranks they commit to and run on time.
+    pub fn register_keeper(ctx: Context<RegisterKeeper>, stake: u64) -> Result<()> {
+        require!(
+            stake > 0 && stake >= ctx.accounts.config.min_keeper_stake,
+            BettingError::KeeperStakeTooLow
+        );
+        Funder::Signer(ctx.accounts.authority.to_account_info()).pay(
+            &ctx.accounts.keeper.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            stake,
+        )?;
+        let keeper = &mut ctx.accounts.keeper;
+        keeper.authority = ctx.accounts.authority.key();
+        keeper.stake = stake;
+        keeper.registered_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Pays the keeper its crank fees, as far as the treasury's free balance
+    /// covers them; the rest stays owed.
+    pub fn claim_keeper_fees(ctx: Context<ClaimKeeperFees>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let treasury = &ctx.accounts.treasury;
+        let available = treasury
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0))
+            .saturating_sub(config.accrued_revenue_share)
+            .saturating_sub(config.reserved_rake);
+        let keeper = &mut ctx.accounts.keeper;
+        let amount = keeper.fees_owed.min(available);
+        require!(amount > 0, BettingError::NothingToClaim);
+
+        let seeds: &[&[u8]] = &[TREASURY_SEED, &[ctx.bumps.treasury]];
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                treasury.key,
+                ctx.accounts.authority.key,
+                amount,
+            ),
+            &[
+                treasury.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[seeds],
+        )?;
+        keeper.fees_owed -= amount;
+        Ok(())
+    }
+
+    /// Commits the keeper to crank `lobby` within `KEEPER_FEE_WINDOW`. Crank
+    /// fees are only paid on a committed lobby, and a keeper that lets the
+    /// window pass without cranking it can be slashed.
+    pub fn commit_keeper(ctx: Context<CommitKeeper>) -> Result<()> {
+        let keeper = &mut ctx.accounts.keeper;
+        logic::check_keeper_commits(keeper, &ctx.accounts.config)?;
+        keeper.committed_lobby = Some(ctx.accounts.lobby.key());
+        keeper.crank_by = Clock::get()?.unix_timestamp + KEEPER_FEE_WINDOW;
+        Ok(())
+    }
+
+    /// Moves up to `amount` of a keeper's stake into the treasury once it has
+    /// missed the crank it committed to, and releases the commitment.
+    pub fn slash_keeper(ctx: Context<SlashKeeper>, amount: u64) -> Result<()> {
+        let keeper = &mut ctx.accounts.keeper;
+        let (lobby, amount) = logic::slash_keeper(keeper, amount, Clock::get()?.unix_timestamp)?;
+        Funder::Program(keeper.to_account_info()).pay(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+        emit!(KeeperSlashed {
+            keeper: keeper.key(),
+            authority: keeper.authority,
+            amount,
+            stake: keeper.stake,
+            lobby,
+        });
+        Ok(())
+    }
+
+    /// Stops the keeper earning fees and starts its stake unbonding.
+    pub fn retire_keeper(ctx: Context<RetireKeeper>) -> Result<()> {
+        let keeper = &mut ctx.accounts.keeper;
+        require!(keeper.retired_at == 0, BettingError::KeeperRetired);
+        keeper.retired_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Closes a retired keeper once `KEEPER_UNBONDING_PERIOD` has passed,
+    /// returning its stake and rent. Fees still owed are forfeited.
+    pub fn withdraw_keeper_stake(ctx: Context<WithdrawKeeperStake>) -> Result<()> {
+        let retired_at = ctx.accounts.keeper.retired_at;
+        require!(
+            retired_at != 0 && Clock::get()?.unix_timestamp >= retired_at + KEEPER_UNBONDING_PERIOD,
+            BettingError::KeeperUnbonding
+        );
+        Ok(())
+    }
+
+    /// Creates the owner's lobby template `name`, checking `terms` as
+    /// `create_lobby` would.
+    pub fn create_lobby_template(
+        ctx: Context<CreateLobbyTemplate>,
+        name: String,
+        terms: LobbyTerms,
+    ) -> Result<()> {
+        require!(
+            !name.is_empty() && name.len() <= MAX_TEMPLATE_NAME_LEN,
+            BettingError::InvalidTemplateName
+        );
+        terms.validate()?;
+        let template = &mut ctx.accounts.template;
+        template.owner = ctx.accounts.owner.key();
+        template.name = name;
+        template.terms = terms;
+        template.lobbies_created = 0;
+        template.created_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Opens lobby `game_id` on a copy of the template's terms.
+    pub fn create_lobby_from_template(
+        ctx: Context<CreateLobbyFromTemplate>,
+        game_id: String,
+    ) -> Result<()> {
+        let template = &mut ctx.accounts.template;
+        template.lobbies_created = template
+            .lobbies_created
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        let terms = template.terms.clone();
+        open_lobby(ctx.accounts.lobby_accounts(), game_id, terms)
+    }
+
+    /// Closes a template, returning its rent. Lobbies opened from it are
+    /// unaffected.
+    pub fn close_lobby_template(_ctx: Context<CloseLobbyTemplate>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Validates, prices and escrows a bet. Every instruction that places a bet
+/// goes through here so the compliance and limit checks can't be bypassed.
+#[allow(clippy::too_many_arguments)]
+fn place_bet_with(
+    accounts: BetAccounts,
+    program_id: &Pubkey,
+    player_name: String,
+    amount: u64,
+    min_expected_odds_bps: u32,
+    side: BetSide,
+    client_id: [u8; 16],
+    memo: Option<String>,
+) -> Result<()> {
+    let lobby = accounts.lobby;
+    let bet = accounts.bet;
+    let bettor = accounts.bettor;
+    let escrow = &accounts.escrow;
+    require!(
+        bet.bettor == Pubkey::default(),
+        BettingError::BetAlreadyPlaced
+    );
+    require!(
+        memo.as_deref().unwrap_or_default().len() <= MAX_BET_MEMO_LEN,
+        BettingError::MemoTooLong
+    );
+
+    let price = check_open_for_bets(
+        lobby,
+        accounts.config,
+        amount,
+        accounts.instructions.as_ref(),
+        accounts.price_update.as_ref(),
+    )?;
+    require!(
+        lobby.model_names.contains(&player_name),
+        BettingError::InvalidPlayerName
+    );
+    check_attestation(lobby, accounts.config, accounts.attestation, bettor)?;
+
+    // Protocol fee goes to the treasury, the rest is the stake held in escrow
+    let requested_amount = amount;
+    let now = Clock::get()?.unix_timestamp;
+    let fee = bettor_fee(accounts.config, accounts.bettor_stats, lobby.key(), amount, now)?;
+    let stake = amount.checked_sub(fee).ok_or(BettingError::Overflow)?;
+    require!(stake > 0, BettingError::BetAmountMustBePositive);
+
+    let model_index = lobby.model_index(&player_name)?;
+    let logic::Fill {
+        odds_bps,
+        stake,
+        fee,
+    } = logic::fill_bet(
+        lobby,
+        &player_name,
+        side,
+        stake,
+        fee,
+        min_expected_odds_bps,
+        now,
+    )?;
+    let amount = stake + fee;
+
+    record_wager(
+        accounts.config,
+        accounts.bettor_stats,
+        &accounts.self_exclusion,
+        &accounts.denylist,
+        bettor,
+        amount,
+        now,
+        price.as_ref(),
+        program_id,
+    )?;
+    collect_fee(
+        &accounts.funder,
+        lobby,
+        accounts.config,
+        &accounts.treasury,
+        &accounts.system_program,
+        fee,
+    )?;
+
+    accounts
+        .funder
+        .pay(escrow, &accounts.system_program, stake)?;
+    lobby.track_deposit(stake)?;
+
+    bet.bettor = bettor;
+    bet.lobby = lobby.key();
+    bet.player_name = player_name;
+    bet.amount = stake;
+    bet.requested_amount = requested_amount;
+    bet.placed_at = Clock::get()?.unix_timestamp;
+    bet.status = BetStatus::Active;
+    lobby.outstanding_bets = lobby
+        .outstanding_bets
+        .checked_add(1)
+        .ok_or(BettingError::Overflow)?;
+    bet.odds_bps = odds_bps;
+    bet.side = side;
+    bet.payout_address = None;
+    bet.client_id = client_id;
+    bet.memo = memo.clone();
+    (bet.usd_per_sol, bet.usd_value) = match &price {
+        Some(price) => (price.micro_usd(LAMPORTS_PER_SOL)?, price.micro_usd(amount)?),
+        None => (0, 0),
+    };
+    bet.raffle_ticket = None;
+    bet.selection_changes = 0;
+
+    lobby.total_bets = lobby
+        .total_bets
+        .checked_add(stake)
+        .ok_or(BettingError::Overflow)?;
+    match side {
+        BetSide::Back => {
+            lobby.model_pools[model_index] = lobby.model_pools[model_index]
+                .checked_add(stake)
+                .ok_or(BettingError::Overflow)?;
+            lobby.model_weights[model_index] = lobby.model_weights[model_index]
+                .checked_add(lobby.weighting.weight(stake))
+                .ok_or(BettingError::Overflow)?;
+        }
+        BetSide::Lay => {
+            lobby.lay_pools[model_index] = lobby.lay_pools[model_index]
+                .checked_add(stake)
+                .ok_or(BettingError::Overflow)?;
+            lobby.total_lay = lobby
+                .total_lay
+                .checked_add(stake)
+                .ok_or(BettingError::Overflow)?;
+        }
+    }
+    touch_lobby(lobby, LobbyAction::BetPlaced)?;
+    emit!(BetPlaced {
+        lobby: lobby.key(),
+        bet: bet.key(),
+        bettor,
+        player_name: bet.player_name.clone(),
+        side,
+        amount: stake,
+        client_id,
+        memo,
+    });
+
+    Ok(())
+}
+
+/// Borrowed view of the accounts `place_bet_with` needs. `bettor` owns the bet,
+/// while `funder` is where the lamports come from.
+pub struct BetAccounts<'a, 'info> {
+    pub lobby: &'a mut Account<'info, Lobby>,
+    pub bet: &'a mut Account<'info, Bet>,
+    pub bettor: Pubkey,
+    pub funder: Funder<'info>,
+    pub bettor_stats: &'a mut Account<'info, BettorStats>,
+    pub self_exclusion: AccountInfo<'info>,
+    pub denylist: AccountInfo<'info>,
+    pub escrow: AccountInfo<'info>,
+    pub config: &'a mut Account<'info, Config>,
+    pub treasury: AccountInfo<'info>,
+    pub attestation: Option<&'a Account<'info, Attestation>>,
+    pub system_program: AccountInfo<'info>,
+    /// Pyth SOL/USD update, needed once USD bounds or caps apply
+    pub price_update: Option<AccountInfo<'info>>,
+    /// Instructions sysvar, needed while the bet composition guard is on
+    pub instructions: Option<AccountInfo<'info>>,
+}
+
+/// Borrowed view of the accounts `open_lobby` needs.
+pub struct LobbyAccounts<'a, 'info> {
+    pub config: &'a Account<'info, Config>,
+    pub owner_stats: &'a mut Account<'info, OwnerStats>,
+    pub operator_profile: &'a mut Account<'info, OperatorProfile>,
+    pub lobby: &'a mut Account<'info, Lobby>,
+    pub bond: &'a mut Account<'info, OperatorBond>,
+    pub owner: &'a Signer<'info>,
+    pub escrow: AccountInfo<'info>,
+    pub system_program: &'a Program<'info, System>,
+    /// Lobby the new one continues, when it is a continuation
+    pub parent_lobby: Option<&'a mut Account<'info, Lobby>>,
+}
+
+pub enum Funder<'info> {
+    /// Wallet signing the transaction, paying through the System Program
+    Signer(AccountInfo<'info>),
+    /// Account owned by this program, debited directly
+    Program(AccountInfo<'info>),
+}
+
+impl<'info> Funder<'info> {
+    fn pay(&self, to: &AccountInfo<'info>, system_program: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        match self {
+            Funder::Signer(from) => {
+                // Use Anchor's CPI helper which properly handles account permissions
+                let cpi_accounts = anchor_lang::system_program::Transfer {
+                    from: from.clone(),
+                    to: to.clone(),
+                };
+                let cpi_context = CpiContext::new(system_program.clone(), cpi_accounts);
+                anchor_lang::system_program::transfer(cpi_context, amount)
+            }
+            Funder::Program(from) => {
+                let remaining = from
+                    .lamports()
+                    .checked_sub(amount)
+                    .ok_or(BettingError::InsufficientDeposit)?;
+                require!(
+                    remaining >= Rent::get()?.minimum_balance(from.data_len()),
+                    BettingError::InsufficientDeposit
+                );
+                **from.try_borrow_mut_lamports()? = remaining;
+                **to.try_borrow_mut_lamports()? = to
+                    .lamports()
+                    .checked_add(amount)
+                    .ok_or(BettingError::Overflow)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Records a lobby mutation: bumps `seq` and `updated_at` and emits the
+/// matching `LobbyUpdated` event. Returns the new sequence number.
+fn touch_lobby(lobby: &mut Account<Lobby>, action: LobbyAction) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    lobby.seq = lobby.seq.checked_add(1).ok_or(BettingError::Overflow)?;
+    lobby.updated_at = now;
+    emit!(LobbyUpdated {
+        lobby: lobby.key(),
+        seq: lobby.seq,
+        action,
+        updated_at: now,
+    });
+    Ok(lobby.seq)
+}
+
+/// Message an enclave signs to attest a lobby's result.
+pub fn attested_result_message(lobby: &Pubkey, measurement: &[u8; 32], winner_name: &str) -> Vec<u8> {
+    [
+        b"poker-x402:result:".as_slice(),
+        lobby.as_ref(),
+        measurement,
+        winner_name.as_bytes(),
+    ]
+    .concat()
+}
+
+/// Checks that the instruction before the current one is an Ed25519 program
+/// instruction with a single signature by `signer` over `message`, all data
+/// carried inline.
+fn verify_preceding_ed25519(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    use anchor_lang::solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    };
+
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, BettingError::InvalidEnclaveSignature);
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(
+        ix.program_id == anchor_lang::solana_program::ed25519_program::ID,
+        BettingError::InvalidEnclaveSignature
+    );
+
+    // Layout: count, padding, then seven u16 offsets per signature.
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, BettingError::InvalidEnclaveSignature);
+    let read = |i: usize| u16::from_le_bytes([data[2 + 2 * i], data[3 + 2 * i]]) as usize;
+    let (signature_ix, pubkey_offset, pubkey_ix) = (read(1), read(2), read(3));
+    let (message_offset, message_size, message_ix) = (read(4), read(5), read(6));
+    require!(
+        [signature_ix, pubkey_ix, message_ix]
+            .iter()
+            .all(|index| *index == u16::MAX as usize),
+        BettingError::InvalidEnclaveSignature
+    );
+    require!(
+        data.get(pubkey_offset..pubkey_offset + 32) == Some(signer.as_ref())
+            && data.get(message_offset..message_offset + message_size) == Some(message),
+        BettingError::InvalidEnclaveSignature
+    );
+    Ok(())
+}
+
+/// Where a winning bet is paid: its payout address if set, else the bettor.
+fn payout_recipient<'info>(
+    bet: &Bet,
+    bettor: AccountInfo<'info>,
+    payout_recipient: Option<AccountInfo<'info>>,
+) -> Result<AccountInfo<'info>> {
+    match bet.payout_address {
+        Some(payout_address) => {
+            let recipient = payout_recipient.ok_or(BettingError::InvalidPayoutRecipient)?;
+            require!(
+                recipient.key() == payout_address,
+                BettingError::InvalidPayoutRecipient
+            );
+            Ok(recipient)
+        }
+        None => Ok(bettor),
+    }
+}
+
+/// Pays a winning bet on a finished lobby from its escrow to `recipient` and
+/// marks it paid. Winnings below `config.min_payout_lamports` go to the
+/// treasury instead and the bet is marked `BelowMinimum`. Returns the amount
+/// paid to `recipient`.
+#[allow(clippy::too_many_arguments)]
+fn pay_winning_bet<'info>(
+    lobby: &mut Account<'info, Lobby>,
+    bet: &mut Account<'info, Bet>,
+    snapshot: Option<&PoolSnapshot>,
+    config: &Config,
+    denylist: &AccountInfo<'info>,
+    escrow: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    treasury: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<u64> {
+    check_not_denied(denylist, &bet.bettor, program_id)?;
+    check_not_denied(denylist, recipient.key, program_id)?;
+    require!(bet.lobby == lobby.key(), BettingError::InvalidBetAccount);
+    let logic::Payout {
+        amount: transfer_amount,
+        below_minimum,
+    } = logic::settle_winning_bet(lobby, bet, snapshot, config)?;
+
+    let lobby_key = lobby.key();
+    let (_escrow_pda, escrow_bump) =
+        Pubkey::find_program_address(&[ESCROW_SEED, lobby_key.as_ref()], program_id);
+    let escrow_bump_array = [escrow_bump];
+    let seeds = &[ESCROW_SEED, lobby_key.as_ref(), &escrow_bump_array];
+    let signer_seeds = &[&seeds[..]];
+
+    let to = if below_minimum { treasury } else { recipient };
+    if transfer_amount > 0 {
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                escrow.key,
+                to.key,
+                transfer_amount,
+            ),
+            &[escrow, to, system_program],
+            signer_seeds,
+        )?;
+    }
+
+    if below_minimum {
+        transition_bet(lobby, bet, BetStatus::BelowMinimum)?;
+        emit_bet_settled(bet, 0)?;
+        touch_lobby(lobby, LobbyAction::PayoutSwept)?;
+        Ok(0)
+    } else {
+        transition_bet(lobby, bet, BetStatus::Paid)?;
+        emit_bet_settled(bet, transfer_amount)?;
+        touch_lobby(lobby, LobbyAction::WinningsPaid)?;
+        Ok(transfer_amount)
+    }
+}
+
+/// Applies a sensitive config update at once, which an admin can only do
+/// while no timelock is set; otherwise it must go through
+/// `propose_config_change`.
+fn set_config_directly(config: &mut Config, change: ConfigChange) -> Result<()> {
+    logic::set_config(config, change, Clock::get()?.unix_timestamp)
+}
+
+/// Fails if `wallet` is on the screening denylist. An uninitialized denylist
+/// denies no one.
+fn check_not_denied(denylist: &AccountInfo, wallet: &Pubkey, program_id: &Pubkey) -> Result<()> {
+    if denylist.owner == program_id && !denylist.data_is_empty() {
+        let denylist = Denylist::try_deserialize(&mut &denylist.try_borrow_data()?[..])?;
+        require!(!denylist.contains(wallet), BettingError::WalletDenied);
+    }
+    Ok(())
+}
+
+/// Fails unless `lobby` is taking bets of `amount` in this transaction, within
+/// the USD bounds. Returns the SOL/USD price when an update was passed.
+fn check_open_for_bets(
+    lobby: &Lobby,
+    config: &Config,
+    amount: u64,
+    instructions: Option<&AccountInfo>,
+    price_update: Option<&AccountInfo>,
+) -> Result<Option<pyth::UsdPrice>> {
+    logic::check_taking_bets(lobby, Clock::get()?.unix_timestamp)?;
+    check_bet_terms(config, amount, instructions, price_update)
+}
+
+/// The checks on a bet of `amount` that don't depend on which market takes
+/// it; hand markets, which stay open after the match market closes, run only
+/// these. Returns the SOL/USD price when an update was passed.
+fn check_bet_terms(
+    config: &Config,
+    amount: u64,
+    instructions: Option<&AccountInfo>,
+    price_update: Option<&AccountInfo>,
+) -> Result<Option<pyth::UsdPrice>> {
+    logic::check_bet_amount(config, amount)?;
+    check_bet_not_composed(config, instructions)?;
+    let price = load_usd_price(config, price_update, Clock::get()?.unix_timestamp)?;
+    check_usd_bet_bounds(config, price.as_ref(), amount)?;
+    Ok(price)
+}
+
+/// Checks the bettor's KYC attestation when the lobby or protocol requires one.
+fn check_attestation(
+    lobby: &Lobby,
+    config: &Config,
+    attestation: Option<&Account<Attestation>>,
+    bettor: Pubkey,
+) -> Result<()> {
+    if let Some(issuer) = lobby.attestation_issuer {
+        let attestation = attestation.ok_or(BettingError::AttestationRequired)?;
+        require!(
+            attestation.issuer == issuer && attestation.subject == bettor,
+            BettingError::InvalidAttestation
+        );
+        require!(!attestation.revoked, BettingError::InvalidAttestation);
+        require!(
+            attestation.expires_at > Clock::get()?.unix_timestamp,
+            BettingError::AttestationExpired
+        );
+        require!(
+            !lobby.restricted_regions.contains(&attestation.jurisdiction)
+                && !config.restricted_regions.contains(&attestation.jurisdiction),
+            BettingError::RegionRestricted
+        );
+    } else {
+        require!(
+            !lobby.attestation_required && !config.attestation_required,
+            BettingError::AttestationRequired
+        );
+    }
+    Ok(())
+}
+
+/// Applies self-exclusion, screening, rate and wager limits to a bet of
+/// `amount` and records it on the bettor's stats.
+#[allow(clippy::too_many_arguments)]
+fn record_wager(
+    config: &Config,
+    stats: &mut BettorStats,
+    self_exclusion: &AccountInfo,
+    denylist: &AccountInfo,
+    bettor: Pubkey,
+    amount: u64,
+    now: i64,
+    price: Option<&pyth::UsdPrice>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if self_exclusion.owner == program_id && !self_exclusion.data_is_empty() {
+        let exclusion =
+            SelfExclusion::try_deserialize(&mut &self_exclusion.try_borrow_data()?[..])?;
+        require!(now >= exclusion.until, BettingError::SelfExcluded);
+    }
+    check_not_denied(denylist, &bettor, program_id)?;
+
+    stats.bettor = bettor;
+    if config.bet_window > 0 && now >= stats.window_started_at + config.bet_window {
+        stats.window_started_at = now;
+        stats.bets_in_window = 0;
+    }
+    if config.max_bets_per_window > 0 {
+        require!(
+            stats.bets_in_window < config.max_bets_per_window,
+            BettingError::RateLimited
+        );
+    }
+    stats.roll_limit_windows(now);
+    stats.apply_pending_limits(now);
+    stats.record_wager(amount)?;
+    if stats.usd_limits != BettingLimits::default() {
+        let price = price.ok_or(BettingError::PriceUpdateRequired)?;
+        stats.check_limits(&stats.usd_limits.in_lamports(price)?)?;
+    }
+    if let Some(price) = price {
+        let value = price.micro_usd(amount)?;
+        stats.usd_wagered = stats
+            .usd_wagered
+            .checked_add(value)
+            .ok_or(BettingError::Overflow)?;
+        stats.usd_pnl = stats
+            .usd_pnl
+            .checked_sub(value as i64)
+            .ok_or(BettingError::Overflow)?;
+    }
+    stats.accrue_points(config.season, math::apply_bps(amount, config.volume_points_bps as u64)?)?;
+    stats.bets_in_window = stats.bets_in_window.saturating_add(1);
+    stats.total_bets = stats.total_bets.checked_add(1).ok_or(BettingError::Overflow)?;
+    stats.total_wagered = stats
+        .total_wagered
+        .checked_add(amount)
+        .ok_or(BettingError::Overflow)?;
+    stats.last_bet_at = now;
+    Ok(())
+}
+
+/// SOL/USD price from `price_update`, or `None` when no feed is configured or
+/// no update was passed; checks that need a price then fail on their own.
+fn load_usd_price(
+    config: &Config,
+    price_update: Option<&AccountInfo>,
+    now: i64,
+) -> Result<Option<pyth::UsdPrice>> {
+    let (Some(feed_id), Some(price_update)) = (&config.usd_price_feed, price_update) else {
+        return Ok(None);
+    };
+    pyth::UsdPrice::load(
+        price_update,
+        feed_id,
+        config.max_price_age,
+        config.max_price_conf_bps,
+        now,
+    )
+    .map(Some)
+}
+
+/// Fails unless a bet of `amount` lamports is within the USD bounds in config.
+fn check_usd_bet_bounds(
+    config: &Config,
+    price: Option<&pyth::UsdPrice>,
+    amount: u64,
+) -> Result<()> {
+    if config.min_bet_usd == 0 && config.max_bet_usd == 0 {
+        return Ok(());
+    }
+    let value = price
+        .ok_or(BettingError::PriceUpdateRequired)?
+        .micro_usd(amount)?;
+    require!(
+        value >= config.min_bet_usd && (config.max_bet_usd == 0 || value <= config.max_bet_usd),
+        BettingError::BetOutsideUsdBounds
+    );
+    Ok(())
+}
+
+/// Pays the protocol fee to the treasury and accrues the stakers' revenue share.
+fn collect_fee<'info>(
+    funder: &Funder<'info>,
+    lobby: &mut Lobby,
+    config: &mut Config,
+    treasury: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    fee: u64,
+) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
+    }
+    funder.pay(treasury, system_program, fee)?;
+    let revenue_share = math::apply_bps(fee, config.revenue_share_bps as u64)?;
+    config.accrued_revenue_share = config
+        .accrued_revenue_share
+        .checked_add(revenue_share)
+        .ok_or(BettingError::Overflow)?;
+    let net = fee - revenue_share;
+    let raffle = math::apply_bps(net, config.raffle_bps as u64)?;
+    lobby.raffle_pool = lobby
+        .raffle_pool
+        .checked_add(raffle)
+        .ok_or(BettingError::Overflow)?;
+    let reimbursement = math::apply_bps(net - raffle, lobby.reimbursement_bps as u64)?;
+    lobby.operator_reimbursement = lobby
+        .operator_reimbursement
+        .checked_add(reimbursement)
+        .ok_or(BettingError::Overflow)?;
+    let rake = if lobby.rake_splits.is_empty() {
+        0
+    } else {
+        net - raffle - reimbursement
+    };
+    lobby.rake = lobby.rake.checked_add(rake).ok_or(BettingError::Overflow)?;
+    config.reserved_rake = config
+        .reserved_rake
+        .checked_add(raffle + reimbursement + rake)
+        .ok_or(BettingError::Overflow)?;
+    Ok(())
+}
+
+/// Pays each rake recipient its share of the lobby's rake out of the
+/// treasury and releases the rest, rounding dust included, to the treasury.
+fn pay_rake<'info>(
+    lobby: &mut Lobby,
+    config: &mut Config,
+    treasury: &AccountInfo<'info>,
+    recipients: &[AccountInfo<'info>],
+    system_program: &AccountInfo<'info>,
+    treasury_bump: u8,
+) -> Result<()> {
+    if lobby.rake == 0 {
+        return Ok(());
+    }
+    let treasury_key = treasury.key();
+    let mut recipients = recipients.iter();
+    for split in &lobby.rake_splits {
+        if split.recipient == treasury_key {
+            continue;
+        }
+        let recipient = recipients.next().ok_or(BettingError::InvalidRakeSplits)?;
+        require!(
+            recipient.key() == split.recipient,
+            BettingError::InvalidRakeSplits
+        );
+        let share = math::apply_bps(lobby.rake, split.bps as u64)?;
+        if share == 0 {
+            continue;
+        }
+        anchor_lang::solana_program::program::invoke_signed(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                treasury.key,
+                recipient.key,
+                share,
+            ),
+            &[treasury.clone(), recipient.clone(), system_program.clone()],
+            &[&[TREASURY_SEED, &[treasury_bump]]],
+        )?;
+    }
+    release_rake(lobby, config);
+    Ok(())
+}
+
+/// Releases whatever of the lobby's rake is still reserved to the treasury.
+/// Runs on every move to a terminal status, so an over lobby never holds
+/// treasury funds back.
+fn release_rake(lobby: &mut Lobby, config: &mut Config) {
+    config.reserved_rake = config.reserved_rake.saturating_sub(lobby.rake);
+    lobby.rake = 0;
+}
+
+/// Calendar week index of a unix timestamp, weeks starting Thursday 00:00 UTC.
+pub fn calendar_week(now: i64) -> i64 {
+    now.div_euclid(BettorStats::WEEK)
+}
+
+/// Seed of the lobby for `game_id`, after its owner's key. Game ids are hashed
+/// so ids of any length fit the 32-byte seed limit.
+pub fn game_id_hash(game_id: &str) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hash(game_id.as_bytes()).to_bytes()
+}
+
+/// Commitment a blind bet on `player_name` is placed under.
+pub fn blind_bet_commitment(
+    lobby: &Pubkey,
+    bettor: &Pubkey,
+    player_name: &str,
+    salt: &[u8; 32],
+) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        lobby.as_ref(),
+        bettor.as_ref(),
+        player_name.as_bytes(),
+        salt,
+    ])
+    .to_bytes()
+}
+
+/// Binds a bounty proof to a hand record and its decision traces, truncated
+/// to a BN254 scalar.
+pub fn bounty_hand_binding(hand: &Pubkey, trace_hashes: &[[u8; 32]]) -> [u8; 32] {
+    let mut data: Vec<&[u8]> = vec![hand.as_ref()];
+    data.extend(trace_hashes.iter().map(|hash| hash.as_ref()));
+    let mut binding = anchor_lang::solana_program::hash::hashv(&data).to_bytes();
+    binding[0] &= 0x1f;
+    binding
+}
+
+fn add_to_bounty<'info>(
+    bounty: &mut Account<'info, Bounty>,
+    contribution: &mut Account<'info, BountyContribution>,
+    funder: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, BettingError::BetAmountMustBePositive);
+    Funder::Signer(funder.to_account_info()).pay(
+        &bounty.to_account_info(),
+        &system_program.to_account_info(),
+        amount,
+    )?;
+    bounty.total = bounty.total.checked_add(amount).ok_or(BettingError::Overflow)?;
+    contribution.bounty = bounty.key();
+    contribution.funder = funder.key();
+    contribution.amount = contribution
+        .amount
+        .checked_add(amount)
+        .ok_or(BettingError::Overflow)?;
+    emit!(BountyFunded {
+        bounty: bounty.key(),
+        funder: funder.key(),
+        amount,
+        total: bounty.total,
+    });
+    Ok(())
+}
+
+/// Opens a lobby on `terms`, for `create_lobby` and
+/// `create_lobby_from_template`.
+fn open_lobby(accounts: LobbyAccounts, game_id: String, terms: LobbyTerms) -> Result<()> {
+    require!(!accounts.config.paused, BettingError::ProtocolPaused);
+    terms.validate()?;
+    let LobbyTerms {
+        model_names,
+        starting_chips,
+        blinds,
+        max_hands,
+        payout_curve,
+        chip_buckets,
+        rake_splits,
+    } = terms;
+
+    let now = Clock::get()?.unix_timestamp;
+    let owner_stats = accounts.owner_stats;
+    if owner_stats.lobbies_created > 0 {
+        require!(
+            now >= owner_stats.last_lobby_created_at + accounts.config.lobby_creation_cooldown,
+            BettingError::LobbyCreationCooldown
+        );
+    }
+    owner_stats.owner = accounts.owner.key();
+    owner_stats.lobbies_created = owner_stats
+        .lobbies_created
+        .checked_add(1)
+        .ok_or(BettingError::Overflow)?;
+    owner_stats.last_lobby_created_at = now;
+
+    let profile = accounts.operator_profile;
+    require!(
+        profile.reputation() >= accounts.config.min_operator_reputation
+            || profile.on_probation(accounts.config.min_operator_reputation),
+        BettingError::ReputationTooLow
+    );
+    profile.owner = accounts.owner.key();
+    profile.lobbies_run = profile
+        .lobbies_run
+        .checked_add(1)
+        .ok_or(BettingError::Overflow)?;
+
+    let lobby = accounts.lobby;
+    lobby.owner = accounts.owner.key();
+    require!(
+        game_id.len() <= MAX_GAME_ID_LEN,
+        BettingError::GameIdTooLong
+    );
+    lobby.game_id_hash = game_id_hash(&game_id);
+    lobby.game_id = game_id;
+    let payout_curve = if payout_curve.is_empty() {
+        vec![math::BPS_DENOMINATOR as u16]
+    } else {
+        payout_curve
+    };
+    lobby.position_stake_paid = vec![0; payout_curve.len()];
+    lobby.position_paid = vec![0; payout_curve.len()];
+    lobby.payout_curve = payout_curve;
+    lobby.placings = Vec::new();
+    lobby.bucket_pools = if chip_buckets.is_empty() {
+        Vec::new()
+    } else {
+        vec![0; chip_buckets.len() + 1]
+    };
+    lobby.chip_buckets = chip_buckets;
+    lobby.rake_splits = rake_splits;
+    lobby.rake = 0;
+    lobby.charity = None;
+    lobby.charity_bps = 0;
+    lobby.charity_amount = 0;
+    lobby.freeroll_pool = 0;
+    lobby.freeroll_paid_picks = 0;
+    lobby.freeroll_paid = 0;
+    lobby.final_chips = Vec::new();
+    lobby.bucket_stake_paid = 0;
+    lobby.bucket_paid = 0;
+    lobby.spectator_fee = 0;
+    lobby.payout_root = None;
+    lobby.payout_root_posted_at = 0;
+    lobby.payout_root_total = 0;
+    lobby.payout_root_challenged = false;
+    lobby.merkle_paid = 0;
+    lobby.bet_tree = None;
+    lobby.compressed_bets = 0;
+    lobby.reveal_deadline = 0;
+    lobby.blind_stake = 0;
+    lobby.disputes_lost = 0;
+    lobby.slashed_bond = 0;
+    lobby.bond_slashed_at = 0;
+    lobby.compensable_stake = 0;
+    lobby.compensation_paid = 0;
+    lobby.player_pools = 0;
+    lobby.results_authority = None;
+    lobby.pending_results_authority = None;
+    lobby.results_authority_change_at = 0;
+    lobby.model_pools = vec![0; model_names.len()];
+    lobby.freeroll_picks = vec![0; model_names.len()];
+    lobby.weighting = PoolWeighting::Stake;
+    lobby.raffle_pool = 0;
+    lobby.raffle_tickets = 0;
+    lobby.raffle_entries_close_at = 0;
+    lobby.raffle_seed = None;
+    lobby.raffle_ticket = None;
+    lobby.model_weights = vec![0; model_names.len()];
+    lobby.disqualified = vec![false; model_names.len()];
+    lobby.model_names = model_names;
+    lobby.starting_chips = starting_chips;
+    lobby.blinds = blinds;
+    lobby.max_hands = max_hands;
+    lobby.status = LobbyStatus::Waiting;
+    lobby.total_bets = 0;
+    lobby.attestation_issuer = None;
+    lobby.sponsor_pool = 0;
+    lobby.match_pool = 0;
+    lobby.winner = None;
+    lobby.market_mode = MarketMode::Parimutuel;
+    lobby.market_maker = None;
+    lobby.quoted_odds_bps = vec![0; lobby.model_names.len()];
+    lobby.quoted_at = 0;
+    lobby.quote_max_age = 0;
+    lobby.market_maker_bankroll = 0;
+    lobby.model_liabilities = vec![0; lobby.model_names.len()];
+    lobby.fixed_odds_paid = 0;
+    lobby.order_book_locked = 0;
+    lobby.escrow_balance = 0;
+    lobby.seq = 0;
+    lobby.betting_deadline = 0;
+    lobby.pools_locked = false;
+    lobby.transcript_commitment = None;
+    lobby.verified_winner = None;
+    lobby.lay_pools = vec![0; lobby.model_names.len()];
+    lobby.total_lay = 0;
+    lobby.compliance_mode = accounts.config.compliance_mode.clone();
+    lobby.restricted_regions = accounts.config.restricted_regions.clone();
+    lobby.attestation_required = accounts.config.attestation_required;
+    lobby.created_at = Clock::get()?.unix_timestamp;
+    lobby.parent_lobby = None;
+    lobby.continuation = None;
+    lobby.chip_market_rolled = false;
+    lobby.term_amendments = Vec::new();
+    lobby.game_config = None;
+    lobby.reimbursement_bps = 0;
+    lobby.operator_reimbursement = 0;
+    lobby.reimbursement_paid = 0;
+    lobby.hands_counted = 0;
+    lobby.reimbursed_hands = vec![0; lobby.model_names.len()];
+    lobby.reimbursement_claimed = vec![false; lobby.model_names.len()];
+    lobby.reimbursement_closes_at = 0;
+    lobby.hand_escrow_balance = 0;
+    lobby.hands_checkpointed = 0;
+    lobby.open_hand_markets = Vec::new();
+    if let Some(parent) = accounts.parent_lobby {
+        lobby.parent_lobby = Some(parent.key());
+        lobby.charity = parent.charity;
+        lobby.charity_bps = parent.charity_bps;
+        lobby.weighting = parent.weighting.clone();
+        lobby.disqualified = parent.disqualified.clone();
+        lobby.results_authority = parent.results_authority;
+        lobby.attestation_issuer = parent.attestation_issuer;
+        lobby.compliance_mode = parent.compliance_mode.clone();
+        lobby.restricted_regions = parent.restricted_regions.clone();
+        lobby.attestation_required = parent.attestation_required;
+        parent.continuation = Some(lobby.key());
+        touch_lobby(parent, LobbyAction::ContinuationCreated)?;
+    }
+    touch_lobby(lobby, LobbyAction::Created)?;
+
+    // Fund the escrow PDA with rent-exempt minimum so it can receive CPI transfers
+    // The minimum rent-exempt balance for a 0-byte account is ~890880 lamports
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
+    
+    anchor_lang::solana_program::program::invoke(
+        &anchor_lang::solana_program::system_instruction::transfer(
+            accounts.owner.key,
+            accounts.escrow.key,
+            rent_exempt_minimum,
+        ),
+        &[
+            accounts.owner.to_account_info(),
+            accounts.escrow.to_account_info(),
+            accounts.system_program.to_account_info(),
+        ],
+    )?;
+
+    let bond = accounts.bond;
+    bond.owner = accounts.owner.key();
+    bond.lobby = lobby.key();
+    bond.amount = 0;
+    bond.settle_by = match accounts.config.settlement_window {
+        0 => 0,
+        window => lobby
+            .created_at
+            .checked_add(window)
+            .ok_or(BettingError::Overflow)?,
+    };
+    bond.slashed = false;
+    add_to_bond(
+        bond,
+        accounts.owner,
+        accounts.system_program,
+        accounts.config.min_operator_bond,
+    )
+}
+
+fn add_to_bond<'info>(
+    bond: &mut Account<'info, OperatorBond>,
+    owner: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount > 0 {
+        Funder::Signer(owner.to_account_info()).pay(
+            &bond.to_account_info(),
+            &system_program.to_account_info(),
+            amount,
+        )?;
+    }
+    bond.amount = bond
+        .amount
+        .checked_add(amount)
+        .ok_or(BettingError::Overflow)?;
+    Ok(())
+}
+
+/// Protocol fee on a bet of `amount` by the bettor of `stats`, at the fee of
+/// the tier its trailing 30-day volume reaches. Emits the discount a tier
+/// gives on the base fee.
+fn bettor_fee(
+    config: &Config,
+    stats: &BettorStats,
+    lobby: Pubkey,
+    amount: u64,
+    now: i64,
+) -> Result<u64> {
+    let volume = stats.rolling_monthly_volume(now)?;
+    let fee = logic::bettor_fee(config, volume, amount)?;
+    if let Some((tier, fee_bps)) = config.fee_tier(volume) {
+        let base_fee = math::apply_bps(amount, config.fee_bps as u64)?;
+        emit!(FeeTierApplied {
+            bettor: stats.bettor,
+            lobby,
+            tier,
+            fee_bps,
+            rebate: base_fee.saturating_sub(fee),
+        });
+    }
+    Ok(fee)
+}
+
+/// Moves each player pool's stake into the lobby's escrow and back pools and
+/// its fees to the treasury. `pools` must be every pool opened for the lobby.
+fn fold_player_pools<'info>(
+    lobby: &mut Account<'info, Lobby>,
+    config: &mut Config,
+    pools: &'info [AccountInfo<'info>],
+    escrow: &AccountInfo<'info>,
+    treasury: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    require!(
+        pools.len() == lobby.player_pools as usize,
+        BettingError::InvalidPlayerPools
+    );
+    if pools.is_empty() {
+        return Ok(());
+    }
+    let lobby_key = lobby.key();
+    let mut seen = vec![false; lobby.model_names.len()];
+    for info in pools {
+        let mut pool = Account::<PlayerPool>::try_from(info)?;
+        let index = pool.model_index as usize;
+        require!(
+            pool.lobby == lobby_key && !seen[index],
+            BettingError::InvalidPlayerPools
+        );
+        seen[index] = true;
+
+        let funder = Funder::Program(info.clone());
+        collect_fee(&funder, lobby, config, treasury, system_program, pool.fees)?;
+        funder.pay(escrow, system_program, pool.stake)?;
+        lobby.track_deposit(pool.stake)?;
+        lobby.total_bets = lobby
+            .total_bets
+            .checked_add(pool.stake)
+            .ok_or(BettingError::Overflow)?;
+        lobby.model_pools[index] = lobby.model_pools[index]
+            .checked_add(pool.stake)
+            .ok_or(BettingError::Overflow)?;
+        lobby.outstanding_bets = lobby
+            .outstanding_bets
+            .checked_add(pool.pending_bets as u64)
+            .ok_or(BettingError::Overflow)?;
+        pool.stake = 0;
+        pool.fees = 0;
+        pool.pending_bets = 0;
+        pool.exit(program_id)?;
+    }
+    touch_lobby(lobby, LobbyAction::PlayerPoolsAggregated)?;
+    Ok(())
+}
+
+/// Moves `lobby` to `status`, failing on a transition the lifecycle doesn't
+/// allow.
+fn transition_lobby(lobby: &mut Lobby, status: LobbyStatus) -> Result<()> {
+    require!(
+        lobby.status.can_become(&status),
+        BettingError::InvalidLobbyTransition
+    );
+    lobby.status = status;
+    Ok(())
+}
+
+/// Moves `bet` to `status`, failing on a transition the lifecycle doesn't
+/// allow.
+fn transition_bet(lobby: &mut Lobby, bet: &mut Account<Bet>, status: BetStatus) -> Result<()> {
+    require!(
+        bet.status.can_become(&status),
+        BettingError::InvalidBetTransition
+    );
+    lobby.outstanding_bets = match (bet.status.is_outstanding(), status.is_outstanding()) {
+        (true, false) => lobby.outstanding_bets.checked_sub(1),
+        (false, true) => lobby.outstanding_bets.checked_add(1),
+        _ => Some(lobby.outstanding_bets),
+    }
+    .ok_or(BettingError::Overflow)?;
+    bet.status = status.clone();
+    emit!(BetStatusChanged {
+        bet: bet.key(),
+        lobby: bet.lobby,
+        status,
+    });
+    Ok(())
+}
+
+/// Reports what a bet returned once it is paid, cashed out or lost, valued in
+/// USD at its placement price.
+fn emit_bet_settled(bet: &Account<Bet>, payout: u64) -> Result<()> {
+    let usd_payout = bet.usd(payout)?;
+    emit!(BetSettled {
+        bet: bet.key(),
+        lobby: bet.lobby,
+        bettor: bet.bettor,
+        status: bet.status.clone(),
+        payout,
+        usd_per_sol: bet.usd_per_sol,
+        usd_value: bet.usd_value,
+        usd_payout,
+        usd_pnl: usd_payout as i64 - bet.usd_value as i64,
+    });
+    Ok(())
+}
+
+/// Instructions that settle a lobby, pay, refund or settle its bets, or change
+/// its status, which `check_bet_not_composed` keeps out of a bet's transaction.
+const SETTLEMENT_INSTRUCTIONS: [[u8; 8]; 28] = [
+    instruction::UpdateLobbyStatus::DISCRIMINATOR,
+    instruction::LockPools::DISCRIMINATOR,
+    instruction::FinishGame::DISCRIMINATOR,
+    instruction::VerifyOutcomeProof::DISCRIMINATOR,
+    instruction::SubmitAttestedResult::DISCRIMINATOR,
+    instruction::DistributeSingleWinning::DISCRIMINATOR,
+    instruction::ClaimAll::DISCRIMINATOR,
+    instruction::DistributeChipBucketWinning::DISCRIMINATOR,
+    instruction::PostPayoutRoot::DISCRIMINATOR,
+    instruction::ChallengePayoutRoot::DISCRIMINATOR,
+    instruction::ResolvePayoutChallenge::DISCRIMINATOR,
+    instruction::ClaimWithProof::DISCRIMINATOR,
+    instruction::SettleClaimedBet::DISCRIMINATOR,
+    instruction::ClaimCompressedBet::DISCRIMINATOR,
+    instruction::RefundUnrevealedBet::DISCRIMINATOR,
+    instruction::LockBet::DISCRIMINATOR,
+    instruction::RecordBetResult::DISCRIMINATOR,
+    instruction::DisputeBet::DISCRIMINATOR,
+    instruction::CashOutBet::DISCRIMINATOR,
+    instruction::SettleLosingBets::DISCRIMINATOR,
+    instruction::SweepExcess::DISCRIMINATOR,
+    instruction::SweepUnwonPool::DISCRIMINATOR,
+    instruction::DisqualifyModel::DISCRIMINATOR,
+    instruction::RefundVoidedBet::DISCRIMINATOR,
+    instruction::CheckpointSettle::DISCRIMINATOR,
+    instruction::VoidUnplayedHandMarkets::DISCRIMINATOR,
+    instruction::ClaimHandBet::DISCRIMINATOR,
+    instruction::ArchiveLobby::DISCRIMINATOR,
+];
+
+/// With `config.guard_bet_composition` on, fails unless the instructions sysvar
+/// is passed and no other instruction in the transaction is one of
+/// `SETTLEMENT_INSTRUCTIONS`, so a compromised owner key can't bet and settle
+/// atomically.
+fn check_bet_not_composed(config: &Config, instructions: Option<&AccountInfo>) -> Result<()> {
+    use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+
+    if !config.guard_bet_composition {
+        return Ok(());
+    }
+    let instructions = instructions.ok_or(BettingError::InstructionsSysvarRequired)?;
+    let mut index = 0;
+    while let Ok(ix) = load_instruction_at_checked(index, instructions) {
+        require!(
+            ix.program_id != crate::ID
+                || !SETTLEMENT_INSTRUCTIONS
+                    .iter()
+                    .any(|d| ix.data.starts_with(d)),
+            BettingError::BetComposedWithSettlement
+        );
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Credits `keeper` the crank fee for `steps` steps when it committed to
+/// `lobby` and cranked it within `KEEPER_FEE_WINDOW` of `due_at`, staked and
+/// not retired, and releases the commitment. A crank without a keeper, by one
+/// not committed to the lobby, or too late, still goes through, unpaid.
+fn credit_keeper(
+    keeper: Option<&mut Account<Keeper>>,
+    keeper_authority: Option<&Signer>,
+    config: Option<&Config>,
+    lobby: Pubkey,
+    due_at: i64,
+    steps: u64,
+) -> Result<()> {
+    let Some(keeper) = keeper else {
+        return Ok(());
+    };
+    require!(
+        keeper_authority.map(|authority| authority.key()) == Some(keeper.authority),
+        BettingError::Unauthorized
+    );
+    let config = config.ok_or(BettingError::InvalidKeeperAccounts)?;
+    logic::credit_keeper(
+        keeper,
+        config,
+        lobby,
+        due_at,
+        steps,
+        Clock::get()?.unix_timestamp,
+    )
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::LEN,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Must be the program's upgrade authority, so nobody can take over the
+    /// config by initializing it before the deployer does
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ BettingError::InvalidProgramData
+    )]
+    pub program: Program<'info, crate::program::PokerBetting>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ BettingError::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminAction<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AssignGovernance<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+    #[account(owner = SPL_GOVERNANCE_ID @ BettingError::InvalidGovernance)]
+    /// CHECK: Governance account of a realm; only its owning program is checked
+    pub governance: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExcess<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives swept SOL)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TreasurySpend<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, sends SOL via CPI)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Any account may receive treasury funds
+    pub recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CreateLobby<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + OwnerStats::LEN,
+        seeds = [OWNER_STATS_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub owner_stats: Account<'info, OwnerStats>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + OperatorProfile::LEN,
+        seeds = [OPERATOR_PROFILE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub operator_profile: Account<'info, OperatorProfile>,
+    #[account(
+        init,
+        payer = owner,
+        space = Lobby::space(&game_id),
+        seeds = [LOBBY_SEED, owner.key().as_ref(), &game_id_hash(&game_id)],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + OperatorBond::LEN,
+        seeds = [OPERATOR_BOND_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub bond: Account<'info, OperatorBond>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA for holding bet funds (will be created by System Program on first transfer)
+    pub escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// Lobby this one continues into overtime, when it is a continuation
+    #[account(
+        mut,
+        seeds = [
+            LOBBY_SEED,
+            parent_lobby.owner.as_ref(),
+            parent_lobby.game_id_hash.as_ref()
+        ],
+        bump
+    )]
+    pub parent_lobby: Option<Account<'info, Lobby>>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_name: String, amount: u64, min_expected_odds_bps: u32, side: BetSide)]
+pub struct PlaceBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + Bet::LEN,
+        seeds = [
+            side.bet_seed(),
+            lobby.key().as_ref(),
+            bettor.key().as_ref()
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLobbyStatus<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitTranscript<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    pub owner: Signer<'info>,
+}
+
+impl<'info> CreateLobby<'info> {
+    pub fn lobby_accounts(&mut self) -> LobbyAccounts<'_, 'info> {
+        LobbyAccounts {
+            config: &self.config,
+            owner_stats: &mut self.owner_stats,
+            operator_profile: &mut self.operator_profile,
+            lobby: &mut self.lobby,
+            bond: &mut self.bond,
+            owner: &self.owner,
+            escrow: self.escrow.to_account_info(),
+            system_program: &self.system_program,
+            parent_lobby: self.parent_lobby.as_mut(),
+        }
+    }
+}
+
+impl<'info> CreateLobbyFromTemplate<'info> {
+    pub fn lobby_accounts(&mut self) -> LobbyAccounts<'_, 'info> {
+        LobbyAccounts {
+            config: &self.config,
+            owner_stats: &mut self.owner_stats,
+            operator_profile: &mut self.operator_profile,
+            lobby: &mut self.lobby,
+            bond: &mut self.bond,
+            owner: &self.owner,
+            escrow: self.escrow.to_account_info(),
+            system_program: &self.system_program,
+            parent_lobby: None,
+        }
+    }
+}
+
+impl<'info> PlaceBet<'info> {
+    pub fn bet_accounts(&mut self) -> BetAccounts<'_, 'info> {
+        BetAccounts {
+            lobby: &mut self.lobby,
+            bet: &mut self.bet,
+            bettor: self.bettor.key(),
+            funder: Funder::Signer(self.bettor.to_account_info()),
+            bettor_stats: &mut self.bettor_stats,
+            self_exclusion: self.self_exclusion.to_account_info(),
+            denylist: self.denylist.to_account_info(),
+            escrow: self.escrow.to_account_info(),
+            config: &mut self.config,
+            treasury: self.treasury.to_account_info(),
+            attestation: self.attestation.as_ref(),
+            system_program: self.system_program.to_account_info(),
+            price_update: self.price_update.as_ref().map(|p| p.to_account_info()),
+            instructions: self.instructions.as_ref().map(|i| i.to_account_info()),
+        }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(winner_name: String)]
+pub struct DistributeSingleWinning<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [
+            bet.side.bet_seed(),
+            lobby.key().as_ref(),
+            bettor.key().as_ref()
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Bettor receives the funds unless the bet has a payout address
+    pub bettor: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Required only when the bet has a payout address; checked against it
+    pub payout_recipient: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    /// Required once the lobby's pools are locked
+    #[account(seeds = [SNAPSHOT_SEED, lobby.key().as_ref()], bump)]
+    pub snapshot: Option<Account<'info, PoolSnapshot>>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives payouts below the minimum)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeStakePool<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + StakePool::LEN,
+        seeds = [STAKE_POOL_SEED],
+        bump
+    )]
+    pub stake_pool: Account<'info, StakePool>,
+    pub stake_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [STAKE_VAULT_SEED],
+        bump,
+        token::mint = stake_mint,
+        token::authority = stake_pool
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [REVENUE_SEED],
+        bump
+    )]
+    /// CHECK: Revenue vault PDA (System Program owned, holds stakers' SOL rewards)
+    pub revenue_vault: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceEpoch<'info> {
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, sends SOL via CPI)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [REVENUE_SEED],
+        bump
+    )]
+    /// CHECK: Revenue vault PDA (System Program owned, receives SOL via CPI)
+    pub revenue_vault: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeProtocolToken<'info> {
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        init_if_needed,
+        payer = staker,
+        space = 8 + StakePosition::LEN,
+        seeds = [STAKE_SEED, staker.key().as_ref()],
+        bump
+    )]
+    pub stake_position: Account<'info, StakePosition>,
+    #[account(mut, address = stake_pool.vault)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = stake_pool.stake_mint,
+        token::authority = staker
+    )]
+    pub staker_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub staker: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeProtocolToken<'info> {
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [STAKE_SEED, staker.key().as_ref()],
+        bump
+    )]
+    pub stake_position: Account<'info, StakePosition>,
+    #[account(mut, address = stake_pool.vault)]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = stake_pool.stake_mint,
+        token::authority = staker
+    )]
+    pub staker_token_account: Account<'info, TokenAccount>,
+    pub staker: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ActivateStake<'info> {
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [STAKE_SEED, stake_position.owner.as_ref()],
+        bump
+    )]
+    pub stake_position: Account<'info, StakePosition>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRevenueShare<'info> {
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,
+    #[account(
+        mut,
+        seeds = [STAKE_SEED, staker.key().as_ref()],
+        bump
+    )]
+    pub stake_position: Account<'info, StakePosition>,
+    #[account(
+        mut,
+        seeds = [REVENUE_SEED],
+        bump
+    )]
+    /// CHECK: Revenue vault PDA (System Program owned, sends SOL via CPI)
+    pub revenue_vault: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub staker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(subject: Pubkey)]
+pub struct IssueAttestation<'info> {
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + Attestation::LEN,
+        seeds = [ATTESTATION_SEED, issuer.key().as_ref(), subject.as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAttestation<'info> {
+    #[account(
+        mut,
+        has_one = issuer @ BettingError::Unauthorized
+    )]
+    pub attestation: Account<'info, Attestation>,
+    pub issuer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SelfExclude<'info> {
+    #[account(
+        init_if_needed,
+        payer = wallet,
+        space = 8 + SelfExclusion::LEN,
+        seeds = [SELF_EXCLUSION_SEED, wallet.key().as_ref()],
+        bump
+    )]
+    pub self_exclusion: Account<'info, SelfExclusion>,
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetBettingLimits<'info> {
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PostMessage<'info> {
+    #[account(
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init_if_needed,
+        payer = author,
+        space = 8 + LobbyFeed::LEN,
+        seeds = [FEED_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub feed: Account<'info, LobbyFeed>,
+    #[account(mut)]
+    pub author: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BoostPrizePool<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(model_name: String)]
+pub struct RegisterModelOperator<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + MatchStake::LEN,
+        seeds = [MATCH_STAKE_SEED, lobby.key().as_ref(), model_name.as_bytes()],
+        bump
+    )]
+    pub match_stake: Account<'info, MatchStake>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(model_name: String)]
+pub struct RefundMatchStake<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [MATCH_STAKE_SEED, lobby.key().as_ref(), model_name.as_bytes()],
+        bump,
+        has_one = operator @ BettingError::InvalidMatchStake,
+        close = owner
+    )]
+    pub match_stake: Account<'info, MatchStake>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Operator the stake is refunded to
+    pub operator: UncheckedAccount<'info>,
+    #[account(mut, address = lobby.owner @ BettingError::Unauthorized)]
+    /// CHECK: Lobby owner, who paid the match stake account's rent
+    pub owner: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(model_name: String)]
+pub struct PostMatchStake<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [MATCH_STAKE_SEED, lobby.key().as_ref(), model_name.as_bytes()],
+        bump,
+        has_one = operator @ BettingError::Unauthorized
+    )]
+    pub match_stake: Account<'info, MatchStake>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinishGame<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [OPERATOR_PROFILE_SEED, lobby.owner.as_ref()],
+        bump
+    )]
+    pub operator_profile: Account<'info, OperatorProfile>,
+    /// Canonical game record written by the game server through the engine.
+    /// Not needed once the winner has been verified by proof or enclave attestation.
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Option<Account<'info, poker_engine::GameRecord>>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    /// Required only when operators posted match stakes
+    pub winner_stake: Option<Account<'info, MatchStake>>,
+    #[account(mut)]
+    /// CHECK: Checked against `winner_stake.operator`
+    pub winner_operator: Option<UncheckedAccount<'info>>,
+    pub system_program: Program<'info, System>,
+    /// Required once the lobby has a results authority
+    pub results_authority: Option<Signer<'info>>,
+    /// Required for a charity round whose pools are locked
+    #[account(seeds = [SNAPSHOT_SEED, lobby.key().as_ref()], bump)]
+    pub snapshot: Option<Account<'info, PoolSnapshot>>,
+    #[account(mut)]
+    /// CHECK: Checked against `lobby.charity`
+    pub charity: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateQuotedOdds<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    pub market_maker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FundBankroll<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, holds the bankroll)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub market_maker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: u64)]
+pub struct PostOrder<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + Order::LEN,
+        seeds = [
+            ORDER_SEED,
+            lobby.key().as_ref(),
+            maker.key().as_ref(),
+            &order_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        has_one = lobby @ BettingError::InvalidBetAccount,
+        has_one = maker @ BettingError::Unauthorized
+    )]
+    pub order: Account<'info, Order>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    #[account(
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut)]
+    pub back_order: Account<'info, Order>,
+    #[account(mut)]
+    pub lay_order: Account<'info, Order>,
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + MatchedPosition::LEN,
+        seeds = [
+            POSITION_SEED,
+            back_order.key().as_ref(),
+            lay_order.key().as_ref(),
+            &back_order.filled_stake.to_le_bytes()
+        ],
+        bump
+    )]
+    pub position: Account<'info, MatchedPosition>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePosition<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        has_one = lobby @ BettingError::InvalidBetAccount,
+        has_one = backer @ BettingError::InvalidBettor,
+        has_one = layer @ BettingError::InvalidBettor
+    )]
+    pub position: Account<'info, MatchedPosition>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Backer of the position, checked by `has_one`
+    pub backer: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Layer of the position, checked by `has_one`
+    pub layer: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_name: String, max_amount: u64, min_expected_odds_bps: u32, side: BetSide)]
+pub struct HedgePosition<'info> {
+    pub place: PlaceBet<'info>,
+    #[account(
+        seeds = [
+            side.opposite().bet_seed(),
+            place.lobby.key().as_ref(),
+            place.bettor.key().as_ref()
+        ],
+        bump
+    )]
+    pub existing_bet: Account<'info, Bet>,
+}
+
+#[derive(Accounts)]
+pub struct CreateConditionalBet<'info> {
+    #[account(
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ConditionalBet::LEN,
+        seeds = [CONDITIONAL_SEED, lobby.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub conditional_bet: Account<'info, ConditionalBet>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConditionalBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [CONDITIONAL_SEED, lobby.key().as_ref(), owner.key().as_ref()],
+        bump,
+        has_one = owner @ BettingError::InvalidBettor
+    )]
+    pub conditional_bet: Account<'info, ConditionalBet>,
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + Bet::LEN,
+        seeds = [
+            conditional_bet.side.bet_seed(),
+            lobby.key().as_ref(),
+            owner.key().as_ref()
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Owner of the conditional bet, checked by `has_one`
+    pub owner: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, owner.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Owner's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelConditionalBet<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub conditional_bet: Account<'info, ConditionalBet>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToVault<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + DepositVault::LEN,
+        seeds = [DEPOSIT_VAULT_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, DepositVault>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromVault<'info> {
+    #[account(
+        mut,
+        seeds = [DEPOSIT_VAULT_SEED, owner.key().as_ref()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub vault: Account<'info, DepositVault>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lobby_owner: Pubkey)]
+pub struct Subscribe<'info> {
+    #[account(
+        init,
+        payer = subscriber,
+        space = 8 + Subscription::LEN,
+        seeds = [SUBSCRIPTION_SEED, subscriber.key().as_ref(), lobby_owner.as_ref()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        close = subscriber,
+        has_one = subscriber @ BettingError::Unauthorized
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteSubscriptionBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SEED,
+            subscriber.key().as_ref(),
+            subscription.lobby_owner.as_ref()
+        ],
+        bump,
+        has_one = subscriber @ BettingError::InvalidBettor
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(
+        mut,
+        seeds = [DEPOSIT_VAULT_SEED, subscriber.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, DepositVault>,
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + Bet::LEN,
+        seeds = [BET_SEED, lobby.key().as_ref(), subscriber.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Subscriber, checked by `has_one`
+    pub subscriber: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, subscriber.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, subscriber.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Subscriber's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(leader: Pubkey)]
+pub struct Follow<'info> {
+    #[account(
+        init,
+        payer = follower,
+        space = 8 + FollowRecord::LEN,
+        seeds = [FOLLOW_SEED, follower.key().as_ref(), leader.as_ref()],
+        bump
+    )]
+    pub follow: Account<'info, FollowRecord>,
+    #[account(mut)]
+    pub follower: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFollow<'info> {
+    #[account(
+        mut,
+        has_one = follower @ BettingError::Unauthorized
+    )]
+    pub follow: Account<'info, FollowRecord>,
+    pub follower: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Unfollow<'info> {
+    #[account(
+        mut,
+        close = follower,
+        has_one = follower @ BettingError::Unauthorized
+    )]
+    pub follow: Account<'info, FollowRecord>,
+    #[account(mut)]
+    pub follower: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MirrorBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [FOLLOW_SEED, follower.key().as_ref(), follow.leader.as_ref()],
+        bump,
+        has_one = follower @ BettingError::InvalidBettor
+    )]
+    pub follow: Account<'info, FollowRecord>,
+    #[account(
+        seeds = [
+            leader_bet.side.bet_seed(),
+            lobby.key().as_ref(),
+            follow.leader.as_ref()
+        ],
+        bump
+    )]
+    pub leader_bet: Account<'info, Bet>,
+    #[account(
+        mut,
+        seeds = [DEPOSIT_VAULT_SEED, follower.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, DepositVault>,
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + Bet::LEN,
+        seeds = [
+            leader_bet.side.bet_seed(),
+            lobby.key().as_ref(),
+            follower.key().as_ref()
+        ],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Follower, checked by `has_one`
+    pub follower: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, follower.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, follower.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Follower's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct LockPools<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = caller,
+        space = 8 + PoolSnapshot::LEN,
+        seeds = [SNAPSHOT_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub snapshot: Account<'info, PoolSnapshot>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL from player pools)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetOutcomeVerifier<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + OutcomeVerifier::LEN,
+        seeds = [OUTCOME_VERIFIER_SEED],
+        bump
+    )]
+    pub verifier: Account<'info, OutcomeVerifier>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyOutcomeProof<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(seeds = [OUTCOME_VERIFIER_SEED], bump)]
+    pub verifier: Account<'info, OutcomeVerifier>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitAttestedResult<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: Instructions sysvar, read for the enclave's Ed25519 signature
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetBridgeConfig<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + BridgeConfig::LEN,
+        seeds = [BRIDGE_CONFIG_SEED],
+        bump
+    )]
+    pub bridge_config: Account<'info, BridgeConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(vaa_hash: [u8; 32], side: BetSide)]
+pub struct PlaceBetFromWormhole<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(seeds = [BRIDGE_CONFIG_SEED], bump)]
+    pub bridge_config: Account<'info, BridgeConfig>,
+    /// CHECK: Core bridge PostedVAA account; owner and address checked in the handler
+    pub posted_vaa: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + ConsumedVaa::LEN,
+        seeds = [CONSUMED_VAA_SEED, vaa_hash.as_ref()],
+        bump
+    )]
+    pub consumed_vaa: Account<'info, ConsumedVaa>,
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + Bet::LEN,
+        seeds = [side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Solana wallet named in the VAA payload
+    pub bettor: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutAddress<'info> {
+    #[account(
+        mut,
+        seeds = [bet.side.bet_seed(), bet.lobby.as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAll<'info> {
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives payouts below the minimum)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageDenylist<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + Denylist::LEN,
+        seeds = [DENYLIST_SEED],
+        bump
+    )]
+    pub denylist: Account<'info, Denylist>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceChipBucketBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + ChipBucketBet::LEN,
+        seeds = [CHIP_BET_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub chip_bet: Account<'info, ChipBucketBet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeChipBucketWinning<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    /// The lobby's own chip bet, or its parent's once the parent's chip market
+    /// is rolled into it
+    #[account(
+        mut,
+        seeds = [CHIP_BET_SEED, chip_bet.lobby.as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub chip_bet: Account<'info, ChipBucketBet>,
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Bettor that placed the chip bet; seeds tie it to `chip_bet`
+    pub bettor: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives payouts below the minimum)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PickFreeroll<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + FreerollPick::LEN,
+        seeds = [FREEROLL_PICK_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub pick: Account<'info, FreerollPick>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFreerollPrize<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [FREEROLL_PICK_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub pick: Account<'info, FreerollPick>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuySpectatorPass<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = viewer,
+        space = 8 + SpectatorPass::LEN,
+        seeds = [SPECTATOR_PASS_SEED, lobby.key().as_ref(), viewer.key().as_ref()],
+        bump
+    )]
+    pub pass: Account<'info, SpectatorPass>,
+    #[account(mut)]
+    pub viewer: Signer<'info>,
+    #[account(mut, address = lobby.owner)]
+    /// CHECK: Lobby owner, receives the pass fee net of the protocol share
+    pub owner: UncheckedAccount<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAchievementTree<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + AchievementConfig::LEN,
+        seeds = [ACHIEVEMENT_CONFIG_SEED],
+        bump
+    )]
+    pub achievement_config: Account<'info, AchievementConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(week: i64)]
+pub struct SubmitWeeklyPayout<'info> {
+    #[account(
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + WeeklyPayoutRecord::LEN,
+        seeds = [WEEKLY_PAYOUT_SEED, &week.to_le_bytes()],
+        bump
+    )]
+    pub weekly_record: Account<'info, WeeklyPayoutRecord>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(achievement: Achievement)]
+pub struct ClaimAchievement<'info> {
+    #[account(
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + AchievementClaim::LEN,
+        seeds = [ACHIEVEMENT_SEED, bettor.key().as_ref(), &achievement.seed()],
+        bump
+    )]
+    pub claim: Account<'info, AchievementClaim>,
+    /// Required for `BiggestPayoutOfWeek`
+    pub weekly_record: Option<Account<'info, WeeklyPayoutRecord>>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(seeds = [ACHIEVEMENT_CONFIG_SEED], bump)]
+    pub achievement_config: Account<'info, AchievementConfig>,
+    #[account(seeds = [ACHIEVEMENT_AUTHORITY_SEED], bump)]
+    /// CHECK: PDA that signs as the tree's creator or delegate
+    pub achievement_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum::BUBBLEGUM_ID
+    )]
+    /// CHECK: Bubblegum tree config, checked by Bubblegum
+    pub tree_config: UncheckedAccount<'info>,
+    #[account(mut, address = achievement_config.merkle_tree)]
+    /// CHECK: Achievement tree, checked by the compression program
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(address = bubblegum::BUBBLEGUM_ID)]
+    /// CHECK: Bubblegum program
+    pub bubblegum_program: UncheckedAccount<'info>,
+    #[account(address = compression::NOOP_ID)]
+    /// CHECK: SPL Noop program
+    pub log_wrapper: UncheckedAccount<'info>,
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: SPL Account Compression program
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSeason<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Season::LEN,
+        seeds = [SEASON_SEED, &config.season.to_le_bytes()],
+        bump
+    )]
+    pub season: Account<'info, Season>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengePayoutRoot<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        constraint = bet.lobby == lobby.key() && bet.bettor == challenger.key()
+            @ BettingError::InvalidBetAccount
+    )]
+    pub bet: Account<'info, Bet>,
+    pub challenger: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolvePayoutChallenge<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [OPERATOR_PROFILE_SEED, lobby.owner.as_ref()],
+        bump
+    )]
+    pub operator_profile: Account<'info, OperatorProfile>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithProof<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + MerkleClaim::LEN,
+        seeds = [MERKLE_CLAIM_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub claim: Account<'info, MerkleClaim>,
+    /// Any of the bettor's bets on the lobby; its payout address gets the claim
+    #[account(
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Required only when the bet has a payout address; checked against it
+    pub payout_recipient: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleClaimedBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        constraint = bet.lobby == lobby.key() @ BettingError::InvalidBetAccount
+    )]
+    pub bet: Account<'info, Bet>,
+    /// The bettor's claim against the payout root
+    #[account(seeds = [MERKLE_CLAIM_SEED, lobby.key().as_ref(), bet.bettor.as_ref()], bump)]
+    pub claim: Account<'info, MerkleClaim>,
+}
+
+#[derive(Accounts)]
+pub struct InitBetTree<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    pub owner: Signer<'info>,
+    #[account(mut, owner = compression::ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: Allocated, uninitialized tree; initialized by the compression program
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(seeds = [BET_TREE_AUTHORITY_SEED], bump)]
+    /// CHECK: PDA that signs as the bet trees' authority
+    pub tree_authority: UncheckedAccount<'info>,
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: SPL Account Compression program
+    pub compression_program: UncheckedAccount<'info>,
+    #[account(address = compression::NOOP_ID)]
+    /// CHECK: SPL Noop program
+    pub log_wrapper: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceCompressedBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    #[account(
+        mut,
+        constraint = lobby.bet_tree == Some(merkle_tree.key()) @ BettingError::CompressedBetsUnsupported
+    )]
+    /// CHECK: The lobby's bet tree
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(seeds = [BET_TREE_AUTHORITY_SEED], bump)]
+    /// CHECK: PDA that signs as the bet trees' authority
+    pub tree_authority: UncheckedAccount<'info>,
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: SPL Account Compression program
+    pub compression_program: UncheckedAccount<'info>,
+    #[account(address = compression::NOOP_ID)]
+    /// CHECK: SPL Noop program
+    pub log_wrapper: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCompressedBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    /// Required once the lobby's pools are locked
+    #[account(seeds = [SNAPSHOT_SEED, lobby.key().as_ref()], bump)]
+    pub snapshot: Option<Account<'info, PoolSnapshot>>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives payouts below the minimum)
+    pub treasury: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = lobby.bet_tree == Some(merkle_tree.key()) @ BettingError::CompressedBetsUnsupported
+    )]
+    /// CHECK: The lobby's bet tree
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(seeds = [BET_TREE_AUTHORITY_SEED], bump)]
+    /// CHECK: PDA that signs as the bet trees' authority
+    pub tree_authority: UncheckedAccount<'info>,
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: SPL Account Compression program
+    pub compression_program: UncheckedAccount<'info>,
+    #[account(address = compression::NOOP_ID)]
+    /// CHECK: SPL Noop program
+    pub log_wrapper: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetShieldedVerifier<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + OutcomeVerifier::space(shielded::PUBLIC_INPUTS),
+        seeds = [SHIELDED_VERIFIER_SEED],
+        bump
+    )]
+    pub verifier: Account<'info, OutcomeVerifier>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeShieldedPool<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ShieldedPool::LEN,
+        seeds = [SHIELDED_POOL_SEED],
+        bump
+    )]
+    pub pool: Account<'info, ShieldedPool>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ShieldedDeposit<'info> {
+    #[account(mut, seeds = [SHIELDED_POOL_SEED], bump)]
+    pub pool: Account<'info, ShieldedPool>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32], root: [u8; 32], proof: groth16::Proof, player_name: String, side: BetSide)]
+pub struct PlaceShieldedBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut, seeds = [SHIELDED_POOL_SEED], bump)]
+    pub pool: Account<'info, ShieldedPool>,
+    #[account(seeds = [SHIELDED_VERIFIER_SEED], bump)]
+    pub verifier: Account<'info, OutcomeVerifier>,
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + Nullifier::LEN,
+        seeds = [NULLIFIER_SEED, nullifier_hash.as_ref()],
+        bump
+    )]
+    pub nullifier: Account<'info, Nullifier>,
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + Bet::LEN,
+        seeds = [side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    /// CHECK: Wallet the bet is placed for; bound by the proof
+    pub bettor: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBlindBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + BlindBet::LEN,
+        seeds = [BLIND_BET_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub blind_bet: Account<'info, BlindBet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct RevealBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [BLIND_BET_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub blind_bet: Account<'info, BlindBet>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::LEN,
+        seeds = [BetSide::Back.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundUnrevealedBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [BLIND_BET_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub blind_bet: Account<'info, BlindBet>,
+    #[account(mut)]
+    /// CHECK: Bettor that placed the blind bet; seeds tie it to `blind_bet`
+    pub bettor: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives the penalty)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// Keeper credited the crank fee, signed for by its authority
+    #[account(mut, seeds = [KEEPER_SEED, keeper.authority.as_ref()], bump)]
+    pub keeper: Option<Account<'info, Keeper>>,
+    pub keeper_authority: Option<Signer<'info>>,
+    /// Required with `keeper`
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Option<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct SetBountyVerifier<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + OutcomeVerifier::space(BOUNTY_PUBLIC_INPUTS),
+        seeds = [BOUNTY_VERIFIER_SEED],
+        bump
+    )]
+    pub verifier: Account<'info, OutcomeVerifier>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(bounty_id: u64)]
+pub struct CreateBounty<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = funder,
+        space = 8 + Bounty::LEN,
+        seeds = [BOUNTY_SEED, lobby.key().as_ref(), &bounty_id.to_le_bytes()],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+    #[account(
+        init,
+        payer = funder,
+        space = 8 + BountyContribution::LEN,
+        seeds = [BOUNTY_CONTRIBUTION_SEED, bounty.key().as_ref(), funder.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, BountyContribution>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundBounty<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [BOUNTY_SEED, lobby.key().as_ref(), &bounty.bounty_id.to_le_bytes()],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + BountyContribution::LEN,
+        seeds = [BOUNTY_CONTRIBUTION_SEED, bounty.key().as_ref(), funder.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, BountyContribution>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBounty<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [BOUNTY_SEED, lobby.key().as_ref(), &bounty.bounty_id.to_le_bytes()],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+    #[account(
+        seeds = [b"engine"],
+        bump,
+        seeds::program = poker_engine::ID,
+        has_one = authority @ BettingError::Unauthorized
+    )]
+    pub engine: Account<'info, poker_engine::EngineConfig>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    #[account(
+        seeds = [b"hand", engine_game.key().as_ref(), &hand.hand_number.to_le_bytes()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub hand: Account<'info, poker_engine::HandRecord>,
+    /// Required only for `BountyEvent::Proven`
+    #[account(seeds = [BOUNTY_VERIFIER_SEED], bump)]
+    pub verifier: Option<Account<'info, OutcomeVerifier>>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Receives the bounty; chosen by the game server
+    pub recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundBounty<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [BOUNTY_SEED, lobby.key().as_ref(), &bounty.bounty_id.to_le_bytes()],
+        bump
+    )]
+    pub bounty: Account<'info, Bounty>,
+    #[account(
+        mut,
+        close = funder,
+        seeds = [BOUNTY_CONTRIBUTION_SEED, bounty.key().as_ref(), funder.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, BountyContribution>,
+    #[account(mut)]
+    /// CHECK: Funder of the contribution; seeds tie it to `contribution`
+    pub funder: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PostBond<'info> {
+    #[account(
+        mut,
+        seeds = [OPERATOR_BOND_SEED, bond.lobby.as_ref()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub bond: Account<'info, OperatorBond>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SlashBond<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [OPERATOR_BOND_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub bond: Account<'info, OperatorBond>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// Keeper credited the crank fee, signed for by its authority
+    #[account(mut, seeds = [KEEPER_SEED, keeper.authority.as_ref()], bump)]
+    pub keeper: Option<Account<'info, Keeper>>,
+    pub keeper_authority: Option<Signer<'info>>,
+    /// Required with `keeper`
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Option<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBondCompensation<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + BondClaim::LEN,
+        seeds = [BOND_CLAIM_SEED, bet.key().as_ref()],
+        bump
+    )]
+    pub claim: Account<'info, BondClaim>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Required only when the bet has a payout address; checked against it
+    pub payout_recipient: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBond<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        close = owner,
+        seeds = [OPERATOR_BOND_SEED, lobby.key().as_ref()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub bond: Account<'info, OperatorBond>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(model_index: u8)]
+pub struct OpenPlayerPool<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PlayerPool::LEN,
+        seeds = [PLAYER_POOL_SEED, lobby.key().as_ref(), &[model_index]],
+        bump
+    )]
+    pub pool: Account<'info, PlayerPool>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlacePooledBet<'info> {
+    #[account(
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [PLAYER_POOL_SEED, lobby.key().as_ref(), &[pool.model_index]],
+        bump,
+        has_one = lobby
+    )]
+    pub pool: Account<'info, PlayerPool>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::LEN,
+        seeds = [BET_SEED, lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds or the bettor's USD caps apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct AggregatePlayerPools<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL from player pools)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBetStatus<'info> {
+    #[account(mut, seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bet.bettor.as_ref()],
+        bump,
+        has_one = lobby @ BettingError::InvalidBetAccount
+    )]
+    pub bet: Account<'info, Bet>,
+}
+
+#[derive(Accounts)]
+pub struct CashOutBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// Credited with the returned stake's USD value when passed
+    #[account(
+        mut,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Option<Account<'info, BettorStats>>,
+}
+
+#[derive(Accounts)]
+pub struct SettleLosingBets<'info> {
+    #[account(mut, seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    /// Keeper credited the crank fee, signed for by its authority
+    #[account(mut, seeds = [KEEPER_SEED, keeper.authority.as_ref()], bump)]
+    pub keeper: Option<Account<'info, Keeper>>,
+    pub keeper_authority: Option<Signer<'info>>,
+    /// Required with `keeper`
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Option<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnwonPool<'info> {
+    #[account(mut, seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    /// Required once the lobby's pools are locked
+    #[account(seeds = [SNAPSHOT_SEED, lobby.key().as_ref()], bump)]
+    pub snapshot: Option<Account<'info, PoolSnapshot>>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives swept SOL)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// Keeper credited the crank fee, signed for by its authority
+    #[account(mut, seeds = [KEEPER_SEED, keeper.authority.as_ref()], bump)]
+    pub keeper: Option<Account<'info, Keeper>>,
+    pub keeper_authority: Option<Signer<'info>>,
+    /// Required with `keeper`
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Option<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBet<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        close = bettor,
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    /// CHECK: The bet's bettor, who gets its rent back
+    pub bettor: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyProgramAuthority<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ BettingError::InvalidProgramData
+    )]
+    pub program: Program<'info, crate::program::PokerBetting>,
+    pub program_data: Account<'info, ProgramData>,
+}
+
+#[derive(Accounts)]
+pub struct ViewLobby<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+}
+
+#[derive(Accounts)]
+pub struct GetImpliedOdds<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(bettor: Pubkey)]
+pub struct GetClaimableAmount<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    /// CHECK: the bettor's back bet, if placed
+    #[account(
+        seeds = [BetSide::Back.bet_seed(), lobby.key().as_ref(), bettor.as_ref()],
+        bump
+    )]
+    pub back_bet: UncheckedAccount<'info>,
+    /// CHECK: the bettor's lay bet, if placed
+    #[account(
+        seeds = [BetSide::Lay.bet_seed(), lobby.key().as_ref(), bettor.as_ref()],
+        bump
+    )]
+    pub lay_bet: UncheckedAccount<'info>,
+    /// Required once the lobby's pools are locked
+    #[account(seeds = [SNAPSHOT_SEED, lobby.key().as_ref()], bump)]
+    pub snapshot: Option<Account<'info, PoolSnapshot>>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct BeginBetSwap<'info> {
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + SwapReceipt::LEN,
+        seeds = [SWAP_RECEIPT_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub swap_receipt: Account<'info, SwapReceipt>,
+    #[account(
+        token::mint = native_mint::ID,
+        token::authority = bettor
+    )]
+    pub wsol_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_name: String, min_output: u64, min_expected_odds_bps: u32, side: BetSide)]
+pub struct PlaceBetWithSwap<'info> {
+    pub place: PlaceBet<'info>,
+    #[account(
+        mut,
+        seeds = [SWAP_RECEIPT_SEED, place.bettor.key().as_ref()],
+        bump
+    )]
+    pub swap_receipt: Account<'info, SwapReceipt>,
+    #[account(
+        mut,
+        token::mint = native_mint::ID,
+        token::authority = place.bettor
+    )]
+    pub wsol_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SweepToCustody<'info> {
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Checked against `config.custody`
+    pub custody: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundSettlement<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub custody: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: u64)]
+pub struct CreateStreakSeries<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + StreakSeries::LEN,
+        seeds = [STREAK_SERIES_SEED, authority.key().as_ref(), &series_id.to_le_bytes()],
+        bump
+    )]
+    pub series: Account<'info, StreakSeries>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddStreakLobby<'info> {
+    #[account(
+        mut,
+        seeds = [STREAK_SERIES_SEED, authority.key().as_ref(), &series.series_id.to_le_bytes()],
+        bump,
+        has_one = authority @ BettingError::Unauthorized
+    )]
+    pub series: Account<'info, StreakSeries>,
+    pub authority: Signer<'info>,
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+}
+
+#[derive(Accounts)]
+pub struct FundStreakSeries<'info> {
+    #[account(
+        mut,
+        seeds = [STREAK_SERIES_SEED, series.authority.as_ref(), &series.series_id.to_le_bytes()],
+        bump
+    )]
+    pub series: Account<'info, StreakSeries>,
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordStreakPick<'info> {
+    #[account(
+        seeds = [STREAK_SERIES_SEED, series.authority.as_ref(), &series.series_id.to_le_bytes()],
+        bump
+    )]
+    pub series: Account<'info, StreakSeries>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + Streak::LEN,
+        seeds = [STREAK_SEED, series.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub streak: Account<'info, Streak>,
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    /// The bettor's back bet on `lobby`
+    #[account(
+        seeds = [BetSide::Back.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimStreakPrize<'info> {
+    #[account(
+        mut,
+        seeds = [STREAK_SERIES_SEED, series.authority.as_ref(), &series.series_id.to_le_bytes()],
+        bump
+    )]
+    pub series: Account<'info, StreakSeries>,
+    #[account(
+        mut,
+        seeds = [STREAK_SEED, series.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub streak: Account<'info, Streak>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnterRaffle<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestRaffle<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct RunRaffle<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    /// CHECK: ORAO VRF request for `lobby.raffle_seed`; checked in `vrf`
+    pub randomness: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRafflePrize<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, sends SOL via CPI)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChangeBetSelection<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives SOL)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DisqualifyModel<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    pub owner: Signer<'info>,
+    /// Required once pools are locked
+    #[account(
+        mut,
+        seeds = [SNAPSHOT_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub snapshot: Option<Account<'info, PoolSnapshot>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundVoidedBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [bet.side.bet_seed(), lobby.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    /// CHECK: Bettor receives the refund unless the bet has a payout address
+    pub bettor: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Required only when the bet has a payout address; checked against it
+    pub payout_recipient: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
+    pub escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RollChipMarketForward<'info> {
+    #[account(
+        mut,
+        seeds = [
+            LOBBY_SEED,
+            parent_lobby.owner.as_ref(),
+            parent_lobby.game_id_hash.as_ref()
+        ],
+        bump
+    )]
+    pub parent_lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, parent_lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Parent's escrow PDA (System Program owned, sends SOL via CPI)
+    pub parent_escrow: UncheckedAccount<'info>,
+    /// The parent's continuation
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        constraint = lobby.parent_lobby == Some(parent_lobby.key()) @ BettingError::InvalidParentLobby
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA (System Program owned, receives SOL)
+    pub escrow: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeGameConfig<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + GameConfig::LEN,
+        seeds = [GAME_CONFIG_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub game_config: Account<'info, GameConfig>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CountReimbursedHand<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    #[account(
+        seeds = [b"hand", engine_game.key().as_ref(), &hand.hand_number.to_le_bytes()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub hand: Account<'info, poker_engine::HandRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(model_name: String)]
+pub struct ClaimOperatorReimbursement<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    #[account(
+        seeds = [MATCH_STAKE_SEED, lobby.key().as_ref(), model_name.as_bytes()],
+        bump,
+        has_one = operator @ BettingError::Unauthorized
+    )]
+    pub match_stake: Account<'info, MatchStake>,
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, sends SOL via CPI)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseOperatorReimbursement<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// Keeper credited the crank fee, signed for by its authority
+    #[account(mut, seeds = [KEEPER_SEED, keeper.authority.as_ref()], bump)]
+    pub keeper: Option<Account<'info, Keeper>>,
+    pub keeper_authority: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(hand_number: u64)]
+pub struct OpenHandMarket<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + HandMarket::LEN,
+        seeds = [HAND_MARKET_SEED, lobby.key().as_ref(), &hand_number.to_le_bytes()],
+        bump
+    )]
+    pub hand_market: Account<'info, HandMarket>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceHandBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    #[account(
+        mut,
+        seeds = [HAND_MARKET_SEED, lobby.key().as_ref(), &hand_market.hand_number.to_le_bytes()],
+        bump
+    )]
+    pub hand_market: Account<'info, HandMarket>,
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + HandBet::LEN,
+        seeds = [HAND_BET_SEED, hand_market.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub hand_bet: Account<'info, HandBet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + BettorStats::LEN,
+        seeds = [BETTOR_STATS_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    pub bettor_stats: Account<'info, BettorStats>,
+    #[account(
+        seeds = [SELF_EXCLUSION_SEED, bettor.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Bettor's self-exclusion PDA; may be uninitialized if never set
+    pub self_exclusion: UncheckedAccount<'info>,
+    #[account(seeds = [DENYLIST_SEED], bump)]
+    /// CHECK: Screening denylist PDA; may be uninitialized if never set
+    pub denylist: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [HAND_ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Hand escrow PDA (System Program owned, receives SOL via CPI)
+    pub hand_escrow: UncheckedAccount<'info>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives protocol fees)
+    pub treasury: UncheckedAccount<'info>,
+    /// Required only when the lobby is gated on an attestation issuer
+    pub attestation: Option<Account<'info, Attestation>>,
+    pub system_program: Program<'info, System>,
+    /// Required only while `config.guard_bet_composition` is on
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read to inspect the rest of the transaction
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Required only once USD bet bounds apply
+    /// CHECK: Pyth price update, checked against `config.usd_price_feed`
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CheckpointSettle<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    #[account(seeds = [HAND_ESCROW_SEED, lobby.key().as_ref()], bump)]
+    /// CHECK: Hand escrow PDA; only its balance is read
+    pub hand_escrow: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoidUnplayedHandMarkets<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHandBet<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        mut,
+        seeds = [HAND_MARKET_SEED, lobby.key().as_ref(), &hand_market.hand_number.to_le_bytes()],
+        bump
+    )]
+    pub hand_market: Account<'info, HandMarket>,
+    #[account(
+        mut,
+        seeds = [HAND_BET_SEED, hand_market.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        has_one = bettor @ BettingError::InvalidBettor
+    )]
+    pub hand_bet: Account<'info, HandBet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [HAND_ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Hand escrow PDA (System Program owned, sends SOL via CPI)
+    pub hand_escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ArchiveLobby<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(mut, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseHandRecord<'info> {
+    #[account(
+        seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    /// CHECK: The engine checks its config and authority
+    pub engine: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"game", lobby.owner.as_ref(), lobby.game_id_hash.as_ref()],
+        bump,
+        seeds::program = poker_engine::ID
+    )]
+    pub engine_game: Account<'info, poker_engine::GameRecord>,
+    /// CHECK: The engine checks it is a hand record of `engine_game`
+    #[account(mut)]
+    pub hand: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub engine_program: Program<'info, poker_engine::program::PokerEngine>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterKeeper<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Keeper::LEN,
+        seeds = [KEEPER_SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub keeper: Account<'info, Keeper>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimKeeperFees<'info> {
+    #[account(
+        mut,
+        seeds = [KEEPER_SEED, authority.key().as_ref()],
+        bump,
+        has_one = authority @ BettingError::Unauthorized
+    )]
+    pub keeper: Account<'info, Keeper>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, pays the fees)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitKeeper<'info> {
+    #[account(
+        mut,
+        seeds = [KEEPER_SEED, authority.key().as_ref()],
+        bump,
+        has_one = authority @ BettingError::Unauthorized
+    )]
+    pub keeper: Account<'info, Keeper>,
+    pub authority: Signer<'info>,
+    #[account(seeds = [LOBBY_SEED, lobby.owner.as_ref(), lobby.game_id_hash.as_ref()], bump)]
+    pub lobby: Account<'info, Lobby>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SlashKeeper<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        has_one = admin @ BettingError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [KEEPER_SEED, keeper.authority.as_ref()], bump)]
+    pub keeper: Account<'info, Keeper>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    /// CHECK: Treasury PDA (System Program owned, receives the slashed stake)
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RetireKeeper<'info> {
+    #[account(
+        mut,
+        seeds = [KEEPER_SEED, authority.key().as_ref()],
+        bump,
+        has_one = authority @ BettingError::Unauthorized
+    )]
+    pub keeper: Account<'info, Keeper>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawKeeperStake<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [KEEPER_SEED, authority.key().as_ref()],
+        bump,
+        has_one = authority @ BettingError::Unauthorized
+    )]
+    pub keeper: Account<'info, Keeper>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CreateLobbyTemplate<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + LobbyTemplate::LEN,
+        seeds = [LOBBY_TEMPLATE_SEED, owner.key().as_ref(), name.as_bytes()],
+        bump
+    )]
+    pub template: Account<'info, LobbyTemplate>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CreateLobbyFromTemplate<'info> {
+    #[account(
+        mut,
+        seeds = [LOBBY_TEMPLATE_SEED, owner.key().as_ref(), template.name.as_bytes()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub template: Account<'info, LobbyTemplate>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + OwnerStats::LEN,
+        seeds = [OWNER_STATS_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub owner_stats: Account<'info, OwnerStats>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + OperatorProfile::LEN,
+        seeds = [OPERATOR_PROFILE_SEED, owner.key().as_ref()],
+        bump
+    )]
+    pub operator_profile: Account<'info, OperatorProfile>,
+    #[account(
+        init,
+        payer = owner,
+        space = Lobby::space(&game_id),
+        seeds = [LOBBY_SEED, owner.key().as_ref(), &game_id_hash(&game_id)],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + OperatorBond::LEN,
+        seeds = [OPERATOR_BOND_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    pub bond: Account<'info, OperatorBond>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ESCROW_SEED, lobby.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Escrow PDA for holding bet funds (will be created by System Program on first transfer)
+    pub escrow: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseLobbyTemplate<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [LOBBY_TEMPLATE_SEED, owner.key().as_ref(), template.name.as_bytes()],
+        bump,
+        has_one = owner @ BettingError::Unauthorized
+    )]
+    pub template: Account<'info, LobbyTemplate>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub fee_bps: u16,
+    pub paused: bool,
+    pub revenue_share_bps: u16,
+    pub accrued_revenue_share: u64,
+    pub max_bets_per_window: u32,
+    pub bet_window: i64,
+    pub lobby_creation_cooldown: i64,
+    pub compliance_mode: ComplianceMode,
+    pub restricted_regions: Vec<[u8; 2]>,
+    pub attestation_required: bool,
+    pub updated_at: i64,
+    /// Expected enclave measurement (MRENCLAVE or Nitro PCR digest) of the game server
+    pub enclave_measurement: Option<[u8; 32]>,
+    /// Signing key generated inside that enclave and bound to it by its attestation
+    pub enclave_signer: Option<Pubkey>,
+    /// Winnings below this are swept to the treasury rather than paid out
+    pub min_payout_lamports: u64,
+    /// Season points currently accrue to
+    pub season: u32,
+    /// Points per lamport wagered, in basis points
+    pub volume_points_bps: u16,
+    /// Points per lamport won, in basis points
+    pub win_points_bps: u16,
+    /// Bond a lobby owner posts at `create_lobby`
+    pub min_operator_bond: u64,
+    /// Time after creation a lobby must finish by before its bond can be slashed;
+    /// zero for no deadline
+    pub settlement_window: i64,
+    /// `OperatorProfile::reputation` an owner needs to create a lobby
+    pub min_operator_reputation: u64,
+    /// Discounted fees by 30-day volume, ascending by `min_volume`
+    pub fee_tiers: Vec<FeeTier>,
+    /// Rejects `place_bet` in a transaction that also settles or changes the
+    /// status of a lobby
+    pub guard_bet_composition: bool,
+    /// Delay between proposing and executing a `ConfigChange`; zero leaves the
+    /// direct setters usable
+    pub admin_timelock: i64,
+    pub pending_change: Option<ConfigChange>,
+    /// When `pending_change` may be executed
+    pub pending_change_eta: i64,
+    /// Upgrade authority the program is expected to have
+    pub expected_upgrade_authority: Option<Pubkey>,
+    /// The program is expected to be immutable
+    pub program_frozen: bool,
+    /// Pyth feed id of SOL/USD; USD bounds and caps are off without it
+    pub usd_price_feed: Option<[u8; 32]>,
+    /// Oldest price update accepted, in seconds
+    pub max_price_age: i64,
+    /// Widest confidence interval accepted, in basis points of the price
+    pub max_price_conf_bps: u16,
+    /// Smallest and largest bet in micro-USD; zero disables a bound
+    pub min_bet_usd: u64,
+    pub max_bet_usd: u64,
+    /// Cold custody account, e.g. a Squads vault, that escrow above
+    /// `custody_threshold` is swept into while a lobby runs
+    pub custody: Option<Pubkey>,
+    pub custody_threshold: u64,
+    /// Treasury lamports owed to lobbies' rake recipients and raffles
+    pub reserved_rake: u64,
+    /// Share of net fees that funds each lobby's raffle, in basis points
+    pub raffle_bps: u16,
+    /// Fee on the stake for switching a bet's model, in basis points
+    pub selection_change_fee_bps: u16,
+    /// Stake a keeper needs to earn crank fees
+    pub min_keeper_stake: u64,
+    /// Lamports a keeper earns per crank step, paid from the treasury
+    pub keeper_fee: u64,
+}
+
+impl Config {
+    pub const LEN: usize = 32 + 2 + 1 + 2 + 8 + 4 + 8 + 8 + 1 + (4 + 2 * MAX_RESTRICTED_REGIONS) + 1 + 8
+        + (1 + 32) + (1 + 32) + 8 + 4 + 2 + 2 + 8 + 8 + 8
+        + (4 + FeeTier::LEN * MAX_FEE_TIERS) + 1 + 8 + (1 + ConfigChange::LEN) + 8
+        + (1 + 32) + 1 + (1 + 32) + 8 + 2 + 8 + 8 + (1 + 32) + 8 + 8 + 2 + 2 + 8 + 8;
+
+    /// Index and fee of the highest tier `volume` reaches.
+    pub fn fee_tier(&self, volume: u64) -> Option<(u8, u16)> {
+        self.fee_tiers
+            .iter()
+            .rposition(|tier| volume >= tier.min_volume)
+            .map(|index| (index as u8, self.fee_tiers[index].fee_bps))
+    }
+
+    /// Fee a bettor with `volume` over the trailing 30 days pays.
+    pub fn bettor_fee_bps(&self, volume: u64) -> u16 {
+        self.fee_tier(volume).map_or(self.fee_bps, |(_, fee_bps)| fee_bps)
+    }
+}
+
+/// Wrapped SOL balance recorded by `begin_bet_swap`, at
+/// `["swap_receipt", bettor]`. Closed by the `place_bet_with_swap` that reads it.
+#[account]
+pub struct SwapReceipt {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub token_account: Pubkey,
+    pub balance_before: u64,
+    /// A receipt is only good in the slot it was recorded in
+    pub slot: u64,
+}
+
+impl SwapReceipt {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8;
+}
+
+#[account]
+pub struct BettorStats {
+    pub bettor: Pubkey,
+    pub window_started_at: i64,
+    pub bets_in_window: u32,
+    pub total_bets: u64,
+    pub total_wagered: u64,
+    pub last_bet_at: i64,
+    pub limits: BettingLimits,
+    pub pending_limits: Option<BettingLimits>,
+    pub pending_limits_at: i64,
+    pub day_started_at: i64,
+    pub wagered_today: u64,
+    pub lost_today: u64,
+    pub week_started_at: i64,
+    pub wagered_this_week: u64,
+    pub lost_this_week: u64,
+    /// Bets that paid out
+    pub wins: u64,
+    /// Calendar week of `best_payout`
+    pub best_payout_week: i64,
+    pub best_payout: u64,
+    /// Season `season_points` were earned in
+    pub season: u32,
+    pub season_points: u64,
+    pub month_started_at: i64,
+    /// Volume in the 30-day window from `month_started_at`
+    pub wagered_this_month: u64,
+    /// Caps in micro-USD, checked against the lamport totals at each bet's price
+    pub usd_limits: BettingLimits,
+    pub pending_usd_limits: Option<BettingLimits>,
+    pub pending_usd_limits_at: i64,
+    /// Micro-USD wagered on bets placed with a price update
+    pub usd_wagered: u64,
+    /// Micro-USD returned on those bets less `usd_wagered`, each bet valued at
+    /// its placement price
+    pub usd_pnl: i64,
+    /// Volume in the 30-day window before `month_started_at`
+    pub wagered_last_month: u64,
+}
+
+impl BettorStats {
+    pub const LEN: usize = 32 + 8 + 4 + 8 + 8 + 8
+        + BettingLimits::LEN + (1 + BettingLimits::LEN) + 8
+        + 8 + 8 + 8 + 8 + 8 + 8
+        + 8 + 8 + 8 + 4 + 8 + 8 + 8
+        + BettingLimits::LEN + (1 + BettingLimits::LEN) + 8 + 8 + 8 + 8;
+
+    const DAY: i64 = 24 * 60 * 60;
+    pub const WEEK: i64 = 7 * Self::DAY;
+    const MONTH: i64 = 30 * Self::DAY;
+
+    fn roll_limit_windows(&mut self, now: i64) {
+        if now >= self.day_started_at + Self::DAY {
+            self.day_started_at = now;
+            self.wagered_today = 0;
+            self.lost_today = 0;
+        }
+        if now >= self.week_started_at + Self::WEEK {
+            self.week_started_at = now;
+            self.wagered_this_week = 0;
+            self.lost_this_week = 0;
+        }
+        let elapsed = now - self.month_started_at;
+        if elapsed >= Self::MONTH {
+            self.wagered_last_month = if elapsed < 2 * Self::MONTH {
+                self.wagered_this_month
+            } else {
+                0
+            };
+            self.wagered_this_month = 0;
+            self.month_started_at = now - elapsed % Self::MONTH;
+        }
+    }
+
+    /// Volume over the trailing 30 days, which sets the fee tier. The previous
+    /// window's volume is taken as spread evenly over it, so the total slides
+    /// rather than dropping to zero when a window ends.
+    pub fn rolling_monthly_volume(&self, now: i64) -> Result<u64> {
+        let elapsed = now - self.month_started_at;
+        let (current, previous, into) = match elapsed / Self::MONTH {
+            0 => (self.wagered_this_month, self.wagered_last_month, elapsed),
+            1 => (0, self.wagered_this_month, elapsed - Self::MONTH),
+            _ => return Ok(0),
+        };
+        let carried = math::mul_div(previous, (Self::MONTH - into) as u64, Self::MONTH as u64)?;
+        current.checked_add(carried).ok_or(BettingError::Overflow.into())
+    }
+
+    fn apply_pending_limits(&mut self, now: i64) {
+        if now >= self.pending_limits_at {
+            if let Some(limits) = self.pending_limits.take() {
+                self.limits = limits;
+            }
+        }
+        if now >= self.pending_usd_limits_at {
+            if let Some(limits) = self.pending_usd_limits.take() {
+                self.usd_limits = limits;
+            }
+        }
+    }
+
+    /// Counts a new stake against the wager caps and, until it is paid out,
+    /// against the loss caps.
+    fn record_wager(&mut self, amount: u64) -> Result<()> {
+        self.wagered_today = self.wagered_today.checked_add(amount).ok_or(BettingError::Overflow)?;
+        self.wagered_this_week = self
+            .wagered_this_week
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        self.wagered_this_month = self
+            .wagered_this_month
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        self.lost_today = self.lost_today.checked_add(amount).ok_or(BettingError::Overflow)?;
+        self.lost_this_week = self.lost_this_week.checked_add(amount).ok_or(BettingError::Overflow)?;
+        self.check_limits(&self.limits)
+    }
+
+    /// Fails if the day's or week's totals exceed `limits`, given in lamports.
+    fn check_limits(&self, limits: &BettingLimits) -> Result<()> {
+        let within = |limit: u64, used: u64| limit == 0 || used <= limit;
+        require!(
+            within(limits.daily_wager, self.wagered_today)
+                && within(limits.weekly_wager, self.wagered_this_week),
+            BettingError::WagerLimitExceeded
+        );
+        require!(
+            within(limits.daily_loss, self.lost_today)
+                && within(limits.weekly_loss, self.lost_this_week),
+            BettingError::LossLimitExceeded
+        );
+        Ok(())
+    }
+
+    /// Credits a payout against the loss caps and counts it towards the
+    /// bettor's wins, best payout of the calendar week and season points.
+    fn record_payout(&mut self, amount: u64, now: i64, config: &Config) -> Result<()> {
+        self.lost_today = self.lost_today.saturating_sub(amount);
+        self.lost_this_week = self.lost_this_week.saturating_sub(amount);
+        if amount == 0 {
+            return Ok(());
+        }
+        self.accrue_points(config.season, math::apply_bps(amount, config.win_points_bps as u64)?)?;
+        self.wins = self.wins.saturating_add(1);
+        let week = calendar_week(now);
+        if week != self.best_payout_week {
+            self.best_payout_week = week;
+            self.best_payout = 0;
+        }
+        self.best_payout = self.best_payout.max(amount);
+        Ok(())
+    }
+
+    /// Credits `payout` of `bet` to the USD P&L at the bet's placement price.
+    fn record_usd_payout(&mut self, bet: &Bet, payout: u64) -> Result<()> {
+        self.usd_pnl = self
+            .usd_pnl
+            .checked_add(bet.usd(payout)? as i64)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+
+    /// Adds points to `season`, starting over if the stats were last active in
+    /// an earlier season.
+    fn accrue_points(&mut self, season: u32, points: u64) -> Result<()> {
+        if self.season != season {
+            self.season = season;
+            self.season_points = 0;
+        }
+        self.season_points = self
+            .season_points
+            .checked_add(points)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+}
+
+/// Fee charged to bettors with at least `min_volume` wagered in 30 days.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct FeeTier {
+    pub min_volume: u64,
+    pub fee_bps: u16,
+}
+
+impl FeeTier {
+    pub const LEN: usize = 8 + 2;
+}
+
+/// How much a back bet's stake counts toward its share of a parimutuel pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum PoolWeighting {
+    Stake,
+    /// Square root of the stake, so a whale's share grows slower than its stake
+    Quadratic,
+    /// Stake up to `max_stake`; anything above it earns no extra share
+    Capped {
+        max_stake: u64,
+    },
+}
+
+impl PoolWeighting {
+    pub const LEN: usize = 1 + 8;
+
+    pub fn weight(&self, stake: u64) -> u64 {
+        match self {
+            PoolWeighting::Stake => stake,
+            PoolWeighting::Quadratic => math::isqrt(stake),
+            PoolWeighting::Capped { max_stake } => stake.min(*max_stake),
+        }
+    }
+}
+
+/// Game terms a lobby's owner amended to before the game started.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct TermAmendment {
+    pub max_hands: u64,
+    pub blinds: Vec<BlindLevel>,
+    pub amended_at: i64,
+}
+
+impl TermAmendment {
+    pub const LEN: usize = 8 + (4 + BlindLevel::LEN * MAX_BLIND_LEVELS) + 8;
+}
+
+/// Blinds for `hands` hands, after the previous level's. The last level runs
+/// until the game ends.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct BlindLevel {
+    pub hands: u64,
+    pub small: u64,
+    pub big: u64,
+}
+
+impl BlindLevel {
+    pub const LEN: usize = 8 + 8 + 8;
+
+    /// One to `MAX_BLIND_LEVELS` levels of at least one hand, each with a
+    /// positive small blind, a big blind no smaller, and blinds no lower than
+    /// the level before.
+    pub fn valid(levels: &[BlindLevel]) -> bool {
+        !levels.is_empty()
+            && levels.len() <= MAX_BLIND_LEVELS
+            && levels
+                .iter()
+                .all(|level| level.hands > 0 && level.small > 0 && level.big >= level.small)
+            && levels
+                .windows(2)
+                .all(|pair| pair[1].small >= pair[0].small && pair[1].big >= pair[0].big)
+    }
+}
+
+/// Share of a lobby's rake paid to `recipient` at settlement. A split to the
+/// treasury PDA stays in the treasury.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct RakeSplit {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+impl RakeSplit {
+    pub const LEN: usize = 32 + 2;
+
+    /// No splits, or up to `MAX_RAKE_RECIPIENTS` distinct recipients with
+    /// nonzero shares summing to the whole rake.
+    pub fn valid(splits: &[RakeSplit]) -> bool {
+        splits.is_empty()
+            || (splits.len() <= MAX_RAKE_RECIPIENTS
+                && splits.iter().all(|split| split.bps > 0)
+                && splits.iter().map(|split| split.bps as u64).sum::<u64>()
+                    == math::BPS_DENOMINATOR
+                && splits.iter().enumerate().all(|(i, split)| {
+                    !splits[..i]
+                        .iter()
+                        .any(|other| other.recipient == split.recipient)
+                }))
+    }
+}
+
+/// The settings a lobby is declared with, as a `LobbyTemplate` keeps them.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct LobbyTerms {
+    pub model_names: Vec<String>,
+    pub starting_chips: u64,
+    pub blinds: Vec<BlindLevel>,
+    pub max_hands: u64,
+    /// Pot share per finishing position in basis points; empty for winner
+    /// takes all
+    pub payout_curve: Vec<u16>,
+    pub chip_buckets: Vec<u64>,
+    pub rake_splits: Vec<RakeSplit>,
+}
+
+impl LobbyTerms {
+    pub const LEN: usize = (4 + (4 + 32) * MAX_MODELS)
+        + 8
+        + (4 + BlindLevel::LEN * MAX_BLIND_LEVELS)
+        + 8
+        + (4 + 2 * MAX_MODELS)
+        + (4 + 8 * MAX_CHIP_BUCKETS)
+        + (4 + RakeSplit::LEN * MAX_RAKE_RECIPIENTS);
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.model_names.is_empty() && self.model_names.len() <= MAX_MODELS,
+            BettingError::InvalidModelCount
+        );
+        require!(
+            self.payout_curve.is_empty()
+                || (self.payout_curve.len() <= self.model_names.len()
+                    && self.payout_curve.iter().map(|bps| *bps as u64).sum::<u64>()
+                        == math::BPS_DENOMINATOR),
+            BettingError::InvalidPayoutCurve
+        );
+        require!(
+            self.chip_buckets.len() < MAX_CHIP_BUCKETS
+                && !self.chip_buckets.contains(&0)
+                && self.chip_buckets.windows(2).all(|pair| pair[0] < pair[1]),
+            BettingError::InvalidChipBuckets
+        );
+        require!(
+            RakeSplit::valid(&self.rake_splits),
+            BettingError::InvalidRakeSplits
+        );
+        require!(BlindLevel::valid(&self.blinds), BettingError::InvalidBlinds);
+        Ok(())
+    }
+}
+
+/// Per-wallet responsible-gambling caps in lamports. Zero disables a cap.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Default)]
+pub struct BettingLimits {
+    pub daily_wager: u64,
+    pub weekly_wager: u64,
+    pub daily_loss: u64,
+    pub weekly_loss: u64,
+}
+
+impl BettingLimits {
+    pub const LEN: usize = 8 + 8 + 8 + 8;
+
+    /// The stricter of these limits and `requested`, cap by cap.
+    fn tightened(&self, requested: &BettingLimits) -> BettingLimits {
+        BettingLimits {
+            daily_wager: tighter_limit(self.daily_wager, requested.daily_wager),
+            weekly_wager: tighter_limit(self.weekly_wager, requested.weekly_wager),
+            daily_loss: tighter_limit(self.daily_loss, requested.daily_loss),
+            weekly_loss: tighter_limit(self.weekly_loss, requested.weekly_loss),
+        }
+    }
+
+    /// These micro-USD caps converted to lamports at `price`.
+    fn in_lamports(&self, price: &pyth::UsdPrice) -> Result<BettingLimits> {
+        Ok(BettingLimits {
+            daily_wager: price.lamports(self.daily_wager)?,
+            weekly_wager: price.lamports(self.weekly_wager)?,
+            daily_loss: price.lamports(self.daily_loss)?,
+            weekly_loss: price.lamports(self.weekly_loss)?,
+        })
+    }
+}
+
+/// Returns whichever of two caps is stricter, treating zero as "no cap".
+fn tighter_limit(current: u64, requested: u64) -> u64 {
+    match (current, requested) {
+        (0, r) => r,
+        (c, 0) => c,
+        (c, r) => c.min(r),
+    }
+}
+
+/// Admin-maintained screening denylist, kept sorted for binary search.
+#[account]
+pub struct Denylist {
+    pub entries: Vec<Pubkey>,
+    pub updated_at: i64,
+}
+
+impl Denylist {
+    pub const LEN: usize = (4 + 32 * MAX_DENYLIST_ENTRIES) + 8;
+
+    pub fn contains(&self, wallet: &Pubkey) -> bool {
+        self.entries.binary_search(wallet).is_ok()
+    }
+}
+
+#[account]
+pub struct SelfExclusion {
+    pub wallet: Pubkey,
+    pub until: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl SelfExclusion {
+    pub const LEN: usize = 32 + 8 + 8 + 8;
+}
+
+/// Track record of a lobby owner, for gating lobby creation and for trust
+/// badges in clients.
+#[account]
+pub struct OperatorProfile {
+    pub owner: Pubkey,
+    pub lobbies_run: u64,
+    /// Lobbies finished, whose latencies make up `total_settlement_latency`
+    pub lobbies_settled: u64,
+    /// Stake bet across settled lobbies
+    pub total_volume: u64,
+    pub disputes_lost: u64,
+    /// Seconds from creation to finish, summed over settled lobbies
+    pub total_settlement_latency: i64,
+    /// Settled lobbies that drew at least `MIN_REPUTATION_VOLUME` of bets
+    pub qualifying_lobbies: u64,
+}
+
+impl OperatorProfile {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8;
+
+    pub fn address(owner: &Pubkey) -> Pubkey {
+        pda::operator_profile_pda(owner)
+    }
+
+    /// Qualifying lobbies, less `DISPUTE_REPUTATION_PENALTY` per dispute lost.
+    pub fn reputation(&self) -> u64 {
+        let penalty = self.disputes_lost.saturating_mul(DISPUTE_REPUTATION_PENALTY);
+        self.qualifying_lobbies.saturating_sub(penalty)
+    }
+
+    /// Whether an operator that hasn't lost a dispute may still run a lobby
+    /// below `min_reputation`: its first `min_reputation` lobbies, which it
+    /// needs to earn that reputation, are run on probation.
+    pub fn on_probation(&self, min_reputation: u64) -> bool {
+        self.disputes_lost == 0 && self.lobbies_run < min_reputation
+    }
+
+    pub fn average_settlement_latency(&self) -> Option<i64> {
+        (self.lobbies_settled > 0)
+            .then(|| self.total_settlement_latency / self.lobbies_settled as i64)
+    }
+
+    fn record_settlement(&mut self, volume: u64, latency: i64) -> Result<()> {
+        self.lobbies_settled = self
+            .lobbies_settled
+            .checked_add(1)
+            .ok_or(BettingError::Overflow)?;
+        if volume >= MIN_REPUTATION_VOLUME {
+            self.qualifying_lobbies = self
+                .qualifying_lobbies
+                .checked_add(1)
+                .ok_or(BettingError::Overflow)?;
+        }
+        self.total_volume = self
+            .total_volume
+            .checked_add(volume)
+            .ok_or(BettingError::Overflow)?;
+        self.total_settlement_latency = self
+            .total_settlement_latency
+            .checked_add(latency)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct OwnerStats {
+    pub owner: Pubkey,
+    pub lobbies_created: u64,
+    pub last_lobby_created_at: i64,
+}
+
+impl OwnerStats {
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+#[account]
+pub struct StakePool {
+    pub stake_mint: Pubkey,
+    pub vault: Pubkey,
+    pub total_staked: u64,
+    pub acc_reward_per_share: u128,
+    pub epoch: u64,
+    pub epoch_duration: i64,
+    pub epoch_started_at: i64,
+    pub total_distributed: u64,
+    pub bump: u8,
+}
+
+impl StakePool {
+    pub const LEN: usize = 32 + 32 + 8 + 16 + 8 + 8 + 8 + 8 + 1;
+}
+
+#[account]
+pub struct StakePosition {
+    pub owner: Pubkey,
+    /// Active stake, earning each epoch's revenue share
+    pub amount: u64,
+    pub reward_debt: u128,
+    pub unclaimed: u64,
+    /// Stake added in `pending_epoch`, active from the next one
+    pub pending: u64,
+    pub pending_epoch: u64,
+}
+
+impl StakePosition {
+    pub const LEN: usize = 32 + 8 + 16 + 8 + 8 + 8;
+
+    fn accrued(&self, acc_reward_per_share: u128) -> Result<u128> {
+        math::mul_div_wide(self.amount as u128, acc_reward_per_share, REWARD_PRECISION)
+    }
+
+    /// Moves rewards earned since the last update into `unclaimed`, then
+    /// activates pending stake once its epoch has closed. Pending stake joins
+    /// at the current reward level, so it earns nothing from epochs that
+    /// closed before it was active.
+    fn settle(&mut self, pool: &mut StakePool) -> Result<()> {
+        let acc_reward_per_share = pool.acc_reward_per_share;
+        let earned = self
+            .accrued(acc_reward_per_share)?
+            .saturating_sub(self.reward_debt);
+        self.unclaimed = self
+            .unclaimed
+            .checked_add(earned as u64)
+            .ok_or(BettingError::Overflow)?;
+        if self.pending > 0 && pool.epoch > self.pending_epoch {
+            self.amount = self
+                .amount
+                .checked_add(self.pending)
+                .ok_or(BettingError::Overflow)?;
+            pool.total_staked = pool
+                .total_staked
+                .checked_add(self.pending)
+                .ok_or(BettingError::Overflow)?;
+            self.pending = 0;
+        }
+        self.reward_debt = self.accrued(acc_reward_per_share)?;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Lobby {
+    pub owner: Pubkey,
+    pub game_id: String,
+    pub model_names: Vec<String>,
+    pub starting_chips: u64,
+    /// Blinds schedule, escalating level by level
+    pub blinds: Vec<BlindLevel>,
+    pub max_hands: u64,
+    pub status: LobbyStatus,
+    pub total_bets: u64,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub attestation_issuer: Option<Pubkey>,
+    pub compliance_mode: ComplianceMode,
+    pub restricted_regions: Vec<[u8; 2]>,
+    pub attestation_required: bool,
+    pub model_pools: Vec<u64>,
+    pub sponsor_pool: u64,
+    pub match_pool: u64,
+    pub winner: Option<String>,
+    pub market_mode: MarketMode,
+    pub market_maker: Option<Pubkey>,
+    pub quoted_odds_bps: Vec<u32>,
+    pub quoted_at: i64,
+    pub quote_max_age: i64,
+    pub market_maker_bankroll: u64,
+    pub model_liabilities: Vec<u64>,
+    pub fixed_odds_paid: u64,
+    pub order_book_locked: u64,
+    pub lay_pools: Vec<u64>,
+    pub total_lay: u64,
+    /// Lamports the program itself moved into the escrow, net of payouts. Anything
+    /// above this plus the rent reserve arrived by direct transfer.
+    pub escrow_balance: u64,
+    /// Incremented on every mutation; each increment emits a `LobbyUpdated`
+    /// event so indexers can detect gaps.
+    pub seq: u64,
+    pub betting_deadline: i64,
+    pub pools_locked: bool,
+    /// Commitment to the game transcript that outcome proofs are checked against
+    pub transcript_commitment: Option<[u8; 32]>,
+    pub verified_winner: Option<String>,
+    /// Share of the pool, in basis points, paid to backers of each finishing
+    /// position; `[10000]` is winner-takes-pool
+    pub payout_curve: Vec<u16>,
+    /// Models in finishing order, one per paid position
+    pub placings: Vec<String>,
+    /// Winning parimutuel stake settled so far, per paid position
+    pub position_stake_paid: Vec<u64>,
+    /// Parimutuel winnings paid so far, per paid position
+    pub position_paid: Vec<u64>,
+    /// Upper bounds of the chip-count buckets, ascending; empty when the lobby
+    /// has no chip-count market
+    pub chip_buckets: Vec<u64>,
+    /// Stake on each chip-count bucket, one more than `chip_buckets`
+    pub bucket_pools: Vec<u64>,
+    /// Each model's final chip count, in `model_names` order
+    pub final_chips: Vec<u64>,
+    pub bucket_stake_paid: u64,
+    pub bucket_paid: u64,
+    /// Price of a spectator pass; zero when passes are not on sale
+    pub spectator_fee: u64,
+    /// Root of `(bettor, payout)` leaves computed off-chain, when the lobby
+    /// settles by Merkle distribution instead of bet by bet
+    pub payout_root: Option<[u8; 32]>,
+    pub payout_root_posted_at: i64,
+    /// Total the payout root distributes
+    pub payout_root_total: u64,
+    pub payout_root_challenged: bool,
+    /// Paid out against the payout root so far
+    pub merkle_paid: u64,
+    /// Concurrent Merkle tree holding the lobby's compressed bets
+    pub bet_tree: Option<Pubkey>,
+    /// Compressed bets appended so far; the next one's leaf index
+    pub compressed_bets: u64,
+    /// End of the blind bet reveal window, which opens at the betting deadline
+    pub reveal_deadline: i64,
+    /// Stake of blind bets not yet revealed or refunded
+    pub blind_stake: u64,
+    /// Payout roots rejected after a challenge
+    pub disputes_lost: u32,
+    /// Owner's bond moved into escrow as compensation for bettors
+    pub slashed_bond: u64,
+    /// Player pools opened for the lobby; every one is folded back into the
+    /// lobby's pools at lock
+    pub player_pools: u8,
+    /// Key that must co-sign `finish_game` with the owner, e.g. the game server's
+    pub results_authority: Option<Pubkey>,
+    /// Results authority the owner rotated to, taking over at
+    /// `results_authority_change_at`
+    pub pending_results_authority: Option<Pubkey>,
+    pub results_authority_change_at: i64,
+    /// Custody account holding `in_custody`
+    pub custody: Pubkey,
+    /// Escrow lamports swept to custody, still owed to the lobby's bettors
+    pub in_custody: u64,
+    /// How the rake is split at settlement; empty leaves it all to the treasury
+    pub rake_splits: Vec<RakeSplit>,
+    /// Fees collected on this lobby's bets, net of revenue share, when it has
+    /// `rake_splits`; reserved in the treasury until the lobby is over
+    pub rake: u64,
+    /// Charity a charity round sends `charity_bps` of the losing pool to
+    pub charity: Option<Pubkey>,
+    pub charity_bps: u16,
+    /// Sent to the charity at settlement and kept out of the winners' pool
+    pub charity_amount: u64,
+    /// Sponsor prize shared by correct freeroll picks
+    pub freeroll_pool: u64,
+    /// Freeroll picks on each model, in `model_names` order
+    pub freeroll_picks: Vec<u64>,
+    pub freeroll_paid_picks: u64,
+    pub freeroll_paid: u64,
+    /// How back stakes weigh in the parimutuel pool
+    pub weighting: PoolWeighting,
+    /// Sum of `weighting` over each model's back bets
+    pub model_weights: Vec<u64>,
+    /// Consolation prize for one losing bettor, held in the treasury
+    pub raffle_pool: u64,
+    /// Tickets entered so far, one per lamport of losing stake
+    pub raffle_tickets: u64,
+    pub raffle_entries_close_at: i64,
+    /// ORAO VRF seed fixed once entries close
+    pub raffle_seed: Option<[u8; 32]>,
+    pub raffle_ticket: Option<u64>,
+    /// Models disqualified mid-tournament, in `model_names` order; bets on
+    /// them are void
+    pub disqualified: Vec<bool>,
+    /// Lobby this one continues into overtime
+    pub parent_lobby: Option<Pubkey>,
+    pub continuation: Option<Pubkey>,
+    /// Whether the parent's chip-count market settles here
+    pub chip_market_rolled: bool,
+    /// Game terms amended before start, oldest first; the terms at creation
+    /// are the ones before the first
+    pub term_amendments: Vec<TermAmendment>,
+    /// `GameConfig` the terms were frozen into; they can't be amended after
+    pub game_config: Option<Pubkey>,
+    /// Share of the rake set aside to reimburse model operators' API costs
+    pub reimbursement_bps: u16,
+    /// Reimbursement pool, held in the treasury
+    pub operator_reimbursement: u64,
+    pub reimbursement_paid: u64,
+    /// Engine hand records counted so far; hand numbers below this are done
+    pub hands_counted: u64,
+    /// Hands each model played, in `model_names` order
+    pub reimbursed_hands: Vec<u64>,
+    pub reimbursement_claimed: Vec<bool>,
+    pub reimbursement_closes_at: i64,
+    /// Stake held in the hand markets' escrow, apart from `escrow_balance`
+    pub hand_escrow_balance: u64,
+    /// Hands below this number have been checkpoint-settled
+    pub hands_checkpointed: u64,
+    /// Hand numbers with an open market
+    pub open_hand_markets: Vec<u64>,
+    /// SHA-256 of `game_id`; the lobby is seeded by `owner` and this
+    pub game_id_hash: [u8; 32],
+    /// When the lobby finished; zero until then
+    pub finished_at: i64,
+    /// Models whose operator registered a match stake, one bit per
+    /// `model_names` index
+    pub match_stakes_registered: u16,
+    /// Models whose operator posted it
+    pub match_stakes_posted: u16,
+    /// Registered match stakes not all posted by then call the match off
+    pub match_stake_deadline: i64,
+    /// Bets on the lobby that may still be paid out of escrow
+    pub outstanding_bets: u64,
+    /// When the bond was slashed; bets placed later aren't compensated
+    pub bond_slashed_at: i64,
+    /// Stake in the pools when the bond was slashed, which `slashed_bond` is
+    /// shared over
+    pub compensable_stake: u64,
+    /// Compensation paid out of `slashed_bond` so far
+    pub compensation_paid: u64,
+}
+
+impl Lobby {
+    /// Size without the game id's bytes; see `space`.
+    pub const LEN: usize = 32 + 4 + 4 + (4 + 32) * MAX_MODELS + 8
+        + (4 + BlindLevel::LEN * MAX_BLIND_LEVELS) + 8  + 8 + 8 + 8 + (1 + 32)
+        + 1 + (4 + 2 * MAX_RESTRICTED_REGIONS) + 1 + (4 + 8 * MAX_MODELS) + 8 + 8 + (1 + 4 + 32)
+        + 1 + (1 + 32) + (4 + 4 * MAX_MODELS) + 8 + 8 + 8 + (4 + 8 * MAX_MODELS) + 8
+        + 1 + 8 + (4 + 8 * MAX_MODELS) + 8 + 8 + 8 + 8 + 1 + (1 + 32) + (1 + 4 + 32)
+        + (4 + 2 * MAX_MODELS) + (4 + (4 + 32) * MAX_MODELS) + (4 + 8 * MAX_MODELS) * 2
+        + (4 + 8 * MAX_CHIP_BUCKETS) * 2 + (4 + 8 * MAX_MODELS) + 8 + 8 + 8
+        + (1 + 32) + 8 + 8 + 1 + 8 + (1 + 32) + 8 + 8 + 8 + 4 + 8 + 1
+        + (1 + 32) + (1 + 32) + 8 + 32 + 8
+        + (4 + RakeSplit::LEN * MAX_RAKE_RECIPIENTS) + 8 + (1 + 32) + 2 + 8
+        + 8 + (4 + 8 * MAX_MODELS) + 8 + 8 + PoolWeighting::LEN + (4 + 8 * MAX_MODELS)
+        + 8 + 8 + 8 + (1 + 32) + (1 + 8) + (4 + MAX_MODELS)
+        + (1 + 32) + (1 + 32) + 1 + (4 + TermAmendment::LEN * MAX_TERM_AMENDMENTS)
+        + (1 + 32) + 2 + 8 + 8 + 8 + (4 + 8 * MAX_MODELS) + (4 + MAX_MODELS) + 8
+        + 8 + 8 + (4 + 8 * MAX_OPEN_HAND_MARKETS) + 32 + 8 + 2 + 2 + 8 + 8 + 8 + 8 + 8;
+
+    /// Account space for a lobby of `game_id`.
+    pub fn space(game_id: &str) -> usize {
+        8 + Self::LEN + game_id.len()
+    }
+
+    pub fn track_deposit(&mut self, amount: u64) -> Result<()> {
+        self.escrow_balance = self
+            .escrow_balance
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+        Ok(())
+    }
+
+    pub fn track_withdrawal(&mut self, amount: u64) -> Result<()> {
+        self.escrow_balance = self
+            .escrow_balance
+            .checked_sub(amount)
+            .ok_or(BettingError::EscrowUnderflow)?;
+        Ok(())
+    }
+
+    pub fn is_disqualified(&self, name: &str) -> bool {
+        self.model_names
+            .iter()
+            .zip(&self.disqualified)
+            .any(|(model, disqualified)| *disqualified && model == name)
+    }
+
+    /// Blinds of the zero-based hand `hand`, for checking hand records.
+    pub fn blinds_at(&self, hand: u64) -> Option<&BlindLevel> {
+        let mut end = 0u64;
+        for level in &self.blinds {
+            end = end.saturating_add(level.hands);
+            if hand < end {
+                return Some(level);
+            }
+        }
+        self.blinds.last()
+    }
+
+    pub fn model_index(&self, name: &str) -> Result<usize> {
+        self.model_names
+            .iter()
+            .position(|m| m == name)
+            .ok_or(error!(BettingError::InvalidPlayerName))
+    }
+
+    /// Total stake that wins if `winner_index` wins: its back pool plus the lay
+    /// pools of every other model.
+    fn winning_stake(&self, winner_index: usize) -> u64 {
+        self.model_pools[winner_index] + self.total_lay - self.lay_pools[winner_index]
+    }
+
+    /// Parimutuel totals if `winner_index` wins: every pool on the lobby,
+    /// including sponsor boosts, and the winning stake that shares it pro rata
+    /// (backers of the winner and layers of every other model).
+    pub fn parimutuel_totals(&self, winner_index: usize) -> Result<(u64, u64)> {
+        let distributable = self
+            .total_bets
+            .checked_add(self.sponsor_pool)
+            .ok_or(BettingError::Overflow)?;
+        Ok((distributable, self.winning_stake(winner_index)))
+    }
+
+    /// What a charity round sends to its charity out of `distributable` when
+    /// `winning_stake` of it wins: `charity_bps` of the losing pool.
+    pub fn charity_cut(&self, distributable: u64, winning_stake: u64) -> Result<u64> {
+        math::apply_bps(
+            distributable.saturating_sub(winning_stake),
+            self.charity_bps as u64,
+        )
+    }
+
+    /// Parimutuel payout for a winning stake at a paid `position`. Pro-rata
+    /// shares round down; the claim that settles the last of the position's
+    /// winning stake receives whatever is left of its pool, so the escrow
+    /// empties exactly.
+    pub fn settle_parimutuel(
+        &mut self,
+        position: usize,
+        stake: u64,
+        distributable: u64,
+        winning_stake: u64,
+    ) -> Result<u64> {
+        let payout = self.parimutuel_share(position, stake, distributable, winning_stake)?;
+        self.position_stake_paid[position] = self.position_stake_paid[position]
+            .checked_add(stake)
+            .ok_or(BettingError::Overflow)?;
+        self.position_paid[position] = self.position_paid[position]
+            .checked_add(payout)
+            .ok_or(BettingError::Overflow)?;
+        Ok(payout)
+    }
+
+    /// Payout `settle_parimutuel` would make for `stake` now, without
+    /// recording it.
+    pub fn parimutuel_share(
+        &self,
+        position: usize,
+        stake: u64,
+        distributable: u64,
+        winning_stake: u64,
+    ) -> Result<u64> {
+        math::parimutuel_claim(
+            stake,
+            self.position_stake_paid[position],
+            self.position_paid[position],
+            distributable,
+            winning_stake,
+        )
+    }
+
+    /// Basis points of the pool each paid position receives given the back
+    /// pools at settlement. A position nobody backed can't be claimed, so its
+    /// share rolls to the best-placed position that was backed.
+    pub fn position_shares(&self, model_pools: &[u64]) -> Result<Vec<u64>> {
+        let mut shares: Vec<u64> = self.payout_curve.iter().map(|bps| *bps as u64).collect();
+        let mut backed = Vec::with_capacity(shares.len());
+        for name in &self.placings[..shares.len()] {
+            backed.push(model_pools[self.model_index(name)?] > 0);
+        }
+        if let Some(first) = backed.iter().position(|backed| *backed) {
+            for (position, backed) in backed.iter().enumerate() {
+                if !backed {
+                    shares[first] += std::mem::take(&mut shares[position]);
+                }
+            }
+        }
+        Ok(shares)
+    }
+
+    /// Chip-count bucket holding the winner's final stack.
+    pub fn winning_chip_bucket(&self) -> Result<usize> {
+        require!(!self.chip_buckets.is_empty(), BettingError::InvalidChipBucket);
+        let winner = self.winner.as_deref().ok_or(BettingError::LobbyNotFinished)?;
+        let chips = self.final_chips[self.model_index(winner)?];
+        Ok(self.chip_buckets.iter().filter(|bound| chips >= **bound).count())
+    }
+
+    /// Whether nothing is left to pay out or refund: every escrow is empty
+    /// and no payout, raffle or reimbursement is pending.
+    pub fn fully_settled(&self) -> bool {
+        self.status == LobbyStatus::Finished
+            && self.escrow_balance == 0
+            && self.hand_escrow_balance == 0
+            && self.in_custody == 0
+            && self.blind_stake == 0
+            && self.open_hand_markets.is_empty()
+            && self.raffle_pool == 0
+            && self.operator_reimbursement == self.reimbursement_paid
+            && !self.payout_root_challenged
+    }
+
+    /// Empties the per-model, per-position and per-bucket vectors, leaving
+    /// the owner, game id, winner and totals as an audit stub.
+    pub fn strip_for_archive(&mut self) {
+        self.model_names = Vec::new();
+        self.blinds = Vec::new();
+        self.restricted_regions = Vec::new();
+        self.model_pools = Vec::new();
+        self.quoted_odds_bps = Vec::new();
+        self.model_liabilities = Vec::new();
+        self.lay_pools = Vec::new();
+        self.payout_curve = Vec::new();
+        self.placings = Vec::new();
+        self.position_stake_paid = Vec::new();
+        self.position_paid = Vec::new();
+        self.chip_buckets = Vec::new();
+        self.bucket_pools = Vec::new();
+        self.final_chips = Vec::new();
+        self.rake_splits = Vec::new();
+        self.freeroll_picks = Vec::new();
+        self.model_weights = Vec::new();
+        self.disqualified = Vec::new();
+        self.term_amendments = Vec::new();
+        self.reimbursed_hands = Vec::new();
+        self.reimbursement_claimed = Vec::new();
+        self.open_hand_markets = Vec::new();
+    }
+
+    /// Whether the head-to-head match is off, so posted stakes go back to
+    /// their operators: a registered stake wasn't posted before the game
+    /// started or the window closed, or the game finished without paying the
+    /// match pool to the winner's operator.
+    pub fn match_void(&self, now: i64) -> bool {
+        if self.status.is_over() {
+            return self.match_pool > 0;
+        }
+        self.match_stakes_posted != self.match_stakes_registered
+            && (self.status != LobbyStatus::Waiting || now >= self.match_stake_deadline)
+    }
+
+    pub fn betting_closed(&self, now: i64) -> bool {
+        self.pools_locked || (self.betting_deadline > 0 && now >= self.betting_deadline)
+    }
+
+    /// Parimutuel decimal odds, in basis points, once `stake` more has been added
+    /// on `side` of a model. Lay odds are the worst case over the other models.
+    /// In a weighted lobby a back bet's odds depend on its own stake; with no
+    /// stake they are the odds by stake alone.
+    pub fn implied_odds_after(&self, model_index: usize, side: BetSide, stake: u64) -> Result<u64> {
+        let distributable = self
+            .total_bets
+            .checked_add(self.sponsor_pool)
+            .and_then(|v| v.checked_add(stake))
+            .ok_or(BettingError::Overflow)?;
+        let winning_stake = match side {
+            BetSide::Back => self.winning_stake(model_index) as u128 + stake as u128,
+            BetSide::Lay => (0..self.model_names.len())
+                .filter(|&i| i != model_index)
+                .map(|i| self.winning_stake(i) as u128 + stake as u128)
+                .max()
+                .ok_or(BettingError::InvalidPlayerName)?,
+        };
+        let charity = self.charity_cut(
+            distributable,
+            winning_stake.min(distributable as u128) as u64,
+        )?;
+        if self.weighting != PoolWeighting::Stake && side == BetSide::Back && stake > 0 {
+            let weight = self.weighting.weight(stake);
+            let winning_weight = self.model_weights[model_index]
+                .checked_add(weight)
+                .ok_or(BettingError::Overflow)?;
+            return math::weighted_odds_bps(stake, weight, distributable - charity, winning_weight);
+        }
+        math::implied_odds_bps(distributable - charity, winning_stake)
+    }
+
+    /// Odds a back bet on `model_index` gets right now, in basis points: the
+    /// market maker's quote in a fixed-odds lobby, otherwise the parimutuel
+    /// odds. Zero when nothing backs the model yet.
+    pub fn current_odds_bps(&self, model_index: usize) -> Result<u64> {
+        match self.market_mode {
+            MarketMode::FixedOdds => Ok(self.quoted_odds_bps[model_index] as u64),
+            MarketMode::Parimutuel if self.winning_stake(model_index) == 0 => Ok(0),
+            MarketMode::Parimutuel => self.implied_odds_after(model_index, BetSide::Back, 0),
+        }
+    }
+
+    /// Share of all back stakes, in basis points, that is on `model_index`.
+    pub fn pool_share_bps(&self, model_index: usize) -> Result<u64> {
+        let back_total: u64 = self.model_pools.iter().sum();
+        math::bps_share(self.model_pools[model_index], back_total)
+    }
+
+    /// Lamports available to pay fixed-odds winners once `incoming` is escrowed:
+    /// the market maker's bankroll plus every stake in the lobby.
+    pub fn fixed_odds_backing(&self, incoming: u64) -> Result<u64> {
+        self.market_maker_bankroll
+            .checked_add(self.total_bets)
+            .and_then(|v| v.checked_add(incoming))
+            .ok_or(error!(BettingError::Overflow))
+    }
+}
+
+pub use math::{fixed_odds_payout, parimutuel_payout};
+
+/// Credential written by a KYC issuer, proving the subject passed the issuer's
+/// jurisdiction and age checks until `expires_at`.
+#[account]
+pub struct Attestation {
+    pub issuer: Pubkey,
+    pub subject: Pubkey,
+    pub jurisdiction: [u8; 2],
+    pub issued_at: i64,
+    pub expires_at: i64,
+    pub revoked: bool,
+}
+
+impl Attestation {
+    pub const LEN: usize = 32 + 32 + 2 + 8 + 8 + 1;
+}
+
+/// Ring buffer of the latest chat messages for a lobby. The message at
+/// `total_messages % FEED_CAPACITY` is the next one to be overwritten.
+#[account]
+pub struct LobbyFeed {
+    pub lobby: Pubkey,
+    pub total_messages: u64,
+    pub messages: Vec<FeedMessage>,
+}
+
+impl LobbyFeed {
+    pub const LEN: usize = 32 + 8 + 4 + FeedMessage::LEN * FEED_CAPACITY;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FeedMessage {
+    pub author: Pubkey,
+    pub text: String,
+    pub posted_at: i64,
+}
+
+impl FeedMessage {
+    pub const LEN: usize = 32 + 4 + MAX_MESSAGE_LEN + 8;
+}
+
+/// Wormhole intake settings for bets from other chains.
+#[account]
+pub struct BridgeConfig {
+    pub core_bridge: Pubkey,
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub lamports_per_usdc: u64,
+}
+
+impl BridgeConfig {
+    pub const LEN: usize = 32 + 2 + 32 + 8;
+}
+
+/// Marks a VAA as used so it cannot place a second bet.
+#[account]
+pub struct ConsumedVaa {
+    pub vaa_hash: [u8; 32],
+    pub emitter_chain: u16,
+    pub sequence: u64,
+    pub evm_bettor: [u8; 20],
+    pub bet: Pubkey,
+    pub consumed_at: i64,
+}
+
+impl ConsumedVaa {
+    pub const LEN: usize = 32 + 2 + 8 + 20 + 32 + 8;
+}
+
+/// Groth16 verifying key for the game outcome circuit, also used for the
+/// shielded pool's spend circuit.
+#[account]
+pub struct OutcomeVerifier {
+    pub alpha_g1: groth16::G1,
+    pub beta_g2: groth16::G2,
+    pub gamma_g2: groth16::G2,
+    pub delta_g2: groth16::G2,
+    pub ic: Vec<groth16::G1>,
+}
+
+impl OutcomeVerifier {
+    pub const LEN: usize = Self::space(OUTCOME_PUBLIC_INPUTS);
+
+    pub const fn space(public_inputs: usize) -> usize {
+        64 + 128 * 3 + (4 + 64 * (public_inputs + 1))
+    }
+}
+
+/// Shielded note pool; holds the lamports of every unspent note.
+#[account]
+pub struct ShieldedPool {
+    pub denomination: u64,
+    pub next_index: u32,
+    /// Rightmost filled node at each level of the note tree
+    pub filled_subtrees: Vec<[u8; 32]>,
+    /// Ring of recent roots
+    pub roots: Vec<[u8; 32]>,
+    pub root_index: u8,
+}
+
+impl ShieldedPool {
+    pub const LEN: usize =
+        8 + 4 + (4 + 32 * shielded::TREE_DEPTH) + (4 + 32 * shielded::ROOT_HISTORY) + 1;
+
+    fn push_root(&mut self, root: [u8; 32]) {
+        if self.roots.len() < shielded::ROOT_HISTORY {
+            self.roots.push(root);
+            self.root_index = (self.roots.len() - 1) as u8;
+        } else {
+            self.root_index = ((self.root_index as usize + 1) % shielded::ROOT_HISTORY) as u8;
+            self.roots[self.root_index as usize] = root;
+        }
+    }
+}
+
+/// Marks a shielded note spent.
+#[account]
+pub struct Nullifier {
+    pub nullifier_hash: [u8; 32],
+    pub spent_at: i64,
+}
+
+impl Nullifier {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// Copy of a lobby's pools taken when betting closes, changed afterwards only
+/// to void a disqualified model's pools. Parimutuel payouts use it instead of
+/// the live lobby totals.
+#[account]
+pub struct PoolSnapshot {
+    pub lobby: Pubkey,
+    pub model_pools: Vec<u64>,
+    pub lay_pools: Vec<u64>,
+    pub total_bets: u64,
+    pub total_lay: u64,
+    pub sponsor_pool: u64,
+    pub seq: u64,
+    pub locked_at: i64,
+}
+
+impl PoolSnapshot {
+    pub const LEN: usize = 32 + (4 + 8 * MAX_MODELS) * 2 + 8 + 8 + 8 + 8 + 8;
+
+    pub fn parimutuel_totals(&self, winner_index: usize) -> Result<(u64, u64)> {
+        let distributable = self
+            .total_bets
+            .checked_add(self.sponsor_pool)
+            .ok_or(BettingError::Overflow)?;
+        let winning_stake =
+            self.model_pools[winner_index] + self.total_lay - self.lay_pools[winner_index];
+        Ok((distributable, winning_stake))
+    }
+}
+
+/// A lobby's game terms, frozen once the game starts.
+#[account]
+pub struct GameConfig {
+    pub lobby: Pubkey,
+    pub model_names: Vec<String>,
+    pub starting_chips: u64,
+    pub blinds: Vec<BlindLevel>,
+    pub max_hands: u64,
+    pub frozen_at: i64,
+}
+
+impl GameConfig {
+    pub const LEN: usize =
+        32 + (4 + (4 + 32) * MAX_MODELS) + 8 + (4 + BlindLevel::LEN * MAX_BLIND_LEVELS) + 8 + 8;
+}
+
+/// Head-to-head stake a model's operator escrows against the other operators.
+#[account]
+pub struct MatchStake {
+    pub lobby: Pubkey,
+    pub model_name: String,
+    pub operator: Pubkey,
+    pub amount: u64,
+    pub posted: bool,
+}
+
+impl MatchStake {
+    pub const LEN: usize = 32 + 4 + 32 + 32 + 8 + 1;
+}
+
+/// Resting order on a lobby's order book. `remaining_stake` is always expressed
+/// as the backer's stake, so lay orders lock `stake * (odds - 1)`.
+#[account]
+pub struct Order {
+    pub lobby: Pubkey,
+    pub maker: Pubkey,
+    pub model_index: u8,
+    pub side: BetSide,
+    pub odds_bps: u32,
+    pub remaining_stake: u64,
+    pub filled_stake: u64,
+    pub status: OrderStatus,
+    pub created_at: i64,
+}
+
+impl Order {
+    pub const LEN: usize = 32 + 32 + 1 + 1 + 4 + 8 + 8 + 1 + 8;
+
+    pub fn collateral_for(&self, stake: u64) -> Result<u64> {
+        match self.side {
+            BetSide::Back => Ok(stake),
+            BetSide::Lay => fixed_odds_payout(stake, self.odds_bps)?
+                .checked_sub(stake)
+                .ok_or(error!(BettingError::Overflow)),
+        }
+    }
+}
+
+/// Matched back/lay pair. The winner of the position takes both sides' funds.
+#[account]
+pub struct MatchedPosition {
+    pub lobby: Pubkey,
+    pub backer: Pubkey,
+    pub layer: Pubkey,
+    pub model_index: u8,
+    pub odds_bps: u32,
+    pub backer_stake: u64,
+    pub layer_liability: u64,
+    pub settled: bool,
+    pub matched_at: i64,
+}
+
+impl MatchedPosition {
+    pub const LEN: usize = 32 + 32 + 32 + 1 + 4 + 8 + 8 + 1 + 8;
+}
+
+/// Deposit plus trigger for a bet a crank places on the owner's behalf. The
+/// deposit is held as lamports on this account until the bet is placed.
+#[account]
+pub struct ConditionalBet {
+    pub owner: Pubkey,
+    pub lobby: Pubkey,
+    pub player_name: String,
+    pub side: BetSide,
+    pub amount: u64,
+    pub condition: TriggerCondition,
+    pub threshold_bps: u32,
+    pub deadline: i64,
+    pub min_expected_odds_bps: u32,
+    pub executed: bool,
+    pub created_at: i64,
+}
+
+impl ConditionalBet {
+    pub const LEN: usize = 32 + 32 + 4 + 32 + 1 + 8 + 1 + 4 + 8 + 4 + 1 + 8;
+
+    pub fn is_triggered(&self, lobby: &Lobby) -> Result<bool> {
+        let model_index = lobby.model_index(&self.player_name)?;
+        let threshold = self.threshold_bps as u64;
+        Ok(match self.condition {
+            TriggerCondition::PoolShareBelow => lobby.pool_share_bps(model_index)? < threshold,
+            TriggerCondition::PoolShareAbove => lobby.pool_share_bps(model_index)? > threshold,
+            TriggerCondition::OddsAtLeast => {
+                lobby.implied_odds_after(model_index, self.side, 0)? >= threshold
+            }
+        })
+    }
+}
+
+/// Program-owned account holding a wallet's pre-funded balance for automated
+/// betting. The balance is the account's lamports above its rent reserve.
+#[account]
+pub struct DepositVault {
+    pub owner: Pubkey,
+    pub total_deposited: u64,
+}
+
+impl DepositVault {
+    pub const LEN: usize = 32 + 8;
+}
+
+#[account]
+pub struct Subscription {
+    pub subscriber: Pubkey,
+    pub lobby_owner: Pubkey,
+    pub model_name: String,
+    pub amount_per_lobby: u64,
+    pub lobbies_bet: u64,
+    pub created_at: i64,
+}
+
+impl Subscription {
+    pub const LEN: usize = 32 + 32 + 4 + 32 + 8 + 8 + 8;
+}
+
+#[account]
+pub struct FollowRecord {
+    pub follower: Pubkey,
+    pub leader: Pubkey,
+    pub per_lobby_amount: u64,
+    pub paused: bool,
+    pub bets_mirrored: u64,
+    pub created_at: i64,
+}
+
+impl FollowRecord {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 8 + 8;
+}
+
+#[account]
+pub struct Bet {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub player_name: String,
+    pub amount: u64,
+    pub placed_at: i64,
+    pub status: BetStatus,
+    pub odds_bps: u32,
+    /// Gross amount the bettor asked to bet; more than `amount` plus fees when a
+    /// fixed-odds bet was only partially filled.
+    pub requested_amount: u64,
+    pub side: BetSide,
+    /// Wallet winnings are sent to instead of `bettor`
+    pub payout_address: Option<Pubkey>,
+    /// Caller's order reference; all zeros when none was given
+    pub client_id: [u8; 16],
+    pub memo: Option<String>,
+    /// Micro-USD per SOL when the bet was placed; zero when it was placed
+    /// without a price update
+    pub usd_per_sol: u64,
+    /// Micro-USD value of the stake and fee paid, at `usd_per_sol`
+    pub usd_value: u64,
+    /// First raffle ticket of a losing bet entered in the lobby's raffle
+    pub raffle_ticket: Option<u64>,
+    /// Times the bettor switched the bet's model before lock
+    pub selection_changes: u8,
+}
+
+impl Bet {
+    /// Whether the bet wins in the finished `lobby`.
+    pub fn wins(&self, lobby: &Lobby) -> Result<bool> {
+        let winner = lobby
+            .winner
+            .as_ref()
+            .ok_or(BettingError::LobbyNotFinished)?;
+        // Void, to be refunded rather than won or lost
+        if lobby.is_disqualified(&self.player_name) {
+            return Ok(false);
+        }
+        Ok(match self.side {
+            BetSide::Back if lobby.payout_curve.len() > 1 => {
+                lobby.placings.contains(&self.player_name)
+            }
+            BetSide::Back => self.player_name == *winner,
+            BetSide::Lay => self.player_name != *winner,
+        })
+    }
+
+    pub const LEN: usize =
+        32 + 32 + 4 + 32 + 8 + 8 + 1 + 4 + 8 + 1 + (1 + 32) + 16 + (1 + 4 + MAX_BET_MEMO_LEN)
+            + 8 + 8 + (1 + 8) + 1;
+
+    /// Micro-USD value of `lamports` at the bet's placement price.
+    pub fn usd(&self, lamports: u64) -> Result<u64> {
+        math::mul_div(lamports, self.usd_per_sol, LAMPORTS_PER_SOL)
+    }
+}
+
+/// A finished season: Merkle root over `(bettor, points)` leaves for airdrop
+/// claims.
+#[account]
+pub struct Season {
+    pub season: u32,
+    pub merkle_root: [u8; 32],
+    pub total_points: u64,
+    pub finalized_at: i64,
+}
+
+impl Season {
+    pub const LEN: usize = 4 + 32 + 8 + 8;
+}
+
+/// Compressed NFT tree achievements are minted into.
+#[account]
+pub struct AchievementConfig {
+    pub merkle_tree: Pubkey,
+    pub metadata_uri: String,
+}
+
+impl AchievementConfig {
+    pub const LEN: usize = 32 + (4 + MAX_ACHIEVEMENT_URI_LEN);
+}
+
+/// Largest single payout entered for a calendar week.
+#[account]
+pub struct WeeklyPayoutRecord {
+    pub week: i64,
+    pub holder: Pubkey,
+    pub amount: u64,
+}
+
+impl WeeklyPayoutRecord {
+    pub const LEN: usize = 8 + 32 + 8;
+}
+
+/// Marks an achievement as minted, at `["achievement", bettor, seed]`.
+#[account]
+pub struct AchievementClaim {
+    pub bettor: Pubkey,
+    pub achievement: Achievement,
+    pub claimed_at: i64,
+}
+
+impl AchievementClaim {
+    pub const LEN: usize = 32 + (1 + 8) + 8;
+}
+
+/// Back bet stored as a leaf of a lobby's bet tree rather than an account.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CompressedBet {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub player_name: String,
+    /// Stake net of the protocol fee
+    pub amount: u64,
+    pub placed_at: i64,
+    /// Leaf index in the bet tree
+    pub index: u64,
+}
+
+impl CompressedBet {
+    pub fn leaf(&self) -> Result<[u8; 32]> {
+        let data = self.try_to_vec()?;
+        Ok(anchor_lang::solana_program::hash::hashv(&[b"compressed_bet", &data]).to_bytes())
+    }
+}
+
+/// Receipt of a claim against a lobby's payout root; its existence blocks a
+/// second claim.
+#[account]
+pub struct MerkleClaim {
+    pub lobby: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+}
+
+impl MerkleClaim {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+}
+
+/// Paid access to a lobby's stream, at `["spectator_pass", lobby, viewer]`.
+#[account]
+pub struct SpectatorPass {
+    pub lobby: Pubkey,
+    pub viewer: Pubkey,
+    pub paid: u64,
+    pub purchased_at: i64,
+}
+
+impl SpectatorPass {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+
+    pub fn address(lobby: &Pubkey, viewer: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[SPECTATOR_PASS_SEED, lobby.as_ref(), viewer.as_ref()],
+            &crate::ID,
+        )
+        .0
+    }
+
+    /// True if this pass, read from `address`, admits `viewer` to `lobby`.
+    /// Off-chain gates must also check the account is owned by this program.
+    pub fn grants_access(&self, address: &Pubkey, lobby: &Pubkey, viewer: &Pubkey) -> bool {
+        self.lobby == *lobby
+            && self.viewer == *viewer
+            && *address == Self::address(lobby, viewer)
+    }
+}
+
+/// Lobby owner's bond, held on the account itself.
+#[account]
+pub struct OperatorBond {
+    pub owner: Pubkey,
+    pub lobby: Pubkey,
+    pub amount: u64,
+    /// Lobby must finish by then or the bond can be slashed; zero for never
+    pub settle_by: i64,
+    pub slashed: bool,
+}
+
+impl OperatorBond {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+/// Marks a bet's share of a slashed bond as paid.
+#[account]
+pub struct BondClaim {
+    pub bet: Pubkey,
+    pub amount: u64,
+}
+
+impl BondClaim {
+    pub const LEN: usize = 32 + 8;
+}
+
+/// Lamports escrowed on an in-game event, held on the account itself.
+#[account]
+pub struct Bounty {
+    pub lobby: Pubkey,
+    pub bounty_id: u64,
+    pub event: BountyEvent,
+    /// Funded and not yet refunded
+    pub total: u64,
+    /// Hand record the bounty was claimed against
+    pub claimed_by: Option<Pubkey>,
+    pub created_at: i64,
+}
+
+impl Bounty {
+    pub const LEN: usize = 32 + 8 + BountyEvent::LEN + 8 + (1 + 32) + 8;
+}
+
+#[account]
+pub struct BountyContribution {
+    pub bounty: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+}
+
+impl BountyContribution {
+    pub const LEN: usize = 32 + 32 + 8;
+}
+
+/// Back bet whose model stays hidden until revealed.
+#[account]
+pub struct BlindBet {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub commitment: [u8; 32],
+    /// Stake net of the protocol fee
+    pub amount: u64,
+    pub requested_amount: u64,
+    pub placed_at: i64,
+}
+
+impl BlindBet {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8;
+}
+
+/// Stake on the bucket of a lobby winner's final chip count.
+#[account]
+pub struct ChipBucketBet {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub bucket: u8,
+    pub amount: u64,
+    pub placed_at: i64,
+    pub status: BetStatus,
+}
+
+impl ChipBucketBet {
+    pub const LEN: usize = 32 + 32 + 1 + 8 + 8 + 1;
+}
+
+/// Parimutuel market on the winner of one hand, settled by
+/// `checkpoint_settle` while the match market stays open. Stakes are held in
+/// the lobby's hand escrow, never in the match escrow.
+#[account]
+pub struct HandMarket {
+    pub lobby: Pubkey,
+    pub hand_number: u64,
+    pub model_pools: Vec<u64>,
+    pub total: u64,
+    pub settled: bool,
+    /// Index of the hand's winner in `model_names`; `None` once settled means
+    /// the market is void and every stake is refunded
+    pub winner_index: Option<u8>,
+    pub stake_paid: u64,
+    pub paid: u64,
+}
+
+impl HandMarket {
+    pub const LEN: usize = 32 + 8 + (4 + 8 * MAX_MODELS) + 8 + 1 + (1 + 1) + 8 + 8;
+}
+
+#[account]
+pub struct HandBet {
+    pub bettor: Pubkey,
+    pub market: Pubkey,
+    pub model_index: u8,
+    pub amount: u64,
+    pub placed_at: i64,
+    pub status: BetStatus,
+}
+
+impl HandBet {
+    pub const LEN: usize = 32 + 32 + 1 + 8 + 8 + 1;
+}
+
+/// Run of lobbies a streak is scored over, at
+/// `["streak_series", authority, series_id]`. Holds the sponsors' prize funds.
+#[account]
+pub struct StreakSeries {
+    pub authority: Pubkey,
+    pub series_id: u64,
+    /// Lobbies in the order picks are scored
+    pub lobbies: Vec<Pubkey>,
+    /// Prize for reaching each streak length, starting at one
+    pub prizes: Vec<u64>,
+}
+
+impl StreakSeries {
+    pub const LEN: usize = 32 + 8 + (4 + 32 * MAX_STREAK_LOBBIES) + (4 + 8 * MAX_STREAK_PRIZES);
+}
+
+/// A wallet's consecutive correct picks in a series, at
+/// `["streak", series, bettor]`.
+#[account]
+pub struct Streak {
+    pub bettor: Pubkey,
+    pub series: Pubkey,
+    pub current: u32,
+    pub best: u32,
+    /// Streak length prizes have been paid up to in the current run
+    pub claimed: u32,
+    /// Series index of the next lobby to score
+    pub next_index: u32,
+}
+
+impl Streak {
+    pub const LEN: usize = 32 + 32 + 4 + 4 + 4 + 4;
+}
+
+/// Free pick of a freeroll lobby's winner, at
+/// `["freeroll_pick", lobby, bettor]` so each wallet gets one.
+#[account]
+pub struct FreerollPick {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub model_index: u8,
+    pub picked_at: i64,
+}
+
+impl FreerollPick {
+    pub const LEN: usize = 32 + 32 + 1 + 8;
+}
 
-        bet.bettor = bettor.key();
-        bet.lobby = lobby.key();
-        bet.player_name = player_name;
-        bet.amount = amount;
-        bet.placed_at = Clock::get()?.unix_timestamp;
-        bet.status = BetStatus::Active;
+/// Deployment profile that off-chain clients and the x402 facilitator are expected
+/// to honor. The program itself enforces the region list and attestation bit.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum ComplianceMode {
+    Open,
+    Geofenced,
+    Regulated,
+}
 
-        lobby.total_bets = lobby
-            .total_bets
-            .checked_add(amount)
-            .ok_or(BettingError::Overflow)?;
-        lobby.updated_at = Clock::get()?.unix_timestamp;
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum MarketMode {
+    Parimutuel,
+    FixedOdds,
+}
 
-        Ok(())
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BetSide {
+    Back,
+    Lay,
+}
+
+impl BetSide {
+    pub fn opposite(&self) -> BetSide {
+        match self {
+            BetSide::Back => BetSide::Lay,
+            BetSide::Lay => BetSide::Back,
+        }
     }
 
-    pub fn update_lobby_status(ctx: Context<UpdateLobbyStatus>, status: LobbyStatus) -> Result<()> {
-        let lobby = &mut ctx.accounts.lobby;
-        require!(lobby.owner == ctx.accounts.owner.key(), BettingError::Unauthorized);
-        lobby.status = status;
-        lobby.updated_at = Clock::get()?.unix_timestamp;
-        Ok(())
+    /// Back bets keep the original `bet` seed so one wallet can hold a back and a
+    /// lay position on the same lobby.
+    pub fn bet_seed(&self) -> &'static [u8] {
+        match self {
+            BetSide::Back => BET_SEED,
+            BetSide::Lay => LAY_BET_SEED,
+        }
     }
+}
 
-    pub fn distribute_single_winning(
-        ctx: Context<DistributeSingleWinning>,
-        winner_name: String,
-    ) -> Result<()> {
-        let lobby = &mut ctx.accounts.lobby;
-        let bet = &mut ctx.accounts.bet;
-        let bettor = &ctx.accounts.bettor;
-        let escrow = &ctx.accounts.escrow;
-        let owner = &ctx.accounts.owner;
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum OrderStatus {
+    Open,
+    Filled,
+    Cancelled,
+}
 
-        require!(lobby.owner == owner.key(), BettingError::Unauthorized);
-        require!(
-            lobby.status == LobbyStatus::Finished,
-            BettingError::LobbyNotFinished
-        );
-        require!(bet.lobby == lobby.key(), BettingError::InvalidBetAccount);
-        require!(bet.bettor == bettor.key(), BettingError::InvalidBettor);
-        require!(bet.player_name == winner_name, BettingError::BetOnWrongPlayer);
-        require!(
-            bet.status == BetStatus::Active,
-            BettingError::BetAlreadyProcessed
-        );
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerCondition {
+    /// Model's share of back stakes drops below the threshold
+    PoolShareBelow,
+    /// Model's share of back stakes rises above the threshold
+    PoolShareAbove,
+    /// Odds for the bet's side reach at least the threshold
+    OddsAtLeast,
+}
 
-        let transfer_amount = bet.amount;
-        let lobby_key = lobby.key();
-        let (_escrow_pda, escrow_bump) = Pubkey::find_program_address(
-            &[b"escrow", lobby_key.as_ref()],
-            ctx.program_id,
-        );
-        let escrow_bump_array = [escrow_bump];
-        let seeds = &[
-            b"escrow",
-            lobby_key.as_ref(),
-            &escrow_bump_array,
-        ];
-        let signer_seeds = &[&seeds[..]];
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LobbyAction {
+    Created,
+    StatusUpdated,
+    AttestationIssuerSet,
+    ComplianceUpdated,
+    BetPlaced,
+    WinningsPaid,
+    PrizePoolBoosted,
+    MatchStakePosted,
+    GameFinished,
+    MarketMakerSet,
+    OddsQuoted,
+    BankrollFunded,
+    MarketMakerWithdrawn,
+    OrderPosted,
+    OrderCancelled,
+    PositionSettled,
+    EscrowSwept,
+    DeadlineSet,
+    PoolsLocked,
+    TranscriptCommitted,
+    OutcomeVerified,
+    ResultAttested,
+    PayoutSwept,
+    SpectatorFeeSet,
+    PayoutRootPosted,
+    PayoutRootChallenged,
+    PayoutRootResolved,
+    BetTreeInitialized,
+    BetRevealed,
+    BlindBetRefunded,
+    BondSlashed,
+    PlayerPoolOpened,
+    PlayerPoolsAggregated,
+    BetCashedOut,
+    ResultsAuthoritySet,
+    CustodySwept,
+    SettlementFunded,
+    CharitySet,
+    PoolWeightingSet,
+    RaffleEntered,
+    RaffleRequested,
+    RaffleDrawn,
+    RafflePrizePaid,
+    BetSelectionChanged,
+    BetReduced,
+    ModelDisqualified,
+    BetRefunded,
+    ContinuationCreated,
+    ChipMarketRolled,
+    TermsAmended,
+    GameConfigFrozen,
+    FreerollFunded,
+    FreerollPicked,
+    FreerollPrizePaid,
+    OperatorReimbursementSet,
+    ReimbursedHandCounted,
+    OperatorReimbursed,
+    OperatorReimbursementReleased,
+    HandMarketOpened,
+    HandsCheckpointed,
+    Archived,
+    MatchStakeRefunded,
+    HandMarketsVoided,
+}
 
-        anchor_lang::solana_program::program::invoke_signed(
-            &anchor_lang::solana_program::system_instruction::transfer(
-                escrow.key,
-                bettor.key,
-                transfer_amount,
-            ),
-            &[
-                escrow.to_account_info(),
-                bettor.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            signer_seeds,
-        )?;
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    /// Placed a bet
+    FirstBet,
+    /// Ten bets paid out
+    TenWins,
+    /// Largest payout entered for calendar week `week`
+    BiggestPayoutOfWeek { week: i64 },
+}
+
+impl Achievement {
+    pub fn seed(&self) -> [u8; 9] {
+        let (kind, week) = match self {
+            Achievement::FirstBet => (0, 0),
+            Achievement::TenWins => (1, 0),
+            Achievement::BiggestPayoutOfWeek { week } => (2, *week),
+        };
+        let mut seed = [kind; 9];
+        seed[1..].copy_from_slice(&week.to_le_bytes());
+        seed
+    }
 
-        bet.status = BetStatus::Paid;
-        lobby.updated_at = Clock::get()?.unix_timestamp;
+    pub fn name(&self) -> String {
+        match self {
+            Achievement::FirstBet => "First Bet".to_string(),
+            Achievement::TenWins => "Ten Wins".to_string(),
+            Achievement::BiggestPayoutOfWeek { week } => format!("Biggest Payout, Week {week}"),
+        }
+    }
 
-        Ok(())
+    fn slug(&self) -> String {
+        match self {
+            Achievement::FirstBet => "first-bet.json".to_string(),
+            Achievement::TenWins => "ten-wins.json".to_string(),
+            Achievement::BiggestPayoutOfWeek { week } => format!("biggest-payout-{week}.json"),
+        }
     }
 }
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct CreateLobby<'info> {
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + Lobby::LEN,
-        seeds = [b"lobby", game_id.as_bytes()],
-        bump
-    )]
-    pub lobby: Account<'info, Lobby>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    #[account(
-        mut,
-        seeds = [b"escrow", lobby.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Escrow PDA for holding bet funds (will be created by System Program on first transfer)
-    pub escrow: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum BountyEvent {
+    /// `player_name` wins a hand
+    HandWonBy { player_name: String },
+    /// A hand's pot reaches `pot` chips
+    PotAtLeast { pot: u64 },
+    /// An event the hand record can't show, such as an all-in pre-flop that
+    /// wins, proven over the hand's decision traces by the bounty circuit
+    Proven { statement: [u8; 32] },
 }
 
-#[derive(Accounts)]
-pub struct PlaceBet<'info> {
-    #[account(
-        mut,
-        seeds = [b"lobby", lobby.game_id.as_bytes()],
-        bump
-    )]
-    pub lobby: Account<'info, Lobby>,
-    #[account(
-        init,
-        payer = bettor,
-        space = 8 + Bet::LEN,
-        seeds = [
-            b"bet",
-            lobby.key().as_ref(),
-            bettor.key().as_ref()
-        ],
-        bump
-    )]
-    pub bet: Account<'info, Bet>,
-    #[account(mut)]
-    pub bettor: Signer<'info>,
-    #[account(
-        mut,
-        seeds = [b"escrow", lobby.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Escrow PDA (System Program owned, receives SOL via CPI)
-    pub escrow: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
+impl BountyEvent {
+    pub const LEN: usize = 1 + 4 + 32;
 }
 
-#[derive(Accounts)]
-pub struct UpdateLobbyStatus<'info> {
-    #[account(
-        mut,
-        seeds = [b"lobby", lobby.game_id.as_bytes()],
-        bump
-    )]
-    pub lobby: Account<'info, Lobby>,
-    pub owner: Signer<'info>,
+/// One model's share of a lobby's back stake, kept out of the lobby account so
+/// bets on different models can execute in parallel. Stake and fees sit in the
+/// pool's own lamports until aggregated.
+#[account]
+pub struct PlayerPool {
+    pub lobby: Pubkey,
+    pub model_index: u8,
+    /// Stake not yet moved into the lobby's escrow and pools
+    pub stake: u64,
+    /// Protocol fees not yet paid to the treasury
+    pub fees: u64,
+    pub bets: u32,
+    /// Bets not yet counted in the lobby's `outstanding_bets`
+    pub pending_bets: u32,
 }
 
-#[derive(Accounts)]
-#[instruction(winner_name: String)]
-pub struct DistributeSingleWinning<'info> {
-    #[account(
-        mut,
-        seeds = [b"lobby", lobby.game_id.as_bytes()],
-        bump
-    )]
-    pub lobby: Account<'info, Lobby>,
-    #[account(
-        mut,
-        seeds = [
-            b"bet",
-            lobby.key().as_ref(),
-            bettor.key().as_ref()
-        ],
-        bump
-    )]
-    pub bet: Account<'info, Bet>,
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    #[account(
-        mut,
-        seeds = [b"escrow", lobby.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Escrow PDA (System Program owned, sends SOL via CPI)
-    pub escrow: UncheckedAccount<'info>,
-    #[account(mut)]
-    /// CHECK: Bettor receives the funds
-    pub bettor: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
+impl PlayerPool {
+    pub const LEN: usize = 32 + 1 + 8 + 8 + 4 + 4;
 }
 
-#[account]
-pub struct Lobby {
-    pub owner: Pubkey,
-    pub game_id: String,
-    pub model_names: Vec<String>,
-    pub starting_chips: u64,
-    pub small_blind: u64,
-    pub big_blind: u64,
-    pub max_hands: u64,
+/// Config update that goes through the admin timelock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum ConfigChange {
+    FeeBps(u16),
+    Admin(Pubkey),
+    AdminTimelock(i64),
+    Custody {
+        custody: Option<Pubkey>,
+        threshold: u64,
+    },
+    MinPayout(u64),
+    FeeTiers(Vec<FeeTier>),
+    RevenueShareBps(u16),
+    RaffleBps(u16),
+    SelectionChangeFeeBps(u16),
+    BondRequirements {
+        min_operator_bond: u64,
+        settlement_window: i64,
+    },
+    ExpectedUpgradeAuthority(Option<Pubkey>),
+}
+
+impl ConfigChange {
+    /// Sized for `FeeTiers`, the largest variant
+    pub const LEN: usize = 1 + (4 + FeeTier::LEN * MAX_FEE_TIERS);
+
+    /// Fails unless the change is valid against the rest of `config`.
+    pub fn check(&self, config: &Config) -> Result<()> {
+        match self {
+            Self::FeeBps(fee_bps) => {
+                require!(*fee_bps <= MAX_FEE_BPS, BettingError::InvalidFeeBps)
+            }
+            Self::AdminTimelock(delay) => {
+                require!(*delay >= 0, BettingError::InvalidRateLimit)
+            }
+            // Higher tiers need more volume and charge no more
+            Self::FeeTiers(fee_tiers) => require!(
+                fee_tiers.len() <= MAX_FEE_TIERS
+                    && fee_tiers.iter().all(|tier| tier.fee_bps <= config.fee_bps)
+                    && fee_tiers.windows(2).all(|pair| {
+                        pair[0].min_volume < pair[1].min_volume
+                            && pair[0].fee_bps >= pair[1].fee_bps
+                    }),
+                BettingError::InvalidFeeTiers
+            ),
+            Self::RevenueShareBps(bps) | Self::RaffleBps(bps) | Self::SelectionChangeFeeBps(bps) => {
+                require!(
+                    *bps as u64 <= math::BPS_DENOMINATOR,
+                    BettingError::InvalidFeeBps
+                )
+            }
+            Self::BondRequirements {
+                settlement_window, ..
+            } => require!(*settlement_window >= 0, BettingError::InvalidRateLimit),
+            Self::Admin(_)
+            | Self::Custody { .. }
+            | Self::MinPayout(_)
+            | Self::ExpectedUpgradeAuthority(_) => {}
+        }
+        Ok(())
+    }
+
+    pub fn apply(self, config: &mut Config) {
+        match self {
+            Self::FeeBps(fee_bps) => config.fee_bps = fee_bps,
+            Self::Admin(admin) => config.admin = admin,
+            Self::AdminTimelock(delay) => config.admin_timelock = delay,
+            Self::Custody { custody, threshold } => {
+                config.custody = custody;
+                config.custody_threshold = threshold;
+            }
+            Self::MinPayout(min_payout_lamports) => config.min_payout_lamports = min_payout_lamports,
+            Self::FeeTiers(fee_tiers) => config.fee_tiers = fee_tiers,
+            Self::RevenueShareBps(bps) => config.revenue_share_bps = bps,
+            Self::RaffleBps(bps) => config.raffle_bps = bps,
+            Self::SelectionChangeFeeBps(bps) => config.selection_change_fee_bps = bps,
+            Self::BondRequirements {
+                min_operator_bond,
+                settlement_window,
+            } => {
+                config.min_operator_bond = min_operator_bond;
+                config.settlement_window = settlement_window;
+            }
+            Self::ExpectedUpgradeAuthority(authority) => {
+                config.program_frozen = authority.is_none();
+                config.expected_upgrade_authority = authority;
+            }
+        }
+    }
+}
+
+/// Return data of `verify_program_authority`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ProgramAuthority {
+    /// `None` once the program is frozen
+    pub upgrade_authority: Option<Pubkey>,
+    /// Config records an expected authority, which `upgrade_authority` matched
+    pub recorded: bool,
+}
+
+/// Return data of `get_lobby_summary`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct LobbySummary {
     pub status: LobbyStatus,
+    pub market_mode: MarketMode,
+    pub model_names: Vec<String>,
+    pub model_pools: Vec<u64>,
+    pub lay_pools: Vec<u64>,
+    /// Current back odds of each model, in basis points; zero when unpriced
+    pub odds_bps: Vec<u64>,
     pub total_bets: u64,
-    pub created_at: i64,
-    pub updated_at: i64,
+    pub sponsor_pool: u64,
+    pub betting_closed: bool,
+    pub winner: Option<String>,
+    pub seq: u64,
+    /// Charity taking `charity_bps` of the losing pool in a charity round
+    pub charity: Option<Pubkey>,
+    pub charity_bps: u16,
+    /// Sponsor prize for free picks, and the picks on each model
+    pub freeroll_pool: u64,
+    pub freeroll_picks: Vec<u64>,
+    pub weighting: PoolWeighting,
 }
 
-impl Lobby {
-    pub const LEN: usize = 32 + 4 + 32 + 4 + (4 + 32) * 10 + 8 + 8 + 8 + 8  + 8 + 8 + 8;
+/// A registered keeper. Its stake is held in this account above its rent.
+#[account]
+pub struct Keeper {
+    pub authority: Pubkey,
+    pub stake: u64,
+    /// Crank fees earned and not yet claimed
+    pub fees_owed: u64,
+    /// Crank steps it was paid for
+    pub cranks: u64,
+    pub registered_at: i64,
+    /// When it retired and stopped earning; zero while active
+    pub retired_at: i64,
+    /// Lobby it committed to crank by `crank_by`
+    pub committed_lobby: Option<Pubkey>,
+    pub crank_by: i64,
+}
+
+impl Keeper {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + (1 + 32) + 8;
 }
 
+/// Lobby settings an operator fixes once and opens lobbies from with
+/// `create_lobby_from_template`, so a series runs on the same terms
+/// throughout. Templates can't be changed, only closed.
 #[account]
-pub struct Bet {
-    pub bettor: Pubkey,
-    pub lobby: Pubkey,
-    pub player_name: String,
-    pub amount: u64,
-    pub placed_at: i64,
-    pub status: BetStatus,
+pub struct LobbyTemplate {
+    pub owner: Pubkey,
+    pub name: String,
+    pub terms: LobbyTerms,
+    /// Lobbies opened from it
+    pub lobbies_created: u64,
+    pub created_at: i64,
 }
 
-impl Bet {
-    pub const LEN: usize = 32 + 32 + 4 + 32 + 8 + 8 ;
+impl LobbyTemplate {
+    pub const LEN: usize = 32 + (4 + MAX_TEMPLATE_NAME_LEN) + LobbyTerms::LEN + 8 + 8;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
@@ -303,13 +12541,214 @@ pub enum LobbyStatus {
     Waiting,
     Running,
     Finished,
+    /// Fully settled and shrunk to an audit stub by `archive_lobby`
+    Archived,
+}
+
+impl LobbyStatus {
+    /// Finished, whether or not the lobby has been archived since.
+    pub fn is_over(&self) -> bool {
+        matches!(self, LobbyStatus::Finished | LobbyStatus::Archived)
+    }
+
+    /// Whether a lobby may move from this status to `next`. A started lobby
+    /// never goes back to Waiting, so terms that can change only while it
+    /// waits stay fixed, and Finished and Archived lobbies never go back.
+    pub fn can_become(&self, next: &LobbyStatus) -> bool {
+        use LobbyStatus::*;
+        matches!(
+            (self, next),
+            (Waiting, Running) | (Waiting | Running, Finished) | (Finished, Archived)
+        )
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
 pub enum BetStatus {
+    /// Placed; betting is still open
     Active,
     Paid,
     Refunded,
+    /// Won less than the minimum payout; swept to the treasury
+    BelowMinimum,
+    /// Betting has closed and the stake is committed
+    Locked,
+    /// The lobby finished and the bet is owed a payout
+    Won,
+    Lost,
+    /// Stake withdrawn by the bettor before betting closed
+    CashedOut,
+    /// Result held while the lobby's payout root is challenged
+    Disputed,
+}
+
+impl BetStatus {
+    /// Whether the bet may still be paid out of escrow.
+    pub fn is_outstanding(&self) -> bool {
+        matches!(
+            self,
+            BetStatus::Active | BetStatus::Locked | BetStatus::Won | BetStatus::Disputed
+        )
+    }
+
+    /// Whether a bet may move from this status to `next`.
+    pub fn can_become(&self, next: &BetStatus) -> bool {
+        use BetStatus::*;
+        matches!(
+            (self, next),
+            (Active, Locked)
+                | (Active | Locked, Won | Lost | Refunded)
+                | (Active | Locked | Won, Paid | BelowMinimum)
+                | (Active, CashedOut)
+                | (Won | Lost, Disputed)
+                | (Disputed, Won | Lost)
+        )
+    }
+}
+
+#[event]
+pub struct MessagePosted {
+    pub lobby: Pubkey,
+    /// Lobby `seq` at the time of posting; the feed has its own `sequence`
+    pub lobby_seq: u64,
+    pub author: Pubkey,
+    pub sequence: u64,
+    pub text: String,
+    pub posted_at: i64,
+}
+
+#[event]
+pub struct PrizePoolBoosted {
+    pub lobby: Pubkey,
+    pub seq: u64,
+    pub sponsor: Pubkey,
+    pub amount: u64,
+    pub sponsor_pool: u64,
+}
+
+#[event]
+pub struct EscrowSwept {
+    pub lobby: Pubkey,
+    pub seq: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LobbyUpdated {
+    pub lobby: Pubkey,
+    pub seq: u64,
+    pub action: LobbyAction,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct SpectatorPassPurchased {
+    pub lobby: Pubkey,
+    pub viewer: Pubkey,
+    pub paid: u64,
+    pub purchased_at: i64,
+}
+
+#[event]
+pub struct CompressedBetPlaced {
+    pub bet: CompressedBet,
+}
+
+#[event]
+pub struct ShieldedNoteDeposited {
+    pub commitment: [u8; 32],
+    pub leaf_index: u32,
+    pub root: [u8; 32],
+}
+
+#[event]
+pub struct BountyFunded {
+    pub bounty: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+}
+
+#[event]
+pub struct BountyClaimed {
+    pub bounty: Pubkey,
+    pub hand: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeeTierApplied {
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub tier: u8,
+    pub fee_bps: u16,
+    /// Lamports the tier took off the base fee
+    pub rebate: u64,
+}
+
+#[event]
+pub struct BetPlaced {
+    pub lobby: Pubkey,
+    pub bet: Pubkey,
+    pub bettor: Pubkey,
+    pub player_name: String,
+    pub side: BetSide,
+    /// Stake after fees
+    pub amount: u64,
+    pub client_id: [u8; 16],
+    pub memo: Option<String>,
+}
+
+#[event]
+pub struct BetStatusChanged {
+    pub bet: Pubkey,
+    pub lobby: Pubkey,
+    pub status: BetStatus,
+}
+
+#[event]
+pub struct ConfigChangeProposed {
+    pub change: ConfigChange,
+    /// Earliest time the change can be executed
+    pub eta: i64,
+}
+
+#[event]
+pub struct ConfigChangeExecuted {
+    pub change: ConfigChange,
+}
+
+#[event]
+pub struct ConfigChangeCancelled {
+    pub change: ConfigChange,
+}
+
+/// USD accounting of a bet that was paid, cashed out or lost, for reporting.
+#[event]
+pub struct BetSettled {
+    pub bet: Pubkey,
+    pub lobby: Pubkey,
+    pub bettor: Pubkey,
+    pub status: BetStatus,
+    /// Lamports returned to the bettor
+    pub payout: u64,
+    /// Micro-USD per SOL when the bet was placed; zero when unpriced
+    pub usd_per_sol: u64,
+    pub usd_value: u64,
+    pub usd_payout: u64,
+    pub usd_pnl: i64,
+}
+
+#[event]
+pub struct KeeperSlashed {
+    pub keeper: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+    /// Stake left after the slash
+    pub stake: u64,
+    /// Lobby whose crank it missed
+    pub lobby: Pubkey,
 }
 
 #[error_code]
@@ -334,4 +12773,401 @@ pub enum BettingError {
     BetAlreadyProcessed,
     #[msg("Arithmetic overflow.")]
     Overflow,
+    #[msg("Fee exceeds the maximum allowed.")]
+    InvalidFeeBps,
+    #[msg("Protocol is paused.")]
+    ProtocolPaused,
+    #[msg("Account is not owned by the SPL Governance program.")]
+    InvalidGovernance,
+    #[msg("Treasury balance is insufficient.")]
+    InsufficientTreasury,
+    #[msg("Epoch duration must be positive.")]
+    InvalidEpochDuration,
+    #[msg("Current epoch has not elapsed yet.")]
+    EpochNotElapsed,
+    #[msg("Stake amount must be positive.")]
+    StakeAmountMustBePositive,
+    #[msg("Insufficient staked balance.")]
+    InsufficientStake,
+    #[msg("Nothing to claim.")]
+    NothingToClaim,
+    #[msg("Rate limit parameters must not be negative.")]
+    InvalidRateLimit,
+    #[msg("Too many bets in the current window.")]
+    RateLimited,
+    #[msg("Lobby creation cooldown has not elapsed.")]
+    LobbyCreationCooldown,
+    #[msg("Lobby has already started.")]
+    LobbyAlreadyStarted,
+    #[msg("This lobby requires an attestation.")]
+    AttestationRequired,
+    #[msg("Attestation is invalid for this bettor or lobby.")]
+    InvalidAttestation,
+    #[msg("Attestation has expired.")]
+    AttestationExpired,
+    #[msg("Too many restricted regions.")]
+    TooManyRegions,
+    #[msg("Bettor's jurisdiction is restricted for this lobby.")]
+    RegionRestricted,
+    #[msg("Exclusion must end in the future.")]
+    InvalidExclusionPeriod,
+    #[msg("Self-exclusion cannot be shortened.")]
+    ExclusionCannotBeShortened,
+    #[msg("Wallet is self-excluded from betting.")]
+    SelfExcluded,
+    #[msg("Bet exceeds the wallet's wager limit.")]
+    WagerLimitExceeded,
+    #[msg("Bet exceeds the wallet's loss limit.")]
+    LossLimitExceeded,
+    #[msg("Message must be between 1 and 140 bytes.")]
+    InvalidMessageLength,
+    #[msg("A lobby needs between 1 and 10 models.")]
+    InvalidModelCount,
+    #[msg("Lobby has already finished.")]
+    LobbyAlreadyFinished,
+    #[msg("Match stake has already been posted.")]
+    MatchStakeAlreadyPosted,
+    #[msg("Winning operator has not posted a match stake.")]
+    MatchStakeNotPosted,
+    #[msg("Match stake account does not match the winner.")]
+    InvalidMatchStake,
+    #[msg("Quoted odds are stale.")]
+    StaleQuote,
+    #[msg("Odds are invalid for this lobby.")]
+    InvalidOdds,
+    #[msg("Market maker liquidity cannot cover this bet.")]
+    InsufficientLiquidity,
+    #[msg("Odds moved below the bettor's minimum.")]
+    SlippageExceeded,
+    #[msg("Order is not open.")]
+    OrderNotOpen,
+    #[msg("Orders do not cross.")]
+    OrdersDoNotCross,
+    #[msg("Lay bets are only available in parimutuel lobbies.")]
+    LayRequiresParimutuel,
+    #[msg("Deposit balance is insufficient.")]
+    InsufficientDeposit,
+    #[msg("Deadline has passed or is invalid.")]
+    InvalidDeadline,
+    #[msg("Trigger condition is not met.")]
+    ConditionNotMet,
+    #[msg("Following is paused.")]
+    FollowPaused,
+    #[msg("Payout exceeds the escrow's tracked balance.")]
+    EscrowUnderflow,
+    #[msg("Betting is closed for this lobby.")]
+    BettingClosed,
+    #[msg("Pool snapshot account is required.")]
+    SnapshotRequired,
+    #[msg("Game has not been finalized by the poker engine.")]
+    GameNotFinalized,
+    #[msg("Game transcript is already committed.")]
+    TranscriptAlreadyCommitted,
+    #[msg("Game transcript has not been committed.")]
+    TranscriptNotCommitted,
+    #[msg("Winner has already been verified.")]
+    WinnerAlreadyVerified,
+    #[msg("Verifying key does not match the outcome circuit.")]
+    InvalidVerifyingKey,
+    #[msg("Public input is not a valid field element.")]
+    InvalidPublicInput,
+    #[msg("Outcome proof failed verification.")]
+    InvalidProof,
+    #[msg("No enclave measurement and signer are configured.")]
+    EnclaveNotConfigured,
+    #[msg("Result is not signed by the configured enclave.")]
+    InvalidEnclaveSignature,
+    #[msg("VAA is not a valid cross-chain bet for these accounts.")]
+    InvalidVaa,
+    #[msg("VAA was not sent by the registered emitter.")]
+    UnknownEmitter,
+    #[msg("Payout recipient does not match the bet's payout address.")]
+    InvalidPayoutRecipient,
+    #[msg("Claim accounts must be groups of lobby, bet, escrow, snapshot and recipient.")]
+    InvalidClaimAccounts,
+    #[msg("Wallet is on the screening denylist.")]
+    WalletDenied,
+    #[msg("Denylist is full.")]
+    DenylistFull,
+    #[msg("Payout curve must sum to 10000 bps over at most one position per model, and is parimutuel only.")]
+    InvalidPayoutCurve,
+    #[msg("Placings must list distinct models in finishing order, starting with the declared winner, with an eligible model for every paid position.")]
+    InvalidPlacings,
+    #[msg("Lay bets are only available on winner-takes-pool lobbies.")]
+    LayRequiresWinnerTakesAll,
+    #[msg("Chip buckets must be positive, strictly ascending bounds, fewer than the bucket limit.")]
+    InvalidChipBuckets,
+    #[msg("Lobby has no such chip-count bucket.")]
+    InvalidChipBucket,
+    #[msg("Final chip counts must cover every model and add up to the chips in play.")]
+    InvalidFinalChips,
+    #[msg("Spectator passes are not on sale for this lobby.")]
+    SpectatorPassesNotForSale,
+    #[msg("Achievement metadata URI is too long.")]
+    AchievementUriTooLong,
+    #[msg("Achievement has not been earned.")]
+    AchievementNotEarned,
+    #[msg("Week is not the current one, or has not ended yet.")]
+    InvalidAchievementWeek,
+    #[msg("Lobby settles either by payout root or bet by bet, not both.")]
+    MerkleSettlement,
+    #[msg("Payout root is missing or does not cover this claim.")]
+    InvalidPayoutRoot,
+    #[msg("Payout root challenge window has closed.")]
+    ChallengeWindowClosed,
+    #[msg("Payout root is challenged or still in its challenge window.")]
+    ChallengeWindowOpen,
+    #[msg("Merkle proof does not match the root.")]
+    InvalidMerkleProof,
+    #[msg("Compressed bets need the lobby's bet tree and a parimutuel winner-takes-pool market.")]
+    CompressedBetsUnsupported,
+    #[msg("Shielded note tree is full.")]
+    ShieldedPoolFull,
+    #[msg("Root is not one of the shielded pool's recent roots.")]
+    UnknownShieldedRoot,
+    #[msg("Blind bets need a reveal deadline and a parimutuel market.")]
+    BlindBetsDisabled,
+    #[msg("Reveal deadline must come after the betting deadline.")]
+    InvalidRevealDeadline,
+    #[msg("Blind bet reveal window is still open.")]
+    RevealWindowOpen,
+    #[msg("Blind bets can only be revealed between the betting and reveal deadlines.")]
+    RevealWindowClosed,
+    #[msg("Revealed model and salt do not match the commitment.")]
+    CommitmentMismatch,
+    #[msg("Bounty has already been claimed.")]
+    BountyClaimed,
+    #[msg("Hand does not satisfy the bounty's event.")]
+    BountyEventNotMet,
+    #[msg("Operator bond has been slashed.")]
+    BondSlashed,
+    #[msg("Lobby has neither lost a dispute nor missed its settlement deadline.")]
+    BondNotSlashable,
+    #[msg("Operator bond is held after a lost or pending dispute.")]
+    BondNotWithdrawable,
+    #[msg("Operator reputation is below the minimum to create a lobby.")]
+    ReputationTooLow,
+    #[msg("Fee tiers must ascend in volume, not rise in fee, and not exceed the base fee.")]
+    InvalidFeeTiers,
+    #[msg("Pooled bets are only taken in parimutuel lobbies")]
+    PooledBetRequiresParimutuel,
+    #[msg("Every player pool of the lobby must be passed once")]
+    InvalidPlayerPools,
+    #[msg("Player pools must be aggregated by locking the pools first")]
+    PlayerPoolsNotAggregated,
+    #[msg("Bet memo is too long")]
+    MemoTooLong,
+    #[msg("A bet already exists for this wallet, lobby and side")]
+    BetAlreadyPlaced,
+    #[msg("Client ID was already used for a different bet")]
+    ClientIdReused,
+    #[msg("Bet cannot move to that status from its current one")]
+    InvalidBetTransition,
+    #[msg("Betting is still open on this lobby")]
+    BettingStillOpen,
+    #[msg("Only parimutuel bets in lobbies without player pools can be cashed out")]
+    CashOutUnavailable,
+    #[msg("Bet is on a winning model")]
+    BetWon,
+    #[msg("Bet has not been settled")]
+    BetNotSettled,
+    #[msg("Instructions sysvar is required while the bet composition guard is on")]
+    InstructionsSysvarRequired,
+    #[msg("Bets cannot share a transaction with settlement or status updates")]
+    BetComposedWithSettlement,
+    #[msg("The lobby's results authority must co-sign")]
+    ResultsAuthorityRequired,
+    #[msg("No results authority change is pending")]
+    NoPendingResultsAuthority,
+    #[msg("Results authority change is still in its delay")]
+    ResultsAuthorityDelayActive,
+    #[msg("Change must wait for the admin timelock")]
+    TimelockActive,
+    #[msg("A config change is already pending")]
+    ConfigChangePending,
+    #[msg("No config change is pending")]
+    NoPendingConfigChange,
+    #[msg("Program upgrade authority differs from the recorded one")]
+    UpgradeAuthorityMismatch,
+    #[msg("Account is not this program's program data")]
+    InvalidProgramData,
+    #[msg("Swap receipt does not match this bet")]
+    InvalidSwapReceipt,
+    #[msg("Swap receipt was recorded in an earlier slot")]
+    SwapReceiptExpired,
+    #[msg("Swap output is below the minimum")]
+    SwapOutputTooLow,
+    #[msg("Account is not a verified Pyth price update for the configured feed")]
+    InvalidPriceUpdate,
+    #[msg("Price update is too old")]
+    StalePrice,
+    #[msg("Price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("A Pyth price update is required")]
+    PriceUpdateRequired,
+    #[msg("Bet is outside the USD bet bounds")]
+    BetOutsideUsdBounds,
+    #[msg("Invalid USD pricing settings")]
+    InvalidPriceSettings,
+    #[msg("Custody account does not match")]
+    InvalidCustody,
+    #[msg("Escrow swept to custody must be returned before settlement")]
+    FundsInCustody,
+    #[msg("Rake splits must be distinct recipients whose shares sum to 10000 bps")]
+    InvalidRakeSplits,
+    #[msg("Charity rounds need a charity, a share of at most 10000 bps and a parimutuel market")]
+    InvalidCharity,
+    #[msg("This lobby has no freeroll prize")]
+    FreerollNotFunded,
+    #[msg("Streak prizes must be nonzero and never decrease")]
+    InvalidStreakPrizes,
+    #[msg("Lobby is not the next one in this streak series")]
+    InvalidStreakLobby,
+    #[msg("Weighted pools need a winner-take-all parimutuel lobby without player pools")]
+    InvalidPoolWeighting,
+    #[msg("Weighted pools only take plain back bets")]
+    WeightedPoolUnsupported,
+    #[msg("Randomness account is not the VRF request for this raffle")]
+    InvalidRandomness,
+    #[msg("VRF randomness has not been fulfilled yet")]
+    RandomnessNotFulfilled,
+    #[msg("The raffle is not at this stage")]
+    RaffleClosed,
+    #[msg("Raffle entries are still open")]
+    RaffleEntriesOpen,
+    #[msg("Bet is not an eligible raffle entry")]
+    NotARaffleEntry,
+    #[msg("Bet holds a raffle entry that may still win")]
+    RafflePending,
+    #[msg("Only parimutuel bets outside player pools can switch models")]
+    SelectionChangeUnavailable,
+    #[msg("Reduce amount must be positive and less than the stake")]
+    InvalidReduceAmount,
+    #[msg("Model can't be disqualified while pooled, compressed, blind or order book stake is open, or if it is the last one")]
+    DisqualificationUnavailable,
+    #[msg("Model has been disqualified")]
+    ModelDisqualified,
+    #[msg("Bet is on a disqualified model and must be refunded")]
+    BetVoided,
+    #[msg("Bet is not on a disqualified model")]
+    BetNotVoided,
+    #[msg("Parent lobby is finished, already continued, or not this lobby's parent")]
+    InvalidParentLobby,
+    #[msg("Game terms are final once betting closes")]
+    TermsLocked,
+    #[msg("Lobby has reached the amendment limit")]
+    TooManyAmendments,
+    #[msg("Blinds schedule must have escalating levels of positive blinds, each at least one hand long")]
+    InvalidBlinds,
+    #[msg("Lobby has not started yet")]
+    LobbyNotStarted,
+    #[msg("Reimbursement share can't exceed the whole rake")]
+    InvalidReimbursementShare,
+    #[msg("Lobby doesn't reimburse its model operators")]
+    NoOperatorReimbursement,
+    #[msg("Hands must be counted in order")]
+    HandOutOfOrder,
+    #[msg("Every hand must be counted before reimbursements are claimed")]
+    HandsNotCounted,
+    #[msg("Reimbursement claim window has closed")]
+    ReimbursementClosed,
+    #[msg("Reimbursement claim window is still open")]
+    ReimbursementOpen,
+    #[msg("Hand has already been played or checkpointed")]
+    HandMarketClosed,
+    #[msg("Lobby has too many open hand markets")]
+    TooManyHandMarkets,
+    #[msg("Hand range must start at the last checkpoint and end at a recorded hand")]
+    InvalidHandRange,
+    #[msg("Hand market or hand record can't be settled here")]
+    InvalidHandMarket,
+    #[msg("Every open hand market in the range must be settled")]
+    HandMarketsUnsettled,
+    #[msg("Hand market has not been settled")]
+    HandMarketNotSettled,
+    #[msg("Game id is too long")]
+    GameIdTooLong,
+    #[msg("Lobby still has payouts, refunds or escrowed funds outstanding")]
+    LobbyNotSettled,
+    #[msg("Lobby has been archived")]
+    LobbyArchived,
+    #[msg("Keeper stake is below the minimum.")]
+    KeeperStakeTooLow,
+    #[msg("A keeper crank needs the config account.")]
+    InvalidKeeperAccounts,
+    #[msg("Keeper has retired.")]
+    KeeperRetired,
+    #[msg("Keeper stake is still unbonding.")]
+    KeeperUnbonding,
+    #[msg("Template name must be 1 to 32 bytes.")]
+    InvalidTemplateName,
+    #[msg("Only the program's upgrade authority can initialize the config")]
+    NotUpgradeAuthority,
+    #[msg("Match stakes can no longer be registered or posted.")]
+    MatchStakeWindowClosed,
+    #[msg("The match is on; its stakes are paid at finish.")]
+    MatchStillOn,
+    #[msg("A lobby can only finish through finish_game.")]
+    FinishThroughFinishGame,
+    #[msg("Bets on the lobby are still unsettled.")]
+    BetsOutstanding,
+    #[msg("The bet is not owed compensation from the slashed bond.")]
+    NotCompensable,
+    #[msg("Lobby cannot move to that status from its current one")]
+    InvalidLobbyTransition,
+    #[msg("Keeper has an open crank commitment")]
+    KeeperCommitted,
+    #[msg("Keeper has no crank commitment past its deadline")]
+    KeeperNotSlashable,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    /// What a handler that settles a lobby or its bets, or changes the
+    /// lobby's status, does
+    const SETTLES: [&str; 19] = [
+        "transition_lobby(",
+        "transition_bet(",
+        "pay_winning_bet(",
+        "LobbyAction::StatusUpdated",
+        "LobbyAction::GameFinished",
+        "LobbyAction::PoolsLocked",
+        "LobbyAction::OutcomeVerified",
+        "LobbyAction::ResultAttested",
+        "LobbyAction::WinningsPaid",
+        "LobbyAction::PayoutSwept",
+        "LobbyAction::EscrowSwept",
+        "LobbyAction::PayoutRootPosted",
+        "LobbyAction::PayoutRootChallenged",
+        "LobbyAction::PayoutRootResolved",
+        "LobbyAction::ModelDisqualified",
+        "LobbyAction::HandsCheckpointed",
+        "LobbyAction::HandMarketsVoided",
+        "LobbyAction::BetRefunded",
+        "LobbyAction::BlindBetRefunded",
+    ];
+
+    #[test]
+    fn every_settling_instruction_is_kept_out_of_bets() {
+        let source = include_str!("lib.rs");
+        let start = source.find("pub mod poker_betting {").unwrap();
+        let end = start + source[start..].find("\n}\n").unwrap();
+        let mut settling = 0;
+        for handler in source[start..end].split("\n    pub fn ").skip(1) {
+            if !SETTLES.iter().any(|marker| handler.contains(marker)) {
+                continue;
+            }
+            let name = handler.split(['(', '<']).next().unwrap();
+            let discriminator = &hash(format!("global:{name}").as_bytes()).to_bytes()[..8];
+            assert!(
+                SETTLEMENT_INSTRUCTIONS.contains(&discriminator.try_into().unwrap()),
+                "{name} settles but isn't in SETTLEMENT_INSTRUCTIONS"
+            );
+            settling += 1;
+        }
+        assert_eq!(settling, SETTLEMENT_INSTRUCTIONS.len());
+    }
 }
@@ -0,0 +1,1510 @@
+//! Betting and settlement rules as plain functions over account data.
+//!
+//! Nothing here touches `AccountInfo`s, CPIs or sysvars, so these run the same
+//! natively as on chain. Handlers check the accounts they were passed, call
+//! in here, then move the lamports.
+
+use anchor_lang::prelude::*;
+
+use crate::math::{self, fixed_odds_payout, ODDS_SCALE};
+use crate::{
+    Bet, BetSide, BetStatus, BettingError, Config, ConfigChange, HandBet, HandMarket, Keeper,
+    Lobby, LobbyStatus, MarketMode, PoolSnapshot, PoolWeighting, KEEPER_FEE_WINDOW,
+    MAX_OPEN_HAND_MARKETS,
+};
+
+/// What a winning bet is paid, and whether it goes to the treasury for being
+/// below the payout minimum.
+pub struct Payout {
+    pub amount: u64,
+    pub below_minimum: bool,
+}
+
+/// Settles `bet` as a winner against the lobby's declared winner and records
+/// the payout on the lobby. The bet's status is left to the caller.
+pub fn settle_winning_bet(
+    lobby: &mut Lobby,
+    bet: &Bet,
+    snapshot: Option<&PoolSnapshot>,
+    config: &Config,
+) -> Result<Payout> {
+    require!(lobby.status.is_over(), BettingError::LobbyNotFinished);
+    require!(lobby.payout_root.is_none(), BettingError::MerkleSettlement);
+    require!(lobby.in_custody == 0, BettingError::FundsInCustody);
+    let winner_name = lobby.winner.clone().ok_or(BettingError::LobbyNotFinished)?;
+    let winner_name = winner_name.as_str();
+    let position = winning_position(lobby, bet, winner_name)?;
+    require!(
+        bet.status.can_become(&BetStatus::Paid),
+        BettingError::BetAlreadyProcessed
+    );
+
+    let amount = if bet.odds_bps > 0 {
+        fixed_odds_payout(bet.amount, bet.odds_bps)?
+    } else {
+        let (distributable, winning_stake) =
+            parimutuel_terms(lobby, bet, winner_name, snapshot, position)?;
+        let weight = lobby.weighting.weight(bet.amount);
+        lobby.settle_parimutuel(position, weight, distributable, winning_stake)?
+    };
+    lobby.track_withdrawal(amount)?;
+    if bet.odds_bps > 0 {
+        lobby.fixed_odds_paid = lobby
+            .fixed_odds_paid
+            .checked_add(amount)
+            .ok_or(BettingError::Overflow)?;
+    }
+    Ok(Payout {
+        amount,
+        below_minimum: amount < config.min_payout_lamports,
+    })
+}
+
+/// Paid position a winning bet settles at; always 0 for winner-takes-pool.
+pub fn winning_position(lobby: &Lobby, bet: &Bet, winner_name: &str) -> Result<usize> {
+    Ok(match bet.side {
+        BetSide::Back if lobby.payout_curve.len() > 1 => lobby
+            .placings
+            .iter()
+            .position(|name| *name == bet.player_name)
+            .ok_or(BettingError::BetOnWrongPlayer)?,
+        BetSide::Back => {
+            require!(bet.player_name == winner_name, BettingError::BetOnWrongPlayer);
+            0
+        }
+        BetSide::Lay => {
+            require!(bet.player_name != winner_name, BettingError::BetOnWrongPlayer);
+            0
+        }
+    })
+}
+
+/// Pool a parimutuel bet's `position` distributes and the stake sharing it,
+/// read from the snapshot once the lobby's pools are locked. In a weighted
+/// lobby the pool is shared by weight, so claims pass the bet's weight.
+pub fn parimutuel_terms(
+    lobby: &Lobby,
+    bet: &Bet,
+    winner_name: &str,
+    snapshot: Option<&PoolSnapshot>,
+    position: usize,
+) -> Result<(u64, u64)> {
+    let winner_index = lobby.model_index(winner_name)?;
+    let (distributable, winning_stake, model_pools) = pool_totals(lobby, snapshot, winner_index)?;
+    let distributable = distributable
+        .checked_sub(lobby.charity_amount)
+        .ok_or(BettingError::Overflow)?;
+    if lobby.payout_curve.len() > 1 {
+        let share = lobby.position_shares(model_pools)?[position];
+        let placed_stake = model_pools[lobby.model_index(&bet.player_name)?];
+        Ok((math::apply_bps(distributable, share)?, placed_stake))
+    } else if lobby.weighting != PoolWeighting::Stake {
+        Ok((distributable, lobby.model_weights[winner_index]))
+    } else {
+        Ok((distributable, winning_stake))
+    }
+}
+
+/// Pool, winning stake and back pools at settlement, read from the snapshot
+/// once the lobby's pools are locked.
+fn pool_totals<'a>(
+    lobby: &'a Lobby,
+    snapshot: Option<&'a PoolSnapshot>,
+    winner_index: usize,
+) -> Result<(u64, u64, &'a [u64])> {
+    if lobby.pools_locked {
+        let snapshot = snapshot.ok_or(BettingError::SnapshotRequired)?;
+        let (distributable, winning_stake) = snapshot.parimutuel_totals(winner_index)?;
+        Ok((distributable, winning_stake, &snapshot.model_pools))
+    } else {
+        let (distributable, winning_stake) = lobby.parimutuel_totals(winner_index)?;
+        Ok((distributable, winning_stake, &lobby.model_pools))
+    }
+}
+
+/// Pool of a finished parimutuel lobby that no bet can claim because nobody
+/// backed a paid position, less what has been swept already; zero once any
+/// paid position was backed.
+pub fn unwon_pool(lobby: &Lobby, snapshot: Option<&PoolSnapshot>) -> Result<u64> {
+    let winner_name = lobby.winner.as_deref().ok_or(BettingError::LobbyNotFinished)?;
+    let (distributable, winning_stake, model_pools) =
+        pool_totals(lobby, snapshot, lobby.model_index(winner_name)?)?;
+    if winning_stake > 0 {
+        return Ok(0);
+    }
+    for name in &lobby.placings {
+        if model_pools[lobby.model_index(name)?] > 0 {
+            return Ok(0);
+        }
+    }
+    let paid = lobby
+        .position_paid
+        .iter()
+        .try_fold(0u64, |sum, paid| sum.checked_add(*paid))
+        .ok_or(BettingError::Overflow)?;
+    distributable
+        .checked_sub(lobby.charity_amount)
+        .and_then(|distributable| distributable.checked_sub(paid))
+        .ok_or(error!(BettingError::Overflow))
+}
+
+/// Chip-count pool of a finished lobby that no bet can claim because nobody
+/// picked the winning bucket, less what has been swept already; zero without
+/// a chip-count market or once the winning bucket was picked.
+pub fn unwon_chip_pool(lobby: &Lobby) -> Result<u64> {
+    if lobby.bucket_pools.is_empty() || lobby.bucket_pools[lobby.winning_chip_bucket()?] > 0 {
+        return Ok(0);
+    }
+    lobby
+        .bucket_pools
+        .iter()
+        .try_fold(0u64, |sum, pool| sum.checked_add(*pool))
+        .and_then(|distributable| distributable.checked_sub(lobby.bucket_paid))
+        .ok_or(error!(BettingError::Overflow))
+}
+
+/// Freeroll prize of a finished lobby that no pick can claim because nobody
+/// picked the winner, less what has been swept already.
+pub fn unwon_freeroll_prize(lobby: &Lobby) -> Result<u64> {
+    let winner_name = lobby.winner.as_deref().ok_or(BettingError::LobbyNotFinished)?;
+    if lobby.freeroll_picks[lobby.model_index(winner_name)?] > 0 {
+        return Ok(0);
+    }
+    lobby
+        .freeroll_pool
+        .checked_sub(lobby.freeroll_paid)
+        .ok_or(error!(BettingError::Overflow))
+}
+
+/// What `settle_winning_bet` would pay for `bet` now; zero unless it is an
+/// unpaid winner of a lobby settling bet by bet, or when it would be swept
+/// as below the payout minimum.
+pub fn claimable_amount(
+    lobby: &Lobby,
+    bet: &Bet,
+    snapshot: Option<&PoolSnapshot>,
+    config: &Config,
+) -> Result<u64> {
+    let Some(winner_name) = lobby.winner.as_deref() else {
+        return Ok(0);
+    };
+    if lobby.status != LobbyStatus::Finished
+        || lobby.payout_root.is_some()
+        || !bet.status.can_become(&BetStatus::Paid)
+        || !bet.wins(lobby)?
+    {
+        return Ok(0);
+    }
+    let position = winning_position(lobby, bet, winner_name)?;
+    let amount = if bet.odds_bps > 0 {
+        fixed_odds_payout(bet.amount, bet.odds_bps)?
+    } else {
+        let (distributable, winning_stake) =
+            parimutuel_terms(lobby, bet, winner_name, snapshot, position)?;
+        let weight = lobby.weighting.weight(bet.amount);
+        lobby.parimutuel_share(position, weight, distributable, winning_stake)?
+    };
+    Ok(if amount < config.min_payout_lamports {
+        0
+    } else {
+        amount
+    })
+}
+
+/// Checks that `bet` can be closed: it is settled, it isn't a raffle entry
+/// that could still win, and the lobby's payout root isn't under challenge.
+pub fn check_bet_closable(lobby: &Lobby, bet: &Bet) -> Result<()> {
+    require!(!bet.status.is_outstanding(), BettingError::BetNotSettled);
+    if let (Some(start), true) = (bet.raffle_ticket, lobby.raffle_pool > 0) {
+        let drawn = lobby.raffle_ticket.ok_or(BettingError::RafflePending)?;
+        require!(
+            !(start..start + bet.amount).contains(&drawn),
+            BettingError::RafflePending
+        );
+    }
+    require!(
+        !lobby.payout_root_challenged,
+        BettingError::ChallengeWindowOpen
+    );
+    Ok(())
+}
+
+/// Checks that `lobby` takes bets at `now`: it hasn't finished and betting
+/// hasn't closed.
+pub fn check_taking_bets(lobby: &Lobby, now: i64) -> Result<()> {
+    require!(
+        lobby.status == LobbyStatus::Waiting || lobby.status == LobbyStatus::Running,
+        BettingError::LobbyNotOpenForBets
+    );
+    require!(!lobby.betting_closed(now), BettingError::BettingClosed);
+    Ok(())
+}
+
+/// Checks that a bet of `amount` stakes something while betting isn't paused.
+pub fn check_bet_amount(config: &Config, amount: u64) -> Result<()> {
+    require!(amount > 0, BettingError::BetAmountMustBePositive);
+    require!(!config.paused, BettingError::ProtocolPaused);
+    Ok(())
+}
+
+/// Protocol fee on a bet of `amount` by a bettor with `volume` over the
+/// trailing 30 days, at the highest fee tier it reaches.
+pub fn bettor_fee(config: &Config, volume: u64, amount: u64) -> Result<u64> {
+    math::apply_bps(amount, config.bettor_fee_bps(volume) as u64)
+}
+
+/// Odds, stake and fee a bet is placed at.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// Zero for a parimutuel bet
+    pub odds_bps: u32,
+    pub stake: u64,
+    pub fee: u64,
+}
+
+/// Prices `stake` on `player_name` in the lobby's market. A fixed-odds bet is
+/// filled only as far as the bankroll covers it, its fee scaled down with
+/// the stake, and its liability booked on the lobby.
+pub fn fill_bet(
+    lobby: &mut Lobby,
+    player_name: &str,
+    side: BetSide,
+    stake: u64,
+    fee: u64,
+    min_expected_odds_bps: u32,
+    now: i64,
+) -> Result<Fill> {
+    let model_index = lobby.model_index(player_name)?;
+    require!(
+        !lobby.is_disqualified(player_name),
+        BettingError::ModelDisqualified
+    );
+    match lobby.market_mode {
+        MarketMode::Parimutuel => {
+            require!(
+                side == BetSide::Back || lobby.payout_curve.len() == 1,
+                BettingError::LayRequiresWinnerTakesAll
+            );
+            require!(
+                side == BetSide::Back || lobby.weighting == PoolWeighting::Stake,
+                BettingError::WeightedPoolUnsupported
+            );
+            require!(
+                lobby.implied_odds_after(model_index, side, stake)?
+                    >= min_expected_odds_bps as u64,
+                BettingError::SlippageExceeded
+            );
+            Ok(Fill {
+                odds_bps: 0,
+                stake,
+                fee,
+            })
+        }
+        MarketMode::FixedOdds => {
+            require!(side == BetSide::Back, BettingError::LayRequiresParimutuel);
+            require!(
+                lobby.quoted_at > 0 && now - lobby.quoted_at <= lobby.quote_max_age,
+                BettingError::StaleQuote
+            );
+            let odds_bps = lobby.quoted_odds_bps[model_index];
+            require!(odds_bps as u64 > ODDS_SCALE, BettingError::InvalidOdds);
+            require!(
+                odds_bps >= min_expected_odds_bps,
+                BettingError::SlippageExceeded
+            );
+
+            // Fill only what the bankroll can cover; the unfilled part of the
+            // bet is simply never pulled from the bettor
+            let headroom = lobby
+                .fixed_odds_backing(0)?
+                .saturating_sub(lobby.model_liabilities[model_index]);
+            let max_fill = math::mul_div_wide(
+                headroom as u128,
+                ODDS_SCALE as u128,
+                (odds_bps as u64 - ODDS_SCALE) as u128,
+            )?;
+            let (stake, fee) = if (stake as u128) > max_fill {
+                let filled = max_fill as u64;
+                require!(filled > 0, BettingError::InsufficientLiquidity);
+                (filled, math::mul_div(fee, filled, stake)?)
+            } else {
+                (stake, fee)
+            };
+
+            let payout = fixed_odds_payout(stake, odds_bps)?;
+            let liability = lobby.model_liabilities[model_index]
+                .checked_add(payout)
+                .ok_or(BettingError::Overflow)?;
+            require!(
+                liability <= lobby.fixed_odds_backing(stake)?,
+                BettingError::InsufficientLiquidity
+            );
+            lobby.model_liabilities[model_index] = liability;
+            Ok(Fill {
+                odds_bps,
+                stake,
+                fee,
+            })
+        }
+    }
+}
+
+/// Checks that `bet` can be adjusted at `now`: betting is still open and its
+/// stake is parimutuel and in the lobby's pools. Fails with `unavailable`
+/// otherwise.
+fn check_adjustable(lobby: &Lobby, bet: &Bet, now: i64, unavailable: BettingError) -> Result<()> {
+    require!(
+        !lobby.status.is_over() && !lobby.betting_closed(now),
+        BettingError::BettingClosed
+    );
+    // Fixed-odds liabilities and pooled stake can't follow the bet
+    if bet.odds_bps != 0 || lobby.player_pools != 0 {
+        return Err(unavailable.into());
+    }
+    Ok(())
+}
+
+/// Takes `amount` of a parimutuel bet's stake out of the lobby's pools while
+/// betting is still open: all of it for a cash-out, or part of it for a
+/// reduction, which leaves the rest live. The bet is left to the caller.
+pub fn withdraw_stake(lobby: &mut Lobby, bet: &Bet, amount: u64, now: i64) -> Result<()> {
+    check_adjustable(lobby, bet, now, BettingError::CashOutUnavailable)?;
+    let remaining = bet
+        .amount
+        .checked_sub(amount)
+        .ok_or(BettingError::Overflow)?;
+    let model_index = lobby.model_index(&bet.player_name)?;
+    let pools = match bet.side {
+        BetSide::Back => &mut lobby.model_pools,
+        BetSide::Lay => &mut lobby.lay_pools,
+    };
+    pools[model_index] = pools[model_index]
+        .checked_sub(amount)
+        .ok_or(BettingError::Overflow)?;
+    if bet.side == BetSide::Lay {
+        lobby.total_lay = lobby
+            .total_lay
+            .checked_sub(amount)
+            .ok_or(BettingError::Overflow)?;
+    } else {
+        // Weights aren't linear in the stake, so swap the bet's weight
+        lobby.model_weights[model_index] = lobby.model_weights[model_index]
+            .checked_sub(lobby.weighting.weight(bet.amount))
+            .and_then(|weight| weight.checked_add(lobby.weighting.weight(remaining)))
+            .ok_or(BettingError::Overflow)?;
+    }
+    lobby.total_bets = lobby
+        .total_bets
+        .checked_sub(amount)
+        .ok_or(BettingError::Overflow)?;
+    Ok(())
+}
+
+/// Checks that `amount` can be taken off a live bet and returns the stake
+/// left; taking everything is a cash-out.
+pub fn check_reduction(bet: &Bet, amount: u64) -> Result<u64> {
+    require!(
+        bet.status == BetStatus::Active,
+        BettingError::InvalidBetTransition
+    );
+    require!(
+        amount > 0 && amount < bet.amount,
+        BettingError::InvalidReduceAmount
+    );
+    Ok(bet.amount - amount)
+}
+
+/// Moves a live parimutuel bet's stake to `new_player` while betting is still
+/// open and returns the change fee.
+pub fn change_selection(
+    lobby: &mut Lobby,
+    bet: &mut Bet,
+    new_player: String,
+    config: &Config,
+    now: i64,
+) -> Result<u64> {
+    check_adjustable(lobby, bet, now, BettingError::SelectionChangeUnavailable)?;
+    require!(
+        bet.status == BetStatus::Active,
+        BettingError::InvalidBetTransition
+    );
+    require!(
+        new_player != bet.player_name,
+        BettingError::InvalidPlayerName
+    );
+    let from = lobby.model_index(&bet.player_name)?;
+    let to = lobby.model_index(&new_player)?;
+    require!(
+        !lobby.is_disqualified(&new_player),
+        BettingError::ModelDisqualified
+    );
+
+    let pools = match bet.side {
+        BetSide::Back => &mut lobby.model_pools,
+        BetSide::Lay => &mut lobby.lay_pools,
+    };
+    pools[from] = pools[from]
+        .checked_sub(bet.amount)
+        .ok_or(BettingError::Overflow)?;
+    pools[to] = pools[to]
+        .checked_add(bet.amount)
+        .ok_or(BettingError::Overflow)?;
+    if bet.side == BetSide::Back {
+        let weight = lobby.weighting.weight(bet.amount);
+        lobby.model_weights[from] = lobby.model_weights[from]
+            .checked_sub(weight)
+            .ok_or(BettingError::Overflow)?;
+        lobby.model_weights[to] = lobby.model_weights[to]
+            .checked_add(weight)
+            .ok_or(BettingError::Overflow)?;
+    }
+
+    bet.player_name = new_player;
+    bet.selection_changes = bet
+        .selection_changes
+        .checked_add(1)
+        .ok_or(BettingError::Overflow)?;
+    math::apply_bps(bet.amount, config.selection_change_fee_bps as u64)
+}
+
+/// Takes a disqualified model's stake out of the lobby's pools, and out of
+/// their snapshot once they are locked, leaving its bets to be refunded.
+pub fn disqualify_model(
+    lobby: &mut Lobby,
+    snapshot: Option<&mut PoolSnapshot>,
+    player_name: &str,
+) -> Result<()> {
+    require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+    // Stake these hold can't be refunded bet by bet
+    require!(
+        (lobby.player_pools == 0 || lobby.pools_locked)
+            && lobby.compressed_bets == 0
+            && lobby.blind_stake == 0
+            && lobby.order_book_locked == 0,
+        BettingError::DisqualificationUnavailable
+    );
+    let model_index = lobby.model_index(player_name)?;
+    require!(
+        !lobby.is_disqualified(player_name),
+        BettingError::ModelDisqualified
+    );
+    require!(
+        lobby.disqualified.iter().filter(|d| !**d).count() > 1,
+        BettingError::DisqualificationUnavailable
+    );
+
+    let back = lobby.model_pools[model_index];
+    let lay = lobby.lay_pools[model_index];
+    lobby.total_bets = lobby
+        .total_bets
+        .checked_sub(back + lay)
+        .ok_or(BettingError::Overflow)?;
+    lobby.total_lay = lobby
+        .total_lay
+        .checked_sub(lay)
+        .ok_or(BettingError::Overflow)?;
+    lobby.model_pools[model_index] = 0;
+    lobby.lay_pools[model_index] = 0;
+    lobby.model_weights[model_index] = 0;
+    lobby.model_liabilities[model_index] = 0;
+    lobby.disqualified[model_index] = true;
+
+    if lobby.pools_locked {
+        let snapshot = snapshot.ok_or(BettingError::SnapshotRequired)?;
+        let back = snapshot.model_pools[model_index];
+        let lay = snapshot.lay_pools[model_index];
+        snapshot.total_bets = snapshot
+            .total_bets
+            .checked_sub(back + lay)
+            .ok_or(BettingError::Overflow)?;
+        snapshot.total_lay = snapshot
+            .total_lay
+            .checked_sub(lay)
+            .ok_or(BettingError::Overflow)?;
+        snapshot.model_pools[model_index] = 0;
+        snapshot.lay_pools[model_index] = 0;
+    }
+    Ok(())
+}
+
+/// Checks that a market can open on hand `hand_number` of a game that has
+/// played `hands_played`: the hand isn't being dealt yet and no checkpoint
+/// has passed it.
+pub fn check_hand_market_opens(lobby: &Lobby, hands_played: u64, hand_number: u64) -> Result<()> {
+    require!(!lobby.status.is_over(), BettingError::LobbyAlreadyFinished);
+    require!(
+        hand_number > hands_played && hand_number >= lobby.hands_checkpointed,
+        BettingError::HandMarketClosed
+    );
+    require!(
+        lobby.open_hand_markets.len() < MAX_OPEN_HAND_MARKETS,
+        BettingError::TooManyHandMarkets
+    );
+    Ok(())
+}
+
+/// Checks that `market` takes bets once the game has played `hands_played`:
+/// the lobby takes bets, even after the match market has locked, and the
+/// engine hasn't recorded the hand before the market's.
+pub fn check_hand_bets_open(lobby: &Lobby, market: &HandMarket, hands_played: u64) -> Result<()> {
+    require!(
+        lobby.status == LobbyStatus::Waiting || lobby.status == LobbyStatus::Running,
+        BettingError::LobbyNotOpenForBets
+    );
+    require!(
+        !market.settled && hands_played < market.hand_number,
+        BettingError::HandMarketClosed
+    );
+    Ok(())
+}
+
+/// Settles `market` on the hand `winner_name` won, or voids it when no lobby
+/// model won it or nobody backed the one that did; `None` voids it outright.
+pub fn settle_hand_market(lobby: &mut Lobby, market: &mut HandMarket, winner_name: Option<&str>) {
+    market.winner_index = winner_name.and_then(|winner_name| {
+        lobby
+            .model_names
+            .iter()
+            .position(|name| name == winner_name)
+            .filter(|index| market.model_pools[*index] > 0)
+            .map(|index| index as u8)
+    });
+    market.settled = true;
+    lobby
+        .open_hand_markets
+        .retain(|open| *open != market.hand_number);
+}
+
+/// What a hand bet is paid, or refunded on a void hand, and the status it
+/// settles at. The claim that settles the last of the winning stake takes
+/// the rounding remainder.
+pub fn claim_hand_bet(market: &mut HandMarket, hand_bet: &HandBet) -> Result<(u64, BetStatus)> {
+    require!(market.settled, BettingError::HandMarketNotSettled);
+    require!(
+        hand_bet.status == BetStatus::Active,
+        BettingError::BetAlreadyProcessed
+    );
+    let Some(winner) = market.winner_index else {
+        return Ok((hand_bet.amount, BetStatus::Refunded));
+    };
+    require!(
+        hand_bet.model_index == winner,
+        BettingError::BetOnWrongPlayer
+    );
+    let payout = math::parimutuel_claim(
+        hand_bet.amount,
+        market.stake_paid,
+        market.paid,
+        market.total,
+        market.model_pools[winner as usize],
+    )?;
+    market.stake_paid += hand_bet.amount;
+    market.paid += payout;
+    Ok((payout, BetStatus::Paid))
+}
+
+/// Checks that `keeper` can commit to crank a lobby: it is staked, not
+/// retired and not committed elsewhere.
+pub fn check_keeper_commits(keeper: &Keeper, config: &Config) -> Result<()> {
+    require!(keeper.retired_at == 0, BettingError::KeeperRetired);
+    require!(
+        keeper.stake >= config.min_keeper_stake,
+        BettingError::KeeperStakeTooLow
+    );
+    require!(
+        keeper.committed_lobby.is_none(),
+        BettingError::KeeperCommitted
+    );
+    Ok(())
+}
+
+/// Credits `keeper` the crank fee for `steps` steps when it committed to
+/// `lobby` and cranked it at `now`, within `KEEPER_FEE_WINDOW` of `due_at`,
+/// staked and not retired, and releases the commitment.
+pub fn credit_keeper(
+    keeper: &mut Keeper,
+    config: &Config,
+    lobby: Pubkey,
+    due_at: i64,
+    steps: u64,
+    now: i64,
+) -> Result<()> {
+    if keeper.committed_lobby != Some(lobby) {
+        return Ok(());
+    }
+    keeper.committed_lobby = None;
+    if keeper.retired_at != 0
+        || keeper.stake < config.min_keeper_stake
+        || now > due_at.saturating_add(KEEPER_FEE_WINDOW)
+    {
+        return Ok(());
+    }
+    let fee = config
+        .keeper_fee
+        .checked_mul(steps)
+        .ok_or(BettingError::Overflow)?;
+    keeper.fees_owed = keeper
+        .fees_owed
+        .checked_add(fee)
+        .ok_or(BettingError::Overflow)?;
+    keeper.cranks = keeper
+        .cranks
+        .checked_add(steps)
+        .ok_or(BettingError::Overflow)?;
+    Ok(())
+}
+
+/// Releases the commitment of a keeper that missed its crank and returns the
+/// lobby it committed to and the stake slashed, up to `amount`.
+pub fn slash_keeper(keeper: &mut Keeper, amount: u64, now: i64) -> Result<(Pubkey, u64)> {
+    let lobby = keeper
+        .committed_lobby
+        .take()
+        .ok_or(BettingError::KeeperNotSlashable)?;
+    require!(now > keeper.crank_by, BettingError::KeeperNotSlashable);
+    let amount = amount.min(keeper.stake);
+    keeper.stake -= amount;
+    Ok((lobby, amount))
+}
+
+/// Applies `change` at once, which only an admin without a timelock may do.
+pub fn set_config(config: &mut Config, change: ConfigChange, now: i64) -> Result<()> {
+    require!(config.admin_timelock == 0, BettingError::TimelockActive);
+    change.check(config)?;
+    change.apply(config);
+    config.updated_at = now;
+    Ok(())
+}
+
+/// Queues `change` to be executed once `admin_timelock` has passed and
+/// returns when it can be. Only one change can be pending at a time.
+pub fn propose_config_change(config: &mut Config, change: ConfigChange, now: i64) -> Result<i64> {
+    change.check(config)?;
+    require!(
+        config.pending_change.is_none(),
+        BettingError::ConfigChangePending
+    );
+    let eta = now + config.admin_timelock;
+    config.pending_change = Some(change);
+    config.pending_change_eta = eta;
+    Ok(eta)
+}
+
+/// Applies the pending change once its timelock has passed and returns it.
+pub fn execute_config_change(config: &mut Config, now: i64) -> Result<ConfigChange> {
+    let change = config
+        .pending_change
+        .take()
+        .ok_or(BettingError::NoPendingConfigChange)?;
+    require!(
+        now >= config.pending_change_eta,
+        BettingError::TimelockActive
+    );
+    // Other settings may have moved since the change was proposed
+    change.check(config)?;
+    change.clone().apply(config);
+    config.pending_change_eta = 0;
+    config.updated_at = now;
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FeeTier;
+
+    const MODELS: [&str; 3] = ["model-a", "model-b", "model-c"];
+
+    fn zeroed<T: AnchorDeserialize>(len: usize) -> T {
+        T::deserialize(&mut &vec![0; len][..]).unwrap()
+    }
+
+    /// A finished winner-takes-pool lobby won by model-a, with `pools` backed
+    /// on the models in turn, all of it in escrow.
+    fn finished_lobby(pools: &[u64]) -> Lobby {
+        let mut lobby: Lobby = zeroed(Lobby::LEN);
+        lobby.model_names = MODELS[..pools.len()]
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        lobby.model_pools = pools.to_vec();
+        lobby.lay_pools = vec![0; pools.len()];
+        lobby.model_weights = pools.to_vec();
+        lobby.disqualified = vec![false; pools.len()];
+        lobby.total_bets = pools.iter().sum();
+        lobby.escrow_balance = lobby.total_bets;
+        lobby.payout_curve = vec![10_000];
+        lobby.position_stake_paid = vec![0];
+        lobby.position_paid = vec![0];
+        lobby.status = LobbyStatus::Finished;
+        lobby.winner = Some(MODELS[0].to_string());
+        lobby.placings = vec![MODELS[0].to_string()];
+        lobby
+    }
+
+    /// `finished_lobby` before the game ended, still taking bets.
+    fn open_lobby(pools: &[u64]) -> Lobby {
+        let mut lobby = finished_lobby(pools);
+        lobby.status = LobbyStatus::Running;
+        lobby.winner = None;
+        lobby.placings = Vec::new();
+        lobby.model_liabilities = vec![0; pools.len()];
+        lobby
+    }
+
+    fn bet(player_name: &str, amount: u64) -> Bet {
+        let mut bet: Bet = zeroed(Bet::LEN);
+        bet.player_name = player_name.to_string();
+        bet.amount = amount;
+        bet
+    }
+
+    fn config() -> Config {
+        zeroed(Config::LEN)
+    }
+
+    fn hand_market(model_pools: &[u64]) -> HandMarket {
+        let mut market: HandMarket = zeroed(HandMarket::LEN);
+        market.hand_number = 3;
+        market.model_pools = model_pools.to_vec();
+        market.total = model_pools.iter().sum();
+        market
+    }
+
+    fn hand_bet(model_index: u8, amount: u64) -> HandBet {
+        let mut hand_bet: HandBet = zeroed(HandBet::LEN);
+        hand_bet.model_index = model_index;
+        hand_bet.amount = amount;
+        hand_bet
+    }
+
+    fn keeper(committed_lobby: Pubkey) -> Keeper {
+        let mut keeper: Keeper = zeroed(Keeper::LEN);
+        keeper.stake = 1_000;
+        keeper.committed_lobby = Some(committed_lobby);
+        keeper.crank_by = 100;
+        keeper
+    }
+
+    fn settle(lobby: &mut Lobby, bet: &Bet) -> Result<u64> {
+        settle_winning_bet(lobby, bet, None, &config()).map(|payout| payout.amount)
+    }
+
+    #[test]
+    fn a_winner_is_paid_its_share_of_the_pool() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        assert_eq!(settle(&mut lobby, &bet("model-a", 100)), Ok(333));
+        assert_eq!(lobby.position_stake_paid, [100]);
+        assert_eq!(lobby.position_paid, [333]);
+        assert_eq!(lobby.escrow_balance, 1_000 - 333);
+    }
+
+    #[test]
+    fn the_last_claim_takes_the_rounding_dust() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        let paid: u64 = [100, 100, 100]
+            .iter()
+            .map(|stake| settle(&mut lobby, &bet("model-a", *stake)).unwrap())
+            .sum();
+        assert_eq!(paid, 1_000);
+        assert_eq!(lobby.escrow_balance, 0);
+        // Nothing is left for a claim beyond the winning stake
+        assert_eq!(
+            settle(&mut lobby, &bet("model-a", 1)),
+            Err(BettingError::Overflow.into())
+        );
+    }
+
+    #[test]
+    fn a_lay_bet_wins_when_its_model_loses() {
+        let mut lobby = finished_lobby(&[300, 600]);
+        lobby.lay_pools = vec![0, 100];
+        lobby.total_lay = 100;
+        lobby.total_bets = 1_000;
+        lobby.escrow_balance = 1_000;
+        let mut lay = bet("model-b", 100);
+        lay.side = BetSide::Lay;
+        assert_eq!(settle(&mut lobby, &lay), Ok(250));
+        let mut lay = bet("model-a", 100);
+        lay.side = BetSide::Lay;
+        assert_eq!(
+            settle(&mut lobby, &lay),
+            Err(BettingError::BetOnWrongPlayer.into())
+        );
+    }
+
+    #[test]
+    fn a_fixed_odds_bet_is_paid_at_its_odds() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        let mut fixed = bet("model-a", 100);
+        fixed.odds_bps = 25_000;
+        assert_eq!(settle(&mut lobby, &fixed), Ok(250));
+        assert_eq!(lobby.fixed_odds_paid, 250);
+        assert_eq!(lobby.position_paid, [0]);
+    }
+
+    #[test]
+    fn only_unpaid_winners_of_a_finished_lobby_settle() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        assert_eq!(
+            settle(&mut lobby, &bet("model-b", 100)),
+            Err(BettingError::BetOnWrongPlayer.into())
+        );
+        let mut paid = bet("model-a", 100);
+        paid.status = BetStatus::Paid;
+        assert_eq!(
+            settle(&mut lobby, &paid),
+            Err(BettingError::BetAlreadyProcessed.into())
+        );
+
+        let mut running = finished_lobby(&[300, 700]);
+        running.status = LobbyStatus::Running;
+        assert_eq!(
+            settle(&mut running, &bet("model-a", 100)),
+            Err(BettingError::LobbyNotFinished.into())
+        );
+        let mut merkle = finished_lobby(&[300, 700]);
+        merkle.payout_root = Some([1; 32]);
+        assert_eq!(
+            settle(&mut merkle, &bet("model-a", 100)),
+            Err(BettingError::MerkleSettlement.into())
+        );
+        let mut custody = finished_lobby(&[300, 700]);
+        custody.in_custody = 1;
+        assert_eq!(
+            settle(&mut custody, &bet("model-a", 100)),
+            Err(BettingError::FundsInCustody.into())
+        );
+    }
+
+    #[test]
+    fn a_payout_under_the_minimum_is_flagged() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        let mut config = config();
+        config.min_payout_lamports = 400;
+        let payout = settle_winning_bet(&mut lobby, &bet("model-a", 100), None, &config).unwrap();
+        assert_eq!(payout.amount, 333);
+        assert!(payout.below_minimum);
+    }
+
+    #[test]
+    fn placed_bets_settle_at_their_finishing_position() {
+        let mut lobby = finished_lobby(&[300, 500, 200]);
+        lobby.payout_curve = vec![7_000, 3_000];
+        lobby.placings = vec!["model-a".to_string(), "model-c".to_string()];
+        lobby.position_stake_paid = vec![0, 0];
+        lobby.position_paid = vec![0, 0];
+        assert_eq!(
+            winning_position(&lobby, &bet("model-c", 1), "model-a"),
+            Ok(1)
+        );
+        assert_eq!(
+            winning_position(&lobby, &bet("model-b", 1), "model-a"),
+            Err(BettingError::BetOnWrongPlayer.into())
+        );
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-c", 1), "model-a", None, 1),
+            Ok((300, 200))
+        );
+        assert_eq!(settle(&mut lobby, &bet("model-c", 200)), Ok(300));
+        assert_eq!(settle(&mut lobby, &bet("model-a", 300)), Ok(700));
+    }
+
+    #[test]
+    fn an_unbacked_position_rolls_its_share_to_the_winner() {
+        let mut lobby = finished_lobby(&[300, 700, 0]);
+        lobby.payout_curve = vec![7_000, 3_000];
+        lobby.placings = vec!["model-a".to_string(), "model-c".to_string()];
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-a", 1), "model-a", None, 0),
+            Ok((1_000, 300))
+        );
+    }
+
+    #[test]
+    fn an_unbacked_winner_rolls_its_share_to_the_next_backed_position() {
+        let mut lobby = finished_lobby(&[0, 700, 300]);
+        lobby.payout_curve = vec![7_000, 3_000];
+        lobby.placings = vec!["model-a".to_string(), "model-c".to_string()];
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-c", 1), "model-a", None, 1),
+            Ok((1_000, 300))
+        );
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-a", 1), "model-a", None, 0),
+            Ok((0, 0))
+        );
+    }
+
+    #[test]
+    fn a_pool_nobody_backed_to_win_is_unwon() {
+        let mut lobby = finished_lobby(&[0, 700, 300]);
+        lobby.charity_amount = 100;
+        assert_eq!(unwon_pool(&lobby, None), Ok(900));
+        lobby.position_paid = vec![900];
+        assert_eq!(unwon_pool(&lobby, None), Ok(0));
+
+        // A lay bet on a loser wins
+        let mut lobby = finished_lobby(&[0, 700, 300]);
+        lobby.lay_pools = vec![0, 50, 0];
+        lobby.total_lay = 50;
+        assert_eq!(unwon_pool(&lobby, None), Ok(0));
+
+        let mut lobby = finished_lobby(&[0, 700, 300]);
+        lobby.payout_curve = vec![7_000, 3_000];
+        lobby.placings = vec!["model-a".to_string(), "model-c".to_string()];
+        lobby.position_paid = vec![0, 0];
+        assert_eq!(unwon_pool(&lobby, None), Ok(0));
+        lobby.placings = vec!["model-a".to_string(), "model-b".to_string()];
+        lobby.model_pools = vec![0, 0, 1_000];
+        assert_eq!(unwon_pool(&lobby, None), Ok(1_000));
+    }
+
+    #[test]
+    fn a_chip_pool_nobody_picked_to_win_is_unwon() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        assert_eq!(unwon_chip_pool(&lobby), Ok(0));
+        // model-a finished on 1_500 chips, in the middle bucket
+        lobby.chip_buckets = vec![1_000, 2_000];
+        lobby.final_chips = vec![1_500, 500];
+        lobby.bucket_pools = vec![100, 0, 200];
+        assert_eq!(unwon_chip_pool(&lobby), Ok(300));
+        lobby.bucket_paid = 300;
+        assert_eq!(unwon_chip_pool(&lobby), Ok(0));
+        lobby.bucket_pools = vec![100, 50, 200];
+        lobby.bucket_paid = 0;
+        assert_eq!(unwon_chip_pool(&lobby), Ok(0));
+    }
+
+    #[test]
+    fn a_freeroll_prize_nobody_picked_to_win_is_unwon() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        lobby.freeroll_picks = vec![0, 4];
+        assert_eq!(unwon_freeroll_prize(&lobby), Ok(0));
+        lobby.freeroll_pool = 500;
+        assert_eq!(unwon_freeroll_prize(&lobby), Ok(500));
+        lobby.freeroll_paid = 500;
+        assert_eq!(unwon_freeroll_prize(&lobby), Ok(0));
+        lobby.freeroll_picks = vec![1, 4];
+        lobby.freeroll_paid = 0;
+        assert_eq!(unwon_freeroll_prize(&lobby), Ok(0));
+    }
+
+    #[test]
+    fn locked_pools_settle_from_the_snapshot() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        lobby.pools_locked = true;
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-a", 1), "model-a", None, 0),
+            Err(BettingError::SnapshotRequired.into())
+        );
+        let mut snapshot: PoolSnapshot = zeroed(PoolSnapshot::LEN);
+        snapshot.model_pools = vec![200, 600];
+        snapshot.lay_pools = vec![0, 0];
+        snapshot.total_bets = 800;
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-a", 1), "model-a", Some(&snapshot), 0),
+            Ok((800, 200))
+        );
+    }
+
+    #[test]
+    fn the_charity_cut_comes_out_of_the_pool() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        lobby.charity_amount = 70;
+        assert_eq!(
+            parimutuel_terms(&lobby, &bet("model-a", 1), "model-a", None, 0),
+            Ok((930, 300))
+        );
+    }
+
+    #[test]
+    fn a_weighted_pool_is_shared_by_weight() {
+        let mut lobby = finished_lobby(&[400, 600]);
+        lobby.weighting = PoolWeighting::Quadratic;
+        lobby.model_weights = vec![20, 0];
+        assert_eq!(settle(&mut lobby, &bet("model-a", 100)), Ok(500));
+        assert_eq!(lobby.position_stake_paid, [10]);
+    }
+
+    #[test]
+    fn the_claimable_amount_is_what_settling_pays() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        let config = config();
+        for stake in [100, 150, 50] {
+            let winner = bet("model-a", stake);
+            let estimate = claimable_amount(&lobby, &winner, None, &config).unwrap();
+            assert_eq!(settle(&mut lobby, &winner), Ok(estimate));
+        }
+        assert_eq!(lobby.escrow_balance, 0);
+    }
+
+    #[test]
+    fn nothing_is_claimable_for_losing_paid_or_small_bets() {
+        let lobby = finished_lobby(&[300, 700]);
+        let mut config = config();
+        assert_eq!(
+            claimable_amount(&lobby, &bet("model-b", 100), None, &config),
+            Ok(0)
+        );
+        let mut paid = bet("model-a", 100);
+        paid.status = BetStatus::Paid;
+        assert_eq!(claimable_amount(&lobby, &paid, None, &config), Ok(0));
+        config.min_payout_lamports = 400;
+        assert_eq!(
+            claimable_amount(&lobby, &bet("model-a", 100), None, &config),
+            Ok(0)
+        );
+        let mut running = finished_lobby(&[300, 700]);
+        running.winner = None;
+        assert_eq!(
+            claimable_amount(&running, &bet("model-a", 100), None, &config),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn only_settled_bets_close() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        let mut closing = bet("model-b", 100);
+        assert_eq!(
+            check_bet_closable(&lobby, &closing),
+            Err(BettingError::BetNotSettled.into())
+        );
+        closing.status = BetStatus::Lost;
+        assert_eq!(check_bet_closable(&lobby, &closing), Ok(()));
+
+        lobby.payout_root_challenged = true;
+        assert_eq!(
+            check_bet_closable(&lobby, &closing),
+            Err(BettingError::ChallengeWindowOpen.into())
+        );
+    }
+
+    #[test]
+    fn a_raffle_entry_closes_once_the_draw_passes_it_over() {
+        let mut lobby = finished_lobby(&[300, 700]);
+        lobby.raffle_pool = 50;
+        let mut entry = bet("model-b", 100);
+        entry.status = BetStatus::Lost;
+        entry.raffle_ticket = Some(200);
+        assert_eq!(
+            check_bet_closable(&lobby, &entry),
+            Err(BettingError::RafflePending.into())
+        );
+        lobby.raffle_ticket = Some(250);
+        assert_eq!(
+            check_bet_closable(&lobby, &entry),
+            Err(BettingError::RafflePending.into())
+        );
+        lobby.raffle_ticket = Some(300);
+        assert_eq!(check_bet_closable(&lobby, &entry), Ok(()));
+    }
+
+    #[test]
+    fn bets_are_taken_until_betting_closes() {
+        let mut lobby = open_lobby(&[300, 700]);
+        assert_eq!(check_taking_bets(&lobby, 10), Ok(()));
+        lobby.betting_deadline = 10;
+        assert_eq!(
+            check_taking_bets(&lobby, 10),
+            Err(BettingError::BettingClosed.into())
+        );
+        lobby.betting_deadline = 0;
+        lobby.pools_locked = true;
+        assert_eq!(
+            check_taking_bets(&lobby, 10),
+            Err(BettingError::BettingClosed.into())
+        );
+        assert_eq!(
+            check_taking_bets(&finished_lobby(&[300, 700]), 10),
+            Err(BettingError::LobbyNotOpenForBets.into())
+        );
+    }
+
+    #[test]
+    fn a_bet_stakes_something_while_betting_is_not_paused() {
+        let mut config = config();
+        assert_eq!(check_bet_amount(&config, 1), Ok(()));
+        assert_eq!(
+            check_bet_amount(&config, 0),
+            Err(BettingError::BetAmountMustBePositive.into())
+        );
+        config.paused = true;
+        assert_eq!(
+            check_bet_amount(&config, 1),
+            Err(BettingError::ProtocolPaused.into())
+        );
+    }
+
+    #[test]
+    fn the_fee_drops_with_the_bettors_volume_tier() {
+        let mut config = config();
+        config.fee_bps = 200;
+        config.fee_tiers = vec![
+            FeeTier {
+                min_volume: 1_000,
+                fee_bps: 100,
+            },
+            FeeTier {
+                min_volume: 10_000,
+                fee_bps: 50,
+            },
+        ];
+        assert_eq!(bettor_fee(&config, 999, 10_000), Ok(200));
+        assert_eq!(bettor_fee(&config, 1_000, 10_000), Ok(100));
+        assert_eq!(bettor_fee(&config, 50_000, 10_000), Ok(50));
+    }
+
+    #[test]
+    fn a_parimutuel_bet_is_filled_within_its_slippage() {
+        let mut lobby = open_lobby(&[300, 700]);
+        // 1_100 shared by 400 on model-a
+        assert_eq!(
+            fill_bet(&mut lobby, "model-a", BetSide::Back, 100, 2, 27_500, 0),
+            Ok(Fill {
+                odds_bps: 0,
+                stake: 100,
+                fee: 2,
+            })
+        );
+        assert_eq!(
+            fill_bet(&mut lobby, "model-a", BetSide::Back, 100, 2, 27_501, 0),
+            Err(BettingError::SlippageExceeded.into())
+        );
+        lobby.payout_curve = vec![7_000, 3_000];
+        assert_eq!(
+            fill_bet(&mut lobby, "model-a", BetSide::Lay, 100, 2, 0, 0),
+            Err(BettingError::LayRequiresWinnerTakesAll.into())
+        );
+        lobby.disqualified = vec![true, false];
+        assert_eq!(
+            fill_bet(&mut lobby, "model-a", BetSide::Back, 100, 2, 0, 0),
+            Err(BettingError::ModelDisqualified.into())
+        );
+    }
+
+    #[test]
+    fn a_fixed_odds_bet_is_filled_as_far_as_the_bankroll_covers() {
+        let mut lobby = open_lobby(&[300, 700]);
+        lobby.market_mode = MarketMode::FixedOdds;
+        lobby.quoted_odds_bps = vec![20_000, 15_000];
+        lobby.quoted_at = 5;
+        lobby.quote_max_age = 60;
+        // 1_000 in the pools backs 1_000 more at evens
+        assert_eq!(
+            fill_bet(&mut lobby, "model-a", BetSide::Back, 2_000, 40, 20_000, 10),
+            Ok(Fill {
+                odds_bps: 20_000,
+                stake: 1_000,
+                fee: 20,
+            })
+        );
+        assert_eq!(lobby.model_liabilities, [2_000, 0]);
+        assert_eq!(
+            fill_bet(&mut lobby, "model-a", BetSide::Back, 100, 2, 0, 10),
+            Err(BettingError::InsufficientLiquidity.into())
+        );
+        assert_eq!(
+            fill_bet(&mut lobby, "model-b", BetSide::Back, 100, 2, 0, 66),
+            Err(BettingError::StaleQuote.into())
+        );
+        assert_eq!(
+            fill_bet(&mut lobby, "model-b", BetSide::Lay, 100, 2, 0, 10),
+            Err(BettingError::LayRequiresParimutuel.into())
+        );
+    }
+
+    #[test]
+    fn cashing_out_takes_the_stake_and_its_weight_out_of_the_pools() {
+        let mut lobby = open_lobby(&[300, 700]);
+        assert_eq!(withdraw_stake(&mut lobby, &bet("model-a", 100), 100, 0), Ok(()));
+        assert_eq!(lobby.model_pools, [200, 700]);
+        assert_eq!(lobby.model_weights, [200, 700]);
+        assert_eq!(lobby.total_bets, 900);
+
+        let mut lay = bet("model-b", 100);
+        lay.side = BetSide::Lay;
+        lobby.lay_pools = vec![0, 100];
+        lobby.total_lay = 100;
+        lobby.total_bets = 1_000;
+        assert_eq!(withdraw_stake(&mut lobby, &lay, 40, 0), Ok(()));
+        assert_eq!(lobby.lay_pools, [0, 60]);
+        assert_eq!(lobby.total_lay, 60);
+        assert_eq!(lobby.model_weights, [200, 700]);
+    }
+
+    #[test]
+    fn reducing_a_weighted_bet_swaps_its_weight() {
+        let mut lobby = open_lobby(&[400, 600]);
+        lobby.weighting = PoolWeighting::Quadratic;
+        lobby.model_weights = vec![20, 0];
+        let reduced = bet("model-a", 400);
+        assert_eq!(check_reduction(&reduced, 300), Ok(100));
+        assert_eq!(withdraw_stake(&mut lobby, &reduced, 300, 0), Ok(()));
+        assert_eq!(lobby.model_weights, [10, 0]);
+        assert_eq!(
+            check_reduction(&reduced, 400),
+            Err(BettingError::InvalidReduceAmount.into())
+        );
+        let mut lost = bet("model-a", 400);
+        lost.status = BetStatus::Lost;
+        assert_eq!(
+            check_reduction(&lost, 1),
+            Err(BettingError::InvalidBetTransition.into())
+        );
+    }
+
+    #[test]
+    fn only_open_parimutuel_stake_is_withdrawn() {
+        let mut lobby = open_lobby(&[300, 700]);
+        lobby.pools_locked = true;
+        assert_eq!(
+            withdraw_stake(&mut lobby, &bet("model-a", 100), 100, 0),
+            Err(BettingError::BettingClosed.into())
+        );
+        let mut lobby = open_lobby(&[300, 700]);
+        let mut fixed = bet("model-a", 100);
+        fixed.odds_bps = 20_000;
+        assert_eq!(
+            withdraw_stake(&mut lobby, &fixed, 100, 0),
+            Err(BettingError::CashOutUnavailable.into())
+        );
+        lobby.player_pools = 1;
+        assert_eq!(
+            withdraw_stake(&mut lobby, &bet("model-a", 100), 100, 0),
+            Err(BettingError::CashOutUnavailable.into())
+        );
+    }
+
+    #[test]
+    fn changing_selection_moves_the_stake_for_a_fee() {
+        let mut lobby = open_lobby(&[300, 700, 0]);
+        let mut config = config();
+        config.selection_change_fee_bps = 100;
+        let mut moved = bet("model-a", 100);
+        assert_eq!(
+            change_selection(&mut lobby, &mut moved, "model-c".to_string(), &config, 0),
+            Ok(1)
+        );
+        assert_eq!(lobby.model_pools, [200, 700, 100]);
+        assert_eq!(lobby.model_weights, [200, 700, 100]);
+        assert_eq!(moved.player_name, "model-c");
+        assert_eq!(moved.selection_changes, 1);
+
+        assert_eq!(
+            change_selection(&mut lobby, &mut moved, "model-c".to_string(), &config, 0),
+            Err(BettingError::InvalidPlayerName.into())
+        );
+        lobby.disqualified = vec![false, true, false];
+        assert_eq!(
+            change_selection(&mut lobby, &mut moved, "model-b".to_string(), &config, 0),
+            Err(BettingError::ModelDisqualified.into())
+        );
+        moved.odds_bps = 20_000;
+        assert_eq!(
+            change_selection(&mut lobby, &mut moved, "model-a".to_string(), &config, 0),
+            Err(BettingError::SelectionChangeUnavailable.into())
+        );
+    }
+
+    #[test]
+    fn disqualifying_a_model_takes_its_stake_out_of_the_pools() {
+        let mut lobby = open_lobby(&[300, 600, 100]);
+        lobby.lay_pools = vec![0, 50, 0];
+        lobby.total_lay = 50;
+        lobby.total_bets = 1_050;
+        assert_eq!(disqualify_model(&mut lobby, None, "model-b"), Ok(()));
+        assert_eq!(lobby.model_pools, [300, 0, 100]);
+        assert_eq!(lobby.total_bets, 400);
+        assert_eq!(lobby.total_lay, 0);
+        assert_eq!(lobby.disqualified, [false, true, false]);
+        assert_eq!(
+            disqualify_model(&mut lobby, None, "model-b"),
+            Err(BettingError::ModelDisqualified.into())
+        );
+        // The last model standing stays
+        assert_eq!(disqualify_model(&mut lobby, None, "model-c"), Ok(()));
+        assert_eq!(
+            disqualify_model(&mut lobby, None, "model-a"),
+            Err(BettingError::DisqualificationUnavailable.into())
+        );
+    }
+
+    #[test]
+    fn disqualifying_after_the_lock_adjusts_the_snapshot() {
+        let mut lobby = open_lobby(&[300, 700]);
+        lobby.pools_locked = true;
+        assert_eq!(
+            disqualify_model(&mut lobby.clone(), None, "model-b"),
+            Err(BettingError::SnapshotRequired.into())
+        );
+        let mut snapshot: PoolSnapshot = zeroed(PoolSnapshot::LEN);
+        snapshot.model_pools = vec![300, 700];
+        snapshot.lay_pools = vec![0, 0];
+        snapshot.total_bets = 1_000;
+        assert_eq!(
+            disqualify_model(&mut lobby, Some(&mut snapshot), "model-b"),
+            Ok(())
+        );
+        assert_eq!(snapshot.model_pools, [300, 0]);
+        assert_eq!(snapshot.total_bets, 300);
+
+        let mut blind = open_lobby(&[300, 700]);
+        blind.blind_stake = 1;
+        assert_eq!(
+            disqualify_model(&mut blind, None, "model-b"),
+            Err(BettingError::DisqualificationUnavailable.into())
+        );
+    }
+
+    #[test]
+    fn a_hand_market_opens_before_its_hand_is_dealt() {
+        let mut lobby = open_lobby(&[300, 700]);
+        assert_eq!(check_hand_market_opens(&lobby, 2, 3), Ok(()));
+        assert_eq!(
+            check_hand_market_opens(&lobby, 3, 3),
+            Err(BettingError::HandMarketClosed.into())
+        );
+        lobby.hands_checkpointed = 4;
+        assert_eq!(
+            check_hand_market_opens(&lobby, 2, 3),
+            Err(BettingError::HandMarketClosed.into())
+        );
+        lobby.hands_checkpointed = 0;
+        lobby.open_hand_markets = vec![10; MAX_OPEN_HAND_MARKETS];
+        assert_eq!(
+            check_hand_market_opens(&lobby, 2, 3),
+            Err(BettingError::TooManyHandMarkets.into())
+        );
+
+        let market = hand_market(&[0, 0]);
+        assert_eq!(check_hand_bets_open(&lobby, &market, 2), Ok(()));
+        assert_eq!(
+            check_hand_bets_open(&lobby, &market, 3),
+            Err(BettingError::HandMarketClosed.into())
+        );
+        // The match market locking doesn't close it
+        lobby.pools_locked = true;
+        assert_eq!(check_hand_bets_open(&lobby, &market, 2), Ok(()));
+    }
+
+    #[test]
+    fn a_hand_market_pays_its_winners_and_refunds_a_void_hand() {
+        let mut lobby = open_lobby(&[300, 700]);
+        lobby.open_hand_markets = vec![3];
+        let mut market = hand_market(&[100, 200]);
+        settle_hand_market(&mut lobby, &mut market, Some("model-a"));
+        assert!(market.settled && lobby.open_hand_markets.is_empty());
+        assert_eq!(market.winner_index, Some(0));
+        assert!(claim_hand_bet(&mut market, &hand_bet(0, 40)) == Ok((120, BetStatus::Paid)));
+        assert!(claim_hand_bet(&mut market, &hand_bet(0, 60)) == Ok((180, BetStatus::Paid)));
+        assert!(
+            claim_hand_bet(&mut market, &hand_bet(1, 200))
+                == Err(BettingError::BetOnWrongPlayer.into())
+        );
+
+        // Won by a model nobody backed, or never dealt
+        for winner in [Some("model-b"), None] {
+            let mut market = hand_market(&[100, 0]);
+            settle_hand_market(&mut lobby, &mut market, winner);
+            let claim = claim_hand_bet(&mut market, &hand_bet(0, 40));
+            assert!(claim == Ok((40, BetStatus::Refunded)));
+        }
+        assert!(
+            claim_hand_bet(&mut hand_market(&[100, 0]), &hand_bet(0, 40))
+                == Err(BettingError::HandMarketNotSettled.into())
+        );
+    }
+
+    #[test]
+    fn a_keeper_is_paid_for_a_committed_crank_on_time() {
+        let lobby = Pubkey::new_unique();
+        let mut config = config();
+        config.keeper_fee = 5;
+        config.min_keeper_stake = 1_000;
+        let mut on_time = keeper(lobby);
+        assert_eq!(credit_keeper(&mut on_time, &config, lobby, 50, 2, 50), Ok(()));
+        assert_eq!((on_time.fees_owed, on_time.cranks), (10, 2));
+        assert_eq!(on_time.committed_lobby, None);
+
+        // Late, or on a lobby it didn't commit to, it goes unpaid
+        let mut late = keeper(lobby);
+        let late_at = 50 + KEEPER_FEE_WINDOW + 1;
+        assert_eq!(credit_keeper(&mut late, &config, lobby, 50, 2, late_at), Ok(()));
+        assert_eq!((late.fees_owed, late.committed_lobby), (0, None));
+        let mut elsewhere = keeper(Pubkey::new_unique());
+        assert_eq!(credit_keeper(&mut elsewhere, &config, lobby, 50, 2, 50), Ok(()));
+        assert_eq!(elsewhere.fees_owed, 0);
+        assert!(elsewhere.committed_lobby.is_some());
+
+        assert_eq!(
+            check_keeper_commits(&elsewhere, &config),
+            Err(BettingError::KeeperCommitted.into())
+        );
+        assert_eq!(check_keeper_commits(&on_time, &config), Ok(()));
+        on_time.retired_at = 1;
+        assert_eq!(
+            check_keeper_commits(&on_time, &config),
+            Err(BettingError::KeeperRetired.into())
+        );
+    }
+
+    #[test]
+    fn a_keeper_that_misses_its_crank_is_slashed() {
+        let lobby = Pubkey::new_unique();
+        let mut missed = keeper(lobby);
+        assert_eq!(
+            slash_keeper(&mut missed.clone(), 400, 100),
+            Err(BettingError::KeeperNotSlashable.into())
+        );
+        assert_eq!(slash_keeper(&mut missed, 4_000, 101), Ok((lobby, 1_000)));
+        assert_eq!(missed.stake, 0);
+        assert_eq!(
+            slash_keeper(&mut missed, 1, 101),
+            Err(BettingError::KeeperNotSlashable.into())
+        );
+    }
+
+    #[test]
+    fn a_timelocked_change_waits_for_its_eta() {
+        let mut config = config();
+        assert_eq!(set_config(&mut config, ConfigChange::FeeBps(100), 5), Ok(()));
+        assert_eq!((config.fee_bps, config.updated_at), (100, 5));
+
+        config.admin_timelock = 60;
+        assert_eq!(
+            set_config(&mut config, ConfigChange::FeeBps(200), 5),
+            Err(BettingError::TimelockActive.into())
+        );
+        assert_eq!(
+            propose_config_change(&mut config, ConfigChange::FeeBps(200), 10),
+            Ok(70)
+        );
+        assert_eq!(
+            propose_config_change(&mut config, ConfigChange::FeeBps(300), 10),
+            Err(BettingError::ConfigChangePending.into())
+        );
+        assert!(
+            execute_config_change(&mut config.clone(), 69)
+                == Err(BettingError::TimelockActive.into())
+        );
+        assert!(execute_config_change(&mut config, 70) == Ok(ConfigChange::FeeBps(200)));
+        assert_eq!(config.fee_bps, 200);
+        assert!(
+            execute_config_change(&mut config, 70)
+                == Err(BettingError::NoPendingConfigChange.into())
+        );
+    }
+
+    #[test]
+    fn a_change_is_checked_when_proposed_and_when_executed() {
+        let mut config = config();
+        config.admin_timelock = 60;
+        assert_eq!(
+            propose_config_change(&mut config, ConfigChange::RaffleBps(10_001), 0),
+            Err(BettingError::InvalidFeeBps.into())
+        );
+        assert!(config.pending_change.is_none());
+    }
+}
@@ -0,0 +1,96 @@
+//! Checked fixed-point helpers shared by fee, odds and payout calculations.
+//!
+//! The arithmetic lives in the `poker-betting-math` crate so off-chain clients
+//! compute exactly what the program pays; these wrappers report its failures
+//! as `BettingError::Overflow`, like every other arithmetic failure in the
+//! program.
+
+use anchor_lang::prelude::*;
+use poker_betting_math as shared;
+
+use crate::BettingError;
+
+pub use shared::{isqrt, BPS_DENOMINATOR, ODDS_SCALE};
+
+fn checked<T>(result: shared::Result<T>) -> Result<T> {
+    result.map_err(|_| error!(BettingError::Overflow))
+}
+
+/// `a * b / c`, rounded down.
+pub fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    checked(shared::mul_div(a, b, c))
+}
+
+/// `a * b / c`, rounded up.
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Result<u64> {
+    checked(shared::mul_div_ceil(a, b, c))
+}
+
+/// `a * b / c` over u128, rounded down, for accumulators that outgrow u64.
+pub fn mul_div_wide(a: u128, b: u128, c: u128) -> Result<u128> {
+    checked(shared::mul_div_wide(a, b, c))
+}
+
+/// `bps` basis points of `amount`, rounded down.
+pub fn apply_bps(amount: u64, bps: u64) -> Result<u64> {
+    checked(shared::apply_bps(amount, bps))
+}
+
+/// `part` as basis points of `total`; zero when `total` is zero.
+pub fn bps_share(part: u64, total: u64) -> Result<u64> {
+    checked(shared::bps_share(part, total))
+}
+
+pub fn narrow(value: u128) -> Result<u64> {
+    checked(shared::narrow(value))
+}
+
+pub fn stake_after_fee(amount: u64, fee_bps: u64) -> Result<u64> {
+    checked(shared::stake_after_fee(amount, fee_bps))
+}
+
+pub fn parimutuel_payout(stake: u64, distributable: u64, winning_stake: u64) -> Result<u64> {
+    checked(shared::parimutuel_payout(
+        stake,
+        distributable,
+        winning_stake,
+    ))
+}
+
+pub fn parimutuel_claim(
+    stake: u64,
+    settled_stake: u64,
+    paid: u64,
+    distributable: u64,
+    winning_stake: u64,
+) -> Result<u64> {
+    checked(shared::parimutuel_claim(
+        stake,
+        settled_stake,
+        paid,
+        distributable,
+        winning_stake,
+    ))
+}
+
+pub fn fixed_odds_payout(stake: u64, odds_bps: u32) -> Result<u64> {
+    checked(shared::fixed_odds_payout(stake, odds_bps))
+}
+
+pub fn implied_odds_bps(distributable: u64, winning_stake: u128) -> Result<u64> {
+    checked(shared::implied_odds_bps(distributable, winning_stake))
+}
+
+pub fn weighted_odds_bps(
+    stake: u64,
+    weight: u64,
+    distributable: u64,
+    winning_weight: u64,
+) -> Result<u64> {
+    checked(shared::weighted_odds_bps(
+        stake,
+        weight,
+        distributable,
+        winning_weight,
+    ))
+}
@@ -0,0 +1,25 @@
+//! Merkle proofs for roots computed off-chain (payout distributions, season
+//! snapshots).
+//!
+//! Leaves and nodes are domain-separated so a node can't pass as a leaf, and
+//! each pair is hashed in sorted order so proofs need no direction bits.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+/// Leaf for `amount` owed to `account`.
+pub fn leaf(account: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[&[0], account.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let (left, right) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        hashv(&[&[1], &left, &right]).to_bytes()
+    });
+    computed == *root
+}
@@ -0,0 +1,158 @@
+//! Addresses of the program's PDAs, derived from the seed constants at the
+//! crate root.
+//!
+//! Clients should derive accounts through these rather than hardcoding seed
+//! strings, so a seed change shows up as a compile error instead of a wrong
+//! address.
+
+use anchor_lang::prelude::*;
+
+use crate::*;
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+pub fn config_pda() -> Pubkey {
+    find(&[CONFIG_SEED])
+}
+
+pub fn treasury_pda() -> Pubkey {
+    find(&[TREASURY_SEED])
+}
+
+pub fn denylist_pda() -> Pubkey {
+    find(&[DENYLIST_SEED])
+}
+
+pub fn stake_pool_pda() -> Pubkey {
+    find(&[STAKE_POOL_SEED])
+}
+
+pub fn revenue_vault_pda() -> Pubkey {
+    find(&[REVENUE_SEED])
+}
+
+/// Lobby `owner` created for `game_id`. Lobbies are scoped to their owner,
+/// so nobody else can take a game server's id first.
+pub fn lobby_pda(owner: &Pubkey, game_id: &str) -> Pubkey {
+    find(&[LOBBY_SEED, owner.as_ref(), &game_id_hash(game_id)])
+}
+
+/// Escrow holding a lobby's match-market stakes.
+pub fn escrow_pda(lobby: &Pubkey) -> Pubkey {
+    find(&[ESCROW_SEED, lobby.as_ref()])
+}
+
+pub fn snapshot_pda(lobby: &Pubkey) -> Pubkey {
+    find(&[SNAPSHOT_SEED, lobby.as_ref()])
+}
+
+pub fn game_config_pda(lobby: &Pubkey) -> Pubkey {
+    find(&[GAME_CONFIG_SEED, lobby.as_ref()])
+}
+
+pub fn operator_bond_pda(lobby: &Pubkey) -> Pubkey {
+    find(&[OPERATOR_BOND_SEED, lobby.as_ref()])
+}
+
+/// Marks `bet` compensated from its lobby's slashed bond.
+pub fn bond_claim_pda(bet: &Pubkey) -> Pubkey {
+    find(&[BOND_CLAIM_SEED, bet.as_ref()])
+}
+
+pub fn feed_pda(lobby: &Pubkey) -> Pubkey {
+    find(&[FEED_SEED, lobby.as_ref()])
+}
+
+pub fn match_stake_pda(lobby: &Pubkey, model_name: &str) -> Pubkey {
+    find(&[MATCH_STAKE_SEED, lobby.as_ref(), model_name.as_bytes()])
+}
+
+pub fn player_pool_pda(lobby: &Pubkey, model_index: u8) -> Pubkey {
+    find(&[PLAYER_POOL_SEED, lobby.as_ref(), &[model_index]])
+}
+
+pub fn bounty_pda(lobby: &Pubkey, bounty_id: u64) -> Pubkey {
+    find(&[BOUNTY_SEED, lobby.as_ref(), &bounty_id.to_le_bytes()])
+}
+
+/// A wallet's back or lay bet on a lobby.
+pub fn bet_pda(lobby: &Pubkey, bettor: &Pubkey, side: &BetSide) -> Pubkey {
+    find(&[side.bet_seed(), lobby.as_ref(), bettor.as_ref()])
+}
+
+/// Marks a wallet's claim against a lobby's payout root.
+pub fn merkle_claim_pda(lobby: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    find(&[MERKLE_CLAIM_SEED, lobby.as_ref(), bettor.as_ref()])
+}
+
+pub fn blind_bet_pda(lobby: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    find(&[BLIND_BET_SEED, lobby.as_ref(), bettor.as_ref()])
+}
+
+pub fn chip_bet_pda(lobby: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    find(&[CHIP_BET_SEED, lobby.as_ref(), bettor.as_ref()])
+}
+
+pub fn freeroll_pick_pda(lobby: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    find(&[FREEROLL_PICK_SEED, lobby.as_ref(), bettor.as_ref()])
+}
+
+pub fn spectator_pass_pda(lobby: &Pubkey, viewer: &Pubkey) -> Pubkey {
+    find(&[SPECTATOR_PASS_SEED, lobby.as_ref(), viewer.as_ref()])
+}
+
+pub fn hand_market_pda(lobby: &Pubkey, hand_number: u64) -> Pubkey {
+    find(&[HAND_MARKET_SEED, lobby.as_ref(), &hand_number.to_le_bytes()])
+}
+
+pub fn hand_bet_pda(hand_market: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    find(&[HAND_BET_SEED, hand_market.as_ref(), bettor.as_ref()])
+}
+
+/// Escrow holding a lobby's hand-market stakes, apart from its match escrow.
+pub fn hand_escrow_pda(lobby: &Pubkey) -> Pubkey {
+    find(&[HAND_ESCROW_SEED, lobby.as_ref()])
+}
+
+pub fn bettor_stats_pda(bettor: &Pubkey) -> Pubkey {
+    find(&[BETTOR_STATS_SEED, bettor.as_ref()])
+}
+
+pub fn self_exclusion_pda(bettor: &Pubkey) -> Pubkey {
+    find(&[SELF_EXCLUSION_SEED, bettor.as_ref()])
+}
+
+pub fn stake_position_pda(staker: &Pubkey) -> Pubkey {
+    find(&[STAKE_SEED, staker.as_ref()])
+}
+
+pub fn owner_stats_pda(owner: &Pubkey) -> Pubkey {
+    find(&[OWNER_STATS_SEED, owner.as_ref()])
+}
+
+pub fn operator_profile_pda(owner: &Pubkey) -> Pubkey {
+    find(&[OPERATOR_PROFILE_SEED, owner.as_ref()])
+}
+
+pub fn attestation_pda(issuer: &Pubkey, subject: &Pubkey) -> Pubkey {
+    find(&[ATTESTATION_SEED, issuer.as_ref(), subject.as_ref()])
+}
+
+pub fn keeper_pda(authority: &Pubkey) -> Pubkey {
+    find(&[KEEPER_SEED, authority.as_ref()])
+}
+
+pub fn lobby_template_pda(owner: &Pubkey, name: &str) -> Pubkey {
+    find(&[LOBBY_TEMPLATE_SEED, owner.as_ref(), name.as_bytes()])
+}
+
+/// The program's `ProgramData` account, which holds its upgrade authority.
+pub fn program_data() -> Pubkey {
+    Pubkey::find_program_address(
+        &[crate::ID.as_ref()],
+        &anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+    )
+    .0
+}
@@ -0,0 +1,130 @@
+//! Reads Pyth pull-oracle prices for limits denominated in USD.
+//!
+//! The Pyth receiver program verifies price updates and stores them in
+//! `PriceUpdateV2` accounts it owns. This program only reads those accounts,
+//! so the SDK is not needed. USD amounts are in micro-USD, millionths of a
+//! dollar.
+
+use anchor_lang::prelude::*;
+
+use crate::BettingError;
+
+pub const RECEIVER_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// `sha256("account:PriceUpdateV2")[..8]`
+const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+/// Lamports per SOL, as a power of ten.
+const LAMPORTS_DECIMALS: i32 = 9;
+/// Micro-USD per dollar, as a power of ten.
+const MICRO_USD_DECIMALS: i32 = 6;
+
+/// Fields of a `PriceUpdateV2` that pricing needs.
+pub struct PriceUpdate {
+    pub fully_verified: bool,
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+impl PriceUpdate {
+    /// Parses the discriminator, write authority, verification level and the
+    /// start of the price message.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() > 41 && data[..8] == PRICE_UPDATE_V2_DISCRIMINATOR,
+            BettingError::InvalidPriceUpdate
+        );
+        // `VerificationLevel::Partial { num_signatures: u8 }` or `Full`
+        let (fully_verified, message) = match data[40] {
+            0 => (false, &data[42..]),
+            1 => (true, &data[41..]),
+            _ => return err!(BettingError::InvalidPriceUpdate),
+        };
+        require!(message.len() >= 60, BettingError::InvalidPriceUpdate);
+        Ok(Self {
+            fully_verified,
+            feed_id: message[..32].try_into().unwrap(),
+            price: i64::from_le_bytes(message[32..40].try_into().unwrap()),
+            conf: u64::from_le_bytes(message[40..48].try_into().unwrap()),
+            exponent: i32::from_le_bytes(message[48..52].try_into().unwrap()),
+            publish_time: i64::from_le_bytes(message[52..60].try_into().unwrap()),
+        })
+    }
+}
+
+/// SOL/USD price that passed the feed, staleness and confidence checks:
+/// one SOL is worth `price * 10^exponent` dollars.
+#[derive(Clone, Copy, Debug)]
+pub struct UsdPrice {
+    pub price: u64,
+    pub exponent: i32,
+}
+
+impl UsdPrice {
+    /// Reads a fully verified update of `feed_id` published within `max_age`
+    /// seconds of `now`, with a confidence interval of at most `max_conf_bps`
+    /// of the price.
+    pub fn load(
+        info: &AccountInfo,
+        feed_id: &[u8; 32],
+        max_age: i64,
+        max_conf_bps: u16,
+        now: i64,
+    ) -> Result<Self> {
+        require_keys_eq!(*info.owner, RECEIVER_ID, BettingError::InvalidPriceUpdate);
+        let update = PriceUpdate::parse(&info.try_borrow_data()?)?;
+        require!(
+            update.fully_verified && update.feed_id == *feed_id,
+            BettingError::InvalidPriceUpdate
+        );
+        require!(
+            update.publish_time >= now - max_age,
+            BettingError::StalePrice
+        );
+        let price = u64::try_from(update.price)
+            .ok()
+            .filter(|price| *price > 0)
+            .ok_or(BettingError::InvalidPriceUpdate)?;
+        require!(
+            update.conf <= crate::math::apply_bps(price, max_conf_bps as u64)?,
+            BettingError::PriceConfidenceTooWide
+        );
+        Ok(Self {
+            price,
+            exponent: update.exponent,
+        })
+    }
+
+    /// Micro-USD `lamports` are worth, rounded down.
+    pub fn micro_usd(&self, lamports: u64) -> Result<u64> {
+        let value = lamports as u128 * self.price as u128;
+        crate::math::narrow(scale(
+            value,
+            self.exponent + MICRO_USD_DECIMALS - LAMPORTS_DECIMALS,
+        )?)
+    }
+
+    /// Lamports worth `micro_usd`, rounded down.
+    pub fn lamports(&self, micro_usd: u64) -> Result<u64> {
+        let value = scale(
+            micro_usd as u128,
+            LAMPORTS_DECIMALS - MICRO_USD_DECIMALS - self.exponent,
+        )?;
+        crate::math::narrow(value / self.price as u128)
+    }
+}
+
+/// `value * 10^exponent`, rounded down.
+fn scale(value: u128, exponent: i32) -> Result<u128> {
+    let power = 10u128
+        .checked_pow(exponent.unsigned_abs())
+        .ok_or(BettingError::Overflow)?;
+    if exponent >= 0 {
+        Ok(value.checked_mul(power).ok_or(BettingError::Overflow)?)
+    } else {
+        Ok(value / power)
+    }
+}
@@ -0,0 +1,72 @@
+//! Shielded note pool for private betting.
+//!
+//! Deposits are a fixed denomination, so amounts reveal nothing, and each one
+//! appends a note commitment to an incremental Merkle tree. A note is later
+//! spent on a bet with a Groth16 proof that it is in the tree under a recent
+//! root, revealing only its nullifier hash, so the bet can't be linked to the
+//! depositing wallet. Nodes are SHA-256 truncated to 253 bits, keeping every
+//! root a valid BN254 scalar for the spend circuit.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::{BetSide, BettingError};
+
+/// Depth of the note tree; it holds 2^20 notes.
+pub const TREE_DEPTH: usize = 20;
+
+/// Recent roots a spend proof may be made against.
+pub const ROOT_HISTORY: usize = 32;
+
+/// Public inputs of the spend circuit: root, nullifier hash and bet binding.
+pub const PUBLIC_INPUTS: usize = 3;
+
+fn truncate(mut hash: [u8; 32]) -> [u8; 32] {
+    hash[0] &= 0x1f;
+    hash
+}
+
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    truncate(hashv(&[left, right]).to_bytes())
+}
+
+/// Root of an empty subtree of height `level`.
+pub fn zero(level: usize) -> [u8; 32] {
+    (0..level).fold([0; 32], |node, _| hash_pair(&node, &node))
+}
+
+/// Appends `leaf` at `index`, updating the tree's frontier, and returns the
+/// new root.
+pub fn insert(filled_subtrees: &mut [[u8; 32]], index: u32, leaf: [u8; 32]) -> Result<[u8; 32]> {
+    require!(
+        (index as u64) < 1 << TREE_DEPTH,
+        BettingError::ShieldedPoolFull
+    );
+    let mut node = leaf;
+    let mut empty = [0; 32];
+    let mut index = index;
+    for filled in filled_subtrees.iter_mut() {
+        node = if index & 1 == 0 {
+            *filled = node;
+            hash_pair(&node, &empty)
+        } else {
+            hash_pair(filled, &node)
+        };
+        empty = hash_pair(&empty, &empty);
+        index >>= 1;
+    }
+    Ok(node)
+}
+
+/// Binds a spend proof to the bet it funds, so a relayer can't redirect it.
+pub fn bet_binding(lobby: &Pubkey, bettor: &Pubkey, side: BetSide, player_name: &str) -> [u8; 32] {
+    truncate(
+        hashv(&[
+            lobby.as_ref(),
+            bettor.as_ref(),
+            &[side as u8],
+            player_name.as_bytes(),
+        ])
+        .to_bytes(),
+    )
+}
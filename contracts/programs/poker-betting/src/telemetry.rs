@@ -0,0 +1,61 @@
+//! Per-instruction compute telemetry, built with the `telemetry` feature.
+//!
+//! The feature swaps Anchor's entrypoint for one that runs the instruction and
+//! then emits `InstructionTelemetry`, so operators can watch compute headroom
+//! per instruction from the logs.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::Discriminator;
+
+use crate::Lobby;
+
+#[event]
+pub struct InstructionTelemetry {
+    /// Discriminator of the instruction that ran
+    pub instruction: [u8; 8],
+    /// Compute units left when the instruction returned
+    pub compute_units_remaining: u64,
+    pub succeeded: bool,
+    /// Counters of the first lobby passed in, zero if none
+    pub lobby_seq: u64,
+    pub escrow_balance: u64,
+    pub total_bets: u64,
+}
+
+#[cfg(not(feature = "cpi"))]
+anchor_lang::solana_program::entrypoint!(entry);
+
+pub fn entry<'info>(
+    program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    data: &[u8],
+) -> ProgramResult {
+    let result = crate::entry(program_id, accounts, data);
+    let compute_units_remaining = sol_remaining_compute_units();
+
+    let mut instruction = [0; 8];
+    if let Some(discriminator) = data.get(..8) {
+        instruction.copy_from_slice(discriminator);
+    }
+    let lobby = accounts
+        .iter()
+        .filter(|account| account.owner == program_id)
+        .find_map(|account| {
+            let data = account.try_borrow_data().ok()?;
+            if !data.starts_with(&Lobby::DISCRIMINATOR) {
+                return None;
+            }
+            Lobby::try_deserialize(&mut &data[..]).ok()
+        });
+    emit!(InstructionTelemetry {
+        instruction,
+        compute_units_remaining,
+        succeeded: result.is_ok(),
+        lobby_seq: lobby.as_ref().map_or(0, |lobby| lobby.seq),
+        escrow_balance: lobby.as_ref().map_or(0, |lobby| lobby.escrow_balance),
+        total_bets: lobby.as_ref().map_or(0, |lobby| lobby.total_bets),
+    });
+    result
+}
@@ -0,0 +1,40 @@
+//! Reads ORAO VRF randomness for lobby raffles.
+//!
+//! Randomness is requested from the ORAO VRF program, by the client or any
+//! other caller, for a seed this program picks. Once the oracle fulfils the
+//! request, its `Randomness` account holds 64 random bytes. This program
+//! only reads that account, so the SDK is not needed.
+
+use anchor_lang::prelude::*;
+
+use crate::BettingError;
+
+pub const VRF_ID: Pubkey = pubkey!("VRFzZoJdhFWL8rkvu87LpKM3RbcVezpMEc6X5GVDr7y");
+
+const RANDOMNESS_SEED: &[u8] = b"orao-vrf-randomness-request";
+
+/// `sha256("account:Randomness")[..8]`
+const RANDOMNESS_DISCRIMINATOR: [u8; 8] = [188, 96, 216, 248, 93, 94, 49, 112];
+
+/// Address of the randomness request for `seed`.
+pub fn randomness_address(seed: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[RANDOMNESS_SEED, seed], &VRF_ID).0
+}
+
+/// Fulfilled randomness for `seed`, read from `info`, which must be the
+/// request account for that seed.
+pub fn fulfilled_randomness(info: &AccountInfo, seed: &[u8; 32]) -> Result<[u8; 64]> {
+    require!(
+        info.key() == randomness_address(seed) && *info.owner == VRF_ID,
+        BettingError::InvalidRandomness
+    );
+    let data = info.try_borrow_data()?;
+    // Discriminator, seed, then the randomness
+    require!(
+        data.len() >= 8 + 32 + 64 && data[..8] == RANDOMNESS_DISCRIMINATOR,
+        BettingError::InvalidRandomness
+    );
+    let randomness: [u8; 64] = data[40..104].try_into().unwrap();
+    require!(randomness != [0; 64], BettingError::RandomnessNotFulfilled);
+    Ok(randomness)
+}
@@ -0,0 +1,80 @@
+//! Reads Wormhole messages that carry bets from other chains.
+//!
+//! The core bridge verifies guardian signatures and stores the message in a
+//! `PostedVAA` account at `["PostedVAA", vaa_hash]`, owned by the bridge. This
+//! program only reads that account, so the SDK is not needed.
+
+use anchor_lang::prelude::*;
+
+use crate::{BetSide, BettingError};
+
+/// Payload id of a cross-chain bet.
+pub const BET_PAYLOAD_ID: u8 = 1;
+
+/// Fields of a posted VAA that bet intake needs.
+pub struct PostedVaa {
+    pub sequence: u64,
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub payload: Vec<u8>,
+}
+
+impl PostedVaa {
+    /// Parses core bridge `PostedVAAData`: a `vaa` magic followed by the
+    /// borsh-encoded message.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= 95 && &data[..3] == b"vaa",
+            BettingError::InvalidVaa
+        );
+        let payload_len = u32::from_le_bytes(data[91..95].try_into().unwrap()) as usize;
+        let payload = data
+            .get(95..95 + payload_len)
+            .ok_or(BettingError::InvalidVaa)?
+            .to_vec();
+        Ok(Self {
+            sequence: u64::from_le_bytes(data[49..57].try_into().unwrap()),
+            emitter_chain: u16::from_le_bytes(data[57..59].try_into().unwrap()),
+            emitter_address: data[59..91].try_into().unwrap(),
+            payload,
+        })
+    }
+}
+
+/// Bet carried in a VAA payload. Big-endian, as emitted by the EVM contract:
+/// `payload_id u8 | evm_bettor [20] | bettor [32] | lobby [32] | side u8 |
+/// amount u64 | player_name (rest, UTF-8)`.
+pub struct BetPayload {
+    /// Address that placed the bet on the source chain
+    pub evm_bettor: [u8; 20],
+    /// Solana wallet the bet is placed for and paid out to
+    pub bettor: Pubkey,
+    pub lobby: Pubkey,
+    pub side: BetSide,
+    /// Bridged USDC in base units
+    pub amount: u64,
+    pub player_name: String,
+}
+
+impl BetPayload {
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        require!(
+            payload.len() > 94 && payload[0] == BET_PAYLOAD_ID,
+            BettingError::InvalidVaa
+        );
+        let side = match payload[85] {
+            0 => BetSide::Back,
+            1 => BetSide::Lay,
+            _ => return err!(BettingError::InvalidVaa),
+        };
+        Ok(Self {
+            evm_bettor: payload[1..21].try_into().unwrap(),
+            bettor: Pubkey::new_from_array(payload[21..53].try_into().unwrap()),
+            lobby: Pubkey::new_from_array(payload[53..85].try_into().unwrap()),
+            side,
+            amount: u64::from_be_bytes(payload[86..94].try_into().unwrap()),
+            player_name: String::from_utf8(payload[94..].to_vec())
+                .map_err(|_| error!(BettingError::InvalidVaa))?,
+        })
+    }
+}
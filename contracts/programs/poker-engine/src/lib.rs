@@ -0,0 +1,1310 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::{hash, hashv};
+
+pub mod shuffle;
+
+use shuffle::{Card, CARD_BYTES, DECK_SIZE};
+
+declare_id!("DxMcUAAnvcgJck6vXb8w4nDe7ax5bu2Li7wRQKghW19q");
+
+#[program]
+pub mod poker_engine {
+    use super::*;
+
+    /// Creates the engine config with the caller as the game server authority.
+    pub fn initialize_engine(ctx: Context<InitializeEngine>) -> Result<()> {
+        ctx.accounts.engine.authority = ctx.accounts.authority.key();
+        Ok(())
+    }
+
+    pub fn set_authority(ctx: Context<UpdateEngine>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.engine.authority = new_authority;
+        Ok(())
+    }
+
+    /// Opens the canonical record for `game_id`, matching the id of the
+    /// betting lobby `owner` created for it.
+    pub fn create_game(
+        ctx: Context<CreateGame>,
+        game_id: String,
+        player_names: Vec<String>,
+        owner: Pubkey,
+    ) -> Result<()> {
+        require!(game_id.len() <= MAX_GAME_ID_LEN, EngineError::NameTooLong);
+        require!(
+            (2..=MAX_PLAYERS).contains(&player_names.len()),
+            EngineError::InvalidPlayerCount
+        );
+        require!(
+            player_names.iter().all(|name| name.len() <= MAX_NAME_LEN),
+            EngineError::NameTooLong
+        );
+
+        let game = &mut ctx.accounts.game;
+        game.owner = owner;
+        game.game_id_hash = hash(game_id.as_bytes()).to_bytes();
+        game.game_id = game_id;
+        game.player_names = player_names;
+        game.hands_played = 0;
+        game.finalized = false;
+        game.winner = None;
+        game.placings = Vec::new();
+        game.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Records the result of the next hand. Hands are numbered from zero and
+    /// must be written in order.
+    pub fn record_hand(
+        ctx: Context<RecordHand>,
+        hand_number: u64,
+        pot: u64,
+        board: [u8; 5],
+        winner_name: String,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(!game.finalized, EngineError::GameFinalized);
+        require!(hand_number == game.hands_played, EngineError::HandOutOfOrder);
+        require!(
+            game.player_names.contains(&winner_name),
+            EngineError::UnknownPlayer
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let hand = &mut ctx.accounts.hand;
+        hand.game = game.key();
+        hand.hand_number = hand_number;
+        hand.pot = pot;
+        hand.board = board;
+        hand.winner_name = winner_name;
+        hand.recorded_at = now;
+        hand.trace_hashes = vec![[0; 32]; game.player_names.len()];
+
+        game.hands_played += 1;
+        game.updated_at = now;
+        Ok(())
+    }
+
+    /// Stores the hash of `player_name`'s reasoning trace for a recorded hand,
+    /// so published model logs can be checked against play. Each hash can be
+    /// written once.
+    pub fn record_decision_hash(
+        ctx: Context<RecordDecisionHash>,
+        _hand_number: u64,
+        player_name: String,
+        trace_hash: [u8; 32],
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        require!(!game.finalized, EngineError::GameFinalized);
+        require!(trace_hash != [0; 32], EngineError::InvalidTraceHash);
+        let seat = game
+            .player_names
+            .iter()
+            .position(|name| *name == player_name)
+            .ok_or(EngineError::UnknownPlayer)?;
+
+        let hand = &mut ctx.accounts.hand;
+        require!(
+            hand.trace_hashes[seat] == [0; 32],
+            EngineError::TraceHashAlreadyRecorded
+        );
+        hand.trace_hashes[seat] = trace_hash;
+        Ok(())
+    }
+
+    /// Seals the game with its overall winner and, optionally, the full
+    /// finishing order, starting with the winner. The betting program reads
+    /// this account to settle the lobby and its placed positions.
+    pub fn finalize_game(
+        ctx: Context<FinalizeGame>,
+        winner_name: String,
+        placings: Vec<String>,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(!game.finalized, EngineError::GameFinalized);
+        require!(
+            game.player_names.contains(&winner_name),
+            EngineError::UnknownPlayer
+        );
+        require!(
+            placings.is_empty() || placings[0] == winner_name,
+            EngineError::InvalidPlacings
+        );
+        for (i, name) in placings.iter().enumerate() {
+            require!(game.player_names.contains(name), EngineError::UnknownPlayer);
+            require!(!placings[..i].contains(name), EngineError::InvalidPlacings);
+        }
+
+        game.finalized = true;
+        game.winner = Some(winner_name);
+        game.placings = placings;
+        game.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Closes a hand record of a finalized game, returning its rent to the
+    /// authority. The betting program reads hand records to settle hand
+    /// markets and count reimbursed hands, so it calls this through
+    /// `close_hand_record`, signing as the game's lobby once that has settled.
+    pub fn close_hand(_ctx: Context<CloseHand>, _hand_number: u64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Opens a heads-up table for a two-player game record. Seat `i` plays as
+    /// `game.player_names[i]` and only `players[i]` may take it.
+    pub fn create_table(
+        ctx: Context<CreateTable>,
+        players: [Pubkey; 2],
+        buy_in: u64,
+        small_blind: u64,
+        big_blind: u64,
+        turn_timeout: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.game.player_names.len() == 2,
+            EngineError::InvalidPlayerCount
+        );
+        require!(
+            small_blind > 0 && big_blind >= small_blind && buy_in >= big_blind,
+            EngineError::InvalidTableParams
+        );
+        require!(turn_timeout > 0, EngineError::InvalidTableParams);
+        require!(
+            players[0] != players[1] && !players.contains(&Pubkey::default()),
+            EngineError::InvalidTableParams
+        );
+
+        let table = &mut ctx.accounts.table;
+        table.game = ctx.accounts.game.key();
+        table.players = players;
+        table.seats = [Pubkey::default(); 2];
+        table.stacks = [0; 2];
+        table.buy_in = buy_in;
+        table.small_blind = small_blind;
+        table.big_blind = big_blind;
+        table.turn_timeout = turn_timeout;
+        table.hand_number = 0;
+        table.street = Street::Idle;
+        table.awaiting_deal = false;
+        table.dealer = 0;
+        table.to_act = 0;
+        table.street_bets = [0; 2];
+        table.last_raise = big_blind;
+        table.acted = [false; 2];
+        table.pot = 0;
+        table.hole_commitments = [[0; 32]; 2];
+        table.board_commitment = [0; 32];
+        table.board = [0; 5];
+        table.board_len = 0;
+        table.last_action_at = 0;
+        table.missed_turns = [0; 2];
+        table.forfeited = None;
+        Ok(())
+    }
+
+    /// Takes the player's empty seat and escrows the buy-in on the table.
+    /// Closed once a seat has forfeited the match.
+    pub fn join_table(ctx: Context<JoinTable>, seat: u8) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let seat = seat as usize;
+        require!(seat < 2, EngineError::InvalidSeat);
+        require!(table.forfeited.is_none(), EngineError::MatchForfeited);
+        require!(
+            table.players[seat] == ctx.accounts.player.key(),
+            EngineError::NotSeatPlayer
+        );
+        require!(
+            table.seats[seat] == Pubkey::default(),
+            EngineError::SeatTaken
+        );
+
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.player.to_account_info(),
+            to: table.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_ctx, table.buy_in)?;
+
+        table.seats[seat] = ctx.accounts.player.key();
+        table.stacks[seat] = table.buy_in;
+        Ok(())
+    }
+
+    /// Cashes out the player's stack between hands. Leaving once the first
+    /// hand has been played forfeits the match to the remaining seat.
+    pub fn leave_table(ctx: Context<LeaveTable>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        require!(table.street == Street::Idle, EngineError::HandInProgress);
+        let seat = table.seat_of(&ctx.accounts.player.key())?;
+        if table.hand_number > 0 && table.forfeited.is_none() {
+            table.forfeited = Some(seat as u8);
+        }
+
+        let amount = table.stacks[seat];
+        table.stacks[seat] = 0;
+        table.seats[seat] = Pubkey::default();
+        if amount > 0 {
+            table.sub_lamports(amount)?;
+            ctx.accounts.player.add_lamports(amount)?;
+        }
+        Ok(())
+    }
+
+    /// Starts the next hand: posts the blinds and records commitments to each
+    /// seat's encrypted hole cards and to the full board. The dealer posts the
+    /// small blind and acts first preflop. Every shuffler must have sealed the
+    /// hand's deck, which the cards are dealt from in `DEAL_ORDER`.
+    pub fn start_hand(
+        ctx: Context<TableDeck>,
+        hole_commitments: [[u8; 32]; 2],
+        board_commitment: [u8; 32],
+    ) -> Result<()> {
+        let deck = &ctx.accounts.deck;
+        require!(
+            deck.sealed_stages as usize == deck.shufflers.len(),
+            EngineError::DeckNotShuffled
+        );
+        let table = &mut ctx.accounts.table;
+        require!(table.street == Street::Idle, EngineError::HandInProgress);
+        require!(
+            !table.seats.contains(&Pubkey::default()),
+            EngineError::TableNotFull
+        );
+        require!(
+            table.stacks.iter().all(|stack| *stack >= table.big_blind),
+            EngineError::InsufficientStack
+        );
+
+        let dealer = table.dealer as usize;
+        table.hole_commitments = hole_commitments;
+        table.board_commitment = board_commitment;
+        table.board = [0; 5];
+        table.board_len = 0;
+        table.street_bets = [0; 2];
+        table.pot = 0;
+        let (small_blind, big_blind) = (table.small_blind, table.big_blind);
+        table.post(dealer, small_blind);
+        table.post(1 - dealer, big_blind);
+        table.last_raise = table.big_blind;
+        table.acted = [false; 2];
+        table.to_act = table.dealer;
+        table.street = Street::Preflop;
+        table.awaiting_deal = false;
+        table.last_action_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Folds, checks, calls or raises for the seat whose turn it is. A raise
+    /// gives the player's total bet for the street.
+    pub fn act(ctx: Context<Act>, action: PlayerAction) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        let seat = table.seat_of(&ctx.accounts.player.key())?;
+        require!(seat == table.to_act as usize, EngineError::NotYourTurn);
+        table.missed_turns[seat] = 0;
+        table.apply_action(seat, action, Clock::get()?.unix_timestamp)
+    }
+
+    /// Acts for a player who let their turn timer run out: checks if nothing
+    /// is owed, otherwise folds. A seat that runs out `MAX_MISSED_TURNS` turns
+    /// in a row is taken as absent and forfeits its whole stack, so
+    /// `finalize_table_game` can end the match.
+    pub fn claim_timeout(ctx: Context<ClaimTimeout>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let table = &mut ctx.accounts.table;
+        require!(table.betting_open(), EngineError::NoActionPending);
+        require!(
+            now > table.last_action_at.saturating_add(table.turn_timeout),
+            EngineError::TurnNotExpired
+        );
+        let seat = table.to_act as usize;
+        table.missed_turns[seat] += 1;
+        if table.missed_turns[seat] >= MAX_MISSED_TURNS {
+            return table.forfeit(seat);
+        }
+        let action = if table.to_call(seat) == 0 {
+            PlayerAction::Check
+        } else {
+            PlayerAction::Fold
+        };
+        table.apply_action(seat, action, now)
+    }
+
+    /// Deals the flop, turn or river once the previous betting round is over.
+    pub fn deal_street(ctx: Context<TableAuthority>, cards: Vec<u8>) -> Result<()> {
+        let table = &mut ctx.accounts.table;
+        require!(table.awaiting_deal, EngineError::NotAwaitingDeal);
+        let (next, count) = match table.street {
+            Street::Preflop => (Street::Flop, 3),
+            Street::Flop => (Street::Turn, 1),
+            Street::Turn => (Street::River, 1),
+            _ => return err!(EngineError::NotAwaitingDeal),
+        };
+        require!(cards.len() == count, EngineError::InvalidCards);
+        require!(cards.iter().all(|card| *card < 52), EngineError::InvalidCards);
+
+        let start = table.board_len as usize;
+        table.board[start..start + count].copy_from_slice(&cards);
+        table.board_len += count as u8;
+        table.street = next;
+
+        if table.stacks.contains(&0) {
+            // Someone is all in: no more betting, run the board out.
+            if next == Street::River {
+                table.street = Street::Showdown;
+                table.awaiting_deal = false;
+            }
+        } else {
+            table.awaiting_deal = false;
+            table.to_act = 1 - table.dealer;
+            table.last_action_at = Clock::get()?.unix_timestamp;
+        }
+        Ok(())
+    }
+
+    /// Reveals both hands and the board against their commitments and the
+    /// hand's verified deck, evaluates them and pays the pot to the best hand,
+    /// splitting ties.
+    pub fn showdown(
+        ctx: Context<TableDeck>,
+        hole_cards: [[u8; 2]; 2],
+        hole_salts: [[u8; 32]; 2],
+        board_salt: [u8; 32],
+    ) -> Result<()> {
+        let deck = &ctx.accounts.deck;
+        require!(
+            deck.verified_stages as usize == deck.shufflers.len(),
+            EngineError::DeckNotVerified
+        );
+        let dealt: Vec<u8> = hole_cards
+            .iter()
+            .flatten()
+            .chain(ctx.accounts.table.board.iter())
+            .copied()
+            .collect();
+        require!(
+            DEAL_ORDER.iter().map(|position| deck.order[*position as usize]).eq(dealt),
+            EngineError::NotDealtFromDeck
+        );
+        let table = &mut ctx.accounts.table;
+        require!(table.street == Street::Showdown, EngineError::NotAtShowdown);
+        for seat in 0..2 {
+            require!(
+                hashv(&[&hole_cards[seat], &hole_salts[seat]]).to_bytes()
+                    == table.hole_commitments[seat],
+                EngineError::CommitmentMismatch
+            );
+        }
+        require!(
+            hashv(&[&table.board, &board_salt]).to_bytes() == table.board_commitment,
+            EngineError::CommitmentMismatch
+        );
+
+        let mut seen = [false; 52];
+        for card in hole_cards.iter().flatten().chain(table.board.iter()) {
+            require!(
+                *card < 52 && !seen[*card as usize],
+                EngineError::InvalidCards
+            );
+            seen[*card as usize] = true;
+        }
+
+        let score = |seat: usize| {
+            let mut cards = [0u8; 7];
+            cards[..2].copy_from_slice(&hole_cards[seat]);
+            cards[2..].copy_from_slice(&table.board);
+            best_hand(&cards)
+        };
+        let (first, second) = (score(0), score(1));
+        let winner = match first.cmp(&second) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        };
+        table.award(winner)
+    }
+
+    /// Finalizes the game record once a seat has forfeited, or has no chips
+    /// left between hands while both players are seated.
+    pub fn finalize_table_game(ctx: Context<FinalizeTableGame>) -> Result<()> {
+        let table = &ctx.accounts.table;
+        let game = &mut ctx.accounts.game;
+        require!(!game.finalized, EngineError::GameFinalized);
+        require!(table.street == Street::Idle, EngineError::HandInProgress);
+        let winner = match (table.forfeited, table.stacks) {
+            (Some(seat), _) => 1 - seat as usize,
+            // A player who cashed out before the first hand hasn't lost
+            (None, _) if table.seats != table.players => return err!(EngineError::NoWinnerYet),
+            (None, [0, stack]) if stack > 0 => 1,
+            (None, [stack, 0]) if stack > 0 => 0,
+            _ => return err!(EngineError::NoWinnerYet),
+        };
+
+        game.finalized = true;
+        game.winner = Some(game.player_names[winner].clone());
+        game.placings = vec![
+            game.player_names[winner].clone(),
+            game.player_names[1 - winner].clone(),
+        ];
+        game.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Opens a mental poker deck for a hand. `shufflers` re-encrypt and
+    /// permute the deck in order, starting from the plaintext deck.
+    pub fn create_deck(
+        ctx: Context<CreateDeck>,
+        hand_number: u64,
+        shufflers: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            !shufflers.is_empty() && shufflers.len() <= MAX_SHUFFLERS,
+            EngineError::InvalidShufflers
+        );
+
+        let deck = &mut ctx.accounts.deck;
+        deck.table = ctx.accounts.table.key();
+        deck.hand_number = hand_number;
+        deck.shufflers = shufflers;
+        deck.sealed_stages = 0;
+        deck.commitments = Vec::new();
+        deck.deck_hashes = vec![shuffle::deck_hash(&shuffle::plaintext_deck())];
+        deck.verified_stages = 0;
+        deck.order = (0..DECK_SIZE as u8).collect();
+        Ok(())
+    }
+
+    /// Creates the account holding the shuffler's output deck for `stage`
+    /// (1-based, in shuffler order).
+    pub fn init_shuffle_stage(ctx: Context<InitShuffleStage>, stage: u8) -> Result<()> {
+        let deck = &ctx.accounts.deck;
+        require!(
+            stage >= 1
+                && deck.shufflers.get(stage as usize - 1) == Some(&ctx.accounts.shuffler.key()),
+            EngineError::NotShuffler
+        );
+
+        let output = &mut ctx.accounts.output;
+        output.deck = deck.key();
+        output.stage = stage;
+        output.sealed = false;
+        output.cards = vec![[0; CARD_BYTES]; DECK_SIZE];
+        Ok(())
+    }
+
+    /// Uploads part of a shuffled deck; a full deck does not fit in one
+    /// transaction.
+    pub fn write_shuffle_chunk(
+        ctx: Context<WriteShuffleStage>,
+        offset: u8,
+        cards: Vec<Card>,
+    ) -> Result<()> {
+        let output = &mut ctx.accounts.output;
+        require!(!output.sealed, EngineError::StageSealed);
+        let start = offset as usize;
+        require!(start + cards.len() <= DECK_SIZE, EngineError::InvalidCards);
+        output.cards[start..start + cards.len()].copy_from_slice(&cards);
+        Ok(())
+    }
+
+    /// Seals the uploaded deck with a commitment to `(key, permutation, salt)`.
+    /// Stages must be sealed in shuffler order.
+    pub fn seal_shuffle(ctx: Context<WriteShuffleStage>, commitment: [u8; 32]) -> Result<()> {
+        let deck = &mut ctx.accounts.deck;
+        let output = &mut ctx.accounts.output;
+        require!(!output.sealed, EngineError::StageSealed);
+        require!(
+            output.stage == deck.sealed_stages + 1,
+            EngineError::StageOutOfOrder
+        );
+
+        output.sealed = true;
+        deck.sealed_stages = output.stage;
+        deck.commitments.push(commitment);
+        deck.deck_hashes.push(shuffle::deck_hash(&output.cards));
+        Ok(())
+    }
+
+    /// Checks a revealed key and permutation against the stage's commitment
+    /// and its input and output decks. Anyone can submit the proof.
+    pub fn verify_shuffle_proof(
+        ctx: Context<VerifyShuffleProof>,
+        stage: u8,
+        key: Card,
+        permutation: Vec<u8>,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let deck = &mut ctx.accounts.deck;
+        let output = &ctx.accounts.output;
+        require!(output.sealed, EngineError::StageNotSealed);
+        require!(
+            stage == deck.verified_stages + 1,
+            EngineError::StageOutOfOrder
+        );
+        require!(
+            shuffle::shuffle_commitment(&key, &permutation, &salt)
+                == deck.commitments[stage as usize - 1],
+            EngineError::CommitmentMismatch
+        );
+
+        let input = if stage == 1 {
+            shuffle::plaintext_deck()
+        } else {
+            let input = ctx
+                .accounts
+                .input
+                .as_ref()
+                .ok_or(EngineError::StageNotSealed)?;
+            require!(input.sealed, EngineError::StageNotSealed);
+            input.cards.clone()
+        };
+        require!(
+            shuffle::verify_shuffle(&input, &output.cards, &key, &permutation),
+            EngineError::InvalidShuffle
+        );
+
+        deck.order = permutation
+            .iter()
+            .map(|index| deck.order[*index as usize])
+            .collect();
+        deck.verified_stages = stage;
+        Ok(())
+    }
+}
+
+/// Maximum number of players seated in a game.
+pub const MAX_PLAYERS: usize = 10;
+
+/// Maximum byte length of a player name.
+pub const MAX_NAME_LEN: usize = 32;
+
+/// Maximum byte length of a game id, as long as a betting lobby's.
+pub const MAX_GAME_ID_LEN: usize = 256;
+
+/// Maximum number of parties shuffling one deck.
+pub const MAX_SHUFFLERS: usize = 4;
+
+/// Positions in the final shuffled deck each card of a hand is dealt from:
+/// seat 0's hole cards, seat 1's, then the five board cards.
+pub const DEAL_ORDER: [u8; 9] = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+
+/// Turns in a row a seat may run out before it forfeits the match.
+pub const MAX_MISSED_TURNS: u8 = 3;
+
+/// The betting program, whose lobbies must sign for their game's hand records
+/// to be closed.
+pub const BETTING_PROGRAM_ID: Pubkey = pubkey!("85kCu1ahjWTXMmgbpmrXgKNL2DxrrWusYrTYWwA68NMq");
+
+#[derive(Accounts)]
+pub struct InitializeEngine<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + EngineConfig::LEN,
+        seeds = [b"engine"],
+        bump
+    )]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEngine<'info> {
+    #[account(mut, seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String, player_names: Vec<String>, owner: Pubkey)]
+pub struct CreateGame<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GameRecord::LEN,
+        seeds = [b"game", owner.as_ref(), hash(game_id.as_bytes()).as_ref()],
+        bump
+    )]
+    pub game: Account<'info, GameRecord>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(hand_number: u64)]
+pub struct RecordHand<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(
+        mut,
+        seeds = [b"game", game.owner.as_ref(), game.game_id_hash.as_ref()],
+        bump
+    )]
+    pub game: Account<'info, GameRecord>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + HandRecord::LEN,
+        seeds = [b"hand", game.key().as_ref(), &hand_number.to_le_bytes()],
+        bump
+    )]
+    pub hand: Account<'info, HandRecord>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(hand_number: u64)]
+pub struct RecordDecisionHash<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(seeds = [b"game", game.owner.as_ref(), game.game_id_hash.as_ref()], bump)]
+    pub game: Account<'info, GameRecord>,
+    #[account(
+        mut,
+        seeds = [b"hand", game.key().as_ref(), &hand_number.to_le_bytes()],
+        bump
+    )]
+    pub hand: Account<'info, HandRecord>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeGame<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(
+        mut,
+        seeds = [b"game", game.owner.as_ref(), game.game_id_hash.as_ref()],
+        bump
+    )]
+    pub game: Account<'info, GameRecord>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(hand_number: u64)]
+pub struct CloseHand<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(
+        seeds = [b"game", game.owner.as_ref(), game.game_id_hash.as_ref()],
+        bump,
+        constraint = game.finalized @ EngineError::GameNotFinalized
+    )]
+    pub game: Account<'info, GameRecord>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"hand", game.key().as_ref(), &hand_number.to_le_bytes()],
+        bump
+    )]
+    pub hand: Account<'info, HandRecord>,
+    /// The betting program's lobby on the game, which only signs once settled
+    #[account(
+        seeds = [b"lobby", game.owner.as_ref(), game.game_id_hash.as_ref()],
+        bump,
+        seeds::program = BETTING_PROGRAM_ID
+    )]
+    pub lobby: Signer<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTable<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(
+        seeds = [b"game", game.owner.as_ref(), game.game_id_hash.as_ref()],
+        bump
+    )]
+    pub game: Account<'info, GameRecord>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Table::LEN,
+        seeds = [b"table", game.key().as_ref()],
+        bump
+    )]
+    pub table: Account<'info, Table>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinTable<'info> {
+    #[account(mut, seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveTable<'info> {
+    #[account(mut, seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TableDeck<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(mut, seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    /// The deck of the table's current hand
+    #[account(
+        seeds = [b"deck", table.key().as_ref(), &table.hand_number.to_le_bytes()],
+        bump
+    )]
+    pub deck: Account<'info, ShuffleDeck>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TableAuthority<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(mut, seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Act<'info> {
+    #[account(mut, seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimTimeout<'info> {
+    #[account(mut, seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeTableGame<'info> {
+    #[account(seeds = [b"table", game.key().as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    #[account(
+        mut,
+        seeds = [b"game", game.owner.as_ref(), game.game_id_hash.as_ref()],
+        bump
+    )]
+    pub game: Account<'info, GameRecord>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(hand_number: u64)]
+pub struct CreateDeck<'info> {
+    #[account(seeds = [b"engine"], bump, has_one = authority)]
+    pub engine: Account<'info, EngineConfig>,
+    #[account(seeds = [b"table", table.game.as_ref()], bump)]
+    pub table: Account<'info, Table>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ShuffleDeck::LEN,
+        seeds = [b"deck", table.key().as_ref(), &hand_number.to_le_bytes()],
+        bump
+    )]
+    pub deck: Account<'info, ShuffleDeck>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(stage: u8)]
+pub struct InitShuffleStage<'info> {
+    #[account(
+        seeds = [b"deck", deck.table.as_ref(), &deck.hand_number.to_le_bytes()],
+        bump
+    )]
+    pub deck: Account<'info, ShuffleDeck>,
+    #[account(
+        init,
+        payer = shuffler,
+        space = 8 + ShuffleStage::LEN,
+        seeds = [b"stage", deck.key().as_ref(), &[stage]],
+        bump
+    )]
+    pub output: Account<'info, ShuffleStage>,
+    #[account(mut)]
+    pub shuffler: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WriteShuffleStage<'info> {
+    #[account(
+        mut,
+        seeds = [b"deck", deck.table.as_ref(), &deck.hand_number.to_le_bytes()],
+        bump
+    )]
+    pub deck: Account<'info, ShuffleDeck>,
+    #[account(
+        mut,
+        seeds = [b"stage", deck.key().as_ref(), &[output.stage]],
+        bump,
+        constraint = deck.shufflers[output.stage as usize - 1] == shuffler.key() @ EngineError::NotShuffler
+    )]
+    pub output: Account<'info, ShuffleStage>,
+    pub shuffler: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(stage: u8)]
+pub struct VerifyShuffleProof<'info> {
+    #[account(
+        mut,
+        seeds = [b"deck", deck.table.as_ref(), &deck.hand_number.to_le_bytes()],
+        bump
+    )]
+    pub deck: Account<'info, ShuffleDeck>,
+    /// Previous stage's deck; not needed for stage 1, whose input is the plaintext deck
+    #[account(seeds = [b"stage", deck.key().as_ref(), &[stage.saturating_sub(1)]], bump)]
+    pub input: Option<Account<'info, ShuffleStage>>,
+    #[account(seeds = [b"stage", deck.key().as_ref(), &[stage]], bump)]
+    pub output: Account<'info, ShuffleStage>,
+    pub caller: Signer<'info>,
+}
+
+#[account]
+pub struct EngineConfig {
+    pub authority: Pubkey,
+}
+
+impl EngineConfig {
+    pub const LEN: usize = 32;
+}
+
+#[account]
+pub struct GameRecord {
+    /// Owner of the betting lobby the game is played for; games are scoped
+    /// to it as lobbies are
+    pub owner: Pubkey,
+    pub game_id: String,
+    /// Hash of `game_id`, which seeds the record's address
+    pub game_id_hash: [u8; 32],
+    pub player_names: Vec<String>,
+    pub hands_played: u64,
+    pub finalized: bool,
+    pub winner: Option<String>,
+    pub updated_at: i64,
+    /// Finishing order, starting with the winner; empty if only the winner
+    /// was recorded
+    pub placings: Vec<String>,
+}
+
+impl GameRecord {
+    pub const LEN: usize = 32
+        + (4 + MAX_GAME_ID_LEN)
+        + 32
+        + (4 + (4 + MAX_NAME_LEN) * MAX_PLAYERS)
+        + 8
+        + 1
+        + (1 + 4 + MAX_NAME_LEN)
+        + 8
+        + (4 + (4 + MAX_NAME_LEN) * MAX_PLAYERS);
+}
+
+#[account]
+pub struct HandRecord {
+    pub game: Pubkey,
+    pub hand_number: u64,
+    pub pot: u64,
+    pub board: [u8; 5],
+    pub winner_name: String,
+    pub recorded_at: i64,
+    /// Hash of each player's reasoning trace for the hand, in `player_names`
+    /// order; zero until recorded
+    pub trace_hashes: Vec<[u8; 32]>,
+}
+
+impl HandRecord {
+    pub const LEN: usize = 32 + 8 + 8 + 5 + (4 + MAX_NAME_LEN) + 8 + (4 + 32 * MAX_PLAYERS);
+}
+
+/// Heads-up table state. Chips are lamports escrowed on the table account.
+#[account]
+pub struct Table {
+    pub game: Pubkey,
+    /// Wallet of each seat's player, `game.player_names[i]`
+    pub players: [Pubkey; 2],
+    /// Wallet seated in each seat, or the default key while it is empty
+    pub seats: [Pubkey; 2],
+    pub stacks: [u64; 2],
+    pub buy_in: u64,
+    pub small_blind: u64,
+    pub big_blind: u64,
+    pub turn_timeout: i64,
+    pub hand_number: u64,
+    pub street: Street,
+    /// Betting round is over and the next street must be dealt
+    pub awaiting_deal: bool,
+    pub dealer: u8,
+    pub to_act: u8,
+    pub street_bets: [u64; 2],
+    pub last_raise: u64,
+    pub acted: [bool; 2],
+    pub pot: u64,
+    /// sha256(hole cards || salt) per seat
+    pub hole_commitments: [[u8; 32]; 2],
+    /// sha256(board || salt)
+    pub board_commitment: [u8; 32],
+    pub board: [u8; 5],
+    pub board_len: u8,
+    pub last_action_at: i64,
+    /// Turns each seat has run out in a row
+    pub missed_turns: [u8; 2],
+    /// Seat that forfeited the match by leaving mid-game or running out its
+    /// turn timer
+    pub forfeited: Option<u8>,
+}
+
+impl Table {
+    pub const LEN: usize = 32 + 32 * 2 + 32 * 2 + 8 * 2 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 1
+        + 8 * 2 + 8 + 2 + 8 + 32 * 2 + 32 + 5 + 1 + 8 + 2 + (1 + 1);
+
+    pub fn seat_of(&self, player: &Pubkey) -> Result<usize> {
+        self.seats
+            .iter()
+            .position(|seat| seat == player)
+            .ok_or(error!(EngineError::NotSeated))
+    }
+
+    pub fn betting_open(&self) -> bool {
+        matches!(
+            self.street,
+            Street::Preflop | Street::Flop | Street::Turn | Street::River
+        ) && !self.awaiting_deal
+    }
+
+    pub fn to_call(&self, seat: usize) -> u64 {
+        self.street_bets[1 - seat].saturating_sub(self.street_bets[seat])
+    }
+
+    /// Moves up to `amount` from the seat's stack into its street bet.
+    fn post(&mut self, seat: usize, amount: u64) {
+        let amount = amount.min(self.stacks[seat]);
+        self.stacks[seat] -= amount;
+        self.street_bets[seat] += amount;
+    }
+
+    fn apply_action(&mut self, seat: usize, action: PlayerAction, now: i64) -> Result<()> {
+        require!(self.betting_open(), EngineError::NoActionPending);
+        let other = 1 - seat;
+        let to_call = self.to_call(seat);
+        match action {
+            PlayerAction::Fold => return self.award(Some(other)),
+            PlayerAction::Check => require!(to_call == 0, EngineError::InvalidAction),
+            PlayerAction::Call => {
+                require!(to_call > 0, EngineError::InvalidAction);
+                self.post(seat, to_call);
+            }
+            PlayerAction::Raise { amount } => {
+                let current = self.street_bets[other].max(self.street_bets[seat]);
+                require!(amount > current, EngineError::InvalidAction);
+                let added = amount - self.street_bets[seat];
+                require!(added <= self.stacks[seat], EngineError::InsufficientStack);
+                let raise_by = amount - current;
+                let all_in = added == self.stacks[seat];
+                require!(
+                    raise_by >= self.last_raise || all_in,
+                    EngineError::RaiseTooSmall
+                );
+                self.post(seat, added);
+                self.last_raise = self.last_raise.max(raise_by);
+                self.acted[other] = false;
+            }
+        }
+        self.acted[seat] = true;
+        self.last_action_at = now;
+
+        if self.round_complete() {
+            self.end_round();
+        } else {
+            self.to_act = other as u8;
+        }
+        Ok(())
+    }
+
+    fn round_complete(&self) -> bool {
+        let high = self.street_bets[0].max(self.street_bets[1]);
+        (0..2).all(|seat| {
+            self.stacks[seat] == 0 || (self.acted[seat] && self.street_bets[seat] == high)
+        })
+    }
+
+    /// Returns any uncalled chips and sweeps the street bets into the pot.
+    fn end_round(&mut self) {
+        let matched = self.street_bets[0].min(self.street_bets[1]);
+        for seat in 0..2 {
+            self.stacks[seat] += self.street_bets[seat] - matched;
+        }
+        self.pot += matched * 2;
+        self.street_bets = [0; 2];
+        self.acted = [false; 2];
+        self.last_raise = self.big_blind;
+        if self.street == Street::River {
+            self.street = Street::Showdown;
+        } else {
+            self.awaiting_deal = true;
+        }
+    }
+
+    /// Hands the pot and everything `seat` has left to the other seat,
+    /// ending the hand with `seat` out of chips.
+    fn forfeit(&mut self, seat: usize) -> Result<()> {
+        self.forfeited = Some(seat as u8);
+        let stack = self.stacks[seat];
+        self.stacks[seat] = 0;
+        self.stacks[1 - seat] += stack;
+        self.award(Some(1 - seat))
+    }
+
+    /// Pays the pot to `winner`, or splits it with the odd chip to the
+    /// non-dealer, and moves the button.
+    fn award(&mut self, winner: Option<usize>) -> Result<()> {
+        let pot = self.pot + self.street_bets[0] + self.street_bets[1];
+        match winner {
+            Some(seat) => self.stacks[seat] += pot,
+            None => {
+                let non_dealer = 1 - self.dealer as usize;
+                self.stacks[non_dealer] += pot - pot / 2;
+                self.stacks[1 - non_dealer] += pot / 2;
+            }
+        }
+        self.pot = 0;
+        self.street_bets = [0; 2];
+        self.acted = [false; 2];
+        self.street = Street::Idle;
+        self.awaiting_deal = false;
+        self.hand_number += 1;
+        self.dealer = 1 - self.dealer;
+        Ok(())
+    }
+}
+
+/// Scores the best five-card hand out of seven. Higher is better.
+pub fn best_hand(cards: &[u8; 7]) -> u32 {
+    let mut best = 0;
+    for skip_a in 0..7 {
+        for skip_b in skip_a + 1..7 {
+            let mut hand = [0u8; 5];
+            let mut n = 0;
+            for (i, card) in cards.iter().enumerate() {
+                if i != skip_a && i != skip_b {
+                    hand[n] = *card;
+                    n += 1;
+                }
+            }
+            best = best.max(score_five(&hand));
+        }
+    }
+    best
+}
+
+/// Cards are `suit * 13 + rank` with rank 0 as a deuce and 12 as an ace. The
+/// score is the hand category in the top bits followed by tie-break ranks.
+fn score_five(hand: &[u8; 5]) -> u32 {
+    let mut counts = [0u8; 13];
+    for card in hand {
+        counts[(card % 13) as usize] += 1;
+    }
+    let flush = hand.iter().all(|card| card / 13 == hand[0] / 13);
+
+    // Rank groups ordered by size, then rank.
+    let mut groups: Vec<(u8, u8)> = (0..13u8)
+        .rev()
+        .filter(|rank| counts[*rank as usize] > 0)
+        .map(|rank| (counts[rank as usize], rank))
+        .collect();
+    groups.sort_by(|a, b| b.cmp(a));
+
+    let straight_high = if groups.len() == 5 {
+        let (high, low) = (groups[0].1, groups[4].1);
+        if high - low == 4 {
+            Some(high)
+        } else if high == 12 && groups[1].1 == 3 {
+            // Wheel: A-2-3-4-5 plays five high.
+            Some(3)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let category = match (straight_high, flush, groups[0].0, groups.get(1).map(|g| g.0)) {
+        (Some(_), true, _, _) => 8,
+        (_, _, 4, _) => 7,
+        (_, _, 3, Some(2)) => 6,
+        (_, true, _, _) => 5,
+        (Some(_), _, _, _) => 4,
+        (_, _, 3, _) => 3,
+        (_, _, 2, Some(2)) => 2,
+        (_, _, 2, _) => 1,
+        _ => 0,
+    };
+
+    let mut score = category << 20;
+    match straight_high {
+        Some(high) if category == 8 || category == 4 => score |= (high as u32) << 16,
+        _ => {
+            for (i, (_, rank)) in groups.iter().enumerate() {
+                score |= (*rank as u32) << (16 - 4 * i);
+            }
+        }
+    }
+    score
+}
+
+/// Shuffle chain for one hand: who shuffles, each sealed stage's commitment
+/// and deck hash, and how far the revealed proofs have been verified.
+#[account]
+pub struct ShuffleDeck {
+    pub table: Pubkey,
+    pub hand_number: u64,
+    pub shufflers: Vec<Pubkey>,
+    pub sealed_stages: u8,
+    pub commitments: Vec<[u8; 32]>,
+    /// Plaintext deck hash followed by each sealed stage's
+    pub deck_hashes: Vec<[u8; 32]>,
+    pub verified_stages: u8,
+    /// Card index at each position of the last verified stage's deck
+    pub order: Vec<u8>,
+}
+
+impl ShuffleDeck {
+    pub const LEN: usize = 32
+        + 8
+        + (4 + 32 * MAX_SHUFFLERS)
+        + 1
+        + (4 + 32 * MAX_SHUFFLERS)
+        + (4 + 32 * (MAX_SHUFFLERS + 1))
+        + 1
+        + (4 + DECK_SIZE);
+}
+
+/// One shuffler's re-encrypted, permuted deck.
+#[account]
+pub struct ShuffleStage {
+    pub deck: Pubkey,
+    pub stage: u8,
+    pub sealed: bool,
+    pub cards: Vec<Card>,
+}
+
+impl ShuffleStage {
+    pub const LEN: usize = 32 + 1 + 1 + (4 + CARD_BYTES * DECK_SIZE);
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Street {
+    Idle,
+    Preflop,
+    Flop,
+    Turn,
+    River,
+    Showdown,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlayerAction {
+    Fold,
+    Check,
+    Call,
+    Raise { amount: u64 },
+}
+
+#[error_code]
+pub enum EngineError {
+    #[msg("Name exceeds the maximum length.")]
+    NameTooLong,
+    #[msg("A game needs between two and MAX_PLAYERS players.")]
+    InvalidPlayerCount,
+    #[msg("Game is already finalized.")]
+    GameFinalized,
+    #[msg("Hands must be recorded in order.")]
+    HandOutOfOrder,
+    #[msg("Player is not seated in this game.")]
+    UnknownPlayer,
+    #[msg("Invalid blinds, buy-in or turn timeout.")]
+    InvalidTableParams,
+    #[msg("Seat index must be 0 or 1.")]
+    InvalidSeat,
+    #[msg("Seat is already taken.")]
+    SeatTaken,
+    #[msg("Signer is not seated at this table.")]
+    NotSeated,
+    #[msg("Both seats must be filled.")]
+    TableNotFull,
+    #[msg("A hand is in progress.")]
+    HandInProgress,
+    #[msg("Stack is too small.")]
+    InsufficientStack,
+    #[msg("It is not this seat's turn.")]
+    NotYourTurn,
+    #[msg("No player action is pending.")]
+    NoActionPending,
+    #[msg("Action is not allowed here.")]
+    InvalidAction,
+    #[msg("Raise is smaller than the minimum.")]
+    RaiseTooSmall,
+    #[msg("Turn timer has not expired.")]
+    TurnNotExpired,
+    #[msg("Table is not waiting for a street to be dealt.")]
+    NotAwaitingDeal,
+    #[msg("Invalid or duplicate cards.")]
+    InvalidCards,
+    #[msg("Hand is not at showdown.")]
+    NotAtShowdown,
+    #[msg("Revealed cards do not match the commitment.")]
+    CommitmentMismatch,
+    #[msg("Neither seat has busted yet.")]
+    NoWinnerYet,
+    #[msg("A deck needs between one and MAX_SHUFFLERS shufflers.")]
+    InvalidShufflers,
+    #[msg("Signer is not the shuffler for this stage.")]
+    NotShuffler,
+    #[msg("Shuffle stage is already sealed.")]
+    StageSealed,
+    #[msg("Shuffle stage is not sealed.")]
+    StageNotSealed,
+    #[msg("Shuffle stages must be sealed and verified in order.")]
+    StageOutOfOrder,
+    #[msg("Revealed shuffle does not map the input deck onto the output deck.")]
+    InvalidShuffle,
+    #[msg("Trace hash must be non-zero.")]
+    InvalidTraceHash,
+    #[msg("A trace hash is already recorded for this player and hand.")]
+    TraceHashAlreadyRecorded,
+    #[msg("Placings must list distinct players in finishing order, starting with the winner.")]
+    InvalidPlacings,
+    #[msg("Game is not finalized yet.")]
+    GameNotFinalized,
+    #[msg("Every shuffler must seal the hand's deck first.")]
+    DeckNotShuffled,
+    #[msg("Every stage of the hand's deck must be verified first.")]
+    DeckNotVerified,
+    #[msg("Revealed cards were not dealt from the hand's deck.")]
+    NotDealtFromDeck,
+    #[msg("Signer is not the player registered for this seat.")]
+    NotSeatPlayer,
+    #[msg("A seat has forfeited the match.")]
+    MatchForfeited,
+}
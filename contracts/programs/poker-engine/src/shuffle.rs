@@ -0,0 +1,88 @@
+//! Mental poker primitives: SRA commutative encryption over a 256-bit prime
+//! field and verification of revealed shuffles.
+//!
+//! Each shuffler raises every card of the previous deck to their secret key
+//! `e` (coprime to `P - 1`) and permutes the result. Because
+//! `(m^a)^b = (m^b)^a mod P`, players can strip their own layer from any card
+//! in any order, so no single party — including the game server — learns or
+//! chooses the deal. A shuffler commits to `(key, permutation, salt)` when
+//! sealing their deck and reveals it after the hand so anyone can check the
+//! stage was an honest re-encryption and permutation of its input.
+
+use anchor_lang::solana_program::{big_mod_exp::big_mod_exp, hash::hashv};
+
+/// Cards in a deck.
+pub const DECK_SIZE: usize = 52;
+
+/// Bytes in an encrypted card, a key and the modulus.
+pub const CARD_BYTES: usize = 32;
+
+/// secp256k1 field prime, 2^256 - 2^32 - 977, big-endian.
+pub const SHUFFLE_PRIME: [u8; CARD_BYTES] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xfc, 0x2f,
+];
+
+pub type Card = [u8; CARD_BYTES];
+
+/// Plaintext encoding of card `index` (`suit * 13 + rank`): `(index + 2)^2`.
+/// Squares are quadratic residues, so encryption does not leak a card's
+/// residuosity.
+pub fn encode_card(index: u8) -> Card {
+    let value = (index as u32 + 2).pow(2);
+    let mut card = [0u8; CARD_BYTES];
+    card[CARD_BYTES - 4..].copy_from_slice(&value.to_be_bytes());
+    card
+}
+
+/// Recovers the card index from a fully decrypted value.
+pub fn decode_card(card: &Card) -> Option<u8> {
+    (0..DECK_SIZE as u8).find(|index| encode_card(*index) == *card)
+}
+
+pub fn plaintext_deck() -> Vec<Card> {
+    (0..DECK_SIZE as u8).map(encode_card).collect()
+}
+
+/// Applies one SRA layer: `card^key mod P`. Decryption is the same operation
+/// with the key's inverse modulo `P - 1`.
+pub fn encrypt(card: &Card, key: &Card) -> Card {
+    let mut out = [0u8; CARD_BYTES];
+    out.copy_from_slice(&big_mod_exp(card, key, &SHUFFLE_PRIME));
+    out
+}
+
+/// Checks that `share` is `card` with the holder's layer removed, by
+/// re-applying the revealed key.
+pub fn verify_decryption(card: &Card, share: &Card, key: &Card) -> bool {
+    encrypt(share, key) == *card
+}
+
+pub fn is_permutation(permutation: &[u8]) -> bool {
+    let mut seen = [false; DECK_SIZE];
+    permutation.len() == DECK_SIZE
+        && permutation.iter().all(|index| {
+            let index = *index as usize;
+            index < DECK_SIZE && !std::mem::replace(&mut seen[index], true)
+        })
+}
+
+pub fn shuffle_commitment(key: &Card, permutation: &[u8], salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[key, permutation, salt]).to_bytes()
+}
+
+pub fn deck_hash(deck: &[Card]) -> [u8; 32] {
+    let cards: Vec<&[u8]> = deck.iter().map(|card| card.as_slice()).collect();
+    hashv(&cards).to_bytes()
+}
+
+/// True if `output[i] == input[permutation[i]]^key` for every position.
+pub fn verify_shuffle(input: &[Card], output: &[Card], key: &Card, permutation: &[u8]) -> bool {
+    input.len() == DECK_SIZE
+        && output.len() == DECK_SIZE
+        && is_permutation(permutation)
+        && output
+            .iter()
+            .zip(permutation)
+            .all(|(card, index)| encrypt(&input[*index as usize], key) == *card)
+}